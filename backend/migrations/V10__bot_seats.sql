@@ -0,0 +1,2 @@
+-- seats kept filled with server played bots
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS bot_seats INT NOT NULL DEFAULT 0;
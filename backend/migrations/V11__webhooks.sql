@@ -0,0 +1,11 @@
+-- endpoints notified of room events, a NULL room_id receives events from every room
+CREATE TABLE webhooks (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    room_id UUID REFERENCES room_info (room_id),
+    url VARCHAR NOT NULL,
+    secret VARCHAR NOT NULL,
+    created_by UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhooks_room_id ON webhooks (room_id);
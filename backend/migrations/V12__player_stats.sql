@@ -0,0 +1,10 @@
+-- lifetime totals per player, added to after every hand
+CREATE TABLE player_stats (
+    user_id UUID PRIMARY KEY,
+    hands_played BIGINT NOT NULL DEFAULT 0,
+    hands_won BIGINT NOT NULL DEFAULT 0,
+    net_winnings BIGINT NOT NULL DEFAULT 0,
+    vpip_hands BIGINT NOT NULL DEFAULT 0,
+    showdowns BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,13 @@
+-- single use, a new one is issued every time one is redeemed
+CREATE TABLE refresh_tokens (
+    token UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES auth_users (id),
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_refresh_tokens_user_id ON refresh_tokens (user_id);
+
+-- sessions are signed access tokens now
+DROP INDEX IF EXISTS idx_auth_users_session_token;
+ALTER TABLE auth_users DROP COLUMN IF EXISTS session_token;
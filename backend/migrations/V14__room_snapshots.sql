@@ -0,0 +1,8 @@
+-- stacks of seated players, written on shutdown and paid back to balances on the next start
+CREATE TABLE room_snapshots (
+    room_id UUID NOT NULL REFERENCES room_info (room_id),
+    user_id UUID NOT NULL,
+    chips BIGINT NOT NULL,
+    taken_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (room_id, user_id)
+);
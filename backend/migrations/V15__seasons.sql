@@ -0,0 +1,22 @@
+-- a row per finished season, ended_at keeps a scheduled rollover from running twice
+CREATE TABLE seasons (
+    id BIGSERIAL PRIMARY KEY,
+    ended_at TIMESTAMPTZ NOT NULL UNIQUE,
+    reset_balances BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- archived leaderboard of every season, with the cosmetic reward each finisher got
+CREATE TABLE season_standings (
+    season_id BIGINT NOT NULL REFERENCES seasons (id),
+    user_id UUID NOT NULL REFERENCES users (id),
+    rank BIGINT NOT NULL,
+    balance BIGINT NOT NULL,
+    reward VARCHAR(32),
+    PRIMARY KEY (season_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_season_standings_user_id ON season_standings (user_id);
+
+-- season resets are made by the server, not an admin
+ALTER TABLE balance_ledger ALTER COLUMN admin_id DROP NOT NULL;
@@ -0,0 +1,10 @@
+-- chips kept by the house, one row per raked hand
+CREATE TABLE rake_ledger (
+    id UUID PRIMARY KEY,
+    room_id UUID NOT NULL REFERENCES room_info (room_id),
+    hand_id UUID NOT NULL,
+    amount BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_rake_ledger_created_at ON rake_ledger (created_at);
@@ -0,0 +1,5 @@
+-- all-in luck, what hands all in before the river were worth against what they paid
+ALTER TABLE player_stats
+    ADD COLUMN all_in_hands BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN all_in_ev BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN all_in_net BIGINT NOT NULL DEFAULT 0;
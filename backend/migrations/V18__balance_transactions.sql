@@ -0,0 +1,10 @@
+-- every credit and debit of a balance is recorded here, not only admin adjustments
+ALTER TABLE balance_ledger RENAME TO balance_transactions;
+ALTER INDEX idx_balance_ledger_user_id RENAME TO idx_balance_transactions_user_id;
+
+-- the room chips were bought into or cashed out from
+ALTER TABLE balance_transactions ADD COLUMN IF NOT EXISTS room_id UUID;
+
+-- looked up when a user claims their daily free chips
+CREATE INDEX IF NOT EXISTS idx_balance_transactions_user_id_reason_created_at
+    ON balance_transactions (user_id, reason, created_at);
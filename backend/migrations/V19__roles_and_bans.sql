@@ -0,0 +1,10 @@
+-- moderators can close tables and ban players, admins can do that and everything else under /admin
+-- there is no endpoint to grant a role, promote with: UPDATE auth_users SET role = 'admin' WHERE email = ...
+ALTER TABLE auth_users ADD COLUMN IF NOT EXISTS role VARCHAR(16) NOT NULL DEFAULT 'player';
+UPDATE auth_users SET role = 'admin' WHERE is_admin;
+ALTER TABLE auth_users DROP COLUMN IF EXISTS is_admin;
+
+-- banned users cannot log in, refresh their tokens or connect a socket
+ALTER TABLE auth_users ADD COLUMN IF NOT EXISTS banned_at TIMESTAMPTZ;
+ALTER TABLE auth_users ADD COLUMN IF NOT EXISTS banned_by UUID REFERENCES auth_users (id);
+ALTER TABLE auth_users ADD COLUMN IF NOT EXISTS ban_reason TEXT;
@@ -0,0 +1,4 @@
+-- folds the player did not choose, the turn timer running out or leaving mid hand
+ALTER TABLE player_stats
+    ADD COLUMN timeout_folds BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN disconnect_folds BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,12 @@
+-- bug reports filed from the client, with the state it held at the time
+CREATE TABLE feedback (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users (id),
+    note TEXT,
+    client_version VARCHAR(32) NOT NULL,
+    client_state JSONB,
+    events JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_feedback_created_at ON feedback (created_at DESC);
@@ -0,0 +1,13 @@
+-- how each hand changed the stacks of the humans dealt in, written when SETTLE_PER_HAND is on.
+-- room_snapshots then holds the stacks after the last finished hand, so a crash pays those back.
+CREATE TABLE hand_settlements (
+    hand_id UUID NOT NULL,
+    room_id UUID NOT NULL REFERENCES room_info (room_id),
+    user_id UUID NOT NULL,
+    delta BIGINT NOT NULL,
+    chips_after BIGINT NOT NULL,
+    settled_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (hand_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_hand_settlements_user_id ON hand_settlements (user_id);
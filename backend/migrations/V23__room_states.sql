@@ -0,0 +1,6 @@
+-- full state of each room, written as it changes so a crash does not end the hands in progress
+CREATE TABLE room_states (
+    room_id UUID PRIMARY KEY REFERENCES room_info (room_id),
+    state JSONB NOT NULL,
+    saved_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
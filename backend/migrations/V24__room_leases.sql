@@ -0,0 +1,5 @@
+-- the backend instance serving each room, until its lease runs out without being renewed
+ALTER TABLE room_info
+    ADD COLUMN owner_id UUID,
+    ADD COLUMN owner_url TEXT,
+    ADD COLUMN lease_expires_at TIMESTAMPTZ;
@@ -0,0 +1,13 @@
+-- accounts made before verification existed count as verified
+ALTER TABLE auth_users ADD COLUMN IF NOT EXISTS verified_at TIMESTAMPTZ;
+UPDATE auth_users SET verified_at = created_at WHERE verified_at IS NULL;
+
+-- single use, sent by email on signup and again on every login until one is redeemed
+CREATE TABLE email_verifications (
+    token UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES auth_users (id),
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_verifications_user_id ON email_verifications (user_id);
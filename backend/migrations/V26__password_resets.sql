@@ -0,0 +1,8 @@
+-- single use codes emailed to reset a forgotten password, checked together with the email
+CREATE TABLE password_resets (
+    user_id UUID NOT NULL REFERENCES auth_users (id),
+    code VARCHAR(16) NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, code)
+);
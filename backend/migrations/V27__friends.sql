@@ -0,0 +1,11 @@
+-- one row per pair, from the player who asked. Declining a request deletes it.
+CREATE TABLE friends (
+    requester_id UUID NOT NULL REFERENCES users (id),
+    addressee_id UUID NOT NULL REFERENCES users (id),
+    accepted_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (requester_id, addressee_id),
+    CHECK (requester_id <> addressee_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_friends_addressee_id ON friends (addressee_id);
@@ -0,0 +1,9 @@
+-- what each player won or lost in every hand, summed over a day or week for the leaderboard
+CREATE TABLE hand_results (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    net_chips BIGINT NOT NULL,
+    played_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_hand_results_played_at ON hand_results (played_at);
@@ -0,0 +1,2 @@
+-- players the turn timer folds for this many hands in a row are removed, 0 never
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS kick_after_timeouts INT NOT NULL DEFAULT 3;
@@ -0,0 +1,6 @@
+-- what each table is doing, so the lobby can tell active tables from idle ones
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS stage JSONB NOT NULL DEFAULT '"NotEnoughPlayers"';
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS small_blind INT NOT NULL DEFAULT 1;
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS big_blind INT NOT NULL DEFAULT 2;
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS average_pot INT;
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS last_hand_at TIMESTAMPTZ;
@@ -0,0 +1,2 @@
+-- no_limit, pot_limit or fixed_limit, see `BettingStructure`
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS betting JSONB NOT NULL DEFAULT '"no_limit"';
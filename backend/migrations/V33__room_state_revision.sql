@@ -0,0 +1,2 @@
+-- saves of the same room can finish out of order, only a newer revision replaces the state
+ALTER TABLE room_states ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- SHA-256 of the order of the deck each hand was dealt from, empty for hands recorded before
+ALTER TABLE hand_histories ADD COLUMN deck_hash TEXT;
@@ -0,0 +1,2 @@
+-- seed and commitment each hand was shuffled from, empty for scripted hands and older ones
+ALTER TABLE hand_histories ADD COLUMN deal JSONB;
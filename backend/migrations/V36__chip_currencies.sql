@@ -0,0 +1,8 @@
+-- premium chips, kept apart from the play chips in `balance`
+ALTER TABLE users ADD COLUMN IF NOT EXISTS premium_balance BIGINT NOT NULL DEFAULT 0;
+
+-- play or premium, each currency's transactions make a ledger of their own
+ALTER TABLE balance_transactions ADD COLUMN IF NOT EXISTS currency TEXT NOT NULL DEFAULT 'play';
+
+-- play or premium, see `ChipCurrency`
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS currency JSONB NOT NULL DEFAULT '"play"';
@@ -0,0 +1,16 @@
+-- what finished hands suggested about pairs of players, for the admin integrity report
+CREATE TABLE integrity_events (
+    id UUID PRIMARY KEY,
+    room_id UUID NOT NULL,
+    hand_id UUID NOT NULL,
+    signal TEXT NOT NULL,
+    -- who folded or shared the address
+    player_id UUID NOT NULL,
+    -- who it was folded to or shared with
+    other_player_id UUID NOT NULL,
+    -- chips folded away, 0 for signals without any
+    amount BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_integrity_events_created_at ON integrity_events (created_at);
@@ -0,0 +1,2 @@
+-- seats at the table, rooms opened before table sizes kept five
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS max_players INT NOT NULL DEFAULT 5;
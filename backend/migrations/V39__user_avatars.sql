@@ -0,0 +1,2 @@
+-- picked with the profile, see `Avatar`
+ALTER TABLE users ADD COLUMN IF NOT EXISTS avatar TEXT NOT NULL DEFAULT 'spade';
@@ -0,0 +1,2 @@
+-- the player who opened the room, rooms opened before this have none
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS created_by UUID;
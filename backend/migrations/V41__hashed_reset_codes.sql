@@ -0,0 +1,8 @@
+-- reset codes are kept as SHA-256 hashes, and stop working after too many wrong guesses.
+-- codes already sent are dropped, they cannot be hashed in place
+DELETE FROM password_resets;
+ALTER TABLE password_resets DROP CONSTRAINT IF EXISTS password_resets_pkey;
+ALTER TABLE password_resets DROP COLUMN IF EXISTS code;
+ALTER TABLE password_resets ADD COLUMN IF NOT EXISTS code_hash BYTEA NOT NULL;
+ALTER TABLE password_resets ADD COLUMN IF NOT EXISTS failed_attempts INT NOT NULL DEFAULT 0;
+ALTER TABLE password_resets ADD PRIMARY KEY (user_id, code_hash);
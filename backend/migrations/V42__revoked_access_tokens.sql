@@ -0,0 +1,5 @@
+-- access tokens logged out before they expire, every instance turns them away
+CREATE TABLE IF NOT EXISTS revoked_access_tokens (
+    jti UUID PRIMARY KEY,
+    expires_at TIMESTAMPTZ NOT NULL
+);
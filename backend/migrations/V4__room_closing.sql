@@ -0,0 +1,2 @@
+-- rooms with a deadline stop dealing new hands and close once it has passed
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS closes_at TIMESTAMPTZ DEFAULT NULL;
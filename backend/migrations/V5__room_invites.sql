@@ -0,0 +1,6 @@
+CREATE TABLE room_invites (
+    code VARCHAR(16) PRIMARY KEY,
+    room_id UUID NOT NULL REFERENCES room_info (room_id),
+    created_by UUID NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
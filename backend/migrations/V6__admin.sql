@@ -0,0 +1,16 @@
+-- admins can adjust user balances, every adjustment is recorded on the ledger
+-- there is no endpoint to grant admin, promote with: UPDATE auth_users SET is_admin = TRUE WHERE email = ...
+ALTER TABLE auth_users ADD COLUMN IF NOT EXISTS is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE balance_ledger (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id),
+    admin_id UUID NOT NULL REFERENCES auth_users (id),
+    amount BIGINT NOT NULL,
+    reason VARCHAR(32) NOT NULL,
+    note TEXT,
+    balance_after BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_balance_ledger_user_id ON balance_ledger (user_id);
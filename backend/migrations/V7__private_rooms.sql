@@ -0,0 +1,2 @@
+-- rooms with a password hash are private and can only be joined with the password
+ALTER TABLE room_info ADD COLUMN IF NOT EXISTS password_hash VARCHAR DEFAULT NULL;
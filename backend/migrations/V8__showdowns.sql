@@ -0,0 +1,11 @@
+-- one row per player per showdown, used for hand class stats
+CREATE TABLE showdowns (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    room_id UUID NOT NULL,
+    hand_class VARCHAR(32) NOT NULL,
+    won BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_showdowns_user_id ON showdowns (user_id);
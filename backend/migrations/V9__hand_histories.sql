@@ -0,0 +1,12 @@
+-- every completed hand, kept for players to review
+CREATE TABLE hand_histories (
+    id UUID PRIMARY KEY,
+    room_id UUID NOT NULL,
+    players JSONB NOT NULL,
+    community_cards JSONB NOT NULL,
+    actions JSONB NOT NULL,
+    results JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_hand_histories_room_id ON hand_histories (room_id, created_at DESC);
@@ -1,15 +1,34 @@
+use serde::{Deserialize, Serialize};
 use sqlx::types::chrono::{DateTime, Utc};
 use sqlx::types::Uuid;
 use sqlx::FromRow;
 
+use types::domain::Role;
+
 #[derive(Debug, Clone, FromRow)]
 #[allow(dead_code)]
 pub struct AuthUser {
     pub id: Uuid,
     pub email: String,
     pub hashed_password: String,
-    pub session_token: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub sid: Option<String>,
+    #[sqlx(try_from = "String")]
+    pub role: Role,
+    pub banned_at: Option<DateTime<Utc>>,
+    pub verified_at: Option<DateTime<Utc>>,
+}
+
+/// What an access token vouches for, checked without going to the database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    // user id
+    pub sub: Uuid,
+    // expiry, in seconds since the epoch
+    pub exp: i64,
+    // token id, so a logged out token can be turned away before it expires
+    pub jti: Uuid,
+    #[serde(default)]
+    pub role: Role,
 }
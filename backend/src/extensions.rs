@@ -1,5 +1,3 @@
-use std::str::FromStr;
-
 use axum::extract::FromRequestParts;
 use axum::http::request::Parts;
 use axum::http::StatusCode;
@@ -11,11 +9,36 @@ use log::{error, info};
 use tap::TapFallible;
 use uuid::Uuid;
 
+use types::domain::Role;
+
+use crate::domain::auth::Claims;
 use crate::routes::Api;
 
 #[derive(Debug, Clone)]
 pub struct ExtractUserFromToken(pub Uuid);
 
+// verifies the access token in the Authorization header, without a database lookup,
+// logged out tokens are found among the revoked ones kept in memory
+async fn claims_from_parts<S>(req: &mut Parts, state: &S) -> Result<Claims, StatusCode>
+where
+    S: Send + Sync,
+{
+    let TypedHeader(Authorization(bearer)) =
+        TypedHeader::<Authorization<Bearer>>::from_request_parts(req, state)
+            .await
+            .tap_err(|e| error!("Failed to extract Authorization header: {}", e))
+            .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    let Extension(api) = Extension::<Api>::from_request_parts(req, state)
+        .await
+        .tap_err(|e| error!("Failed to extract API: {}", e))
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    api.verify_access_token(bearer.token())
+        .tap_err(|e| error!("Failed to verify token: {}", e))
+        .map_err(|_| StatusCode::UNAUTHORIZED)
+}
+
 impl<S> FromRequestParts<S> for ExtractUserFromToken
 where
     S: Send + Sync,
@@ -24,27 +47,70 @@ where
 
     async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         info!("Extracting user from token");
-        let TypedHeader(Authorization(bearer)) =
-            TypedHeader::<Authorization<Bearer>>::from_request_parts(req, state)
-                .await
-                .tap_err(|e| error!("Failed to extract Authorization header: {}", e))
-                .map_err(|_| StatusCode::UNAUTHORIZED)?;
-
-        let token = Uuid::from_str(bearer.token())
-            .tap_err(|e| error!("Failed to parse token: {}", e))
-            .map_err(|_| StatusCode::UNAUTHORIZED)?;
-        let Extension(api) = Extension::<Api>::from_request_parts(req, state)
-            .await
-            .tap_err(|e| error!("Failed to extract API: {}", e))
-            .map_err(|_| StatusCode::UNAUTHORIZED)?;
+        let claims = claims_from_parts(req, state).await?;
+        Ok(ExtractUserFromToken(claims.sub))
+    }
+}
+
+// the whole token, for the few routes that need more than the user
+#[derive(Debug, Clone)]
+pub struct ExtractClaimsFromToken(pub Claims);
+
+impl<S> FromRequestParts<S> for ExtractClaimsFromToken
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        info!("Extracting claims from token");
+        let claims = claims_from_parts(req, state).await?;
+        Ok(ExtractClaimsFromToken(claims))
+    }
+}
+
+// rejects tokens of users whose role is below the one required
+async fn claims_with_role<S>(req: &mut Parts, state: &S, role: Role) -> Result<Claims, StatusCode>
+where
+    S: Send + Sync,
+{
+    let claims = claims_from_parts(req, state).await?;
+    if claims.role < role {
+        error!("User {} is not a {}", claims.sub, role.as_ref());
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(claims)
+}
+
+#[derive(Debug, Clone)]
+pub struct ExtractAdminFromToken(pub Uuid);
+
+impl<S> FromRequestParts<S> for ExtractAdminFromToken
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        info!("Extracting admin from token");
+        let claims = claims_with_role(req, state, Role::Admin).await?;
+        Ok(ExtractAdminFromToken(claims.sub))
+    }
+}
+
+// admins pass as moderators too
+#[derive(Debug, Clone)]
+pub struct ExtractModeratorFromToken(pub Uuid);
 
-        let auth_user = match api.get_user_by_session_token(token).await {
-            Ok(Some(auth_user)) => auth_user,
-            _ => {
-                error!("Failed to get user from token");
-                return Err(StatusCode::UNAUTHORIZED);
-            }
-        };
-        Ok(ExtractUserFromToken(auth_user.id))
+impl<S> FromRequestParts<S> for ExtractModeratorFromToken
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        info!("Extracting moderator from token");
+        let claims = claims_with_role(req, state, Role::Moderator).await?;
+        Ok(ExtractModeratorFromToken(claims.sub))
     }
 }
@@ -0,0 +1,8 @@
+pub use server::{serve, shutdown_signal, ServerConfig};
+
+mod domain;
+mod extensions;
+mod repository;
+mod routes;
+mod server;
+mod service;
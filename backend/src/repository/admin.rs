@@ -0,0 +1,78 @@
+use eyre::Result;
+use sqlx::types::Uuid;
+use sqlx::{PgPool, Row};
+
+use types::domain::{AdjustmentReason, AdminUserView, ChipCurrency, LedgerEntry};
+
+use crate::repository::ledger::{self, BalanceChange};
+
+const MAX_SEARCH_RESULTS: i64 = 100;
+
+#[derive(Clone)]
+pub struct AdminRepository {
+    pool: PgPool,
+}
+
+impl AdminRepository {
+    pub fn new(pool: PgPool) -> Self {
+        AdminRepository { pool }
+    }
+
+    /// Applies the adjustment and records it on the ledger in one transaction.
+    /// Returns None when the balance would go negative.
+    pub async fn adjust_balance(
+        &self,
+        user_id: Uuid,
+        admin_id: Uuid,
+        amount: i64,
+        reason: AdjustmentReason,
+        note: Option<String>,
+        currency: ChipCurrency,
+    ) -> Result<Option<LedgerEntry>> {
+        let mut tx = self.pool.begin().await?;
+        let change = BalanceChange {
+            user_id,
+            amount,
+            reason,
+            admin_id: Some(admin_id),
+            room_id: None,
+            note,
+            currency,
+        };
+        let entry = ledger::apply(&mut tx, change).await?;
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    pub async fn search_users(&self, query: Option<&str>, limit: i64) -> Result<Vec<AdminUserView>> {
+        sqlx::query_as(
+            r#"
+            SELECT users.id, users.name, auth_users.email, users.balance, users.premium_balance,
+                users.current_room, auth_users.role, auth_users.banned_at
+            FROM users
+            JOIN auth_users ON auth_users.id = users.id
+            WHERE $1::TEXT IS NULL OR users.name ILIKE $1 OR auth_users.email ILIKE $1
+            ORDER BY users.name
+            LIMIT $2
+            "#,
+        )
+        .bind(query.map(|q| format!("%{}%", q)))
+        .bind(limit.clamp(1, MAX_SEARCH_RESULTS))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Number of users, and of those how many are banned
+    pub async fn user_counts(&self) -> Result<(i64, i64)> {
+        let row = sqlx::query(
+            r#"
+            SELECT COUNT(*), COUNT(banned_at)
+            FROM auth_users
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok((row.get(0), row.get(1)))
+    }
+}
@@ -1,7 +1,9 @@
 use eyre::Result;
 use socketioxide::socket::Sid;
+use sqlx::types::chrono::{DateTime, Utc};
 use sqlx::types::Uuid;
 use sqlx::{PgPool, Row};
+use subtle::{Choice, ConstantTimeEq};
 
 use crate::domain::auth::AuthUser;
 
@@ -15,16 +17,23 @@ impl AuthUserRepository {
         AuthUserRepository { pool }
     }
 
-    pub async fn create_user(&self, email: String, hashed_password: String) -> Result<AuthUser> {
+    /// Creates an account, unverified ones cannot log in until they redeem an emailed token
+    pub async fn create_user(
+        &self,
+        email: String,
+        hashed_password: String,
+        verified: bool,
+    ) -> Result<AuthUser> {
         sqlx::query_as(
             r#"
-            INSERT INTO auth_users (id, email, hashed_password)
-            VALUES ($1, $2, $3) RETURNING *
+            INSERT INTO auth_users (id, email, hashed_password, verified_at)
+            VALUES ($1, $2, $3, CASE WHEN $4 THEN NOW() END) RETURNING *
             "#,
         )
         .bind(Uuid::new_v4())
         .bind(email)
         .bind(hashed_password)
+        .bind(verified)
         .fetch_one(&self.pool)
         .await
         .map_err(Into::into)
@@ -59,33 +68,302 @@ impl AuthUserRepository {
         .map_err(Into::into)
     }
 
-    pub async fn update_token(&self, user_id: Uuid, token: Uuid) -> Result<()> {
+    pub async fn get_by_id(&self, user_id: Uuid) -> Result<Option<AuthUser>> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM auth_users
+            WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    pub async fn create_refresh_token(
+        &self,
+        user_id: Uuid,
+        token: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<()> {
         sqlx::query(
             r#"
-            UPDATE auth_users
-            SET session_token = $1
-            WHERE id = $2
+            INSERT INTO refresh_tokens (token, user_id, expires_at)
+            VALUES ($1, $2, $3)
             "#,
         )
         .bind(token)
         .bind(user_id)
+        .bind(expires_at)
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
-    pub async fn get_by_session_token(&self, token: Uuid) -> Result<Option<AuthUser>> {
+    /// Redeems a refresh token, returning its user if it was valid
+    pub async fn take_refresh_token(&self, token: Uuid) -> Result<Option<Uuid>> {
+        sqlx::query(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE token = $1 AND expires_at > NOW()
+            RETURNING user_id
+            "#,
+        )
+        .bind(token)
+        .fetch_optional(&self.pool)
+        .await
+        .map(|row| row.map(|row| row.get(0)))
+        .map_err(Into::into)
+    }
+
+    /// Revokes one of the user's refresh tokens, returning whether it was theirs
+    pub async fn revoke_refresh_token(&self, user_id: Uuid, token: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE token = $1 AND user_id = $2
+            "#,
+        )
+        .bind(token)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Revokes every refresh token of the user, returning how many there were
+    pub async fn revoke_refresh_tokens(&self, user_id: Uuid) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn revoke_access_token(&self, jti: Uuid, expires_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO revoked_access_tokens (jti, expires_at)
+            VALUES ($1, $2)
+            ON CONFLICT (jti) DO NOTHING
+            "#,
+        )
+        .bind(jti)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Access tokens logged out on any instance that have not expired yet
+    pub async fn revoked_access_tokens(&self) -> Result<Vec<(Uuid, DateTime<Utc>)>> {
         sqlx::query_as(
             r#"
-            SELECT * FROM auth_users
-            WHERE session_token = $1
+            SELECT jti, expires_at FROM revoked_access_tokens
+            WHERE expires_at > NOW()
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Deletes expired refresh tokens, verification links, reset codes and revoked access
+    /// tokens, returning how many went
+    pub async fn delete_expired_tokens(&self) -> Result<i64> {
+        sqlx::query(
+            r#"
+            WITH refresh AS (
+                DELETE FROM refresh_tokens WHERE expires_at <= NOW() RETURNING 1
+            ), verifications AS (
+                DELETE FROM email_verifications WHERE expires_at <= NOW() RETURNING 1
+            ), resets AS (
+                DELETE FROM password_resets WHERE expires_at <= NOW() RETURNING 1
+            ), revoked AS (
+                DELETE FROM revoked_access_tokens WHERE expires_at <= NOW() RETURNING 1
+            )
+            SELECT (SELECT COUNT(*) FROM refresh)
+                + (SELECT COUNT(*) FROM verifications)
+                + (SELECT COUNT(*) FROM resets)
+                + (SELECT COUNT(*) FROM revoked)
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map(|row| row.get(0))
+        .map_err(Into::into)
+    }
+
+    pub async fn create_verification_token(
+        &self,
+        user_id: Uuid,
+        token: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO email_verifications (token, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(token)
+        .bind(user_id)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Redeems a verification token and marks its user verified, returning them if it was valid
+    pub async fn verify_email(&self, token: Uuid) -> Result<Option<Uuid>> {
+        sqlx::query(
+            r#"
+            WITH taken AS (
+                DELETE FROM email_verifications
+                WHERE token = $1 AND expires_at > NOW()
+                RETURNING user_id
+            )
+            UPDATE auth_users
+            SET verified_at = COALESCE(verified_at, NOW())
+            FROM taken
+            WHERE auth_users.id = taken.user_id
+            RETURNING auth_users.id
             "#,
         )
         .bind(token)
         .fetch_optional(&self.pool)
         .await
+        .map(|row| row.map(|row| row.get(0)))
         .map_err(Into::into)
     }
+
+    pub async fn create_password_reset(
+        &self,
+        user_id: Uuid,
+        code_hash: &[u8],
+        expires_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO password_resets (user_id, code_hash, expires_at)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(user_id)
+        .bind(code_hash)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Redeems a reset code and sets the new password in one transaction, returning the user
+    /// if the code was valid. The user's other codes and refresh tokens stop working, and the
+    /// email counts as verified. A wrong code counts against all of the user's codes, those
+    /// guessed at `max_attempts` times stop working.
+    pub async fn reset_password(
+        &self,
+        email: String,
+        code_hash: Vec<u8>,
+        hashed_password: String,
+        max_attempts: i32,
+    ) -> Result<Option<Uuid>> {
+        let mut tx = self.pool.begin().await?;
+        let codes: Vec<(Uuid, Vec<u8>)> = sqlx::query_as(
+            r#"
+            SELECT user_id, code_hash FROM password_resets
+            WHERE user_id = (SELECT id FROM auth_users WHERE email = $1)
+                AND expires_at > NOW() AND failed_attempts < $2
+            FOR UPDATE
+            "#,
+        )
+        .bind(email)
+        .bind(max_attempts)
+        .fetch_all(&mut *tx)
+        .await?;
+        let Some(&(user_id, _)) = codes.first() else {
+            return Ok(None);
+        };
+        // every code is compared in full, so the time taken gives nothing away
+        let matched = codes.iter().fold(Choice::from(0), |matched, (_, hash)| {
+            matched | hash.as_slice().ct_eq(&code_hash)
+        });
+        if !bool::from(matched) {
+            sqlx::query(
+                r#"
+                UPDATE password_resets
+                SET failed_attempts = failed_attempts + 1
+                WHERE user_id = $1
+                "#,
+            )
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            return Ok(None);
+        }
+        sqlx::query(
+            r#"
+            UPDATE auth_users
+            SET hashed_password = $2, verified_at = COALESCE(verified_at, NOW())
+            WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .bind(hashed_password)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            DELETE FROM password_resets
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(Some(user_id))
+    }
+
+    /// A socket's session lasts while its user is not banned, still holds an unexpired
+    /// refresh token and has not connected another socket since
+    pub async fn is_session_live(&self, user_id: Uuid, sid: Sid) -> Result<bool> {
+        sqlx::query(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM auth_users u
+                WHERE u.id = $1 AND u.sid = $2 AND u.banned_at IS NULL
+                AND EXISTS (
+                    SELECT 1 FROM refresh_tokens t
+                    WHERE t.user_id = u.id AND t.expires_at > NOW()
+                )
+            )
+            "#,
+        )
+        .bind(user_id)
+        .bind(sid.to_string())
+        .fetch_one(&self.pool)
+        .await
+        .map(|row| row.get(0))
+        .map_err(Into::into)
+    }
+
     pub async fn update_sid(&self, user_id: Uuid, sid: Sid) -> Result<Option<AuthUser>> {
         sqlx::query_as(
             r#"
@@ -101,4 +379,41 @@ impl AuthUserRepository {
         .await
         .map_err(Into::into)
     }
+
+    /// Bans the user and revokes their refresh tokens in one transaction.
+    /// An earlier ban is kept as it was.
+    pub async fn ban(
+        &self,
+        user_id: Uuid,
+        banned_by: Uuid,
+        reason: Option<String>,
+    ) -> Result<Option<AuthUser>> {
+        let mut tx = self.pool.begin().await?;
+        let user = sqlx::query_as(
+            r#"
+            UPDATE auth_users
+            SET banned_at = COALESCE(banned_at, NOW()),
+                banned_by = COALESCE(banned_by, $2),
+                ban_reason = COALESCE(ban_reason, $3)
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(banned_by)
+        .bind(reason)
+        .fetch_optional(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            DELETE FROM refresh_tokens
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(user)
+    }
 }
@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use eyre::Result;
+use sqlx::types::{Json, Uuid};
+use sqlx::{FromRow, PgPool};
+
+use types::domain::{Feedback, FeedbackRequest};
+
+#[derive(FromRow)]
+struct FeedbackRow {
+    id: Uuid,
+    user_id: Uuid,
+    note: Option<String>,
+    client_version: String,
+    client_state: Option<Json<serde_json::Value>>,
+    events: Json<Vec<String>>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<FeedbackRow> for Feedback {
+    fn from(row: FeedbackRow) -> Self {
+        Feedback {
+            id: row.id,
+            user_id: row.user_id,
+            note: row.note,
+            client_version: row.client_version,
+            client_state: row.client_state.map(|state| state.0),
+            events: row.events.0,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FeedbackRepository {
+    pool: PgPool,
+}
+
+impl FeedbackRepository {
+    pub fn new(pool: PgPool) -> Self {
+        FeedbackRepository { pool }
+    }
+
+    pub async fn create(&self, user_id: Uuid, request: FeedbackRequest) -> Result<Uuid> {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO feedback (user_id, note, client_version, client_state, events)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+        )
+        .bind(user_id)
+        .bind(request.note)
+        .bind(request.client_version)
+        .bind(request.client_state.map(Json))
+        .bind(Json(request.events))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Latest reports first
+    pub async fn list(&self, limit: i64) -> Result<Vec<Feedback>> {
+        let rows: Vec<FeedbackRow> = sqlx::query_as(
+            r#"
+            SELECT id, user_id, note, client_version, client_state, events, created_at
+            FROM feedback
+            ORDER BY created_at DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+}
@@ -0,0 +1,127 @@
+use eyre::Result;
+use sqlx::types::Uuid;
+use sqlx::{PgPool, Row};
+
+use types::domain::Friend;
+
+#[derive(Clone)]
+pub struct FriendRepository {
+    pool: PgPool,
+}
+
+impl FriendRepository {
+    pub fn new(pool: PgPool) -> Self {
+        FriendRepository { pool }
+    }
+
+    pub async fn find_by_name(&self, name: &str) -> Result<Vec<Uuid>> {
+        sqlx::query_scalar(
+            r#"
+            SELECT id FROM users
+            WHERE name = $1
+            LIMIT 2
+            "#,
+        )
+        .bind(name)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Asks `addressee_id` to be friends, returning false if either already asked the other
+    pub async fn create_request(&self, requester_id: Uuid, addressee_id: Uuid) -> Result<bool> {
+        let created = sqlx::query(
+            r#"
+            INSERT INTO friends (requester_id, addressee_id)
+            SELECT $1, $2
+            WHERE NOT EXISTS (
+                SELECT 1 FROM friends
+                WHERE requester_id = $2 AND addressee_id = $1
+            )
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(requester_id)
+        .bind(addressee_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(created > 0)
+    }
+
+    /// Accepts a pending request, returning false if there was none
+    pub async fn accept(&self, requester_id: Uuid, addressee_id: Uuid) -> Result<bool> {
+        let accepted = sqlx::query(
+            r#"
+            UPDATE friends
+            SET accepted_at = NOW()
+            WHERE requester_id = $1 AND addressee_id = $2 AND accepted_at IS NULL
+            "#,
+        )
+        .bind(requester_id)
+        .bind(addressee_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(accepted > 0)
+    }
+
+    /// Declines a pending request, returning false if there was none
+    pub async fn decline(&self, requester_id: Uuid, addressee_id: Uuid) -> Result<bool> {
+        let declined = sqlx::query(
+            r#"
+            DELETE FROM friends
+            WHERE requester_id = $1 AND addressee_id = $2 AND accepted_at IS NULL
+            "#,
+        )
+        .bind(requester_id)
+        .bind(addressee_id)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(declined > 0)
+    }
+
+    pub async fn are_friends(&self, user_id: Uuid, other_id: Uuid) -> Result<bool> {
+        sqlx::query(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM friends
+                WHERE accepted_at IS NOT NULL
+                    AND ((requester_id = $1 AND addressee_id = $2)
+                        OR (requester_id = $2 AND addressee_id = $1))
+            )
+            "#,
+        )
+        .bind(user_id)
+        .bind(other_id)
+        .fetch_one(&self.pool)
+        .await
+        .map(|row| row.get(0))
+        .map_err(Into::into)
+    }
+
+    /// Friends first, then requests waiting on either side, by name
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<Friend>> {
+        sqlx::query_as(
+            r#"
+            SELECT u.id AS user_id, u.name,
+                CASE
+                    WHEN f.accepted_at IS NOT NULL THEN 'friend'
+                    WHEN f.addressee_id = $1 THEN 'incoming'
+                    ELSE 'outgoing'
+                END AS status,
+                CASE WHEN f.accepted_at IS NOT NULL THEN u.current_room END AS current_room
+            FROM friends f
+            JOIN users u
+                ON u.id = CASE WHEN f.requester_id = $1 THEN f.addressee_id ELSE f.requester_id END
+            WHERE f.requester_id = $1 OR f.addressee_id = $1
+            ORDER BY f.accepted_at IS NULL, u.name
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+}
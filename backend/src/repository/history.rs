@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use eyre::Result;
+use sqlx::types::{Json, Uuid};
+use sqlx::{FromRow, PgPool};
+
+use types::fairness::Deal;
+use types::history::{HandHistory, HistoryPlayer};
+use types::room::{LoggedAction, Winnings};
+use types::state::SerdeCard;
+
+#[derive(FromRow)]
+struct HandHistoryRow {
+    id: Uuid,
+    room_id: Uuid,
+    players: Json<Vec<HistoryPlayer>>,
+    community_cards: Json<Vec<SerdeCard>>,
+    actions: Json<Vec<LoggedAction>>,
+    results: Json<Vec<Winnings>>,
+    created_at: DateTime<Utc>,
+    deck_hash: Option<String>,
+    deal: Option<Json<Deal>>,
+}
+
+impl From<HandHistoryRow> for HandHistory {
+    fn from(row: HandHistoryRow) -> Self {
+        HandHistory {
+            id: row.id,
+            room_id: row.room_id,
+            players: row.players.0,
+            community_cards: row.community_cards.0,
+            actions: row.actions.0,
+            results: row.results.0,
+            created_at: row.created_at,
+            deck_hash: row.deck_hash,
+            deal: row.deal.map(|deal| deal.0),
+        }
+    }
+}
+
+#[cfg_attr(test, faux::create)]
+#[derive(Clone)]
+pub struct HandHistoryRepository {
+    pool: PgPool,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl HandHistoryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        HandHistoryRepository { pool }
+    }
+
+    pub async fn record(&self, history: HandHistory) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO hand_histories (id, room_id, players, community_cards, actions, results, created_at, deck_hash, deal)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(history.id)
+        .bind(history.room_id)
+        .bind(Json(history.players))
+        .bind(Json(history.community_cards))
+        .bind(Json(history.actions))
+        .bind(Json(history.results))
+        .bind(history.created_at)
+        .bind(history.deck_hash)
+        .bind(history.deal.map(Json))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Hands played in a room, newest first
+    pub async fn get_by_room(
+        &self,
+        room_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<HandHistory>> {
+        let rows: Vec<HandHistoryRow> = sqlx::query_as(
+            r#"
+            SELECT * FROM hand_histories
+            WHERE room_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(room_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    pub async fn get(&self, room_id: Uuid, hand_id: Uuid) -> Result<Option<HandHistory>> {
+        let row: Option<HandHistoryRow> = sqlx::query_as(
+            r#"
+            SELECT * FROM hand_histories
+            WHERE id = $1 AND room_id = $2
+            "#,
+        )
+        .bind(hand_id)
+        .bind(room_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(Into::into))
+    }
+}
@@ -0,0 +1,88 @@
+use eyre::Result;
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+
+use types::domain::{IntegritySignal, SuspiciousPair};
+
+/// A signal one of the integrity checks raised about two players of a hand
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntegrityEvent {
+    pub signal: IntegritySignal,
+    pub player_id: Uuid,
+    pub other_player_id: Uuid,
+    pub amount: i64,
+}
+
+#[derive(Clone)]
+pub struct IntegrityRepository {
+    pool: PgPool,
+}
+
+impl IntegrityRepository {
+    pub fn new(pool: PgPool) -> Self {
+        IntegrityRepository { pool }
+    }
+
+    pub async fn record(
+        &self,
+        room_id: Uuid,
+        hand_id: Uuid,
+        events: Vec<IntegrityEvent>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for event in events {
+            sqlx::query(
+                r#"
+                INSERT INTO integrity_events
+                    (id, room_id, hand_id, signal, player_id, other_player_id, amount)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+            )
+            .bind(Uuid::new_v4())
+            .bind(room_id)
+            .bind(hand_id)
+            .bind(event.signal.as_ref())
+            .bind(event.player_id)
+            .bind(event.other_player_id)
+            .bind(event.amount)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await.map_err(Into::into)
+    }
+
+    /// Pairs with a chip dump or a shared address over the last `days`, or that folded to one
+    /// another at least `min_folds` times, most chips dumped first
+    pub async fn suspicious_pairs(&self, days: i32, min_folds: i64) -> Result<Vec<SuspiciousPair>> {
+        sqlx::query_as(
+            r#"
+            WITH pairs AS (
+                SELECT LEAST(player_id, other_player_id) AS first_player,
+                    GREATEST(player_id, other_player_id) AS second_player,
+                    COUNT(DISTINCT hand_id) AS hands,
+                    COUNT(*) FILTER (WHERE signal = $2) AS folds,
+                    COUNT(*) FILTER (WHERE signal = $3) AS chip_dumps,
+                    COALESCE(SUM(amount) FILTER (WHERE signal = $3), 0)::BIGINT AS chips_dumped,
+                    COUNT(DISTINCT hand_id) FILTER (WHERE signal = $4) AS shared_address_hands
+                FROM integrity_events
+                WHERE created_at > NOW() - make_interval(days => $1)
+                GROUP BY first_player, second_player
+            )
+            SELECT pairs.*, first.name AS first_name, second.name AS second_name
+            FROM pairs
+            JOIN users first ON first.id = pairs.first_player
+            JOIN users second ON second.id = pairs.second_player
+            WHERE chip_dumps > 0 OR shared_address_hands > 0 OR folds >= $5
+            ORDER BY chips_dumped DESC, shared_address_hands DESC, folds DESC
+            "#,
+        )
+        .bind(days)
+        .bind(IntegritySignal::FoldedTo.as_ref())
+        .bind(IntegritySignal::ChipDump.as_ref())
+        .bind(IntegritySignal::SharedAddress.as_ref())
+        .bind(min_folds)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+}
@@ -0,0 +1,43 @@
+use eyre::Result;
+use sqlx::types::Uuid;
+use sqlx::{PgPool, Row};
+
+#[derive(Clone)]
+pub struct InviteRepository {
+    pool: PgPool,
+}
+
+impl InviteRepository {
+    pub fn new(pool: PgPool) -> Self {
+        InviteRepository { pool }
+    }
+
+    pub async fn create(&self, code: &str, room_id: Uuid, created_by: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO room_invites (code, room_id, created_by)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(code)
+        .bind(room_id)
+        .bind(created_by)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_room_id(&self, code: &str) -> Result<Option<Uuid>> {
+        sqlx::query(
+            r#"
+            SELECT room_id FROM room_invites
+            WHERE code = $1
+            "#,
+        )
+        .bind(code)
+        .fetch_optional(&self.pool)
+        .await
+        .map(|row| row.map(|row| row.get(0)))
+        .map_err(Into::into)
+    }
+}
@@ -0,0 +1,84 @@
+use eyre::Result;
+use sqlx::types::{Json, Uuid};
+use sqlx::{PgConnection, Row};
+
+use types::domain::{AdjustmentReason, ChipCurrency, LedgerEntry};
+
+/// One credit or debit of a user's balance
+pub(crate) struct BalanceChange {
+    pub user_id: Uuid,
+    // positive credits the user, negative debits
+    pub amount: i64,
+    pub reason: AdjustmentReason,
+    // None for changes made by the server itself
+    pub admin_id: Option<Uuid>,
+    pub room_id: Option<Uuid>,
+    pub note: Option<String>,
+    pub currency: ChipCurrency,
+}
+
+/// Applies the change and records it as a transaction, as part of the caller's transaction.
+/// Returns None when the balance would go negative, nothing is written then.
+pub(crate) async fn apply(
+    conn: &mut PgConnection,
+    change: BalanceChange,
+) -> Result<Option<LedgerEntry>> {
+    let column = balance_column(change.currency);
+    let balance: Option<i64> = sqlx::query(&format!(
+        r#"
+        UPDATE users
+        SET {column} = {column} + $1, updated_at = NOW()
+        WHERE id = $2 AND {column} + $1 >= 0
+        RETURNING {column}
+        "#,
+    ))
+    .bind(change.amount)
+    .bind(change.user_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    .map(|row| row.get(0));
+    let Some(balance) = balance else {
+        return Ok(None);
+    };
+    let entry = sqlx::query_as(
+        r#"
+        INSERT INTO balance_transactions
+            (id, user_id, admin_id, room_id, amount, reason, note, balance_after, currency)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(change.user_id)
+    .bind(change.admin_id)
+    .bind(change.room_id)
+    .bind(change.amount)
+    .bind(change.reason.as_ref())
+    .bind(change.note)
+    .bind(balance)
+    .bind(change.currency.as_ref())
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(Some(entry))
+}
+
+/// What chips bought into or cashed out of the room are counted in
+pub(crate) async fn room_currency(conn: &mut PgConnection, room_id: Uuid) -> Result<ChipCurrency> {
+    let currency: Option<Json<ChipCurrency>> = sqlx::query_scalar(
+        r#"
+        SELECT currency FROM room_info
+        WHERE room_id = $1
+        "#,
+    )
+    .bind(room_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    Ok(currency.map(|Json(currency)| currency).unwrap_or_default())
+}
+
+// the users column holding the balance in that currency
+fn balance_column(currency: ChipCurrency) -> &'static str {
+    match currency {
+        ChipCurrency::Play => "balance",
+        ChipCurrency::Premium => "premium_balance",
+    }
+}
@@ -1,3 +1,14 @@
+pub(crate) mod admin;
 pub(crate) mod auth;
+pub(crate) mod feedback;
+pub(crate) mod friends;
+pub(crate) mod history;
+pub(crate) mod integrity;
+pub(crate) mod invites;
+pub(crate) mod ledger;
+pub(crate) mod rake;
 pub(crate) mod rooms;
+pub(crate) mod seasons;
+pub(crate) mod stats;
 pub(crate) mod users;
+pub(crate) mod webhooks;
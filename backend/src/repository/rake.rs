@@ -0,0 +1,56 @@
+use eyre::Result;
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+
+use types::domain::RakeDay;
+
+// how many days of totals the rake report goes back
+const REPORT_DAYS: i32 = 90;
+
+#[cfg_attr(test, faux::create)]
+#[derive(Clone)]
+pub struct RakeRepository {
+    pool: PgPool,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl RakeRepository {
+    pub fn new(pool: PgPool) -> Self {
+        RakeRepository { pool }
+    }
+
+    pub async fn record(&self, room_id: Uuid, hand_id: Uuid, amount: u32) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO rake_ledger (id, room_id, hand_id, amount)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(room_id)
+        .bind(hand_id)
+        .bind(amount as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Rake per UTC day, most recent first
+    pub async fn daily_totals(&self) -> Result<Vec<RakeDay>> {
+        sqlx::query_as(
+            r#"
+            SELECT (created_at AT TIME ZONE 'UTC')::DATE AS day,
+                COUNT(*) AS hands,
+                SUM(amount)::BIGINT AS total
+            FROM rake_ledger
+            WHERE created_at > NOW() - make_interval(days => $1)
+            GROUP BY day
+            ORDER BY day DESC
+            "#,
+        )
+        .bind(REPORT_DAYS)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+}
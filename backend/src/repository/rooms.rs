@@ -1,27 +1,161 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
 use dashmap::mapref::one::RefMut;
 use dashmap::DashMap;
 use eyre::{bail, Result};
-use sqlx::types::Uuid;
-use sqlx::PgPool;
+use poker::Evaluator;
+use sha2::{Digest, Sha256};
+use sqlx::types::{Json, Uuid};
+use sqlx::{PgPool, Row};
+use tracing::error;
 
-use types::domain::RoomInfo;
+use types::crypto::{HandKey, SecretKey};
+use types::domain::{AdjustmentReason, ChipCurrency, RoomActivity, RoomInfo};
 use types::error::Error;
-use types::room::Room;
+use types::room::{HandSettlement, Room, RoomConfig, RoomExport, RoomSnapshot};
+
+use crate::repository::ledger::{self, BalanceChange};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Where the full state of every room is written as it changes, to be resumed after a crash
+pub trait RoomStore: Send + Sync {
+    // takes what it needs from the room right away, older revisions never overwrite newer ones
+    fn save(&self, room: &Room, revision: i64) -> BoxFuture<'static, Result<()>>;
+    fn delete(&self, room_id: Uuid) -> BoxFuture<'_, Result<()>>;
+    fn load<'a>(
+        &'a self,
+        room_ids: &'a [Uuid],
+        evaluator: &'a Evaluator,
+    ) -> BoxFuture<'a, Result<Vec<Room>>>;
+}
+
+/// Rooms only live in the repository's map, a crash loses them
+pub struct MemoryRoomStore;
+
+impl RoomStore for MemoryRoomStore {
+    fn save(&self, _room: &Room, _revision: i64) -> BoxFuture<'static, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn delete(&self, _room_id: Uuid) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn load<'a>(
+        &'a self,
+        _room_ids: &'a [Uuid],
+        _evaluator: &'a Evaluator,
+    ) -> BoxFuture<'a, Result<Vec<Room>>> {
+        Box::pin(async { Ok(vec![]) })
+    }
+}
+
+/// Rooms kept in Postgres as exports, their cards sealed to a key derived from a secret
+/// that outlives the process
+pub struct PgRoomStore {
+    pool: PgPool,
+    key: HandKey,
+    secret: SecretKey,
+}
+
+impl PgRoomStore {
+    pub fn new(pool: PgPool, secret: &str) -> Self {
+        let (key, secret) = HandKey::from_secret(Sha256::digest(secret.as_bytes()).into());
+        Self { pool, key, secret }
+    }
+}
+
+impl RoomStore for PgRoomStore {
+    fn save(&self, room: &Room, revision: i64) -> BoxFuture<'static, Result<()>> {
+        let room_id = room.id;
+        let export = room.export(&self.key);
+        let pool = self.pool.clone();
+        Box::pin(async move {
+            sqlx::query(
+                r#"
+                INSERT INTO room_states (room_id, state, revision)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (room_id) DO UPDATE SET
+                    state = EXCLUDED.state,
+                    revision = EXCLUDED.revision,
+                    saved_at = NOW()
+                WHERE room_states.revision < EXCLUDED.revision
+                "#,
+            )
+            .bind(room_id)
+            .bind(Json(export?))
+            .bind(revision)
+            .execute(&pool)
+            .await?;
+            Ok(())
+        })
+    }
+
+    fn delete(&self, room_id: Uuid) -> BoxFuture<'_, Result<()>> {
+        Box::pin(async move {
+            sqlx::query(
+                r#"
+                DELETE FROM room_states
+                WHERE room_id = $1
+                "#,
+            )
+            .bind(room_id)
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+    }
+
+    fn load<'a>(
+        &'a self,
+        room_ids: &'a [Uuid],
+        evaluator: &'a Evaluator,
+    ) -> BoxFuture<'a, Result<Vec<Room>>> {
+        Box::pin(async move {
+            let states: Vec<Json<RoomExport>> = sqlx::query_scalar(
+                r#"
+                SELECT state FROM room_states
+                WHERE room_id = ANY($1)
+                "#,
+            )
+            .bind(room_ids)
+            .fetch_all(&self.pool)
+            .await?;
+            states
+                .into_iter()
+                .map(|Json(export)| Room::import(export, &self.secret, evaluator))
+                .collect()
+        })
+    }
+}
 
 #[derive(Clone)]
 pub struct RoomRepository {
     pub(crate) rooms: Arc<DashMap<Uuid, Room>>,
+    store: Arc<dyn RoomStore>,
+    // last revision handed to the store, saves finishing out of order keep the newest state
+    revision: Arc<AtomicI64>,
 }
 
 impl RoomRepository {
     pub fn new() -> Self {
+        Self::with_store(Arc::new(MemoryRoomStore))
+    }
+
+    pub fn with_store(store: Arc<dyn RoomStore>) -> Self {
         RoomRepository {
             rooms: Arc::new(DashMap::new()),
+            store,
+            revision: Arc::new(AtomicI64::new(0)),
         }
     }
 
+    #[cfg(test)]
     pub fn upsert(&mut self, room: Room) {
         self.rooms.insert(room.id, room);
     }
@@ -33,37 +167,253 @@ impl RoomRepository {
     pub fn get_mut_lock(&self, id: Uuid) -> Option<RefMut<Uuid, Room>> {
         self.rooms.get_mut(&id)
     }
+
+    /// Takes the room out of play, its saved state goes with it
+    pub async fn remove(&self, id: Uuid) -> Option<Room> {
+        if let Err(e) = self.store.delete(id).await {
+            error!("Failed to delete the saved state of room {}: {:?}", id, e);
+        }
+        self.rooms.remove(&id).map(|(_, room)| room)
+    }
+
+    /// Writes the room through to the store, the table carries on even if that fails.
+    /// The state is taken when called, so the room's lock can be let go before awaiting.
+    pub fn save(&self, room: &Room) -> impl Future<Output = ()> + Send + 'static {
+        let room_id = room.id;
+        let saving = self.store.save(room, self.next_revision());
+        async move {
+            if let Err(e) = saving.await {
+                error!("Failed to save the state of room {}: {:?}", room_id, e);
+            }
+        }
+    }
+
+    // microseconds since the epoch, so revisions keep going up when another replica takes over
+    fn next_revision(&self) -> i64 {
+        let now = Utc::now().timestamp_micros();
+        let last = self
+            .revision
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(now.max(last + 1))
+            })
+            .unwrap_or_default();
+        now.max(last + 1)
+    }
+
+    /// Those of the rooms saved before the last crash, with their hands still in progress
+    pub async fn load(&self, room_ids: &[Uuid], evaluator: &Evaluator) -> Result<Vec<Room>> {
+        self.store.load(room_ids, evaluator).await
+    }
+}
+
+// a room is served elsewhere once its owner has not renewed the lease for this long
+const LEASE_SECS: i64 = 30;
+
+/// This backend replica, as the owner of the rooms it serves
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub id: Uuid,
+    // where clients reach this replica, shown with the rooms it serves
+    pub url: Option<String>,
+    // rooms taken on at most, the rest are left to other replicas
+    pub room_limit: Option<usize>,
+}
+
+impl Instance {
+    pub fn from_env() -> Self {
+        Self {
+            // kept across restarts when set, so the rooms of this replica are taken back at once
+            id: std::env::var("INSTANCE_ID")
+                .ok()
+                .and_then(|id| id.parse().ok())
+                .unwrap_or_else(Uuid::new_v4),
+            url: std::env::var("INSTANCE_URL").ok(),
+            room_limit: std::env::var("MAX_ROOMS_PER_INSTANCE")
+                .ok()
+                .and_then(|limit| limit.parse().ok()),
+        }
+    }
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            url: None,
+            room_limit: None,
+        }
+    }
 }
 
 #[cfg_attr(test, faux::create)]
 #[derive(Clone)]
 pub struct RoomInfoRepository {
     pool: PgPool,
+    instance: Instance,
 }
 #[cfg_attr(test, faux::methods)]
 impl RoomInfoRepository {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+    pub fn new(pool: PgPool, instance: Instance) -> Self {
+        Self { pool, instance }
     }
-    pub async fn get_all(&self) -> Result<Vec<RoomInfo>> {
+
+    /// Takes the lease on rooms nobody serves, as many as this instance may still take on
+    /// besides the `held` ones. Rooms still leased to this instance from before a restart
+    /// are taken back too.
+    pub async fn claim_rooms(&self, held: Vec<Uuid>) -> Result<Vec<RoomInfo>> {
+        let limit = self
+            .instance
+            .room_limit
+            .map(|limit| limit.saturating_sub(held.len()) as i64);
         sqlx::query_as(
             r#"
-            SELECT * FROM room_info
+            WITH claimable AS (
+                SELECT room_id FROM room_info
+                WHERE (closes_at IS NULL OR closes_at > NOW())
+                    AND (owner_id IS NULL OR owner_id = $1 OR lease_expires_at < NOW())
+                    AND room_id <> ALL($5)
+                ORDER BY room_id
+                LIMIT $4
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE room_info
+            SET owner_id = $1, owner_url = $2, lease_expires_at = NOW() + $3 * INTERVAL '1 second'
+            FROM claimable
+            WHERE room_info.room_id = claimable.room_id
+            RETURNING room_info.*, room_info.password_hash IS NOT NULL AS is_private
             "#,
         )
+        .bind(self.instance.id)
+        .bind(&self.instance.url)
+        .bind(LEASE_SECS)
+        .bind(limit)
+        .bind(held)
         .fetch_all(&self.pool)
         .await
         .map_err(Into::into)
     }
 
+    /// Extends the lease on the rooms this instance still owns, returning them. The others
+    /// were taken over after their lease ran out.
+    pub async fn renew_leases(&self, room_ids: Vec<Uuid>) -> Result<Vec<Uuid>> {
+        sqlx::query_scalar(
+            r#"
+            UPDATE room_info
+            SET lease_expires_at = NOW() + $3 * INTERVAL '1 second'
+            WHERE room_id = ANY($2) AND owner_id = $1
+            RETURNING room_id
+            "#,
+        )
+        .bind(self.instance.id)
+        .bind(room_ids)
+        .bind(LEASE_SECS)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Leaves every room of this instance for other replicas to take on right away
+    pub async fn release_leases(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE room_info
+            SET owner_id = NULL, owner_url = NULL, lease_expires_at = NULL
+            WHERE owner_id = $1
+            "#,
+        )
+        .bind(self.instance.id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Moves the lease on the given open rooms to this instance whoever held it, returning them
+    pub async fn take_over_leases(&self, room_ids: Vec<Uuid>) -> Result<Vec<Uuid>> {
+        sqlx::query_scalar(
+            r#"
+            UPDATE room_info
+            SET owner_id = $1, owner_url = $2, lease_expires_at = NOW() + $4 * INTERVAL '1 second'
+            WHERE room_id = ANY($3) AND (closes_at IS NULL OR closes_at > NOW())
+            RETURNING room_id
+            "#,
+        )
+        .bind(self.instance.id)
+        .bind(&self.instance.url)
+        .bind(room_ids)
+        .bind(LEASE_SECS)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Open rooms, only those played in `currency` when given
+    pub async fn get_all(&self, currency: Option<ChipCurrency>) -> Result<Vec<RoomInfo>> {
+        sqlx::query_as(
+            r#"
+            SELECT *, password_hash IS NOT NULL AS is_private FROM room_info
+            WHERE (closes_at IS NULL OR closes_at > NOW())
+                AND ($1::JSONB IS NULL OR currency = $1)
+            "#,
+        )
+        .bind(currency.map(Json))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    pub async fn create(
+        &self,
+        created_by: Uuid,
+        password_hash: Option<String>,
+        bot_seats: i32,
+        config: RoomConfig,
+        closes_at: Option<DateTime<Utc>>,
+    ) -> Result<RoomInfo> {
+        sqlx::query_as(
+            r#"
+            INSERT INTO room_info
+                (password_hash, bot_seats, kick_after_timeouts, betting, currency, max_players, owner_id, owner_url, lease_expires_at, created_by, closes_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW() + $9 * INTERVAL '1 second', $10, $11)
+            RETURNING *, password_hash IS NOT NULL AS is_private
+            "#,
+        )
+        .bind(password_hash)
+        .bind(bot_seats)
+        .bind(config.kick_after_timeouts as i32)
+        .bind(Json(config.betting))
+        .bind(Json(config.currency))
+        .bind(config.max_players as i32)
+        .bind(self.instance.id)
+        .bind(&self.instance.url)
+        .bind(LEASE_SECS)
+        .bind(created_by)
+        .bind(closes_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    pub async fn get(&self, room_id: Uuid) -> Result<Option<RoomInfo>> {
+        sqlx::query_as(
+            r#"
+            SELECT *, password_hash IS NOT NULL AS is_private FROM room_info
+            WHERE room_id = $1 AND (closes_at IS NULL OR closes_at > NOW())
+            "#,
+        )
+        .bind(room_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
     pub async fn get_room_for_update(
         &self,
         room_id: Uuid,
-    ) -> Result<(RoomInfo, sqlx::Transaction<'_, sqlx::Postgres>)> {
+    ) -> Result<(RoomInfo, sqlx::Transaction<'static, sqlx::Postgres>)> {
         let mut tx = self.pool.begin().await?;
         let room_info: Option<RoomInfo> = sqlx::query_as(
             r#"
-            SELECT * FROM room_info
+            SELECT *, password_hash IS NOT NULL AS is_private FROM room_info
             WHERE room_id = $1
             FOR UPDATE
             "#,
@@ -84,7 +434,7 @@ impl RoomInfoRepository {
         &self,
         room_id: Uuid,
         player_count: i32,
-        mut tx: sqlx::Transaction<'_, sqlx::Postgres>,
+        mut tx: sqlx::Transaction<'static, sqlx::Postgres>,
     ) -> Result<()> {
         sqlx::query(
             r#"
@@ -100,15 +450,321 @@ impl RoomInfoRepository {
         tx.commit().await.map_err(Into::into)
     }
 
+    /// Lists what the table is doing now, so the lobby can tell active rooms from idle ones
+    pub async fn update_activity(&self, room_id: Uuid, activity: &RoomActivity) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE room_info
+            SET stage = $2, average_pot = $3, last_hand_at = $4
+            WHERE room_id = $1
+            "#,
+        )
+        .bind(room_id)
+        .bind(Json(&activity.stage))
+        .bind(activity.average_pot.map(|pot| pot as i32))
+        .bind(activity.last_hand_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Takes the room off the lobby for good, nobody is left in it
+    pub async fn mark_closed(
+        &self,
+        room_id: Uuid,
+        mut tx: sqlx::Transaction<'static, sqlx::Postgres>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE room_info
+            SET player_count = 0, closes_at = LEAST(COALESCE(closes_at, NOW()), NOW())
+            WHERE room_id = $1
+            "#,
+        )
+        .bind(room_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await.map_err(Into::into)
+    }
+
+    /// Debits the buy-in, seats the user in the room and updates its player count, all
+    /// committed with the room's transaction. Returns None when the balance is too low,
+    /// nothing is written then.
+    pub async fn commit_join(
+        &self,
+        mut tx: sqlx::Transaction<'static, sqlx::Postgres>,
+        room_id: Uuid,
+        user_id: Uuid,
+        buy_in: i64,
+        player_count: i32,
+        // the buy-in is owed back from the start when stacks are settled per hand
+        snapshot_seat: bool,
+    ) -> Result<Option<i64>> {
+        let currency = ledger::room_currency(&mut tx, room_id).await?;
+        let change = BalanceChange {
+            user_id,
+            amount: -buy_in,
+            reason: AdjustmentReason::BuyIn,
+            admin_id: None,
+            room_id: Some(room_id),
+            note: None,
+            currency,
+        };
+        let Some(entry) = ledger::apply(&mut tx, change).await? else {
+            tx.rollback().await?;
+            return Ok(None);
+        };
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET current_room = $1
+            WHERE id = $2
+            "#,
+        )
+        .bind(room_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        if snapshot_seat {
+            upsert_snapshot(&mut tx, room_id, user_id, buy_in).await?;
+        }
+        self.update(room_id, player_count, tx).await?;
+        Ok(Some(entry.balance_after))
+    }
+
+    /// Takes chips a seated user adds to their stack from their balance.
+    /// Returns the balance after it, or None when it was too low.
+    pub async fn commit_top_up(
+        &self,
+        room_id: Uuid,
+        user_id: Uuid,
+        amount: i64,
+        // the top-up is owed back along with the stack when stacks are settled per hand
+        snapshot_seat: bool,
+    ) -> Result<Option<i64>> {
+        let mut tx = self.pool.begin().await?;
+        let currency = ledger::room_currency(&mut tx, room_id).await?;
+        let change = BalanceChange {
+            user_id,
+            amount: -amount,
+            reason: AdjustmentReason::BuyIn,
+            admin_id: None,
+            room_id: Some(room_id),
+            note: None,
+            currency,
+        };
+        let Some(entry) = ledger::apply(&mut tx, change).await? else {
+            tx.rollback().await?;
+            return Ok(None);
+        };
+        if snapshot_seat {
+            sqlx::query(
+                r#"
+                UPDATE room_snapshots
+                SET chips = chips + $3, taken_at = NOW()
+                WHERE room_id = $1 AND user_id = $2
+                "#,
+            )
+            .bind(room_id)
+            .bind(user_id)
+            .bind(amount)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(Some(entry.balance_after))
+    }
+
+    /// Pays the chips back to a user still seated in the room, takes them out of it and
+    /// updates its player count, all committed with the room's transaction.
+    /// Returns false when the user was no longer in the room, it is safe to repeat.
+    pub async fn commit_leave(
+        &self,
+        mut tx: sqlx::Transaction<'static, sqlx::Postgres>,
+        room_id: Uuid,
+        user_id: Uuid,
+        chips: i64,
+        player_count: i32,
+    ) -> Result<bool> {
+        let current_room: Option<Uuid> = sqlx::query(
+            r#"
+            SELECT current_room FROM users
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .and_then(|row| row.get(0));
+        let is_seated = current_room == Some(room_id);
+        if is_seated {
+            if chips > 0 {
+                let currency = ledger::room_currency(&mut tx, room_id).await?;
+                let change = BalanceChange {
+                    user_id,
+                    amount: chips,
+                    reason: AdjustmentReason::CashOut,
+                    admin_id: None,
+                    room_id: Some(room_id),
+                    note: None,
+                    currency,
+                };
+                ledger::apply(&mut tx, change).await?;
+            }
+            sqlx::query(
+                r#"
+                UPDATE users
+                SET current_room = NULL
+                WHERE id = $1
+                "#,
+            )
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        // cashed out, there is nothing left to pay back after a crash
+        sqlx::query(
+            r#"
+            DELETE FROM room_snapshots
+            WHERE room_id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(room_id)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+        self.update(room_id, player_count, tx).await?;
+        Ok(is_seated)
+    }
+
+    pub async fn save_snapshots(&self, snapshots: Vec<RoomSnapshot>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for snapshot in snapshots {
+            for seat in snapshot.seats {
+                upsert_snapshot(&mut tx, snapshot.room_id, seat.player_id, seat.chips as i64)
+                    .await?;
+            }
+        }
+        tx.commit().await.map_err(Into::into)
+    }
+
+    /// Records what a finished hand did to each stack and replaces the room's snapshot
+    /// with the stacks after it, in one transaction
+    pub async fn settle_hand(
+        &self,
+        hand_id: Uuid,
+        snapshot: RoomSnapshot,
+        settlements: Vec<HandSettlement>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            DELETE FROM room_snapshots
+            WHERE room_id = $1
+            "#,
+        )
+        .bind(snapshot.room_id)
+        .execute(&mut *tx)
+        .await?;
+        for seat in snapshot.seats {
+            upsert_snapshot(&mut tx, snapshot.room_id, seat.player_id, seat.chips as i64).await?;
+        }
+        for settlement in settlements {
+            sqlx::query(
+                r#"
+                INSERT INTO hand_settlements (hand_id, room_id, user_id, delta, chips_after)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(hand_id)
+            .bind(snapshot.room_id)
+            .bind(settlement.player_id)
+            .bind(settlement.delta)
+            .bind(settlement.chips_after as i64)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await.map_err(Into::into)
+    }
+
+    /// Pays the saved stacks of the given rooms back to the players still marked as sitting
+    /// in them, and clears their snapshots. Returns how many players were refunded.
+    pub async fn refund_snapshots(&self, room_ids: Vec<Uuid>) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            WITH taken AS (
+                DELETE FROM room_snapshots
+                WHERE room_id = ANY($2)
+                RETURNING room_id, user_id, chips
+            ),
+            -- paid back in the currency of the room they were taken from
+            priced AS (
+                SELECT taken.*, COALESCE(room_info.currency #>> '{}', 'play') AS currency
+                FROM taken
+                LEFT JOIN room_info ON room_info.room_id = taken.room_id
+            ),
+            refunded AS (
+                UPDATE users
+                SET balance = users.balance
+                        + CASE WHEN priced.currency = 'play' THEN priced.chips ELSE 0 END,
+                    premium_balance = users.premium_balance
+                        + CASE WHEN priced.currency = 'premium' THEN priced.chips ELSE 0 END,
+                    current_room = NULL
+                FROM priced
+                WHERE users.id = priced.user_id AND users.current_room = priced.room_id
+                RETURNING users.id, priced.room_id, priced.chips, priced.currency,
+                    CASE WHEN priced.currency = 'premium' THEN users.premium_balance
+                        ELSE users.balance END AS balance
+            )
+            INSERT INTO balance_transactions
+                (id, user_id, admin_id, room_id, amount, reason, note, balance_after, currency)
+            SELECT gen_random_uuid(), id, NULL, room_id, chips, $1, NULL, balance, currency
+            FROM refunded
+            "#,
+        )
+        .bind(AdjustmentReason::RestartRefund.as_ref())
+        .bind(room_ids)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Counts nobody at the rooms no other instance is serving
     pub async fn zero_all_player_counts(&self) -> Result<()> {
         sqlx::query(
             r#"
             UPDATE room_info
             SET player_count = 0
+            WHERE owner_id IS NULL OR owner_id = $1 OR lease_expires_at < NOW()
             "#,
         )
+        .bind(self.instance.id)
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 }
+
+async fn upsert_snapshot(
+    tx: &mut sqlx::Transaction<'static, sqlx::Postgres>,
+    room_id: Uuid,
+    user_id: Uuid,
+    chips: i64,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO room_snapshots (room_id, user_id, chips)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (room_id, user_id) DO UPDATE SET
+            chips = EXCLUDED.chips,
+            taken_at = NOW()
+        "#,
+    )
+    .bind(room_id)
+    .bind(user_id)
+    .bind(chips)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use chrono::{DateTime, Utc};
+use eyre::Result;
+use sqlx::types::Uuid;
+use sqlx::{PgPool, Row};
+
+use types::domain::AdjustmentReason;
+use types::rules::DEFAULT_BALANCE;
+use types::season::{SeasonReward, SeasonStanding, SeasonSummary};
+
+#[derive(Clone)]
+pub struct SeasonRepository {
+    pool: PgPool,
+}
+
+impl SeasonRepository {
+    pub fn new(pool: PgPool) -> Self {
+        SeasonRepository { pool }
+    }
+
+    /// Archives the top balances, rewards the top finishers and optionally resets every
+    /// balance, all in one transaction. Returns None when a season already ended at `ended_at`.
+    pub async fn rollover(
+        &self,
+        ended_at: DateTime<Utc>,
+        reset_balances: bool,
+        standings_size: i64,
+    ) -> Result<Option<SeasonSummary>> {
+        let mut tx = self.pool.begin().await?;
+        let season_id: Option<i64> = sqlx::query(
+            r#"
+            INSERT INTO seasons (ended_at, reset_balances)
+            VALUES ($1, $2)
+            ON CONFLICT (ended_at) DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(ended_at)
+        .bind(reset_balances)
+        .fetch_optional(&mut *tx)
+        .await?
+        .map(|row| row.get(0));
+        let Some(season_id) = season_id else {
+            tx.rollback().await?;
+            return Ok(None);
+        };
+
+        let leaders: Vec<(Uuid, String, i64)> = sqlx::query_as(
+            r#"
+            SELECT id, name, balance FROM users
+            ORDER BY balance DESC, name
+            LIMIT $1
+            "#,
+        )
+        .bind(standings_size)
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut standings = vec![];
+        for (i, (user_id, name, balance)) in leaders.into_iter().enumerate() {
+            let rank = i as i64 + 1;
+            let reward = SeasonReward::for_rank(rank);
+            sqlx::query(
+                r#"
+                INSERT INTO season_standings (season_id, user_id, rank, balance, reward)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(season_id)
+            .bind(user_id)
+            .bind(rank)
+            .bind(balance)
+            .bind(reward.map(|reward| reward.as_ref().to_string()))
+            .execute(&mut *tx)
+            .await?;
+            standings.push(SeasonStanding {
+                user_id,
+                name,
+                rank,
+                balance,
+                reward,
+            });
+        }
+
+        if reset_balances {
+            // every change of balance goes on the ledger, with the server as the author
+            sqlx::query(
+                r#"
+                WITH reset AS (
+                    UPDATE users
+                    SET balance = $1, updated_at = NOW()
+                    FROM users AS old
+                    WHERE users.id = old.id AND old.balance <> $1
+                    RETURNING users.id, old.balance AS balance_before
+                )
+                INSERT INTO balance_transactions (id, user_id, admin_id, amount, reason, note, balance_after)
+                SELECT gen_random_uuid(), id, NULL, $1 - balance_before, $2, $3, $1
+                FROM reset
+                "#,
+            )
+            .bind(DEFAULT_BALANCE)
+            .bind(AdjustmentReason::SeasonReset.as_ref())
+            .bind(format!("Season {} ended", season_id))
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(Some(SeasonSummary {
+            season_id,
+            ended_at,
+            reset_balances,
+            standings,
+        }))
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use eyre::Result;
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+
+use types::domain::FoldReason;
+use types::stats::{HandClassStats, HandOutcome, LeaderboardEntry, PlayerStats, ShowdownResult};
+
+#[cfg_attr(test, faux::create)]
+#[derive(Clone)]
+pub struct StatsRepository {
+    pool: PgPool,
+}
+
+#[cfg_attr(test, faux::methods)]
+impl StatsRepository {
+    pub fn new(pool: PgPool) -> Self {
+        StatsRepository { pool }
+    }
+
+    pub async fn record_showdowns(
+        &self,
+        room_id: Uuid,
+        results: Vec<ShowdownResult>,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for result in results {
+            sqlx::query(
+                r#"
+                INSERT INTO showdowns (user_id, room_id, hand_class, won)
+                VALUES ($1, $2, $3, $4)
+                "#,
+            )
+            .bind(result.player_id)
+            .bind(room_id)
+            .bind(result.hand_class.as_ref())
+            .bind(result.won)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await.map_err(Into::into)
+    }
+
+    pub async fn record_hands(&self, outcomes: Vec<HandOutcome>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for outcome in outcomes {
+            sqlx::query(
+                r#"
+                INSERT INTO player_stats
+                    (user_id, hands_played, hands_won, net_winnings, vpip_hands, showdowns,
+                     all_in_hands, all_in_ev, all_in_net, timeout_folds, disconnect_folds)
+                VALUES ($1, 1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                ON CONFLICT (user_id) DO UPDATE SET
+                    hands_played = player_stats.hands_played + 1,
+                    hands_won = player_stats.hands_won + EXCLUDED.hands_won,
+                    net_winnings = player_stats.net_winnings + EXCLUDED.net_winnings,
+                    vpip_hands = player_stats.vpip_hands + EXCLUDED.vpip_hands,
+                    showdowns = player_stats.showdowns + EXCLUDED.showdowns,
+                    all_in_hands = player_stats.all_in_hands + EXCLUDED.all_in_hands,
+                    all_in_ev = player_stats.all_in_ev + EXCLUDED.all_in_ev,
+                    all_in_net = player_stats.all_in_net + EXCLUDED.all_in_net,
+                    timeout_folds = player_stats.timeout_folds + EXCLUDED.timeout_folds,
+                    disconnect_folds = player_stats.disconnect_folds + EXCLUDED.disconnect_folds,
+                    updated_at = NOW()
+                "#,
+            )
+            .bind(outcome.player_id)
+            .bind(outcome.won as i64)
+            .bind(outcome.net_chips)
+            .bind(outcome.vpip as i64)
+            .bind(outcome.showdown as i64)
+            .bind(outcome.all_in_ev.is_some() as i64)
+            .bind(outcome.all_in_ev.unwrap_or_default())
+            .bind(outcome.all_in_ev.map_or(0, |_| outcome.net_chips))
+            .bind((outcome.fold_reason == Some(FoldReason::Timeout)) as i64)
+            .bind((outcome.fold_reason == Some(FoldReason::Disconnect)) as i64)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("INSERT INTO hand_results (user_id, net_chips) VALUES ($1, $2)")
+                .bind(outcome.player_id)
+                .bind(outcome.net_chips)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await.map_err(Into::into)
+    }
+
+    pub async fn player_stats(&self, user_id: Uuid) -> Result<Option<PlayerStats>> {
+        sqlx::query_as(
+            r#"
+            SELECT hands_played, hands_won, net_winnings, vpip_hands, showdowns,
+                all_in_hands, all_in_ev, all_in_net, timeout_folds, disconnect_folds
+            FROM player_stats
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    pub async fn hand_class_stats(&self, user_id: Uuid) -> Result<Vec<HandClassStats>> {
+        sqlx::query_as(
+            r#"
+            SELECT hand_class, COUNT(*) AS showdowns, COUNT(*) FILTER (WHERE won) AS wins
+            FROM showdowns
+            WHERE user_id = $1
+            GROUP BY hand_class
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Players with the most net winnings since the given time, or ever without one
+    pub async fn leaderboard(
+        &self,
+        since: Option<DateTime<Utc>>,
+        limit: i64,
+    ) -> Result<Vec<LeaderboardEntry>> {
+        let query = match since {
+            // lifetime totals go back further than the per hand results
+            None => sqlx::query_as(
+                r#"
+                SELECT RANK() OVER (ORDER BY s.net_winnings DESC) AS rank,
+                    s.user_id, u.name, s.net_winnings, s.hands_played
+                FROM player_stats s
+                JOIN users u ON u.id = s.user_id
+                ORDER BY s.net_winnings DESC, u.name
+                LIMIT $1
+                "#,
+            ),
+            Some(since) => sqlx::query_as(
+                r#"
+                SELECT RANK() OVER (ORDER BY SUM(r.net_chips) DESC) AS rank,
+                    r.user_id, u.name, SUM(r.net_chips)::BIGINT AS net_winnings,
+                    COUNT(*) AS hands_played
+                FROM hand_results r
+                JOIN users u ON u.id = r.user_id
+                WHERE r.played_at >= $1
+                GROUP BY r.user_id, u.name
+                ORDER BY net_winnings DESC, u.name
+                LIMIT $2
+                "#,
+            )
+            .bind(since),
+        };
+        query
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(Into::into)
+    }
+}
@@ -1,8 +1,13 @@
-use eyre::Result;
+use chrono::{DateTime, TimeDelta, Utc};
+use eyre::{ensure, Result};
 use sqlx::types::Uuid;
 use sqlx::Row;
 
-use types::domain::User;
+use types::domain::{AdjustmentReason, Avatar, ChipCurrency, LedgerEntry, User};
+use types::error::Error;
+use types::rules::DEFAULT_BALANCE;
+
+use crate::repository::ledger::{self, BalanceChange};
 
 #[cfg_attr(test, faux::create)]
 #[derive(Clone)]
@@ -10,8 +15,6 @@ pub struct UserRepository {
     pool: sqlx::PgPool,
 }
 
-const DEFAULT_BALANCE: i64 = 1000;
-
 #[cfg_attr(test, faux::methods)]
 impl UserRepository {
     pub fn new(pool: sqlx::PgPool) -> Self {
@@ -34,18 +37,26 @@ impl UserRepository {
         .map_err(Into::into)
     }
 
-    pub async fn upsert_user_with_username(&self, id: Uuid, name: String) -> Result<User> {
+    /// Sets the user's name, and their avatar when one is given
+    pub async fn upsert_profile(
+        &self,
+        id: Uuid,
+        name: String,
+        avatar: Option<Avatar>,
+    ) -> Result<User> {
         sqlx::query_as(
             r#"
-            INSERT INTO users (id, name, balance)
-            VALUES ($1, $2, $3)
-            ON CONFLICT (id) DO UPDATE SET name = $2
+            INSERT INTO users (id, name, balance, avatar)
+            VALUES ($1, $2, $3, COALESCE($4, $5))
+            ON CONFLICT (id) DO UPDATE SET name = $2, avatar = COALESCE($4, users.avatar)
             RETURNING *
             "#,
         )
         .bind(id)
         .bind(&name)
         .bind(DEFAULT_BALANCE)
+        .bind(avatar.as_ref().map(AsRef::<str>::as_ref))
+        .bind(Avatar::default().as_ref())
         .fetch_one(&self.pool)
         .await
         .map_err(Into::into)
@@ -64,43 +75,135 @@ impl UserRepository {
         .map_err(Into::into)
     }
 
-    pub async fn update_balance_and_room(
+    /// Takes the user out of their room and pays their chips back, in one transaction
+    pub async fn remove_player_and_reimburse_chips(
         &self,
-        id: Uuid,
-        balance: i64,
-        room_id: Uuid,
-    ) -> Result<()> {
-        sqlx::query(
+        user_id: Uuid,
+        reimburse_chips: i64,
+    ) -> Result<Option<User>> {
+        let mut tx = self.pool.begin().await?;
+        let room_id: Option<Uuid> = sqlx::query(
+            r#"
+            SELECT current_room FROM users
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .and_then(|row| row.get(0));
+        if reimburse_chips > 0 {
+            let currency = match room_id {
+                Some(room_id) => ledger::room_currency(&mut tx, room_id).await?,
+                None => ChipCurrency::Play,
+            };
+            let change = BalanceChange {
+                user_id,
+                amount: reimburse_chips,
+                reason: AdjustmentReason::CashOut,
+                admin_id: None,
+                room_id,
+                note: None,
+                currency,
+            };
+            ledger::apply(&mut tx, change).await?;
+        }
+        let user = sqlx::query_as(
             r#"
             UPDATE users
-            SET balance = $1, current_room = $2
-            WHERE id = $3
+            SET current_room = NULL
+            WHERE id = $1
+            RETURNING *
             "#,
         )
-        .bind(balance)
-        .bind(room_id)
-        .bind(id)
-        .execute(&self.pool)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
         .await?;
-        Ok(())
+        tx.commit().await?;
+        Ok(user)
     }
 
-    pub async fn remove_player_and_reimburse_chips(
+    /// Credits free play chips, at most once every interval.
+    /// Fails with TopUpNotReady when the last claim is more recent than that.
+    pub async fn top_up(
         &self,
         user_id: Uuid,
-        reimburse_chips: i64,
-    ) -> Result<Option<User>> {
+        amount: i64,
+        interval: TimeDelta,
+    ) -> Result<Option<LedgerEntry>> {
+        let mut tx = self.pool.begin().await?;
+        // locks the user so two claims at once cannot both pass the check
+        let exists = sqlx::query("SELECT 1 FROM users WHERE id = $1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .is_some();
+        if !exists {
+            tx.rollback().await?;
+            return Ok(None);
+        }
+        let last_claim: Option<DateTime<Utc>> = sqlx::query(
+            r#"
+            SELECT MAX(created_at) FROM balance_transactions
+            WHERE user_id = $1 AND reason = $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(AdjustmentReason::TopUp.as_ref())
+        .fetch_one(&mut *tx)
+        .await?
+        .get(0);
+        if let Some(next_claim) = last_claim.map(|at| at + interval) {
+            ensure!(next_claim <= Utc::now(), Error::TopUpNotReady(next_claim));
+        }
+        let change = BalanceChange {
+            user_id,
+            amount,
+            reason: AdjustmentReason::TopUp,
+            admin_id: None,
+            room_id: None,
+            note: None,
+            currency: ChipCurrency::Play,
+        };
+        let entry = ledger::apply(&mut tx, change).await?;
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    /// The user's ledger, newest first. Reasons, dates and the currency are only filtered on
+    /// when given, `until` is exclusive.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn get_transactions(
+        &self,
+        user_id: Uuid,
+        reasons: Option<Vec<String>>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        currency: Option<ChipCurrency>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<LedgerEntry>> {
         sqlx::query_as(
             r#"
-            UPDATE users
-            SET current_room = NULL, balance = balance + $1
-            WHERE id = $2
-            RETURNING *
+            SELECT * FROM balance_transactions
+            WHERE user_id = $1
+                AND ($2::TEXT[] IS NULL OR reason = ANY($2))
+                AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR created_at < $4)
+                AND ($5::TEXT IS NULL OR currency = $5)
+            ORDER BY created_at DESC
+            LIMIT $6 OFFSET $7
             "#,
         )
-        .bind(reimburse_chips)
         .bind(user_id)
-        .fetch_optional(&self.pool)
+        .bind(reasons)
+        .bind(since)
+        .bind(until)
+        .bind(currency.map(|currency| currency.as_ref().to_string()))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
         .await
         .map_err(Into::into)
     }
@@ -0,0 +1,62 @@
+use eyre::Result;
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+
+use types::webhook::Webhook;
+
+#[derive(Clone)]
+pub struct WebhookRepository {
+    pool: PgPool,
+}
+
+impl WebhookRepository {
+    pub fn new(pool: PgPool) -> Self {
+        WebhookRepository { pool }
+    }
+
+    pub async fn create(
+        &self,
+        room_id: Option<Uuid>,
+        url: &str,
+        secret: &str,
+        created_by: Uuid,
+    ) -> Result<Webhook> {
+        sqlx::query_as(
+            r#"
+            INSERT INTO webhooks (room_id, url, secret, created_by)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id, room_id, url, secret, created_at
+            "#,
+        )
+        .bind(room_id)
+        .bind(url)
+        .bind(secret)
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    // only the ones registered on the room itself
+    pub async fn count_for_room(&self, room_id: Uuid) -> Result<i64> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM webhooks WHERE room_id = $1")
+            .bind(room_id)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(Into::into)
+    }
+
+    // webhooks registered on the room, plus the server wide ones
+    pub async fn get_for_room(&self, room_id: Uuid) -> Result<Vec<Webhook>> {
+        sqlx::query_as(
+            r#"
+            SELECT id, room_id, url, secret, created_at FROM webhooks
+            WHERE room_id = $1 OR room_id IS NULL
+            "#,
+        )
+        .bind(room_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(Into::into)
+    }
+}
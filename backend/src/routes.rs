@@ -1,40 +1,95 @@
+use std::net::IpAddr;
 use std::str::FromStr;
 
-use eyre::{ensure, ContextCompat, Result};
+use chrono::{TimeDelta, Utc};
+use eyre::{bail, ensure, ContextCompat, Result};
 use socketioxide::socket::Sid;
 use sqlx::types::Uuid;
 use validator::Validate;
 
 use types::domain::{
-    ActionRequest, JoinGameRequest, LoginRequest, SignupRequest, UpdateProfileRequest, User,
+    ActionRequest, AdjustBalanceRequest, AdminOverview, AdminUserView, Announcement, BanRequest, BroadcastRequest, ChatRequest, CreateRoomRequest, DarkActionRequest,
+    Feedback, FeedbackQuery, FeedbackRequest, ForgotPasswordRequest, Friend, FriendRequest, IntegrityQuery, JoinGameRequest, LedgerEntry,
+    LoginRequest, LogoutRequest, PreActionRequest, RakeDay, RefreshRequest, ResetPasswordRequest, Role, RoomInfo, RoomInvite, ShowCardsRequest, SignupRequest,
+    SignupResponse, SuspiciousPair,
+    TokenPair, TopUpRequest, TransactionPage, TransactionQuery,
+    UpdateProfileRequest, User,
+    UserSearchQuery,
 };
 use types::error::Error;
-use types::room::Room;
+use types::crypto::HandKey;
+use types::room::{Room, RoomConfig, RoomExport, Scenario};
+use types::rules::TABLE_SIZES;
+use types::season::{RolloverRequest, SeasonSummary};
+use types::state::ChatMessage;
+use types::stats::{Leaderboard, LeaderboardPeriod, ProfileStats};
+use types::webhook::{RegisterWebhookRequest, Webhook};
 
-use crate::domain::auth::AuthUser;
+use crate::domain::auth::{AuthUser, Claims};
+use crate::service::admin::AdminService;
 use crate::service::auth::AuthService;
+use crate::service::feedback::FeedbackService;
+use crate::service::friends::FriendService;
 use crate::service::game::GameService;
+use crate::service::integrity::IntegrityService;
+use crate::service::invites::InviteService;
+use crate::service::limits::LoadShedder;
+use crate::service::metrics::{Gauges, ServerMetrics};
+use crate::service::migration::MigrationService;
+use crate::service::seasons::SeasonService;
 use crate::service::users::UserService;
+use crate::service::webhooks::WebhookService;
 
 #[derive(Clone)]
 pub struct Api {
     pub game_service: GameService,
     pub auth_service: AuthService,
     pub user_service: UserService,
+    pub invite_service: InviteService,
+    pub admin_service: AdminService,
+    pub webhook_service: WebhookService,
+    pub load_shedder: LoadShedder,
+    pub season_service: SeasonService,
+    pub feedback_service: FeedbackService,
+    pub friend_service: FriendService,
+    pub migration_service: MigrationService,
+    pub integrity_service: IntegrityService,
+    pub metrics: ServerMetrics,
 }
 
 impl Api {
-    pub async fn signup(&self, request: SignupRequest) -> Result<()> {
+    pub async fn signup(&self, request: SignupRequest) -> Result<SignupResponse> {
         request
             .validate()
             .map_err(|_| Error::InvalidEmailOrPassword)?;
-        self.auth_service
+        let verification_sent = self
+            .auth_service
             .signup(request.email, request.password)
             .await?;
-        Ok(())
+        Ok(SignupResponse { verification_sent })
+    }
+
+    pub async fn verify_email(&self, token: Uuid) -> Result<()> {
+        self.auth_service.verify_email(token).await
+    }
+
+    pub async fn forgot_password(&self, request: ForgotPasswordRequest) -> Result<()> {
+        request
+            .validate()
+            .map_err(|_| Error::InvalidEmailOrPassword)?;
+        self.auth_service.forgot_password(request.email).await
     }
 
-    pub async fn login(&self, request: LoginRequest) -> Result<Uuid> {
+    pub async fn reset_password(&self, request: ResetPasswordRequest) -> Result<()> {
+        request
+            .validate()
+            .map_err(|_| Error::InvalidEmailOrPassword)?;
+        self.auth_service
+            .reset_password(request.email, request.code, request.new_password)
+            .await
+    }
+
+    pub async fn login(&self, request: LoginRequest) -> Result<TokenPair> {
         request
             .validate()
             .map_err(|_| Error::InvalidEmailOrPassword)?;
@@ -43,50 +98,136 @@ impl Api {
             .await
     }
 
+    pub async fn refresh(&self, request: RefreshRequest) -> Result<TokenPair> {
+        self.auth_service.refresh(request.refresh_token).await
+    }
+
+    /// Logs out of one device, taking the user's game socket out of play
+    pub async fn logout(&self, claims: Claims, request: LogoutRequest) -> Result<()> {
+        self.auth_service
+            .logout(&claims, request.refresh_token)
+            .await?;
+        self.disconnect_user(claims.sub).await
+    }
+
+    /// Logs out of every device
+    pub async fn logout_everywhere(&self, claims: Claims) -> Result<()> {
+        self.auth_service.logout_everywhere(&claims).await?;
+        self.disconnect_user(claims.sub).await
+    }
+
+    // the access token may outlive the session by a few minutes, the socket does not
+    async fn disconnect_user(&self, user_id: Uuid) -> Result<()> {
+        let user = self
+            .auth_service
+            .get_user(user_id)
+            .await?
+            .wrap_err(Error::UserNotFound)?;
+        if let Some(sid) = user.sid {
+            let sid = Sid::from_str(&sid)?;
+            self.auth_service.forget_session(sid);
+            self.end_session(user_id, sid).await?;
+        }
+        Ok(())
+    }
+
     pub async fn update_profile(
         &self,
         user_id: Uuid,
         request: UpdateProfileRequest,
     ) -> Result<User> {
         self.user_service
-            .upsert_profile(user_id, request.username)
+            .upsert_profile(user_id, request.username, request.avatar)
             .await
     }
 
-    pub async fn get_user_by_session_token(&self, token: Uuid) -> Result<Option<AuthUser>> {
-        self.auth_service.get_user_by_session_token(token).await
+    pub fn verify_access_token(&self, token: &str) -> Result<Claims> {
+        self.auth_service.verify_access_token(token)
     }
 
-    pub async fn connect_player_by_token(&self, token: Uuid, sid: Sid) -> Result<Option<AuthUser>> {
-        // get user by token, disconnect user from old socket, update socket id
+    pub async fn connect_player_by_token(&self, token: &str, sid: Sid) -> Result<Option<AuthUser>> {
+        // verify token, disconnect user from old socket, update socket id
+        let claims = self.verify_access_token(token)?;
         let user = self
-            .get_user_by_session_token(token)
+            .auth_service
+            .get_user(claims.sub)
             .await?
             .wrap_err("User not found")?;
+        // the access token may outlive the ban by a few minutes
+        ensure!(user.banned_at.is_none(), Error::UserBanned);
 
         if let Some(old_sid) = user.sid {
             let old_sid = Sid::from_str(&old_sid)?;
-            // remove player from old room
-            self.game_service.leave_player(user.id, old_sid).await?;
+            // the seat is held for this connection to take back by joining the room again
+            self.game_service
+                .disconnect_player(user.id, old_sid)
+                .await?;
             // break old connection
             self.game_service.disconnect_socket(old_sid)?;
         }
         self.auth_service.update_sid(user.id, sid).await
     }
 
+    pub async fn is_session_live(&self, user_id: Uuid, sid: Sid) -> Result<bool> {
+        self.auth_service.is_session_live(user_id, sid).await
+    }
+
+    /// Takes a socket whose session was revoked or expired out of play, like a ban does
+    pub async fn end_session(&self, user_id: Uuid, sid: Sid) -> Result<()> {
+        self.game_service.leave_player(user_id, sid).await?;
+        self.game_service.disconnect_socket(sid)?;
+        Ok(())
+    }
+
     pub async fn get_profile(&self, user_id: Uuid) -> Result<Option<User>> {
         self.user_service.get(user_id).await
     }
 
+    pub async fn get_profile_stats(&self, user_id: Uuid) -> Result<ProfileStats> {
+        self.user_service.get_stats(user_id).await
+    }
+
+    pub async fn get_leaderboard(&self, period: LeaderboardPeriod) -> Result<Leaderboard> {
+        self.user_service.get_leaderboard(period).await
+    }
+
+    pub async fn top_up(&self, user_id: Uuid) -> Result<LedgerEntry> {
+        self.user_service.top_up(user_id).await
+    }
+
+    pub async fn get_transactions(
+        &self,
+        user_id: Uuid,
+        query: TransactionQuery,
+    ) -> Result<TransactionPage> {
+        self.user_service.get_transactions(user_id, query).await
+    }
+
     pub async fn join_game(
         &self,
         user_id: Uuid,
         request: JoinGameRequest,
         sid: Sid,
     ) -> Result<Room> {
+        // players rejoining their seat are already counted
+        if !self.game_service.is_seated(request.room_id, user_id) {
+            self.load_shedder
+                .check_players(self.game_service.seated_players())?;
+        }
+        let room = self
+            .game_service
+            .join_player(
+                request.room_id,
+                user_id,
+                request.buy_in,
+                request.password,
+                request.seat,
+                sid,
+            )
+            .await?;
         self.game_service
-            .join_player(request.room_id, user_id, request.buy_in, sid)
-            .await
+            .send_resume_summary(room.id, user_id, request.last_seen_seq, sid)?;
+        Ok(room)
     }
 
     pub async fn take_action(&self, user_id: Uuid, request: ActionRequest) -> Result<Room> {
@@ -100,4 +241,272 @@ impl Api {
             .take_action(request.room_id, user_id, request.action)
             .await
     }
+
+    pub async fn declare_dark_action(
+        &self,
+        user_id: Uuid,
+        request: DarkActionRequest,
+    ) -> Result<Room> {
+        ensure!(
+            self.user_service
+                .is_user_in_room(user_id, request.room_id)
+                .await?,
+            Error::NotInRoom
+        );
+        self.game_service
+            .declare_dark_action(request.room_id, user_id, request.action)
+            .await
+    }
+
+    pub async fn set_pre_action(&self, user_id: Uuid, request: PreActionRequest) -> Result<Room> {
+        ensure!(
+            self.user_service
+                .is_user_in_room(user_id, request.room_id)
+                .await?,
+            Error::NotInRoom
+        );
+        self.game_service
+            .set_pre_action(request.room_id, user_id, request.pre_action)
+            .await
+    }
+
+    pub async fn ready(&self, user_id: Uuid, room_id: Uuid) -> Result<()> {
+        ensure!(
+            self.user_service.is_user_in_room(user_id, room_id).await?,
+            Error::NotInRoom
+        );
+        self.game_service.player_ready(room_id, user_id).await
+    }
+
+    pub async fn show_cards(&self, user_id: Uuid, request: ShowCardsRequest) -> Result<()> {
+        ensure!(
+            self.user_service.is_user_in_room(user_id, request.room_id).await?,
+            Error::NotInRoom
+        );
+        self.game_service
+            .show_cards(request.room_id, user_id, request.show)
+            .await
+    }
+
+    pub async fn send_chat(&self, user_id: Uuid, request: ChatRequest) -> Result<ChatMessage> {
+        ensure!(
+            self.user_service.is_user_in_room(user_id, request.room_id).await?,
+            Error::NotInRoom
+        );
+        self.game_service
+            .send_chat(request.room_id, user_id, request.message)
+            .await
+    }
+
+    pub async fn sit_out(&self, user_id: Uuid, room_id: Uuid) -> Result<Room> {
+        ensure!(
+            self.user_service.is_user_in_room(user_id, room_id).await?,
+            Error::NotInRoom
+        );
+        self.game_service.sit_out(room_id, user_id).await
+    }
+
+    pub async fn top_up_at_table(&self, user_id: Uuid, request: TopUpRequest) -> Result<()> {
+        ensure!(
+            self.user_service.is_user_in_room(user_id, request.room_id).await?,
+            Error::NotInRoom
+        );
+        self.game_service
+            .top_up(request.room_id, user_id, request.amount)
+            .await
+    }
+
+    pub async fn sit_in(&self, user_id: Uuid, room_id: Uuid) -> Result<Room> {
+        ensure!(
+            self.user_service.is_user_in_room(user_id, room_id).await?,
+            Error::NotInRoom
+        );
+        self.game_service.sit_in(room_id, user_id).await
+    }
+
+    pub async fn create_room(&self, user_id: Uuid, request: CreateRoomRequest) -> Result<RoomInfo> {
+        self.load_shedder
+            .check_rooms(self.game_service.room_repository.rooms.len())?;
+        let mut config = RoomConfig::default();
+        if let Some(kick_after_timeouts) = request.kick_after_timeouts {
+            config.kick_after_timeouts = kick_after_timeouts;
+        }
+        config.betting = request.betting;
+        config.currency = request.currency;
+        if let Some(max_players) = request.max_players {
+            ensure!(TABLE_SIZES.contains(&max_players), Error::InvalidTableSize);
+            config.max_players = max_players;
+        }
+        let closes_at = match (request.closes_at, request.closes_after_minutes) {
+            (Some(_), Some(_)) => bail!(Error::InvalidClosingTime),
+            (closes_at, None) => closes_at,
+            (None, Some(minutes)) => Some(Utc::now() + TimeDelta::minutes(minutes.into())),
+        };
+        ensure!(
+            closes_at.is_none_or(|closes_at| closes_at > Utc::now()),
+            Error::InvalidClosingTime
+        );
+        self.game_service
+            .open_room(user_id, request.password, request.bot_seats, config, closes_at)
+            .await
+    }
+
+    pub async fn create_invite(&self, user_id: Uuid, room_id: Uuid) -> Result<RoomInvite> {
+        self.invite_service.create_invite(room_id, user_id).await
+    }
+
+    pub async fn get_invited_room(&self, code: &str) -> Result<RoomInfo> {
+        self.invite_service.get_invited_room(code).await
+    }
+
+    pub async fn register_room_webhook(
+        &self,
+        user_id: Uuid,
+        room_id: Uuid,
+        request: RegisterWebhookRequest,
+    ) -> Result<Webhook> {
+        let is_admin = self
+            .auth_service
+            .get_user(user_id)
+            .await?
+            .is_some_and(|user| user.role == Role::Admin);
+        self.webhook_service
+            .register(Some(room_id), user_id, is_admin, request.url)
+            .await
+    }
+
+    pub async fn register_server_webhook(
+        &self,
+        admin_id: Uuid,
+        request: RegisterWebhookRequest,
+    ) -> Result<Webhook> {
+        self.webhook_service
+            .register(None, admin_id, true, request.url)
+            .await
+    }
+
+    pub async fn adjust_balance(
+        &self,
+        admin_id: Uuid,
+        user_id: Uuid,
+        request: AdjustBalanceRequest,
+    ) -> Result<LedgerEntry> {
+        self.admin_service
+            .adjust_balance(admin_id, user_id, request)
+            .await
+    }
+
+    pub async fn broadcast(&self, admin_id: Uuid, request: BroadcastRequest) -> Result<Announcement> {
+        self.admin_service.broadcast(admin_id, request).await
+    }
+
+    pub async fn set_scenario(&self, room_id: Uuid, scenario: Option<Scenario>) -> Result<()> {
+        self.game_service.set_scenario(room_id, scenario).await?;
+        Ok(())
+    }
+
+    pub async fn end_season(&self, request: RolloverRequest) -> Result<SeasonSummary> {
+        self.season_service.rollover(request.reset_balances).await
+    }
+
+    pub async fn rake_report(&self) -> Result<Vec<RakeDay>> {
+        self.admin_service.rake_report().await
+    }
+
+    pub async fn integrity_report(&self, query: IntegrityQuery) -> Result<Vec<SuspiciousPair>> {
+        self.integrity_service.report(query).await
+    }
+
+    pub fn remember_address(&self, user_id: Uuid, address: IpAddr) {
+        self.integrity_service.remember_address(user_id, address);
+    }
+
+    pub fn render_metrics(&self) -> String {
+        self.metrics.render(Gauges {
+            active_rooms: self.game_service.room_repository.rooms.len(),
+            connected_sockets: self.load_shedder.metrics().sockets,
+        })
+    }
+
+    pub async fn search_users(&self, query: UserSearchQuery) -> Result<Vec<AdminUserView>> {
+        self.admin_service.search_users(query).await
+    }
+
+    pub async fn close_room(&self, room_id: Uuid) -> Result<()> {
+        self.game_service.force_close_room(room_id).await
+    }
+
+    /// Bans the user and takes them off their table, their stack goes back to their balance
+    pub async fn ban_user(
+        &self,
+        moderator_id: Uuid,
+        user_id: Uuid,
+        request: BanRequest,
+    ) -> Result<()> {
+        let user = self
+            .auth_service
+            .ban(user_id, moderator_id, request.reason)
+            .await?;
+        if let Some(sid) = user.sid {
+            let sid = Sid::from_str(&sid)?;
+            self.game_service.leave_player(user.id, sid).await?;
+            self.game_service.disconnect_socket(sid)?;
+        }
+        Ok(())
+    }
+
+    pub async fn admin_overview(&self) -> Result<AdminOverview> {
+        let (users, banned_users) = self.admin_service.user_counts().await?;
+        Ok(AdminOverview {
+            rooms: self.game_service.room_repository.rooms.len(),
+            rooms_in_play: self.game_service.rooms_in_play(),
+            seated_players: self.game_service.seated_players(),
+            connected_sockets: self.load_shedder.metrics().sockets,
+            staged_changes: self.game_service.outbox.len(),
+            users,
+            banned_users,
+        })
+    }
+
+    pub async fn submit_feedback(&self, user_id: Uuid, request: FeedbackRequest) -> Result<Uuid> {
+        self.feedback_service.submit(user_id, request).await
+    }
+
+    pub async fn list_feedback(&self, query: FeedbackQuery) -> Result<Vec<Feedback>> {
+        self.feedback_service.list(query).await
+    }
+
+    pub async fn list_friends(&self, user_id: Uuid) -> Result<Vec<Friend>> {
+        self.friend_service.list(user_id).await
+    }
+
+    pub async fn request_friend(&self, user_id: Uuid, request: FriendRequest) -> Result<()> {
+        self.friend_service
+            .request(user_id, &request.username)
+            .await
+    }
+
+    pub async fn accept_friend(&self, user_id: Uuid, requester_id: Uuid) -> Result<()> {
+        self.friend_service.accept(user_id, requester_id).await
+    }
+
+    pub async fn decline_friend(&self, user_id: Uuid, requester_id: Uuid) -> Result<()> {
+        self.friend_service.decline(user_id, requester_id).await
+    }
+
+    pub async fn invite_friend(&self, user_id: Uuid, friend_id: Uuid) -> Result<()> {
+        self.friend_service.invite(user_id, friend_id).await
+    }
+
+    pub fn room_import_key(&self) -> HandKey {
+        self.migration_service.import_key.clone()
+    }
+
+    pub async fn export_rooms(&self, key: HandKey) -> Result<Vec<RoomExport>> {
+        self.migration_service.export_rooms(&key).await
+    }
+
+    pub async fn import_rooms(&self, exports: Vec<RoomExport>) -> Result<usize> {
+        self.migration_service.import_rooms(exports).await
+    }
 }
@@ -0,0 +1,1322 @@
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, DefaultBodyLimit, Path, Query};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, patch, post};
+use axum::{Extension, Json, Router};
+use chrono::TimeDelta;
+use eyre::Result;
+use tracing::{debug, error, info, instrument, warn};
+use poker::Evaluator;
+use refinery::config::Config;
+use socketioxide::extract::Extension as SocketExtension;
+use socketioxide::extract::{Data, HttpExtension};
+use sqlx::types::Uuid;
+use sqlx::PgPool;
+use tokio::net::TcpListener;
+use tokio::signal::unix::{signal, SignalKind};
+use tower_http::services::ServeDir;
+use tower_http::trace::TraceLayer;
+
+use types::domain::{
+    ActionRequest, AdjustBalanceRequest, BanRequest, BroadcastRequest, ChatRequest, ClientEvent, ConnectionRefused,
+    CreateRoomRequest, DarkActionRequest, FeedbackQuery, FeedbackRequest, ForgotPasswordRequest,
+    FriendRequest,
+    HandStrengthHints,
+    IntegrityQuery,
+    JoinGameRequest,
+    LoginRequest, LogoutRequest, PreActionRequest, RefreshRequest, ResetPasswordRequest,
+    RoomQuery,
+    ServiceEvent, ShowCardsRequest, SignupRequest, SocketAuth, TopUpRequest, TransactionQuery,
+    UpdateProfileRequest, UserSearchQuery, VerifyEmailQuery,
+};
+use types::error::Error;
+use types::history::HistoryQuery;
+use types::protocol::{Feature, Handshake, UpgradeRequired};
+use types::crypto::HandKey;
+use types::room::{Rake, RoomExport, Scenario};
+use types::rules::DEFAULT_RECONNECT_GRACE;
+use types::season::RolloverRequest;
+use types::state::SharedGameState;
+use types::stats::LeaderboardQuery;
+use types::webhook::RegisterWebhookRequest;
+
+use crate::extensions::{
+    ExtractAdminFromToken, ExtractClaimsFromToken, ExtractModeratorFromToken, ExtractUserFromToken,
+};
+use crate::repository::admin::AdminRepository;
+use crate::repository::auth::AuthUserRepository;
+use crate::repository::feedback::FeedbackRepository;
+use crate::repository::friends::FriendRepository;
+use crate::repository::history::HandHistoryRepository;
+use crate::repository::integrity::IntegrityRepository;
+use crate::repository::invites::InviteRepository;
+use crate::repository::rake::RakeRepository;
+use crate::repository::rooms::{Instance, PgRoomStore, RoomInfoRepository, RoomRepository};
+use crate::repository::seasons::SeasonRepository;
+use crate::repository::stats::StatsRepository;
+use crate::repository::users::UserRepository;
+use crate::repository::webhooks::WebhookRepository;
+use crate::routes::Api;
+use crate::service::adapter::{self, PubSub, Socket};
+use crate::service::admin::AdminService;
+use crate::service::auth::{AuthService, RevokedTokens, SessionChecks};
+use crate::service::bots::BotService;
+use crate::service::broadcast::{RoomBroadcaster, DEFAULT_BROADCAST_WINDOW};
+use crate::service::chat::ChatRateLimiter;
+use crate::service::events::RoomEvents;
+use crate::service::feedback::FeedbackService;
+use crate::service::friends::{lobby_room, FriendService};
+use crate::service::game::{GameService, Settlement};
+use crate::service::integrity::IntegrityService;
+use crate::service::invites::InviteService;
+use crate::service::jwt::JwtKeys;
+use crate::service::leases::RoomLeases;
+use crate::service::lifecycle::RoomLifecycleManager;
+use crate::service::limits::{LoadShedder, ServerLimits, RETRY_AFTER_SECS};
+use crate::service::mail::{AccountMail, SmtpMailer};
+use crate::service::metrics::ServerMetrics;
+use crate::service::migration::MigrationService;
+use crate::service::outbox::{Outbox, OutboxRelay};
+use crate::service::seasons::{SeasonSchedule, SeasonService};
+use crate::service::turn_timer::TurnTimer;
+use crate::service::users::{LeaderboardCache, UserService};
+use crate::service::webhooks::WebhookService;
+
+refinery::embed_migrations!("migrations");
+
+// every room on a server in one request, well past the default body limit
+const MAX_ROOM_IMPORT_BYTES: usize = 64 * 1024 * 1024;
+
+/// What the server needs from its environment to start
+pub struct ServerConfig {
+    pub database_url: String,
+    // signs access tokens, every instance behind the same domain needs the same one
+    pub jwt_secret: String,
+}
+
+/// Migrates the database and serves the API and sockets on the listener until `shutdown`
+/// resolves, then saves the rooms to be refunded on the next start
+pub async fn serve(
+    listener: TcpListener,
+    config: ServerConfig,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<()> {
+    // run migrations
+    let mut refinery_config = Config::from_str(&config.database_url)?;
+    migrations::runner().run_async(&mut refinery_config).await?;
+    let pool = PgPool::connect(&config.database_url).await?;
+    let jwt_secret = config.jwt_secret;
+
+    // repositories
+    // with ROOM_STATE_SECRET set rooms are written to the database as they change and resumed
+    // after a crash, the secret seals the cards nobody has seen yet
+    let room_repository = match std::env::var("ROOM_STATE_SECRET") {
+        Ok(secret) if !secret.is_empty() => {
+            RoomRepository::with_store(Arc::new(PgRoomStore::new(pool.clone(), &secret)))
+        }
+        _ => RoomRepository::new(),
+    };
+    let room_info_repository = RoomInfoRepository::new(pool.clone(), Instance::from_env());
+    let user_repository = Arc::new(UserRepository::new(pool.clone()));
+    let auth_repository = AuthUserRepository::new(pool.clone());
+    let invite_repository = InviteRepository::new(pool.clone());
+    let admin_repository = AdminRepository::new(pool.clone());
+    let stats_repository = StatsRepository::new(pool.clone());
+    let hand_history_repository = HandHistoryRepository::new(pool.clone());
+    let webhook_repository = WebhookRepository::new(pool.clone());
+    let season_repository = SeasonRepository::new(pool.clone());
+    let rake_repository = RakeRepository::new(pool.clone());
+    let feedback_repository = FeedbackRepository::new(pool.clone());
+    let friend_repository = FriendRepository::new(pool.clone());
+    let integrity_repository = IntegrityRepository::new(pool.clone());
+
+    // zero out all player counts
+    room_info_repository.zero_all_player_counts().await?;
+
+    // setting up websocket
+    let (socket_layer, io) = adapter::new_layer(PubSub::from_env().await?);
+
+    // Register a handler for the default namespace
+    io.ns("/game", connection_handler).await?;
+    // room list changes are pushed here, to the same users who can list rooms
+    io.ns("/lobby", lobby_handler).await?;
+
+    // service
+    let invite_service = InviteService {
+        invite_repository,
+        room_info_repository: room_info_repository.clone(),
+    };
+    let events = RoomEvents::default();
+    let webhook_service = WebhookService {
+        webhook_repository,
+        room_info_repository: room_info_repository.clone(),
+        events: events.clone(),
+        http: reqwest::Client::new(),
+        hands_played: Default::default(),
+    };
+    tokio::spawn(webhook_service.clone().run());
+    let metrics = ServerMetrics::new(pool.clone());
+    tokio::spawn(metrics.clone().run(events.clone()));
+    let integrity_service = IntegrityService {
+        integrity_repository,
+        hand_history_repository: hand_history_repository.clone(),
+        checks: IntegrityService::default_checks(),
+        addresses: Default::default(),
+    };
+    tokio::spawn(integrity_service.clone().run(events.clone()));
+    // state changes within this window are sent to a room as one snapshot
+    let broadcast_window = std::env::var("BROADCAST_WINDOW_MS")
+        .ok()
+        .and_then(|ms| ms.parse().ok())
+        .map_or(DEFAULT_BROADCAST_WINDOW, Duration::from_millis);
+    // share of every pot the house keeps, off unless RAKE_PERCENT is set
+    let rake = Rake {
+        percent: read_env("RAKE_PERCENT").unwrap_or(0).min(100),
+        cap: read_env("RAKE_CAP").unwrap_or(u32::MAX),
+    };
+    // a dropped connection keeps its seat this long, 0 lets the player go right away
+    let reconnect_grace = read_env("RECONNECT_GRACE_SECS")
+        .map_or(DEFAULT_RECONNECT_GRACE, |secs| {
+            TimeDelta::seconds(secs.into())
+        });
+    // rooms deal the same cards on every run with DECK_SEED set, never set it in production
+    let deck_seed = std::env::var("DECK_SEED")
+        .ok()
+        .and_then(|seed| seed.parse().ok());
+    if let Some(seed) = deck_seed {
+        warn!("Dealing every room from seed {}", seed);
+    }
+    let season_service = SeasonService {
+        season_repository,
+        schedule: SeasonSchedule::from_env(),
+        io: io.clone(),
+    };
+    tokio::spawn(season_service.clone().run());
+    let game_service = GameService {
+        evaluator: Evaluator::new(),
+        room_repository: room_repository.clone(),
+        room_info_repository,
+        user_repository: user_repository.clone(),
+        stats_repository: stats_repository.clone(),
+        hand_history_repository,
+        rake,
+        rake_repository: rake_repository.clone(),
+        broadcaster: RoomBroadcaster::new(io.clone(), broadcast_window),
+        chat_limiter: ChatRateLimiter::default(),
+        events,
+        outbox: Outbox::default(),
+        settlement: Settlement::from_env(),
+        reconnect_grace,
+        deck_seed,
+        io,
+    };
+    game_service.init_rooms().await?;
+    tokio::spawn(
+        RoomLeases {
+            game_service: game_service.clone(),
+        }
+        .run(),
+    );
+    tokio::spawn(
+        RoomLifecycleManager {
+            game_service: game_service.clone(),
+        }
+        .run(),
+    );
+    tokio::spawn(
+        TurnTimer {
+            game_service: game_service.clone(),
+        }
+        .run(),
+    );
+    tokio::spawn(
+        OutboxRelay {
+            game_service: game_service.clone(),
+        }
+        .run(),
+    );
+    tokio::spawn(BotService::new(game_service.clone()).run());
+
+    // with SMTP_HOST set new accounts have to open an emailed link before they can log in,
+    // and forgotten passwords can be reset
+    let mail = match SmtpMailer::from_env()? {
+        Some(mailer) => Some(AccountMail {
+            mailer: Arc::new(mailer),
+            public_url: std::env::var("PUBLIC_URL")
+                .map_err(|_| eyre::eyre!("PUBLIC_URL must be set to send verification links"))?,
+        }),
+        None => None,
+    };
+
+    // rooms another server exports to this one are sealed to this key
+    let (import_key, import_secret) = HandKey::generate();
+
+    let auth_service = AuthService {
+        auth_repository,
+        jwt: JwtKeys::new(jwt_secret.as_bytes()),
+        session_checks: SessionChecks::default(),
+        revoked_tokens: RevokedTokens::default(),
+        mail,
+    };
+    tokio::spawn(auth_service.clone().run());
+
+    // API
+    let api = Api {
+        game_service: game_service.clone(),
+        auth_service,
+        user_service: UserService {
+            user_repository: user_repository.clone(),
+            stats_repository,
+            leaderboards: LeaderboardCache::default(),
+        },
+        invite_service,
+        admin_service: AdminService {
+            admin_repository,
+            user_repository: user_repository.clone(),
+            rake_repository,
+            io: game_service.io.clone(),
+        },
+        webhook_service,
+        load_shedder: LoadShedder::new(ServerLimits::from_env()),
+        season_service,
+        feedback_service: FeedbackService {
+            feedback_repository,
+        },
+        friend_service: FriendService {
+            friend_repository,
+            user_repository: user_repository.clone(),
+            room_info_repository: game_service.room_info_repository.clone(),
+            io: game_service.io.clone(),
+        },
+        migration_service: MigrationService {
+            game_service: game_service.clone(),
+            import_key,
+            import_secret,
+        },
+        integrity_service,
+        metrics,
+    };
+
+    let static_files = ServeDir::new("dist");
+
+    // routes
+    let router = Router::new()
+        .route("/games", get(get_room_states))
+        .route("/metrics/broadcasts", get(get_broadcast_metrics))
+        .route("/metrics/load", get(get_load_metrics))
+        .route("/metrics", get(get_metrics))
+        .route("/signup", post(signup))
+        .route("/login", post(login))
+        .route("/verify", get(verify_email))
+        .route("/password/forgot", post(forgot_password))
+        .route("/password/reset", post(reset_password))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
+        .route("/sessions", delete(logout_everywhere))
+        .route("/profile", patch(update_profile))
+        .route("/profile", get(get_profile))
+        .route("/profile/stats", get(get_profile_stats))
+        .route("/balance/topup", post(top_up))
+        .route("/balance/transactions", get(get_transactions))
+        .route("/leaderboard", get(get_leaderboard))
+        .route("/feedback", post(submit_feedback))
+        .route("/friends", get(list_friends))
+        .route("/friends/requests", post(request_friend))
+        .route("/friends/requests/{user_id}/accept", post(accept_friend))
+        .route("/friends/requests/{user_id}/decline", post(decline_friend))
+        .route("/friends/{user_id}/invite", post(invite_friend))
+        .route("/rooms", get(get_rooms))
+        .route("/rooms", post(create_room))
+        .route("/rooms/{room_id}", get(get_room_details))
+        .route("/rooms/{room_id}/invite", post(create_invite))
+        .route("/rooms/{room_id}/history", get(get_hand_history))
+        .route("/rooms/{room_id}/history/{hand_id}", get(get_hand))
+        .route("/rooms/{room_id}/webhooks", post(register_room_webhook))
+        .route("/invites/{code}", get(get_invited_room))
+        .route("/admin/users", get(search_users))
+        .route("/admin/users/{user_id}/adjust-balance", post(adjust_balance))
+        .route("/admin/webhooks", post(register_server_webhook))
+        .route("/admin/rooms/{room_id}/scenario", post(load_scenario))
+        .route("/admin/rooms/{room_id}/scenario", delete(clear_scenario))
+        .route("/admin/seasons/rollover", post(end_season))
+        .route("/admin/rake", get(rake_report))
+        .route("/admin/integrity", get(integrity_report))
+        .route("/admin/overview", get(admin_overview))
+        .route("/admin/broadcast", post(broadcast))
+        .route("/admin/rooms/{room_id}", delete(close_room))
+        .route("/admin/users/{user_id}/ban", post(ban_user))
+        .route("/admin/feedback", get(list_feedback))
+        .route("/admin/rooms/import-key", get(get_room_import_key))
+        .route("/admin/rooms/export", post(export_rooms))
+        .route(
+            "/admin/rooms/import",
+            post(import_rooms).layer(DefaultBodyLimit::max(MAX_ROOM_IMPORT_BYTES)),
+        )
+        .route("/resume", get(resume_pdf))
+        .fallback_service(static_files)
+        .layer(socket_layer)
+        .layer(TraceLayer::new_for_http())
+        .layer(Extension(api));
+
+    // socket connections are told apart by address in the integrity report
+    let app = router.into_make_service_with_connect_info::<SocketAddr>();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            shutdown.await;
+            info!("Shutting down, saving room snapshots");
+            if let Err(e) = game_service.snapshot_rooms().await {
+                error!("Failed to save room snapshots: {:?}", e);
+            }
+            // other replicas take the rooms on from here
+            if let Err(e) = game_service.room_info_repository.release_leases().await {
+                error!("Failed to release room leases: {:?}", e);
+            }
+        })
+        .await?;
+    Ok(())
+}
+
+/// Resolves on Ctrl+C or SIGTERM
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl+C: {:?}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    let terminate = async {
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {:?}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+fn read_env(key: &str) -> Option<u32> {
+    std::env::var(key).ok().and_then(|value| value.parse().ok())
+}
+
+async fn resume_pdf() -> impl IntoResponse {
+    let file_path = "static/resume.pdf";
+    match tokio::fs::read(file_path).await {
+        Ok(contents) => {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, "application/pdf".parse().unwrap());
+            headers.insert(
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"Yew-Jung-Sien-Resume.pdf\""
+                    .parse()
+                    .unwrap(),
+            );
+            (headers, contents)
+                .into_response()
+        }
+        Err(e) => {
+            error!("Failed to read resume PDF: {:?}", e);
+            StatusCode::NOT_FOUND.into_response()
+        }
+    }
+}
+
+async fn signup(
+    Extension(api): Extension<Api>,
+    Json(payload): Json<SignupRequest>,
+) -> impl IntoResponse {
+    match api.signup(payload).await {
+        Ok(response) => (StatusCode::CREATED, Json(response)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+// opened from the emailed link in a browser
+async fn verify_email(
+    Extension(api): Extension<Api>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> impl IntoResponse {
+    match api.verify_email(query.token).await {
+        Ok(()) => (
+            StatusCode::OK,
+            "Your email is verified, you can log in from the app now.",
+        )
+            .into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+// answers the same whether or not the email has an account
+async fn forgot_password(
+    Extension(api): Extension<Api>,
+    Json(payload): Json<ForgotPasswordRequest>,
+) -> impl IntoResponse {
+    match api.forgot_password(payload).await {
+        Ok(()) => StatusCode::ACCEPTED.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn reset_password(
+    Extension(api): Extension<Api>,
+    Json(payload): Json<ResetPasswordRequest>,
+) -> impl IntoResponse {
+    match api.reset_password(payload).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn login(
+    Extension(api): Extension<Api>,
+    Json(payload): Json<LoginRequest>,
+) -> impl IntoResponse {
+    match api.login(payload).await {
+        Ok(tokens) => (StatusCode::OK, Json(tokens)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn refresh(
+    Extension(api): Extension<Api>,
+    Json(payload): Json<RefreshRequest>,
+) -> impl IntoResponse {
+    match api.refresh(payload).await {
+        Ok(tokens) => (StatusCode::OK, Json(tokens)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn logout(
+    Extension(api): Extension<Api>,
+    ExtractClaimsFromToken(claims): ExtractClaimsFromToken,
+    Json(payload): Json<LogoutRequest>,
+) -> impl IntoResponse {
+    match api.logout(claims, payload).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn logout_everywhere(
+    Extension(api): Extension<Api>,
+    ExtractClaimsFromToken(claims): ExtractClaimsFromToken,
+) -> impl IntoResponse {
+    match api.logout_everywhere(claims).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn update_profile(
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+    Extension(api): Extension<Api>,
+    Json(payload): Json<UpdateProfileRequest>,
+) -> impl IntoResponse {
+    match api.update_profile(user_id, payload).await {
+        Ok(user) => (StatusCode::OK, Json(user)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn get_profile(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+) -> impl IntoResponse {
+    match api.get_profile(user_id).await {
+        Ok(Some(user)) => (StatusCode::OK, Json(user)).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn get_profile_stats(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+) -> impl IntoResponse {
+    match api.get_profile_stats(user_id).await {
+        Ok(stats) => (StatusCode::OK, Json(stats)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn top_up(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+) -> impl IntoResponse {
+    match api.top_up(user_id).await {
+        Ok(entry) => (StatusCode::OK, Json(entry)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn get_transactions(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+    Query(query): Query<TransactionQuery>,
+) -> impl IntoResponse {
+    match api.get_transactions(user_id, query).await {
+        Ok(page) => (StatusCode::OK, Json(page)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn get_leaderboard(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(_): ExtractUserFromToken,
+    Query(query): Query<LeaderboardQuery>,
+) -> impl IntoResponse {
+    match api.get_leaderboard(query.period).await {
+        Ok(leaderboard) => (StatusCode::OK, Json(leaderboard)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn get_room_states(Extension(api): Extension<Api>) -> impl IntoResponse {
+    let rooms: Vec<SharedGameState> = api
+        .game_service
+        .room_repository
+        .rooms
+        .iter()
+        .map(|room| SharedGameState::from_room(room.deref().clone(), true))
+        .collect();
+    (StatusCode::OK, Json(rooms)).into_response()
+}
+
+async fn get_broadcast_metrics(Extension(api): Extension<Api>) -> impl IntoResponse {
+    (StatusCode::OK, Json(api.game_service.broadcaster.metrics())).into_response()
+}
+
+async fn get_load_metrics(Extension(api): Extension<Api>) -> impl IntoResponse {
+    (StatusCode::OK, Json(api.load_shedder.metrics())).into_response()
+}
+
+async fn get_metrics(Extension(api): Extension<Api>) -> impl IntoResponse {
+    let content_type = [(header::CONTENT_TYPE, "text/plain; version=0.0.4")];
+    (StatusCode::OK, content_type, api.render_metrics()).into_response()
+}
+
+async fn get_rooms(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(_user_id): ExtractUserFromToken,
+    Query(query): Query<RoomQuery>,
+) -> impl IntoResponse {
+    match api.game_service.get_rooms(query.currency).await {
+        Ok(rooms) => (StatusCode::OK, Json(rooms)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn create_room(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+    Json(payload): Json<CreateRoomRequest>,
+) -> impl IntoResponse {
+    match api.create_room(user_id, payload).await {
+        Ok(room) => (StatusCode::CREATED, Json(room)).into_response(),
+        Err(e) => shed_into_response(e),
+    }
+}
+
+async fn get_room_details(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(_user_id): ExtractUserFromToken,
+    Path(room_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match api.game_service.get_room_details(room_id).await {
+        Ok(details) => (StatusCode::OK, Json(details)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn create_invite(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+    Path(room_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match api.create_invite(user_id, room_id).await {
+        Ok(invite) => (StatusCode::CREATED, Json(invite)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn get_hand_history(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(_user_id): ExtractUserFromToken,
+    Path(room_id): Path<Uuid>,
+    Query(query): Query<HistoryQuery>,
+) -> impl IntoResponse {
+    match api.game_service.get_hand_history(room_id, query).await {
+        Ok(page) => (StatusCode::OK, Json(page)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn get_hand(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(_user_id): ExtractUserFromToken,
+    Path((room_id, hand_id)): Path<(Uuid, Uuid)>,
+) -> impl IntoResponse {
+    match api.game_service.get_hand(room_id, hand_id).await {
+        Ok(hand) => (StatusCode::OK, Json(hand)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn get_invited_room(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(_user_id): ExtractUserFromToken,
+    Path(code): Path<String>,
+) -> impl IntoResponse {
+    match api.get_invited_room(&code).await {
+        Ok(room) => (StatusCode::OK, Json(room)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn search_users(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(_admin_id): ExtractAdminFromToken,
+    Query(query): Query<UserSearchQuery>,
+) -> impl IntoResponse {
+    match api.search_users(query).await {
+        Ok(users) => (StatusCode::OK, Json(users)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn register_room_webhook(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+    Path(room_id): Path<Uuid>,
+    Json(payload): Json<RegisterWebhookRequest>,
+) -> impl IntoResponse {
+    match api.register_room_webhook(user_id, room_id, payload).await {
+        Ok(webhook) => (StatusCode::CREATED, Json(webhook)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn register_server_webhook(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(admin_id): ExtractAdminFromToken,
+    Json(payload): Json<RegisterWebhookRequest>,
+) -> impl IntoResponse {
+    match api.register_server_webhook(admin_id, payload).await {
+        Ok(webhook) => (StatusCode::CREATED, Json(webhook)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn adjust_balance(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(admin_id): ExtractAdminFromToken,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<AdjustBalanceRequest>,
+) -> impl IntoResponse {
+    match api.adjust_balance(admin_id, user_id, payload).await {
+        Ok(entry) => (StatusCode::CREATED, Json(entry)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn broadcast(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(admin_id): ExtractAdminFromToken,
+    Json(payload): Json<BroadcastRequest>,
+) -> impl IntoResponse {
+    match api.broadcast(admin_id, payload).await {
+        Ok(announcement) => (StatusCode::OK, Json(announcement)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn load_scenario(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(_admin_id): ExtractAdminFromToken,
+    Path(room_id): Path<Uuid>,
+    Json(payload): Json<Scenario>,
+) -> impl IntoResponse {
+    match api.set_scenario(room_id, Some(payload)).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn clear_scenario(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(_admin_id): ExtractAdminFromToken,
+    Path(room_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match api.set_scenario(room_id, None).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn rake_report(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(_admin_id): ExtractAdminFromToken,
+) -> impl IntoResponse {
+    match api.rake_report().await {
+        Ok(days) => (StatusCode::OK, Json(days)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn integrity_report(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(_admin_id): ExtractAdminFromToken,
+    Query(query): Query<IntegrityQuery>,
+) -> impl IntoResponse {
+    match api.integrity_report(query).await {
+        Ok(pairs) => (StatusCode::OK, Json(pairs)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn end_season(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(admin_id): ExtractAdminFromToken,
+    Json(payload): Json<RolloverRequest>,
+) -> impl IntoResponse {
+    info!("Admin {} is ending the season", admin_id);
+    match api.end_season(payload).await {
+        Ok(summary) => (StatusCode::CREATED, Json(summary)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn admin_overview(
+    Extension(api): Extension<Api>,
+    ExtractModeratorFromToken(_moderator_id): ExtractModeratorFromToken,
+) -> impl IntoResponse {
+    match api.admin_overview().await {
+        Ok(overview) => (StatusCode::OK, Json(overview)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn close_room(
+    Extension(api): Extension<Api>,
+    ExtractModeratorFromToken(moderator_id): ExtractModeratorFromToken,
+    Path(room_id): Path<Uuid>,
+) -> impl IntoResponse {
+    info!("Moderator {} is closing room {}", moderator_id, room_id);
+    match api.close_room(room_id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn ban_user(
+    Extension(api): Extension<Api>,
+    ExtractModeratorFromToken(moderator_id): ExtractModeratorFromToken,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<BanRequest>,
+) -> impl IntoResponse {
+    match api.ban_user(moderator_id, user_id, payload).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn submit_feedback(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+    Json(payload): Json<FeedbackRequest>,
+) -> impl IntoResponse {
+    match api.submit_feedback(user_id, payload).await {
+        Ok(id) => (StatusCode::CREATED, Json(id)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn list_friends(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+) -> impl IntoResponse {
+    match api.list_friends(user_id).await {
+        Ok(friends) => (StatusCode::OK, Json(friends)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn request_friend(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+    Json(payload): Json<FriendRequest>,
+) -> impl IntoResponse {
+    match api.request_friend(user_id, payload).await {
+        Ok(()) => StatusCode::CREATED.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn accept_friend(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+    Path(requester_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match api.accept_friend(user_id, requester_id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn decline_friend(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+    Path(requester_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match api.decline_friend(user_id, requester_id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn invite_friend(
+    Extension(api): Extension<Api>,
+    ExtractUserFromToken(user_id): ExtractUserFromToken,
+    Path(friend_id): Path<Uuid>,
+) -> impl IntoResponse {
+    match api.invite_friend(user_id, friend_id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn list_feedback(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(_admin_id): ExtractAdminFromToken,
+    Query(query): Query<FeedbackQuery>,
+) -> impl IntoResponse {
+    match api.list_feedback(query).await {
+        Ok(feedback) => (StatusCode::OK, Json(feedback)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn get_room_import_key(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(_admin_id): ExtractAdminFromToken,
+) -> impl IntoResponse {
+    (StatusCode::OK, Json(api.room_import_key()))
+}
+
+async fn export_rooms(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(admin_id): ExtractAdminFromToken,
+    Json(key): Json<HandKey>,
+) -> impl IntoResponse {
+    info!("Admin {} is exporting every room", admin_id);
+    match api.export_rooms(key).await {
+        Ok(exports) => (StatusCode::OK, Json(exports)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+async fn import_rooms(
+    Extension(api): Extension<Api>,
+    ExtractAdminFromToken(admin_id): ExtractAdminFromToken,
+    Json(exports): Json<Vec<RoomExport>>,
+) -> impl IntoResponse {
+    info!("Admin {} is importing {} rooms", admin_id, exports.len());
+    match api.import_rooms(exports).await {
+        Ok(imported) => (StatusCode::OK, Json(imported)).into_response(),
+        Err(e) => report_into_response(e).into_response(),
+    }
+}
+
+// a revoked or expired session is told so and disconnected instead of acting
+async fn has_live_session(s: &Socket, user_id: Uuid, api: &Api) -> bool {
+    match api.is_session_live(user_id, s.id).await {
+        Ok(true) => true,
+        Ok(false) => {
+            debug!("Session of user {} ended", user_id);
+            let (_, message) = Error::SessionEnded.into_response_tuple();
+            let _ = s.emit(ServiceEvent::ServiceError, &message);
+            if let Err(e) = api.end_session(user_id, s.id).await {
+                error!("Failed to end session of user {}: {:?}", user_id, e);
+            }
+            false
+        }
+        // the tables play on from memory, a database hiccup shouldn't kick everyone
+        Err(e) => {
+            error!("Failed to check session of user {}: {:?}", user_id, e);
+            true
+        }
+    }
+}
+
+#[instrument(skip_all, fields(%user_id, sid = %s.id))]
+async fn join_game(
+    s: Socket,
+    SocketExtension(user_id): SocketExtension<Uuid>,
+    Data(request): Data<JoinGameRequest>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    if !has_live_session(&s, user_id, &api).await {
+        return;
+    }
+    let room_id = request.room_id;
+    match api.join_game(user_id, request, s.id).await {
+        Ok(room) => {
+            debug!("User {} joined room {}", user_id, room.id);
+        }
+        Err(e) => {
+            s.leave(room_id.to_string());
+            let (_, message) = report_into_response(e);
+            let _ = s.emit(ServiceEvent::ServiceError, &message);
+        }
+    }
+}
+
+#[instrument(skip_all, fields(%user_id, sid = %s.id))]
+async fn take_action(
+    s: Socket,
+    SocketExtension(user_id): SocketExtension<Uuid>,
+    Data(request): Data<ActionRequest>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    if !has_live_session(&s, user_id, &api).await {
+        return;
+    }
+    let action = request.action;
+    let started = Instant::now();
+    let result = api.take_action(user_id, request).await;
+    api.metrics.observe_action(started.elapsed());
+    match result {
+        Ok(room) => {
+            debug!(
+                "User {} took action, {:?} in room {}",
+                user_id, action, room.id
+            );
+        }
+        Err(e) => {
+            let (_, message) = report_into_response(e);
+            let _ = s.emit(ServiceEvent::ServiceError, &message);
+        }
+    }
+}
+
+#[instrument(skip_all, fields(%user_id, sid = %s.id))]
+async fn dark_action(
+    s: Socket,
+    SocketExtension(user_id): SocketExtension<Uuid>,
+    Data(request): Data<DarkActionRequest>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    if !has_live_session(&s, user_id, &api).await {
+        return;
+    }
+    let action = request.action;
+    match api.declare_dark_action(user_id, request).await {
+        Ok(room) => debug!(
+            "User {} declared {:?} dark in room {}",
+            user_id, action, room.id
+        ),
+        Err(e) => {
+            let (_, message) = report_into_response(e);
+            let _ = s.emit(ServiceEvent::ServiceError, &message);
+        }
+    }
+}
+
+#[instrument(skip_all, fields(%user_id, sid = %s.id))]
+async fn pre_action(
+    s: Socket,
+    SocketExtension(user_id): SocketExtension<Uuid>,
+    Data(request): Data<PreActionRequest>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    if !has_live_session(&s, user_id, &api).await {
+        return;
+    }
+    let pre_action = request.pre_action;
+    match api.set_pre_action(user_id, request).await {
+        Ok(room) => debug!(
+            "User {} set pre-action {:?} in room {}",
+            user_id, pre_action, room.id
+        ),
+        Err(e) => {
+            let (_, message) = report_into_response(e);
+            let _ = s.emit(ServiceEvent::ServiceError, &message);
+        }
+    }
+}
+
+#[instrument(skip_all, fields(%user_id, sid = %s.id))]
+async fn ready(
+    s: Socket,
+    SocketExtension(user_id): SocketExtension<Uuid>,
+    Data(room_id): Data<Uuid>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    if !has_live_session(&s, user_id, &api).await {
+        return;
+    }
+    if let Err(e) = api.ready(user_id, room_id).await {
+        let (_, message) = report_into_response(e);
+        let _ = s.emit(ServiceEvent::ServiceError, &message);
+    }
+}
+
+#[instrument(skip_all, fields(%user_id, sid = %s.id))]
+async fn show_cards(
+    s: Socket,
+    SocketExtension(user_id): SocketExtension<Uuid>,
+    Data(request): Data<ShowCardsRequest>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    if !has_live_session(&s, user_id, &api).await {
+        return;
+    }
+    if let Err(e) = api.show_cards(user_id, request).await {
+        let (_, message) = report_into_response(e);
+        let _ = s.emit(ServiceEvent::ServiceError, &message);
+    }
+}
+
+#[instrument(skip_all, fields(%user_id, sid = %s.id))]
+async fn chat(
+    s: Socket,
+    SocketExtension(user_id): SocketExtension<Uuid>,
+    Data(request): Data<ChatRequest>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    if !has_live_session(&s, user_id, &api).await {
+        return;
+    }
+    if let Err(e) = api.send_chat(user_id, request).await {
+        let (_, message) = report_into_response(e);
+        let _ = s.emit(ServiceEvent::ServiceError, &message);
+    }
+}
+
+#[instrument(skip_all, fields(%user_id, sid = %s.id))]
+async fn sit_out(
+    s: Socket,
+    SocketExtension(user_id): SocketExtension<Uuid>,
+    Data(room_id): Data<Uuid>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    if !has_live_session(&s, user_id, &api).await {
+        return;
+    }
+    match api.sit_out(user_id, room_id).await {
+        Ok(room) => debug!("User {} sat out in room {}", user_id, room.id),
+        Err(e) => {
+            let (_, message) = report_into_response(e);
+            let _ = s.emit(ServiceEvent::ServiceError, &message);
+        }
+    }
+}
+
+#[instrument(skip_all, fields(%user_id, sid = %s.id))]
+async fn top_up_at_table(
+    s: Socket,
+    SocketExtension(user_id): SocketExtension<Uuid>,
+    Data(request): Data<TopUpRequest>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    if !has_live_session(&s, user_id, &api).await {
+        return;
+    }
+    if let Err(e) = api.top_up_at_table(user_id, request).await {
+        let (_, message) = report_into_response(e);
+        let _ = s.emit(ServiceEvent::ServiceError, &message);
+    }
+}
+
+#[instrument(skip_all, fields(%user_id, sid = %s.id))]
+async fn sit_in(
+    s: Socket,
+    SocketExtension(user_id): SocketExtension<Uuid>,
+    Data(room_id): Data<Uuid>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    if !has_live_session(&s, user_id, &api).await {
+        return;
+    }
+    match api.sit_in(user_id, room_id).await {
+        Ok(room) => debug!("User {} sat in in room {}", user_id, room.id),
+        Err(e) => {
+            let (_, message) = report_into_response(e);
+            let _ = s.emit(ServiceEvent::ServiceError, &message);
+        }
+    }
+}
+
+#[instrument(skip_all, fields(%user_id, sid = %s.id))]
+async fn leave_game(
+    s: Socket,
+    SocketExtension(user_id): SocketExtension<Uuid>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    api.game_service.leave_waiting_lists(user_id).await;
+    match api.game_service.leave_player(user_id, s.id).await {
+        Ok(_) => debug!("User {} left socket connection", user_id),
+        Err(e) => {
+            let (_, message) = report_into_response(e);
+            let _ = s.emit(ServiceEvent::ServiceError, &message);
+        }
+    }
+}
+#[instrument(skip_all, fields(%user_id, sid = %s.id))]
+async fn handle_disconnect(
+    s: Socket,
+    SocketExtension(user_id): SocketExtension<Uuid>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    debug!("User {} disconnected", user_id);
+    api.load_shedder.release_socket();
+    api.auth_service.forget_session(s.id);
+    if let Err(e) = api.game_service.disconnect_player(user_id, s.id).await {
+        error!(
+            "Error occurred when disconnecting user {}: {:?}",
+            user_id, e
+        );
+    }
+}
+
+#[instrument(skip_all, fields(sid = %s.id))]
+async fn lobby_handler(
+    s: Socket,
+    Data(auth): Data<SocketAuth>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    match api.verify_access_token(&auth.token) {
+        Ok(claims) => {
+            debug!("User {} watching the lobby", claims.sub);
+            // friends' invites are pushed to this room
+            s.join(lobby_room(claims.sub));
+        }
+        Err(e) => {
+            debug!("Refused lobby socket: {:?}", e);
+            let _ = s.disconnect();
+        }
+    }
+}
+
+#[instrument(skip_all, fields(sid = %s.id))]
+async fn connection_handler(
+    s: Socket,
+    Data(auth): Data<SocketAuth>,
+    HttpExtension(api): HttpExtension<Api>,
+) {
+    if let Some(upgrade) = UpgradeRequired::check(auth.protocol_version) {
+        debug!("Refused protocol version {}", auth.protocol_version);
+        let _ = s.emit(ServiceEvent::UpgradeRequired, &upgrade);
+        let _ = s.disconnect();
+        return;
+    }
+    if let Err(e) = api.load_shedder.acquire_socket() {
+        let (_, reason) = report_into_response(e);
+        let refused = ConnectionRefused {
+            reason,
+            retry_after_secs: RETRY_AFTER_SECS,
+        };
+        let _ = s.emit(ServiceEvent::ConnectionRefused, &refused);
+        let _ = s.disconnect();
+        return;
+    }
+    // stored before anything can be dealt to this socket
+    if let Some(hand_key) = auth.hand_key {
+        s.extensions.insert(hand_key);
+    }
+    if auth.hand_strength {
+        s.extensions.insert(HandStrengthHints);
+    }
+    let user_id = match api.connect_player_by_token(&auth.token, s.id).await {
+        Ok(Some(auth_user)) => auth_user.id,
+        Ok(None) => {
+            error!("Failed to get user from token");
+            api.load_shedder.release_socket();
+            return;
+        }
+        Err(e) => {
+            error!("Failed to get user from token: {:?}", e);
+            api.load_shedder.release_socket();
+            return;
+        }
+    };
+    debug!("User {} connected", user_id);
+    s.extensions.insert(user_id);
+    if let Some(address) = client_address(&s) {
+        api.remember_address(user_id, address);
+    }
+    s.on(ClientEvent::Join, join_game);
+    s.on(ClientEvent::Action, take_action);
+    s.on(ClientEvent::Leave, leave_game);
+    s.on(ClientEvent::Ready, ready);
+    s.on(ClientEvent::SitOut, sit_out);
+    s.on(ClientEvent::SitIn, sit_in);
+    // events of features the client did not ask for are not listened to
+    let handshake = Handshake::negotiate(&auth.features);
+    if handshake.supports(Feature::Chat) {
+        s.on(ClientEvent::Chat, chat);
+    }
+    if handshake.supports(Feature::DarkActions) {
+        s.on(ClientEvent::DarkAction, dark_action);
+    }
+    if handshake.supports(Feature::ShowCards) {
+        s.on(ClientEvent::ShowCards, show_cards);
+    }
+    if handshake.supports(Feature::TopUp) {
+        s.on(ClientEvent::TopUp, top_up_at_table);
+    }
+    if handshake.supports(Feature::PreActions) {
+        s.on(ClientEvent::PreAction, pre_action);
+    }
+    s.on_disconnect(handle_disconnect);
+    let _ = s.emit(ServiceEvent::Handshake, &handshake);
+}
+
+// the first address a proxy in front of the server forwarded for, or the peer's own
+fn client_address(s: &Socket) -> Option<IpAddr> {
+    let parts = s.req_parts();
+    let forwarded = parts
+        .headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .and_then(|address| address.trim().parse().ok());
+    forwarded.or_else(|| {
+        parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(peer)| peer.ip())
+    })
+}
+
+// like report_into_response, but tells clients when to come back if the server is full
+fn shed_into_response(e: eyre::Report) -> Response {
+    let (status, message) = report_into_response(e);
+    if status == StatusCode::SERVICE_UNAVAILABLE {
+        let retry_after = [(header::RETRY_AFTER, RETRY_AFTER_SECS.to_string())];
+        return (status, retry_after, message).into_response();
+    }
+    (status, message).into_response()
+}
+
+fn report_into_response(e: eyre::Report) -> (StatusCode, String) {
+    error!("Error occurred: {:?}", e);
+    match e.downcast::<Error>() {
+        Ok(error) => error.into_response_tuple(),
+        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "".to_string()),
+    }
+}
@@ -0,0 +1,78 @@
+use eyre::Result;
+use socketioxide::adapter::Emitter;
+use socketioxide::extract::SocketRef;
+use socketioxide::layer::SocketIoLayer;
+use socketioxide::SocketIo;
+use socketioxide_redis::drivers::redis::{RedisDriver, RedisError};
+use socketioxide_redis::drivers::{ChanItem, Driver, MessageStream};
+use socketioxide_redis::{CustomRedisAdapter, RedisAdapterConfig, RedisAdapterCtr};
+
+/// Broadcasts reach the sockets of every instance sharing the Redis server
+pub type Adapter = CustomRedisAdapter<Emitter, PubSub>;
+pub type Io = SocketIo<Adapter>;
+pub type Socket = SocketRef<Adapter>;
+
+/// Carries broadcasts between instances, or keeps them on this one without `REDIS_URL`
+#[derive(Clone)]
+pub enum PubSub {
+    Redis(RedisDriver),
+    Local,
+}
+
+impl PubSub {
+    /// Connects to `REDIS_URL` when it is set, it needs Redis 7 or newer for RESP3
+    pub async fn from_env() -> Result<Self> {
+        match std::env::var("REDIS_URL") {
+            Ok(url) if !url.is_empty() => {
+                let client = redis::Client::open(url)?;
+                Ok(PubSub::Redis(RedisDriver::new(&client).await?))
+            }
+            _ => Ok(PubSub::Local),
+        }
+    }
+}
+
+impl Driver for PubSub {
+    type Error = RedisError;
+
+    async fn publish(&self, chan: String, val: Vec<u8>) -> Result<(), Self::Error> {
+        match self {
+            PubSub::Redis(driver) => driver.publish(chan, val).await,
+            PubSub::Local => Ok(()),
+        }
+    }
+
+    async fn subscribe(
+        &self,
+        chan: String,
+        size: usize,
+    ) -> Result<MessageStream<ChanItem>, Self::Error> {
+        match self {
+            PubSub::Redis(driver) => driver.subscribe(chan, size).await,
+            PubSub::Local => Ok(MessageStream::new_empty()),
+        }
+    }
+
+    async fn unsubscribe(&self, chan: String) -> Result<(), Self::Error> {
+        match self {
+            PubSub::Redis(driver) => driver.unsubscribe(chan).await,
+            PubSub::Local => Ok(()),
+        }
+    }
+
+    // counts this instance too
+    async fn num_serv(&self, chan: &str) -> Result<u16, Self::Error> {
+        match self {
+            PubSub::Redis(driver) => driver.num_serv(chan).await,
+            PubSub::Local => Ok(1),
+        }
+    }
+}
+
+/// The socket.io layer, namespaces are only served once they are registered and awaited
+pub fn new_layer(pubsub: PubSub) -> (SocketIoLayer<Adapter>, Io) {
+    let adapter = RedisAdapterCtr::new_with_driver(pubsub, RedisAdapterConfig::default());
+    SocketIo::builder()
+        .with_adapter::<Adapter>(adapter)
+        .build_layer()
+}
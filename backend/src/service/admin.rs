@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use eyre::{ensure, ContextCompat, Result};
+use log::{error, info};
+use sqlx::types::Uuid;
+use validator::Validate;
+
+use types::domain::{
+    AdjustBalanceRequest, AdminUserView, Announcement, BroadcastRequest, LedgerEntry, RakeDay,
+    ServiceEvent, UserSearchQuery,
+};
+use types::error::Error;
+
+use crate::repository::admin::AdminRepository;
+use crate::repository::rake::RakeRepository;
+use crate::repository::users::UserRepository;
+use crate::service::adapter::Io;
+
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+
+#[derive(Clone)]
+pub struct AdminService {
+    pub admin_repository: AdminRepository,
+    pub user_repository: Arc<UserRepository>,
+    pub rake_repository: RakeRepository,
+    pub io: Io,
+}
+
+impl AdminService {
+    pub async fn adjust_balance(
+        &self,
+        admin_id: Uuid,
+        user_id: Uuid,
+        request: AdjustBalanceRequest,
+    ) -> Result<LedgerEntry> {
+        ensure!(request.amount != 0, Error::InvalidAdjustment);
+        ensure!(request.reason.is_manual(), Error::NotAManualAdjustment);
+        ensure!(
+            self.user_repository.get(user_id).await?.is_some(),
+            Error::UserNotFound
+        );
+        let entry = self
+            .admin_repository
+            .adjust_balance(
+                user_id,
+                admin_id,
+                request.amount,
+                request.reason,
+                request.note,
+                request.currency,
+            )
+            .await?
+            .wrap_err(Error::InsufficientBalance)?;
+        info!(
+            "Admin {} adjusted {} balance of user {} by {} ({})",
+            admin_id,
+            entry.currency.as_ref(),
+            user_id,
+            entry.amount,
+            entry.reason.as_ref()
+        );
+        Ok(entry)
+    }
+
+    pub async fn rake_report(&self) -> Result<Vec<RakeDay>> {
+        self.rake_repository.daily_totals().await
+    }
+
+    pub async fn user_counts(&self) -> Result<(i64, i64)> {
+        self.admin_repository.user_counts().await
+    }
+
+    pub async fn search_users(&self, query: UserSearchQuery) -> Result<Vec<AdminUserView>> {
+        let q = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty());
+        self.admin_repository
+            .search_users(q, query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+            .await
+    }
+
+    pub async fn broadcast(
+        &self,
+        admin_id: Uuid,
+        request: BroadcastRequest,
+    ) -> Result<Announcement> {
+        // a message of only blanks is as empty as no message
+        let request = BroadcastRequest {
+            message: request.message.trim().to_string(),
+        };
+        request.validate().map_err(|_| Error::InvalidAnnouncement)?;
+        let announcement = Announcement {
+            id: Uuid::new_v4(),
+            message: request.message,
+        };
+        info!("Admin {} announced {}", admin_id, announcement.id);
+        announce(&self.io, &announcement).await;
+        Ok(announcement)
+    }
+}
+
+/// Sends the announcement to every socket, whether at a table or in the lobby
+pub async fn announce(io: &Io, announcement: &Announcement) {
+    for namespace in ["/game", "/lobby"] {
+        let Some(operator) = io.of(namespace) else {
+            continue;
+        };
+        if let Err(e) = operator
+            .emit(ServiceEvent::Announcement, announcement)
+            .await
+        {
+            error!("Error occurred when announcing on {}: {:?}", namespace, e);
+        }
+    }
+}
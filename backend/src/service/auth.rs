@@ -1,50 +1,415 @@
-use bcrypt::{hash, verify, DEFAULT_COST};
-use eyre::{ensure, ContextCompat, Result};
+use std::sync::Arc;
+use std::time::Duration;
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use chrono::{DateTime, TimeDelta, Utc};
+use dashmap::DashMap;
+use eyre::{bail, ensure, eyre, ContextCompat, Result};
+use log::{error, info};
+use rand::distributions::{Alphanumeric, DistString};
+use sha2::{Digest, Sha256};
 use socketioxide::socket::Sid;
 use sqlx::types::Uuid;
+use tokio::time::interval;
 
-use crate::domain::auth::AuthUser;
+use crate::domain::auth::{AuthUser, Claims};
 use crate::repository::auth::AuthUserRepository;
+use crate::service::jwt::{JwtKeys, ACCESS_TOKEN_TTL};
+use crate::service::mail::AccountMail;
+use types::domain::{Role, TokenPair};
 use types::error::Error;
 
+const REFRESH_TOKEN_TTL: TimeDelta = TimeDelta::days(30);
+const VERIFICATION_TOKEN_TTL: TimeDelta = TimeDelta::days(1);
+const PASSWORD_RESET_TTL: TimeDelta = TimeDelta::minutes(30);
+const RESET_CODE_LENGTH: usize = 10;
+// wrong guesses a user's reset codes take before they stop working
+const MAX_RESET_ATTEMPTS: i32 = 5;
+// a live session is looked up again once its last check is this old
+const SESSION_CHECK_TTL: TimeDelta = TimeDelta::seconds(30);
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// how soon a token logged out on another instance stops working here
+const REVOCATION_SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(Clone)]
 pub struct AuthService {
     pub auth_repository: AuthUserRepository,
+    pub jwt: JwtKeys,
+    pub session_checks: SessionChecks,
+    pub revoked_tokens: RevokedTokens,
+    // without a mailer accounts are verified as they are made and passwords cannot be reset
+    pub mail: Option<AccountMail>,
+}
+
+// When each socket's session was last found live
+#[derive(Clone, Default)]
+pub struct SessionChecks {
+    checked_at: Arc<DashMap<Sid, DateTime<Utc>>>,
+}
+
+impl SessionChecks {
+    fn is_fresh(&self, sid: Sid, now: DateTime<Utc>) -> bool {
+        self.checked_at
+            .get(&sid)
+            .is_some_and(|at| now - *at < SESSION_CHECK_TTL)
+    }
+
+    fn record(&self, sid: Sid, now: DateTime<Utc>) {
+        self.checked_at.insert(sid, now);
+    }
+
+    fn forget(&self, sid: Sid) {
+        self.checked_at.remove(&sid);
+    }
+}
+
+// Access tokens logged out before they expire, by token id, until they would have expired
+#[derive(Clone, Default)]
+pub struct RevokedTokens {
+    expires_at: Arc<DashMap<Uuid, DateTime<Utc>>>,
+}
+
+impl RevokedTokens {
+    fn contains(&self, jti: Uuid) -> bool {
+        self.expires_at.contains_key(&jti)
+    }
+
+    fn insert(&self, jti: Uuid, expires_at: DateTime<Utc>) {
+        self.expires_at.insert(jti, expires_at);
+    }
+
+    fn prune(&self, now: DateTime<Utc>) {
+        self.expires_at.retain(|_, expires_at| *expires_at > now);
+    }
 }
 
 impl AuthService {
-    pub async fn signup(&self, email: String, password: String) -> Result<AuthUser> {
+    /// Creates an account, returning whether a verification link was emailed for it
+    pub async fn signup(&self, email: String, password: String) -> Result<bool> {
         ensure!(
             !self.auth_repository.exists(email.clone()).await?,
             Error::EmailAlreadyExists
         );
-        let hashed_password = hash(password, DEFAULT_COST)?;
-        self.auth_repository
-            .create_user(email, hashed_password)
-            .await
+        let hashed_password = hash_password(&password)?;
+        let user = self
+            .auth_repository
+            .create_user(email, hashed_password, self.mail.is_none())
+            .await?;
+        if self.mail.is_none() {
+            return Ok(false);
+        }
+        // the account is made either way, logging in sends another link
+        if let Err(e) = self.send_verification(&user).await {
+            error!(
+                "Failed to email user {} a verification link: {:?}",
+                user.id, e
+            );
+        }
+        Ok(true)
     }
 
-    pub async fn login(&self, email: String, password: String) -> Result<Uuid> {
+    pub async fn login(&self, email: String, password: String) -> Result<TokenPair> {
         let user = self
             .auth_repository
             .get(email)
             .await?
             .wrap_err(Error::UserNotFound)?;
         ensure!(
-            verify(password, &user.hashed_password)?,
+            verify_password(&password, &user.hashed_password)?,
             Error::InvalidPassword
         );
+        if user.verified_at.is_none() {
+            self.send_verification(&user).await?;
+            bail!(Error::EmailNotVerified);
+        }
+        self.issue_tokens(&user).await
+    }
+
+    async fn send_verification(&self, user: &AuthUser) -> Result<()> {
+        let Some(mail) = &self.mail else {
+            return Ok(());
+        };
         let token = Uuid::new_v4();
-        self.auth_repository.update_token(user.id, token).await?;
+        self.auth_repository
+            .create_verification_token(user.id, token, Utc::now() + VERIFICATION_TOKEN_TTL)
+            .await?;
+        mail.send_verification(&user.email, token).await
+    }
+
+    pub async fn verify_email(&self, token: Uuid) -> Result<()> {
+        let user_id = self
+            .auth_repository
+            .verify_email(token)
+            .await?
+            .wrap_err(Error::InvalidVerificationToken)?;
+        info!("User {} verified their email", user_id);
+        Ok(())
+    }
+
+    /// Emails a reset code to the account, if there is one. Unknown emails are not told apart
+    pub async fn forgot_password(&self, email: String) -> Result<()> {
+        let mail = self
+            .mail
+            .as_ref()
+            .wrap_err(Error::PasswordResetUnavailable)?;
+        let Some(user) = self.auth_repository.get(email).await? else {
+            return Ok(());
+        };
+        let code = Alphanumeric
+            .sample_string(&mut rand::thread_rng(), RESET_CODE_LENGTH)
+            .to_uppercase();
+        let expires_at = Utc::now() + PASSWORD_RESET_TTL;
+        self.auth_repository
+            .create_password_reset(user.id, &hash_reset_code(&code), expires_at)
+            .await?;
+        mail.send_password_reset(&user.email, &code).await
+    }
+
+    /// Sets a new password with an emailed code, logging the account out everywhere
+    pub async fn reset_password(
+        &self,
+        email: String,
+        code: String,
+        new_password: String,
+    ) -> Result<()> {
+        let hashed_password = hash_password(&new_password)?;
+        let user_id = self
+            .auth_repository
+            .reset_password(
+                email,
+                hash_reset_code(&code.trim().to_uppercase()),
+                hashed_password,
+                MAX_RESET_ATTEMPTS,
+            )
+            .await?
+            .wrap_err(Error::InvalidResetCode)?;
+        info!("User {} reset their password", user_id);
+        Ok(())
+    }
+
+    /// Trades a refresh token for a new pair, the old refresh token stops working
+    pub async fn refresh(&self, refresh_token: Uuid) -> Result<TokenPair> {
+        let user_id = self
+            .auth_repository
+            .take_refresh_token(refresh_token)
+            .await?
+            .wrap_err(Error::InvalidToken)?;
+        let user = self
+            .auth_repository
+            .get_by_id(user_id)
+            .await?
+            .wrap_err(Error::UserNotFound)?;
+        self.issue_tokens(&user).await
+    }
 
-        Ok(token)
+    /// Ends the session behind a refresh token and the access token it was asked with,
+    /// the user's other devices stay logged in
+    pub async fn logout(&self, claims: &Claims, refresh_token: Uuid) -> Result<()> {
+        let revoked = self
+            .auth_repository
+            .revoke_refresh_token(claims.sub, refresh_token)
+            .await?;
+        ensure!(revoked, Error::InvalidToken);
+        self.revoke_access_token(claims).await?;
+        info!("User {} logged out", claims.sub);
+        Ok(())
     }
 
-    pub async fn get_user_by_session_token(&self, token: Uuid) -> Result<Option<AuthUser>> {
-        self.auth_repository.get_by_session_token(token).await
+    /// Ends every session of the user. Access tokens of the other devices run out on their own,
+    /// their sockets are closed and they cannot refresh.
+    pub async fn logout_everywhere(&self, claims: &Claims) -> Result<()> {
+        let revoked = self
+            .auth_repository
+            .revoke_refresh_tokens(claims.sub)
+            .await?;
+        self.revoke_access_token(claims).await?;
+        info!("User {} logged out of {} sessions", claims.sub, revoked);
+        Ok(())
+    }
+
+    async fn revoke_access_token(&self, claims: &Claims) -> Result<()> {
+        let expires_at =
+            DateTime::from_timestamp(claims.exp, 0).wrap_err("Access token expiry out of range")?;
+        self.auth_repository
+            .revoke_access_token(claims.jti, expires_at)
+            .await?;
+        self.revoked_tokens.insert(claims.jti, expires_at);
+        Ok(())
+    }
+
+    /// Picks up the access tokens other instances logged out every few seconds,
+    /// and deletes expired tokens every hour
+    pub async fn run(self) {
+        let mut sweep = interval(SWEEP_INTERVAL);
+        let mut sync = interval(REVOCATION_SYNC_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = sweep.tick() => match self.auth_repository.delete_expired_tokens().await {
+                    Ok(0) => {}
+                    Ok(deleted) => info!("Deleted {} expired tokens", deleted),
+                    Err(e) => error!("Error occurred when deleting expired tokens: {:?}", e),
+                },
+                _ = sync.tick() => match self.auth_repository.revoked_access_tokens().await {
+                    Ok(revoked) => {
+                        self.revoked_tokens.prune(Utc::now());
+                        for (jti, expires_at) in revoked {
+                            self.revoked_tokens.insert(jti, expires_at);
+                        }
+                    }
+                    Err(e) => error!("Error occurred when loading revoked tokens: {:?}", e),
+                },
+            }
+        }
+    }
+
+    async fn issue_tokens(&self, user: &AuthUser) -> Result<TokenPair> {
+        ensure!(user.banned_at.is_none(), Error::UserBanned);
+        let now = Utc::now();
+        let access_token = self.jwt.issue(user.id, user.role, now)?;
+        let refresh_token = Uuid::new_v4();
+        self.auth_repository
+            .create_refresh_token(user.id, refresh_token, now + REFRESH_TOKEN_TTL)
+            .await?;
+        Ok(TokenPair {
+            access_token,
+            refresh_token,
+            expires_in: ACCESS_TOKEN_TTL.num_seconds(),
+        })
+    }
+
+    /// Checks the token and that it was not logged out
+    pub fn verify_access_token(&self, token: &str) -> Result<Claims> {
+        let claims = self.jwt.verify(token)?;
+        ensure!(
+            !self.revoked_tokens.contains(claims.jti),
+            Error::InvalidToken
+        );
+        Ok(claims)
+    }
+
+    pub async fn get_user(&self, user_id: Uuid) -> Result<Option<AuthUser>> {
+        self.auth_repository.get_by_id(user_id).await
+    }
+
+    /// Bans a player, staff have to be demoted first
+    pub async fn ban(
+        &self,
+        user_id: Uuid,
+        banned_by: Uuid,
+        reason: Option<String>,
+    ) -> Result<AuthUser> {
+        let user = self
+            .auth_repository
+            .get_by_id(user_id)
+            .await?
+            .wrap_err(Error::UserNotFound)?;
+        ensure!(user.role == Role::Player, Error::CannotBanStaff);
+        let user = self
+            .auth_repository
+            .ban(user_id, banned_by, reason)
+            .await?
+            .wrap_err(Error::UserNotFound)?;
+        info!("User {} banned by {}", user_id, banned_by);
+        Ok(user)
     }
 
     pub async fn update_sid(&self, user_id: Uuid, sid: Sid) -> Result<Option<AuthUser>> {
         self.auth_repository.update_sid(user_id, sid).await
     }
+
+    /// Whether the socket's session was revoked or expired, cached for a short while once live
+    pub async fn is_session_live(&self, user_id: Uuid, sid: Sid) -> Result<bool> {
+        let now = Utc::now();
+        if self.session_checks.is_fresh(sid, now) {
+            return Ok(true);
+        }
+        let live = self.auth_repository.is_session_live(user_id, sid).await?;
+        if live {
+            self.session_checks.record(sid, now);
+        } else {
+            self.session_checks.forget(sid);
+        }
+        Ok(live)
+    }
+
+    pub fn forget_session(&self, sid: Sid) {
+        self.session_checks.forget(sid);
+    }
+}
+
+fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| eyre!("Failed to hash password: {}", e))
+}
+
+// codes are short lived and random, so a plain SHA-256 is enough to keep them out of the database
+fn hash_reset_code(code: &str) -> Vec<u8> {
+    Sha256::digest(code.as_bytes()).to_vec()
+}
+
+// accounts made before argon2 keep their bcrypt hash until the password is reset
+fn verify_password(password: &str, hashed_password: &str) -> Result<bool> {
+    if !hashed_password.starts_with("$argon2") {
+        return Ok(bcrypt::verify(password, hashed_password)?);
+    }
+    let hash =
+        PasswordHash::new(hashed_password).map_err(|e| eyre!("Invalid password hash: {}", e))?;
+    Ok(Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_checks_should_expire_after_the_ttl() {
+        let checks = SessionChecks::default();
+        let sid = Sid::new();
+        let now = Utc::now();
+        assert!(!checks.is_fresh(sid, now));
+
+        checks.record(sid, now);
+        assert!(checks.is_fresh(sid, now + TimeDelta::seconds(1)));
+        assert!(!checks.is_fresh(sid, now + SESSION_CHECK_TTL));
+        // other sockets are checked on their own
+        assert!(!checks.is_fresh(Sid::new(), now));
+
+        checks.forget(sid);
+        assert!(!checks.is_fresh(sid, now));
+    }
+
+    #[test]
+    fn revoked_tokens_should_be_kept_until_they_expire() {
+        let revoked = RevokedTokens::default();
+        let [jti, other] = [Uuid::new_v4(), Uuid::new_v4()];
+        let now = Utc::now();
+        revoked.insert(jti, now + ACCESS_TOKEN_TTL);
+        assert!(revoked.contains(jti));
+        assert!(!revoked.contains(other));
+
+        revoked.prune(now + TimeDelta::minutes(1));
+        assert!(revoked.contains(jti));
+        revoked.prune(now + ACCESS_TOKEN_TTL);
+        assert!(!revoked.contains(jti));
+    }
+
+    #[test]
+    fn passwords_should_verify_against_argon2_and_older_bcrypt_hashes() -> Result<()> {
+        let hashed = hash_password("correct horse")?;
+        assert!(hashed.starts_with("$argon2"));
+        assert!(verify_password("correct horse", &hashed)?);
+        assert!(!verify_password("wrong horse", &hashed)?);
+
+        let legacy = bcrypt::hash("correct horse", 4)?;
+        assert!(verify_password("correct horse", &legacy)?);
+        assert!(!verify_password("wrong horse", &legacy)?);
+        Ok(())
+    }
 }
@@ -0,0 +1,183 @@
+use std::sync::{Arc, Mutex};
+
+use eyre::{eyre, ContextCompat, Result};
+use log::{debug, error};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::sleep;
+use uuid::Uuid;
+
+use types::bot::{
+    BotView, Decision, HistoryEntry, LegalActions, LooseAggressive, Strategy, TightPassive,
+};
+use types::room::Room;
+
+use crate::service::game::GameService;
+
+// Plays the bot seats of every room: whenever a room's state goes out and a bot is in turn,
+// it acts for the bot once the strategy's think time has passed.
+#[derive(Clone)]
+pub struct BotService {
+    pub game_service: GameService,
+    // a bot plays the style its seat picks, so a table gets a mix of opponents
+    pub strategies: Arc<Vec<Mutex<Box<dyn Strategy>>>>,
+}
+
+impl BotService {
+    pub fn new(game_service: GameService) -> Self {
+        let strategies: Vec<Box<dyn Strategy>> = vec![
+            Box::new(LooseAggressive::default()),
+            Box::new(TightPassive::default()),
+        ];
+        Self {
+            game_service,
+            strategies: Arc::new(strategies.into_iter().map(Mutex::new).collect()),
+        }
+    }
+
+    pub async fn run(self) {
+        let mut changes = self.game_service.broadcaster.subscribe();
+        loop {
+            match changes.recv().await {
+                Ok(room_id) => self.schedule_turn(room_id),
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!("Bot service skipped {} room changes", skipped)
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+
+    fn schedule_turn(&self, room_id: Uuid) {
+        let Some(turn) = self.bot_turn(room_id) else {
+            return;
+        };
+        let service = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = service.play_turn(room_id, turn).await {
+                error!("Error occurred when playing a bot turn: {:?}", e);
+            }
+        });
+    }
+
+    // the bot in turn and the action count when its turn started
+    fn bot_turn(&self, room_id: Uuid) -> Option<(Uuid, u64)> {
+        let room = self.game_service.room_repository.rooms.get(&room_id)?;
+        room.player_in_turn
+            .filter(|player_id| room.is_bot(*player_id))
+            .map(|player_id| (player_id, room.action_seq))
+    }
+
+    async fn play_turn(&self, room_id: Uuid, turn: (Uuid, u64)) -> Result<()> {
+        let (bot_id, _) = turn;
+        let room = self
+            .game_service
+            .room_repository
+            .get(room_id)
+            .wrap_err("Room not found")?;
+        let seat = room
+            .players
+            .iter()
+            .find(|p| p.id == bot_id)
+            .map(|p| p.seat)
+            .wrap_err("Bot not found")?;
+        let Decision { action, think_time } = {
+            let mut strategy = self.strategies[seat % self.strategies.len()]
+                .lock()
+                .map_err(|_| eyre!("Bot strategy poisoned"))?;
+            decide(&room, bot_id, strategy.as_mut())
+        };
+        sleep(think_time).await;
+        // another change may have scheduled the same turn, or the bot timed out meanwhile
+        if self.bot_turn(room_id) != Some(turn) {
+            return Ok(());
+        }
+        self.game_service
+            .take_action(room_id, bot_id, action)
+            .await?;
+        Ok(())
+    }
+}
+
+/// What the strategy plays for the bot, checking or folding when it picks an illegal action
+fn decide(room: &Room, bot_id: Uuid, strategy: &mut dyn Strategy) -> Decision {
+    let view = BotView::from_room(room, bot_id);
+    let legal = LegalActions::from_view(&view);
+    let mut decision = strategy.decide(&view, &legal, &HistoryEntry::from_room(room));
+    if !legal.allows(&decision.action) {
+        debug!(
+            "{} chose an illegal action {:?}",
+            strategy.name(),
+            decision.action
+        );
+        decision.action = legal.check_or_fold();
+    }
+    decision
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use poker::{cards, Card};
+
+    use types::domain::Action;
+    use types::room::{Hand, Player, Stage};
+
+    use super::*;
+
+    fn room_with_bot(hole_cards: [Card; 2], opponent_bet: u32) -> (Room, Uuid) {
+        let mut room = Room::new();
+        room.stage = Stage::PreFlop;
+        let mut bot = Player::new("Bot 1".to_string(), 100);
+        bot.hand = Some(Hand(hole_cards));
+        bot.bet = 2;
+        let mut opponent = Player::new("Alice".to_string(), 100);
+        opponent.bet = opponent_bet;
+        let bot_id = bot.id;
+        room.bots.insert(bot_id);
+        room.players = vec![bot, opponent];
+        room.player_in_turn = Some(bot_id);
+        (room, bot_id)
+    }
+
+    // always shoves far more than it has
+    struct Reckless;
+
+    impl Strategy for Reckless {
+        fn name(&self) -> &str {
+            "reckless"
+        }
+
+        fn decide(&mut self, _: &BotView, _: &LegalActions, _: &[HistoryEntry]) -> Decision {
+            Decision {
+                action: Action::RaiseTo(10_000),
+                think_time: Duration::ZERO,
+            }
+        }
+    }
+
+    #[test]
+    fn bot_seats_should_play_the_shared_strategies() {
+        let mut loose = LooseAggressive::default();
+        let mut tight = TightPassive::default();
+        let (room, bot_id) = room_with_bot(cards!(Ace, Spades; Ace, Hearts;), 10);
+        let action = decide(&room, bot_id, &mut loose).action;
+        assert!(matches!(action, Action::RaiseTo(_)));
+        assert_eq!(decide(&room, bot_id, &mut tight).action, Action::Call);
+
+        let (room, bot_id) = room_with_bot(cards!(Seven, Spades; Two, Hearts;), 10);
+        assert_eq!(decide(&room, bot_id, &mut tight).action, Action::Fold);
+
+        // nothing to call, so a weak hand checks
+        let (room, bot_id) = room_with_bot(cards!(Seven, Spades; Two, Hearts;), 2);
+        assert_eq!(decide(&room, bot_id, &mut tight).action, Action::Check);
+    }
+
+    #[test]
+    fn illegal_choice_should_be_turned_into_a_check_or_fold() {
+        let (room, bot_id) = room_with_bot(cards!(Ace, Spades; Ace, Hearts;), 10);
+        assert_eq!(decide(&room, bot_id, &mut Reckless).action, Action::Fold);
+        let (room, bot_id) = room_with_bot(cards!(Ace, Spades; Ace, Hearts;), 2);
+        assert_eq!(decide(&room, bot_id, &mut Reckless).action, Action::Check);
+    }
+}
@@ -0,0 +1,214 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use log::{debug, error};
+use serde::Serialize;
+use socketioxide::socket::Sid;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+use uuid::Uuid;
+
+use types::domain::ServiceEvent;
+use types::room::{LoggedAction, Room};
+use types::state::{SharedGameState, Timestamped};
+
+use crate::service::adapter::Io;
+
+pub const DEFAULT_BROADCAST_WINDOW: Duration = Duration::from_millis(50);
+const CHANGES_CAPACITY: usize = 256;
+
+// orders the snapshots of a room the way they were taken, they are taken under its lock
+static NEXT_STAMP: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Default)]
+pub struct BroadcastMetrics {
+    // snapshots actually emitted to rooms
+    pub broadcasts: AtomicU64,
+    // snapshots replaced by a newer one before they were emitted
+    pub coalesced: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BroadcastMetricsSnapshot {
+    pub broadcasts: u64,
+    pub coalesced: u64,
+}
+
+/// A room's state with who to send it to, taken while the room is locked
+pub(crate) struct PendingBroadcast {
+    stamp: u64,
+    state: SharedGameState,
+    action_log: Vec<LoggedAction>,
+    seats: Vec<(Uuid, Sid)>,
+}
+
+impl PendingBroadcast {
+    pub(crate) fn new(room: &Room, state: SharedGameState) -> Self {
+        Self {
+            stamp: NEXT_STAMP.fetch_add(1, Ordering::Relaxed),
+            state,
+            action_log: room.action_log.clone(),
+            seats: room
+                .players
+                .iter()
+                .chain(room.players_sitting_out.iter())
+                // waiting for the next hand, they watch the one under way
+                .chain(room.player_joining_next_round.iter())
+                .map(|p| (p.id, p.sid))
+                .collect(),
+        }
+    }
+}
+
+// Coalesces room state broadcasts: state changes within `window` of the first one
+// are sent as a single snapshot of the latest state, carrying every action since
+// the previous snapshot so clients don't miss what happened in between.
+#[derive(Clone)]
+pub struct RoomBroadcaster {
+    io: Io,
+    window: Duration,
+    pending: Arc<DashMap<Uuid, PendingBroadcast>>,
+    // stamp and action count of the last snapshot sent to each room
+    last_sent: Arc<DashMap<Uuid, (u64, u64)>>,
+    metrics: Arc<BroadcastMetrics>,
+    // ids of rooms whose state was just sent out, for in-process subscribers
+    changes: broadcast::Sender<Uuid>,
+}
+
+impl RoomBroadcaster {
+    pub fn new(io: Io, window: Duration) -> Self {
+        Self {
+            io,
+            window,
+            pending: Arc::new(DashMap::new()),
+            last_sent: Arc::new(DashMap::new()),
+            metrics: Arc::new(BroadcastMetrics::default()),
+            changes: broadcast::channel(CHANGES_CAPACITY).0,
+        }
+    }
+
+    /// Notified with the room id every time a room's state is sent out
+    pub fn subscribe(&self) -> broadcast::Receiver<Uuid> {
+        self.changes.subscribe()
+    }
+
+    pub fn metrics(&self) -> BroadcastMetricsSnapshot {
+        BroadcastMetricsSnapshot {
+            broadcasts: self.metrics.broadcasts.load(Ordering::Relaxed),
+            coalesced: self.metrics.coalesced.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) async fn broadcast(&self, room_id: Uuid, pending: PendingBroadcast) {
+        if self.window.is_zero() {
+            self.send(room_id, pending).await;
+            return;
+        }
+        match self.pending.entry(room_id) {
+            Entry::Occupied(mut entry) => {
+                if entry.get().stamp < pending.stamp {
+                    entry.insert(pending);
+                }
+                self.metrics.coalesced.fetch_add(1, Ordering::Relaxed);
+                debug!("Coalesced state broadcast for room {}", room_id);
+                return;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(pending);
+            }
+        }
+        // first change in this window, flush whatever is latest once it ends
+        let broadcaster = self.clone();
+        tokio::spawn(async move {
+            sleep(broadcaster.window).await;
+            if let Some((_, pending)) = broadcaster.pending.remove(&room_id) {
+                broadcaster.send(room_id, pending).await;
+            }
+        });
+    }
+
+    async fn send(&self, room_id: Uuid, pending: PendingBroadcast) {
+        let PendingBroadcast {
+            stamp,
+            mut state,
+            action_log,
+            seats,
+        } = pending;
+        let (mut last_sent_seq, mut is_stale) = (0, false);
+        self.last_sent
+            .entry(room_id)
+            .and_modify(|(sent_stamp, sent_seq)| {
+                last_sent_seq = *sent_seq;
+                is_stale = *sent_stamp > stamp;
+                if !is_stale {
+                    *sent_stamp = stamp;
+                    *sent_seq = state.last_action_seq;
+                }
+            })
+            .or_insert((stamp, state.last_action_seq));
+        // rooms are let go before their changes are sent, a newer snapshot may have won the race
+        if is_stale {
+            debug!("Dropped a stale state broadcast for room {}", room_id);
+            return;
+        }
+        state.recent_actions = action_log
+            .into_iter()
+            .filter(|a| a.seq > last_sent_seq)
+            .collect();
+        self.metrics.broadcasts.fetch_add(1, Ordering::Relaxed);
+        // every seated player gets their own copy with their betting figures filled in
+        if let Some(operator) = self.io.of("/game") {
+            for (player_id, sid) in seats {
+                let Some(socket) = operator.get_socket(sid) else {
+                    continue;
+                };
+                let state = Timestamped::new(state.personalized(player_id));
+                if let Err(e) = socket.emit(ServiceEvent::Room, &state) {
+                    error!("Error occurred when broadcasting room state: {:?}", e);
+                }
+            }
+        }
+        // fails only when nobody is subscribed
+        let _ = self.changes.send(room_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service::adapter::{self, PubSub};
+
+    #[tokio::test]
+    async fn broadcasts_within_window_should_be_coalesced() {
+        let (_, io) = adapter::new_layer(PubSub::Local);
+        let broadcaster = RoomBroadcaster::new(io, Duration::from_millis(50));
+        let room = Room::new();
+        for _ in 0..3 {
+            let state = SharedGameState::from_room(room.clone(), false);
+            let pending = PendingBroadcast::new(&room, state);
+            broadcaster.broadcast(room.id, pending).await;
+        }
+        sleep(Duration::from_millis(100)).await;
+
+        let metrics = broadcaster.metrics();
+        assert_eq!(metrics.broadcasts, 1);
+        assert_eq!(metrics.coalesced, 2);
+        assert!(broadcaster.pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn snapshots_older_than_the_last_sent_should_be_dropped() {
+        let (_, io) = adapter::new_layer(PubSub::Local);
+        let broadcaster = RoomBroadcaster::new(io, Duration::ZERO);
+        let room = Room::new();
+        let older = PendingBroadcast::new(&room, SharedGameState::from_room(room.clone(), false));
+        let newer = PendingBroadcast::new(&room, SharedGameState::from_room(room.clone(), false));
+        broadcaster.broadcast(room.id, newer).await;
+        broadcaster.broadcast(room.id, older).await;
+
+        assert_eq!(broadcaster.metrics().broadcasts, 1);
+    }
+}
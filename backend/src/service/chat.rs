@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use dashmap::DashMap;
+use uuid::Uuid;
+
+// a player may send this many messages within the window
+const CHAT_BURST: usize = 5;
+const CHAT_WINDOW: TimeDelta = TimeDelta::seconds(10);
+
+// Sliding window limit on chat messages, per player across all rooms
+#[derive(Clone, Default)]
+pub struct ChatRateLimiter {
+    sent: Arc<DashMap<Uuid, VecDeque<DateTime<Utc>>>>,
+}
+
+impl ChatRateLimiter {
+    /// Records a message from the player, returns false if they are over the limit
+    pub fn try_send(&self, player_id: Uuid) -> bool {
+        self.try_send_at(player_id, Utc::now())
+    }
+
+    fn try_send_at(&self, player_id: Uuid, now: DateTime<Utc>) -> bool {
+        let mut sent = self.sent.entry(player_id).or_default();
+        while sent.front().is_some_and(|at| now - *at >= CHAT_WINDOW) {
+            sent.pop_front();
+        }
+        if sent.len() >= CHAT_BURST {
+            return false;
+        }
+        sent.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_over_the_burst_should_wait_for_the_window() {
+        let limiter = ChatRateLimiter::default();
+        let player_id = Uuid::new_v4();
+        let start = Utc::now();
+        for _ in 0..CHAT_BURST {
+            assert!(limiter.try_send_at(player_id, start));
+        }
+        assert!(!limiter.try_send_at(player_id, start + TimeDelta::seconds(1)));
+        // other players have their own allowance
+        assert!(limiter.try_send_at(Uuid::new_v4(), start));
+        assert!(limiter.try_send_at(player_id, start + CHAT_WINDOW));
+    }
+}
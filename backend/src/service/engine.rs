@@ -0,0 +1,177 @@
+use eyre::Result;
+use poker::{Card, Evaluator};
+use socketioxide::socket::Sid;
+use tap::TapFallible;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use types::domain::{ServiceRequiredAction, ShowOrMuckPrompt};
+use types::history::HandHistory;
+use types::room::{
+    ActionLogEntry, GameResult, Hand, HandSettlement, Outcome, Room, RoomSnapshot, Winnings,
+};
+use types::rules::{SHOWDOWN_TIME, TURN_TIME};
+use types::state::SharedGameState;
+use types::stats::{HandOutcome, ShowdownResult};
+
+use crate::service::broadcast::PendingBroadcast;
+
+/// What came of a change to a room, for the game service to send out and write down
+/// once the room is unlocked
+pub(crate) enum GameEvent {
+    StateChanged(Box<PendingBroadcast>),
+    // hole cards for every player dealt in, with the board their hints are judged on
+    HandsDealt {
+        hands: Vec<(Sid, [Card; 2])>,
+        board: Vec<Card>,
+    },
+    // only the players who asked for hints hear about a new street
+    BoardChanged {
+        hands: Vec<(Sid, [Card; 2])>,
+        board: Vec<Card>,
+    },
+    ActionLog(Vec<ActionLogEntry>),
+    ShowOrMuck {
+        sids: Vec<Sid>,
+        prompt: ShowOrMuckPrompt,
+    },
+    // the rest of the board is dealt a street at a time from here
+    RunOutStarted,
+    HandFinished(Box<FinishedHand>),
+    Outcome(Outcome),
+}
+
+/// Everything about a finished hand that is kept outside of the room
+pub(crate) struct FinishedHand {
+    pub room_id: Uuid,
+    // empty for scripted hands, they say nothing about how anyone plays
+    pub showdowns: Vec<ShowdownResult>,
+    pub outcomes: Vec<HandOutcome>,
+    pub history: HandHistory,
+    pub rake_taken: u32,
+    pub snapshot: RoomSnapshot,
+    pub settlements: Vec<HandSettlement>,
+    pub results: Vec<Winnings>,
+    pub hands_per_hour: Option<u32>,
+}
+
+/// Moves the room on from what the last change asked for, without waiting on anything
+pub(crate) fn advance(
+    room: &mut Room,
+    action: ServiceRequiredAction,
+    evaluator: &Evaluator,
+) -> Result<Vec<GameEvent>> {
+    // the turn may have passed to someone who already declared what to play
+    let action = room.play_dark_actions(action)?;
+    room.refresh_turn_deadline(TURN_TIME);
+
+    let mut events = match action {
+        ServiceRequiredAction::NoAction => {
+            let hands = room
+                .players
+                .iter()
+                .filter(|p| !p.has_folded)
+                .filter_map(|p| p.hand.as_ref().map(|Hand(cards)| (p.sid, *cards)))
+                .collect();
+            let board = room.visible_board().to_vec();
+            vec![
+                state_changed(room),
+                GameEvent::BoardChanged { hands, board },
+            ]
+        }
+        // an all-in board is shown a street at a time before the pots are paid out
+        ServiceRequiredAction::FindWinners if room.start_run_out(evaluator)? => {
+            vec![state_changed(room), GameEvent::RunOutStarted]
+        }
+        ServiceRequiredAction::FindWinners => finish_hand(room, evaluator)?,
+        ServiceRequiredAction::PlayerReceiveCards => {
+            if let Some(deck_hash) = &room.deck_hash {
+                info!(
+                    "Room {} dealt hand {} from deck {}",
+                    room.id, room.hand_number, deck_hash
+                );
+            }
+            let hands = room
+                .players
+                .iter()
+                .filter_map(|p| p.hand.as_ref().map(|Hand(cards)| (p.sid, *cards)))
+                .collect();
+            let board = room.community_cards.clone();
+            vec![state_changed(room), GameEvent::HandsDealt { hands, board }]
+        }
+    };
+    events.push(GameEvent::ActionLog(std::mem::take(&mut room.pending_log)));
+    Ok(events)
+}
+
+// the results stay up until the deadline or until everyone is ready,
+// losers' cards stay mucked unless they choose to show them
+fn finish_hand(room: &mut Room, evaluator: &Evaluator) -> Result<Vec<GameEvent>> {
+    let game_result = room.find_winners(evaluator)?;
+    let is_scripted = room.scenario.is_some();
+    let showdowns = if is_scripted {
+        vec![]
+    } else {
+        game_result.showdown_results()
+    };
+    let GameResult {
+        hands_eval,
+        winners,
+    } = game_result;
+    let winner_ids = winners.iter().flat_map(|(_, ids)| ids.iter().copied());
+    room.start_showdown_phase(SHOWDOWN_TIME, hands_eval.clone(), winner_ids.collect());
+    let mut events = vec![state_changed(room)];
+    if let Some(showdown) = &room.showdown {
+        let sids = room
+            .players
+            .iter()
+            .filter(|p| showdown.undecided.contains(&p.id))
+            .map(|p| p.sid)
+            .collect();
+        let prompt = ShowOrMuckPrompt {
+            room_id: room.id,
+            deadline: showdown.deadline,
+        };
+        events.push(GameEvent::ShowOrMuck { sids, prompt });
+    }
+
+    // judged before the pots are paid out, a luck stat is not worth failing the hand
+    let expected_winnings = room
+        .all_in_expected_winnings(evaluator)
+        .tap_err(|e| error!("Error occurred when estimating all-in EV: {:?}", e))
+        .unwrap_or_default();
+    let pot_splits = room.split_pot(winners)?;
+    let results: Vec<Winnings> = pot_splits.into_iter().flatten().collect();
+    let outcomes = if is_scripted {
+        vec![]
+    } else {
+        room.hand_outcomes(&hands_eval, &results, &expected_winnings)
+    };
+    let history = HandHistory::from_room(room, &hands_eval, results.clone());
+    let mut winnings = Winnings::merge(results.clone());
+    // nobody shows a hand that won without a showdown
+    if !hands_eval.is_empty() {
+        let shown = room.show_best_hands(evaluator, &mut winnings);
+        if let Err(e) = shown {
+            error!("Error occurred when finding the winning cards: {:?}", e);
+        }
+    }
+    events.push(GameEvent::HandFinished(Box::new(FinishedHand {
+        room_id: room.id,
+        showdowns,
+        outcomes,
+        history,
+        rake_taken: room.rake_taken,
+        snapshot: room.snapshot(),
+        settlements: room.settlements(),
+        results: Winnings::merge(results),
+        hands_per_hour: room.pace.hands_per_hour(),
+    })));
+    events.push(GameEvent::Outcome(room.outcome(&hands_eval, winnings)));
+    Ok(events)
+}
+
+fn state_changed(room: &Room) -> GameEvent {
+    let state = SharedGameState::from_room(room.clone(), false);
+    GameEvent::StateChanged(Box::new(PendingBroadcast::new(room, state)))
+}
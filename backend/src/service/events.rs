@@ -0,0 +1,32 @@
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use types::webhook::RoomEvent;
+
+const EVENTS_CAPACITY: usize = 256;
+
+// Room events for in-process subscribers such as webhook delivery,
+// publishing never waits on them and events are dropped when nobody listens.
+#[derive(Clone)]
+pub struct RoomEvents {
+    sender: broadcast::Sender<(Uuid, RoomEvent)>,
+}
+
+impl Default for RoomEvents {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(EVENTS_CAPACITY).0,
+        }
+    }
+}
+
+impl RoomEvents {
+    pub fn publish(&self, room_id: Uuid, event: RoomEvent) {
+        // fails only when nobody is subscribed
+        let _ = self.sender.send((room_id, event));
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<(Uuid, RoomEvent)> {
+        self.sender.subscribe()
+    }
+}
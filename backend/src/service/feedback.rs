@@ -0,0 +1,34 @@
+use eyre::Result;
+use log::info;
+use sqlx::types::Uuid;
+use validator::Validate;
+
+use types::domain::{Feedback, FeedbackQuery, FeedbackRequest};
+use types::error::Error;
+
+use crate::repository::feedback::FeedbackRepository;
+
+const DEFAULT_LIST_LIMIT: i64 = 20;
+const MAX_LIST_LIMIT: i64 = 100;
+
+#[derive(Clone)]
+pub struct FeedbackService {
+    pub feedback_repository: FeedbackRepository,
+}
+
+impl FeedbackService {
+    pub async fn submit(&self, user_id: Uuid, request: FeedbackRequest) -> Result<Uuid> {
+        request.validate().map_err(|_| Error::InvalidFeedback)?;
+        let id = self.feedback_repository.create(user_id, request).await?;
+        info!("User {} filed feedback {}", user_id, id);
+        Ok(id)
+    }
+
+    pub async fn list(&self, query: FeedbackQuery) -> Result<Vec<Feedback>> {
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_LIST_LIMIT)
+            .clamp(1, MAX_LIST_LIMIT);
+        self.feedback_repository.list(limit).await
+    }
+}
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use eyre::{bail, ensure, ContextCompat, Result};
+use log::{error, info};
+use sqlx::types::Uuid;
+
+use types::domain::{Friend, FriendInvite, ServiceEvent};
+use types::error::Error;
+
+use crate::repository::friends::FriendRepository;
+use crate::repository::rooms::RoomInfoRepository;
+use crate::repository::users::UserRepository;
+use crate::service::adapter::Io;
+
+/// The socket.io room every lobby socket of a user joins, so they can be reached by id
+pub fn lobby_room(user_id: Uuid) -> String {
+    format!("user:{}", user_id)
+}
+
+#[derive(Clone)]
+pub struct FriendService {
+    pub friend_repository: FriendRepository,
+    pub user_repository: Arc<UserRepository>,
+    pub room_info_repository: RoomInfoRepository,
+    pub io: Io,
+}
+
+impl FriendService {
+    pub async fn list(&self, user_id: Uuid) -> Result<Vec<Friend>> {
+        self.friend_repository.list(user_id).await
+    }
+
+    /// Asks the player going by `username` to be friends. A request they already sent the
+    /// other way is accepted instead.
+    pub async fn request(&self, user_id: Uuid, username: &str) -> Result<()> {
+        let matches = self.friend_repository.find_by_name(username.trim()).await?;
+        let friend_id = match matches[..] {
+            [] => bail!(Error::PlayerNotFound),
+            [friend_id] => friend_id,
+            _ => bail!(Error::AmbiguousPlayerName),
+        };
+        ensure!(friend_id != user_id, Error::CannotFriendYourself);
+        if self.friend_repository.accept(friend_id, user_id).await? {
+            info!(
+                "User {} accepted the friend request of {}",
+                user_id, friend_id
+            );
+            return Ok(());
+        }
+        ensure!(
+            self.friend_repository
+                .create_request(user_id, friend_id)
+                .await?,
+            Error::FriendRequestExists
+        );
+        info!("User {} asked {} to be friends", user_id, friend_id);
+        Ok(())
+    }
+
+    pub async fn accept(&self, user_id: Uuid, requester_id: Uuid) -> Result<()> {
+        ensure!(
+            self.friend_repository.accept(requester_id, user_id).await?,
+            Error::NoFriendRequest
+        );
+        Ok(())
+    }
+
+    pub async fn decline(&self, user_id: Uuid, requester_id: Uuid) -> Result<()> {
+        ensure!(
+            self.friend_repository
+                .decline(requester_id, user_id)
+                .await?,
+            Error::NoFriendRequest
+        );
+        Ok(())
+    }
+
+    /// Pushes the room the user sits in to a friend's lobby
+    pub async fn invite(&self, user_id: Uuid, friend_id: Uuid) -> Result<()> {
+        ensure!(
+            self.friend_repository
+                .are_friends(user_id, friend_id)
+                .await?,
+            Error::NotFriends
+        );
+        let user = self
+            .user_repository
+            .get(user_id)
+            .await?
+            .wrap_err(Error::UserNotFound)?;
+        let room_id = user.current_room.wrap_err(Error::NotInRoom)?;
+        let room = self
+            .room_info_repository
+            .get(room_id)
+            .await?
+            .wrap_err(Error::RoomClosed)?;
+        let invite = FriendInvite {
+            from_id: user.id,
+            from_name: user.name,
+            room,
+        };
+        if let Some(operator) = self.io.of("/lobby") {
+            let result = operator
+                .to(lobby_room(friend_id))
+                .emit(ServiceEvent::Invite, &invite)
+                .await;
+            if let Err(e) = result {
+                error!("Error occurred when emitting friend invite: {:?}", e);
+            }
+        }
+        info!("User {} invited {} to room {}", user_id, friend_id, room_id);
+        Ok(())
+    }
+}
@@ -1,114 +1,460 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
+use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::{DateTime, TimeDelta, Utc};
 use dashmap::mapref::one::RefMut;
 use eyre::{bail, ensure, ContextCompat, Result};
-use itertools::Itertools;
-use log::{debug, error, info};
-use poker::{Eval, Evaluator};
+use poker::{Card, Evaluator};
 use serde::Serialize;
 use socketioxide::socket::Sid;
-use socketioxide::SocketIo;
 use tap::TapFallible;
 use tokio::time::sleep;
+use tracing::{debug, error, info, instrument};
 use uuid::Uuid;
 
-use types::domain::{Action, RoomInfo, ServiceEvent, ServiceRequiredAction};
+use types::crypto::{HandKey, SealedHand, SecretKey};
+use types::domain::{
+    Action, ChipCurrency, DarkAction, HandStrengthHints, Kicked, LobbyUpdate, PreAction, RoomInfo,
+    ServiceEvent, ServiceRequiredAction, Waitlisted,
+};
 use types::error::Error;
-use types::room::{Hand, Player, Room, Stage, Winnings};
-use types::state::{PlayerHand, SharedGameState, Timestamped};
-
+use types::history::{HandHistory, HandHistoryPage, HistoryQuery};
+use types::room::{
+    Outcome, Player, Rake, Room, RoomClosing, RoomConfig, RoomDetails, RoomExport, RoomSnapshot,
+    Scenario, Stage,
+};
+use types::state::{ChatMessage, DealtHand, PlayerHand, SharedGameState, Timestamped};
+use types::strength::HandStrength;
+use types::webhook::RoomEvent;
+
+use crate::repository::history::HandHistoryRepository;
+use crate::repository::rake::RakeRepository;
 use crate::repository::rooms::{RoomInfoRepository, RoomRepository};
+use crate::service::adapter::{Io, Socket};
+use crate::service::broadcast::{PendingBroadcast, RoomBroadcaster};
+use crate::service::chat::ChatRateLimiter;
+use crate::service::engine::{self, FinishedHand, GameEvent};
+use crate::service::events::RoomEvents;
+use crate::service::outbox::{self, Outbox, StagedChange};
+use crate::repository::stats::StatsRepository;
 use crate::repository::users::UserRepository;
 
 #[cfg(test)]
 use types::domain::User;
 
+// how long the results of a hand stay up when not everyone says they are ready
+const DEFAULT_HISTORY_PAGE_SIZE: u32 = 10;
+const MAX_HISTORY_PAGE_SIZE: u32 = 50;
+const MAX_CHAT_MESSAGE_LENGTH: usize = 200;
+// pause between the streets of an all-in board
+const RUN_OUT_STREET_DELAY: Duration = Duration::from_secs(2);
+
 #[derive(Clone)]
 pub struct GameService {
     pub evaluator: Evaluator,
     pub room_repository: RoomRepository,
     pub room_info_repository: RoomInfoRepository,
     pub user_repository: Arc<UserRepository>,
-    pub io: SocketIo,
+    pub stats_repository: StatsRepository,
+    pub hand_history_repository: HandHistoryRepository,
+    pub rake: Rake,
+    pub rake_repository: RakeRepository,
+    pub broadcaster: RoomBroadcaster,
+    pub chat_limiter: ChatRateLimiter,
+    pub events: RoomEvents,
+    pub outbox: Outbox,
+    pub settlement: Settlement,
+    // how long a dropped connection keeps its seat, zero lets the player go right away
+    pub reconnect_grace: TimeDelta,
+    // every room deals from this seed when set, for tests that need to replay a deal
+    pub deck_seed: Option<u64>,
+    pub io: Io,
+}
+
+/// When the database learns how the hands at a table went
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Settlement {
+    // stacks only reach balances when a player leaves, a crash loses what was at the tables
+    #[default]
+    OnLeave,
+    // each finished hand is written down, a crash pays back the stacks after the last one
+    PerHand,
 }
 
-pub struct GameResult {
-    pub hands_eval: HashMap<Uuid, Eval>,
-    pub winners: Vec<(u32, HashSet<Uuid>)>,
+impl Settlement {
+    pub fn from_env() -> Self {
+        let per_hand =
+            std::env::var("SETTLE_PER_HAND").is_ok_and(|value| value != "0" && !value.is_empty());
+        if per_hand {
+            Settlement::PerHand
+        } else {
+            Settlement::OnLeave
+        }
+    }
 }
 
 impl GameService {
-    pub async fn init_rooms(&mut self) -> Result<()> {
-        let rooms = self.room_info_repository.get_all().await?;
-        for room in rooms {
-            self.room_repository.upsert(Room::new_with_id(room.room_id));
+    pub async fn init_rooms(&self) -> Result<()> {
+        let rooms = self.room_info_repository.claim_rooms(Vec::new()).await?;
+        self.adopt_rooms(rooms).await
+    }
+
+    /// Puts rooms this instance just took the lease on into play. Rooms saved as they were
+    /// before a crash carry on with their players seated. Hands cut short anywhere else
+    /// cannot be resumed, so those stacks go back to the players' balances.
+    pub async fn adopt_rooms(&self, rooms: Vec<RoomInfo>) -> Result<()> {
+        let room_ids: Vec<Uuid> = rooms.iter().map(|room| room.room_id).collect();
+        let mut resumed: HashMap<Uuid, Room> = self
+            .room_repository
+            .load(&room_ids, &self.evaluator)
+            .await?
+            .into_iter()
+            .map(|room| (room.id, room))
+            .collect();
+        // only the rooms taken on just now, the others may still be seated elsewhere
+        let cut_short = room_ids
+            .iter()
+            .filter(|id| {
+                !resumed.contains_key(id) && !self.room_repository.rooms.contains_key(id)
+            })
+            .copied()
+            .collect();
+        let refunded = self.room_info_repository.refund_snapshots(cut_short).await?;
+        if refunded > 0 {
+            info!("Refunded the stacks of {} players from the last shutdown", refunded);
+        }
+        if !resumed.is_empty() {
+            info!("Resuming {} rooms from before the last shutdown", resumed.len());
+        }
+        for room_info in rooms {
+            // imported before its lease came over
+            if self.room_repository.rooms.contains_key(&room_info.room_id) {
+                continue;
+            }
+            let room = match resumed.remove(&room_info.room_id) {
+                Some(room) => room,
+                None => self.fresh_room(&room_info)?,
+            };
+            let running_out = room.is_running_out();
+            self.room_repository.rooms.insert(room.id, room);
+            if running_out {
+                self.schedule_run_out(room_info.room_id);
+            }
         }
         Ok(())
     }
 
-    pub async fn get_rooms(&self) -> Result<Vec<RoomInfo>> {
-        self.room_info_repository.get_all().await
+    fn fresh_room(&self, room_info: &RoomInfo) -> Result<Room> {
+        let mut room = Room::new_with_id(room_info.room_id);
+        room.closes_at = room_info.closes_at;
+        room.rake = self.rake;
+        room.config.kick_after_timeouts = room_info.kick_after_timeouts.max(0) as u32;
+        room.config.betting = room_info.betting;
+        room.config.currency = room_info.currency;
+        room.config.max_players = room_info.max_players as usize;
+        if let Some(seed) = self.deck_seed {
+            room.reseed(seed);
+        }
+        room.set_bot_seats(room_info.bot_seats as usize)?;
+        Ok(room)
     }
-    pub fn find_winners(&self, room: &Room) -> Result<GameResult> {
-        ensure!(
-            room.stage.is_showdown(),
-            "Game is not in the showdown stage yet"
-        );
-        // in special case where stage is Showdown(false), evaluation is not needed, winner should be the last man standing
-        if matches!(room.stage, Stage::Showdown(false)) {
-            let sole_player = room
-                .players
-                .iter()
-                .find_or_first(|p| !p.has_folded)
-                .wrap_err("No player left in the game")?;
-            let total_pot = room.pots.iter().map(|pot| pot.amount).sum();
-            return Ok(GameResult {
-                hands_eval: Default::default(),
-                winners: vec![(total_pot, HashSet::from([sole_player.id]))],
+
+    /// Stops serving a room another instance took over, its players reconnect to find it there
+    pub fn give_up_room(&self, room_id: Uuid) {
+        // the saved state now belongs to the new owner
+        let Some((_, room)) = self.room_repository.rooms.remove(&room_id) else {
+            return;
+        };
+        room.players
+            .iter()
+            .chain(room.player_joining_next_round.iter())
+            .chain(room.players_sitting_out.iter())
+            .filter(|p| p.is_connected && !room.is_bot(p.id))
+            .for_each(|p| {
+                if let Err(e) = self.disconnect_socket(p.sid) {
+                    error!("Failed to disconnect player {}: {:?}", p.id, e);
+                }
             });
+    }
+
+    /// Takes every room out of play and saves what each seated player is owed
+    #[instrument(skip(self))]
+    pub async fn snapshot_rooms(&self) -> Result<()> {
+        let room_ids: Vec<Uuid> = self.room_repository.rooms.iter().map(|r| *r.key()).collect();
+        // rooms are removed first so no action can move chips after they are saved,
+        // their stacks are paid back on the next start instead of the rooms resuming
+        let mut snapshots: Vec<RoomSnapshot> = Vec::with_capacity(room_ids.len());
+        for room_id in room_ids {
+            let Some(room) = self.room_repository.remove(room_id).await else {
+                continue;
+            };
+            let snapshot = room.snapshot();
+            if !snapshot.seats.is_empty() {
+                snapshots.push(snapshot);
+            }
         }
-        let hands_eval = room
-            .players_cards()
-            .into_iter()
-            .map(|(k, v)| Ok((k, self.evaluator.evaluate(v)?)))
-            .collect::<Result<HashMap<Uuid, Eval>>>()?;
+        outbox::add_to_snapshots(self.outbox.take_all(), &mut snapshots);
+        info!("Saving snapshots of {} rooms", snapshots.len());
+        self.room_info_repository.save_snapshots(snapshots).await
+    }
 
-        let mut winners: Vec<(u32, HashSet<Uuid>)> = Vec::with_capacity(room.pots.len());
-        for pot in room.pots.iter().rev() {
-            let player_hands: Vec<_> = pot
-                .players
+    /// Takes every room out of play for another server to carry on with. Players are disconnected
+    /// so they reconnect to it, their chips stay at the table.
+    #[instrument(skip_all)]
+    pub async fn export_rooms(&self, key: &HandKey) -> Result<Vec<RoomExport>> {
+        let room_ids: Vec<Uuid> = self
+            .room_repository
+            .rooms
+            .iter()
+            .map(|r| *r.key())
+            .collect();
+        let mut exports = Vec::with_capacity(room_ids.len());
+        for room_id in room_ids {
+            // removed first so no action can change a room after it is exported
+            let Some(room) = self.room_repository.remove(room_id).await else {
+                continue;
+            };
+            let export = match room.export(key) {
+                Ok(export) => export,
+                Err(e) => {
+                    error!(
+                        "Failed to export room {}, it stays in play: {:?}",
+                        room_id, e
+                    );
+                    self.room_repository.save(&room).await;
+                    self.room_repository.rooms.insert(room_id, room);
+                    continue;
+                }
+            };
+            room.players
                 .iter()
-                .map(|player_id| {
-                    (
-                        *player_id,
-                        *hands_eval.get(player_id).unwrap_or(&Eval::WORST),
-                    )
-                })
-                .collect();
-            let best_hands = Self::all_best_hands(&player_hands);
-            winners.push((pot.amount, best_hands));
+                .chain(room.player_joining_next_round.iter())
+                .chain(room.players_sitting_out.iter())
+                .filter(|p| p.is_connected && !room.is_bot(p.id))
+                .for_each(|p| {
+                    if let Err(e) = self.disconnect_socket(p.sid) {
+                        error!("Failed to disconnect player {}: {:?}", p.id, e);
+                    }
+                });
+            exports.push(export);
         }
-        Ok(GameResult {
-            hands_eval,
-            winners,
-        })
+        info!("Exported {} rooms", exports.len());
+        Ok(exports)
+    }
+
+    /// Puts rooms exported by another server into play in place of their empty tables here
+    #[instrument(skip_all)]
+    pub async fn import_rooms(
+        &self,
+        exports: Vec<RoomExport>,
+        secret: &SecretKey,
+    ) -> Result<usize> {
+        let mut rooms = Vec::with_capacity(exports.len());
+        for export in exports {
+            // the room may still be leased to the server it came from
+            let is_empty = self
+                .room_repository
+                .rooms
+                .get(&export.id)
+                .is_none_or(|room| room.player_count() == 0);
+            ensure!(is_empty, Error::InvalidRoomImport);
+            let room = Room::import(export, secret, &self.evaluator)
+                .tap_err(|e| error!("Failed to import a room: {:?}", e))
+                .map_err(|_| Error::InvalidRoomImport)?;
+            rooms.push(room);
+        }
+        let room_ids: Vec<Uuid> = rooms.iter().map(|room| room.id).collect();
+        let taken = self
+            .room_info_repository
+            .take_over_leases(room_ids.clone())
+            .await?;
+        ensure!(taken.len() == room_ids.len(), Error::InvalidRoomImport);
+        for room in rooms {
+            self.room_repository.save(&room).await;
+            let (room_id, running_out) = (room.id, room.is_running_out());
+            self.room_repository.rooms.insert(room_id, room);
+            if running_out {
+                self.schedule_run_out(room_id);
+            }
+        }
+        // seats nobody comes back for are given up by `release_expired_seats`
+        info!("Imported {} rooms", room_ids.len());
+        Ok(room_ids.len())
     }
 
-    fn all_best_hands(v: &[(Uuid, Eval)]) -> HashSet<Uuid> {
-        let mut largest = HashSet::new();
-        let mut best_hand = Eval::WORST;
-        for (player_id, hand) in v {
-            if hand.is_better_than(best_hand) {
-                largest.clear();
-                largest.insert(*player_id);
-                best_hand = *hand;
-            } else if hand.is_equal_to(best_hand) {
-                largest.insert(*player_id);
+    /// Cashes out the players who did not reconnect before their seat stopped being held
+    pub async fn release_expired_seats(&self) {
+        let now = Utc::now();
+        let expired: Vec<(Uuid, Uuid)> = self
+            .room_repository
+            .rooms
+            .iter()
+            .flat_map(|room| {
+                let room_id = room.id;
+                room.expired_seat_holds(now)
+                    .into_iter()
+                    .map(move |user_id| (room_id, user_id))
+            })
+            .collect();
+        for (room_id, user_id) in expired {
+            info!("User {} did not reconnect to room {}", user_id, room_id);
+            if let Err(e) = self.leave_player(user_id, Sid::default()).await {
+                error!("Failed to release the seat of user {}: {:?}", user_id, e);
             }
         }
-        largest
+    }
+
+    /// Keeps the seat and chips of a player whose connection dropped for the grace period,
+    /// without one they leave right away
+    #[instrument(skip(self))]
+    pub async fn disconnect_player(&self, user_id: Uuid, sid: Sid) -> Result<()> {
+        let Some(room_id) = self.seated_room(user_id) else {
+            return self.leave_player(user_id, sid).await;
+        };
+        let Some(mut room) = self.room_repository.get_mut_lock(room_id) else {
+            return self.leave_player(user_id, sid).await;
+        };
+        // a connection the player replaced since has nothing to give up
+        let is_replaced = room
+            .seated_player(user_id)
+            .is_some_and(|p| p.is_connected && p.sid != sid);
+        if is_replaced {
+            return Ok(());
+        }
+        let until = Utc::now() + self.reconnect_grace;
+        if self.reconnect_grace <= TimeDelta::zero() || !room.hold_seat(user_id, sid, until) {
+            drop(room);
+            return self.leave_player(user_id, sid).await;
+        }
+        info!(
+            "Holding the seat of user {} in room {} until {}",
+            user_id, room_id, until
+        );
+        self.remove_player_from_ws_room(room_id, sid);
+        self.service_action_required(ServiceRequiredAction::NoAction, room)
+            .await
+    }
+
+    pub async fn get_rooms(&self, currency: Option<ChipCurrency>) -> Result<Vec<RoomInfo>> {
+        let mut rooms = self.room_info_repository.get_all(currency).await?;
+        for room_info in rooms.iter_mut() {
+            room_info.hands_per_hour = self
+                .room_repository
+                .rooms
+                .get(&room_info.room_id)
+                .and_then(|room| room.pace.hands_per_hour());
+        }
+        Ok(rooms)
+    }
+
+    pub async fn get_room_details(&self, room_id: Uuid) -> Result<RoomDetails> {
+        let room_info = self
+            .room_info_repository
+            .get(room_id)
+            .await?
+            .wrap_err(Error::NoRoomFound)?;
+        let room = self
+            .room_repository
+            .get(room_id)
+            .wrap_err(Error::NoRoomFound)?;
+        Ok(room.details(room_info.is_private))
+    }
+
+    pub async fn get_hand_history(
+        &self,
+        room_id: Uuid,
+        query: HistoryQuery,
+    ) -> Result<HandHistoryPage> {
+        let page = query.page.unwrap_or_default();
+        let per_page = query
+            .per_page
+            .unwrap_or(DEFAULT_HISTORY_PAGE_SIZE)
+            .clamp(1, MAX_HISTORY_PAGE_SIZE);
+        // one extra row tells whether there is another page
+        let mut hands = self
+            .hand_history_repository
+            .get_by_room(room_id, per_page as i64 + 1, page as i64 * per_page as i64)
+            .await?;
+        let has_more = hands.len() > per_page as usize;
+        hands.truncate(per_page as usize);
+        Ok(HandHistoryPage {
+            hands,
+            page,
+            has_more,
+        })
+    }
+
+    pub async fn get_hand(&self, room_id: Uuid, hand_id: Uuid) -> Result<HandHistory> {
+        self.hand_history_repository
+            .get(room_id, hand_id)
+            .await?
+            .wrap_err(Error::HandNotFound)
+    }
+
+    // opens a new table, private when a password is given
+    pub async fn open_room(
+        &self,
+        created_by: Uuid,
+        password: Option<String>,
+        bot_seats: usize,
+        config: RoomConfig,
+        closes_at: Option<DateTime<Utc>>,
+    ) -> Result<RoomInfo> {
+        let mut room = Room::new();
+        room.rake = self.rake;
+        room.config = config;
+        room.closes_at = closes_at;
+        if let Some(seed) = self.deck_seed {
+            room.reseed(seed);
+        }
+        room.set_bot_seats(bot_seats)?;
+        let password_hash = password
+            .map(|password| hash(password, DEFAULT_COST))
+            .transpose()?;
+        let room_info = self
+            .room_info_repository
+            .create(created_by, password_hash, bot_seats as i32, config, closes_at)
+            .await?;
+        room.id = room_info.room_id;
+        self.room_repository.rooms.insert(room.id, room);
+        self.publish_lobby_update(LobbyUpdate::Room(room_info.clone()))
+            .await;
+        Ok(room_info)
+    }
+
+    /// Humans holding a seat in any room, sitting out or not
+    pub fn seated_players(&self) -> usize {
+        self.room_repository
+            .rooms
+            .iter()
+            .map(|room| {
+                room.players
+                    .iter()
+                    .chain(room.players_sitting_out.iter())
+                    .filter(|p| !room.is_bot(p.id))
+                    .count()
+            })
+            .sum()
+    }
+
+    /// Rooms with a hand being played
+    pub fn rooms_in_play(&self) -> usize {
+        self.room_repository
+            .rooms
+            .iter()
+            .filter(|room| room.stage != Stage::NotEnoughPlayers)
+            .count()
+    }
+
+    pub fn is_seated(&self, room_id: Uuid, user_id: Uuid) -> bool {
+        self.room_repository.rooms.get(&room_id).is_some_and(|room| {
+            room.players
+                .iter()
+                .chain(room.players_sitting_out.iter())
+                .any(|p| p.id == user_id)
+        })
     }
 
     #[cfg(test)]
@@ -119,6 +465,7 @@ impl GameService {
     }
 
     // this function takes action from a player
+    #[instrument(skip(self))]
     pub async fn take_action(
         &self,
         room_id: Uuid,
@@ -136,6 +483,15 @@ impl GameService {
             .wrap_err(Error::InvalidRoomId)
     }
 
+    // the lobby namespace has no rooms, every socket on it sees every update
+    async fn publish_lobby_update(&self, update: LobbyUpdate) {
+        if let Some(operator) = self.io.of("/lobby") {
+            if let Err(e) = operator.emit(ServiceEvent::Lobby, &update).await {
+                error!("Error occurred when emitting lobby update: {:?}", e);
+            }
+        }
+    }
+
     async fn emit_to_room<T: ?Sized + Serialize>(
         &self,
         room: String,
@@ -159,6 +515,45 @@ impl GameService {
         }
     }
 
+    /// Sends hole cards to a socket, sealed if it registered a key when connecting.
+    /// Sockets that asked for hints also get what the cards make with the board.
+    fn emit_hand(&self, sid: Sid, cards: [Card; 2], board: &[Card]) {
+        if let Some(operator) = self.io.of("/game") {
+            if let Some(socket) = operator.get_socket(sid) {
+                let hand = PlayerHand::from(cards);
+                if socket.extensions.get::<HandStrengthHints>().is_some() {
+                    let strength = HandStrength::of(&self.evaluator, cards, board)
+                        .tap_err(|e| {
+                            error!("Error occurred when evaluating hand strength: {:?}", e)
+                        })
+                        .ok();
+                    let dealt = DealtHand {
+                        cards: hand,
+                        strength,
+                    };
+                    emit_sealable_hand(&socket, &dealt);
+                } else {
+                    emit_sealable_hand(&socket, &hand);
+                }
+            }
+        }
+    }
+
+    // the hints follow the board as it is dealt
+    fn emit_hand_strength(&self, hands: Vec<(Sid, [Card; 2])>, board: &[Card]) {
+        let Some(operator) = self.io.of("/game") else {
+            return;
+        };
+        for (sid, cards) in hands {
+            let wants_hints = operator
+                .get_socket(sid)
+                .is_some_and(|socket| socket.extensions.get::<HandStrengthHints>().is_some());
+            if wants_hints {
+                self.emit_hand(sid, cards, board);
+            }
+        }
+    }
+
     fn join_player_to_ws_room(&self, room_id: Uuid, sid: Sid) {
         if let Some(operator) = self.io.of("/game") {
             if let Some(socket) = operator.get_socket(sid) {
@@ -174,230 +569,1069 @@ impl GameService {
                 socket.disconnect()?
             }
         }
-        Ok(())
+        Ok(())
+    }
+
+    fn remove_player_from_ws_room(&self, room_id: Uuid, sid: Sid) {
+        if let Some(operator) = self.io.of("/game") {
+            if let Some(socket) = operator.get_socket(sid) {
+                socket.leave(room_id.to_string());
+            }
+        }
+    }
+
+    #[instrument(skip(self, password))]
+    pub async fn join_player(
+        &self,
+        room_id: Uuid,
+        user_id: Uuid,
+        buy_in: i64,
+        password: Option<String>,
+        seat: Option<usize>,
+        sid: Sid,
+    ) -> Result<Room> {
+        // a seat carried over from another server is taken back without buying in again
+        let reclaimed = self
+            .room_repository
+            .get_mut_lock(room_id)
+            .is_some_and(|mut room| room.reclaim_seat(user_id, sid));
+        if reclaimed {
+            info!("User {} reclaimed their seat in room {}", user_id, room_id);
+            self.join_player_to_ws_room(room_id, sid);
+            let room = self
+                .room_repository
+                .get_mut_lock(room_id)
+                .wrap_err(Error::InvalidRoomId)?;
+            self.service_action_required(ServiceRequiredAction::NoAction, room)
+                .await?;
+            let room = self
+                .room_repository
+                .get(room_id)
+                .wrap_err(Error::InvalidRoomId)?;
+            self.emit_seated(&room, user_id, sid);
+            return Ok(room);
+        }
+        let (room_info, tx) = self
+            .room_info_repository
+            .get_room_for_update(room_id)
+            .await
+            .tap_err(|e| {
+                error!(
+                    "error occurred when getting from room_info for room : {:?}. Error: {:?}",
+                    room_id, e
+                )
+            })?;
+        if !self.room_repository.rooms.contains_key(&room_id) {
+            tx.rollback().await?;
+            match room_info.owner_url {
+                Some(url) => bail!(Error::RoomServedElsewhere(url)),
+                None => bail!(Error::InvalidRoomId),
+            }
+        }
+        if let Some(password_hash) = &room_info.password_hash {
+            let is_valid = match password {
+                Some(password) => verify(password, password_hash)?,
+                None => false,
+            };
+            if !is_valid {
+                tx.rollback().await?;
+                bail!(Error::InvalidRoomPassword);
+            }
+        }
+        let seated = self
+            .buy_in_and_seat(room_info, tx, user_id, buy_in, seat, sid)
+            .await;
+        match seated {
+            // a full table queues the player, unless they asked for a particular seat
+            Err(e) if seat.is_none() && is_room_full(&e) => {
+                self.queue_player(room_id, user_id, buy_in, sid).await
+            }
+            seated => seated,
+        }
+    }
+
+    // takes the buy-in from the player's balance and seats them, under the room's row lock
+    async fn buy_in_and_seat(
+        &self,
+        mut room_info: RoomInfo,
+        tx: sqlx::Transaction<'static, sqlx::Postgres>,
+        user_id: Uuid,
+        buy_in: i64,
+        seat: Option<usize>,
+        sid: Sid,
+    ) -> Result<Room> {
+        let room_id = room_info.room_id;
+        // the room is only checked here, other joins wait on the row lock rather than on it
+        let player_count = {
+            let room = self
+                .room_repository
+                .get_mut_lock(room_id)
+                .wrap_err(Error::InvalidRoomId)?;
+            room.check_joinable()?;
+            if let Some(seat) = seat {
+                room.check_seat_free(seat)?;
+            }
+            room.player_count() + 1
+        };
+        let user = self
+            .user_repository
+            .get(user_id)
+            .await?
+            .wrap_err("User not found")?;
+        ensure!(buy_in > 0, Error::InvalidBuyIn);
+        ensure!(
+            buy_in <= user.balance_in(room_info.currency),
+            Error::InsufficientBalance
+        );
+
+        // nothing changes at the table until the buy-in is committed
+        let committed = self
+            .room_info_repository
+            .commit_join(
+                tx,
+                room_id,
+                user_id,
+                buy_in,
+                player_count as i32,
+                self.settlement == Settlement::PerHand,
+            )
+            .await;
+        match committed {
+            Ok(Some(_)) => {}
+            // the balance is checked again as it is debited, it may have changed since
+            Ok(None) => bail!(Error::InsufficientBalance),
+            Err(e) => {
+                // the commit may have gone through before the connection dropped
+                self.outbox.stage(StagedChange::RevertBuyIn {
+                    room_id,
+                    user_id,
+                    chips: buy_in,
+                });
+                return Err(e);
+            }
+        }
+        let player = Player::from_user(&user, buy_in as u32, sid);
+        let seated = self
+            .room_repository
+            .get_mut_lock(room_id)
+            .wrap_err(Error::InvalidRoomId)
+            .and_then(|mut room| {
+                let action_required = room.join_player_at(player, seat)?;
+                Ok((action_required, room))
+            });
+        let (action_required, room) = match seated {
+            Ok(seated) => seated,
+            Err(e) => {
+                self.outbox.stage(StagedChange::RevertBuyIn {
+                    room_id,
+                    user_id,
+                    chips: buy_in,
+                });
+                return Err(e);
+            }
+        };
+        self.join_player_to_ws_room(room_id, sid);
+        room_info.player_count = player_count as i32;
+        room_info.hands_per_hour = room.pace.hands_per_hour();
+        self.service_action_required(action_required, room).await?;
+        self.events.publish(
+            room_id,
+            RoomEvent::PlayerJoined {
+                player_id: user_id,
+                name: user.name,
+            },
+        );
+        self.publish_lobby_update(LobbyUpdate::Room(room_info))
+            .await;
+        let room = self
+            .room_repository
+            .get(room_id)
+            .wrap_err(Error::InvalidRoomId)?;
+        self.emit_seated(&room, user_id, sid);
+        Ok(room)
+    }
+
+    // the buy-in is only taken once a seat frees up, the balance is checked for it now
+    async fn queue_player(
+        &self,
+        room_id: Uuid,
+        user_id: Uuid,
+        buy_in: i64,
+        sid: Sid,
+    ) -> Result<Room> {
+        let currency = self
+            .room_repository
+            .rooms
+            .get(&room_id)
+            .map(|room| room.config.currency)
+            .wrap_err(Error::InvalidRoomId)?;
+        let user = self
+            .user_repository
+            .get(user_id)
+            .await?
+            .wrap_err("User not found")?;
+        ensure!(buy_in > 0, Error::InvalidBuyIn);
+        ensure!(
+            buy_in <= user.balance_in(currency),
+            Error::InsufficientBalance
+        );
+        let mut room = self
+            .room_repository
+            .get_mut_lock(room_id)
+            .wrap_err(Error::InvalidRoomId)?;
+        let position = room.queue_player(user_id, buy_in, sid)?;
+        info!(
+            "User {} is number {} on the waiting list of room {}",
+            user_id, position, room_id
+        );
+        self.service_action_required(ServiceRequiredAction::NoAction, room)
+            .await?;
+        let waitlisted = Waitlisted { room_id, position };
+        self.emit_to_socket(sid, ServiceEvent::Waitlisted, &Timestamped::new(waitlisted));
+        self.room_repository
+            .get(room_id)
+            .wrap_err(Error::InvalidRoomId)
+    }
+
+    /// Takes the player off the waiting list of every room
+    pub async fn leave_waiting_lists(&self, user_id: Uuid) {
+        let room_ids: Vec<Uuid> = self
+            .room_repository
+            .rooms
+            .iter_mut()
+            .filter_map(|mut room| room.leave_waiting_list(user_id).then_some(room.id))
+            .collect();
+        for room_id in room_ids {
+            info!("User {} left the waiting list of room {}", user_id, room_id);
+            if let Some(room) = self.room_repository.get(room_id) {
+                self.room_repository.save(&room).await;
+                self.emit_waiting_positions(&room);
+            }
+        }
+    }
+
+    // everyone still queued hears their new place
+    fn emit_waiting_positions(&self, room: &Room) {
+        for (index, waiting) in room.waiting_list.iter().enumerate() {
+            let waitlisted = Waitlisted {
+                room_id: room.id,
+                position: index + 1,
+            };
+            self.emit_to_socket(
+                waiting.sid,
+                ServiceEvent::Waitlisted,
+                &Timestamped::new(waitlisted),
+            );
+        }
+    }
+
+    // a seat came free at a table with a queue, so the first player still connected gets it
+    fn schedule_seating(&self, room_id: Uuid) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = service.seat_from_waiting_list(room_id).await {
+                error!("Error occurred when seating from the waiting list: {:?}", e);
+            }
+        });
+    }
+
+    async fn seat_from_waiting_list(&self, room_id: Uuid) -> Result<()> {
+        loop {
+            let Some(waiting) = self
+                .room_repository
+                .get_mut_lock(room_id)
+                .and_then(|mut room| room.next_waiting())
+            else {
+                return Ok(());
+            };
+            if !self.is_connected(waiting.sid) {
+                info!(
+                    "User {} left the waiting list of room {} with their connection",
+                    waiting.user_id, room_id
+                );
+                continue;
+            }
+            let (room_info, tx) = self
+                .room_info_repository
+                .get_room_for_update(room_id)
+                .await?;
+            let seated = self
+                .buy_in_and_seat(
+                    room_info,
+                    tx,
+                    waiting.user_id,
+                    waiting.buy_in,
+                    None,
+                    waiting.sid,
+                )
+                .await;
+            match seated {
+                Ok(_) => info!(
+                    "User {} was seated from the waiting list of room {}",
+                    waiting.user_id, room_id
+                ),
+                // someone else took the seat first, the player keeps their place
+                Err(e) if is_room_full(&e) => {
+                    if let Some(mut room) = self.room_repository.get_mut_lock(room_id) {
+                        room.waiting_list.push_front(waiting);
+                    }
+                    return Ok(());
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to seat user {} from the waiting list: {:?}",
+                        waiting.user_id, e
+                    );
+                    if let Ok(error) = e.downcast::<Error>() {
+                        let message = error.to_string();
+                        self.emit_to_socket(waiting.sid, ServiceEvent::ServiceError, &message);
+                    }
+                }
+            }
+            if let Some(room) = self.room_repository.get(room_id) {
+                self.room_repository.save(&room).await;
+                self.emit_waiting_positions(&room);
+            }
+            return Ok(());
+        }
+    }
+
+    fn is_connected(&self, sid: Sid) -> bool {
+        self.io
+            .of("/game")
+            .is_some_and(|operator| operator.get_socket(sid).is_some())
+    }
+
+    // the joining socket hears where it sits even if it is not dealt in yet
+    fn emit_seated(&self, room: &Room, user_id: Uuid, sid: Sid) {
+        if let Some(seated) = room.seating(user_id) {
+            self.emit_to_socket(sid, ServiceEvent::Seated, &Timestamped::new(seated));
+        }
+    }
+
+    // tells a player rejoining mid-hand what happened while they were away
+    pub fn send_resume_summary(
+        &self,
+        room_id: Uuid,
+        user_id: Uuid,
+        last_seen_seq: Option<u64>,
+        sid: Sid,
+    ) -> Result<()> {
+        let room = self
+            .room_repository
+            .get(room_id)
+            .wrap_err(Error::InvalidRoomId)?;
+        if let Some(summary) = room.resume_summary(user_id, last_seen_seq) {
+            self.emit_to_socket(sid, ServiceEvent::Resume, &Timestamped::new(summary));
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub async fn create_user(&self, name: String, balance: i64) -> Result<User> {
+        self.user_repository.create_user(name, balance).await
+    }
+
+    #[instrument(skip(self))]
+    pub async fn leave_player(&self, user_id: Uuid, sid: Sid) -> Result<()> {
+        let room_id = match self.user_repository.get(user_id).await {
+            Ok(user) => user.and_then(|user| user.current_room),
+            Err(e) => {
+                // the player still has to be let go, their table is known in memory
+                error!("Error occurred when getting user {}: {:?}", user_id, e);
+                self.seated_room(user_id)
+            }
+        };
+        let room_id = match room_id {
+            Some(room_id) => room_id,
+            None => {
+                info!("User {} not in any room", user_id);
+                return Ok(());
+            }
+        };
+        // the room row is locked before the table, in the same order as joining
+        let for_update = self.room_info_repository.get_room_for_update(room_id).await;
+        let mut room = self
+            .room_repository
+            .get_mut_lock(room_id)
+            .wrap_err(Error::InvalidRoomId)?;
+        // chips bought for the next hand are paid back with the stack
+        let (chips, is_counted) = room.seated_player(user_id).map_or((0, false), |p| {
+            (
+                p.chips + room.pending_top_up(user_id),
+                p.is_connected && p.chips > 0,
+            )
+        });
+        let player_count = room.player_count() - usize::from(is_counted);
+        room.leave_player(user_id);
+        self.remove_player_from_ws_room(room_id, sid);
+        let hands_per_hour = room.pace.hands_per_hour();
+        // the table moves on without the player, the row lock keeps their seat from being
+        // taken until they are cashed out
+        let serviced = self
+            .service_action_required(ServiceRequiredAction::NoAction, room)
+            .await;
+        let committed = match for_update {
+            Ok((room_info, tx)) => self
+                .room_info_repository
+                .commit_leave(tx, room_id, user_id, chips as i64, player_count as i32)
+                .await
+                .map(|_| room_info),
+            Err(e) => Err(e),
+        };
+        let room_info = match committed {
+            Ok(room_info) => Some(room_info),
+            Err(e) => {
+                error!("Error occurred when cashing out user {}: {:?}", user_id, e);
+                self.outbox.stage(StagedChange::CashOut {
+                    room_id,
+                    user_id,
+                    chips: chips as i64,
+                });
+                None
+            }
+        };
+        serviced?;
+        self.events
+            .publish(room_id, RoomEvent::PlayerLeft { player_id: user_id });
+        if let Some(mut room_info) = room_info {
+            room_info.player_count = player_count as i32;
+            room_info.hands_per_hour = hands_per_hour;
+            self.publish_lobby_update(LobbyUpdate::Room(room_info))
+                .await;
+        }
+        Ok(())
+    }
+
+    // the room the player sits at in memory, whether or not they already left it
+    fn seated_room(&self, user_id: Uuid) -> Option<Uuid> {
+        self.room_repository
+            .rooms
+            .iter()
+            .find(|room| room.seated_player(user_id).is_some())
+            .map(|room| room.id)
+    }
+
+    // unlike is_seated, counts players waiting for the next hand but not those who left
+    fn holds_seat(&self, room_id: Uuid, user_id: Uuid) -> bool {
+        self.room_repository
+            .rooms
+            .get(&room_id)
+            .and_then(|room| room.seated_player(user_id).map(|p| p.is_connected))
+            .unwrap_or(false)
+    }
+
+    /// Tries every staged change again, keeping the ones that still fail.
+    /// Returns how many are left.
+    pub async fn replay_staged(&self) -> usize {
+        for change in self.outbox.take_all() {
+            if let Err(e) = self.replay(change).await {
+                debug!("Staged {:?} failed again: {:?}", change, e);
+                self.outbox.stage(change);
+            }
+        }
+        self.outbox.len()
+    }
+
+    async fn replay(&self, change: StagedChange) -> Result<()> {
+        let (room_id, user_id, chips) = change.seat();
+        // the player got a seat after all, that buy-in is theirs to play
+        if matches!(change, StagedChange::RevertBuyIn { .. }) && self.holds_seat(room_id, user_id) {
+            return Ok(());
+        }
+        let (_, tx) = self
+            .room_info_repository
+            .get_room_for_update(room_id)
+            .await?;
+        let player_count = self
+            .room_repository
+            .rooms
+            .get(&room_id)
+            .map_or(0, |room| room.player_count());
+        let paid = self
+            .room_info_repository
+            .commit_leave(tx, room_id, user_id, chips, player_count as i32)
+            .await?;
+        if paid {
+            info!("Applied staged {:?}", change);
+        }
+        Ok(())
+    }
+
+    // plays check or fold for a player whose turn clock ran out, or folds one who left
+    #[instrument(skip(self))]
+    pub async fn expire_turn(&self, room_id: Uuid) -> Result<()> {
+        let mut room = self
+            .room_repository
+            .get_mut_lock(room_id)
+            .wrap_err(Error::InvalidRoomId)?;
+        // the player may have acted since the timer was checked
+        let Some(player_id) = room.player_in_turn.filter(|_| room.is_turn_expired()) else {
+            return Ok(());
+        };
+        info!("Player {} ran out of time", player_id);
+        let action_required = room.take_timeout_action(player_id)?;
+        let kicked = room
+            .seated_player(player_id)
+            .filter(|_| room.should_kick(player_id))
+            .map(|p| (p.sid, room.timeout_streaks[&player_id]));
+        self.service_action_required(action_required, room).await?;
+        if let Some((sid, timed_out_hands)) = kicked {
+            self.kick_player(room_id, player_id, sid, timed_out_hands)
+                .await?;
+        }
+        Ok(())
+    }
+
+    // cashes out a player who stopped playing and tells them why they were let go
+    async fn kick_player(
+        &self,
+        room_id: Uuid,
+        player_id: Uuid,
+        sid: Sid,
+        timed_out_hands: u32,
+    ) -> Result<()> {
+        let chips = self
+            .room_repository
+            .rooms
+            .get(&room_id)
+            .and_then(|room| {
+                let chips = room.seated_player(player_id)?.chips;
+                Some(chips + room.pending_top_up(player_id))
+            })
+            .unwrap_or_default();
+        info!(
+            "Kicking player {} after {} hands without acting",
+            player_id, timed_out_hands
+        );
+        self.leave_player(player_id, sid).await?;
+        let kicked = Kicked {
+            room_id,
+            timed_out_hands,
+            chips,
+        };
+        self.emit_to_socket(sid, ServiceEvent::Kicked, &Timestamped::new(kicked));
+        Ok(())
+    }
+
+    // a player is done looking at the results of the last hand
+    #[instrument(skip(self))]
+    pub async fn player_ready(&self, room_id: Uuid, player_id: Uuid) -> Result<()> {
+        let mut room = self
+            .room_repository
+            .get_mut_lock(room_id)
+            .wrap_err(Error::InvalidRoomId)?;
+        if room.mark_ready(player_id) {
+            self.finish_showdown(room).await?;
+        }
+        Ok(())
+    }
+
+    /// Buys more chips for a seated player, they reach the stack as the next hand is dealt
+    #[instrument(skip(self))]
+    pub async fn top_up(&self, room_id: Uuid, user_id: Uuid, amount: u32) -> Result<()> {
+        // the room row stays locked until the chips are at the table, so the player cannot leave
+        let (_, tx) = self
+            .room_info_repository
+            .get_room_for_update(room_id)
+            .await?;
+        self.room_repository
+            .get_mut_lock(room_id)
+            .wrap_err(Error::InvalidRoomId)?
+            .check_top_up(user_id, amount)?;
+        let paid = self
+            .room_info_repository
+            .commit_top_up(
+                room_id,
+                user_id,
+                amount as i64,
+                self.settlement == Settlement::PerHand,
+            )
+            .await?;
+        ensure!(paid.is_some(), Error::InsufficientBalance);
+        info!(
+            "User {} topped up {} chips in room {}",
+            user_id, amount, room_id
+        );
+        let mut room = self
+            .room_repository
+            .get_mut_lock(room_id)
+            .wrap_err(Error::InvalidRoomId)?;
+        let action_required = room.add_top_up(user_id, amount)?;
+        let serviced = self.service_action_required(action_required, room).await;
+        tx.rollback().await?;
+        serviced
+    }
+
+    #[instrument(skip(self))]
+    pub async fn sit_out(&self, room_id: Uuid, player_id: Uuid) -> Result<Room> {
+        if let Some(mut room) = self.room_repository.get_mut_lock(room_id) {
+            let action_required = room.sit_out(player_id)?;
+            self.service_action_required(action_required, room).await?;
+        } else {
+            bail!(Error::InvalidRoomId);
+        }
+        self.room_repository
+            .get(room_id)
+            .wrap_err(Error::InvalidRoomId)
+    }
+
+    /// Deals from the scenario from the next hand on, or shuffles again when it is None
+    pub async fn set_scenario(&self, room_id: Uuid, scenario: Option<Scenario>) -> Result<Room> {
+        if let Some(scenario) = &scenario {
+            let room_info = self
+                .room_info_repository
+                .get(room_id)
+                .await?
+                .wrap_err(Error::InvalidRoomId)?;
+            scenario.validate(room_info.max_players as usize)?;
+            ensure!(room_info.password_hash.is_some(), Error::ScenarioNeedsPrivateRoom);
+        }
+        if let Some(mut room) = self.room_repository.get_mut_lock(room_id) {
+            info!(
+                "Room {} now deals {}",
+                room_id,
+                if scenario.is_some() { "a scenario" } else { "at random" }
+            );
+            room.scenario = scenario;
+            // everyone at the table sees the change of deck straight away
+            self.service_action_required(ServiceRequiredAction::NoAction, room)
+                .await?;
+        } else {
+            bail!(Error::InvalidRoomId);
+        }
+        self.room_repository
+            .get(room_id)
+            .wrap_err(Error::InvalidRoomId)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn sit_in(&self, room_id: Uuid, player_id: Uuid) -> Result<Room> {
+        if let Some(mut room) = self.room_repository.get_mut_lock(room_id) {
+            let action_required = room.sit_in(player_id)?;
+            self.service_action_required(action_required, room).await?;
+        } else {
+            bail!(Error::InvalidRoomId);
+        }
+        self.room_repository
+            .get(room_id)
+            .wrap_err(Error::InvalidRoomId)
     }
 
-    fn remove_player_from_ws_room(&self, room_id: Uuid, sid: Sid) {
-        if let Some(operator) = self.io.of("/game") {
-            if let Some(socket) = operator.get_socket(sid) {
-                socket.leave(room_id.to_string());
-            }
+    // records an action to play blind once the action reaches the player on the next street
+    #[instrument(skip(self))]
+    pub async fn declare_dark_action(
+        &self,
+        room_id: Uuid,
+        player_id: Uuid,
+        action: DarkAction,
+    ) -> Result<Room> {
+        if let Some(mut room) = self.room_repository.get_mut_lock(room_id) {
+            room.declare_dark_action(player_id, action)?;
+            self.service_action_required(ServiceRequiredAction::NoAction, room)
+                .await?;
+        } else {
+            bail!(Error::InvalidRoomId);
         }
+        self.room_repository
+            .get(room_id)
+            .wrap_err(Error::InvalidRoomId)
     }
 
-    pub async fn join_player(
+    // sets what to play for the player once their turn comes on this street
+    #[instrument(skip(self))]
+    pub async fn set_pre_action(
         &self,
         room_id: Uuid,
-        user_id: Uuid,
-        buy_in: i64,
-        sid: Sid,
+        player_id: Uuid,
+        pre_action: Option<PreAction>,
     ) -> Result<Room> {
-        let (_, tx) = self
-            .room_info_repository
-            .get_room_for_update(room_id)
-            .await
-            .tap_err(|e| {
-                error!(
-                    "error occurred when getting from room_info for room : {:?}. Error: {:?}",
-                    room_id, e
-                )
-            })?;
-        let update_result = self
-            .update_game_state_and_user(room_id, user_id, buy_in, sid)
-            .await;
-        let player_count = match update_result {
-            Ok(player_count) => player_count,
-            Err(e) => {
-                tx.rollback().await?;
-                error!("error occurred when updating game state and user: {:?}", e);
-                return Err(e);
-            }
-        };
-        self.room_info_repository
-            .update(room_id, player_count as i32, tx)
-            .await?;
+        if let Some(mut room) = self.room_repository.get_mut_lock(room_id) {
+            room.set_pre_action(player_id, pre_action)?;
+            self.service_action_required(ServiceRequiredAction::NoAction, room)
+                .await?;
+        } else {
+            bail!(Error::InvalidRoomId);
+        }
         self.room_repository
             .get(room_id)
             .wrap_err(Error::InvalidRoomId)
     }
 
-    async fn update_game_state_and_user(
+    // relays a chat message to everyone in the room
+    pub async fn send_chat(
         &self,
         room_id: Uuid,
-        user_id: Uuid,
-        buy_in: i64,
-        sid: Sid,
-    ) -> Result<usize> {
+        player_id: Uuid,
+        message: String,
+    ) -> Result<ChatMessage> {
+        let message = message.trim().to_string();
+        ensure!(
+            (1..=MAX_CHAT_MESSAGE_LENGTH).contains(&message.chars().count()),
+            Error::InvalidChatMessage
+        );
+        let room = self
+            .room_repository
+            .get(room_id)
+            .wrap_err(Error::InvalidRoomId)?;
+        let sender_name = room
+            .players
+            .iter()
+            .chain(room.player_joining_next_round.iter())
+            .chain(room.players_sitting_out.iter())
+            .find(|p| p.id == player_id)
+            .map(|p| p.name.clone())
+            .wrap_err(Error::NotInRoom)?;
+        ensure!(self.chat_limiter.try_send(player_id), Error::ChatRateLimited);
+        let chat = ChatMessage {
+            sender_id: player_id,
+            sender_name,
+            message,
+            sent_at: Utc::now(),
+        };
+        self.emit_to_room(room_id.to_string(), ServiceEvent::Chat, &chat)
+            .await;
+        Ok(chat)
+    }
+
+    // deals the next hand once the results have been up long enough
+    #[instrument(skip(self))]
+    pub async fn end_showdown(&self, room_id: Uuid) -> Result<()> {
+        let room = self
+            .room_repository
+            .get_mut_lock(room_id)
+            .wrap_err(Error::InvalidRoomId)?;
+        // everyone may have been ready before the timer got here
+        if room.is_showdown_expired() {
+            self.finish_showdown(room).await?;
+        }
+        Ok(())
+    }
+
+    // a player who lost at showdown reveals or mucks their cards
+    #[instrument(skip(self))]
+    pub async fn show_cards(&self, room_id: Uuid, player_id: Uuid, show: bool) -> Result<()> {
         let mut room = self
             .room_repository
             .get_mut_lock(room_id)
             .wrap_err(Error::InvalidRoomId)?;
-        let mut user = self
-            .user_repository
-            .get(user_id)
-            .await?
-            .wrap_err("User not found")?;
-        ensure!(buy_in <= user.balance, Error::InsufficientBalance);
+        if room.show_or_muck(player_id, show)? {
+            let game_state = SharedGameState::from_room(room.clone(), false);
+            let pending = PendingBroadcast::new(&room, game_state);
+            self.commit(room, vec![GameEvent::StateChanged(Box::new(pending))])
+                .await;
+        }
+        Ok(())
+    }
 
-        let action_required = room.join_player(Player::from_user(&user, buy_in as u32, sid))?;
-        let player_count = room.player_count();
-        self.join_player_to_ws_room(room_id, sid);
-        self.service_action_required(action_required, room).await?;
-        user.balance -= buy_in;
-        self.user_repository
-            .update_balance_and_room(user_id, user.balance, room_id)
-            .await?;
-        Ok(player_count)
+    async fn finish_showdown(&self, mut room: RefMut<'_, Uuid, Room>) -> Result<()> {
+        let action_required = room.end_showdown_phase()?;
+        // the results of the last hand are cleared before the next one is dealt
+        let mut events = vec![GameEvent::Outcome(Outcome::default())];
+        events.extend(engine::advance(
+            &mut room,
+            action_required,
+            &self.evaluator,
+        )?);
+        self.commit(room, events).await;
+        Ok(())
     }
 
-    #[cfg(test)]
-    pub async fn create_user(&self, name: String, balance: i64) -> Result<User> {
-        self.user_repository.create_user(name, balance).await
+    // notifies the table once that the room is about to close
+    pub async fn warn_room_closing(&self, room_id: Uuid) -> Result<()> {
+        let mut room = self
+            .room_repository
+            .get_mut_lock(room_id)
+            .wrap_err(Error::InvalidRoomId)?;
+        if room.closing_notice_sent {
+            return Ok(());
+        }
+        let closes_at = room.closes_at.wrap_err("Room has no closing time")?;
+        room.closing_notice_sent = true;
+        drop(room);
+        self.emit_to_room(
+            room_id.to_string(),
+            ServiceEvent::RoomClosing,
+            &Timestamped::new(RoomClosing::Scheduled(closes_at)),
+        )
+        .await;
+        Ok(())
     }
 
-    pub async fn leave_player(&self, user_id: Uuid, sid: Sid) -> Result<()> {
-        let room_id = self
-            .user_repository
-            .get(user_id)
-            .await?
-            .and_then(|user| user.current_room);
-        let room_id = match room_id {
-            Some(room_id) => room_id,
-            None => {
-                info!("User {} not in any room", user_id);
-                return Ok(());
-            }
-        };
+    // cashes out every player still seated and removes the room from play
+    #[instrument(skip(self))]
+    pub async fn close_room(&self, room_id: Uuid) -> Result<()> {
+        self.close(room_id, false).await
+    }
+
+    /// Closes the room right away, a hand in progress is called off and every player
+    /// gets back the stack they started it with
+    #[instrument(skip(self))]
+    pub async fn force_close_room(&self, room_id: Uuid) -> Result<()> {
+        self.close(room_id, true).await
+    }
+
+    async fn close(&self, room_id: Uuid, forced: bool) -> Result<()> {
         let (_, tx) = self
             .room_info_repository
             .get_room_for_update(room_id)
             .await?;
-
-        let player_count = self
-            .leave_player_and_update_player(user_id, room_id, sid)
-            .await;
-        let player_count = match player_count {
-            Ok(player_count) => player_count,
-            Err(e) => {
+        let (players, seats) = {
+            let mut room = self
+                .room_repository
+                .get_mut_lock(room_id)
+                .wrap_err(Error::InvalidRoomId)?;
+            if !forced && !room.can_close() {
+                drop(room);
                 tx.rollback().await?;
-                return Err(e);
+                bail!("Room {} cannot be closed yet", room_id);
             }
+            // counted the same way as on shutdown, so chips in the pot go back to their owners
+            let seats = room.snapshot().seats;
+            (room.cash_out_players(), seats)
         };
-
-        self.room_info_repository
-            .update(room_id, player_count as i32, tx)
-            .await?;
+        self.emit_to_room(
+            room_id.to_string(),
+            ServiceEvent::RoomClosing,
+            &Timestamped::new(RoomClosing::Closed),
+        )
+        .await;
+        for seat in seats {
+            let cashed_out = self
+                .user_repository
+                .remove_player_and_reimburse_chips(seat.player_id, seat.chips as i64)
+                .await;
+            if let Err(e) = cashed_out {
+                error!("Failed to cash out player {}: {:?}", seat.player_id, e);
+                self.outbox.stage(StagedChange::CashOut {
+                    room_id,
+                    user_id: seat.player_id,
+                    chips: seat.chips as i64,
+                });
+            }
+        }
+        for player in players {
+            self.remove_player_from_ws_room(room_id, player.sid);
+        }
+        self.room_repository.remove(room_id).await;
+        self.room_info_repository.mark_closed(room_id, tx).await?;
+        self.publish_lobby_update(LobbyUpdate::Closed { room_id })
+            .await;
+        info!("Room {} closed", room_id);
         Ok(())
     }
 
-    async fn leave_player_and_update_player(
-        &self,
-        user_id: Uuid,
-        room_id: Uuid,
-        sid: Sid,
-    ) -> Result<usize> {
-        let mut room = self
-            .room_repository
-            .get_mut_lock(room_id)
-            .wrap_err(Error::InvalidRoomId)?;
-        let player_chips = room.leave_player(user_id);
-        self.user_repository
-            .remove_player_and_reimburse_chips(user_id, player_chips as i64)
-            .await?;
-        let player_count = room.player_count();
-        self.remove_player_from_ws_room(room_id, sid);
-        self.service_action_required(ServiceRequiredAction::NoAction, room)
-            .await?;
-        Ok(player_count)
-    }
-
-    // this function takes the ServiceRequiredAction enum and perform the corresponding action
+    // every change to a room ends up here, so it is written through to the store after it
     async fn service_action_required(
         &self,
         action: ServiceRequiredAction,
         mut room: RefMut<'_, Uuid, Room>,
     ) -> Result<()> {
-        let room_id = &room.id.to_string();
+        let events = engine::advance(&mut room, action, &self.evaluator)?;
+        self.commit(room, events).await;
+        Ok(())
+    }
 
-        match action {
-            ServiceRequiredAction::NoAction => {
-                // emit game state
-                let game_state = SharedGameState::from_room(room.clone(), false);
-                self.emit_to_room(room_id.to_owned(), ServiceEvent::Room, &Timestamped::new(game_state))
-                    .await;
-                Ok(())
+    // takes what is left to do from the room, then lets go of it before sending or writing anything
+    async fn commit(&self, mut room: RefMut<'_, Uuid, Room>, events: Vec<GameEvent>) {
+        let room_id = room.id;
+        let saving = self.room_repository.save(&room);
+        let seating = !room.waiting_list.is_empty() && room.check_joinable().is_ok();
+        let activity = room.take_activity_change();
+        drop(room);
+
+        self.apply(room_id, events).await;
+        saving.await;
+        if seating {
+            self.schedule_seating(room_id);
+        }
+        if let Some(activity) = activity {
+            let updated = self
+                .room_info_repository
+                .update_activity(room_id, &activity)
+                .await;
+            if let Err(e) = updated {
+                error!("Error occurred when listing the room's activity: {:?}", e);
             }
-            ServiceRequiredAction::FindWinners => {
-                let GameResult {
-                    hands_eval,
-                    winners,
-                } = self.find_winners(&room)?;
-                // emit game state
-                let game_state =
-                    SharedGameState::from_room(room.clone(), true).with_eval(hands_eval);
-                self.emit_to_room(
-                    room_id.clone(),
-                    ServiceEvent::Room,
-                    &Timestamped::new(game_state),
-                )
+            self.publish_lobby_update(LobbyUpdate::Activity { room_id, activity })
                 .await;
-                // sleep for 5 seconds to show the result
-                sleep(Duration::from_secs(5)).await;
-
-                let mut pot_splits = room.split_pot(winners)?;
-                // reversing the winnings because the last item is the last pot
-                pot_splits.reverse();
-                // emit winnings
-                for winnings in pot_splits {
+        }
+    }
+
+    // in the order the engine gave them, so clients see the hand as it was played
+    #[instrument(skip(self, events))]
+    async fn apply(&self, room_id: Uuid, events: Vec<GameEvent>) {
+        for event in events {
+            match event {
+                GameEvent::StateChanged(pending) => {
+                    self.broadcaster.broadcast(room_id, *pending).await;
+                }
+                GameEvent::HandsDealt { hands, board } => {
+                    for (sid, cards) in hands {
+                        self.emit_hand(sid, cards, &board);
+                    }
+                }
+                GameEvent::BoardChanged { hands, board } => {
+                    self.emit_hand_strength(hands, &board);
+                }
+                GameEvent::ActionLog(entries) => {
+                    // one event per entry, in the order they happened
+                    for entry in entries {
+                        self.emit_to_room(room_id.to_string(), ServiceEvent::ActionLog, &entry)
+                            .await;
+                    }
+                }
+                GameEvent::ShowOrMuck { sids, prompt } => {
+                    let prompt = Timestamped::new(prompt);
+                    for sid in sids {
+                        self.emit_to_socket(sid, ServiceEvent::ShowOrMuck, &prompt);
+                    }
+                }
+                GameEvent::RunOutStarted => self.schedule_run_out(room_id),
+                GameEvent::HandFinished(hand) => self.record_hand(*hand).await,
+                GameEvent::Outcome(outcome) => {
                     self.emit_to_room(
-                        room_id.clone(),
+                        room_id.to_string(),
                         ServiceEvent::Outcome,
-                        &Timestamped::new(winnings),
+                        &Timestamped::new(outcome),
                     )
                     .await;
-                    sleep(Duration::from_secs(3)).await;
                 }
-                self.emit_to_room(
-                    room_id.clone(),
-                    ServiceEvent::Outcome,
-                    &Timestamped::new(Vec::<Winnings>::new()),
-                )
-                .await;
+            }
+        }
+    }
 
-                Box::pin(self.service_action_required(room.proceed()?, room)).await
+    // stats and history are a nice to have, a failure is only logged
+    async fn record_hand(&self, hand: FinishedHand) {
+        let FinishedHand {
+            room_id,
+            showdowns,
+            outcomes,
+            history,
+            rake_taken,
+            snapshot,
+            settlements,
+            results,
+            hands_per_hour,
+        } = hand;
+        if !showdowns.is_empty() {
+            let recorded = self
+                .stats_repository
+                .record_showdowns(room_id, showdowns)
+                .await;
+            if let Err(e) = recorded {
+                error!("Error occurred when recording showdowns: {:?}", e);
             }
-            ServiceRequiredAction::PlayerReceiveCards => {
-                // emit game state
-                let game_state = SharedGameState::from_room(room.clone(), false);
-                self.emit_to_room(room_id.to_owned(), ServiceEvent::Room, &Timestamped::new(game_state))
-                    .await;
+        }
+        if !outcomes.is_empty() {
+            if let Err(e) = self.stats_repository.record_hands(outcomes).await {
+                error!("Error occurred when recording player stats: {:?}", e);
+            }
+        }
+        let hand_id = history.id;
+        if let Err(e) = self.hand_history_repository.record(history).await {
+            error!("Error occurred when recording hand history: {:?}", e);
+        }
+        if rake_taken > 0 {
+            let recorded = self
+                .rake_repository
+                .record(room_id, hand_id, rake_taken)
+                .await;
+            if let Err(e) = recorded {
+                error!("Error occurred when recording rake: {:?}", e);
+            }
+        }
+        if self.settlement == Settlement::PerHand {
+            // the next hand settles the whole table again, so a failure is only logged
+            let settled = self
+                .room_info_repository
+                .settle_hand(hand_id, snapshot, settlements)
+                .await;
+            if let Err(e) = settled {
+                error!("Error occurred when settling hand {}: {:?}", hand_id, e);
+            }
+        }
+        self.events
+            .publish(room_id, RoomEvent::HandCompleted { hand_id, results });
+        self.publish_lobby_update(LobbyUpdate::Pace {
+            room_id,
+            hands_per_hour,
+        })
+        .await;
+    }
 
-                for player in room.players.iter() {
-                    if let Some(Hand(cards)) = player.hand {
-                        let hand: PlayerHand = cards.into();
-                        self.emit_to_socket(
-                            player.sid,
-                            ServiceEvent::Hand,
-                            &Timestamped::new(hand),
-                        );
+    // each street is dealt under its own lock, nothing waits on the room in between
+    fn schedule_run_out(&self, room_id: Uuid) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            loop {
+                sleep(RUN_OUT_STREET_DELAY).await;
+                match service.deal_run_out_street(room_id).await {
+                    Ok(true) => continue,
+                    Ok(false) => return,
+                    Err(e) => {
+                        error!("Error occurred when running out the board: {:?}", e);
+                        return;
                     }
                 }
-                Ok(())
             }
+        });
+    }
+
+    // returns whether there are streets left to show
+    async fn deal_run_out_street(&self, room_id: Uuid) -> Result<bool> {
+        let Some(mut room) = self.room_repository.get_mut_lock(room_id) else {
+            return Ok(false);
+        };
+        // everyone left, or the room was handed over meanwhile
+        if !room.is_running_out() {
+            return Ok(false);
         }
+        let done = room.next_run_out_street(&self.evaluator)?;
+        let action = if done {
+            ServiceRequiredAction::FindWinners
+        } else {
+            ServiceRequiredAction::NoAction
+        };
+        self.service_action_required(action, room).await?;
+        Ok(!done)
     }
 }
 
+// never falls back to plaintext for a socket that asked for sealed hands
+fn emit_sealable_hand<T: Serialize>(socket: &Socket, hand: &T) {
+    let _ = match socket.extensions.get::<HandKey>() {
+        Some(key) => match SealedHand::seal(hand, &key) {
+            Ok(sealed) => socket.emit(ServiceEvent::Hand, &Timestamped::new(sealed)),
+            Err(e) => {
+                error!("Error occurred when sealing hand: {:?}", e);
+                return;
+            }
+        },
+        None => socket.emit(ServiceEvent::Hand, &Timestamped::new(hand)),
+    };
+}
+
+fn is_room_full(e: &eyre::Report) -> bool {
+    matches!(e.downcast_ref::<Error>(), Some(Error::RoomIsFull))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::service::adapter::{self, PubSub};
     use eyre::bail;
     use lazy_static::lazy_static;
+    use chrono::Utc;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::time::Duration;
     use poker::{card, cards};
-    use socketioxide::extract::SocketRef;
     use std::str::FromStr;
     use types::deck::Deck;
-    use types::room::{Position, Pot, ProceedType, Stage};
+    use types::room::{Hand, Position, Pot, ProceedType, Stage};
+    use types::rules::{BIG_BLIND, DEFAULT_RECONNECT_GRACE};
+    use types::state::SerdeCard;
+    use types::stats::HandClass;
 
     use types::domain::User;
 
@@ -408,8 +1642,10 @@ mod tests {
                 User {
                     id: Uuid::from_u128(1),
                     name: "Alice".to_string(),
+                    avatar: Default::default(),
                     balance: 1000,
                     current_room: None,
+                    premium_balance: 0,
                 }
             ),
             (
@@ -417,8 +1653,10 @@ mod tests {
                 User {
                     id: Uuid::from_u128(2),
                     name: "Bob".to_string(),
+                    avatar: Default::default(),
                     balance: 1000,
                     current_room: None,
+                    premium_balance: 0,
                 }
             ),
             (
@@ -426,8 +1664,10 @@ mod tests {
                 User {
                     id: Uuid::from_u128(3),
                     name: "Charlie".to_string(),
+                    avatar: Default::default(),
                     balance: 2000,
                     current_room: None,
+                    premium_balance: 0,
                 }
             ),
             (
@@ -435,8 +1675,10 @@ mod tests {
                 User {
                     id: Uuid::from_u128(4),
                     name: "Dennis".to_string(),
+                    avatar: Default::default(),
                     balance: 2000,
                     current_room: None,
+                    premium_balance: 0,
                 }
             )
         ]);
@@ -458,21 +1700,44 @@ mod tests {
 
         faux::when!(user_repository.get).then(|id| Ok(users.get(&id).cloned()));
 
-        faux::when!(user_repository.update_balance_and_room).then(|(_, _, _)| Ok(()));
         user_repository
     }
 
+    fn mock_stats_repository() -> StatsRepository {
+        let mut stats_repository = StatsRepository::faux();
+        faux::when!(stats_repository.record_showdowns).then(|(_, _)| Ok(()));
+        faux::when!(stats_repository.record_hands).then(|_| Ok(()));
+        stats_repository
+    }
+
+    fn mock_hand_history_repository() -> HandHistoryRepository {
+        let mut hand_history_repository = HandHistoryRepository::faux();
+        faux::when!(hand_history_repository.record).then(|_| Ok(()));
+        hand_history_repository
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_whole_game_flow() -> Result<()> {
         // setup
-        let (_, io) = SocketIo::new_layer();
-        io.ns("/game", |_: SocketRef| async {});
+        let (_, io) = adapter::new_layer(PubSub::Local);
+        io.ns("/game", |_: Socket| async {}).await?;
         let mut service = GameService {
             evaluator: Evaluator::new(),
             room_repository: RoomRepository::new(),
             room_info_repository: RoomInfoRepository::faux(),
             user_repository: Arc::new(mock_user_repository()),
+            stats_repository: mock_stats_repository(),
+            hand_history_repository: mock_hand_history_repository(),
+            rake: Rake::default(),
+            rake_repository: RakeRepository::faux(),
+            broadcaster: RoomBroadcaster::new(io.clone(), Duration::ZERO),
+            chat_limiter: ChatRateLimiter::default(),
+            events: RoomEvents::default(),
+            outbox: Outbox::default(),
+            settlement: Settlement::default(),
+            reconnect_grace: DEFAULT_RECONNECT_GRACE,
+            deck_seed: None,
             io,
         };
         let alice = service.create_user("Alice".to_string(), 1000).await?;
@@ -484,11 +1749,11 @@ mod tests {
 
         // join players
         let room = service
-            .join_player(room.id, alice.id, 500, Sid::default())
+            .join_player(room.id, alice.id, 500, None, None, Sid::default())
             .await?;
         assert_eq!(room.stage, Stage::NotEnoughPlayers);
         let room = service
-            .join_player(room.id, bob.id, 500, Sid::default())
+            .join_player(room.id, bob.id, 500, None, None, Sid::default())
             .await?;
 
         assert_eq!(room.stage, Stage::PreFlop);
@@ -595,7 +1860,7 @@ mod tests {
             .await
             .unwrap_err()
             .to_string();
-        assert_eq!(error_message, "Invalid raise amount".to_string());
+        assert_eq!(error_message, "Raise must be to at least 20 chips".to_string());
 
         // bob calls
         let room = service.take_action(room.id, bob.id, Action::Call).await?;
@@ -673,13 +1938,24 @@ mod tests {
     #[ignore]
     async fn test_fold_and_raise() -> Result<()> {
         // setup
-        let (_, io) = SocketIo::new_layer();
-        io.ns("/game", |_: SocketRef| async {});
+        let (_, io) = adapter::new_layer(PubSub::Local);
+        io.ns("/game", |_: Socket| async {}).await?;
         let mut service = GameService {
             evaluator: Evaluator::new(),
             room_repository: RoomRepository::new(),
             room_info_repository: RoomInfoRepository::faux(),
             user_repository: Arc::new(mock_user_repository()),
+            stats_repository: mock_stats_repository(),
+            hand_history_repository: mock_hand_history_repository(),
+            rake: Rake::default(),
+            rake_repository: RakeRepository::faux(),
+            broadcaster: RoomBroadcaster::new(io.clone(), Duration::ZERO),
+            chat_limiter: ChatRateLimiter::default(),
+            events: RoomEvents::default(),
+            outbox: Outbox::default(),
+            settlement: Settlement::default(),
+            reconnect_grace: DEFAULT_RECONNECT_GRACE,
+            deck_seed: None,
             io,
         };
         let alice = service.create_user("Alice".to_string(), 1000).await?;
@@ -691,11 +1967,11 @@ mod tests {
 
         // join players
         let room = service
-            .join_player(room.id, alice.id, 500, Sid::default())
+            .join_player(room.id, alice.id, 500, None, None, Sid::default())
             .await?;
         assert_eq!(room.stage, Stage::NotEnoughPlayers);
         let room = service
-            .join_player(room.id, bob.id, 500, Sid::default())
+            .join_player(room.id, bob.id, 500, None, None, Sid::default())
             .await?;
         assert_eq!(room.stage, Stage::PreFlop);
 
@@ -741,7 +2017,7 @@ mod tests {
                 evaluator.evaluate(cards!("Ks Kd Kc Qs Qd").try_collect::<Vec<_>>()?)?,
             ),
         ];
-        let best_hands = GameService::all_best_hands(&hands);
+        let best_hands = Room::all_best_hands(&hands);
         assert_eq!(best_hands.len(), 2);
         assert!(best_hands.contains(&Uuid::from_u128(1)));
         assert!(best_hands.contains(&Uuid::from_u128(2)));
@@ -752,13 +2028,24 @@ mod tests {
     #[ignore]
     async fn test_skip_player_with_zero_chips() -> Result<()> {
         // setup
-        let (_, io) = SocketIo::new_layer();
-        io.ns("/game", |_: SocketRef| async {});
+        let (_, io) = adapter::new_layer(PubSub::Local);
+        io.ns("/game", |_: Socket| async {}).await?;
         let mut service = GameService {
             evaluator: Evaluator::new(),
             room_repository: RoomRepository::new(),
             room_info_repository: RoomInfoRepository::faux(),
             user_repository: Arc::new(mock_user_repository()),
+            stats_repository: mock_stats_repository(),
+            hand_history_repository: mock_hand_history_repository(),
+            rake: Rake::default(),
+            rake_repository: RakeRepository::faux(),
+            broadcaster: RoomBroadcaster::new(io.clone(), Duration::ZERO),
+            chat_limiter: ChatRateLimiter::default(),
+            events: RoomEvents::default(),
+            outbox: Outbox::default(),
+            settlement: Settlement::default(),
+            reconnect_grace: DEFAULT_RECONNECT_GRACE,
+            deck_seed: None,
             io,
         };
         let alice = service.create_user("Alice".to_string(), 1000).await?;
@@ -767,14 +2054,31 @@ mod tests {
         // create room
         let room = service.create_room()?;
         assert_eq!(room.stage, Stage::NotEnoughPlayers);
+        // Alice's all-in runs into Bob's aces on a board that helps neither
+        let scenario = Scenario {
+            hole_cards: BTreeMap::from([
+                (0, [SerdeCard(card!("7c")?), SerdeCard(card!("2d")?)]),
+                (1, [SerdeCard(card!("As")?), SerdeCard(card!("Ah")?)]),
+            ]),
+            board: cards!("Kd Qs 9h 5c 3s")
+                .try_collect::<Vec<_>>()?
+                .into_iter()
+                .map(SerdeCard)
+                .collect(),
+        };
+        service
+            .room_repository
+            .get_mut_lock(room.id)
+            .expect("Room not found")
+            .scenario = Some(scenario);
 
         // join players
         let room = service
-            .join_player(room.id, alice.id, 500, Sid::default())
+            .join_player(room.id, alice.id, 500, None, Some(0), Sid::default())
             .await?;
         assert_eq!(room.stage, Stage::NotEnoughPlayers);
         let room = service
-            .join_player(room.id, bob.id, 1000, Sid::default())
+            .join_player(room.id, bob.id, 1000, None, Some(1), Sid::default())
             .await?;
         assert_eq!(room.stage, Stage::PreFlop);
 
@@ -810,19 +2114,23 @@ mod tests {
         assert_eq!(alice.chips, 0);
         assert_eq!(room.stage, Stage::PreFlop);
         let room = service.take_action(room.id, bob.id, Action::Call).await?;
+        assert!(room.is_running_out());
+        while service.deal_run_out_street(room.id).await? {}
+        service.player_ready(room.id, alice.id).await?;
+        service.player_ready(room.id, bob.id).await?;
+
+        // Alice has no chips left to be dealt in with, so Bob waits for another player
+        let room = service
+            .room_repository
+            .get(room.id)
+            .expect("Room not found");
         let bob = room
             .players
             .iter()
             .find(|p| p.id == bob.id)
             .expect("Bob not found");
-        if bob.chips == 1500 {
-            // bob won, alice loss
-            assert_eq!(room.stage, Stage::NotEnoughPlayers);
-        } else {
-            // bob loss, alice won
-            assert_eq!(room.stage, Stage::PreFlop);
-            assert_eq!(room.player_in_turn, Some(bob.id));
-        }
+        assert_eq!(bob.chips, 1500);
+        assert_eq!(room.stage, Stage::NotEnoughPlayers);
         Ok(())
     }
 
@@ -830,13 +2138,24 @@ mod tests {
     #[ignore]
     async fn test_proceed_when_alice_reraise_bob() -> Result<()> {
         // setup
-        let (_, io) = SocketIo::new_layer();
-        io.ns("/game", |_: SocketRef| async {});
+        let (_, io) = adapter::new_layer(PubSub::Local);
+        io.ns("/game", |_: Socket| async {}).await?;
         let mut service = GameService {
             evaluator: Evaluator::new(),
             room_repository: RoomRepository::new(),
             room_info_repository: RoomInfoRepository::faux(),
             user_repository: Arc::new(mock_user_repository()),
+            stats_repository: mock_stats_repository(),
+            hand_history_repository: mock_hand_history_repository(),
+            rake: Rake::default(),
+            rake_repository: RakeRepository::faux(),
+            broadcaster: RoomBroadcaster::new(io.clone(), Duration::ZERO),
+            chat_limiter: ChatRateLimiter::default(),
+            events: RoomEvents::default(),
+            outbox: Outbox::default(),
+            settlement: Settlement::default(),
+            reconnect_grace: DEFAULT_RECONNECT_GRACE,
+            deck_seed: None,
             io,
         };
         let alice = service.create_user("Alice".to_string(), 1000).await?;
@@ -848,11 +2167,11 @@ mod tests {
 
         // join players
         let room = service
-            .join_player(room.id, alice.id, 500, Sid::default())
+            .join_player(room.id, alice.id, 500, None, None, Sid::default())
             .await?;
         assert_eq!(room.stage, Stage::NotEnoughPlayers);
         let room = service
-            .join_player(room.id, bob.id, 1000, Sid::default())
+            .join_player(room.id, bob.id, 1000, None, None, Sid::default())
             .await?;
         assert_eq!(room.stage, Stage::PreFlop);
 
@@ -912,13 +2231,24 @@ mod tests {
     #[ignore]
     async fn test_bob_all_in_during_flop() -> Result<()> {
         // setup
-        let (_, io) = SocketIo::new_layer();
-        io.ns("/game", |_: SocketRef| async {});
+        let (_, io) = adapter::new_layer(PubSub::Local);
+        io.ns("/game", |_: Socket| async {}).await?;
         let mut service = GameService {
             evaluator: Evaluator::new(),
             room_repository: RoomRepository::new(),
             room_info_repository: RoomInfoRepository::faux(),
             user_repository: Arc::new(mock_user_repository()),
+            stats_repository: mock_stats_repository(),
+            hand_history_repository: mock_hand_history_repository(),
+            rake: Rake::default(),
+            rake_repository: RakeRepository::faux(),
+            broadcaster: RoomBroadcaster::new(io.clone(), Duration::ZERO),
+            chat_limiter: ChatRateLimiter::default(),
+            events: RoomEvents::default(),
+            outbox: Outbox::default(),
+            settlement: Settlement::default(),
+            reconnect_grace: DEFAULT_RECONNECT_GRACE,
+            deck_seed: None,
             io,
         };
         let alice = service.create_user("Alice".to_string(), 1000).await?;
@@ -930,11 +2260,11 @@ mod tests {
 
         // join players
         let room = service
-            .join_player(room.id, alice.id, 500, Sid::default())
+            .join_player(room.id, alice.id, 500, None, None, Sid::default())
             .await?;
         assert_eq!(room.stage, Stage::NotEnoughPlayers);
         let room = service
-            .join_player(room.id, bob.id, 1000, Sid::default())
+            .join_player(room.id, bob.id, 1000, None, None, Sid::default())
             .await?;
         assert_eq!(room.stage, Stage::PreFlop);
 
@@ -972,13 +2302,24 @@ mod tests {
     #[ignore]
     async fn test_3_players() -> Result<()> {
         // setup
-        let (_, io) = SocketIo::new_layer();
-        io.ns("/game", |_: SocketRef| async {});
+        let (_, io) = adapter::new_layer(PubSub::Local);
+        io.ns("/game", |_: Socket| async {}).await?;
         let mut service = GameService {
             evaluator: Evaluator::new(),
             room_repository: RoomRepository::new(),
             room_info_repository: RoomInfoRepository::faux(),
             user_repository: Arc::new(mock_user_repository()),
+            stats_repository: mock_stats_repository(),
+            hand_history_repository: mock_hand_history_repository(),
+            rake: Rake::default(),
+            rake_repository: RakeRepository::faux(),
+            broadcaster: RoomBroadcaster::new(io.clone(), Duration::ZERO),
+            chat_limiter: ChatRateLimiter::default(),
+            events: RoomEvents::default(),
+            outbox: Outbox::default(),
+            settlement: Settlement::default(),
+            reconnect_grace: DEFAULT_RECONNECT_GRACE,
+            deck_seed: None,
             io,
         };
         let alice = service.create_user("Alice".to_string(), 1000).await?;
@@ -991,16 +2332,16 @@ mod tests {
 
         // join players
         let room = service
-            .join_player(room.id, alice.id, 500, Sid::default())
+            .join_player(room.id, alice.id, 500, None, None, Sid::default())
             .await?;
         let room = service
-            .join_player(room.id, bob.id, 1000, Sid::default())
+            .join_player(room.id, bob.id, 1000, None, None, Sid::default())
             .await?;
         let room = service
-            .join_player(room.id, charlie.id, 1500, Sid::default())
+            .join_player(room.id, charlie.id, 1500, None, None, Sid::default())
             .await?;
         let room = service
-            .join_player(room.id, dennis.id, 2000, Sid::default())
+            .join_player(room.id, dennis.id, 2000, None, None, Sid::default())
             .await?;
         assert_eq!(room.player_joining_next_round.len(), 2);
 
@@ -1090,6 +2431,57 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn chat_should_only_relay_trimmed_messages_from_players_at_the_table() -> Result<()> {
+        let (_, io) = adapter::new_layer(PubSub::Local);
+        let mut game_service = GameService {
+            evaluator: Evaluator::new(),
+            room_repository: RoomRepository::new(),
+            room_info_repository: RoomInfoRepository::faux(),
+            user_repository: Arc::new(UserRepository::faux()),
+            stats_repository: StatsRepository::faux(),
+            hand_history_repository: HandHistoryRepository::faux(),
+            rake: Rake::default(),
+            rake_repository: RakeRepository::faux(),
+            broadcaster: RoomBroadcaster::new(io.clone(), Duration::ZERO),
+            chat_limiter: ChatRateLimiter::default(),
+            events: RoomEvents::default(),
+            outbox: Outbox::default(),
+            settlement: Settlement::default(),
+            reconnect_grace: DEFAULT_RECONNECT_GRACE,
+            deck_seed: None,
+            io,
+        };
+        let mut room = Room::new();
+        let alice = Player::new("Alice".to_string(), 100);
+        let alice_id = alice.id;
+        room.players.push(alice);
+        let room_id = room.id;
+        game_service.room_repository.upsert(room);
+
+        let chat = game_service
+            .send_chat(room_id, alice_id, "  nice hand ".to_string())
+            .await?;
+        assert_eq!(chat.sender_name, "Alice");
+        assert_eq!(chat.message, "nice hand");
+
+        let blank = game_service
+            .send_chat(room_id, alice_id, "   ".to_string())
+            .await;
+        assert!(matches!(
+            blank.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::InvalidChatMessage))
+        ));
+        let stranger = game_service
+            .send_chat(room_id, Uuid::new_v4(), "hello".to_string())
+            .await;
+        assert!(matches!(
+            stranger.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::NotInRoom))
+        ));
+        Ok(())
+    }
+
     #[test]
     fn test_add_player() -> Result<()> {
         let mut room = Room::new();
@@ -1111,12 +2503,23 @@ mod tests {
 
     #[test]
     fn test_winners() -> Result<()> {
-        let (_, io) = SocketIo::new_layer();
+        let (_, io) = adapter::new_layer(PubSub::Local);
         let game_service = GameService {
             evaluator: Evaluator::new(),
             room_repository: RoomRepository::new(),
             room_info_repository: RoomInfoRepository::faux(),
             user_repository: Arc::new(UserRepository::faux()),
+            stats_repository: StatsRepository::faux(),
+            hand_history_repository: HandHistoryRepository::faux(),
+            rake: Rake::default(),
+            rake_repository: RakeRepository::faux(),
+            broadcaster: RoomBroadcaster::new(io.clone(), Duration::ZERO),
+            chat_limiter: ChatRateLimiter::default(),
+            events: RoomEvents::default(),
+            outbox: Outbox::default(),
+            settlement: Settlement::default(),
+            reconnect_grace: DEFAULT_RECONNECT_GRACE,
+            deck_seed: None,
             io,
         };
         let room = Room {
@@ -1125,6 +2528,7 @@ mod tests {
                 Player {
                     id: Uuid::from_u128(1),
                     name: "Alice".to_string(),
+                    avatar: Default::default(),
                     hand: Some(Hand([card!("3s")?, card!("2s")?])),
                     chips: 0,
                     bet: 0,
@@ -1134,10 +2538,14 @@ mod tests {
                     sid: Sid::from_str("AA9AAA0AAzAAAAHs")?,
                     is_connected: true,
                     last_action: None,
+                    seat: 0,
+                    is_sitting_out: false,
+                    fold_reason: None,
                 },
                 Player {
                     id: Uuid::from_u128(2),
                     name: "Bob".to_string(),
+                    avatar: Default::default(),
                     hand: Some(Hand([card!("4s")?, card!("5s")?])),
                     chips: 0,
                     bet: 0,
@@ -1147,6 +2555,9 @@ mod tests {
                     sid: Sid::from_str("AA9AAA0AAzAAAAHB")?,
                     is_connected: true,
                     last_action: None,
+                    seat: 1,
+                    is_sitting_out: false,
+                    fold_reason: None,
                 },
             ],
             deck: Deck::new(),
@@ -1157,10 +2568,31 @@ mod tests {
                 players: HashSet::from([Uuid::from_u128(1), Uuid::from_u128(2)]),
             }],
             player_joining_next_round: Default::default(),
+            players_sitting_out: Default::default(),
             player_in_turn: None,
+            closes_at: None,
+            closing_notice_sent: false,
+            action_log: vec![],
+            action_seq: 0,
+            turn_deadline: None,
+            current_turn: None,
+            showdown: None,
+            last_raise_size: BIG_BLIND,
+            raise_closed_for: HashSet::new(),
+            dark_actions: HashMap::new(),
+            bot_seats: 0,
+            bots: HashSet::new(),
+            starting_chips: HashMap::new(),
+            scenario: None,
+            ..Room::new()
         };
 
-        let game_result = game_service.find_winners(&room)?;
+        let game_result = room.find_winners(&game_service.evaluator)?;
+        let showdowns = game_result.showdown_results();
+        assert_eq!(showdowns.len(), 2);
+        assert!(showdowns
+            .iter()
+            .all(|s| s.won && s.hand_class == HandClass::StraightFlush));
         let (_, winner_ids) = game_result.winners.first().wrap_err("No winners")?;
         let mut winners: Vec<_> = room
             .players
@@ -1175,6 +2607,7 @@ mod tests {
                 &Player {
                     id: Uuid::from_u128(1),
                     name: "Alice".to_string(),
+                    avatar: Default::default(),
                     hand: Some(Hand([card!("3s")?, card!("2s")?])),
                     chips: 0,
                     bet: 0,
@@ -1184,10 +2617,14 @@ mod tests {
                     sid: Sid::from_str("AA9AAA0AAzAAAAHs")?,
                     is_connected: true,
                     last_action: None,
+                    seat: 0,
+                    is_sitting_out: false,
+                    fold_reason: None,
                 },
                 &Player {
                     id: Uuid::from_u128(2),
                     name: "Bob".to_string(),
+                    avatar: Default::default(),
                     hand: Some(Hand([card!("4s")?, card!("5s")?])),
                     chips: 0,
                     bet: 0,
@@ -1197,6 +2634,9 @@ mod tests {
                     sid: Sid::from_str("AA9AAA0AAzAAAAHB")?,
                     is_connected: true,
                     last_action: None,
+                    seat: 1,
+                    is_sitting_out: false,
+                    fold_reason: None,
                 },
             ]
         );
@@ -1342,6 +2782,7 @@ mod tests {
                 Player {
                     id: Uuid::from_u128(1),
                     name: "Alice".to_string(),
+                    avatar: Default::default(),
                     hand: Some(Hand([card!("3s")?, card!("2s")?])),
                     chips: 500 - alice_bet,
                     bet: alice_bet,
@@ -1351,10 +2792,14 @@ mod tests {
                     sid: Sid::default(),
                     is_connected: true,
                     last_action: None,
+                    seat: 0,
+                    is_sitting_out: false,
+                    fold_reason: None,
                 },
                 Player {
                     id: Uuid::from_u128(2),
                     name: "Bob".to_string(),
+                    avatar: Default::default(),
                     hand: Some(Hand([card!("4s")?, card!("5s")?])),
                     chips: 1000 - bob_bet,
                     bet: bob_bet,
@@ -1364,6 +2809,9 @@ mod tests {
                     sid: Sid::default(),
                     is_connected: true,
                     last_action: None,
+                    seat: 1,
+                    is_sitting_out: false,
+                    fold_reason: None,
                 },
             ],
             deck: Deck::new(),
@@ -1374,11 +2822,27 @@ mod tests {
                 players: HashSet::from([Uuid::from_u128(1), Uuid::from_u128(2)]),
             }],
             player_joining_next_round: Vec::new(),
+            players_sitting_out: Vec::new(),
             player_in_turn: if player_in_turn == "Alice" {
                 Some(Uuid::from_u128(1))
             } else {
                 Some(Uuid::from_u128(2))
             },
+            closes_at: None,
+            closing_notice_sent: false,
+            action_log: vec![],
+            action_seq: 0,
+            turn_deadline: None,
+            current_turn: None,
+            showdown: None,
+            last_raise_size: BIG_BLIND,
+            raise_closed_for: HashSet::new(),
+            dark_actions: HashMap::new(),
+            bot_seats: 0,
+            bots: HashSet::new(),
+            starting_chips: HashMap::new(),
+            scenario: None,
+            ..Room::new()
         };
         assert_eq!(room.can_proceed_to_next_stage(), proceed_type);
         Ok(())
@@ -1506,4 +2970,66 @@ mod tests {
         assert_eq!(closest_player, player2.id);
         Ok(())
     }
+
+    #[test]
+    fn room_should_stop_dealing_after_closing_time() -> Result<()> {
+        let mut room = Room::new();
+        room.join_player(Player::new("Alice".to_string(), 400))?;
+        room.join_player(Player::new("Bob".to_string(), 400))?;
+        assert_eq!(room.stage, Stage::PreFlop);
+
+        room.closes_at = Some(Utc::now());
+        let error = room
+            .join_player(Player::new("Charlie".to_string(), 400))
+            .unwrap_err();
+        assert!(matches!(error.downcast_ref::<Error>(), Some(Error::RoomClosed)));
+
+        // the hand in progress is played out, but no new hand is dealt
+        let player_in_turn = room.player_in_turn.wrap_err("No player in turn")?;
+        let service_action = room.take_action(player_in_turn, Action::Fold)?;
+        assert_eq!(service_action, ServiceRequiredAction::FindWinners);
+        room.proceed()?;
+        assert_eq!(room.stage, Stage::NotEnoughPlayers);
+        assert!(room.can_close());
+
+        let players = room.cash_out_players();
+        assert_eq!(players.len(), 2);
+        assert!(room.players.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn players_should_keep_their_seats_between_hands() -> Result<()> {
+        let mut room = Room::new();
+        let alice = Player::new("Alice".to_string(), 400);
+        let bob = Player::new("Bob".to_string(), 400);
+        let alice_id = alice.id;
+        let bob_id = bob.id;
+        room.join_player(alice)?;
+        room.join_player(bob)?;
+        room.join_player(Player::new("Charlie".to_string(), 400))?;
+
+        // bob's seat is given to david, who joins while the hand is in progress
+        room.leave_player(bob_id);
+        room.join_player(Player::new("David".to_string(), 400))?;
+
+        room.take_action(alice_id, Action::Call)?;
+        room.proceed()?;
+        assert_eq!(room.stage, Stage::PreFlop);
+
+        let seats = SharedGameState::from_room(room, false)
+            .players
+            .into_iter()
+            .map(|p| (p.seat, p.name))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            seats,
+            vec![
+                (0, "Alice".to_string()),
+                (1, "David".to_string()),
+                (2, "Charlie".to_string()),
+            ]
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use eyre::Result;
+use log::error;
+use sqlx::types::Uuid;
+use tokio::sync::broadcast::error::RecvError;
+
+use types::domain::{Action, IntegrityQuery, IntegritySignal, SuspiciousPair};
+use types::history::{HandHistory, ReplaySeat};
+use types::room::Stage;
+use types::webhook::RoomEvent;
+
+use crate::repository::history::HandHistoryRepository;
+use crate::repository::integrity::{IntegrityEvent, IntegrityRepository};
+use crate::service::events::RoomEvents;
+
+const DEFAULT_REPORT_DAYS: i32 = 30;
+const DEFAULT_MIN_FOLDS: i64 = 20;
+// share of a stack folded away to the only winner of a pot that makes it a chip dump
+const CHIP_DUMP_SHARE: f64 = 0.5;
+
+/// A finished hand as the integrity checks see it
+pub struct HandContext {
+    pub history: HandHistory,
+    // where the players dealt in were connected from, when known
+    pub addresses: HashMap<Uuid, IpAddr>,
+}
+
+/// A heuristic run on every finished hand. Checks added to `IntegrityService::checks` have
+/// their signals recorded and reported like the built in ones.
+pub trait IntegrityCheck: Send + Sync {
+    fn inspect(&self, hand: &HandContext) -> Vec<IntegrityEvent>;
+}
+
+/// Records what finished hands suggest about pairs of players. It follows the room events,
+/// so the game service never waits on it.
+#[derive(Clone)]
+pub struct IntegrityService {
+    pub integrity_repository: IntegrityRepository,
+    pub hand_history_repository: HandHistoryRepository,
+    pub checks: Arc<Vec<Box<dyn IntegrityCheck>>>,
+    // where each player's game socket last connected from
+    pub addresses: Arc<DashMap<Uuid, IpAddr>>,
+}
+
+impl IntegrityService {
+    pub fn default_checks() -> Arc<Vec<Box<dyn IntegrityCheck>>> {
+        Arc::new(vec![
+            Box::new(FoldedTo),
+            Box::new(ChipDump),
+            Box::new(SharedAddress),
+        ])
+    }
+
+    pub fn remember_address(&self, user_id: Uuid, address: IpAddr) {
+        self.addresses.insert(user_id, address);
+    }
+
+    pub async fn run(self, events: RoomEvents) {
+        let mut events = events.subscribe();
+        loop {
+            match events.recv().await {
+                Ok((room_id, RoomEvent::HandCompleted { hand_id, .. })) => {
+                    if let Err(e) = self.inspect_hand(room_id, hand_id).await {
+                        error!("Error occurred when checking hand {}: {:?}", hand_id, e);
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    error!(
+                        "Integrity checks fell behind, {} room events dropped",
+                        skipped
+                    )
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+
+    // the history is written before the hand is reported completed
+    async fn inspect_hand(&self, room_id: Uuid, hand_id: Uuid) -> Result<()> {
+        let Some(history) = self.hand_history_repository.get(room_id, hand_id).await? else {
+            return Ok(());
+        };
+        let addresses = history
+            .players
+            .iter()
+            .filter_map(|p| Some((p.id, *self.addresses.get(&p.id)?)))
+            .collect();
+        let hand = HandContext { history, addresses };
+        let events: Vec<IntegrityEvent> = self
+            .checks
+            .iter()
+            .flat_map(|check| check.inspect(&hand))
+            .collect();
+        if events.is_empty() {
+            return Ok(());
+        }
+        self.integrity_repository
+            .record(room_id, hand_id, events)
+            .await
+    }
+
+    pub async fn report(&self, query: IntegrityQuery) -> Result<Vec<SuspiciousPair>> {
+        self.integrity_repository
+            .suspicious_pairs(
+                query.days.unwrap_or(DEFAULT_REPORT_DAYS),
+                query.min_folds.unwrap_or(DEFAULT_MIN_FOLDS),
+            )
+            .await
+    }
+}
+
+// chips the player put in over the hand, 0 when the starting stack is unknown
+fn put_in(history: &HandHistory, seat: &ReplaySeat) -> i64 {
+    let starting_chips = history
+        .players
+        .iter()
+        .find(|p| p.id == seat.id)
+        .and_then(|p| p.starting_chips);
+    match (starting_chips, seat.chips) {
+        (Some(starting_chips), Some(chips)) => starting_chips as i64 - chips as i64,
+        _ => 0,
+    }
+}
+
+/// Folds to the last player to bet or raise on the street, with the chips given up
+pub struct FoldedTo;
+
+impl IntegrityCheck for FoldedTo {
+    fn inspect(&self, hand: &HandContext) -> Vec<IntegrityEvent> {
+        let mut aggressor = None;
+        let mut stage = Stage::PreFlop;
+        let mut events = vec![];
+        for frame in hand.history.frames() {
+            let Some(action) = &frame.action else {
+                continue;
+            };
+            if action.stage != stage {
+                aggressor = None;
+                stage = action.stage.clone();
+            }
+            let Some(seat) = frame.seats.iter().find(|s| s.id == action.player_id) else {
+                continue;
+            };
+            // the blinds alone are not a bet to fold to
+            let raised = frame
+                .seats
+                .iter()
+                .all(|other| other.id == seat.id || other.bet < seat.bet);
+            match action.action {
+                Action::Fold => {
+                    if let Some(aggressor) = aggressor.filter(|id| *id != seat.id) {
+                        events.push(IntegrityEvent {
+                            signal: IntegritySignal::FoldedTo,
+                            player_id: seat.id,
+                            other_player_id: aggressor,
+                            amount: put_in(&hand.history, seat),
+                        });
+                    }
+                }
+                _ if raised => aggressor = Some(seat.id),
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// Players who folded most of their stack away to the only winner of a hand nobody showed down
+pub struct ChipDump;
+
+impl IntegrityCheck for ChipDump {
+    fn inspect(&self, hand: &HandContext) -> Vec<IntegrityEvent> {
+        let history = &hand.history;
+        let Some(winner) = history.results.first().map(|w| w.player) else {
+            return vec![];
+        };
+        let is_split = history.results.iter().any(|w| w.player != winner);
+        let is_showdown = history.players.iter().any(|p| p.hole_cards.is_some());
+        if is_split || is_showdown {
+            return vec![];
+        }
+        let Some(last) = history.frames().pop() else {
+            return vec![];
+        };
+        last.seats
+            .iter()
+            .filter(|seat| seat.folded && seat.id != winner)
+            .filter_map(|seat| {
+                let starting_chips = history
+                    .players
+                    .iter()
+                    .find(|p| p.id == seat.id)?
+                    .starting_chips?;
+                let lost = put_in(history, seat);
+                (lost > 0 && lost as f64 >= starting_chips as f64 * CHIP_DUMP_SHARE).then_some(
+                    IntegrityEvent {
+                        signal: IntegritySignal::ChipDump,
+                        player_id: seat.id,
+                        other_player_id: winner,
+                        amount: lost,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+/// Every pair of players dealt in from the same address
+pub struct SharedAddress;
+
+impl IntegrityCheck for SharedAddress {
+    fn inspect(&self, hand: &HandContext) -> Vec<IntegrityEvent> {
+        let players = &hand.history.players;
+        let mut events = vec![];
+        for (i, player) in players.iter().enumerate() {
+            let Some(address) = hand.addresses.get(&player.id) else {
+                continue;
+            };
+            for other in &players[i + 1..] {
+                if hand.addresses.get(&other.id) == Some(address) {
+                    events.push(IntegrityEvent {
+                        signal: IntegritySignal::SharedAddress,
+                        player_id: player.id,
+                        other_player_id: other.id,
+                        amount: 0,
+                    });
+                }
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use types::history::HistoryPlayer;
+    use types::room::{LoggedAction, Position, Winnings};
+
+    use super::*;
+
+    fn player(seat: usize, position: Position) -> HistoryPlayer {
+        HistoryPlayer {
+            id: Uuid::new_v4(),
+            name: format!("player {}", seat),
+            seat,
+            hole_cards: None,
+            eval: None,
+            position: Some(position),
+            starting_chips: Some(100),
+        }
+    }
+
+    fn logged(seq: u64, player: &HistoryPlayer, stage: Stage, action: Action) -> LoggedAction {
+        LoggedAction {
+            seq,
+            player_id: player.id,
+            player_name: player.name.clone(),
+            stage,
+            action,
+            is_dark: false,
+            fold_reason: None,
+        }
+    }
+
+    fn hand(players: Vec<HistoryPlayer>, actions: Vec<LoggedAction>, winner: Uuid) -> HandContext {
+        HandContext {
+            history: HandHistory {
+                id: Uuid::new_v4(),
+                room_id: Uuid::new_v4(),
+                players,
+                community_cards: vec![],
+                actions,
+                results: vec![Winnings {
+                    player: winner,
+                    amount: 0,
+                    best_hand: vec![],
+                }],
+                created_at: Utc::now(),
+                deck_hash: None,
+                deal: None,
+            },
+            addresses: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn folds_should_be_recorded_against_the_last_raise_only() {
+        let dealer = player(0, Position::DealerAndSmallBlind);
+        let big_blind = player(1, Position::BigBlind);
+        let hand = hand(
+            vec![dealer.clone(), big_blind.clone()],
+            vec![
+                logged(1, &dealer, Stage::PreFlop, Action::RaiseTo(60)),
+                logged(2, &big_blind, Stage::PreFlop, Action::Fold),
+            ],
+            dealer.id,
+        );
+        assert_eq!(
+            FoldedTo.inspect(&hand),
+            vec![IntegrityEvent {
+                signal: IntegritySignal::FoldedTo,
+                player_id: big_blind.id,
+                other_player_id: dealer.id,
+                amount: 2,
+            }]
+        );
+        // only the big blind to fold to
+        let limp_fold = HandContext {
+            history: HandHistory {
+                actions: vec![logged(1, &dealer, Stage::PreFlop, Action::Fold)],
+                ..hand.history
+            },
+            addresses: HashMap::new(),
+        };
+        assert!(FoldedTo.inspect(&limp_fold).is_empty());
+    }
+
+    #[test]
+    fn chip_dump_should_need_most_of_a_stack_folded_away() {
+        let dealer = player(0, Position::DealerAndSmallBlind);
+        let big_blind = player(1, Position::BigBlind);
+        let dump = hand(
+            vec![dealer.clone(), big_blind.clone()],
+            vec![
+                logged(1, &dealer, Stage::PreFlop, Action::RaiseTo(60)),
+                logged(2, &big_blind, Stage::PreFlop, Action::Call),
+                logged(3, &big_blind, Stage::Flop, Action::Bet(10)),
+                logged(4, &dealer, Stage::Flop, Action::Fold),
+            ],
+            big_blind.id,
+        );
+        let events = ChipDump.inspect(&dump);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].player_id, dealer.id);
+        assert_eq!(events[0].other_player_id, big_blind.id);
+        assert_eq!(events[0].amount, 60);
+
+        let small_loss = hand(
+            vec![dealer.clone(), big_blind.clone()],
+            vec![logged(1, &dealer, Stage::PreFlop, Action::Fold)],
+            big_blind.id,
+        );
+        assert!(ChipDump.inspect(&small_loss).is_empty());
+    }
+
+    #[test]
+    fn players_on_the_same_address_should_be_paired() {
+        let players: Vec<_> = (0..3).map(|seat| player(seat, Position::Normal)).collect();
+        let mut hand = hand(players.clone(), vec![], players[0].id);
+        let home: IpAddr = [10, 0, 0, 1].into();
+        hand.addresses = HashMap::from([
+            (players[0].id, home),
+            (players[1].id, [10, 0, 0, 2].into()),
+            (players[2].id, home),
+        ]);
+        let events = SharedAddress.inspect(&hand);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].player_id, players[0].id);
+        assert_eq!(events[0].other_player_id, players[2].id);
+    }
+}
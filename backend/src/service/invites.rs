@@ -0,0 +1,48 @@
+use eyre::{ensure, ContextCompat, Result};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sqlx::types::Uuid;
+
+use types::domain::{RoomInfo, RoomInvite};
+use types::error::Error;
+
+use crate::repository::invites::InviteRepository;
+use crate::repository::rooms::RoomInfoRepository;
+
+const INVITE_CODE_LENGTH: usize = 8;
+
+#[derive(Clone)]
+pub struct InviteService {
+    pub invite_repository: InviteRepository,
+    pub room_info_repository: RoomInfoRepository,
+}
+
+impl InviteService {
+    pub async fn create_invite(&self, room_id: Uuid, user_id: Uuid) -> Result<RoomInvite> {
+        ensure!(
+            self.room_info_repository.get(room_id).await?.is_some(),
+            Error::InvalidRoomId
+        );
+        let code: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(INVITE_CODE_LENGTH)
+            .map(char::from)
+            .collect();
+        self.invite_repository
+            .create(&code, room_id, user_id)
+            .await?;
+        Ok(RoomInvite::new(code, room_id))
+    }
+
+    pub async fn get_invited_room(&self, code: &str) -> Result<RoomInfo> {
+        let room_id = self
+            .invite_repository
+            .get_room_id(code)
+            .await?
+            .wrap_err(Error::InvalidInviteCode)?;
+        self.room_info_repository
+            .get(room_id)
+            .await?
+            .wrap_err(Error::RoomClosed)
+    }
+}
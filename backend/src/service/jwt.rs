@@ -0,0 +1,67 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use eyre::Result;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use log::debug;
+use uuid::Uuid;
+
+use types::domain::Role;
+use types::error::Error;
+
+use crate::domain::auth::Claims;
+
+pub const ACCESS_TOKEN_TTL: TimeDelta = TimeDelta::minutes(15);
+
+// Signs and checks access tokens with a shared secret (HS256)
+#[derive(Clone)]
+pub struct JwtKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+impl JwtKeys {
+    pub fn new(secret: &[u8]) -> Self {
+        Self {
+            encoding: EncodingKey::from_secret(secret),
+            decoding: DecodingKey::from_secret(secret),
+        }
+    }
+
+    pub fn issue(&self, user_id: Uuid, role: Role, now: DateTime<Utc>) -> Result<String> {
+        let claims = Claims {
+            sub: user_id,
+            exp: (now + ACCESS_TOKEN_TTL).timestamp(),
+            jti: Uuid::new_v4(),
+            role,
+        };
+        encode(&Header::default(), &claims, &self.encoding).map_err(Into::into)
+    }
+
+    /// Checks the signature and expiry of an access token
+    pub fn verify(&self, token: &str) -> Result<Claims> {
+        let data = decode::<Claims>(token, &self.decoding, &Validation::default())
+            .map_err(|e| {
+                debug!("Rejected access token: {}", e);
+                Error::InvalidToken
+            })?;
+        Ok(data.claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_unexpired_tokens_signed_with_the_same_secret_should_verify() -> Result<()> {
+        let keys = JwtKeys::new(b"secret");
+        let user_id = Uuid::new_v4();
+        let token = keys.issue(user_id, Role::Admin, Utc::now())?;
+        let claims = keys.verify(&token)?;
+        assert_eq!((claims.sub, claims.role), (user_id, Role::Admin));
+
+        assert!(JwtKeys::new(b"another secret").verify(&token).is_err());
+        let expired = keys.issue(user_id, Role::Player, Utc::now() - TimeDelta::days(1))?;
+        assert!(keys.verify(&expired).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use eyre::Result;
+use log::{error, info};
+use tokio::time::interval;
+use uuid::Uuid;
+
+use crate::service::game::GameService;
+
+// well inside the lease, so one slow round does not lose the rooms
+const RENEW_INTERVAL: Duration = Duration::from_secs(10);
+
+// Keeps the leases on the rooms this instance serves, gives up the ones another replica took
+// over, and takes on rooms left without an owner.
+#[derive(Clone)]
+pub struct RoomLeases {
+    pub game_service: GameService,
+}
+
+impl RoomLeases {
+    pub async fn run(self) {
+        let mut interval = interval(RENEW_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.renew().await {
+                error!("Error occurred when renewing room leases: {:?}", e);
+            }
+        }
+    }
+
+    async fn renew(&self) -> Result<()> {
+        let held: Vec<Uuid> = self
+            .game_service
+            .room_repository
+            .rooms
+            .iter()
+            .map(|room| *room.key())
+            .collect();
+        let renewed: HashSet<Uuid> = self
+            .game_service
+            .room_info_repository
+            .renew_leases(held.clone())
+            .await?
+            .into_iter()
+            .collect();
+        for room_id in held.iter().filter(|id| !renewed.contains(id)) {
+            error!(
+                "Lost the lease on room {}, another instance serves it now",
+                room_id
+            );
+            self.game_service.give_up_room(*room_id);
+        }
+        // the rooms still held are left out, only the ones taken on just now get adopted
+        let claimed = self
+            .game_service
+            .room_info_repository
+            .claim_rooms(renewed.into_iter().collect())
+            .await?;
+        if !claimed.is_empty() {
+            info!("Took on {} rooms left without an instance", claimed.len());
+            self.game_service.adopt_rooms(claimed).await?;
+        }
+        Ok(())
+    }
+}
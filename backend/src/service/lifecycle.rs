@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use eyre::Result;
+use log::{error, warn};
+use tokio::time::interval;
+
+use crate::service::game::GameService;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const CLOSING_NOTICE: TimeDelta = TimeDelta::minutes(10);
+
+// Watches rooms with a closing time: warns the table ahead of the deadline,
+// then cashes everyone out and closes the room once the last hand has finished.
+#[derive(Clone)]
+pub struct RoomLifecycleManager {
+    pub game_service: GameService,
+}
+
+impl RoomLifecycleManager {
+    pub async fn run(self) {
+        let mut interval = interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.check_rooms().await {
+                error!("Error occurred when checking room lifecycles: {:?}", e);
+            }
+        }
+    }
+
+    async fn check_rooms(&self) -> Result<()> {
+        let now = Utc::now();
+        let scheduled_rooms: Vec<_> = self
+            .game_service
+            .room_repository
+            .rooms
+            .iter()
+            .filter_map(|room| room.closes_at.map(|closes_at| (room.id, closes_at)))
+            .collect();
+        for (room_id, closes_at) in scheduled_rooms {
+            if now >= closes_at {
+                let can_close = self
+                    .game_service
+                    .room_repository
+                    .get(room_id)
+                    .is_some_and(|room| room.can_close());
+                if can_close {
+                    // one room failing to close shouldn't hold up the others
+                    if let Err(e) = self.game_service.close_room(room_id).await {
+                        warn!("Error occurred when closing room {}: {:?}", room_id, e);
+                    }
+                }
+            } else if closes_at - now <= CLOSING_NOTICE {
+                if let Err(e) = self.game_service.warn_room_closing(room_id).await {
+                    warn!("Error occurred when warning room {}: {:?}", room_id, e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
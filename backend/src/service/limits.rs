@@ -0,0 +1,157 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use eyre::{bail, Result};
+use log::warn;
+use serde::Serialize;
+
+use types::error::Error;
+
+/// Clients are told to retry after this many seconds when the server sheds load
+pub const RETRY_AFTER_SECS: u64 = 30;
+
+const DEFAULT_MAX_SOCKETS: usize = 1000;
+const DEFAULT_MAX_ROOMS: usize = 100;
+const DEFAULT_MAX_PLAYERS: usize = 500;
+
+// Caps on what the server takes on, so a small deployment turns new work away
+// instead of running out of memory in the middle of a hand
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ServerLimits {
+    pub max_sockets: usize,
+    pub max_rooms: usize,
+    pub max_players: usize,
+}
+
+impl Default for ServerLimits {
+    fn default() -> Self {
+        Self {
+            max_sockets: DEFAULT_MAX_SOCKETS,
+            max_rooms: DEFAULT_MAX_ROOMS,
+            max_players: DEFAULT_MAX_PLAYERS,
+        }
+    }
+}
+
+impl ServerLimits {
+    /// Reads MAX_SOCKETS, MAX_ROOMS and MAX_PLAYERS, falling back to the defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_sockets: read_env("MAX_SOCKETS").unwrap_or(defaults.max_sockets),
+            max_rooms: read_env("MAX_ROOMS").unwrap_or(defaults.max_rooms),
+            max_players: read_env("MAX_PLAYERS").unwrap_or(defaults.max_players),
+        }
+    }
+}
+
+fn read_env(key: &str) -> Option<usize> {
+    std::env::var(key).ok().and_then(|value| value.parse().ok())
+}
+
+#[derive(Debug, Default)]
+struct LoadMetrics {
+    sockets: AtomicUsize,
+    rejected_sockets: AtomicU64,
+    rejected_rooms: AtomicU64,
+    rejected_players: AtomicU64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoadMetricsSnapshot {
+    pub limits: ServerLimits,
+    pub sockets: usize,
+    pub rejected_sockets: u64,
+    pub rejected_rooms: u64,
+    pub rejected_players: u64,
+}
+
+#[derive(Clone, Default)]
+pub struct LoadShedder {
+    limits: ServerLimits,
+    metrics: Arc<LoadMetrics>,
+}
+
+impl LoadShedder {
+    pub fn new(limits: ServerLimits) -> Self {
+        Self {
+            limits,
+            metrics: Arc::default(),
+        }
+    }
+
+    /// Takes a socket slot, to be given back with `release_socket` on disconnect
+    pub fn acquire_socket(&self) -> Result<()> {
+        let acquired = self
+            .metrics
+            .sockets
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |sockets| {
+                (sockets < self.limits.max_sockets).then_some(sockets + 1)
+            });
+        if acquired.is_err() {
+            self.metrics.rejected_sockets.fetch_add(1, Ordering::Relaxed);
+            warn!("Refused a connection, at {} sockets", self.limits.max_sockets);
+            bail!(Error::ServerAtCapacity("connection"));
+        }
+        Ok(())
+    }
+
+    pub fn release_socket(&self) {
+        let _ = self
+            .metrics
+            .sockets
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |sockets| {
+                sockets.checked_sub(1)
+            });
+    }
+
+    pub fn check_rooms(&self, open_rooms: usize) -> Result<()> {
+        if open_rooms >= self.limits.max_rooms {
+            self.metrics.rejected_rooms.fetch_add(1, Ordering::Relaxed);
+            warn!("Refused to open a room, at {} rooms", open_rooms);
+            bail!(Error::ServerAtCapacity("room"));
+        }
+        Ok(())
+    }
+
+    pub fn check_players(&self, seated_players: usize) -> Result<()> {
+        if seated_players >= self.limits.max_players {
+            self.metrics.rejected_players.fetch_add(1, Ordering::Relaxed);
+            warn!("Refused to seat a player, at {} players", seated_players);
+            bail!(Error::ServerAtCapacity("player"));
+        }
+        Ok(())
+    }
+
+    pub fn metrics(&self) -> LoadMetricsSnapshot {
+        LoadMetricsSnapshot {
+            limits: self.limits,
+            sockets: self.metrics.sockets.load(Ordering::Relaxed),
+            rejected_sockets: self.metrics.rejected_sockets.load(Ordering::Relaxed),
+            rejected_rooms: self.metrics.rejected_rooms.load(Ordering::Relaxed),
+            rejected_players: self.metrics.rejected_players.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sockets_over_the_limit_should_be_refused_until_one_is_released() {
+        let shedder = LoadShedder::new(ServerLimits {
+            max_sockets: 2,
+            ..Default::default()
+        });
+        assert!(shedder.acquire_socket().is_ok());
+        assert!(shedder.acquire_socket().is_ok());
+        assert!(shedder.acquire_socket().is_err());
+
+        shedder.release_socket();
+        assert!(shedder.acquire_socket().is_ok());
+        let metrics = shedder.metrics();
+        assert_eq!(metrics.sockets, 2);
+        assert_eq!(metrics.rejected_sockets, 1);
+    }
+}
@@ -0,0 +1,155 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use eyre::{eyre, Result};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use sqlx::types::Uuid;
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Sends plain text emails to players
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, to: &'a str, subject: &'a str, body: String)
+        -> BoxFuture<'a, Result<()>>;
+}
+
+/// Sends through an SMTP relay over TLS
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(host: &str, credentials: Option<(String, String)>, from: &str) -> Result<Self> {
+        let mut transport = AsyncSmtpTransport::<Tokio1Executor>::relay(host)?;
+        if let Some((username, password)) = credentials {
+            transport = transport.credentials(Credentials::new(username, password));
+        }
+        Ok(Self {
+            transport: transport.build(),
+            from: from.parse()?,
+        })
+    }
+
+    /// Reads SMTP_HOST, SMTP_USERNAME, SMTP_PASSWORD and MAIL_FROM, None without a host
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(host) = std::env::var("SMTP_HOST")
+            .ok()
+            .filter(|host| !host.is_empty())
+        else {
+            return Ok(None);
+        };
+        let credentials = std::env::var("SMTP_USERNAME")
+            .ok()
+            .zip(std::env::var("SMTP_PASSWORD").ok());
+        let from = std::env::var("MAIL_FROM").map_err(|_| eyre!("MAIL_FROM must be set"))?;
+        Self::new(&host, credentials, &from).map(Some)
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send<'a>(
+        &'a self,
+        to: &'a str,
+        subject: &'a str,
+        body: String,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let email = Message::builder()
+                .from(self.from.clone())
+                .to(to.parse()?)
+                .subject(subject)
+                .header(ContentType::TEXT_PLAIN)
+                .body(body)?;
+            self.transport.send(email).await?;
+            Ok(())
+        })
+    }
+}
+
+/// The emails an account gets about itself: the signup verification link and password resets
+#[derive(Clone)]
+pub struct AccountMail {
+    pub mailer: Arc<dyn Mailer>,
+    // where players reach this server, verification links point at its /verify endpoint
+    pub public_url: String,
+}
+
+impl AccountMail {
+    pub fn verification_link(&self, token: Uuid) -> String {
+        format!(
+            "{}/verify?token={}",
+            self.public_url.trim_end_matches('/'),
+            token
+        )
+    }
+
+    pub async fn send_verification(&self, email: &str, token: Uuid) -> Result<()> {
+        let body = format!(
+            "Welcome to the table!\n\nOpen this link to verify your email, then log in:\n{}\n\n\
+            The link works once and expires in a day.\n",
+            self.verification_link(token)
+        );
+        self.mailer
+            .send(email, "Verify your poker account", body)
+            .await
+    }
+
+    pub async fn send_password_reset(&self, email: &str, code: &str) -> Result<()> {
+        let body = format!(
+            "Someone asked to reset the password of your poker account.\n\n\
+            Enter this code on the login screen with your new password:\n{}\n\n\
+            It works once and expires in 30 minutes. If it wasn't you, ignore this email.\n",
+            code
+        );
+        self.mailer
+            .send(email, "Reset your poker password", body)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Outbox {
+        sent: Mutex<Vec<(String, String)>>,
+    }
+
+    impl Mailer for Outbox {
+        fn send<'a>(
+            &'a self,
+            to: &'a str,
+            _subject: &'a str,
+            body: String,
+        ) -> BoxFuture<'a, Result<()>> {
+            self.sent.lock().unwrap().push((to.to_string(), body));
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn verification_email_should_carry_a_link_to_the_server() -> Result<()> {
+        let outbox = Arc::new(Outbox::default());
+        let mail = AccountMail {
+            mailer: outbox.clone(),
+            public_url: "https://poker.example.com/".to_string(),
+        };
+        let token = Uuid::new_v4();
+        mail.send_verification("player@example.com", token).await?;
+
+        let sent = outbox.sent.lock().unwrap();
+        let (to, body) = &sent[0];
+        assert_eq!(to, "player@example.com");
+        let link = format!("https://poker.example.com/verify?token={}", token);
+        assert!(body.contains(&link));
+        Ok(())
+    }
+}
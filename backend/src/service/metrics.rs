@@ -0,0 +1,138 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::error;
+use sqlx::PgPool;
+use tokio::sync::broadcast::error::RecvError;
+
+use types::webhook::RoomEvent;
+
+use crate::service::events::RoomEvents;
+
+// upper bounds of the action latency buckets, in seconds
+const LATENCY_BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+#[derive(Debug, Default)]
+struct LatencyHistogram {
+    buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl LatencyHistogram {
+    fn observe(&self, took: Duration) {
+        let seconds = took.as_secs_f64();
+        if let Some(i) = LATENCY_BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(took.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, name: &str, out: &mut String) {
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        // prometheus buckets are cumulative
+        let mut cumulative = 0;
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(self.buckets.iter()) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{}_sum {}", name, sum);
+        let _ = writeln!(out, "{}_count {}", name, count);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    hands_played: AtomicU64,
+    action_latency: LatencyHistogram,
+}
+
+/// Values read from the rest of the server when metrics are scraped
+pub struct Gauges {
+    pub active_rooms: usize,
+    pub connected_sockets: usize,
+}
+
+// Server wide metrics served in the Prometheus text format at /metrics
+#[derive(Clone)]
+pub struct ServerMetrics {
+    pool: PgPool,
+    counters: Arc<Counters>,
+}
+
+impl ServerMetrics {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            counters: Arc::default(),
+        }
+    }
+
+    /// Counts hands as rooms report them finished
+    pub async fn run(self, events: RoomEvents) {
+        let mut events = events.subscribe();
+        loop {
+            match events.recv().await {
+                Ok((_, RoomEvent::HandCompleted { .. })) => {
+                    self.counters.hands_played.fetch_add(1, Ordering::Relaxed);
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    error!("Metrics fell behind, {} room events dropped", skipped)
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+
+    pub fn observe_action(&self, took: Duration) {
+        self.counters.action_latency.observe(took);
+    }
+
+    pub fn render(&self, gauges: Gauges) -> String {
+        let mut out = String::new();
+        let hands_played = self.counters.hands_played.load(Ordering::Relaxed);
+        render_metric(&mut out, "poker_hands_played_total", "counter", hands_played);
+        render_metric(&mut out, "poker_active_rooms", "gauge", gauges.active_rooms);
+        render_metric(&mut out, "poker_connected_sockets", "gauge", gauges.connected_sockets);
+        render_metric(&mut out, "poker_db_pool_connections", "gauge", self.pool.size());
+        render_metric(&mut out, "poker_db_pool_idle_connections", "gauge", self.pool.num_idle());
+        self.counters
+            .action_latency
+            .render("poker_action_latency_seconds", &mut out);
+        out
+    }
+}
+
+fn render_metric(out: &mut String, name: &str, kind: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_should_be_cumulative() {
+        let histogram = LatencyHistogram::default();
+        histogram.observe(Duration::from_micros(500));
+        histogram.observe(Duration::from_millis(20));
+        histogram.observe(Duration::from_secs(5));
+        let mut out = String::new();
+        histogram.render("latency", &mut out);
+
+        assert!(out.contains("latency_bucket{le=\"0.001\"} 1\n"));
+        assert!(out.contains("latency_bucket{le=\"0.025\"} 2\n"));
+        assert!(out.contains("latency_bucket{le=\"2.5\"} 2\n"));
+        assert!(out.contains("latency_bucket{le=\"+Inf\"} 3\n"));
+        assert!(out.contains("latency_count 3\n"));
+    }
+}
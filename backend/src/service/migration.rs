@@ -0,0 +1,27 @@
+use eyre::Result;
+
+use types::crypto::{HandKey, SecretKey};
+use types::room::RoomExport;
+
+use crate::service::game::GameService;
+
+/// Hands live rooms from one server to the next on a blue/green deploy
+#[derive(Clone)]
+pub struct MigrationService {
+    pub game_service: GameService,
+    // rooms exported to this server are sealed to this key, a new one is made on every start
+    pub import_key: HandKey,
+    pub import_secret: SecretKey,
+}
+
+impl MigrationService {
+    pub async fn export_rooms(&self, key: &HandKey) -> Result<Vec<RoomExport>> {
+        self.game_service.export_rooms(key).await
+    }
+
+    pub async fn import_rooms(&self, exports: Vec<RoomExport>) -> Result<usize> {
+        self.game_service
+            .import_rooms(exports, &self.import_secret)
+            .await
+    }
+}
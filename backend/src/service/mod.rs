@@ -1,3 +1,25 @@
+pub(crate) mod adapter;
+pub(crate) mod admin;
 pub(crate) mod auth;
+pub(crate) mod bots;
+pub(crate) mod broadcast;
+pub(crate) mod chat;
+pub(crate) mod engine;
+pub(crate) mod events;
+pub(crate) mod feedback;
+pub(crate) mod friends;
 pub(crate) mod game;
+pub(crate) mod integrity;
+pub(crate) mod invites;
+pub(crate) mod jwt;
+pub(crate) mod leases;
+pub(crate) mod lifecycle;
+pub(crate) mod limits;
+pub(crate) mod mail;
+pub(crate) mod metrics;
+pub(crate) mod migration;
+pub(crate) mod outbox;
+pub(crate) mod seasons;
+pub(crate) mod turn_timer;
 pub(crate) mod users;
+pub(crate) mod webhooks;
@@ -0,0 +1,206 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::interval;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use types::room::{RoomSnapshot, SeatSnapshot};
+
+use crate::service::game::GameService;
+
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A balance change for a seat that the database did not confirm
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StagedChange {
+    // chips of a player who left the table, owed to their balance
+    CashOut {
+        room_id: Uuid,
+        user_id: Uuid,
+        chips: i64,
+    },
+    // a buy-in that may have been taken for a seat the player never got
+    RevertBuyIn {
+        room_id: Uuid,
+        user_id: Uuid,
+        chips: i64,
+    },
+}
+
+impl StagedChange {
+    /// Room, user and chips the change is about
+    pub fn seat(&self) -> (Uuid, Uuid, i64) {
+        match *self {
+            Self::CashOut {
+                room_id,
+                user_id,
+                chips,
+            }
+            | Self::RevertBuyIn {
+                room_id,
+                user_id,
+                chips,
+            } => (room_id, user_id, chips),
+        }
+    }
+}
+
+// Changes staged while the database was unreachable, in the order they happened.
+// They only live in memory, shutting down hands what is left to the room snapshots.
+#[derive(Clone, Default)]
+pub struct Outbox {
+    staged: Arc<Mutex<VecDeque<StagedChange>>>,
+}
+
+impl Outbox {
+    pub fn stage(&self, change: StagedChange) {
+        warn!("Staged {:?} until the database is reachable", change);
+        self.lock().push_back(change);
+    }
+
+    pub fn take_all(&self) -> Vec<StagedChange> {
+        self.lock().drain(..).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<StagedChange>> {
+        // the queue is never left half written, so a poisoned lock is still usable
+        self.staged.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Adds what the staged changes owe to the snapshots saved on shutdown, so the next start
+/// pays it back like any other seat. Changes already applied are skipped there, as the
+/// refund only goes to users still marked as sitting in the room.
+pub fn add_to_snapshots(changes: Vec<StagedChange>, snapshots: &mut Vec<RoomSnapshot>) {
+    let seated: HashSet<(Uuid, Uuid)> = snapshots
+        .iter()
+        .flat_map(|s| s.seats.iter().map(|seat| (s.room_id, seat.player_id)))
+        .collect();
+    for change in changes {
+        let (room_id, user_id, chips) = change.seat();
+        // the player got a seat after all, that buy-in is theirs to play
+        if matches!(change, StagedChange::RevertBuyIn { .. })
+            && seated.contains(&(room_id, user_id))
+        {
+            continue;
+        }
+        let snapshot = match snapshots.iter_mut().position(|s| s.room_id == room_id) {
+            Some(index) => &mut snapshots[index],
+            None => {
+                snapshots.push(RoomSnapshot {
+                    room_id,
+                    seats: vec![],
+                });
+                snapshots.last_mut().expect("snapshot was just pushed")
+            }
+        };
+        match snapshot.seats.iter_mut().find(|s| s.player_id == user_id) {
+            Some(seat) => seat.chips += chips as u32,
+            None => snapshot.seats.push(SeatSnapshot {
+                player_id: user_id,
+                chips: chips as u32,
+            }),
+        }
+    }
+}
+
+// Replays staged changes once the database answers again
+#[derive(Clone)]
+pub struct OutboxRelay {
+    pub game_service: GameService,
+}
+
+impl OutboxRelay {
+    pub async fn run(self) {
+        let mut interval = interval(RETRY_INTERVAL);
+        loop {
+            interval.tick().await;
+            if self.game_service.outbox.is_empty() {
+                continue;
+            }
+            let staged = self.game_service.outbox.len();
+            let left = self.game_service.replay_staged().await;
+            if left < staged {
+                info!("Applied {} staged changes, {} left", staged - left, left);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staged_changes_should_add_up_with_seats_in_snapshots() {
+        let room_id = Uuid::new_v4();
+        let seated = Uuid::new_v4();
+        let left = Uuid::new_v4();
+        let mut snapshots = vec![RoomSnapshot {
+            room_id,
+            seats: vec![SeatSnapshot {
+                player_id: seated,
+                chips: 100,
+            }],
+        }];
+        let closed_room = Uuid::new_v4();
+        let changes = vec![
+            StagedChange::CashOut {
+                room_id,
+                user_id: seated,
+                chips: 50,
+            },
+            StagedChange::CashOut {
+                room_id,
+                user_id: left,
+                chips: 20,
+            },
+            StagedChange::RevertBuyIn {
+                room_id: closed_room,
+                user_id: left,
+                chips: 30,
+            },
+            StagedChange::RevertBuyIn {
+                room_id,
+                user_id: seated,
+                chips: 40,
+            },
+        ];
+        add_to_snapshots(changes, &mut snapshots);
+        assert_eq!(
+            snapshots,
+            vec![
+                RoomSnapshot {
+                    room_id,
+                    seats: vec![
+                        SeatSnapshot {
+                            player_id: seated,
+                            chips: 150,
+                        },
+                        SeatSnapshot {
+                            player_id: left,
+                            chips: 20,
+                        },
+                    ],
+                },
+                RoomSnapshot {
+                    room_id: closed_room,
+                    seats: vec![SeatSnapshot {
+                        player_id: left,
+                        chips: 30,
+                    }],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use eyre::{ContextCompat, Result};
+use log::{error, info};
+use sqlx::types::Uuid;
+use tokio::time::interval;
+
+use types::domain::Announcement;
+use types::error::Error;
+use types::season::{SeasonReward, SeasonSummary};
+
+use crate::repository::seasons::SeasonRepository;
+use crate::service::adapter::Io;
+use crate::service::admin::announce;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+// how many players make it onto the archived leaderboard
+const STANDINGS_SIZE: i64 = 100;
+
+/// When the current season ends, read from SEASON_ENDS_AT (RFC 3339) and SEASON_RESET_BALANCES
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SeasonSchedule {
+    pub ends_at: Option<DateTime<Utc>>,
+    pub reset_balances: bool,
+}
+
+impl SeasonSchedule {
+    pub fn from_env() -> Self {
+        let ends_at = std::env::var("SEASON_ENDS_AT").ok().and_then(|value| {
+            DateTime::parse_from_rfc3339(&value)
+                .map(|ends_at| ends_at.with_timezone(&Utc))
+                .inspect_err(|e| error!("SEASON_ENDS_AT is not an RFC 3339 date: {:?}", e))
+                .ok()
+        });
+        let reset_balances = std::env::var("SEASON_RESET_BALANCES")
+            .is_ok_and(|value| value != "0" && !value.is_empty());
+        Self {
+            ends_at,
+            reset_balances,
+        }
+    }
+}
+
+// Ends seasons: archives the leaderboard, hands out cosmetic rewards and optionally puts
+// every balance back to the default bankroll. Runs at the scheduled date or when an admin asks.
+#[derive(Clone)]
+pub struct SeasonService {
+    pub season_repository: SeasonRepository,
+    pub schedule: SeasonSchedule,
+    pub io: Io,
+}
+
+impl SeasonService {
+    pub async fn run(self) {
+        let Some(ends_at) = self.schedule.ends_at else {
+            return;
+        };
+        let mut interval = interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if Utc::now() < ends_at {
+                continue;
+            }
+            // a restart after the date finds the season already archived and stops here
+            match self
+                .season_repository
+                .rollover(ends_at, self.schedule.reset_balances, STANDINGS_SIZE)
+                .await
+            {
+                Ok(Some(summary)) => self.announce(&summary).await,
+                Ok(None) => {}
+                Err(e) => {
+                    error!("Error occurred when rolling the season over: {:?}", e);
+                    continue;
+                }
+            }
+            return;
+        }
+    }
+
+    pub async fn rollover(&self, reset_balances: bool) -> Result<SeasonSummary> {
+        let summary = self
+            .season_repository
+            .rollover(Utc::now(), reset_balances, STANDINGS_SIZE)
+            .await?
+            .wrap_err(Error::SeasonAlreadyEnded)?;
+        self.announce(&summary).await;
+        Ok(summary)
+    }
+
+    async fn announce(&self, summary: &SeasonSummary) {
+        info!(
+            "Season {} ended with {} players on the leaderboard",
+            summary.season_id,
+            summary.standings.len()
+        );
+        let announcement = Announcement {
+            id: Uuid::new_v4(),
+            message: announcement_message(summary),
+        };
+        announce(&self.io, &announcement).await;
+    }
+}
+
+fn announcement_message(summary: &SeasonSummary) -> String {
+    let mut message = format!("Season {} is over!", summary.season_id);
+    let champion = summary
+        .standings
+        .iter()
+        .find(|standing| standing.reward == Some(SeasonReward::GoldCrown));
+    if let Some(champion) = champion {
+        message.push_str(&format!(" {} takes the gold crown.", champion.name));
+    }
+    if summary.reset_balances {
+        message.push_str(" Every balance is back to the starting bankroll.");
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::Uuid;
+    use types::season::SeasonStanding;
+
+    use super::*;
+
+    #[test]
+    fn announcement_should_name_the_champion_and_the_reset() {
+        let summary = SeasonSummary {
+            season_id: 3,
+            ended_at: Utc::now(),
+            reset_balances: true,
+            standings: vec![SeasonStanding {
+                user_id: Uuid::new_v4(),
+                name: "Ada".to_string(),
+                rank: 1,
+                balance: 5000,
+                reward: Some(SeasonReward::GoldCrown),
+            }],
+        };
+        assert_eq!(
+            announcement_message(&summary),
+            "Season 3 is over! Ada takes the gold crown. Every balance is back to the starting bankroll."
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use log::error;
+use tokio::time::interval;
+
+use crate::service::game::GameService;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// Acts for players who let their turn clock run out, so one idle player can't stall a table,
+// deals the next hand once the results of the last one have been up long enough,
+// and gives up the seats of players who did not reconnect in time.
+#[derive(Clone)]
+pub struct TurnTimer {
+    pub game_service: GameService,
+}
+
+impl TurnTimer {
+    pub async fn run(self) {
+        let mut interval = interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.check_turns().await;
+        }
+    }
+
+    // a room that fails is logged and left for the next tick, the others still move on
+    async fn check_turns(&self) {
+        let expired_rooms: Vec<_> = self
+            .game_service
+            .room_repository
+            .rooms
+            .iter()
+            .filter(|room| room.is_turn_expired())
+            .map(|room| room.id)
+            .collect();
+        for room_id in expired_rooms {
+            if let Err(e) = self.game_service.expire_turn(room_id).await {
+                error!(
+                    "Error occurred when expiring the turn in room {}: {:?}",
+                    room_id, e
+                );
+            }
+        }
+        let finished_showdowns: Vec<_> = self
+            .game_service
+            .room_repository
+            .rooms
+            .iter()
+            .filter(|room| room.is_showdown_expired())
+            .map(|room| room.id)
+            .collect();
+        for room_id in finished_showdowns {
+            if let Err(e) = self.game_service.end_showdown(room_id).await {
+                error!(
+                    "Error occurred when ending the showdown in room {}: {:?}",
+                    room_id, e
+                );
+            }
+        }
+        self.game_service.release_expired_seats().await;
+    }
+}
@@ -1,21 +1,76 @@
+use std::cmp::Reverse;
 use std::sync::Arc;
 
-use eyre::Result;
+use chrono::{DateTime, Datelike, Days, NaiveTime, TimeDelta, Utc};
+use dashmap::DashMap;
+use eyre::{ensure, ContextCompat, Result};
+use log::info;
 use sqlx::types::Uuid;
 
-use types::domain::User;
+use types::domain::{Avatar, LedgerEntry, TransactionPage, TransactionQuery, User};
+use types::error::Error;
+use types::rules::{DAILY_TOP_UP, TOP_UP_INTERVAL};
+use types::stats::{Leaderboard, LeaderboardPeriod, ProfileStats};
 
+use crate::repository::stats::StatsRepository;
 use crate::repository::users::UserRepository;
 
+const DEFAULT_TRANSACTION_PAGE_SIZE: u32 = 20;
+const MAX_TRANSACTION_PAGE_SIZE: u32 = 100;
+const LEADERBOARD_SIZE: i64 = 50;
+// how long a leaderboard is served before it is summed again
+const LEADERBOARD_TTL: TimeDelta = TimeDelta::minutes(1);
+
 #[derive(Clone)]
 pub struct UserService {
     pub user_repository: Arc<UserRepository>,
+    pub stats_repository: StatsRepository,
+    pub leaderboards: LeaderboardCache,
+}
+
+// The latest leaderboard of each period
+#[derive(Clone, Default)]
+pub struct LeaderboardCache {
+    boards: Arc<DashMap<LeaderboardPeriod, Leaderboard>>,
+}
+
+impl LeaderboardCache {
+    // a board from before the period rolled over is stale however young it is
+    fn get(&self, period: LeaderboardPeriod, now: DateTime<Utc>) -> Option<Leaderboard> {
+        self.boards
+            .get(&period)
+            .filter(|board| {
+                now - board.updated_at < LEADERBOARD_TTL && board.since == period_start(period, now)
+            })
+            .map(|board| board.clone())
+    }
+
+    fn store(&self, board: Leaderboard) {
+        self.boards.insert(board.period, board);
+    }
+}
+
+fn period_start(period: LeaderboardPeriod, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let today = now.date_naive();
+    let start = match period {
+        LeaderboardPeriod::Daily => today,
+        LeaderboardPeriod::Weekly => {
+            today - Days::new(today.weekday().num_days_from_monday() as u64)
+        }
+        LeaderboardPeriod::AllTime => return None,
+    };
+    Some(start.and_time(NaiveTime::MIN).and_utc())
 }
 
 impl UserService {
-    pub async fn upsert_profile(&self, user_id: Uuid, username: String) -> Result<User> {
+    pub async fn upsert_profile(
+        &self,
+        user_id: Uuid,
+        username: String,
+        avatar: Option<Avatar>,
+    ) -> Result<User> {
         self.user_repository
-            .upsert_user_with_username(user_id, username)
+            .upsert_profile(user_id, username, avatar)
             .await
     }
 
@@ -23,7 +78,155 @@ impl UserService {
         self.user_repository.get(user_id).await
     }
 
+    pub async fn get_stats(&self, user_id: Uuid) -> Result<ProfileStats> {
+        let mut hand_classes = self.stats_repository.hand_class_stats(user_id).await?;
+        hand_classes.sort_by_key(|stats| Reverse(stats.hand_class));
+        let lifetime = self
+            .stats_repository
+            .player_stats(user_id)
+            .await?
+            .unwrap_or_default();
+        Ok(ProfileStats {
+            lifetime,
+            hand_classes,
+        })
+    }
+
+    /// Top players by net winnings over the period
+    pub async fn get_leaderboard(&self, period: LeaderboardPeriod) -> Result<Leaderboard> {
+        let now = Utc::now();
+        if let Some(board) = self.leaderboards.get(period, now) {
+            return Ok(board);
+        }
+        let since = period_start(period, now);
+        let entries = self
+            .stats_repository
+            .leaderboard(since, LEADERBOARD_SIZE)
+            .await?;
+        let board = Leaderboard {
+            period,
+            since,
+            entries,
+            updated_at: now,
+        };
+        self.leaderboards.store(board.clone());
+        Ok(board)
+    }
+
+    /// Credits the daily free chips
+    pub async fn top_up(&self, user_id: Uuid) -> Result<LedgerEntry> {
+        let entry = self
+            .user_repository
+            .top_up(user_id, DAILY_TOP_UP, TOP_UP_INTERVAL)
+            .await?
+            .wrap_err(Error::UserNotFound)?;
+        info!("User {} claimed {} free chips", user_id, entry.amount);
+        Ok(entry)
+    }
+
+    pub async fn get_transactions(
+        &self,
+        user_id: Uuid,
+        query: TransactionQuery,
+    ) -> Result<TransactionPage> {
+        if let (Some(from), Some(to)) = (query.from, query.to) {
+            ensure!(from <= to, Error::InvalidDateRange);
+        }
+        let page = query.page.unwrap_or_default();
+        let per_page = query
+            .per_page
+            .unwrap_or(DEFAULT_TRANSACTION_PAGE_SIZE)
+            .clamp(1, MAX_TRANSACTION_PAGE_SIZE);
+        let reasons = query.kind.map(|kind| {
+            kind.reasons()
+                .iter()
+                .map(|reason| reason.as_ref().to_string())
+                .collect()
+        });
+        let since = query.from.map(|day| day.and_time(NaiveTime::MIN).and_utc());
+        // the whole last day is included
+        let until = query
+            .to
+            .and_then(|day| day.succ_opt())
+            .map(|day| day.and_time(NaiveTime::MIN).and_utc());
+        // one extra row tells whether there is another page
+        let mut transactions = self
+            .user_repository
+            .get_transactions(
+                user_id,
+                reasons,
+                since,
+                until,
+                query.currency,
+                per_page as i64 + 1,
+                page as i64 * per_page as i64,
+            )
+            .await?;
+        let has_more = transactions.len() > per_page as usize;
+        transactions.truncate(per_page as usize);
+        Ok(TransactionPage {
+            transactions,
+            page,
+            has_more,
+        })
+    }
+
     pub async fn is_user_in_room(&self, user_id: Uuid, room_id: Uuid) -> Result<bool> {
         self.user_repository.is_user_in_room(user_id, room_id).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn weekly_leaderboard_should_start_on_monday() {
+        // a Thursday afternoon
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 14, 30, 0).unwrap();
+        assert_eq!(
+            period_start(LeaderboardPeriod::Weekly, now),
+            Some(Utc.with_ymd_and_hms(2026, 10, 12, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            period_start(LeaderboardPeriod::Daily, now),
+            Some(Utc.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap())
+        );
+        assert_eq!(period_start(LeaderboardPeriod::AllTime, now), None);
+    }
+
+    #[test]
+    fn cached_leaderboard_should_expire_at_the_ttl_or_when_the_day_ends() {
+        let cache = LeaderboardCache::default();
+        let now = Utc.with_ymd_and_hms(2026, 10, 15, 23, 59, 30).unwrap();
+        cache.store(Leaderboard {
+            period: LeaderboardPeriod::Daily,
+            since: period_start(LeaderboardPeriod::Daily, now),
+            entries: vec![],
+            updated_at: now,
+        });
+        assert!(cache
+            .get(LeaderboardPeriod::Daily, now + TimeDelta::seconds(10))
+            .is_some());
+        assert!(cache.get(LeaderboardPeriod::Weekly, now).is_none());
+        // past midnight
+        assert!(cache
+            .get(LeaderboardPeriod::Daily, now + TimeDelta::seconds(40))
+            .is_none());
+
+        cache.store(Leaderboard {
+            period: LeaderboardPeriod::Weekly,
+            since: period_start(LeaderboardPeriod::Weekly, now),
+            entries: vec![],
+            updated_at: now,
+        });
+        assert!(cache
+            .get(LeaderboardPeriod::Weekly, now + TimeDelta::seconds(40))
+            .is_some());
+        assert!(cache
+            .get(LeaderboardPeriod::Weekly, now + LEADERBOARD_TTL)
+            .is_none());
+    }
+}
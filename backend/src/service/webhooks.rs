@@ -0,0 +1,258 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use dashmap::DashMap;
+use eyre::{ensure, ContextCompat, Result};
+use hmac::{Hmac, Mac};
+use log::{debug, error, info};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Url;
+use sha2::Sha256;
+use sqlx::types::Uuid;
+use tokio::net::lookup_host;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::sleep;
+
+use types::error::Error;
+use types::webhook::{RoomEvent, Webhook, WebhookPayload};
+
+use crate::repository::rooms::RoomInfoRepository;
+use crate::repository::webhooks::WebhookRepository;
+use crate::service::events::RoomEvents;
+
+pub const SIGNATURE_HEADER: &str = "X-Poker-Signature";
+const SECRET_LENGTH: usize = 32;
+const MAX_ATTEMPTS: u32 = 5;
+// doubled after every failed attempt
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MILESTONE_HANDS: u64 = 100;
+const MAX_WEBHOOKS_PER_ROOM: i64 = 5;
+
+// Posts room events to the registered webhooks, signing each body with the webhook's secret
+// and retrying failed deliveries with exponential backoff.
+#[derive(Clone)]
+pub struct WebhookService {
+    pub webhook_repository: WebhookRepository,
+    pub room_info_repository: RoomInfoRepository,
+    pub events: RoomEvents,
+    pub http: reqwest::Client,
+    // hands completed per room since the server started, for milestones
+    pub hands_played: Arc<DashMap<Uuid, u64>>,
+}
+
+impl WebhookService {
+    /// Registers a webhook on a room, or on every room when `room_id` is None.
+    /// Only the player who opened a room, or an admin, can add one to it.
+    pub async fn register(
+        &self,
+        room_id: Option<Uuid>,
+        created_by: Uuid,
+        is_admin: bool,
+        url: String,
+    ) -> Result<Webhook> {
+        let parsed = Url::parse(&url)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .wrap_err(Error::InvalidWebhookUrl)?;
+        if let Some(room_id) = room_id {
+            let room_info = self
+                .room_info_repository
+                .get(room_id)
+                .await?
+                .wrap_err(Error::InvalidRoomId)?;
+            ensure!(
+                is_admin || room_info.created_by == Some(created_by),
+                Error::NotRoomCreator
+            );
+            let registered = self.webhook_repository.count_for_room(room_id).await?;
+            ensure!(
+                registered < MAX_WEBHOOKS_PER_ROOM,
+                Error::TooManyWebhooks(MAX_WEBHOOKS_PER_ROOM)
+            );
+        }
+        ensure!(resolves_publicly(&parsed).await, Error::WebhookUrlNotPublic);
+        let secret: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(SECRET_LENGTH)
+            .map(char::from)
+            .collect();
+        let webhook = self
+            .webhook_repository
+            .create(room_id, &url, &secret, created_by)
+            .await?;
+        info!("User {} registered webhook {} for room {:?}", created_by, webhook.id, room_id);
+        Ok(webhook)
+    }
+
+    pub async fn run(self) {
+        let mut events = self.events.subscribe();
+        loop {
+            match events.recv().await {
+                Ok((room_id, event)) => {
+                    if let Err(e) = self.dispatch(room_id, event).await {
+                        error!("Error occurred when dispatching webhooks: {:?}", e);
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    error!("Webhook delivery fell behind, {} room events dropped", skipped)
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+
+    async fn dispatch(&self, room_id: Uuid, event: RoomEvent) -> Result<()> {
+        let milestone = matches!(event, RoomEvent::HandCompleted { .. })
+            .then(|| {
+                let mut hands_played = self.hands_played.entry(room_id).or_default();
+                *hands_played += 1;
+                *hands_played
+            })
+            .filter(|hands_played| hands_played % MILESTONE_HANDS == 0)
+            .map(|hands_played| RoomEvent::Milestone { hands_played });
+
+        let webhooks = self.webhook_repository.get_for_room(room_id).await?;
+        if webhooks.is_empty() {
+            return Ok(());
+        }
+        for event in [Some(event), milestone].into_iter().flatten() {
+            let payload = WebhookPayload {
+                id: Uuid::new_v4(),
+                room_id,
+                event,
+                sent_at: Utc::now(),
+            };
+            let body = serde_json::to_vec(&payload)?;
+            for webhook in &webhooks {
+                tokio::spawn(self.clone().deliver(webhook.clone(), body.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    async fn deliver(self, webhook: Webhook, body: Vec<u8>) {
+        let signature = sign(&webhook.secret, &body);
+        let mut delay = FIRST_RETRY_DELAY;
+        for attempt in 1..=MAX_ATTEMPTS {
+            let response = self
+                .http
+                .post(&webhook.url)
+                .header(CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, &signature)
+                .body(body.clone())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            match response {
+                Ok(_) => {
+                    debug!("Delivered to webhook {} on attempt {}", webhook.id, attempt);
+                    return;
+                }
+                Err(e) if attempt < MAX_ATTEMPTS => {
+                    debug!("Webhook {} attempt {} failed: {:?}", webhook.id, attempt, e);
+                    sleep(delay).await;
+                    delay *= 2;
+                }
+                Err(e) => error!("Giving up on webhook {}: {:?}", webhook.id, e),
+            }
+        }
+    }
+}
+
+// webhooks are posted from inside the server's network, so they must not reach into it
+async fn resolves_publicly(url: &Url) -> bool {
+    let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+        return false;
+    };
+    // ip literals come in brackets for v6
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match lookup_host((host, port)).await {
+        Ok(addrs) => {
+            let addrs: Vec<_> = addrs.collect();
+            !addrs.is_empty() && addrs.iter().all(|addr| is_public(addr.ip()))
+        }
+        Err(_) => false,
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast())
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// Hex encoded HMAC-SHA256 of the body, sent as `sha256=<hex>`
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_should_be_the_hmac_of_the_body() {
+        assert_eq!(
+            sign("key", b"The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn only_public_addresses_should_be_allowed() {
+        let public = ["93.184.215.14", "2606:4700::1111"];
+        let internal = [
+            "127.0.0.1",
+            "10.0.0.5",
+            "172.16.3.4",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ];
+        for ip in public {
+            assert!(is_public(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in internal {
+            assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn webhooks_to_the_server_network_should_be_rejected() {
+        for url in [
+            "http://localhost:8080/hook",
+            "http://127.0.0.1/hook",
+            "https://[::1]/hook",
+            "http://169.254.169.254/latest/meta-data",
+        ] {
+            assert!(!resolves_publicly(&Url::parse(url).unwrap()).await, "{}", url);
+        }
+        assert!(resolves_publicly(&Url::parse("https://93.184.215.14/hook").unwrap()).await);
+    }
+}
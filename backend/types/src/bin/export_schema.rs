@@ -0,0 +1,26 @@
+//! Writes the JSON schema of every socket payload and REST body, one file each.
+//!
+//! ```text
+//! export_schema [--out schema]
+//! ```
+use std::fs;
+use std::path::PathBuf;
+
+use eyre::Result;
+use types::cli::arg_value;
+use types::schema::{rest_schemas, socket_schemas};
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let out = PathBuf::from(arg_value(&args, "--out").unwrap_or_else(|| "schema".to_string()));
+    for (dir, schemas) in [("socket", socket_schemas()), ("rest", rest_schemas())] {
+        let dir = out.join(dir);
+        fs::create_dir_all(&dir)?;
+        for (name, schema) in schemas {
+            let path = dir.join(format!("{}.json", name));
+            fs::write(&path, serde_json::to_string_pretty(&schema)?)?;
+            println!("{}", path.display());
+        }
+    }
+    Ok(())
+}
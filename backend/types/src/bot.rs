@@ -0,0 +1,460 @@
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use poker::{Card, EvalClass, Evaluator, Rank};
+use uuid::Uuid;
+
+use crate::domain::Action;
+use crate::room::{Hand, Room, Stage};
+use crate::state::{PlayerHand, PlayerState, SharedGameState};
+
+/// Everything a bot is allowed to see: the public table plus its own hole cards.
+#[derive(Debug, Clone)]
+pub struct BotView {
+    pub player_id: Uuid,
+    pub game: SharedGameState,
+    pub hand: PlayerHand,
+}
+
+impl BotView {
+    /// What the bot sees of a room played in-process, with its own cards and nobody else's
+    pub fn from_room(room: &Room, player_id: Uuid) -> Self {
+        let hand = room
+            .players
+            .iter()
+            .find(|p| p.id == player_id)
+            .and_then(|p| p.hand.as_ref())
+            .map(|Hand(cards)| (*cards).into())
+            .unwrap_or_default();
+        Self {
+            player_id,
+            game: SharedGameState::from_room(room.clone(), false).personalized(player_id),
+            hand,
+        }
+    }
+
+    pub fn me(&self) -> Option<&PlayerState> {
+        self.game.players.iter().find(|p| p.id == self.player_id)
+    }
+
+    pub fn to_call(&self) -> u32 {
+        if let Some(personal) = &self.game.personal {
+            return personal.to_call;
+        }
+        let bet = self.me().map_or(0, |p| p.bet);
+        self.game.max_bet().saturating_sub(bet)
+    }
+
+    pub fn chips(&self) -> u32 {
+        self.me().map_or(0, |p| p.chips)
+    }
+
+    pub fn bet(&self) -> u32 {
+        self.me().map_or(0, |p| p.bet)
+    }
+
+    /// Chips already collected into pots plus the bets on the table
+    pub fn pot(&self) -> u32 {
+        self.game.pots.iter().sum::<u32>() + self.game.players.iter().map(|p| p.bet).sum::<u32>()
+    }
+
+    pub fn active_opponents(&self) -> usize {
+        self.game
+            .players
+            .iter()
+            .filter(|p| p.id != self.player_id && !p.has_folded && p.is_connected)
+            .count()
+    }
+
+    pub fn hole_cards(&self) -> Vec<Card> {
+        let PlayerHand(cards) = &self.hand;
+        cards.iter().flatten().map(|card| **card).collect()
+    }
+
+    pub fn board(&self) -> Vec<Card> {
+        self.game
+            .community_cards
+            .iter()
+            .map(|card| **card)
+            .collect()
+    }
+}
+
+/// The actions the server will accept from the bot in its current spot.
+/// Raise amounts are the chips added on top of `bet`. They are sent as [`Action::Bet`] when
+/// there is nothing to call and as [`Action::RaiseTo`] otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LegalActions {
+    pub can_check: bool,
+    pub call: Option<u32>,
+    pub raise: Option<RangeInclusive<u32>>,
+    pub can_all_in: bool,
+    // what the bot already has in front of it this street
+    pub bet: u32,
+}
+
+impl LegalActions {
+    pub fn from_view(view: &BotView) -> Self {
+        let to_call = view.to_call();
+        let chips = view.chips();
+        // chips on top of the bet, the stack unless the table limits raises
+        let max_raise = match view.game.min_raise_amount(view.player_id) {
+            Some(_) => view
+                .game
+                .max_raise_to(view.player_id)
+                .map_or(chips, |max| max.saturating_sub(view.bet())),
+            None => 0,
+        };
+        Self {
+            can_check: to_call == 0,
+            call: (to_call > 0 && to_call <= chips).then_some(to_call),
+            // putting in every chip is an all-in, not a raise
+            raise: view
+                .game
+                .min_raise_amount(view.player_id)
+                .filter(|min| chips > *min)
+                .map(|min| min..=max_raise.min(chips - 1)),
+            // an all-in that raises is only allowed while raising is, and up to the limit
+            can_all_in: chips > 0 && (chips <= to_call || max_raise >= chips),
+            bet: view.bet(),
+        }
+    }
+
+    pub fn allows(&self, action: &Action) -> bool {
+        match action {
+            Action::Fold => true,
+            Action::Check => self.can_check,
+            Action::Call => self.call.is_some(),
+            Action::Bet(_) if !self.can_check => false,
+            Action::Raise(amount) => self.raise.as_ref().is_some_and(|r| r.contains(amount)),
+            Action::Bet(total) | Action::RaiseTo(total) => self
+                .raise
+                .as_ref()
+                .is_some_and(|r| total.checked_sub(self.bet).is_some_and(|a| r.contains(&a))),
+            Action::AllIn => self.can_all_in,
+        }
+    }
+
+    /// Raise by `amount` if possible, clamped into the legal range
+    pub fn raise_or_call(&self, amount: u32) -> Action {
+        match &self.raise {
+            Some(range) => {
+                let total = self.bet + amount.clamp(*range.start(), *range.end());
+                if self.can_check {
+                    Action::Bet(total)
+                } else {
+                    Action::RaiseTo(total)
+                }
+            }
+            None => self.call_or_check(),
+        }
+    }
+
+    pub fn call_or_check(&self) -> Action {
+        if self.can_check {
+            Action::Check
+        } else if self.call.is_some() {
+            Action::Call
+        } else if self.can_all_in {
+            Action::AllIn
+        } else {
+            Action::Fold
+        }
+    }
+
+    pub fn check_or_fold(&self) -> Action {
+        if self.can_check {
+            Action::Check
+        } else {
+            Action::Fold
+        }
+    }
+}
+
+/// An action seen at the table during the current hand
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub player: Uuid,
+    pub stage: Stage,
+    pub action: Action,
+}
+
+impl HistoryEntry {
+    /// The actions taken so far in the room's current hand
+    pub fn from_room(room: &Room) -> Vec<Self> {
+        room.action_log
+            .iter()
+            .map(|logged| HistoryEntry {
+                player: logged.player_id,
+                stage: logged.stage.clone(),
+                action: logged.action,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decision {
+    pub action: Action,
+    /// How long to wait before sending the action, so bots don't act instantly
+    pub think_time: Duration,
+}
+
+/// Decision making for a bot seat, shared by the bot client, practice tables and the server's
+/// bot seats.
+pub trait Strategy: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn decide(
+        &mut self,
+        view: &BotView,
+        legal: &LegalActions,
+        history: &[HistoryEntry],
+    ) -> Decision;
+}
+
+/// Rough pre-flop tiers: 0 is junk, 3 is a premium hand
+fn starting_hand_tier(cards: &[Card]) -> u8 {
+    let [a, b] = cards else {
+        return 0;
+    };
+    let (high, low) = if a.rank() >= b.rank() {
+        (a.rank(), b.rank())
+    } else {
+        (b.rank(), a.rank())
+    };
+    let suited = a.suit() == b.suit();
+    match (high, low) {
+        (h, l) if h == l && h >= Rank::Jack => 3,
+        (Rank::Ace, Rank::King) => 3,
+        (h, l) if h == l && h >= Rank::Seven => 2,
+        (h, l) if h >= Rank::Queen && l >= Rank::Ten => 2,
+        (h, l) if h == l => 1,
+        (Rank::Ace, _) => 1,
+        (h, l) if suited && h as u8 - l as u8 == 1 => 1,
+        (h, l) if h >= Rank::Ten && l >= Rank::Nine => 1,
+        _ => 0,
+    }
+}
+
+fn made_hand(evaluator: &Evaluator, view: &BotView) -> Option<EvalClass> {
+    let cards = [view.hole_cards(), view.board()].concat();
+    evaluator.evaluate(cards).ok().map(|eval| eval.class())
+}
+
+fn is_pair_or_better(class: &EvalClass) -> bool {
+    !matches!(class, EvalClass::HighCard { .. })
+}
+
+fn is_two_pair_or_better(class: &EvalClass) -> bool {
+    !matches!(class, EvalClass::HighCard { .. } | EvalClass::Pair { .. })
+}
+
+/// Plays few hands and never raises: calls with strong holdings, otherwise checks or folds.
+pub struct TightPassive {
+    evaluator: Evaluator,
+}
+
+impl Default for TightPassive {
+    fn default() -> Self {
+        Self {
+            evaluator: Evaluator::new(),
+        }
+    }
+}
+
+impl Strategy for TightPassive {
+    fn name(&self) -> &str {
+        "tight-passive"
+    }
+
+    fn decide(
+        &mut self,
+        view: &BotView,
+        legal: &LegalActions,
+        _history: &[HistoryEntry],
+    ) -> Decision {
+        let action = match made_hand(&self.evaluator, view) {
+            None if starting_hand_tier(&view.hole_cards()) >= 2 => legal.call_or_check(),
+            None => legal.check_or_fold(),
+            Some(class) if is_two_pair_or_better(&class) => legal.call_or_check(),
+            // a single pair is only worth a small bet
+            Some(class) if is_pair_or_better(&class) && view.to_call() <= view.pot() / 4 => {
+                legal.call_or_check()
+            }
+            Some(_) => legal.check_or_fold(),
+        };
+        Decision {
+            action,
+            think_time: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Plays most hands and bets them hard, bluffing when few opponents are left.
+pub struct LooseAggressive {
+    evaluator: Evaluator,
+}
+
+impl Default for LooseAggressive {
+    fn default() -> Self {
+        Self {
+            evaluator: Evaluator::new(),
+        }
+    }
+}
+
+impl Strategy for LooseAggressive {
+    fn name(&self) -> &str {
+        "loose-aggressive"
+    }
+
+    fn decide(
+        &mut self,
+        view: &BotView,
+        legal: &LegalActions,
+        history: &[HistoryEntry],
+    ) -> Decision {
+        let pot = view.pot().max(1);
+        let raises_this_stage = history
+            .iter()
+            .filter(|entry| entry.stage == view.game.stage)
+            .filter(|entry| {
+                matches!(
+                    entry.action,
+                    Action::Bet(_) | Action::RaiseTo(_) | Action::AllIn
+                )
+            })
+            .count();
+        let action = match made_hand(&self.evaluator, view) {
+            None => match starting_hand_tier(&view.hole_cards()) {
+                3 => legal.raise_or_call(view.to_call() + pot),
+                1 | 2 if raises_this_stage < 2 => legal.raise_or_call(view.to_call() + pot / 2),
+                1 | 2 => legal.call_or_check(),
+                _ if view.to_call() <= view.chips() / 10 => legal.call_or_check(),
+                _ => legal.check_or_fold(),
+            },
+            Some(class) if is_two_pair_or_better(&class) => {
+                legal.raise_or_call(view.to_call() + pot)
+            }
+            Some(class) if is_pair_or_better(&class) => {
+                legal.raise_or_call(view.to_call() + pot * 2 / 3)
+            }
+            // nothing made yet, take a stab at small pots
+            Some(_) if legal.can_check && view.active_opponents() <= 2 => {
+                legal.raise_or_call(pot / 2)
+            }
+            Some(_) => legal.check_or_fold(),
+        };
+        Decision {
+            action,
+            think_time: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Strategy from its name as used on the command line
+pub fn strategy_by_name(name: &str) -> Option<Box<dyn Strategy>> {
+    match name {
+        "tight-passive" => Some(Box::new(TightPassive::default())),
+        "loose-aggressive" => Some(Box::new(LooseAggressive::default())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use poker::cards;
+
+    use crate::room::{Player, Pot};
+
+    use super::*;
+
+    // the bot faces `to_call` more than it has in, against two opponents still in the hand
+    fn spot(hole_cards: [Card; 2], board: Vec<Card>, to_call: u32) -> (Room, Uuid) {
+        let mut room = Room::new();
+        room.stage = match board.len() {
+            0 => Stage::PreFlop,
+            3 => Stage::Flop,
+            4 => Stage::Turn,
+            _ => Stage::River,
+        };
+        room.community_cards = board;
+        room.pots = vec![Pot {
+            amount: 30,
+            players: HashSet::new(),
+        }];
+        let mut bot = Player::new("Bot 1".to_string(), 200);
+        bot.hand = Some(Hand(hole_cards));
+        bot.bet = 2;
+        let mut raiser = Player::new("Alice".to_string(), 200);
+        raiser.seat = 1;
+        raiser.bet = 2 + to_call;
+        let mut caller = Player::new("Bob".to_string(), 200);
+        caller.seat = 2;
+        caller.bet = 2 + to_call;
+        let bot_id = bot.id;
+        room.last_raise_size = to_call.max(2);
+        room.players = vec![bot, raiser, caller];
+        room.player_in_turn = Some(bot_id);
+        (room, bot_id)
+    }
+
+    fn decide(strategy: &mut dyn Strategy, room: &Room, bot_id: Uuid) -> Action {
+        let view = BotView::from_room(room, bot_id);
+        let legal = LegalActions::from_view(&view);
+        let action = strategy
+            .decide(&view, &legal, &HistoryEntry::from_room(room))
+            .action;
+        assert!(
+            legal.allows(&action),
+            "{} chose {:?}",
+            strategy.name(),
+            action
+        );
+        action
+    }
+
+    #[test]
+    fn tight_passive_should_call_a_raise_with_a_premium_pair_but_never_raise() {
+        let mut strategy = TightPassive::default();
+        let (room, bot_id) = spot(cards!(Ace, Spades; Ace, Hearts;), vec![], 10);
+        assert_eq!(decide(&mut strategy, &room, bot_id), Action::Call);
+        // nothing to call, it still only checks
+        let (room, bot_id) = spot(cards!(Ace, Spades; Ace, Hearts;), vec![], 0);
+        assert_eq!(decide(&mut strategy, &room, bot_id), Action::Check);
+    }
+
+    #[test]
+    fn tight_passive_should_fold_air_facing_a_bet() {
+        let mut strategy = TightPassive::default();
+        let board = cards!(King, Clubs; Queen, Diamonds; Nine, Spades;).to_vec();
+        let (room, bot_id) = spot(cards!(Seven, Spades; Two, Hearts;), board, 10);
+        assert_eq!(decide(&mut strategy, &room, bot_id), Action::Fold);
+    }
+
+    #[test]
+    fn loose_aggressive_should_reraise_a_premium_pair() {
+        let mut strategy = LooseAggressive::default();
+        let (room, bot_id) = spot(cards!(Ace, Spades; Ace, Hearts;), vec![], 10);
+        assert!(matches!(
+            decide(&mut strategy, &room, bot_id),
+            Action::RaiseTo(_)
+        ));
+    }
+
+    #[test]
+    fn loose_aggressive_should_fold_air_facing_a_bet_but_stab_when_checked_to() {
+        let mut strategy = LooseAggressive::default();
+        let board = cards!(King, Clubs; Queen, Diamonds; Nine, Spades;).to_vec();
+        let (room, bot_id) = spot(cards!(Seven, Spades; Two, Hearts;), board.clone(), 10);
+        assert_eq!(decide(&mut strategy, &room, bot_id), Action::Fold);
+        let (room, bot_id) = spot(cards!(Seven, Spades; Two, Hearts;), board, 0);
+        assert!(matches!(
+            decide(&mut strategy, &room, bot_id),
+            Action::Bet(_)
+        ));
+    }
+}
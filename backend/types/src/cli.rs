@@ -0,0 +1,18 @@
+/// The value following `name` on the command line, like the room id of `--room <room id>`
+pub fn arg_value(args: &[String], name: &str) -> Option<String> {
+    args.iter().skip_while(|arg| *arg != name).nth(1).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arg_value_should_be_the_argument_after_its_name() {
+        let args = ["bot", "--room", "abc", "--buy-in"].map(String::from);
+        assert_eq!(arg_value(&args, "--room").as_deref(), Some("abc"));
+        // a flag given without its value, or not given at all
+        assert_eq!(arg_value(&args, "--buy-in"), None);
+        assert_eq!(arg_value(&args, "--strategy"), None);
+    }
+}
@@ -0,0 +1,125 @@
+use crypto_box::aead::OsRng;
+use crypto_box::PublicKey;
+pub use crypto_box::SecretKey;
+use eyre::{eyre, Result};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::room::HiddenCards;
+
+/// Public half of the key pair a client generates for each connection, hands are sealed to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandKey(pub PublicKey);
+
+// the public key is written out as a hex string in JSON
+impl JsonSchema for HandKey {
+    fn schema_name() -> String {
+        "HandKey".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        String::json_schema(gen)
+    }
+}
+
+impl HandKey {
+    pub fn generate() -> (Self, SecretKey) {
+        let secret = SecretKey::generate(&mut OsRng);
+        (Self(secret.public_key()), secret)
+    }
+
+    /// The same key pair on every start, for what the server seals to read back itself
+    pub fn from_secret(bytes: [u8; 32]) -> (Self, SecretKey) {
+        let secret = SecretKey::from(bytes);
+        (Self(secret.public_key()), secret)
+    }
+}
+
+/// Hole cards only the holder of the matching secret key can read
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SealedHand {
+    pub ciphertext: Vec<u8>,
+}
+
+impl SealedHand {
+    /// Seals a `PlayerHand`, or a `DealtHand` for sockets that asked for strength hints
+    pub fn seal<T: Serialize>(hand: &T, key: &HandKey) -> Result<Self> {
+        Ok(Self {
+            ciphertext: seal_json(hand, key)?,
+        })
+    }
+
+    pub fn open<T: DeserializeOwned>(&self, secret: &SecretKey) -> Result<T> {
+        open_json(&self.ciphertext, secret)
+    }
+}
+
+/// Hole cards and undealt deck of an exported room, sealed to the server importing it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedCards {
+    pub ciphertext: Vec<u8>,
+}
+
+impl SealedCards {
+    pub fn seal(cards: &HiddenCards, key: &HandKey) -> Result<Self> {
+        Ok(Self {
+            ciphertext: seal_json(cards, key)?,
+        })
+    }
+
+    pub fn open(&self, secret: &SecretKey) -> Result<HiddenCards> {
+        open_json(&self.ciphertext, secret)
+    }
+}
+
+fn seal_json<T: Serialize>(value: &T, key: &HandKey) -> Result<Vec<u8>> {
+    let plaintext = serde_json::to_vec(value)?;
+    key.0
+        .seal(&mut OsRng, &plaintext)
+        .map_err(|e| eyre!("Failed to seal cards: {}", e))
+}
+
+fn open_json<T: DeserializeOwned>(ciphertext: &[u8], secret: &SecretKey) -> Result<T> {
+    let plaintext = secret
+        .unseal(ciphertext)
+        .map_err(|e| eyre!("Failed to open cards: {}", e))?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use poker::card;
+
+    use super::*;
+    use crate::state::PlayerHand;
+
+    #[test]
+    fn sealed_hand_should_only_open_with_the_matching_key() -> Result<()> {
+        let (key, secret) = HandKey::generate();
+        let hand: PlayerHand = [card!("As")?, card!("Kd")?].into();
+        let sealed = SealedHand::seal(&hand, &key)?;
+
+        let opened: PlayerHand = sealed.open(&secret)?;
+        assert_eq!(format!("{:?}", opened), format!("{:?}", hand));
+
+        let (_, other_secret) = HandKey::generate();
+        assert!(sealed.open::<PlayerHand>(&other_secret).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn key_from_the_same_secret_should_open_what_it_sealed_before() -> Result<()> {
+        let (key, _) = HandKey::from_secret([7; 32]);
+        let hand: PlayerHand = [card!("Qh")?, card!("2c")?].into();
+        let sealed = SealedHand::seal(&hand, &key)?;
+
+        // as after a restart
+        let (_, secret) = HandKey::from_secret([7; 32]);
+        let opened: PlayerHand = sealed.open(&secret)?;
+        assert_eq!(format!("{:?}", opened), format!("{:?}", hand));
+        Ok(())
+    }
+}
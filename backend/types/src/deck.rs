@@ -1,31 +1,88 @@
 use eyre::{ensure, Result};
 use poker::{Card, Rank, Suit};
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sha2::{Digest, Sha256};
 
 use crate::error::Error::{EmptyDeck, InvalidPosition};
 
 #[derive(Debug, Clone)]
-pub struct Deck(u64);
+pub struct Deck {
+    cards: u64,
+    // draws the cards, seeded from the OS unless the deck was made with an RNG
+    rng: StdRng,
+}
 
 const FULL_DECK_INT: u64 = 0x000f_ffff_ffff_ffff;
 
+impl Default for Deck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Deck {
     pub fn new() -> Self {
-        Deck(FULL_DECK_INT)
+        Self::from_bits(FULL_DECK_INT)
+    }
+
+    /// A full deck shuffled by `rng`, a seeded one draws the same cards every time
+    pub fn with_rng<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::from_seed(rng.gen())
+    }
+
+    /// A full deck that draws the same cards for the same seed, see `fairness::Deal`
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Deck {
+            cards: FULL_DECK_INT,
+            rng: StdRng::from_seed(seed),
+        }
     }
 
     pub fn draw(&mut self) -> Result<Card> {
-        let ones = self.0.count_ones();
-        // TODO: Optimize RNG by creating a pool of generators
-        let n = rand::thread_rng().gen_range(1..=ones);
+        let ones = self.cards.count_ones();
+        ensure!(ones > 0, EmptyDeck);
+        let n = self.rng.gen_range(1..=ones);
 
         // Find the position of the nth leading 1 bit
-        let position = pos_of_leading_1_bit(n as u64, self.0)? - 1;
+        let position = pos_of_leading_1_bit(n as u64, self.cards)? - 1;
 
         // Flip the nth trailing bit
-        self.0 -= 1 << position;
+        self.cards -= 1 << position;
         Ok(Card::new(i_to_rank(position), i_to_suit(position)))
     }
+
+    /// SHA-256 of the cards left in the order they will be drawn, taken as a hand is dealt
+    /// so the deck can be audited once the hand is over
+    pub fn fingerprint(&self) -> String {
+        let mut deck = self.clone();
+        let mut hasher = Sha256::new();
+        while let Ok(card) = deck.draw() {
+            hasher.update(card.rank_suit_string());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Cards still in the deck, one bit each, to carry a deck over to another server
+    pub fn bits(&self) -> u64 {
+        self.cards
+    }
+
+    pub fn from_bits(bits: u64) -> Self {
+        Deck {
+            cards: bits & FULL_DECK_INT,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Takes a card out so it is never drawn, used for cards dealt by a scenario
+    pub fn remove(&mut self, card: Card) {
+        if let Some(position) =
+            (0..52).find(|&i| i_to_rank(i) == card.rank() && i_to_suit(i) == card.suit())
+        {
+            self.cards &= !(1 << position);
+        }
+    }
 }
 
 fn i_to_rank(i: u64) -> Rank {
@@ -118,7 +175,7 @@ mod tests {
 
     #[test]
     fn make_sure_full_deck_int_has_only_52_trailing_1_bits() {
-        assert_eq!(52, Deck::new().0.count_ones());
+        assert_eq!(52, Deck::new().cards.count_ones());
     }
 
     #[test]
@@ -150,8 +207,47 @@ mod tests {
             .map(|_| deck.draw())
             .collect::<Result<HashSet<_>>>()?;
         assert_eq!(52, all_cards.len());
-        assert_eq!(deck.0, 0);
+        assert_eq!(deck.cards, 0);
+        assert!(deck.draw().is_err());
+
+        Ok(())
+    }
 
+    #[test]
+    fn removed_card_should_never_be_drawn() -> Result<()> {
+        let mut deck = Deck::new();
+        let removed = Card::new(Rank::Ace, Suit::Spades);
+        deck.remove(removed);
+        let all_cards = (0..51)
+            .map(|_| deck.draw())
+            .collect::<Result<HashSet<_>>>()?;
+        assert!(!all_cards.contains(&removed));
+        assert_eq!(deck.cards, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn same_seed_should_draw_the_same_cards() -> Result<()> {
+        let draw_all = |seed| {
+            let mut deck = Deck::with_rng(&mut StdRng::seed_from_u64(seed));
+            (0..52).map(|_| deck.draw()).collect::<Result<Vec<_>>>()
+        };
+        assert_eq!(draw_all(42)?, draw_all(42)?);
+        assert_ne!(draw_all(42)?, draw_all(43)?);
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprint_should_follow_the_order_of_the_deck() -> Result<()> {
+        let deck = |seed| Deck::with_rng(&mut StdRng::seed_from_u64(seed));
+        assert_eq!(deck(42).fingerprint(), deck(42).fingerprint());
+        assert_ne!(deck(42).fingerprint(), deck(43).fingerprint());
+        // taking it draws nothing, the cards left after a draw make another one
+        let mut drawn = deck(42);
+        let fingerprint = drawn.fingerprint();
+        assert_eq!(drawn.bits().count_ones(), 52);
+        drawn.draw()?;
+        assert_ne!(drawn.fingerprint(), fingerprint);
         Ok(())
     }
 
@@ -1,24 +1,164 @@
 use std::borrow::Cow;
 
+use chrono::{DateTime, NaiveDate, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use strum_macros::AsRefStr;
+use strum_macros::{AsRefStr, EnumString};
 use uuid::Uuid;
 use validator::Validate;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::crypto::HandKey;
+use crate::protocol::Feature;
+use crate::room::Stage;
+use crate::rules::{default_max_players, BettingStructure};
+
+/// Sent when opening the game socket
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SocketAuth {
+    // access token
+    pub token: String,
+    // hole cards are sealed to this key when set
+    #[serde(default)]
+    pub hand_key: Option<HandKey>,
+    // dealt hands come with what they make on the board so far
+    #[serde(default)]
+    pub hand_strength: bool,
+    // 0 for clients from before the protocol was versioned
+    #[serde(default)]
+    pub protocol_version: u32,
+    // optional features the client supports, see `Handshake`
+    #[serde(default)]
+    pub features: Vec<Feature>,
+}
+
+/// Kept on a game socket that opted into hand strength hints
+#[derive(Debug, Clone, Copy)]
+pub struct HandStrengthHints;
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct JoinGameRequest {
     pub room_id: Uuid,
     pub buy_in: i64,
+    // sequence number of the last action the client saw in this room, if any
+    #[serde(default)]
+    pub last_seen_seq: Option<u64>,
+    // required to join a private room
+    #[serde(default)]
+    pub password: Option<String>,
+    // the first free seat is taken when none is asked for
+    #[serde(default)]
+    pub seat: Option<usize>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+pub struct CreateRoomRequest {
+    // makes the room private when set
+    #[serde(default)]
+    pub password: Option<String>,
+    // seats played by server bots, so the table is never empty
+    #[serde(default)]
+    pub bot_seats: usize,
+    // hands in a row a player may let the turn timer fold for them, 0 never removes them
+    #[serde(default)]
+    pub kick_after_timeouts: Option<u32>,
+    #[serde(default)]
+    pub betting: BettingStructure,
+    #[serde(default)]
+    pub currency: ChipCurrency,
+    // seats at the table, 2 to 9, five when not given
+    #[serde(default)]
+    pub max_players: Option<usize>,
+    // the room closes at this time, or this many minutes after it opens, never both
+    #[serde(default)]
+    pub closes_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub closes_after_minutes: Option<u32>,
+}
+
+/// Narrows the lobby down to some rooms
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RoomQuery {
+    #[serde(default)]
+    pub currency: Option<ChipCurrency>,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ChatRequest {
+    pub room_id: Uuid,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ActionRequest {
     pub room_id: Uuid,
     pub action: Action,
 }
 
-#[derive(Debug, Validate, Deserialize, Serialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DarkActionRequest {
+    pub room_id: Uuid,
+    pub action: DarkAction,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PreActionRequest {
+    pub room_id: Uuid,
+    // None takes back the one set earlier
+    pub pre_action: Option<PreAction>,
+}
+
+/// Chips added to a seated player's stack as the next hand is dealt
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TopUpRequest {
+    pub room_id: Uuid,
+    pub amount: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ShowCardsRequest {
+    pub room_id: Uuid,
+    // false mucks the cards
+    pub show: bool,
+}
+
+/// Cards are mucked unless shown before the deadline
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ShowOrMuckPrompt {
+    pub room_id: Uuid,
+    pub deadline: DateTime<Utc>,
+}
+
+/// Sent to a player as they sit down, before the table state reaches them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Seated {
+    pub room_id: Uuid,
+    pub seat: usize,
+    // the hand under way is played out without them
+    pub dealt_in_next_hand: bool,
+    // seconds left of the hand under way at the table's pace, unknown for a new table
+    pub estimated_wait: Option<u64>,
+}
+
+/// Sent to a player queued for a seat at a full table, and again as the queue moves
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Waitlisted {
+    pub room_id: Uuid,
+    // 1 for the next player to be seated
+    pub position: usize,
+}
+
+/// Sent to a player removed from a table for letting their turns run out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Kicked {
+    pub room_id: Uuid,
+    // hands in a row the turn timer folded for them
+    pub timed_out_hands: u32,
+    // stack that went back to their balance
+    pub chips: u32,
+}
+
+#[derive(Debug, Validate, Deserialize, Serialize, JsonSchema)]
 pub struct SignupRequest {
     #[validate(email)]
     pub email: String,
@@ -27,33 +167,514 @@ pub struct SignupRequest {
 }
 
 #[derive(Debug, Validate, Deserialize, Serialize)]
+pub struct ForgotPasswordRequest {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Debug, Validate, Deserialize, Serialize)]
+pub struct ResetPasswordRequest {
+    #[validate(email)]
+    pub email: String,
+    // emailed by `POST /password/forgot`
+    pub code: String,
+    #[validate(length(min = 8))]
+    pub new_password: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema)]
+pub struct SignupResponse {
+    // the account can only log in once the emailed link is opened
+    pub verification_sent: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VerifyEmailQuery {
+    pub token: Uuid,
+}
+
+#[derive(Debug, Validate, Deserialize, Serialize, JsonSchema)]
 pub struct LoginRequest {
     #[validate(email)]
     pub email: String,
     pub password: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Issued on login and on refresh, each refresh token can only be redeemed once
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: Uuid,
+    // seconds until the access token expires
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RefreshRequest {
+    pub refresh_token: Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct LogoutRequest {
+    // the session to end, the one this device was given
+    pub refresh_token: Uuid,
+}
+
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
 pub struct UpdateProfileRequest {
     pub username: String,
+    // keeps the current one when not given
+    #[serde(default)]
+    pub avatar: Option<Avatar>,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, AsRefStr)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, AsRefStr, JsonSchema)]
 #[strum(serialize_all = "snake_case")]
 pub enum Action {
     Fold,
     Check,
     Call,
+    // the player's total bet for the street when opening the betting, only while there is
+    // nothing to call
+    Bet(u32),
+    // deprecated: chips added on top of the player's current bet, still accepted from
+    // older clients but turned into a `RaiseTo` before it is played
     Raise(u32),
+    // the player's total bet for the street after raising
+    #[strum(serialize = "raise")]
+    RaiseTo(u32),
     AllIn,
 }
 
-#[derive(Debug, Clone, FromRow, PartialEq, Serialize, Deserialize)]
+/// Why a player folded, as folds the player did not choose are told apart at the table
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, AsRefStr, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum FoldReason {
+    Voluntary,
+    // the turn timer ran out with a bet to call
+    Timeout,
+    // left the room or lost the connection mid hand
+    Disconnect,
+}
+
+/// Declared before the next street is dealt, played once the action reaches the player
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, AsRefStr, JsonSchema)]
+#[strum(serialize_all = "snake_case")]
+pub enum DarkAction {
+    Check,
+    // bets the size of the pot, or all in when the stack is smaller
+    BetPot,
+}
+
+/// Played as soon as the player's turn comes on the current street
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum PreAction {
+    // checks when nothing is owed, folds otherwise
+    CheckFold,
+    // calls up to exactly this total bet, dropped if someone raised since
+    Call(u32),
+    // calls whatever is bet, checks when nothing is
+    CallAny,
+}
+
+#[derive(Debug, Clone, FromRow, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct User {
     pub id: Uuid,
     pub name: String,
+    // play chips
+    pub balance: i64,
+    pub current_room: Option<Uuid>,
+    #[serde(default)]
+    pub premium_balance: i64,
+    #[sqlx(try_from = "String")]
+    #[serde(default)]
+    pub avatar: Avatar,
+}
+
+impl User {
+    pub fn balance_in(&self, currency: ChipCurrency) -> i64 {
+        match currency {
+            ChipCurrency::Play => self.balance,
+            ChipCurrency::Premium => self.premium_balance,
+        }
+    }
+}
+
+/// Picture shown next to a player at the table, drawn by the client from its own art
+#[derive(
+    Debug,
+    Default,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    AsRefStr,
+    EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Avatar {
+    // what every account starts with
+    #[default]
+    Spade,
+    Cat,
+    Owl,
+    Fox,
+    Ghost,
+    // bots sit with this one
+    Robot,
+}
+
+impl Avatar {
+    pub const ALL: [Avatar; 6] = [
+        Avatar::Spade,
+        Avatar::Cat,
+        Avatar::Owl,
+        Avatar::Fox,
+        Avatar::Ghost,
+        Avatar::Robot,
+    ];
+}
+
+impl TryFrom<String> for Avatar {
+    type Error = strum::ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// What a balance and the chips at a table are counted in, chips never move from one to the
+/// other
+#[derive(
+    Debug,
+    Default,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    AsRefStr,
+    EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ChipCurrency {
+    // free chips, claimed daily and reset with each season
+    #[default]
+    Play,
+    // kept on a balance of their own that seasons never reset
+    Premium,
+}
+
+impl TryFrom<String> for ChipCurrency {
+    type Error = strum::ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// Why an admin changed a user's balance, kept on the ledger for support cases
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, AsRefStr, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AdjustmentReason {
+    CrashRefund,
+    Goodwill,
+    Correction,
+    // balance put back to the default bankroll when a season ends
+    SeasonReset,
+    // chips taken to a table when sitting down
+    BuyIn,
+    // chips brought back from a table when leaving or when it closes
+    CashOut,
+    // saved stacks paid back after a server restart
+    RestartRefund,
+    // free chips claimed by the user
+    TopUp,
+}
+
+impl AdjustmentReason {
+    /// Whether an admin may make an adjustment with this reason, the rest are made by the server
+    pub fn is_manual(&self) -> bool {
+        matches!(
+            self,
+            AdjustmentReason::CrashRefund
+                | AdjustmentReason::Goodwill
+                | AdjustmentReason::Correction
+        )
+    }
+}
+
+impl TryFrom<String> for AdjustmentReason {
+    type Error = strum::ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AdjustBalanceRequest {
+    // positive credits the user, negative debits
+    pub amount: i64,
+    pub reason: AdjustmentReason,
+    #[serde(default)]
+    pub note: Option<String>,
+    #[serde(default)]
+    pub currency: ChipCurrency,
+}
+
+#[derive(Debug, Clone, FromRow, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    // None for changes made by the server itself, like season resets
+    pub admin_id: Option<Uuid>,
+    // the table of a buy-in or cash-out
+    pub room_id: Option<Uuid>,
+    pub amount: i64,
+    #[sqlx(try_from = "String")]
+    pub reason: AdjustmentReason,
+    pub note: Option<String>,
+    // of the balance in `currency`
+    pub balance_after: i64,
+    pub created_at: DateTime<Utc>,
+    #[sqlx(try_from = "String")]
+    #[serde(default)]
+    pub currency: ChipCurrency,
+}
+
+/// Groups of ledger reasons a user can filter their transactions by
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    BuyIn,
+    // winnings reach the balance with the chips brought back from a table
+    CashOut,
+    Bonus,
+    Adjustment,
+}
+
+impl TransactionKind {
+    pub const ALL: [TransactionKind; 4] = [
+        TransactionKind::BuyIn,
+        TransactionKind::CashOut,
+        TransactionKind::Bonus,
+        TransactionKind::Adjustment,
+    ];
+
+    pub fn reasons(&self) -> &'static [AdjustmentReason] {
+        match self {
+            TransactionKind::BuyIn => &[AdjustmentReason::BuyIn],
+            TransactionKind::CashOut => &[AdjustmentReason::CashOut],
+            TransactionKind::Bonus => &[AdjustmentReason::TopUp, AdjustmentReason::Goodwill],
+            TransactionKind::Adjustment => &[
+                AdjustmentReason::CrashRefund,
+                AdjustmentReason::RestartRefund,
+                AdjustmentReason::Correction,
+                AdjustmentReason::SeasonReset,
+            ],
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TransactionKind::BuyIn => "Buy-ins",
+            TransactionKind::CashOut => "Cash-outs and winnings",
+            TransactionKind::Bonus => "Bonuses",
+            TransactionKind::Adjustment => "Refunds and adjustments",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TransactionQuery {
+    // zero based
+    #[serde(default)]
+    pub page: Option<u32>,
+    #[serde(default)]
+    pub per_page: Option<u32>,
+    // first and last day to list, both included, in UTC
+    #[serde(default)]
+    pub from: Option<NaiveDate>,
+    #[serde(default)]
+    pub to: Option<NaiveDate>,
+    #[serde(default)]
+    pub kind: Option<TransactionKind>,
+    // each currency has a ledger of its own, both are listed when not given
+    #[serde(default)]
+    pub currency: Option<ChipCurrency>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionPage {
+    // newest first
+    pub transactions: Vec<LedgerEntry>,
+    pub page: u32,
+    pub has_more: bool,
+}
+
+/// Rake kept by the house on one day
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct RakeDay {
+    pub day: NaiveDate,
+    pub hands: i64,
+    pub total: i64,
+}
+
+/// What a finished hand suggested about two players, kept in `integrity_events`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, AsRefStr, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum IntegritySignal {
+    // folded to a bet or raise of the other player
+    FoldedTo,
+    // folded most of a stack away to the other player, who took the whole pot
+    ChipDump,
+    // both dealt in while connected from the same address
+    SharedAddress,
+}
+
+/// Two players the integrity checks flagged together, in no particular order
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct SuspiciousPair {
+    pub first_player: Uuid,
+    pub first_name: String,
+    pub second_player: Uuid,
+    pub second_name: String,
+    // hands that raised a signal about the pair
+    pub hands: i64,
+    // either way round
+    pub folds: i64,
+    pub chip_dumps: i64,
+    pub chips_dumped: i64,
+    pub shared_address_hands: i64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct IntegrityQuery {
+    // how far back the report looks
+    #[serde(default)]
+    pub days: Option<i32>,
+    // folds to one another that flag a pair without any other signal
+    #[serde(default)]
+    pub min_folds: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UserSearchQuery {
+    // matched against user name and email
+    #[serde(default)]
+    pub q: Option<String>,
+    #[serde(default)]
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, FromRow, PartialEq, Serialize, Deserialize)]
+pub struct AdminUserView {
+    pub id: Uuid,
+    pub name: String,
+    pub email: String,
     pub balance: i64,
+    pub premium_balance: i64,
     pub current_room: Option<Uuid>,
+    #[sqlx(try_from = "String")]
+    pub role: Role,
+    pub banned_at: Option<DateTime<Utc>>,
+}
+
+/// What a user may do besides playing, carried in their access token.
+/// Roles are ordered, each one may do everything the ones before it can.
+#[derive(
+    Debug,
+    Default,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    AsRefStr,
+    EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Role {
+    #[default]
+    Player,
+    // may close tables and ban players
+    Moderator,
+    Admin,
+}
+
+impl TryFrom<String> for Role {
+    type Error = strum::ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct BanRequest {
+    // kept with the ban for other moderators to see
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Live counts for moderators, taken from memory except for the user totals
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdminOverview {
+    pub rooms: usize,
+    // rooms with a hand being played
+    pub rooms_in_play: usize,
+    pub seated_players: usize,
+    pub connected_sockets: usize,
+    // balance changes waiting for the database to come back
+    pub staged_changes: usize,
+    pub users: i64,
+    pub banned_users: i64,
+}
+
+/// Bug report filed from the client, with what the client saw when it was filed
+#[derive(Debug, Validate, Deserialize, Serialize)]
+pub struct FeedbackRequest {
+    #[validate(length(max = 2000))]
+    pub note: Option<String>,
+    #[validate(length(min = 1, max = 32))]
+    pub client_version: String,
+    // game state the client held, with player names taken out
+    pub client_state: Option<serde_json::Value>,
+    // what the client sent and received lately, oldest first
+    #[validate(length(max = 200))]
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Feedback {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub note: Option<String>,
+    pub client_version: String,
+    pub client_state: Option<serde_json::Value>,
+    pub events: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct FeedbackQuery {
+    #[serde(default)]
+    pub limit: Option<i64>,
 }
 
 #[derive(Debug, AsRefStr)]
@@ -62,6 +683,20 @@ pub enum ClientEvent {
     Join,
     Action,
     Leave,
+    // done looking at the results of the last hand
+    Ready,
+    // skip hands without giving up the seat
+    SitOut,
+    SitIn,
+    Chat,
+    // declare an action for the next street before it is dealt
+    DarkAction,
+    // reveal or muck cards that lost at showdown
+    ShowCards,
+    // add chips to the stack from the balance, see `TopUpRequest`
+    TopUp,
+    // set what to play once the turn comes, see `PreAction`
+    PreAction,
 }
 
 impl From<ClientEvent> for Cow<'_, str> {
@@ -77,17 +712,236 @@ pub enum ServiceEvent {
     Hand,
     ServiceError,
     Outcome,
+    RoomClosing,
+    Resume,
+    Chat,
+    // sent right before the server drops a connection it has no room for
+    ConnectionRefused,
+    // server wide news, sent to every connected player
+    Announcement,
+    // asks a player who lost at showdown whether to show their cards
+    ShowOrMuck,
+    // a change to the room list, sent on the lobby namespace
+    Lobby,
+    // an action, a new street or a pot paid out, as it happens at the table
+    ActionLog,
+    // acknowledges a join to the joining socket only
+    Seated,
+    // the joining socket's place in the queue for a seat at a full table
+    Waitlisted,
+    // a friend asks this player to their table, sent on the lobby namespace
+    Invite,
+    // the player was removed from the table, see `Kicked`
+    Kicked,
+    // the protocol version and features agreed on when the game socket opens
+    Handshake,
+    // sent right before the server drops a client too old to play
+    UpgradeRequired,
 }
 
-#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ConnectionRefused {
+    pub reason: String,
+    pub retry_after_secs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Announcement {
+    // the same announcement reaches a client on each of its sockets
+    #[serde(default)]
+    pub id: Uuid,
+    pub message: String,
+}
+
+/// Sent by an admin to every connected player, like a warning before a restart
+#[derive(Debug, Validate, Deserialize, Serialize)]
+pub struct BroadcastRequest {
+    #[validate(length(min = 1, max = 500))]
+    pub message: String,
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
 pub struct RoomInfo {
     pub room_id: Uuid,
     pub player_count: i32,
+    pub closes_at: Option<DateTime<Utc>>,
+    // never sent to clients, they only see `is_private`
+    #[serde(skip)]
+    pub password_hash: Option<String>,
+    #[serde(default)]
+    pub is_private: bool,
+    #[serde(default)]
+    pub bot_seats: i32,
+    #[serde(default)]
+    pub kick_after_timeouts: i32,
+    #[sqlx(json)]
+    #[serde(default)]
+    pub betting: BettingStructure,
+    #[sqlx(json)]
+    #[serde(default)]
+    pub currency: ChipCurrency,
+    #[serde(default = "default_max_players::<i32>")]
+    pub max_players: i32,
+    #[serde(default)]
+    pub small_blind: i32,
+    #[serde(default)]
+    pub big_blind: i32,
+    // kept up to date by the server playing the room, see `RoomActivity`
+    #[sqlx(json)]
+    #[serde(default)]
+    pub stage: Stage,
+    #[serde(default)]
+    pub average_pot: Option<i32>,
+    #[serde(default)]
+    pub last_hand_at: Option<DateTime<Utc>>,
+    // the backend instance serving the room, when it advertises where to reach it
+    #[serde(default)]
+    pub owner_url: Option<String>,
+    // the player who opened the room, never sent to clients
+    #[serde(skip)]
+    pub created_by: Option<Uuid>,
+    // kept in memory by the server, filled in when rooms are listed
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub hands_per_hour: Option<u32>,
+}
+
+/// Change to one row of the room list, pushed to everyone watching the lobby
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LobbyUpdate {
+    // a room was opened, or players joined or left it
+    Room(RoomInfo),
+    // a hand ended, which changes how fast the room plays
+    Pace {
+        room_id: Uuid,
+        hands_per_hour: Option<u32>,
+    },
+    // the room moved on to another stage
+    Activity {
+        room_id: Uuid,
+        activity: RoomActivity,
+    },
+    Closed {
+        room_id: Uuid,
+    },
+}
+
+/// How a room is playing, to tell active tables from idle ones
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RoomActivity {
+    pub stage: Stage,
+    // over the latest hands, rake included
+    pub average_pot: Option<u32>,
+    pub last_hand_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomInvite {
+    pub code: String,
+    pub room_id: Uuid,
+    pub url: String,
+}
+
+const INVITE_URL_PREFIX: &str = "poker://join/";
+
+impl RoomInvite {
+    pub fn new(code: String, room_id: Uuid) -> Self {
+        RoomInvite {
+            url: format!("{}{}", INVITE_URL_PREFIX, code),
+            code,
+            room_id,
+        }
+    }
+
+    /// The code of a bare invite code or of a `poker://join/<code>` link,
+    /// links anywhere else are not invites
+    pub fn code_from_link(link: &str) -> Option<&str> {
+        let link = link.trim();
+        let code = match link.strip_prefix(INVITE_URL_PREFIX) {
+            Some(code) => code.trim_end_matches('/'),
+            None => link,
+        };
+        let is_code = !code.is_empty() && code.chars().all(|c| c.is_ascii_alphanumeric());
+        is_code.then_some(code)
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FriendRequest {
+    // the name the other player goes by at the tables
+    pub username: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, AsRefStr, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum FriendStatus {
+    Friend,
+    // they asked this user, who can accept or decline
+    Incoming,
+    // this user asked them and waits for an answer
+    Outgoing,
+}
+
+impl TryFrom<String> for FriendStatus {
+    type Error = strum::ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+#[derive(Debug, Clone, FromRow, Serialize, Deserialize)]
+pub struct Friend {
+    pub user_id: Uuid,
+    pub name: String,
+    #[sqlx(try_from = "String")]
+    pub status: FriendStatus,
+    // only shown to friends
+    pub current_room: Option<Uuid>,
+}
+
+/// Pushed on the lobby namespace to a friend invited to a table, enough to join it from there
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FriendInvite {
+    pub from_id: Uuid,
+    pub from_name: String,
+    pub room: RoomInfo,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ServiceRequiredAction {
     NoAction,
     FindWinners,
     PlayerReceiveCards,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invite_codes_should_be_read_from_bare_codes_and_invite_links() {
+        assert_eq!(RoomInvite::code_from_link("Ab12Cd34"), Some("Ab12Cd34"));
+        assert_eq!(
+            RoomInvite::code_from_link(" poker://join/Ab12Cd34/\n"),
+            Some("Ab12Cd34")
+        );
+        let invite = RoomInvite::new("Ab12Cd34".to_string(), Uuid::new_v4());
+        assert_eq!(RoomInvite::code_from_link(&invite.url), Some("Ab12Cd34"));
+    }
+
+    #[test]
+    fn malformed_and_foreign_links_should_not_be_read_as_invites() {
+        assert_eq!(RoomInvite::code_from_link(""), None);
+        assert_eq!(RoomInvite::code_from_link("poker://join/"), None);
+        assert_eq!(RoomInvite::code_from_link("poker://join/Ab12/Cd34"), None);
+        assert_eq!(RoomInvite::code_from_link("Ab12 Cd34"), None);
+        assert_eq!(
+            RoomInvite::code_from_link("https://example.com/Ab12Cd34"),
+            None
+        );
+        assert_eq!(RoomInvite::code_from_link("poker://rooms/Ab12Cd34"), None);
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use eyre::Result;
+use itertools::Itertools;
+use poker::{deck, Card, Evaluator};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use uuid::Uuid;
+
+use crate::room::Room;
+
+// boards sampled when too many cards are still to come to try every run out
+const EQUITY_SAMPLES: usize = 2_000;
+// with this many cards to come or fewer every run out is evaluated
+const MAX_ENUMERATED_CARDS: usize = 2;
+
+/// Share of the pot each hand wins over the ways the board can run out, ties are split.
+/// Exact up to two cards to come, sampled with `rng` before that.
+pub fn equity<R: Rng + ?Sized>(
+    evaluator: &Evaluator,
+    hands: &[(Uuid, [Card; 2])],
+    board: &[Card],
+    rng: &mut R,
+) -> Result<HashMap<Uuid, f64>> {
+    let mut shares: HashMap<Uuid, f64> = hands.iter().map(|(id, _)| (*id, 0.0)).collect();
+    if hands.is_empty() {
+        return Ok(shares);
+    }
+    let dealt: Vec<Card> = hands
+        .iter()
+        .flat_map(|(_, cards)| cards.iter().copied())
+        .chain(board.iter().copied())
+        .collect();
+    let remaining: Vec<Card> = deck::generate()
+        .filter(|card| !dealt.contains(card))
+        .collect();
+    let to_come = 5usize.saturating_sub(board.len());
+
+    let run_outs: Vec<Vec<Card>> = if to_come <= MAX_ENUMERATED_CARDS {
+        remaining.iter().copied().combinations(to_come).collect()
+    } else {
+        (0..EQUITY_SAMPLES)
+            .map(|_| remaining.choose_multiple(rng, to_come).copied().collect())
+            .collect()
+    };
+    for run_out in run_outs.iter() {
+        let evals = hands
+            .iter()
+            .map(|(id, cards)| {
+                let seven: Vec<Card> = cards
+                    .iter()
+                    .chain(board.iter())
+                    .chain(run_out.iter())
+                    .copied()
+                    .collect();
+                Ok((*id, evaluator.evaluate(seven)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let winners = Room::all_best_hands(&evals);
+        for id in winners.iter() {
+            *shares.entry(*id).or_default() += 1.0 / winners.len() as f64;
+        }
+    }
+    for share in shares.values_mut() {
+        *share /= run_outs.len().max(1) as f64;
+    }
+    Ok(shares)
+}
+
+#[cfg(test)]
+mod tests {
+    use poker::{card, cards};
+
+    use super::*;
+
+    #[test]
+    fn equity_should_be_exact_on_the_river_and_split_ties() -> Result<()> {
+        let evaluator = Evaluator::new();
+        let [aces, kings] = [Uuid::new_v4(), Uuid::new_v4()];
+        let board: Vec<Card> = cards!("2c 7d 9h Js 3s").try_collect()?;
+        let hands = [
+            (aces, [card!("Ah")?, card!("Ad")?]),
+            (kings, [card!("Kh")?, card!("Kd")?]),
+        ];
+        let shares = equity(&evaluator, &hands, &board, &mut rand::thread_rng())?;
+        assert_eq!(shares[&aces], 1.0);
+        assert_eq!(shares[&kings], 0.0);
+
+        // both play the board
+        let board: Vec<Card> = cards!("Ac Kc Qc Jc Tc").try_collect()?;
+        let shares = equity(&evaluator, &hands, &board, &mut rand::thread_rng())?;
+        assert_eq!(shares[&aces], 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn equity_on_the_turn_should_count_outs() -> Result<()> {
+        let evaluator = Evaluator::new();
+        let [pair, draw] = [Uuid::new_v4(), Uuid::new_v4()];
+        let board: Vec<Card> = cards!("Ad 7c 2s 3h").try_collect()?;
+        let hands = [
+            (pair, [card!("As")?, card!("Kd")?]),
+            // only a queen on the river beats top pair
+            (draw, [card!("Qh")?, card!("Qs")?]),
+        ];
+        let shares = equity(&evaluator, &hands, &board, &mut rand::thread_rng())?;
+        // two queens are left among 44 unseen cards
+        assert!((shares[&draw] - 2.0 / 44.0).abs() < 1e-9);
+        assert!((shares[&pair] + shares[&draw] - 1.0).abs() < 1e-9);
+        Ok(())
+    }
+}
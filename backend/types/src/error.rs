@@ -1,4 +1,5 @@
 use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -15,6 +16,8 @@ pub enum Error {
     InsufficientBalance,
     #[error("Room is full")]
     RoomIsFull,
+    #[error("Seat {0} is not available")]
+    SeatUnavailable(usize),
     #[error("Invalid room id")]
     InvalidRoomId,
     #[error("Player not in room")]
@@ -25,6 +28,110 @@ pub enum Error {
     InvalidEmailOrPassword,
     #[error("No room found")]
     NoRoomFound,
+    #[error("Room is closed")]
+    RoomClosed,
+    #[error("Invalid invite code")]
+    InvalidInviteCode,
+    #[error("Raise exceeds remaining chips")]
+    RaiseExceedsStack,
+    #[error("Admin access required")]
+    AdminOnly,
+    #[error("Balance adjustment must be non-zero")]
+    InvalidAdjustment,
+    #[error("Invalid room password")]
+    InvalidRoomPassword,
+    #[error("Raise must be to at least {0} chips")]
+    RaiseBelowMinimum(u32),
+    #[error("Betting was not reopened by the short all-in, call or fold")]
+    RaiseNotReopened,
+    #[error("Raise can go to {0} chips at most")]
+    RaiseAboveMaximum(u32),
+    #[error("No more raises on this street")]
+    RaisingCapped,
+    #[error("There is a bet to call, raise instead of betting")]
+    BetFacingBet,
+    #[error("Chat messages must be between 1 and 200 characters")]
+    InvalidChatMessage,
+    #[error("Sending messages too quickly, slow down")]
+    ChatRateLimited,
+    #[error("Dark actions can only be declared while in the hand, before the river")]
+    DarkActionUnavailable,
+    #[error("Pre-actions can only be set while in the hand and waiting for the turn")]
+    PreActionUnavailable,
+    #[error("Only players who lost at showdown can show or muck, once")]
+    NoCardsToShow,
+    #[error("A room needs a seat left free of bots")]
+    InvalidBotSeats,
+    #[error("A table seats between 2 and 9 players")]
+    InvalidTableSize,
+    #[error("A room closes once, at a time in the future")]
+    InvalidClosingTime,
+    #[error("Webhook url must be an http or https url")]
+    InvalidWebhookUrl,
+    #[error("Webhook url must resolve to a public address")]
+    WebhookUrlNotPublic,
+    #[error("A room can have at most {0} webhooks")]
+    TooManyWebhooks(i64),
+    #[error("Only the player who opened the room or an admin can do this")]
+    NotRoomCreator,
+    #[error("Server is at its {0} limit, try again later")]
+    ServerAtCapacity(&'static str),
+    #[error("Invalid or expired token")]
+    InvalidToken,
+    #[error("A scenario needs distinct cards, at most 5 on the board and seats 0 to 4")]
+    InvalidScenario,
+    #[error("Scenarios can only be loaded into private rooms")]
+    ScenarioNeedsPrivateRoom,
+    #[error("A season already ended at that time")]
+    SeasonAlreadyEnded,
+    #[error("Admins can only adjust balances as a crash refund, goodwill or correction")]
+    NotAManualAdjustment,
+    #[error("Free chips can be claimed again at {0}")]
+    TopUpNotReady(DateTime<Utc>),
+    #[error("Buy-in must be more than zero")]
+    InvalidBuyIn,
+    #[error("This account is banned")]
+    UserBanned,
+    #[error("Verify your email before logging in, a new link was sent to your inbox")]
+    EmailNotVerified,
+    #[error("This verification link is invalid or has expired")]
+    InvalidVerificationToken,
+    #[error("This reset code is invalid or has expired")]
+    InvalidResetCode,
+    #[error("This server does not send email, ask an admin to reset your password")]
+    PasswordResetUnavailable,
+    #[error("No player goes by that name")]
+    PlayerNotFound,
+    #[error("Several players go by that name, ask your friend to pick another")]
+    AmbiguousPlayerName,
+    #[error("You cannot add yourself as a friend")]
+    CannotFriendYourself,
+    #[error("You are already friends or waiting for an answer")]
+    FriendRequestExists,
+    #[error("There is no friend request from this player")]
+    NoFriendRequest,
+    #[error("You can only invite friends")]
+    NotFriends,
+    #[error("No such hand was played in this room")]
+    HandNotFound,
+    #[error("Chips can only be added up to a stack of {0}")]
+    AboveMaxBuyIn(u32),
+    #[error("Only players can be banned")]
+    CannotBanStaff,
+    #[error("Feedback needs a client version, a note of at most 2000 characters and at most 200 events")]
+    InvalidFeedback,
+    #[error("An announcement needs a message of at most 500 characters")]
+    InvalidAnnouncement,
+    #[error("Rooms can only be imported onto empty tables, sealed with this server's import key")]
+    InvalidRoomImport,
+    #[error("Your session has ended, log in again")]
+    SessionEnded,
+    #[error("The start date must not be after the end date")]
+    InvalidDateRange,
+    #[error("This room is served by {0}")]
+    RoomServedElsewhere(String),
+    #[error("The deal does not check out: {0}")]
+    DealMismatch(&'static str),
 }
 
 impl Error {
@@ -36,11 +143,64 @@ impl Error {
             Error::InvalidPassword => StatusCode::UNAUTHORIZED,
             Error::InsufficientBalance => StatusCode::BAD_REQUEST,
             Error::RoomIsFull => StatusCode::BAD_REQUEST,
+            Error::SeatUnavailable(_) => StatusCode::CONFLICT,
             Error::InvalidRoomId => StatusCode::NOT_FOUND,
             Error::NotInRoom => StatusCode::BAD_REQUEST,
             Error::UserNotFound => StatusCode::NOT_FOUND,
             Error::InvalidEmailOrPassword => StatusCode::BAD_REQUEST,
             Error::NoRoomFound => StatusCode::NOT_FOUND,
+            Error::RoomClosed => StatusCode::GONE,
+            Error::InvalidInviteCode => StatusCode::NOT_FOUND,
+            Error::RaiseExceedsStack => StatusCode::BAD_REQUEST,
+            Error::AdminOnly => StatusCode::FORBIDDEN,
+            Error::InvalidAdjustment => StatusCode::BAD_REQUEST,
+            Error::InvalidRoomPassword => StatusCode::FORBIDDEN,
+            Error::RaiseBelowMinimum(_) => StatusCode::BAD_REQUEST,
+            Error::RaiseNotReopened => StatusCode::BAD_REQUEST,
+            Error::RaiseAboveMaximum(_) => StatusCode::BAD_REQUEST,
+            Error::RaisingCapped => StatusCode::BAD_REQUEST,
+            Error::BetFacingBet => StatusCode::BAD_REQUEST,
+            Error::InvalidChatMessage => StatusCode::BAD_REQUEST,
+            Error::ChatRateLimited => StatusCode::TOO_MANY_REQUESTS,
+            Error::DarkActionUnavailable => StatusCode::BAD_REQUEST,
+            Error::PreActionUnavailable => StatusCode::BAD_REQUEST,
+            Error::NoCardsToShow => StatusCode::BAD_REQUEST,
+            Error::InvalidBotSeats => StatusCode::BAD_REQUEST,
+            Error::InvalidTableSize => StatusCode::BAD_REQUEST,
+            Error::InvalidClosingTime => StatusCode::BAD_REQUEST,
+            Error::InvalidWebhookUrl => StatusCode::BAD_REQUEST,
+            Error::WebhookUrlNotPublic => StatusCode::BAD_REQUEST,
+            Error::TooManyWebhooks(_) => StatusCode::CONFLICT,
+            Error::NotRoomCreator => StatusCode::FORBIDDEN,
+            Error::ServerAtCapacity(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Error::InvalidToken => StatusCode::UNAUTHORIZED,
+            Error::InvalidScenario => StatusCode::BAD_REQUEST,
+            Error::ScenarioNeedsPrivateRoom => StatusCode::BAD_REQUEST,
+            Error::SeasonAlreadyEnded => StatusCode::CONFLICT,
+            Error::NotAManualAdjustment => StatusCode::BAD_REQUEST,
+            Error::TopUpNotReady(_) => StatusCode::TOO_MANY_REQUESTS,
+            Error::InvalidBuyIn => StatusCode::BAD_REQUEST,
+            Error::UserBanned => StatusCode::FORBIDDEN,
+            Error::EmailNotVerified => StatusCode::FORBIDDEN,
+            Error::InvalidVerificationToken => StatusCode::BAD_REQUEST,
+            Error::InvalidResetCode => StatusCode::BAD_REQUEST,
+            Error::PasswordResetUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Error::PlayerNotFound => StatusCode::NOT_FOUND,
+            Error::AmbiguousPlayerName => StatusCode::CONFLICT,
+            Error::CannotFriendYourself => StatusCode::BAD_REQUEST,
+            Error::FriendRequestExists => StatusCode::CONFLICT,
+            Error::NoFriendRequest => StatusCode::NOT_FOUND,
+            Error::NotFriends => StatusCode::FORBIDDEN,
+            Error::HandNotFound => StatusCode::NOT_FOUND,
+            Error::AboveMaxBuyIn(_) => StatusCode::BAD_REQUEST,
+            Error::CannotBanStaff => StatusCode::FORBIDDEN,
+            Error::InvalidFeedback => StatusCode::BAD_REQUEST,
+            Error::InvalidAnnouncement => StatusCode::BAD_REQUEST,
+            Error::InvalidRoomImport => StatusCode::BAD_REQUEST,
+            Error::SessionEnded => StatusCode::UNAUTHORIZED,
+            Error::InvalidDateRange => StatusCode::BAD_REQUEST,
+            Error::RoomServedElsewhere(_) => StatusCode::MISDIRECTED_REQUEST,
+            Error::DealMismatch(_) => StatusCode::BAD_REQUEST,
         }
     }
 
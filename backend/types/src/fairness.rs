@@ -0,0 +1,127 @@
+use eyre::{ensure, Result};
+use poker::Card;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::deck::Deck;
+use crate::error::Error::DealMismatch;
+
+/// The seed a hand's deck is shuffled from. Players get the commitment as the hand is dealt
+/// and the whole deal once it is over, to check that no card was swapped in between.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Deal {
+    pub hand_number: u64,
+    // 32 bytes, hex encoded
+    pub seed: String,
+    // SHA-256 of the deck's order followed by the seed, hex encoded
+    pub commitment: String,
+    // players dealt two cards each, one after the other, before the board
+    pub hands_dealt: usize,
+}
+
+impl Deal {
+    pub fn new(hand_number: u64, seed: [u8; 32], hands_dealt: usize) -> Self {
+        Deal {
+            hand_number,
+            seed: hex::encode(seed),
+            commitment: commitment(seed),
+            hands_dealt,
+        }
+    }
+
+    /// Every card in the order the seed deals them
+    pub fn deck(&self) -> Result<Vec<Card>> {
+        Ok(deck_order(parse_seed(&self.seed)?))
+    }
+
+    /// Checks the seed against the commitment, then that the hole cards and the board
+    /// are where the seed put them in the deck. Unknown hole cards are left out of `hands`.
+    pub fn verify(&self, hands: &[[Card; 2]], board: &[Card]) -> Result<()> {
+        let seed = parse_seed(&self.seed)?;
+        ensure!(
+            commitment(seed) == self.commitment,
+            DealMismatch("the seed is not the one committed to")
+        );
+        let deck = deck_order(seed);
+        let dealt = 2 * self.hands_dealt;
+        ensure!(dealt <= deck.len(), DealMismatch("more hands than cards"));
+        let is_dealt =
+            |hand: &[Card; 2]| deck[..dealt].chunks(2).any(|pair| pair == hand.as_slice());
+        ensure!(
+            hands.iter().all(is_dealt),
+            DealMismatch("a hand was not dealt from the deck")
+        );
+        // a card is burnt before the flop, the turn and the river
+        let board_positions = [1, 2, 3, 5, 7].map(|offset| dealt + offset);
+        let is_board = board
+            .iter()
+            .zip(board_positions)
+            .all(|(card, position)| deck.get(position) == Some(card));
+        ensure!(
+            board.len() <= 5 && is_board,
+            DealMismatch("the board was not dealt from the deck")
+        );
+        Ok(())
+    }
+}
+
+/// Hex encoded SHA-256 of the cards `seed` shuffles the deck to, followed by the seed
+pub fn commitment(seed: [u8; 32]) -> String {
+    let mut hasher = Sha256::new();
+    for card in deck_order(seed) {
+        hasher.update(card.rank_suit_string());
+    }
+    hasher.update(seed);
+    format!("{:x}", hasher.finalize())
+}
+
+fn deck_order(seed: [u8; 32]) -> Vec<Card> {
+    let mut deck = Deck::from_seed(seed);
+    std::iter::from_fn(|| deck.draw().ok()).collect()
+}
+
+fn parse_seed(hex: &str) -> Result<[u8; 32]> {
+    ensure!(
+        hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        DealMismatch("the seed is not 32 bytes of hex")
+    );
+    let mut seed = [0; 32];
+    for (byte, pair) in seed.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair)?, 16)?;
+    }
+    Ok(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poker::card;
+
+    #[test]
+    fn deal_should_verify_the_cards_its_seed_deals() -> Result<()> {
+        let deal = Deal::new(1, [7; 32], 2);
+        let deck = deal.deck()?;
+        assert_eq!(deck.len(), 52);
+        let hands = [[deck[2], deck[3]]];
+        let board = [deck[5], deck[6], deck[7], deck[9]];
+        deal.verify(&hands, &board)?;
+
+        // a card swapped on the turn
+        let swapped = [deck[5], deck[6], deck[7], deck[10]];
+        assert!(deal.verify(&hands, &swapped).is_err());
+        let other_hand = [[deck[3], deck[2]]];
+        assert!(deal.verify(&other_hand, &board).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn deal_should_not_verify_against_another_commitment() -> Result<()> {
+        let mut deal = Deal::new(1, [7; 32], 2);
+        deal.commitment = Deal::new(1, [8; 32], 2).commitment;
+        assert!(deal.verify(&[], &[]).is_err());
+        deal.seed = "not hex".to_string();
+        assert!(deal.verify(&[], &[card!("As")?]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use eyre::{ContextCompat, Result};
+use poker::{Card, Eval};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::Action;
+use crate::fairness::Deal;
+use crate::room::{Hand, LoggedAction, Position, Room, Stage, Winnings};
+use crate::rules::{BIG_BLIND, SMALL_BLIND};
+use crate::state::SerdeCard;
+
+/// A player dealt into a recorded hand
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryPlayer {
+    pub id: Uuid,
+    pub name: String,
+    pub seat: usize,
+    // only known for players who reached showdown
+    pub hole_cards: Option<Vec<SerdeCard>>,
+    pub eval: Option<String>,
+    // both unknown for hands recorded before they were kept
+    #[serde(default)]
+    pub position: Option<Position>,
+    #[serde(default)]
+    pub starting_chips: Option<u32>,
+}
+
+/// Everything needed to replay a completed hand
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandHistory {
+    pub id: Uuid,
+    pub room_id: Uuid,
+    pub players: Vec<HistoryPlayer>,
+    pub community_cards: Vec<SerdeCard>,
+    pub actions: Vec<LoggedAction>,
+    pub results: Vec<Winnings>,
+    pub created_at: DateTime<Utc>,
+    // fingerprint of the deck the hand was dealt from, for fairness audits
+    #[serde(default)]
+    pub deck_hash: Option<String>,
+    // the seed committed to before the deal, missing for scripted hands
+    #[serde(default)]
+    pub deal: Option<Deal>,
+}
+
+impl HandHistory {
+    /// Snapshot of a room once its pots have been paid out
+    pub fn from_room(
+        room: &Room,
+        hands_eval: &HashMap<Uuid, Eval>,
+        results: Vec<Winnings>,
+    ) -> Self {
+        let players = room
+            .players
+            .iter()
+            .filter(|p| p.hand.is_some())
+            .map(|p| {
+                let eval = hands_eval.get(&p.id);
+                HistoryPlayer {
+                    id: p.id,
+                    name: p.name.clone(),
+                    seat: p.seat,
+                    hole_cards: eval
+                        .and(p.hand.as_ref())
+                        .map(|Hand(cards)| cards.iter().copied().map(SerdeCard).collect()),
+                    eval: eval.map(|e| e.to_string()),
+                    position: Some(p.position.clone()),
+                    starting_chips: room.starting_chips.get(&p.id).copied(),
+                }
+            })
+            .collect();
+        HandHistory {
+            id: Uuid::new_v4(),
+            room_id: room.id,
+            players,
+            community_cards: room
+                .community_cards
+                .iter()
+                .copied()
+                .map(SerdeCard)
+                .collect(),
+            actions: room.action_log.clone(),
+            results,
+            created_at: Utc::now(),
+            deck_hash: room.deck_hash.clone(),
+            deal: room.revealed_deal.clone(),
+        }
+    }
+
+    /// Checks the board and the hands shown down against the seed committed to for this hand
+    pub fn verify_deal(&self) -> Result<()> {
+        let deal = self
+            .deal
+            .as_ref()
+            .wrap_err("This hand was not dealt from a seed")?;
+        let hands: Vec<[Card; 2]> = self
+            .players
+            .iter()
+            .filter_map(|p| match p.hole_cards.as_deref() {
+                Some([first, second]) => Some([first.0, second.0]),
+                _ => None,
+            })
+            .collect();
+        let board: Vec<Card> = self.community_cards.iter().map(|card| card.0).collect();
+        deal.verify(&hands, &board)
+    }
+
+    /// The hand step by step: the deal with the blinds posted, a frame per action, then the
+    /// payouts
+    pub fn frames(&self) -> Vec<ReplayFrame> {
+        let mut seats: Vec<ReplaySeat> = self.players.iter().map(ReplaySeat::from).collect();
+        seats.sort_by_key(|seat| seat.seat);
+        for seat in &mut seats {
+            match seat.position {
+                Some(Position::BigBlind) => seat.put_in(BIG_BLIND),
+                Some(Position::SmallBlind | Position::DealerAndSmallBlind) => {
+                    seat.put_in(SMALL_BLIND)
+                }
+                _ => {}
+            }
+        }
+        let mut frame = ReplayFrame {
+            stage: Stage::PreFlop,
+            board: vec![],
+            seats,
+            pot: 0,
+            action: None,
+            results: vec![],
+        };
+        let mut frames = vec![frame.clone()];
+        for action in &self.actions {
+            if action.stage != frame.stage {
+                frame.collect_bets();
+                frame.stage = action.stage.clone();
+                frame.board = self.board_at(&action.stage);
+            }
+            frame.apply(action);
+            frame.action = Some(action.clone());
+            frames.push(frame.clone());
+        }
+        frame.collect_bets();
+        frame.stage = Stage::Showdown(false);
+        frame.board = self.community_cards.clone();
+        frame.action = None;
+        for winnings in &self.results {
+            if let Some(seat) = frame.seats.iter_mut().find(|s| s.id == winnings.player) {
+                seat.chips = seat.chips.map(|chips| chips + winnings.amount);
+            }
+        }
+        frame.results = self.results.clone();
+        frames.push(frame);
+        frames
+    }
+
+    // the community cards dealt by the time the street is played
+    fn board_at(&self, stage: &Stage) -> Vec<SerdeCard> {
+        let dealt = match stage {
+            Stage::NotEnoughPlayers | Stage::PreFlop => 0,
+            Stage::Flop => 3,
+            Stage::Turn => 4,
+            Stage::River | Stage::Showdown(_) => 5,
+        };
+        self.community_cards.iter().take(dealt).cloned().collect()
+    }
+}
+
+/// A seat at one point of a replayed hand
+#[derive(Debug, Clone)]
+pub struct ReplaySeat {
+    pub id: Uuid,
+    pub name: String,
+    pub seat: usize,
+    pub position: Option<Position>,
+    // chips behind, unknown when the starting stack is
+    pub chips: Option<u32>,
+    // put in on the current street
+    pub bet: u32,
+    pub folded: bool,
+    pub hole_cards: Option<Vec<SerdeCard>>,
+}
+
+impl ReplaySeat {
+    fn put_in(&mut self, amount: u32) {
+        let amount = self.chips.map_or(amount, |chips| amount.min(chips));
+        self.bet += amount;
+        self.chips = self.chips.map(|chips| chips - amount);
+    }
+}
+
+impl From<&HistoryPlayer> for ReplaySeat {
+    fn from(player: &HistoryPlayer) -> Self {
+        ReplaySeat {
+            id: player.id,
+            name: player.name.clone(),
+            seat: player.seat,
+            position: player.position.clone(),
+            chips: player.starting_chips,
+            bet: 0,
+            folded: false,
+            hole_cards: player.hole_cards.clone(),
+        }
+    }
+}
+
+/// The table after one step of a recorded hand
+#[derive(Debug, Clone)]
+pub struct ReplayFrame {
+    pub stage: Stage,
+    pub board: Vec<SerdeCard>,
+    // in seat order
+    pub seats: Vec<ReplaySeat>,
+    // bets of the streets before, the current street's are on the seats
+    pub pot: u32,
+    // the action that led here, None for the deal and the payouts
+    pub action: Option<LoggedAction>,
+    // only on the last frame
+    pub results: Vec<Winnings>,
+}
+
+impl ReplayFrame {
+    fn collect_bets(&mut self) {
+        for seat in &mut self.seats {
+            self.pot += seat.bet;
+            seat.bet = 0;
+        }
+    }
+
+    fn apply(&mut self, action: &LoggedAction) {
+        let to_call = self.seats.iter().map(|s| s.bet).max().unwrap_or_default();
+        let Some(seat) = self.seats.iter_mut().find(|s| s.id == action.player_id) else {
+            return;
+        };
+        let owed = to_call.saturating_sub(seat.bet);
+        match action.action {
+            Action::Fold => seat.folded = true,
+            Action::Check => {}
+            Action::Call => seat.put_in(owed),
+            Action::Bet(total) | Action::RaiseTo(total) => {
+                seat.put_in(total.saturating_sub(seat.bet))
+            }
+            Action::Raise(extra) => seat.put_in(owed + extra),
+            // without the starting stack an all in can only be shown as a call
+            Action::AllIn => seat.put_in(seat.chips.unwrap_or(owed)),
+        }
+    }
+
+    /// Chips in the middle, counting the current street's bets
+    pub fn total_pot(&self) -> u32 {
+        self.pot + self.seats.iter().map(|s| s.bet).sum::<u32>()
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct HistoryQuery {
+    // zero based
+    #[serde(default)]
+    pub page: Option<u32>,
+    #[serde(default)]
+    pub per_page: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandHistoryPage {
+    // newest first
+    pub hands: Vec<HandHistory>,
+    pub page: u32,
+    pub has_more: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(seat: usize, position: Position) -> HistoryPlayer {
+        HistoryPlayer {
+            id: Uuid::new_v4(),
+            name: format!("player {}", seat),
+            seat,
+            hole_cards: None,
+            eval: None,
+            position: Some(position),
+            starting_chips: Some(100),
+        }
+    }
+
+    fn logged(seq: u64, player: &HistoryPlayer, stage: Stage, action: Action) -> LoggedAction {
+        LoggedAction {
+            seq,
+            player_id: player.id,
+            player_name: player.name.clone(),
+            stage,
+            action,
+            is_dark: false,
+            fold_reason: None,
+        }
+    }
+
+    #[test]
+    fn frames_should_rebuild_bets_and_pot_after_every_action() {
+        let dealer = player(0, Position::DealerAndSmallBlind);
+        let big_blind = player(1, Position::BigBlind);
+        let history = HandHistory {
+            id: Uuid::new_v4(),
+            room_id: Uuid::new_v4(),
+            players: vec![big_blind.clone(), dealer.clone()],
+            community_cards: vec![],
+            actions: vec![
+                logged(1, &dealer, Stage::PreFlop, Action::RaiseTo(3 * BIG_BLIND)),
+                logged(2, &big_blind, Stage::PreFlop, Action::Call),
+                logged(3, &big_blind, Stage::Flop, Action::Bet(10)),
+                logged(4, &dealer, Stage::Flop, Action::Fold),
+            ],
+            results: vec![Winnings {
+                player: big_blind.id,
+                amount: 6 * BIG_BLIND + 10,
+                best_hand: vec![],
+            }],
+            created_at: Utc::now(),
+            deck_hash: None,
+            deal: None,
+        };
+
+        let frames = history.frames();
+        assert_eq!(frames.len(), 6);
+        // the deal, seated in seat order with the blinds posted
+        assert_eq!(frames[0].seats[0].bet, SMALL_BLIND);
+        assert_eq!(frames[0].seats[1].bet, BIG_BLIND);
+        assert_eq!(frames[2].total_pot(), 6 * BIG_BLIND);
+        // the pre flop bets are in the pot once the flop is played
+        assert_eq!(frames[3].pot, 6 * BIG_BLIND);
+        assert_eq!(frames[3].seats[1].bet, 10);
+        assert!(frames[4].seats[0].folded);
+        let last = frames.last().unwrap();
+        assert!(last.action.is_none());
+        assert_eq!(last.total_pot(), 6 * BIG_BLIND + 10);
+        assert_eq!(last.seats[1].chips, Some(100 + 3 * BIG_BLIND));
+    }
+}
@@ -1,5 +1,18 @@
+pub mod bot;
+pub mod cli;
+pub mod crypto;
 pub mod deck;
 pub mod domain;
+pub mod equity;
 pub mod error;
+pub mod fairness;
+pub mod history;
+pub mod protocol;
 pub mod room;
+pub mod rules;
+pub mod schema;
+pub mod season;
 pub mod state;
+pub mod strength;
+pub mod stats;
+pub mod webhook;
@@ -0,0 +1,92 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Version of the game socket protocol spoken by this build
+pub const PROTOCOL_VERSION: u32 = 2;
+// clients sending an older version are asked to upgrade, those from before versioning send none.
+// Version 2 sends a summary of the hand with the outcome, where it was only the winnings
+pub const MIN_PROTOCOL_VERSION: u32 = 2;
+
+/// Optional part of the game protocol, only used when both sides support it
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    Chat,
+    DarkActions,
+    ShowCards,
+    TopUp,
+    PreActions,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 5] = [
+        Feature::Chat,
+        Feature::DarkActions,
+        Feature::ShowCards,
+        Feature::TopUp,
+        Feature::PreActions,
+    ];
+}
+
+/// Sent once the game socket is accepted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Handshake {
+    pub protocol_version: u32,
+    // the features both sides support
+    pub features: Vec<Feature>,
+}
+
+impl Handshake {
+    /// Agrees on the features this server shares with a client
+    pub fn negotiate(client_features: &[Feature]) -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            features: Feature::ALL
+                .into_iter()
+                .filter(|feature| client_features.contains(feature))
+                .collect(),
+        }
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+}
+
+/// Sent right before the server drops a client too old to play
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UpgradeRequired {
+    // the version this server speaks
+    pub server_version: u32,
+    pub min_version: u32,
+}
+
+impl UpgradeRequired {
+    /// None when a client speaking `protocol_version` can play
+    pub fn check(protocol_version: u32) -> Option<Self> {
+        (protocol_version < MIN_PROTOCOL_VERSION).then_some(Self {
+            server_version: PROTOCOL_VERSION,
+            min_version: MIN_PROTOCOL_VERSION,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_features_both_sides_support_should_be_agreed_on() {
+        let handshake = Handshake::negotiate(&[Feature::TopUp, Feature::Chat]);
+        assert_eq!(handshake.features, vec![Feature::Chat, Feature::TopUp]);
+        assert!(!handshake.supports(Feature::DarkActions));
+    }
+
+    #[test]
+    fn clients_from_before_versioning_should_upgrade() {
+        assert!(UpgradeRequired::check(0).is_some());
+        // they can't read the outcome of a hand
+        assert!(UpgradeRequired::check(1).is_some());
+        assert!(UpgradeRequired::check(PROTOCOL_VERSION).is_none());
+    }
+}
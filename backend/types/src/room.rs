@@ -1,54 +1,598 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::ops::RangeInclusive;
+
+use chrono::{DateTime, Local, TimeDelta, Utc};
 use eyre::{bail, ensure, ContextCompat, Report, Result};
 use itertools::Itertools;
-use poker::{box_cards, Card};
+use poker::{box_cards, Card, Eval, Evaluator};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use ratatui::style::Stylize;
 use ratatui::text::Line;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use socketioxide::socket::Sid;
 use uuid::Uuid;
 
+use crate::crypto::{HandKey, SealedCards, SecretKey};
 use crate::deck::Deck;
 use crate::domain::ServiceRequiredAction;
-use crate::domain::{Action, User};
+use crate::domain::{
+    Action, Avatar, ChipCurrency, DarkAction, FoldReason, PreAction, RoomActivity, Seated, User,
+};
+use crate::equity::equity;
 use crate::error::Error;
+use crate::fairness::Deal;
+use crate::rules::{
+    default_max_players, normalize_raise, BettingRound, BettingStructure, LegalAction, RaiseLimits,
+    BIG_BLIND, BOT_BUY_IN, DEFAULT_KICK_AFTER_TIMEOUTS, DEFAULT_MAX_PLAYERS, MAX_BUY_IN,
+    RECLAIM_SEAT_TIME, SMALL_BLIND,
+};
+use crate::state::{PlayerHand, ResumeSummary, SerdeCard};
+use crate::stats::{HandOutcome, ShowdownResult};
+use crate::strength::best_five;
 
 #[derive(Debug, Clone)]
 pub struct Room {
     pub id: Uuid,
     pub players: Vec<Player>,
     pub deck: Deck,
+    // shuffles the deck of every hand and samples all-in equity, seeded from the OS
+    // unless the room was made with a seed
+    pub rng: StdRng,
     pub community_cards: Vec<Card>,
     pub stage: Stage,
     pub pots: Vec<Pot>,
     pub player_joining_next_round: Vec<Player>,
+    // keep their seat and chips, but are dealt out until they sit back in
+    pub players_sitting_out: Vec<Player>,
     pub player_in_turn: Option<Uuid>,
+    // no new hands are dealt once this deadline has passed
+    pub closes_at: Option<DateTime<Utc>>,
+    pub closing_notice_sent: bool,
+    // actions taken in the current hand, replayed to players rejoining mid-hand
+    pub action_log: Vec<LoggedAction>,
+    pub action_seq: u64,
+    // the player in turn is acted for automatically once this passes
+    pub turn_deadline: Option<DateTime<Utc>>,
+    // player in turn and the action count when their turn started
+    pub current_turn: Option<(Uuid, u64)>,
+    // set while the results of a hand are on display
+    pub showdown: Option<ShowdownPhase>,
+    // size of the last full raise this betting round, the next raise must be at least as big
+    pub last_raise_size: u32,
+    // players who already acted when a short all-in came in, they may only call or fold
+    pub raise_closed_for: HashSet<Uuid>,
+    // actions declared blind, with the street they are played on
+    pub dark_actions: HashMap<Uuid, (Stage, DarkAction)>,
+    // set by players waiting for their turn, dropped as the street ends
+    pub pre_actions: HashMap<Uuid, PreAction>,
+    // seats kept filled with server played bots
+    pub bot_seats: usize,
+    pub bots: HashSet<Uuid>,
+    // chips of everyone dealt into the current hand, before the blinds
+    pub starting_chips: HashMap<Uuid, u32>,
+    // cards are dealt from this script instead of at random
+    pub scenario: Option<Scenario>,
+    pub rake: Rake,
+    // kept by the house from the pots of the last hand paid out
+    pub rake_taken: u32,
+    // counts the hands dealt at this table, the first being 1
+    pub hand_number: u64,
+    // SHA-256 of the deck the current hand is dealt from, see `Deck::fingerprint`
+    pub deck_hash: Option<String>,
+    // seed of the hand in progress, players only see its commitment until the hand is over
+    pub deal: Option<Deal>,
+    pub revealed_deal: Option<Deal>,
+    pub hand_started_at: Option<DateTime<Utc>>,
+    pub street_started_at: Option<DateTime<Utc>>,
+    pub pace: TablePace,
+    pub last_hand_at: Option<DateTime<Utc>>,
+    // stage the room list was last told about, it is written again as the stage changes
+    pub listed_stage: Option<Stage>,
+    // community cards out when the betting closed with the board still to come, for all-in EV
+    pub all_in_board: Option<usize>,
+    // set once an all-in board starts being shown a street at a time
+    pub run_out: Option<RunOut>,
+    pub config: RoomConfig,
+    // hands in a row each player was folded by the turn timer
+    pub timeout_streaks: HashMap<Uuid, u32>,
+    // paid for from the players' balances, added to their stacks as the next hand is dealt
+    pub pending_top_ups: HashMap<Uuid, u32>,
+    // players queued for a seat while the table is full, first come first seated
+    pub waiting_list: VecDeque<WaitingPlayer>,
+    // players whose connection dropped or who were carried over from another server,
+    // their seat and chips are held until then for their client to reconnect
+    pub awaiting_reconnect: HashMap<Uuid, DateTime<Utc>>,
+    // entries for the players' action log, taken by the service as it sends them out
+    pub pending_log: Vec<ActionLogEntry>,
+}
+
+// how many of the latest hands make up a table's pace
+const PACE_SAMPLE_SIZE: usize = 20;
+
+/// How long the latest hands at a table took and how big their pots were,
+/// to tell fast tables from slow ones
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TablePace {
+    recent_hands: VecDeque<TimeDelta>,
+    recent_pots: VecDeque<u32>,
+}
+
+impl TablePace {
+    pub fn record_hand(&mut self, took: TimeDelta) {
+        if self.recent_hands.len() == PACE_SAMPLE_SIZE {
+            self.recent_hands.pop_front();
+        }
+        self.recent_hands.push_back(took);
+    }
+
+    pub fn record_pot(&mut self, pot: u32) {
+        if self.recent_pots.len() == PACE_SAMPLE_SIZE {
+            self.recent_pots.pop_front();
+        }
+        self.recent_pots.push_back(pot);
+    }
+
+    pub fn average_pot(&self) -> Option<u32> {
+        let total: u32 = self.recent_pots.iter().sum();
+        (!self.recent_pots.is_empty()).then(|| total / self.recent_pots.len() as u32)
+    }
+
+    pub fn average_hand(&self) -> Option<TimeDelta> {
+        let total: TimeDelta = self.recent_hands.iter().sum();
+        (!self.recent_hands.is_empty()).then(|| total / self.recent_hands.len() as i32)
+    }
+
+    pub fn hands_per_hour(&self) -> Option<u32> {
+        let seconds = self.average_hand()?.num_seconds();
+        (seconds > 0).then(|| (3600 / seconds) as u32)
+    }
+
+    fn to_millis(&self) -> Vec<i64> {
+        self.recent_hands
+            .iter()
+            .map(|took| took.num_milliseconds())
+            .collect()
+    }
+
+    fn from_millis(millis: &[i64]) -> Self {
+        let skip = millis.len().saturating_sub(PACE_SAMPLE_SIZE);
+        Self {
+            recent_hands: millis
+                .iter()
+                .skip(skip)
+                .map(|ms| TimeDelta::milliseconds(*ms))
+                .collect(),
+            recent_pots: VecDeque::new(),
+        }
+    }
+
+    fn with_pots(mut self, pots: &[u32]) -> Self {
+        let skip = pots.len().saturating_sub(PACE_SAMPLE_SIZE);
+        self.recent_pots = pots.iter().skip(skip).copied().collect();
+        self
+    }
+}
+
+/// Share of each pot the house keeps, capped per hand. No rake by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Rake {
+    pub percent: u32,
+    pub cap: u32,
+}
+
+impl Rake {
+    fn of(&self, pot: u32) -> u32 {
+        (pot as u64 * self.percent.min(100) as u64 / 100) as u32
+    }
 }
 
+/// Predetermined cards for teaching hands, anything not scripted is dealt at random
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    // hole cards by seat
+    #[serde(default)]
+    pub hole_cards: BTreeMap<usize, [SerdeCard; 2]>,
+    // community cards in the order they are dealt
+    #[serde(default)]
+    pub board: Vec<SerdeCard>,
+}
+
+impl Scenario {
+    pub fn validate(&self, max_players: usize) -> Result<()> {
+        ensure!(self.board.len() <= 5, Error::InvalidScenario);
+        ensure!(
+            self.hole_cards.keys().all(|seat| *seat < max_players),
+            Error::InvalidScenario
+        );
+        let cards: Vec<Card> = self.cards().collect();
+        ensure!(cards.iter().all_unique(), Error::InvalidScenario);
+        Ok(())
+    }
+
+    fn cards(&self) -> impl Iterator<Item = Card> + '_ {
+        self.hole_cards
+            .values()
+            .flatten()
+            .chain(self.board.iter())
+            .map(|card| card.0)
+    }
+
+    fn hole_cards_for(&self, seat: usize) -> Option<Hand> {
+        self.hole_cards
+            .get(&seat)
+            .map(|[a, b]| Hand([a.0, b.0]))
+    }
+}
+
+/// Table rules chosen when a room is opened
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RoomConfig {
+    // players folded by the turn timer this many hands in a row are removed, 0 never
+    pub kick_after_timeouts: u32,
+    #[serde(default)]
+    pub betting: BettingStructure,
+    // the balance buy-ins come from and stacks go back to
+    #[serde(default)]
+    pub currency: ChipCurrency,
+    // seats at the table, one of TABLE_SIZES
+    #[serde(default = "default_max_players::<usize>")]
+    pub max_players: usize,
+}
+
+impl Default for RoomConfig {
+    fn default() -> Self {
+        Self {
+            kick_after_timeouts: DEFAULT_KICK_AFTER_TIMEOUTS,
+            betting: BettingStructure::default(),
+            currency: ChipCurrency::default(),
+            max_players: DEFAULT_MAX_PLAYERS,
+        }
+    }
+}
+
+/// A player queued for a seat at a full table, seated with their buy-in once one frees up
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WaitingPlayer {
+    pub user_id: Uuid,
+    pub buy_in: i64,
+    // the socket that asked, gone after a restart
+    #[serde(skip)]
+    pub sid: Sid,
+}
+
+/// An all-in board dealt in full but shown a street at a time, so the table can sweat it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunOut {
+    // community cards shown so far
+    pub shown: usize,
+    // share of the pot each hand still in wins from here, ties split
+    pub equity: HashMap<Uuid, f64>,
+}
+
+/// Results of a hand on display until the deadline, or until every connected player is ready
 #[derive(Debug, Clone, PartialEq)]
+pub struct ShowdownPhase {
+    pub deadline: DateTime<Utc>,
+    pub ready: HashSet<Uuid>,
+    // hands everyone sees, winners always show
+    pub shown: HashSet<Uuid>,
+    // lost at showdown and yet to choose, their cards stay mucked unless they show them
+    pub undecided: HashSet<Uuid>,
+    pub hands_eval: HashMap<Uuid, Eval>,
+}
+
+/// What a room owes its players, saved on shutdown so restarting never loses a buy-in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoomSnapshot {
+    pub room_id: Uuid,
+    pub seats: Vec<SeatSnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeatSnapshot {
+    pub player_id: Uuid,
+    // stack to give back, including anything bet in a hand that was cut short
+    pub chips: u32,
+}
+
+/// What one hand did to a human player's stack
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HandSettlement {
+    pub player_id: Uuid,
+    pub delta: i64,
+    pub chips_after: u32,
+}
+
+/// A room as it stands, moved to another server without ending the hand in progress
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomExport {
+    pub id: Uuid,
+    pub players: Vec<Player>,
+    pub player_joining_next_round: Vec<Player>,
+    pub players_sitting_out: Vec<Player>,
+    pub community_cards: Vec<SerdeCard>,
+    pub stage: Stage,
+    pub pots: Vec<Pot>,
+    pub player_in_turn: Option<Uuid>,
+    pub closes_at: Option<DateTime<Utc>>,
+    pub closing_notice_sent: bool,
+    pub action_log: Vec<LoggedAction>,
+    pub action_seq: u64,
+    pub turn_deadline: Option<DateTime<Utc>>,
+    pub current_turn: Option<(Uuid, u64)>,
+    pub showdown: Option<ShowdownExport>,
+    pub last_raise_size: u32,
+    pub raise_closed_for: HashSet<Uuid>,
+    pub dark_actions: HashMap<Uuid, (Stage, DarkAction)>,
+    #[serde(default)]
+    pub pre_actions: HashMap<Uuid, PreAction>,
+    pub bot_seats: usize,
+    pub bots: HashSet<Uuid>,
+    pub starting_chips: HashMap<Uuid, u32>,
+    pub scenario: Option<Scenario>,
+    pub rake: Rake,
+    pub rake_taken: u32,
+    #[serde(default)]
+    pub hand_number: u64,
+    #[serde(default)]
+    pub deck_hash: Option<String>,
+    #[serde(default)]
+    pub revealed_deal: Option<Deal>,
+    pub hand_started_at: Option<DateTime<Utc>>,
+    pub street_started_at: Option<DateTime<Utc>>,
+    // how long each of the latest hands took, in milliseconds
+    pub pace: Vec<i64>,
+    // the latest hands' pots, for their average
+    #[serde(default)]
+    pub pace_pots: Vec<u32>,
+    #[serde(default)]
+    pub last_hand_at: Option<DateTime<Utc>>,
+    pub all_in_board: Option<usize>,
+    #[serde(default)]
+    pub run_out: Option<RunOut>,
+    #[serde(default)]
+    pub config: RoomConfig,
+    #[serde(default)]
+    pub timeout_streaks: HashMap<Uuid, u32>,
+    #[serde(default)]
+    pub pending_top_ups: HashMap<Uuid, u32>,
+    #[serde(default)]
+    pub waiting_list: VecDeque<WaitingPlayer>,
+    // hole cards and the undealt deck, only the importing server can read them
+    pub cards: SealedCards,
+}
+
+/// Results on display when the room was exported, hands are evaluated again on import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShowdownExport {
+    pub deadline: DateTime<Utc>,
+    pub ready: HashSet<Uuid>,
+    pub shown: HashSet<Uuid>,
+    pub undecided: HashSet<Uuid>,
+    pub evaluated: HashSet<Uuid>,
+}
+
+/// The cards of a room nobody may see in transit
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HiddenCards {
+    pub deck: u64,
+    pub hands: HashMap<Uuid, PlayerHand>,
+}
+
+/// What a player sees of a table before buying in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct RoomDetails {
+    pub room_id: Uuid,
+    pub is_private: bool,
+    pub small_blind: u32,
+    pub big_blind: u32,
+    pub max_players: usize,
+    pub rake: Rake,
+    pub stage: Stage,
+    pub seats: Vec<SeatPreview>,
+    // players seated but waiting for the next hand to be dealt in
+    pub waitlist: usize,
+    // players queued for a seat while the table is full
+    #[serde(default)]
+    pub queued: usize,
+    pub hands_per_hour: Option<u32>,
+    pub closes_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SeatPreview {
+    pub seat: usize,
+    pub name: String,
+    pub chips: u32,
+    pub is_bot: bool,
+    pub is_sitting_out: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LoggedAction {
+    pub seq: u64,
+    pub player_id: Uuid,
+    pub player_name: String,
+    pub stage: Stage,
+    pub action: Action,
+    // declared before the street was dealt
+    #[serde(default)]
+    pub is_dark: bool,
+    // set on folds only
+    #[serde(default)]
+    pub fold_reason: Option<FoldReason>,
+}
+
+impl LoggedAction {
+    pub fn line(&self) -> Line<'_> {
+        let action = match self.action {
+            Action::Fold => match self.fold_reason {
+                Some(FoldReason::Timeout) => "timed out and folded".to_string(),
+                Some(FoldReason::Disconnect) => "left and folded".to_string(),
+                _ => "folded".to_string(),
+            },
+            Action::Check => "checked".to_string(),
+            Action::Call => "called".to_string(),
+            Action::Bet(total) => format!("bet {}", total),
+            Action::Raise(amount) => format!("raised {}", amount),
+            Action::RaiseTo(total) => format!("raised to {}", total),
+            Action::AllIn => "went all in".to_string(),
+        };
+        let mut line = self.stage.line();
+        line.push_span(format!(": {} {}", self.player_name, action));
+        if self.is_dark {
+            line.push_span(" (dark)");
+        }
+        line
+    }
+}
+
+/// A line of the action log shown at the table, sent to players as it happens
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum ActionLogEntry {
+    Action(LoggedAction),
+    // the betting moved on to this street
+    Street(Stage),
+    PotAward { player_name: String, amount: u32 },
+}
+
+impl ActionLogEntry {
+    pub fn line(&self) -> Line<'_> {
+        match self {
+            ActionLogEntry::Action(logged) => logged.line(),
+            ActionLogEntry::Street(stage) => stage.line().bold().centered(),
+            ActionLogEntry::PotAward {
+                player_name,
+                amount,
+            } => format!("{} won {}", player_name, amount).into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Player {
     pub id: Uuid,
     pub name: String,
+    #[serde(default)]
+    pub avatar: Avatar,
+    // never exported in the clear, see HiddenCards
+    #[serde(skip)]
     pub hand: Option<Hand>,
     pub chips: u32,
     pub bet: u32,
     pub has_folded: bool,
     pub position: Position,
     pub has_taken_turn: bool,
+    #[serde(skip)]
     pub sid: Sid,
     pub is_connected: bool,
     pub last_action: Option<Action>,
+    // stable seat index around the table, assigned when joining
+    pub seat: usize,
+    // skips hands from the next deal on, without giving up the seat
+    pub is_sitting_out: bool,
+    // why the player folded this hand, when they have
+    pub fold_reason: Option<FoldReason>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+pub struct GameResult {
+    pub hands_eval: HashMap<Uuid, Eval>,
+    pub winners: Vec<(u32, HashSet<Uuid>)>,
+}
+
+impl GameResult {
+    /// Hand class of every player that showed down and whether they won a pot
+    pub fn showdown_results(&self) -> Vec<ShowdownResult> {
+        self.hands_eval
+            .iter()
+            .map(|(player_id, eval)| ShowdownResult {
+                player_id: *player_id,
+                hand_class: eval.class().into(),
+                won: self
+                    .winners
+                    .iter()
+                    .any(|(_, winners)| winners.contains(player_id)),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Pot {
     pub amount: u32,
     pub players: HashSet<Uuid>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Winnings {
     pub player: Uuid,
     pub amount: u32,
+    // the five cards that won at showdown, empty when everyone else folded
+    #[serde(default)]
+    pub best_hand: Vec<SerdeCard>,
+}
+
+impl Winnings {
+    /// One entry per player, summing what they won across pots
+    pub fn merge(winnings: Vec<Winnings>) -> Vec<Winnings> {
+        let mut merged: Vec<Winnings> = Vec::new();
+        for w in winnings {
+            match merged.iter_mut().find(|m| m.player == w.player) {
+                Some(m) => m.amount += w.amount,
+                None => merged.push(w),
+            }
+        }
+        merged
+    }
+}
+
+/// How a finished hand went for one of the players dealt into it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct PlayerResult {
+    pub player: Uuid,
+    pub name: String,
+    // chips won less chips put in
+    pub net_chips: i64,
+    // the hand shown down, None when the player folded or nobody was called
+    pub eval: Option<String>,
+}
+
+/// Sent to the table once the pots of a hand are paid out, empty when the next hand starts
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Outcome {
+    // one entry per winner, summing what they won across pots
+    pub winnings: Vec<Winnings>,
+    // before the rake
+    pub pot: u32,
+    // in seat order
+    pub players: Vec<PlayerResult>,
+}
+
+impl Outcome {
+    pub fn is_winner(&self, player: Uuid) -> bool {
+        self.winnings.iter().any(|w| w.player == player)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum RoomClosing {
+    Scheduled(DateTime<Utc>),
+    Closed,
+}
+
+impl RoomClosing {
+    pub fn line(&self) -> Line<'static> {
+        match self {
+            RoomClosing::Scheduled(closes_at) => format!(
+                "Room closes at {}",
+                closes_at.with_timezone(&Local).format("%H:%M")
+            )
+            .into(),
+            RoomClosing::Closed => "Room closed, press Esc to leave".into(),
+        }
+    }
 }
 
 impl Player {
@@ -56,6 +600,7 @@ impl Player {
         Player {
             id: Uuid::new_v4(),
             name,
+            avatar: Avatar::default(),
             hand: None,
             chips: buy_in,
             bet: 0,
@@ -65,6 +610,9 @@ impl Player {
             sid: Sid::default(),
             is_connected: true,
             last_action: None,
+            seat: 0,
+            is_sitting_out: false,
+            fold_reason: None,
         }
     }
 
@@ -83,6 +631,7 @@ impl Player {
         Player {
             id: user.id,
             name: user.name.clone(),
+            avatar: user.avatar,
             hand: None,
             chips: buy_in,
             bet: 0,
@@ -92,11 +641,14 @@ impl Player {
             sid,
             is_connected: true,
             last_action: None,
+            seat: 0,
+            is_sitting_out: false,
+            fold_reason: None,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default, JsonSchema)]
 pub enum Stage {
     #[default]
     NotEnoughPlayers,
@@ -139,7 +691,9 @@ impl Stage {
     }
 }
 
-#[derive(Debug, Eq, Hash, PartialEq, Clone, Ord, PartialOrd, Serialize, Deserialize)]
+#[derive(
+    Debug, Eq, Hash, PartialEq, Clone, Ord, PartialOrd, Serialize, Deserialize, JsonSchema,
+)]
 pub enum Position {
     Normal,
     BigBlind,
@@ -162,7 +716,6 @@ impl Default for Room {
         Self::new()
     }
 }
-pub const MAX_NUM_OF_PLAYERS: usize = 5;
 
 impl Room {
     pub fn new() -> Self {
@@ -170,28 +723,183 @@ impl Room {
             id: Uuid::new_v4(),
             players: Vec::new(),
             deck: Deck::new(),
+            rng: StdRng::from_entropy(),
             community_cards: Vec::with_capacity(5),
             stage: Stage::NotEnoughPlayers,
             pots: vec![],
             player_joining_next_round: Vec::new(),
+            players_sitting_out: Vec::new(),
             player_in_turn: None,
+            closes_at: None,
+            closing_notice_sent: false,
+            action_log: Vec::new(),
+            action_seq: 0,
+            turn_deadline: None,
+            current_turn: None,
+            showdown: None,
+            last_raise_size: BIG_BLIND,
+            raise_closed_for: HashSet::new(),
+            dark_actions: HashMap::new(),
+            pre_actions: HashMap::new(),
+            bot_seats: 0,
+            bots: HashSet::new(),
+            starting_chips: HashMap::new(),
+            scenario: None,
+            rake: Rake::default(),
+            rake_taken: 0,
+            hand_number: 0,
+            deck_hash: None,
+            deal: None,
+            revealed_deal: None,
+            hand_started_at: None,
+            street_started_at: None,
+            pace: TablePace::default(),
+            last_hand_at: None,
+            listed_stage: None,
+            all_in_board: None,
+            run_out: None,
+            config: RoomConfig::default(),
+            timeout_streaks: HashMap::new(),
+            pending_top_ups: HashMap::new(),
+            waiting_list: VecDeque::new(),
+            awaiting_reconnect: HashMap::new(),
+            pending_log: Vec::new(),
         }
     }
 
+    /// A room that deals the same cards every time it is made with the same seed
+    pub fn with_seed(seed: u64) -> Self {
+        let mut room = Room::new();
+        room.reseed(seed);
+        room
+    }
+
+    /// Deals from `seed` from now on, the cards that follow are the same for the same seed
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
     pub fn max_bet(&self) -> u32 {
         self.players.iter().map(|p| p.bet).max().unwrap_or_default()
     }
 
+    // collected pots and the bets still on the table
+    fn pot(&self) -> u32 {
+        self.pots.iter().map(|p| p.amount).sum::<u32>()
+            + self.players.iter().map(|p| p.bet).sum::<u32>()
+    }
+
+    // totals the player may bet or raise to under the table's betting structure, whatever their stack
+    fn raise_to_range(&self, player: &Player) -> Option<RangeInclusive<u32>> {
+        let round = BettingRound {
+            max_bet: self.max_bet(),
+            bet: player.bet,
+            last_raise_size: self.last_raise_size,
+            pot: self.pot(),
+            late_street: matches!(self.stage, Stage::Turn | Stage::River),
+        };
+        self.config.betting.raise_to_range(&round)
+    }
+
+    /// Totals the player may bet or raise to, None when they may not raise
+    pub fn raise_limits(&self, player_id: Uuid) -> Option<RaiseLimits> {
+        if self.raise_closed_for.contains(&player_id) {
+            return None;
+        }
+        let player = self.players.iter().find(|p| p.id == player_id)?;
+        let stack = player.bet + player.chips;
+        // without more than the bet, going all-in only calls
+        if stack <= self.max_bet() {
+            return None;
+        }
+        let range = self.raise_to_range(player)?;
+        // a stack short of the smallest raise can still go all-in
+        Some(RaiseLimits {
+            min: (*range.start()).min(stack),
+            max: (*range.end()).min(stack),
+        })
+    }
+
+    /// What the player may do on their turn, the same checks `take_action` makes
+    pub fn legal_actions(&self, player_id: Uuid) -> Vec<LegalAction> {
+        if self.player_in_turn != Some(player_id) {
+            return Vec::new();
+        }
+        let Some(player) = self.players.iter().find(|p| p.id == player_id) else {
+            return Vec::new();
+        };
+        let max_bet = self.max_bet();
+        let to_call = max_bet.saturating_sub(player.bet);
+        let stack = player.bet + player.chips;
+        let mut actions = vec![LegalAction::Fold];
+        if to_call == 0 {
+            actions.push(LegalAction::Check);
+        } else if to_call <= player.chips {
+            actions.push(LegalAction::Call { amount: to_call });
+        }
+        let limits = self.raise_limits(player_id);
+        // raising the whole stack is offered as an all-in
+        match limits.filter(|limits| limits.min < stack) {
+            Some(limits) if to_call == 0 => actions.push(LegalAction::Bet(limits)),
+            Some(limits) => actions.push(LegalAction::Raise(limits)),
+            None => {}
+        }
+        // an all-in that raises has to fit the betting structure, one that only calls does not
+        let all_in_allowed = stack <= max_bet || limits.is_some_and(|limits| stack <= limits.max);
+        if player.chips > 0 && all_in_allowed {
+            actions.push(LegalAction::AllIn {
+                amount: player.chips,
+            });
+        }
+        actions
+    }
+
     pub fn new_with_id(id: Uuid) -> Self {
         Room {
             id,
             players: Vec::new(),
             deck: Deck::new(),
+            rng: StdRng::from_entropy(),
             community_cards: Vec::with_capacity(5),
             stage: Stage::NotEnoughPlayers,
             pots: vec![],
             player_joining_next_round: Vec::new(),
+            players_sitting_out: Vec::new(),
             player_in_turn: None,
+            closes_at: None,
+            closing_notice_sent: false,
+            action_log: Vec::new(),
+            action_seq: 0,
+            turn_deadline: None,
+            current_turn: None,
+            showdown: None,
+            last_raise_size: BIG_BLIND,
+            raise_closed_for: HashSet::new(),
+            dark_actions: HashMap::new(),
+            pre_actions: HashMap::new(),
+            bot_seats: 0,
+            bots: HashSet::new(),
+            starting_chips: HashMap::new(),
+            scenario: None,
+            rake: Rake::default(),
+            rake_taken: 0,
+            hand_number: 0,
+            deck_hash: None,
+            deal: None,
+            revealed_deal: None,
+            hand_started_at: None,
+            street_started_at: None,
+            pace: TablePace::default(),
+            last_hand_at: None,
+            listed_stage: None,
+            all_in_board: None,
+            run_out: None,
+            config: RoomConfig::default(),
+            timeout_streaks: HashMap::new(),
+            pending_top_ups: HashMap::new(),
+            waiting_list: VecDeque::new(),
+            awaiting_reconnect: HashMap::new(),
+            pending_log: Vec::new(),
         }
     }
 
@@ -223,10 +931,100 @@ impl Room {
             })
     }
 
+    /// Where a player sits, and whether they wait for the hand under way to finish
+    pub fn seating(&self, player_id: Uuid) -> Option<Seated> {
+        let seated = self
+            .players
+            .iter()
+            .chain(self.players_sitting_out.iter())
+            .find(|p| p.id == player_id);
+        if let Some(player) = seated {
+            return Some(Seated {
+                room_id: self.id,
+                seat: player.seat,
+                dealt_in_next_hand: false,
+                estimated_wait: None,
+            });
+        }
+        let player = self
+            .player_joining_next_round
+            .iter()
+            .find(|p| p.id == player_id)?;
+        // a hand running longer than usual is about to end
+        let estimated_wait = self
+            .pace
+            .average_hand()
+            .zip(self.hand_started_at)
+            .map(|(average, started)| (started + average - Utc::now()).num_seconds().max(0) as u64);
+        Some(Seated {
+            room_id: self.id,
+            seat: player.seat,
+            dealt_in_next_hand: true,
+            estimated_wait,
+        })
+    }
+
+    /// Fails with the reason a new player cannot sit down right now
+    pub fn check_joinable(&self) -> Result<()> {
+        ensure!(!self.is_past_closing_time(), Error::RoomClosed);
+        ensure!(
+            self.is_joinable() && self.free_seat().is_some(),
+            Error::RoomIsFull
+        );
+        Ok(())
+    }
+
+    /// Queues a player for the next free seat, returns their place in the queue from 1.
+    /// A player already queued keeps their place, with the latest buy-in and socket.
+    pub fn queue_player(&mut self, user_id: Uuid, buy_in: i64, sid: Sid) -> Result<usize> {
+        ensure!(!self.is_past_closing_time(), Error::RoomClosed);
+        let waiting = WaitingPlayer {
+            user_id,
+            buy_in,
+            sid,
+        };
+        match self.waiting_list.iter().position(|w| w.user_id == user_id) {
+            Some(index) => {
+                self.waiting_list[index] = waiting;
+                Ok(index + 1)
+            }
+            None => {
+                self.waiting_list.push_back(waiting);
+                Ok(self.waiting_list.len())
+            }
+        }
+    }
+
+    pub fn leave_waiting_list(&mut self, user_id: Uuid) -> bool {
+        let queued = self.waiting_list.len();
+        self.waiting_list.retain(|w| w.user_id != user_id);
+        self.waiting_list.len() < queued
+    }
+
+    /// Takes the first queued player off the list, once there is a seat for them
+    pub fn next_waiting(&mut self) -> Option<WaitingPlayer> {
+        self.check_joinable().ok()?;
+        self.waiting_list.pop_front()
+    }
+
     pub fn join_player(&mut self, player: Player) -> Result<ServiceRequiredAction> {
-        if !self.is_joinable() {
-            bail!(Error::RoomIsFull);
+        self.join_player_at(player, None)
+    }
+
+    /// Seats the player at the seat they asked for, or the first free one
+    pub fn join_player_at(
+        &mut self,
+        mut player: Player,
+        seat: Option<usize>,
+    ) -> Result<ServiceRequiredAction> {
+        self.check_joinable()?;
+        if let Some(seat) = seat {
+            self.check_seat_free(seat)?;
         }
+        player.seat = match seat {
+            Some(seat) => seat,
+            None => self.free_seat().wrap_err(Error::RoomIsFull)?,
+        };
         match self.stage {
             Stage::NotEnoughPlayers => {
                 self.players.push(player);
@@ -239,104 +1037,650 @@ impl Room {
         }
     }
 
-    pub fn leave_player(&mut self, player_id: Uuid) -> u32 {
-        let chips = self
-            .players
+    /// The player wherever they sit, including after they left the table
+    pub fn seated_player(&self, player_id: Uuid) -> Option<&Player> {
+        self.players
             .iter()
             .chain(self.player_joining_next_round.iter())
+            .chain(self.players_sitting_out.iter())
             .find(|p| p.id == player_id)
+    }
+
+    pub fn leave_player(&mut self, player_id: Uuid) -> u32 {
+        let chips = self
+            .seated_player(player_id)
             .map(|p| p.chips)
-            .unwrap_or_default();
+            .unwrap_or_default()
+            + self.pending_top_ups.remove(&player_id).unwrap_or_default();
+        // the player in turn is folded once their timer runs out, like any other turn
+        // an all-in hand being run out is decided, the cards of a player leaving still play
+        let keeps_cards = self.run_out.is_some();
+        let hand_in_progress =
+            self.stage != Stage::NotEnoughPlayers && self.showdown.is_none() && !keeps_cards;
+        let folds_now = hand_in_progress
+            && self.player_in_turn != Some(player_id)
+            && self
+                .players
+                .iter()
+                .any(|p| p.id == player_id && !p.has_folded);
         self.players
             .iter_mut()
             .chain(self.player_joining_next_round.iter_mut())
+            .chain(self.players_sitting_out.iter_mut())
             .filter(|p| p.id == player_id)
             .for_each(|p| {
                 p.is_connected = false;
-                p.has_folded = true;
+                p.has_folded |= !keeps_cards;
             });
+        self.awaiting_reconnect.remove(&player_id);
+        self.timeout_streaks.remove(&player_id);
+        if folds_now {
+            self.log_disconnect_fold(player_id);
+        }
         if self.players.iter().all(|p| !p.is_connected) {
             self.reset_table();
             self.stage = Stage::NotEnoughPlayers;
+            self.run_out = None;
         }
         chips
     }
 
-    fn is_joinable(&self) -> bool {
-        self.player_count() < MAX_NUM_OF_PLAYERS
+    // a player leaving mid hand shows up in the hand history as folding right then
+    fn log_disconnect_fold(&mut self, player_id: Uuid) {
+        let Some(player) = self.players.iter_mut().find(|p| p.id == player_id) else {
+            return;
+        };
+        player.last_action = Some(Action::Fold);
+        player.fold_reason = Some(FoldReason::Disconnect);
+        let player_name = player.name.clone();
+        let reason = Some(FoldReason::Disconnect);
+        self.log_action(player_id, player_name, Action::Fold, false, reason);
     }
 
-    pub fn player_count(&self) -> usize {
-        self.players
-            .iter()
-            .chain(self.player_joining_next_round.iter())
-            .filter(|p| p.is_connected && p.chips > 0)
-            .count()
+    fn log_action(
+        &mut self,
+        player_id: Uuid,
+        player_name: String,
+        action: Action,
+        is_dark: bool,
+        fold_reason: Option<FoldReason>,
+    ) {
+        self.action_seq += 1;
+        let logged = LoggedAction {
+            seq: self.action_seq,
+            player_id,
+            player_name,
+            stage: self.stage.clone(),
+            action,
+            is_dark,
+            fold_reason,
+        };
+        self.action_log.push(logged.clone());
+        self.pending_log.push(ActionLogEntry::Action(logged));
     }
 
-    pub fn start_game(&mut self) -> Result<()> {
-        self.reset_table();
-        // Reset the bets
-        self.players.iter_mut().try_for_each(|p| {
-            p.bet = 0;
-            p.has_folded = false;
-            p.has_taken_turn = false;
-            p.hand = Some(Hand([self.deck.draw()?, self.deck.draw()?]));
-            Ok::<(), Report>(())
-        })?;
-
-        // find the next dealer
-        let dealer_seat = self
-            .players
-            .iter()
-            .enumerate()
-            .rev()
-            .max_by(|a, b| a.1.position.cmp(&b.1.position))
-            .map(|(i, _)| i)
-            .wrap_err("No players")?;
+    // only streets that are played are logged, not waiting for players
+    fn log_street(&mut self) {
+        if self.stage != Stage::NotEnoughPlayers {
+            let street = ActionLogEntry::Street(self.stage.clone());
+            self.pending_log.push(street);
+        }
+    }
 
-        let dealer = self.players.get(dealer_seat).wrap_err("Dealer not found")?;
-        let next_dealer_seat = match dealer.position {
-            Position::Dealer | Position::DealerAndSmallBlind => {
-                (dealer_seat + 1) % self.players.len()
-            }
-            _ => dealer_seat,
-        };
-        self.readjust_positions(next_dealer_seat)?;
+    /// Keeps `bot_seats` seats played by bots, filled from the next hand on
+    pub fn set_bot_seats(&mut self, bot_seats: usize) -> Result<ServiceRequiredAction> {
+        ensure!(bot_seats < self.config.max_players, Error::InvalidBotSeats);
+        self.bot_seats = bot_seats;
+        if self.stage == Stage::NotEnoughPlayers {
+            self.seat_players();
+            return self.proceed();
+        }
+        Ok(ServiceRequiredAction::NoAction)
+    }
 
-        self.apply_binds()?;
+    pub fn is_bot(&self, player_id: Uuid) -> bool {
+        self.bots.contains(&player_id)
+    }
 
-        self.player_in_turn = Some(self.player_to_act_first()?);
+    /// Checks that a seated player may add the chips, before their balance pays for them
+    pub fn check_top_up(&self, player_id: Uuid, amount: u32) -> Result<()> {
+        ensure!(amount > 0, Error::InvalidBuyIn);
+        let player = self
+            .seated_player(player_id)
+            .filter(|p| p.is_connected && !self.is_bot(p.id))
+            .wrap_err(Error::NotInRoom)?;
+        // chips in the pot still count, the hand may give them back
+        let stack = player.chips + player.bet + self.pending_top_up(player_id);
+        ensure!(
+            amount <= MAX_BUY_IN.saturating_sub(stack),
+            Error::AboveMaxBuyIn(MAX_BUY_IN)
+        );
         Ok(())
     }
 
-    fn apply_binds(&mut self) -> Result<()> {
-        self.players.iter_mut().try_for_each(|p| match p.position {
-            Position::BigBlind => p.bet_amount(2),
-            Position::SmallBlind | Position::DealerAndSmallBlind => p.bet_amount(1),
-            _ => Ok(()),
-        })
+    /// Adds chips the player paid for to their stack, right away between hands,
+    /// otherwise as the next hand is dealt
+    pub fn top_up(&mut self, player_id: Uuid, amount: u32) -> Result<ServiceRequiredAction> {
+        self.check_top_up(player_id, amount)?;
+        self.add_top_up(player_id, amount)
     }
 
-    fn reset_table(&mut self) {
-        self.seat_players();
-
+    /// Adds chips checked and paid for earlier, the hand may have moved on since
+    pub fn add_top_up(&mut self, player_id: Uuid, amount: u32) -> Result<ServiceRequiredAction> {
+        *self.pending_top_ups.entry(player_id).or_default() += amount;
+        if self.stage == Stage::NotEnoughPlayers {
+            self.seat_players();
+            return self.proceed();
+        }
+        Ok(ServiceRequiredAction::NoAction)
+    }
+
+    pub fn pending_top_up(&self, player_id: Uuid) -> u32 {
+        self.pending_top_ups
+            .get(&player_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Deals the player out of hands from the next one on, they keep their seat and chips
+    pub fn sit_out(&mut self, player_id: Uuid) -> Result<ServiceRequiredAction> {
+        self.set_sitting_out(player_id, true)
+    }
+
+    /// Deals the player back in from the next hand
+    pub fn sit_in(&mut self, player_id: Uuid) -> Result<ServiceRequiredAction> {
+        self.set_sitting_out(player_id, false)
+    }
+
+    fn set_sitting_out(
+        &mut self,
+        player_id: Uuid,
+        is_sitting_out: bool,
+    ) -> Result<ServiceRequiredAction> {
+        let player = self
+            .players
+            .iter_mut()
+            .chain(self.player_joining_next_round.iter_mut())
+            .chain(self.players_sitting_out.iter_mut())
+            .find(|p| p.id == player_id && p.is_connected)
+            .wrap_err(Error::NotInRoom)?;
+        player.is_sitting_out = is_sitting_out;
+        if self.stage == Stage::NotEnoughPlayers {
+            // no hand is running, so the table can be rearranged right away
+            self.seat_players();
+            return self.proceed();
+        }
+        Ok(ServiceRequiredAction::NoAction)
+    }
+
+    /// Declares an action for the next street before it is dealt, replacing any earlier one.
+    /// Between hands it is played on the pre-flop of the next hand.
+    pub fn declare_dark_action(&mut self, player_id: Uuid, action: DarkAction) -> Result<()> {
+        let (street, in_hand) = match self.stage {
+            Stage::NotEnoughPlayers | Stage::Showdown(_) => (
+                Stage::PreFlop,
+                self.players
+                    .iter()
+                    .chain(self.player_joining_next_round.iter())
+                    .any(|p| p.id == player_id && p.is_connected && !p.is_sitting_out),
+            ),
+            Stage::PreFlop | Stage::Flop | Stage::Turn => (
+                match self.stage {
+                    Stage::PreFlop => Stage::Flop,
+                    Stage::Flop => Stage::Turn,
+                    _ => Stage::River,
+                },
+                self.players
+                    .iter()
+                    .any(|p| p.id == player_id && !p.has_folded && p.chips > 0),
+            ),
+            Stage::River => bail!(Error::DarkActionUnavailable),
+        };
+        ensure!(in_hand, Error::DarkActionUnavailable);
+        self.dark_actions.insert(player_id, (street, action));
+        Ok(())
+    }
+
+    /// Plays dark actions and pre-actions for as long as the action lands on a player who set one
+    pub fn play_dark_actions(
+        &mut self,
+        mut action_required: ServiceRequiredAction,
+    ) -> Result<ServiceRequiredAction> {
+        while action_required != ServiceRequiredAction::FindWinners {
+            let due = self
+                .due_dark_action()
+                .map(|(player_id, action)| (player_id, action, true))
+                .or_else(|| {
+                    self.due_pre_action()
+                        .map(|(player_id, action)| (player_id, action, false))
+                });
+            let Some((player_id, action, is_dark)) = due else {
+                break;
+            };
+            action_required = match (action_required, self.play(player_id, action, is_dark, FoldReason::Voluntary)?) {
+                // the cards just dealt still have to be sent out
+                (
+                    ServiceRequiredAction::PlayerReceiveCards,
+                    ServiceRequiredAction::NoAction,
+                ) => ServiceRequiredAction::PlayerReceiveCards,
+                (_, played) => played,
+            };
+        }
+        Ok(action_required)
+    }
+
+    // the declaration is used up either way, when no longer legal the player acts as usual
+    fn due_dark_action(&mut self) -> Option<(Uuid, Action)> {
+        let player_id = self.player_in_turn?;
+        if self.dark_actions.get(&player_id)?.0 != self.stage {
+            return None;
+        }
+        let (_, dark_action) = self.dark_actions.remove(&player_id)?;
+        let max_bet = self.max_bet();
+        let pot = self.pot();
+        let player = self.players.iter().find(|p| p.id == player_id)?;
+        // a dark check or bet only stands while nobody has bet in front of the player
+        if player.bet < max_bet {
+            return None;
+        }
+        let action = match dark_action {
+            DarkAction::Check => Action::Check,
+            DarkAction::BetPot if self.raise_closed_for.contains(&player_id) => return None,
+            DarkAction::BetPot => {
+                // as close to the pot as the betting structure allows
+                let range = self.raise_to_range(player)?;
+                let total = (player.bet + pot).clamp(*range.start(), *range.end());
+                if total >= player.bet + player.chips {
+                    Action::AllIn
+                } else {
+                    Action::Bet(total)
+                }
+            }
+        };
+        Some((player_id, action))
+    }
+
+    /// Sets what to play once the player's turn comes on this street, None takes it back
+    pub fn set_pre_action(&mut self, player_id: Uuid, pre_action: Option<PreAction>) -> Result<()> {
+        let Some(pre_action) = pre_action else {
+            self.pre_actions.remove(&player_id);
+            return Ok(());
+        };
+        let betting = matches!(
+            self.stage,
+            Stage::PreFlop | Stage::Flop | Stage::Turn | Stage::River
+        ) && !self.is_running_out();
+        let waiting = self.player_in_turn != Some(player_id)
+            && self
+                .players
+                .iter()
+                .any(|p| p.id == player_id && !p.has_folded && p.chips > 0);
+        ensure!(betting && waiting, Error::PreActionUnavailable);
+        self.pre_actions.insert(player_id, pre_action);
+        Ok(())
+    }
+
+    // used up either way, a call is dropped when someone raised after it was set
+    fn due_pre_action(&mut self) -> Option<(Uuid, Action)> {
+        let player_id = self.player_in_turn?;
+        let pre_action = self.pre_actions.remove(&player_id)?;
+        let max_bet = self.max_bet();
+        let player = self.players.iter().find(|p| p.id == player_id)?;
+        let owed = max_bet.saturating_sub(player.bet);
+        let action = match pre_action {
+            PreAction::Call(total) if total != max_bet => return None,
+            _ if owed == 0 => Action::Check,
+            PreAction::CheckFold => Action::Fold,
+            _ if owed >= player.chips => Action::AllIn,
+            _ => Action::Call,
+        };
+        Some((player_id, action))
+    }
+
+    /// What a player rejoining mid-hand has missed since `last_seen_seq`, if a hand is running
+    pub fn resume_summary(
+        &self,
+        player_id: Uuid,
+        last_seen_seq: Option<u64>,
+    ) -> Option<ResumeSummary> {
+        if matches!(self.stage, Stage::NotEnoughPlayers | Stage::Showdown(_)) {
+            return None;
+        }
+        let actions = self
+            .action_log
+            .iter()
+            .filter(|a| last_seen_seq.is_none_or(|seen| a.seq > seen))
+            .cloned()
+            .collect();
+        let max_bet = self
+            .players
+            .iter()
+            .filter(|p| !p.has_folded)
+            .map(|p| p.bet)
+            .max()
+            .unwrap_or_default();
+        let to_call = self
+            .players
+            .iter()
+            .find(|p| p.id == player_id && !p.has_folded)
+            .map(|p| max_bet - p.bet);
+        Some(ResumeSummary { actions, to_call })
+    }
+
+    pub fn start_showdown_phase(
+        &mut self,
+        duration: TimeDelta,
+        hands_eval: HashMap<Uuid, Eval>,
+        winners: HashSet<Uuid>,
+    ) {
+        let undecided = hands_eval
+            .keys()
+            .filter(|player_id| !winners.contains(player_id))
+            .copied()
+            .collect();
+        self.revealed_deal = self.deal.take();
+        self.showdown = Some(ShowdownPhase {
+            deadline: Utc::now() + duration,
+            ready: HashSet::new(),
+            shown: winners,
+            undecided,
+            hands_eval,
+        });
+    }
+
+    /// A player who lost at showdown reveals or mucks their cards, only once.
+    /// Returns true when the cards are now on display.
+    pub fn show_or_muck(&mut self, player_id: Uuid, show: bool) -> Result<bool> {
+        let showdown = self.showdown.as_mut().wrap_err(Error::NoCardsToShow)?;
+        ensure!(showdown.undecided.remove(&player_id), Error::NoCardsToShow);
+        if show {
+            showdown.shown.insert(player_id);
+        }
+        Ok(show)
+    }
+
+    /// Marks a player as done looking at the results.
+    /// Returns true once every connected player is ready.
+    pub fn mark_ready(&mut self, player_id: Uuid) -> bool {
+        let Some(showdown) = self.showdown.as_mut() else {
+            return false;
+        };
+        if self.players.iter().any(|p| p.id == player_id) {
+            showdown.ready.insert(player_id);
+        }
+        self.players
+            .iter()
+            .filter(|p| p.is_connected)
+            .all(|p| showdown.ready.contains(&p.id))
+    }
+
+    pub fn is_showdown_expired(&self) -> bool {
+        self.showdown
+            .as_ref()
+            .is_some_and(|showdown| Utc::now() >= showdown.deadline)
+    }
+
+    /// Ends the showdown phase and moves on to the next hand
+    pub fn end_showdown_phase(&mut self) -> Result<ServiceRequiredAction> {
+        ensure!(self.showdown.take().is_some(), "Room is not in a showdown phase");
+        self.proceed()
+    }
+
+    /// Starts the turn clock whenever the turn passes to someone new
+    pub fn refresh_turn_deadline(&mut self, turn_time: TimeDelta) {
+        let turn = self.player_in_turn.map(|player| (player, self.action_seq));
+        if turn != self.current_turn {
+            self.current_turn = turn;
+            self.turn_deadline = turn.map(|_| Utc::now() + turn_time);
+        }
+    }
+
+    /// A player whose seat is held has no time to wait for
+    pub fn is_turn_expired(&self) -> bool {
+        let is_held = self
+            .player_in_turn
+            .is_some_and(|id| self.awaiting_reconnect.contains_key(&id));
+        let is_overdue = self
+            .turn_deadline
+            .is_some_and(|deadline| Utc::now() >= deadline);
+        is_held || is_overdue
+    }
+
+    /// What to play for a player who ran out of time: check when possible, otherwise fold
+    pub fn timeout_action(&self, player_id: Uuid) -> Action {
+        let max_bet = self
+            .players
+            .iter()
+            .filter(|p| !p.has_folded)
+            .map(|p| p.bet)
+            .max()
+            .unwrap_or_default();
+        let can_check = self
+            .players
+            .iter()
+            .any(|p| p.id == player_id && p.bet >= max_bet);
+        if can_check {
+            Action::Check
+        } else {
+            Action::Fold
+        }
+    }
+
+    pub fn is_past_closing_time(&self) -> bool {
+        self.closes_at.is_some_and(|closes_at| Utc::now() >= closes_at)
+    }
+
+    /// A room can only be closed between hands, after its deadline has passed
+    pub fn can_close(&self) -> bool {
+        self.is_past_closing_time() && self.stage == Stage::NotEnoughPlayers
+    }
+
+    /// Removes every connected player from the room, returning them with their remaining chips
+    pub fn cash_out_players(&mut self) -> Vec<Player> {
+        let players = self
+            .players
+            .drain(..)
+            .chain(self.player_joining_next_round.drain(..))
+            .chain(self.players_sitting_out.drain(..))
+            .filter(|p| p.is_connected)
+            .collect();
+        self.reset_table();
+        players
+    }
+
+    // seats of players who have left can be reused, since they are removed before the next hand
+    fn taken_seats(&self) -> HashSet<usize> {
+        self.players
+            .iter()
+            .chain(self.player_joining_next_round.iter())
+            .chain(self.players_sitting_out.iter())
+            .filter(|p| p.is_connected)
+            .map(|p| p.seat)
+            .collect()
+    }
+
+    fn free_seat(&self) -> Option<usize> {
+        let taken_seats = self.taken_seats();
+        (0..self.config.max_players).find(|seat| !taken_seats.contains(seat))
+    }
+
+    /// Fails unless the seat is at the table and nobody sits in it
+    pub fn check_seat_free(&self, seat: usize) -> Result<()> {
+        ensure!(
+            seat < self.config.max_players && !self.taken_seats().contains(&seat),
+            Error::SeatUnavailable(seat)
+        );
+        Ok(())
+    }
+
+    fn is_joinable(&self) -> bool {
+        self.player_count() < self.config.max_players
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.players
+            .iter()
+            .chain(self.player_joining_next_round.iter())
+            .chain(self.players_sitting_out.iter())
+            .filter(|p| p.is_connected && p.chips > 0)
+            .count()
+    }
+
+    pub fn start_game(&mut self) -> Result<()> {
+        self.reset_table();
+        // shuffled from a seed the hand can be checked against once it is over
+        let seed = self.rng.gen();
+        self.deck = Deck::from_seed(seed);
+        self.action_log.clear();
+        // later streets of the last hand were never reached
+        self.dark_actions
+            .retain(|_, (street, _)| *street == Stage::PreFlop);
+        self.pre_actions.clear();
+        self.reset_raises();
+        // Reset the bets
+        if let Some(scenario) = &self.scenario {
+            scenario.cards().for_each(|card| self.deck.remove(card));
+        }
+        self.deck_hash = Some(self.deck.fingerprint());
+        self.players.iter_mut().try_for_each(|p| {
+            p.bet = 0;
+            p.has_folded = false;
+            p.fold_reason = None;
+            p.has_taken_turn = false;
+            let scripted = self.scenario.as_ref().and_then(|s| s.hole_cards_for(p.seat));
+            p.hand = match scripted {
+                Some(hand) => Some(hand),
+                None => {
+                    let first = self.deck.draw()?;
+                    let second = self.deck.draw()?;
+                    Some(Hand([first, second]))
+                }
+            };
+            Ok::<(), Report>(())
+        })?;
+        self.starting_chips = self.players.iter().map(|p| (p.id, p.chips)).collect();
+        self.hand_number += 1;
+        // a scripted deck is not the one the seed shuffled
+        if self.scenario.is_none() {
+            self.deal = Some(Deal::new(self.hand_number, seed, self.players.len()));
+        }
+        let now = Utc::now();
+        self.hand_started_at = Some(now);
+        self.street_started_at = Some(now);
+        self.all_in_board = None;
+        self.run_out = None;
+
+        // find the next dealer
+        let dealer_seat = self
+            .players
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by(|a, b| a.1.position.cmp(&b.1.position))
+            .map(|(i, _)| i)
+            .wrap_err("No players")?;
+
+        let dealer = self.players.get(dealer_seat).wrap_err("Dealer not found")?;
+        let next_dealer_seat = match dealer.position {
+            Position::Dealer | Position::DealerAndSmallBlind => {
+                (dealer_seat + 1) % self.players.len()
+            }
+            _ => dealer_seat,
+        };
+        self.readjust_positions(next_dealer_seat)?;
+
+        self.apply_binds()?;
+
+        self.player_in_turn = Some(self.player_to_act_first()?);
+        Ok(())
+    }
+
+    fn apply_binds(&mut self) -> Result<()> {
+        self.players.iter_mut().try_for_each(|p| match p.position {
+            Position::BigBlind => p.bet_amount(BIG_BLIND),
+            Position::SmallBlind | Position::DealerAndSmallBlind => p.bet_amount(SMALL_BLIND),
+            _ => Ok(()),
+        })
+    }
+
+    fn reset_table(&mut self) {
+        self.seat_players();
+
         self.pots = vec![];
         // Reset the community cards
         self.community_cards.clear();
         // Reset the deck
-        self.deck = Deck::new();
+        self.deck = Deck::with_rng(&mut self.rng);
         // reset player turn
         self.player_in_turn = None;
+        // a join or leave may have moved the table on before the results display ended
+        self.showdown = None;
+        self.deal = None;
+        self.revealed_deal = None;
+    }
+
+    fn reset_raises(&mut self) {
+        self.last_raise_size = BIG_BLIND;
+        self.raise_closed_for.clear();
     }
 
     fn seat_players(&mut self) {
+        for player in self
+            .players
+            .iter_mut()
+            .chain(self.player_joining_next_round.iter_mut())
+            .chain(self.players_sitting_out.iter_mut())
+        {
+            player.chips += self.pending_top_ups.remove(&player.id).unwrap_or_default();
+        }
+        self.players.append(&mut self.players_sitting_out);
         // Remove players who left the game or have no chips
         self.players.retain(|p| p.is_connected && p.chips > 0);
         // Add players who joined the game
         self.player_joining_next_round
             .retain(|p| p.is_connected && p.chips > 0);
         self.players.append(&mut self.player_joining_next_round);
+        self.fill_bot_seats();
+        // deal out players sitting out, their old position must not be taken for the dealer's
+        let (mut sitting_out, playing): (Vec<_>, Vec<_>) =
+            self.players.drain(..).partition(|p| p.is_sitting_out);
+        sitting_out.iter_mut().for_each(|p| {
+            // not in the hand, which the rest of the table sees as folded
+            p.hand = None;
+            p.bet = 0;
+            p.has_folded = true;
+            p.has_taken_turn = false;
+            p.last_action = None;
+            p.fold_reason = None;
+            p.position = Position::Normal;
+        });
+        self.players = playing;
+        self.players_sitting_out = sitting_out;
+        // keep the table order fixed, so the dealer button moves around the seats
+        self.players.sort_by_key(|p| p.seat);
+    }
+
+    // replaces bots that went bust, as long as there are free seats
+    fn fill_bot_seats(&mut self) {
+        self.bots
+            .retain(|id| self.players.iter().any(|p| p.id == *id));
+        while self.bots.len() < self.bot_seats {
+            let Some(seat) = self.free_seat() else {
+                break;
+            };
+            let mut bot = Player::new(format!("Bot {}", seat + 1), BOT_BUY_IN);
+            bot.seat = seat;
+            bot.avatar = Avatar::Robot;
+            self.bots.insert(bot.id);
+            self.players.push(bot);
+        }
+    }
+
+    // a hand needs two players and someone to play the bots against
+    fn can_deal(&self) -> bool {
+        self.players.len() >= 2
+            && !self.is_past_closing_time()
+            && self.players.iter().any(|p| !self.is_bot(p.id))
     }
 
     fn player_to_act_first(&self) -> Result<Uuid> {
@@ -384,19 +1728,193 @@ impl Room {
         match stage {
             Stage::Flop => {
                 self.deck.draw()?;
-                self.community_cards.push(self.deck.draw()?);
-                self.community_cards.push(self.deck.draw()?);
-                self.community_cards.push(self.deck.draw()?);
+                for _ in 0..3 {
+                    let card = self.next_board_card()?;
+                    self.community_cards.push(card);
+                }
             }
             Stage::Turn | Stage::River => {
                 self.deck.draw()?;
-                self.community_cards.push(self.deck.draw()?);
+                let card = self.next_board_card()?;
+                self.community_cards.push(card);
             }
             _ => bail!("Invalid stage to deal community card"),
         }
         Ok(())
     }
 
+    fn next_board_card(&mut self) -> Result<Card> {
+        let scripted = self
+            .scenario
+            .as_ref()
+            .and_then(|s| s.board.get(self.community_cards.len()));
+        match scripted {
+            Some(card) => Ok(card.0),
+            None => self.deck.draw(),
+        }
+    }
+
+    pub fn find_winners(&self, evaluator: &Evaluator) -> Result<GameResult> {
+        ensure!(
+            self.stage.is_showdown(),
+            "Game is not in the showdown stage yet"
+        );
+        // in special case where stage is Showdown(false), evaluation is not needed, winner should be the last man standing
+        if matches!(self.stage, Stage::Showdown(false)) {
+            let sole_player = self
+                .players
+                .iter()
+                .find_or_first(|p| !p.has_folded)
+                .wrap_err("No player left in the game")?;
+            let total_pot = self.pots.iter().map(|pot| pot.amount).sum();
+            return Ok(GameResult {
+                hands_eval: Default::default(),
+                winners: vec![(total_pot, HashSet::from([sole_player.id]))],
+            });
+        }
+        let hands_eval = self
+            .players_cards()
+            .into_iter()
+            .map(|(k, v)| Ok((k, evaluator.evaluate(v)?)))
+            .collect::<Result<HashMap<Uuid, Eval>>>()?;
+
+        let mut winners: Vec<(u32, HashSet<Uuid>)> = Vec::with_capacity(self.pots.len());
+        for pot in self.pots.iter().rev() {
+            let player_hands: Vec<_> = pot
+                .players
+                .iter()
+                .map(|player_id| {
+                    (
+                        *player_id,
+                        *hands_eval.get(player_id).unwrap_or(&Eval::WORST),
+                    )
+                })
+                .collect();
+            let best_hands = Self::all_best_hands(&player_hands);
+            winners.push((pot.amount, best_hands));
+        }
+        Ok(GameResult {
+            hands_eval,
+            winners,
+        })
+    }
+
+    /// Chips each player could expect from the pots, by their equity when the money went in.
+    /// Empty unless the hand was all in before the board was complete.
+    pub fn all_in_expected_winnings(
+        &mut self,
+        evaluator: &Evaluator,
+    ) -> Result<HashMap<Uuid, f64>> {
+        let mut expected = HashMap::new();
+        let Some(board_size) = self.all_in_board else {
+            return Ok(expected);
+        };
+        let board = &self.community_cards[..board_size.min(self.community_cards.len())];
+        for pot in self.pots.iter() {
+            let hands: Vec<_> = self
+                .players
+                .iter()
+                .filter(|p| pot.players.contains(&p.id) && !p.has_folded)
+                .filter_map(|p| p.hand.as_ref().map(|Hand(cards)| (p.id, *cards)))
+                .collect();
+            for (player_id, share) in equity(evaluator, &hands, board, &mut self.rng)? {
+                *expected.entry(player_id).or_default() += share * pot.amount as f64;
+            }
+        }
+        Ok(expected)
+    }
+
+    /// Holds back the part of an all-in board dealt after the betting closed, to be shown a
+    /// street at a time. False when the board was out already or is being shown.
+    pub fn start_run_out(&mut self, evaluator: &Evaluator) -> Result<bool> {
+        let Some(shown) = self.all_in_board else {
+            return Ok(false);
+        };
+        if self.run_out.is_some() || shown >= self.community_cards.len() {
+            return Ok(false);
+        }
+        let equity = self.board_equity(evaluator, shown)?;
+        self.run_out = Some(RunOut { shown, equity });
+        Ok(true)
+    }
+
+    /// Shows the next street of an all-in board, returns whether the whole board is out
+    pub fn next_run_out_street(&mut self, evaluator: &Evaluator) -> Result<bool> {
+        let Some(shown) = self.run_out.as_ref().map(|run_out| run_out.shown) else {
+            return Ok(true);
+        };
+        // the flop comes out in one go
+        let next = if shown < 3 { 3 } else { shown + 1 };
+        let shown = next.min(self.community_cards.len());
+        let equity = if shown < self.community_cards.len() {
+            self.board_equity(evaluator, shown)?
+        } else {
+            HashMap::new()
+        };
+        self.run_out = Some(RunOut { shown, equity });
+        Ok(shown == self.community_cards.len())
+    }
+
+    pub fn activity(&self) -> RoomActivity {
+        RoomActivity {
+            stage: self.stage.clone(),
+            average_pot: self.pace.average_pot(),
+            last_hand_at: self.last_hand_at,
+        }
+    }
+
+    /// What the room list should be told, once per change of stage
+    pub fn take_activity_change(&mut self) -> Option<RoomActivity> {
+        if self.listed_stage.as_ref() == Some(&self.stage) {
+            return None;
+        }
+        self.listed_stage = Some(self.stage.clone());
+        Some(self.activity())
+    }
+
+    pub fn is_running_out(&self) -> bool {
+        self.run_out
+            .as_ref()
+            .is_some_and(|run_out| run_out.shown < self.community_cards.len())
+    }
+
+    /// The community cards players may see, all of them unless a board is being run out
+    pub fn visible_board(&self) -> &[Card] {
+        let dealt = self.community_cards.len();
+        let shown = self
+            .run_out
+            .as_ref()
+            .map_or(dealt, |run_out| run_out.shown.min(dealt));
+        &self.community_cards[..shown]
+    }
+
+    // over every hand still in, pots aside
+    fn board_equity(&mut self, evaluator: &Evaluator, shown: usize) -> Result<HashMap<Uuid, f64>> {
+        let hands: Vec<_> = self
+            .players
+            .iter()
+            .filter(|p| !p.has_folded)
+            .filter_map(|p| p.hand.as_ref().map(|Hand(cards)| (p.id, *cards)))
+            .collect();
+        let board = &self.community_cards[..shown];
+        equity(evaluator, &hands, board, &mut self.rng)
+    }
+
+    pub fn all_best_hands(v: &[(Uuid, Eval)]) -> HashSet<Uuid> {
+        let mut largest = HashSet::new();
+        let mut best_hand = Eval::WORST;
+        for (player_id, hand) in v {
+            if hand.is_better_than(best_hand) {
+                largest.clear();
+                largest.insert(*player_id);
+                best_hand = *hand;
+            } else if hand.is_equal_to(best_hand) {
+                largest.insert(*player_id);
+            }
+        }
+        largest
+    }
+
     pub fn players_cards(&self) -> Vec<(Uuid, Box<[Card]>)> {
         self.players
             .iter()
@@ -412,13 +1930,394 @@ impl Room {
             .collect()
     }
 
-    // this function does a few things:
-    // 1. it splits the pot between the winners
-    // 2. it updates the players' chips
+    /// Fills in the five cards each winner made, for winners whose hand was evaluated
+    pub fn show_best_hands(&self, evaluator: &Evaluator, winnings: &mut [Winnings]) -> Result<()> {
+        let players_cards = self.players_cards();
+        for w in winnings.iter_mut() {
+            if let Some((_, cards)) = players_cards.iter().find(|(id, _)| *id == w.player) {
+                w.best_hand = best_five(evaluator, cards)?
+                    .into_iter()
+                    .map(SerdeCard)
+                    .collect();
+            }
+        }
+        Ok(())
+    }
+
+    // this function does a few things:
+    /// How the hand went for every human dealt into it, once the pots are paid out
+    pub fn hand_outcomes(
+        &self,
+        hands_eval: &HashMap<Uuid, Eval>,
+        results: &[Winnings],
+        expected_winnings: &HashMap<Uuid, f64>,
+    ) -> Vec<HandOutcome> {
+        // the rake comes out of what the pots pay, so expectations shrink the same way
+        let expected_total: f64 = expected_winnings.values().sum();
+        let paid: u32 = results.iter().map(|w| w.amount).sum();
+        let scale = if expected_total > 0.0 {
+            paid as f64 / expected_total
+        } else {
+            1.0
+        };
+        self.players
+            .iter()
+            .filter(|p| !self.is_bot(p.id))
+            .filter_map(|p| {
+                let starting_chips = *self.starting_chips.get(&p.id)?;
+                // blinds are not logged, so only calls and raises count as putting money in
+                let vpip = self.action_log.iter().any(|logged| {
+                    logged.player_id == p.id
+                        && logged.stage == Stage::PreFlop
+                        && matches!(
+                            logged.action,
+                            Action::Call | Action::Bet(_) | Action::RaiseTo(_) | Action::AllIn
+                        )
+                });
+                let net_chips = p.chips as i64 - starting_chips as i64;
+                let won: u32 = results
+                    .iter()
+                    .filter(|w| w.player == p.id)
+                    .map(|w| w.amount)
+                    .sum();
+                let all_in_ev = expected_winnings
+                    .get(&p.id)
+                    .map(|expected| net_chips - won as i64 + (expected * scale).round() as i64);
+                Some(HandOutcome {
+                    player_id: p.id,
+                    won: won > 0,
+                    net_chips,
+                    vpip,
+                    showdown: hands_eval.contains_key(&p.id),
+                    all_in_ev,
+                    fold_reason: p.fold_reason.filter(|r| *r != FoldReason::Voluntary),
+                })
+            })
+            .collect()
+    }
+
+    /// What the hand came to for everyone dealt into it, once its pots are paid out
+    pub fn outcome(&self, hands_eval: &HashMap<Uuid, Eval>, winnings: Vec<Winnings>) -> Outcome {
+        let mut dealt_in: Vec<&Player> = self.players.iter().collect();
+        dealt_in.sort_by_key(|p| p.seat);
+        let players = dealt_in
+            .into_iter()
+            .filter_map(|p| {
+                let starting_chips = *self.starting_chips.get(&p.id)?;
+                Some(PlayerResult {
+                    player: p.id,
+                    name: p.name.clone(),
+                    net_chips: p.chips as i64 - starting_chips as i64,
+                    eval: hands_eval.get(&p.id).map(|eval| eval.to_string()),
+                })
+            })
+            .collect();
+        let paid: u32 = winnings.iter().map(|w| w.amount).sum();
+        Outcome {
+            winnings,
+            pot: paid + self.rake_taken,
+            players,
+        }
+    }
+
+    /// How the stacks of the humans dealt into the hand changed, once its pots are paid
+    pub fn settlements(&self) -> Vec<HandSettlement> {
+        self.players
+            .iter()
+            .filter(|p| !self.is_bot(p.id))
+            .filter_map(|p| {
+                let starting_chips = *self.starting_chips.get(&p.id)?;
+                Some(HandSettlement {
+                    player_id: p.id,
+                    delta: p.chips as i64 - starting_chips as i64,
+                    chips_after: p.chips,
+                })
+            })
+            .collect()
+    }
+
+    /// Seats of every human at the table, with chips in an unfinished hand handed back
+    pub fn snapshot(&self) -> RoomSnapshot {
+        let hand_in_progress = self.stage != Stage::NotEnoughPlayers && self.showdown.is_none();
+        let seats = self
+            .players
+            .iter()
+            .chain(self.player_joining_next_round.iter())
+            .chain(self.players_sitting_out.iter())
+            .filter(|p| p.is_connected && !self.is_bot(p.id))
+            .map(|p| {
+                let chips = match self.starting_chips.get(&p.id) {
+                    Some(starting_chips) if hand_in_progress => *starting_chips,
+                    _ => p.chips,
+                };
+                SeatSnapshot {
+                    player_id: p.id,
+                    chips: chips + self.pending_top_up(p.id),
+                }
+            })
+            .collect();
+        RoomSnapshot {
+            room_id: self.id,
+            seats,
+        }
+    }
+
+    /// Everything needed to carry on with the room on another server, the cards sealed to its key
+    pub fn export(&self, key: &HandKey) -> Result<RoomExport> {
+        let hidden = HiddenCards {
+            deck: self.deck.bits(),
+            hands: self
+                .players
+                .iter()
+                .chain(self.player_joining_next_round.iter())
+                .chain(self.players_sitting_out.iter())
+                .filter_map(|p| p.hand.as_ref().map(|Hand(cards)| (p.id, (*cards).into())))
+                .collect(),
+        };
+        Ok(RoomExport {
+            id: self.id,
+            players: self.players.clone(),
+            player_joining_next_round: self.player_joining_next_round.clone(),
+            players_sitting_out: self.players_sitting_out.clone(),
+            community_cards: self
+                .community_cards
+                .iter()
+                .copied()
+                .map(SerdeCard)
+                .collect(),
+            stage: self.stage.clone(),
+            pots: self.pots.clone(),
+            player_in_turn: self.player_in_turn,
+            closes_at: self.closes_at,
+            closing_notice_sent: self.closing_notice_sent,
+            action_log: self.action_log.clone(),
+            action_seq: self.action_seq,
+            turn_deadline: self.turn_deadline,
+            current_turn: self.current_turn,
+            showdown: self.showdown.as_ref().map(|showdown| ShowdownExport {
+                deadline: showdown.deadline,
+                ready: showdown.ready.clone(),
+                shown: showdown.shown.clone(),
+                undecided: showdown.undecided.clone(),
+                evaluated: showdown.hands_eval.keys().copied().collect(),
+            }),
+            last_raise_size: self.last_raise_size,
+            raise_closed_for: self.raise_closed_for.clone(),
+            dark_actions: self.dark_actions.clone(),
+            pre_actions: self.pre_actions.clone(),
+            bot_seats: self.bot_seats,
+            bots: self.bots.clone(),
+            starting_chips: self.starting_chips.clone(),
+            scenario: self.scenario.clone(),
+            rake: self.rake,
+            rake_taken: self.rake_taken,
+            hand_number: self.hand_number,
+            deck_hash: self.deck_hash.clone(),
+            revealed_deal: self.revealed_deal.clone(),
+            hand_started_at: self.hand_started_at,
+            street_started_at: self.street_started_at,
+            pace: self.pace.to_millis(),
+            pace_pots: self.pace.recent_pots.iter().copied().collect(),
+            last_hand_at: self.last_hand_at,
+            all_in_board: self.all_in_board,
+            run_out: self.run_out.clone(),
+            config: self.config,
+            timeout_streaks: self.timeout_streaks.clone(),
+            pending_top_ups: self.pending_top_ups.clone(),
+            waiting_list: self.waiting_list.clone(),
+            cards: SealedCards::seal(&hidden, key)?,
+        })
+    }
+
+    /// Puts an exported room back together, every human at it keeps their seat until they reconnect
+    pub fn import(export: RoomExport, secret: &SecretKey, evaluator: &Evaluator) -> Result<Self> {
+        let mut hidden = export.cards.open(secret)?;
+        let mut room = Room {
+            id: export.id,
+            players: export.players,
+            deck: Deck::from_bits(hidden.deck),
+            rng: StdRng::from_entropy(),
+            community_cards: export.community_cards.into_iter().map(|c| c.0).collect(),
+            stage: export.stage,
+            pots: export.pots,
+            player_joining_next_round: export.player_joining_next_round,
+            players_sitting_out: export.players_sitting_out,
+            player_in_turn: export.player_in_turn,
+            closes_at: export.closes_at,
+            closing_notice_sent: export.closing_notice_sent,
+            action_log: export.action_log,
+            action_seq: export.action_seq,
+            turn_deadline: export.turn_deadline,
+            current_turn: export.current_turn,
+            showdown: None,
+            last_raise_size: export.last_raise_size,
+            raise_closed_for: export.raise_closed_for,
+            dark_actions: export.dark_actions,
+            pre_actions: export.pre_actions,
+            bot_seats: export.bot_seats,
+            bots: export.bots,
+            starting_chips: export.starting_chips,
+            scenario: export.scenario,
+            rake: export.rake,
+            rake_taken: export.rake_taken,
+            hand_number: export.hand_number,
+            deck_hash: export.deck_hash,
+            // the deck draws from a new RNG here, the rest of the hand no longer follows the seed
+            deal: None,
+            revealed_deal: export.revealed_deal,
+            hand_started_at: export.hand_started_at,
+            street_started_at: export.street_started_at,
+            pace: TablePace::from_millis(&export.pace).with_pots(&export.pace_pots),
+            last_hand_at: export.last_hand_at,
+            listed_stage: None,
+            all_in_board: export.all_in_board,
+            run_out: export.run_out,
+            config: export.config,
+            timeout_streaks: export.timeout_streaks,
+            pending_top_ups: export.pending_top_ups,
+            waiting_list: export.waiting_list,
+            awaiting_reconnect: HashMap::new(),
+            pending_log: Vec::new(),
+        };
+        for player in room
+            .players
+            .iter_mut()
+            .chain(room.player_joining_next_round.iter_mut())
+            .chain(room.players_sitting_out.iter_mut())
+        {
+            if let Some(PlayerHand([Some(a), Some(b)])) = hidden.hands.remove(&player.id) {
+                player.hand = Some(Hand([a.0, b.0]));
+            }
+            if player.is_connected && !room.bots.contains(&player.id) {
+                room.awaiting_reconnect
+                    .insert(player.id, Utc::now() + RECLAIM_SEAT_TIME);
+            }
+        }
+        if let Some(showdown) = export.showdown {
+            let hands_eval = room
+                .players_cards()
+                .into_iter()
+                .filter(|(player_id, _)| showdown.evaluated.contains(player_id))
+                .map(|(player_id, cards)| Ok((player_id, evaluator.evaluate(cards)?)))
+                .collect::<Result<HashMap<Uuid, Eval>>>()?;
+            room.showdown = Some(ShowdownPhase {
+                deadline: showdown.deadline,
+                ready: showdown.ready,
+                shown: showdown.shown,
+                undecided: showdown.undecided,
+                hands_eval,
+            });
+        }
+        Ok(room)
+    }
+
+    /// Holds the seat of a player whose connection dropped until `until`, their turns are
+    /// folded meanwhile. Only the connection the player sits with can have its seat held.
+    pub fn hold_seat(&mut self, player_id: Uuid, sid: Sid, until: DateTime<Utc>) -> bool {
+        let is_seated_with = self
+            .seated_player(player_id)
+            .is_some_and(|p| p.is_connected && p.sid == sid && !self.is_bot(p.id));
+        if is_seated_with {
+            self.awaiting_reconnect.insert(player_id, until);
+        }
+        is_seated_with
+    }
+
+    /// Players whose seat was held for longer than they took to come back
+    pub fn expired_seat_holds(&self, now: DateTime<Utc>) -> Vec<Uuid> {
+        self.awaiting_reconnect
+            .iter()
+            .filter(|(_, until)| now >= **until)
+            .map(|(player_id, _)| *player_id)
+            .collect()
+    }
+
+    /// Gives a held seat to its player's new connection
+    pub fn reclaim_seat(&mut self, player_id: Uuid, sid: Sid) -> bool {
+        if self.awaiting_reconnect.remove(&player_id).is_none() {
+            return false;
+        }
+        self.players
+            .iter_mut()
+            .chain(self.player_joining_next_round.iter_mut())
+            .chain(self.players_sitting_out.iter_mut())
+            .filter(|p| p.id == player_id)
+            .for_each(|p| p.sid = sid);
+        true
+    }
+
+    pub fn details(&self, is_private: bool) -> RoomDetails {
+        let seats = self
+            .players
+            .iter()
+            .chain(self.players_sitting_out.iter())
+            .sorted_by_key(|p| p.seat)
+            .map(|p| SeatPreview {
+                seat: p.seat,
+                name: p.name.clone(),
+                chips: p.chips,
+                is_bot: self.is_bot(p.id),
+                is_sitting_out: p.is_sitting_out,
+            })
+            .collect();
+        RoomDetails {
+            room_id: self.id,
+            is_private,
+            small_blind: SMALL_BLIND,
+            big_blind: BIG_BLIND,
+            max_players: self.config.max_players,
+            rake: self.rake,
+            stage: self.stage.clone(),
+            seats,
+            waitlist: self.player_joining_next_round.len(),
+            queued: self.waiting_list.len(),
+            hands_per_hour: self.pace.hands_per_hour(),
+            closes_at: self.closes_at,
+        }
+    }
+
+    // 1. it splits the pot between the winners
+    // 2. it updates the players' chips
     // 3. it returns a nested vector of winnings, where each inner vector represents a pot split
     pub fn split_pot(&mut self, winners: Vec<(u32, HashSet<Uuid>)>) -> Result<Vec<Vec<Winnings>>> {
+        // validate every pot before paying out, so an error never leaves a pot half distributed
+        for (_, winner_ids) in &winners {
+            ensure!(!winner_ids.is_empty(), "Pot has no winners");
+            ensure!(
+                winner_ids
+                    .iter()
+                    .all(|id| self.players.iter().any(|p| p.id == *id)),
+                "Pot winner not found"
+            );
+        }
+        let pot = winners.iter().map(|(amount, _)| amount).sum();
+        // no flop, no drop: pots won before the flop are not raked
+        let mut rake_left = if self.community_cards.is_empty() {
+            0
+        } else {
+            self.rake.cap
+        };
+        self.rake_taken = 0;
+        // chips only one player put in, like a bet nobody called, go back to them unraked
+        let mut uncontested: HashMap<Uuid, u32> = HashMap::new();
+        for pot in self.pots.iter().filter(|pot| pot.players.len() == 1) {
+            for player_id in &pot.players {
+                *uncontested.entry(*player_id).or_default() += pot.amount;
+            }
+        }
         let mut pot_splits = Vec::new();
         for (amount, winner_ids) in winners {
+            let returned = winner_ids
+                .iter()
+                .exactly_one()
+                .ok()
+                .and_then(|player_id| uncontested.remove(player_id))
+                .unwrap_or_default()
+                .min(amount);
+            let rake = self.rake.of(amount - returned).min(rake_left);
+            rake_left -= rake;
+            self.rake_taken += rake;
+            let amount = amount - rake;
             let earnings = amount / winner_ids.len() as u32;
             let mut winnings = Vec::new();
             self.players.iter_mut().for_each(|p| {
@@ -426,6 +2325,7 @@ impl Room {
                     winnings.push(Winnings {
                         player: p.id,
                         amount: earnings,
+                        best_hand: vec![],
                     });
                     p.chips += earnings;
                 }
@@ -442,20 +2342,37 @@ impl Room {
                 .find(|w| w.player == remainder_winner.id) {
                 w.amount += remainder;
             }
+            for w in &winnings {
+                if let Some(player) = self.players.iter().find(|p| p.id == w.player) {
+                    self.pending_log.push(ActionLogEntry::PotAward {
+                        player_name: player.name.clone(),
+                        amount: w.amount,
+                    });
+                }
+            }
             pot_splits.push(winnings);
         }
+        // the hand is over once its pots are paid
+        if let Some(started) = self.hand_started_at.take() {
+            let now = Utc::now();
+            self.pace.record_hand(now - started);
+            self.pace.record_pot(pot);
+            self.last_hand_at = Some(now);
+        }
+        self.street_started_at = None;
         Ok(pot_splits)
     }
 
     pub fn closest_to_dealer(&self, player_ids: &HashSet<Uuid>) -> Result<Uuid> {
-        let dealer_index = self
+        ensure!(!self.players.is_empty(), "No players found");
+        // if the dealer is no longer at the table, start from the first seat
+        let next_to_dealer = self
             .players
             .iter()
             .position(|p| {
                 p.position == Position::Dealer || p.position == Position::DealerAndSmallBlind
             })
-            .wrap_err("Dealer not found")?;
-        let next_to_dealer = (dealer_index + 1) % self.players.len();
+            .map_or(0, |dealer_index| (dealer_index + 1) % self.players.len());
 
         let end_index = next_to_dealer + self.players.len();
         (next_to_dealer..end_index)
@@ -474,7 +2391,7 @@ impl Room {
     /// Check if all non-folded players have the same bet
     pub fn can_proceed_to_next_stage(&self) -> ProceedType {
         match self.stage {
-            Stage::NotEnoughPlayers => if self.players.len() >= 2 {
+            Stage::NotEnoughPlayers => if self.can_deal() {
                 ProceedType::Normal
             } else {
                 ProceedType::NoAction
@@ -512,8 +2429,9 @@ impl Room {
                         ProceedType::NoAction
                     },
                     other_players => {
+                        // an all-in above everyone else's bet still has to be called
                         if other_players.iter().all(|p| p.has_taken_turn)
-                            && other_players.iter().map(|p| p.bet).all_equal() {
+                            && other_players.iter().all(|p| p.bet == self.max_bet()) {
                             ProceedType::Normal
                         } else {
                             ProceedType::NoAction
@@ -528,6 +2446,42 @@ impl Room {
         &mut self,
         player_id: Uuid,
         action: Action,
+    ) -> Result<ServiceRequiredAction> {
+        self.play(player_id, action, false, FoldReason::Voluntary)
+    }
+
+    /// Plays for a player who ran out of time, a player who left or is away folds whatever the bet
+    pub fn take_timeout_action(&mut self, player_id: Uuid) -> Result<ServiceRequiredAction> {
+        let has_left = self.awaiting_reconnect.contains_key(&player_id)
+            || self
+                .players
+                .iter()
+                .any(|p| p.id == player_id && !p.is_connected);
+        if has_left {
+            return self.play(player_id, Action::Fold, false, FoldReason::Disconnect);
+        }
+        let action = self.timeout_action(player_id);
+        self.play(player_id, action, false, FoldReason::Timeout)
+    }
+
+    /// Whether the turn timer folded the player for as many hands in a row as the table allows
+    pub fn should_kick(&self, player_id: Uuid) -> bool {
+        let limit = self.config.kick_after_timeouts;
+        limit > 0
+            && !self.is_bot(player_id)
+            && self
+                .timeout_streaks
+                .get(&player_id)
+                .is_some_and(|streak| *streak >= limit)
+    }
+
+    // `fold_reason` is only kept when the action is a fold
+    fn play(
+        &mut self,
+        player_id: Uuid,
+        action: Action,
+        is_dark: bool,
+        fold_reason: FoldReason,
     ) -> Result<ServiceRequiredAction> {
         ensure!(self.player_in_turn == Some(player_id), "Not player's turn");
         let max_bet = self
@@ -537,33 +2491,97 @@ impl Room {
             .map(|p| p.bet)
             .max()
             .unwrap_or_default();
+        let raise_range = self
+            .players
+            .iter()
+            .find(|p| p.id == player_id)
+            .and_then(|player| self.raise_to_range(player));
         let player = self
             .players
             .iter_mut()
             .find(|p| p.id == player_id)
             .wrap_err("Player not found")?;
-        player.last_action = Some(action);
-        match action {
-            Action::Fold => player.has_folded = true,
-            Action::Check => {
-                if player.bet < max_bet {
-                    bail!("Player must call or raise");
+        // raising the whole stack is an all-in, keep a single action for it
+        let action = normalize_raise(action, player.bet, player.chips, max_bet)?;
+        let raises = match action {
+            Action::Bet(_) | Action::RaiseTo(_) => true,
+            Action::AllIn => player.chips + player.bet > max_bet,
+            _ => false,
+        };
+        ensure!(
+            !raises || !self.raise_closed_for.contains(&player_id),
+            Error::RaiseNotReopened
+        );
+        if raises {
+            let Some((min_total, max_total)) = raise_range.map(|r| r.into_inner()) else {
+                bail!(Error::RaisingCapped);
+            };
+            match action {
+                Action::Bet(total) | Action::RaiseTo(total) => {
+                    ensure!(total >= min_total, Error::RaiseBelowMinimum(min_total));
+                    ensure!(total <= max_total, Error::RaiseAboveMaximum(max_total));
                 }
+                // short of the smallest raise is fine, over the largest is not
+                _ => ensure!(
+                    player.chips + player.bet <= max_total,
+                    Error::RaiseAboveMaximum(max_total)
+                ),
             }
-            Action::Call => {
-                let call_amount = max_bet - player.bet;
-                player.bet_amount(call_amount)?;
+        }
+        // checked in full before anything on the table changes, a refused action leaves no trace
+        let amount = match action {
+            Action::Fold => 0,
+            Action::Check => {
+                ensure!(player.bet >= max_bet, "Player must call or raise");
+                0
             }
-            Action::Raise(amount) => {
-                ensure!(amount + player.bet >= max_bet, "Invalid raise amount");
-                player.bet_amount(amount)?;
+            Action::Call => max_bet - player.bet,
+            Action::Bet(total) | Action::RaiseTo(total) => total - player.bet,
+            Action::Raise(_) => unreachable!("Raise is turned into RaiseTo before it is played"),
+            Action::AllIn => player.chips,
+        };
+        ensure!(
+            amount <= player.chips,
+            "{} does not have enough chips",
+            player.name
+        );
+        // acting takes the place of any pre-action
+        self.pre_actions.remove(&player_id);
+        // a player who left is not counted, their seat is given up once the grace period ends
+        match (fold_reason, action) {
+            (FoldReason::Timeout, Action::Fold) => {
+                *self.timeout_streaks.entry(player_id).or_default() += 1;
             }
-            Action::AllIn => {
-                let all_in_amount = player.chips;
-                player.bet_amount(all_in_amount)?;
+            (FoldReason::Voluntary, _) => {
+                self.timeout_streaks.remove(&player_id);
             }
-        };
+            _ => {}
+        }
+        let fold_reason = (action == Action::Fold).then_some(fold_reason);
+        player.last_action = Some(action);
+        player.fold_reason = fold_reason;
+        if action == Action::Fold {
+            player.has_folded = true;
+        } else {
+            player.bet_amount(amount)?;
+        }
         player.has_taken_turn = true;
+        let raise_size = player.bet.saturating_sub(max_bet);
+        let player_name = player.name.clone();
+        self.log_action(player_id, player_name, action, is_dark, fold_reason);
+        if raise_size >= self.last_raise_size {
+            // a full raise reopens the betting for everyone
+            self.last_raise_size = raise_size;
+            self.raise_closed_for.clear();
+        } else if raise_size > 0 {
+            // a short all-in, players who already matched the last full raise may not re-raise
+            let closed = self
+                .players
+                .iter()
+                .filter(|p| p.id != player_id && p.has_taken_turn && p.bet == max_bet)
+                .map(|p| p.id);
+            self.raise_closed_for.extend(closed);
+        }
         self.proceed()
     }
 
@@ -611,6 +2629,9 @@ impl Room {
                 self.player_in_turn = Some(self.player_to_act_first()?);
             }
             Stage::Showdown(true) => {
+                if self.community_cards.len() < 5 {
+                    self.all_in_board = Some(self.community_cards.len());
+                }
                 match self.community_cards.len() {
                     0 => {
                         self.deal_community_card(Stage::Flop)?;
@@ -681,6 +2702,10 @@ impl Room {
                     p.bet = 0;
                     p.last_action = None;
                 });
+                self.reset_raises();
+                let ended = self.stage.clone();
+                self.dark_actions.retain(|_, (street, _)| *street != ended);
+                self.pre_actions.clear();
             }
         }
         Ok(())
@@ -688,6 +2713,7 @@ impl Room {
 
     fn proceed_to_next_stage(&mut self, proceed_type: ProceedType) -> Result<()> {
         self.end_stage()?;
+        self.street_started_at = Some(Utc::now());
         let showdown = match proceed_type {
             ProceedType::NoAction => unreachable!("Impossible to reach this state"),
             ProceedType::Normal => None,
@@ -696,12 +2722,13 @@ impl Room {
         };
         if let Some(showdown_stage) = showdown {
             self.stage = showdown_stage;
+            self.log_street();
             return Ok(());
         }
         match self.stage {
             Stage::Showdown(_) => {
                 self.seat_players();
-                if self.players.len() >= 2 {
+                if self.can_deal() {
                     self.stage = Stage::PreFlop
                 } else {
                     self.stage = Stage::NotEnoughPlayers
@@ -719,19 +2746,38 @@ impl Room {
             Stage::Turn => self.stage = Stage::River,
             Stage::River => self.stage = Stage::Showdown(true),
         }
+        self.log_street();
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use eyre::Result;
-    use poker::cards;
+    use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+    use chrono::{TimeDelta, Utc};
+    use eyre::{ContextCompat, Report, Result};
+    use itertools::Itertools;
+    use poker::{card, cards, Card, Evaluator, Rank};
+    use proptest::prelude::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use socketioxide::socket::Sid;
     use uuid::Uuid;
 
+    use crate::crypto::HandKey;
     use crate::deck::Deck;
-    use crate::domain::{Action, ServiceRequiredAction};
-    use crate::room::{Hand, Player, Position, Room, Stage};
+    use crate::domain::{Action, DarkAction, FoldReason, PreAction, ServiceRequiredAction};
+    use crate::error::Error;
+    use crate::room::{
+        ActionLogEntry, Hand, HandSettlement, Player, Position, Pot, Rake, Room, RoomConfig,
+        RoomExport, Scenario, ShowdownPhase, Stage, TablePace, Winnings, PACE_SAMPLE_SIZE,
+    };
+    use crate::history::HandHistory;
+    use crate::rules::{
+        BettingStructure, LegalAction, RaiseLimits, BIG_BLIND, DEFAULT_MAX_PLAYERS, MAX_BUY_IN,
+    };
+    use crate::state::{HandState, SerdeCard, SharedGameState};
 
     #[test]
     fn test_take_action() -> Result<()> {
@@ -742,6 +2788,7 @@ mod tests {
                 Player {
                     id: curr_player,
                     name: "yewjung".to_string(),
+                    avatar: Default::default(),
                     hand: Some(Hand(cards!(
                         Ace, Clubs;
                         Nine, Diamonds;
@@ -754,10 +2801,14 @@ mod tests {
                     sid: Default::default(),
                     is_connected: true,
                     last_action: None,
+                    seat: 0,
+                    is_sitting_out: false,
+                    fold_reason: None,
                 },
                 Player {
                     id: Uuid::new_v4(),
                     name: "yewjung2".to_string(),
+                    avatar: Default::default(),
                     hand: Some(Hand(cards!(
                         Four, Clubs;
                         Ace, Diamonds;
@@ -770,18 +2821,1319 @@ mod tests {
                     sid: Default::default(),
                     is_connected: true,
                     last_action: None,
+                    seat: 1,
+                    is_sitting_out: false,
+                    fold_reason: None,
                 },
             ],
             deck: Deck::new(),
+            rng: StdRng::from_entropy(),
             community_cards: vec![],
             stage: Stage::PreFlop,
             pots: vec![],
             player_joining_next_round: vec![],
+            players_sitting_out: vec![],
             player_in_turn: Some(curr_player),
+            closes_at: None,
+            closing_notice_sent: false,
+            action_log: Vec::new(),
+            action_seq: 0,
+            turn_deadline: None,
+            current_turn: None,
+            showdown: None,
+            last_raise_size: BIG_BLIND,
+            raise_closed_for: HashSet::new(),
+            dark_actions: HashMap::new(),
+            pre_actions: HashMap::new(),
+            bot_seats: 0,
+            bots: HashSet::new(),
+            starting_chips: HashMap::new(),
+            scenario: None,
+            rake: Rake::default(),
+            rake_taken: 0,
+            hand_number: 0,
+            deck_hash: None,
+            deal: None,
+            revealed_deal: None,
+            hand_started_at: None,
+            street_started_at: None,
+            pace: TablePace::default(),
+            last_hand_at: None,
+            listed_stage: None,
+            all_in_board: None,
+            run_out: None,
+            config: RoomConfig::default(),
+            timeout_streaks: HashMap::new(),
+            pending_top_ups: HashMap::new(),
+            waiting_list: VecDeque::new(),
+            awaiting_reconnect: HashMap::new(),
+            pending_log: Vec::new(),
         };
 
         let service_action = room.take_action(curr_player, Action::Fold)?;
         assert_eq!(service_action, ServiceRequiredAction::FindWinners);
         Ok(())
     }
+
+    // heads-up pre-flop with the small blind (99 chips behind) to act
+    fn heads_up_room() -> Room {
+        let mut room = room_with_table(&[99, 98], Some(0));
+        room.stage = Stage::PreFlop;
+        room.players[0].bet = 1;
+        room.players[1].bet = 2;
+        room.player_in_turn = Some(room.players[0].id);
+        room
+    }
+
+    #[test]
+    fn actions_and_pot_awards_should_be_queued_for_the_action_log() -> Result<()> {
+        let mut room = heads_up_room();
+        let [first, second] = [0, 1].map(|i| room.players[i].id);
+        room.take_action(first, Action::Fold)?;
+        room.split_pot(vec![(3, HashSet::from([second]))])?;
+        assert!(matches!(
+            room.pending_log.first(),
+            Some(ActionLogEntry::Action(logged)) if logged.player_id == first
+        ));
+        let award = ActionLogEntry::PotAward {
+            player_name: room.players[1].name.clone(),
+            amount: 3,
+        };
+        assert_eq!(room.pending_log.last(), Some(&award));
+        Ok(())
+    }
+
+    #[test]
+    fn raise_of_full_stack_should_become_all_in() -> Result<()> {
+        let mut room = heads_up_room();
+        let player_id = room.players[0].id;
+        room.take_action(player_id, Action::Raise(99))?;
+        assert_eq!(room.players[0].last_action, Some(Action::AllIn));
+        assert_eq!(room.players[0].chips, 0);
+        assert_eq!(room.players[0].bet, 100);
+        Ok(())
+    }
+
+    #[test]
+    fn raise_below_stack_should_stay_a_raise() -> Result<()> {
+        let mut room = heads_up_room();
+        let player_id = room.players[0].id;
+        room.take_action(player_id, Action::RaiseTo(99))?;
+        assert_eq!(room.players[0].last_action, Some(Action::RaiseTo(99)));
+        assert_eq!(room.players[0].chips, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn deprecated_raise_by_should_be_played_as_raise_to() -> Result<()> {
+        let mut room = heads_up_room();
+        let player_id = room.players[0].id;
+        // the small blind already has 1 in, adding 5 makes it 6
+        room.take_action(player_id, Action::Raise(5))?;
+        assert_eq!(room.players[0].last_action, Some(Action::RaiseTo(6)));
+        assert_eq!(room.players[0].bet, 6);
+        assert_eq!(room.action_log[0].action, Action::RaiseTo(6));
+        Ok(())
+    }
+
+    #[test]
+    fn rooms_with_the_same_seed_should_deal_the_same_cards() -> Result<()> {
+        let deal = |seed| {
+            let mut room = room_with_table(&[100, 100, 100], Some(0));
+            room.reseed(seed);
+            room.stage = Stage::PreFlop;
+            room.start_game()?;
+            room.deal_community_card(Stage::Flop)?;
+            let hands: Vec<_> = room.players.iter().map(|p| p.hand.clone()).collect();
+            Ok::<_, Report>((hands, room.community_cards))
+        };
+        assert_eq!(deal(7)?, deal(7)?);
+        Ok(())
+    }
+
+    #[test]
+    fn player_joining_mid_hand_should_wait_for_the_next_hand() -> Result<()> {
+        let mut room = room_with_table(&[100, 100], Some(0));
+        room.stage = Stage::PreFlop;
+        room.pace.record_hand(TimeDelta::seconds(60));
+        room.hand_started_at = Some(Utc::now() - TimeDelta::seconds(10));
+        let seated = room.seating(room.players[0].id).wrap_err("Not seated")?;
+        assert!(!seated.dealt_in_next_hand);
+
+        let joining = Player::new("Late".to_string(), 100);
+        let joining_id = joining.id;
+        room.join_player(joining)?;
+        let seated = room.seating(joining_id).wrap_err("Not seated")?;
+        assert!(seated.dealt_in_next_hand);
+        assert_eq!(seated.seat, 2);
+        assert!(matches!(seated.estimated_wait, Some(49..=50)));
+        Ok(())
+    }
+
+    #[test]
+    fn winners_should_be_shown_the_five_cards_they_won_with() -> Result<()> {
+        let mut room = room_with_table(&[100, 100], Some(0));
+        room.players[0].hand = Some(Hand([card!("As")?, card!("Ah")?]));
+        room.players[1].hand = Some(Hand([card!("Kd")?, card!("Qd")?]));
+        room.community_cards = cards!("Ad 7c 2s 3h 9d").try_collect()?;
+        let mut winnings = vec![Winnings {
+            player: room.players[0].id,
+            amount: 10,
+            best_hand: vec![],
+        }];
+        room.show_best_hands(&Evaluator::new(), &mut winnings)?;
+        let best_hand: Vec<Card> = winnings[0].best_hand.iter().map(|c| c.0).collect();
+        assert_eq!(best_hand.len(), 5);
+        let aces = best_hand.iter().filter(|c| c.rank() == Rank::Ace);
+        assert_eq!(aces.count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn sitting_out_player_should_keep_seat_but_be_dealt_out() -> Result<()> {
+        let mut room = room_with_table(&[100, 100, 100], Some(0));
+        let [dealer, first, second] = [0, 1, 2].map(|i| room.players[i].id);
+        room.stage = Stage::PreFlop;
+        room.sit_out(dealer)?;
+        room.start_game()?;
+
+        assert_eq!(room.players.iter().map(|p| p.id).collect::<Vec<_>>(), vec![first, second]);
+        assert!(room.players.iter().all(|p| p.hand.is_some()));
+        assert_eq!(room.players_sitting_out.len(), 1);
+        assert_eq!(room.players_sitting_out[0].chips, 100);
+        assert_eq!(room.players_sitting_out[0].hand, None);
+        assert_eq!(room.player_count(), 3);
+        assert_eq!(room.free_seat(), Some(3));
+        let state = SharedGameState::from_room(room.clone(), false);
+        assert!(state.players.iter().any(|p| p.id == dealer && p.is_sitting_out));
+
+        // back in from the next hand
+        room.sit_in(dealer)?;
+        assert_eq!(room.players.len(), 2);
+        room.start_game()?;
+        assert_eq!(room.players.len(), 3);
+        assert!(room.players_sitting_out.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn scenario_should_deal_scripted_cards_and_random_ones_elsewhere() -> Result<()> {
+        let mut room = room_with_table(&[100, 100, 100], Some(0));
+        room.stage = Stage::PreFlop;
+        room.scenario = Some(Scenario {
+            hole_cards: BTreeMap::from([(1, [SerdeCard(card!("As")?), SerdeCard(card!("Ah")?)])]),
+            board: vec![SerdeCard(card!("Ad")?), SerdeCard(card!("2c")?)],
+        });
+        room.start_game()?;
+        let scripted = room.players.iter().find(|p| p.seat == 1).unwrap();
+        assert_eq!(scripted.hand, Some(Hand([card!("As")?, card!("Ah")?])));
+        // a scripted deck is not the one the seed shuffled
+        assert_eq!(room.deal, None);
+
+        room.deal_community_card(Stage::Flop)?;
+        assert_eq!(room.community_cards[..2], [card!("Ad")?, card!("2c")?]);
+        // the third flop card is not scripted, and never one already dealt by the scenario
+        let dealt: Vec<Card> = room
+            .players
+            .iter()
+            .filter_map(|p| p.hand.as_ref())
+            .flat_map(|hand| hand.0)
+            .chain(room.community_cards.iter().copied())
+            .collect();
+        assert!(dealt.iter().all_unique());
+        Ok(())
+    }
+
+    #[test]
+    fn deal_should_be_committed_to_until_the_hand_is_over() -> Result<()> {
+        let mut room = room_with_table(&[100, 100, 100], Some(0));
+        room.stage = Stage::PreFlop;
+        room.start_game()?;
+        let commitment = room.deal.as_ref().map(|deal| deal.commitment.clone());
+        assert!(commitment.is_some());
+        assert_eq!(room.revealed_deal, None);
+        for stage in [Stage::Flop, Stage::Turn, Stage::River] {
+            room.deal_community_card(stage)?;
+        }
+
+        room.start_showdown_phase(TimeDelta::seconds(5), HashMap::new(), HashSet::new());
+        let deal = room.revealed_deal.clone().wrap_err("Deal not revealed")?;
+        assert_eq!(Some(deal.commitment.clone()), commitment);
+        assert_eq!(room.deal, None);
+        let hands: Vec<[Card; 2]> = room
+            .players
+            .iter()
+            .filter_map(|p| p.hand.as_ref().map(|hand| hand.0))
+            .collect();
+        deal.verify(&hands, &room.community_cards)
+    }
+
+    #[test]
+    fn scenario_with_a_repeated_card_should_be_rejected() -> Result<()> {
+        let scenario = Scenario {
+            hole_cards: BTreeMap::from([(0, [SerdeCard(card!("As")?), SerdeCard(card!("Kd")?)])]),
+            board: vec![SerdeCard(card!("As")?)],
+        };
+        assert!(matches!(
+            scenario.validate(DEFAULT_MAX_PLAYERS).map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::InvalidScenario))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn rake_should_be_taken_from_pots_up_to_the_cap() -> Result<()> {
+        let mut room = room_with_table(&[0, 0, 0], Some(0));
+        let [winner, other, bettor] = [0, 1, 2].map(|i| room.players[i].id);
+        room.rake = Rake { percent: 5, cap: 6 };
+        room.community_cards = cards!("2c 7d Kh").try_collect()?;
+        room.pots = vec![
+            Pot {
+                amount: 100,
+                players: HashSet::from([winner, other, bettor]),
+            },
+            Pot {
+                amount: 40,
+                players: HashSet::from([other, bettor]),
+            },
+            // the part of the last bet nobody called
+            Pot {
+                amount: 30,
+                players: HashSet::from([bettor]),
+            },
+        ];
+        let pot_splits = room.split_pot(vec![
+            (30, HashSet::from([bettor])),
+            (40, HashSet::from([other])),
+            (100, HashSet::from([winner])),
+        ])?;
+        // nothing from the uncalled bet, 2 from the side pot and the main pot only has 4 left
+        assert_eq!(room.rake_taken, 6);
+        assert_eq!(pot_splits[0][0].amount, 30);
+        assert_eq!(pot_splits[1][0].amount, 38);
+        assert_eq!(pot_splits[2][0].amount, 96);
+        assert_eq!(room.players.iter().map(|p| p.chips).sum::<u32>(), 164);
+
+        // no flop, no drop
+        room.community_cards.clear();
+        room.split_pot(vec![(100, HashSet::from([winner]))])?;
+        assert_eq!(room.rake_taken, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn uncalled_bet_should_not_be_raked_when_everyone_folds() -> Result<()> {
+        let mut room = room_with_table(&[0, 0], Some(0));
+        let [winner, folded] = [0, 1].map(|i| room.players[i].id);
+        room.rake = Rake { percent: 5, cap: 10 };
+        room.community_cards = cards!("2c 7d Kh").try_collect()?;
+        room.pots = vec![
+            Pot {
+                amount: 60,
+                players: HashSet::from([winner, folded]),
+            },
+            Pot {
+                amount: 20,
+                players: HashSet::from([winner]),
+            },
+        ];
+        // with no showdown the winner takes every pot at once
+        let pot_splits = room.split_pot(vec![(80, HashSet::from([winner]))])?;
+        assert_eq!(room.rake_taken, 3);
+        assert_eq!(pot_splits[0][0].amount, 77);
+        Ok(())
+    }
+
+    #[test]
+    fn pace_should_average_the_latest_hands() {
+        let mut pace = TablePace::default();
+        assert_eq!(pace.hands_per_hour(), None);
+        pace.record_hand(TimeDelta::seconds(60));
+        pace.record_hand(TimeDelta::seconds(100));
+        assert_eq!(pace.average_hand(), Some(TimeDelta::seconds(80)));
+        assert_eq!(pace.hands_per_hour(), Some(45));
+
+        // only the latest hands count
+        (0..PACE_SAMPLE_SIZE).for_each(|_| pace.record_hand(TimeDelta::seconds(30)));
+        assert_eq!(pace.hands_per_hour(), Some(120));
+
+        assert_eq!(pace.average_pot(), None);
+        pace.record_pot(10);
+        pace.record_pot(25);
+        assert_eq!(pace.average_pot(), Some(17));
+    }
+
+    #[test]
+    fn activity_should_be_listed_once_per_stage() {
+        let mut room = Room::new();
+        assert!(room.take_activity_change().is_some());
+        assert!(room.take_activity_change().is_none());
+        room.stage = Stage::PreFlop;
+        let activity = room.take_activity_change();
+        assert_eq!(activity.map(|a| a.stage), Some(Stage::PreFlop));
+    }
+
+    #[test]
+    fn raise_smaller_than_the_last_raise_should_be_rejected() -> Result<()> {
+        let mut room = heads_up_room();
+        let player_id = room.players[0].id;
+        // re-raising the big blind by a single chip
+        let result = room.take_action(player_id, Action::RaiseTo(3));
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::RaiseBelowMinimum(4)))
+        ));
+        assert_eq!(room.players[0].last_action, None);
+
+        room.take_action(player_id, Action::RaiseTo(10))?;
+        assert_eq!(room.last_raise_size, 8);
+        let big_blind = room.players[1].id;
+        let result = room.take_action(big_blind, Action::RaiseTo(12));
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::RaiseBelowMinimum(18)))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn pot_limit_raises_should_stop_at_the_pot() -> Result<()> {
+        let mut room = heads_up_room();
+        room.config.betting = BettingStructure::PotLimit;
+        let [first, second] = [0, 1].map(|i| room.players[i].id);
+        // calling 1 makes the pot 4, on top of the 2 to match
+        assert_eq!(
+            room.raise_limits(first),
+            Some(RaiseLimits { min: 4, max: 6 })
+        );
+        let result = room.take_action(first, Action::RaiseTo(7));
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::RaiseAboveMaximum(6)))
+        ));
+        room.take_action(first, Action::RaiseTo(6))?;
+        let result = room.take_action(second, Action::AllIn);
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::RaiseAboveMaximum(18)))
+        ));
+        room.take_action(second, Action::RaiseTo(18))?;
+        Ok(())
+    }
+
+    #[test]
+    fn fixed_limit_raises_should_be_one_bet_and_capped() -> Result<()> {
+        let mut room = heads_up_room();
+        room.config.betting = BettingStructure::FixedLimit;
+        let [first, second] = [0, 1].map(|i| room.players[i].id);
+        assert_eq!(
+            room.raise_limits(first),
+            Some(RaiseLimits { min: 4, max: 4 })
+        );
+        let result = room.take_action(first, Action::RaiseTo(6));
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::RaiseAboveMaximum(4)))
+        ));
+        room.take_action(first, Action::RaiseTo(4))?;
+        room.take_action(second, Action::RaiseTo(6))?;
+        room.take_action(first, Action::RaiseTo(8))?;
+        // the big blind and three raises make four bets
+        assert_eq!(room.raise_limits(second), None);
+        let result = room.take_action(second, Action::RaiseTo(10));
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::RaisingCapped))
+        ));
+        room.take_action(second, Action::Call)?;
+        Ok(())
+    }
+
+    #[test]
+    fn legal_actions_should_follow_the_betting() -> Result<()> {
+        let mut room = heads_up_room();
+        let [first, second] = [0, 1].map(|i| room.players[i].id);
+        assert_eq!(
+            room.legal_actions(first),
+            vec![
+                LegalAction::Fold,
+                LegalAction::Call { amount: 1 },
+                LegalAction::Raise(RaiseLimits { min: 4, max: 100 }),
+                LegalAction::AllIn { amount: 99 },
+            ]
+        );
+        assert!(room.legal_actions(second).is_empty());
+
+        // once fixed limit betting is capped, going all-in would raise too
+        room.config.betting = BettingStructure::FixedLimit;
+        room.take_action(first, Action::RaiseTo(4))?;
+        room.take_action(second, Action::RaiseTo(6))?;
+        room.take_action(first, Action::RaiseTo(8))?;
+        assert_eq!(
+            room.legal_actions(second),
+            vec![LegalAction::Fold, LegalAction::Call { amount: 2 }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn short_all_in_should_not_reopen_betting() -> Result<()> {
+        let mut room = room_with_table(&[100, 100, 15], Some(0));
+        room.stage = Stage::Flop;
+        let [first, second, short] = [0, 1, 2].map(|i| room.players[i].id);
+        room.player_in_turn = Some(first);
+        room.take_action(first, Action::Raise(10))?;
+        room.take_action(second, Action::Call)?;
+        room.take_action(short, Action::AllIn)?;
+        assert_eq!(room.last_raise_size, 10);
+
+        assert_eq!(room.player_in_turn, Some(first));
+        let result = room.take_action(first, Action::Raise(30));
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::RaiseNotReopened))
+        ));
+        let result = room.take_action(first, Action::AllIn);
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::RaiseNotReopened))
+        ));
+        room.take_action(first, Action::Call)?;
+        assert_eq!(room.player_in_turn, Some(second));
+        room.take_action(second, Action::Call)?;
+        assert_eq!(room.stage, Stage::Turn);
+        assert!(room.raise_closed_for.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn dark_actions_should_be_played_when_the_action_reaches_the_player() -> Result<()> {
+        let mut room = room_with_table(&[100, 100, 100], Some(0));
+        let [dealer, first, second] = [0, 1, 2].map(|i| room.players[i].id);
+        room.stage = Stage::Flop;
+        room.pots = vec![Pot {
+            amount: 30,
+            players: HashSet::from([dealer, first, second]),
+        }];
+        room.player_in_turn = Some(first);
+        room.declare_dark_action(second, DarkAction::BetPot)?;
+        room.declare_dark_action(dealer, DarkAction::Check)?;
+        for player_id in [first, second, dealer] {
+            room.take_action(player_id, Action::Check)?;
+        }
+        assert_eq!(room.stage, Stage::Turn);
+
+        room.take_action(first, Action::Check)?;
+        room.play_dark_actions(ServiceRequiredAction::NoAction)?;
+        let last = room.action_log.last().unwrap();
+        assert_eq!((last.player_id, last.action, last.is_dark), (second, Action::Bet(30), true));
+        // the dark check no longer stands against a bet, the dealer acts as usual
+        assert_eq!(room.player_in_turn, Some(dealer));
+        assert!(room.dark_actions.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn pre_actions_should_be_played_when_the_turn_comes_while_still_legal() -> Result<()> {
+        let mut room = room_with_table(&[100, 100, 100], Some(0));
+        let [dealer, first, second] = [0, 1, 2].map(|i| room.players[i].id);
+        room.stage = Stage::Flop;
+        room.player_in_turn = Some(first);
+        let result = room.set_pre_action(first, Some(PreAction::CheckFold));
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::PreActionUnavailable))
+        ));
+        room.set_pre_action(second, Some(PreAction::Call(0)))?;
+        room.set_pre_action(dealer, Some(PreAction::CheckFold))?;
+
+        // the call was set before the bet, so it is dropped and the player acts as usual
+        room.take_action(first, Action::Bet(10))?;
+        room.play_dark_actions(ServiceRequiredAction::NoAction)?;
+        assert_eq!(room.player_in_turn, Some(second));
+        assert!(!room.pre_actions.contains_key(&second));
+
+        room.take_action(second, Action::Call)?;
+        room.play_dark_actions(ServiceRequiredAction::NoAction)?;
+        let last = room.action_log.last().unwrap();
+        assert_eq!(
+            (last.player_id, last.action, last.is_dark),
+            (dealer, Action::Fold, false)
+        );
+        assert_eq!(room.stage, Stage::Turn);
+        assert!(room.pre_actions.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn refused_action_should_leave_the_table_as_it_was() -> Result<()> {
+        let mut room = room_with_table(&[100, 100, 100], Some(0));
+        let [_, first, second] = [0, 1, 2].map(|i| room.players[i].id);
+        room.stage = Stage::Flop;
+        room.player_in_turn = Some(first);
+        room.take_action(first, Action::Bet(10))?;
+        // left over from before the bet, acting would take its place
+        room.pre_actions.insert(second, PreAction::CheckFold);
+        room.timeout_streaks.insert(second, 1);
+        let before = room.players[2].clone();
+
+        assert!(room.take_action(second, Action::Check).is_err());
+        assert!(room.take_action(second, Action::RaiseTo(1000)).is_err());
+        assert_eq!(room.player_in_turn, Some(second));
+        assert!(room.pre_actions.contains_key(&second));
+        assert_eq!(room.timeout_streaks.get(&second), Some(&1));
+        let after = &room.players[2];
+        assert_eq!(
+            (after.last_action, after.fold_reason, after.bet, after.chips),
+            (before.last_action, before.fold_reason, before.bet, before.chips)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn dark_action_should_be_rejected_on_the_river_or_when_out_of_the_hand() -> Result<()> {
+        let mut room = room_with_table(&[100, 100, 100], Some(0));
+        let [dealer, first, _] = [0, 1, 2].map(|i| room.players[i].id);
+        room.stage = Stage::Flop;
+        room.players[0].has_folded = true;
+        let result = room.declare_dark_action(dealer, DarkAction::Check);
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::DarkActionUnavailable))
+        ));
+        room.stage = Stage::River;
+        let result = room.declare_dark_action(first, DarkAction::Check);
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::DarkActionUnavailable))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn hand_outcomes_should_track_net_chips_and_voluntary_calls() -> Result<()> {
+        let mut room = heads_up_room();
+        let [small_blind, big_blind] = [0, 1].map(|i| room.players[i].id);
+        room.starting_chips = HashMap::from([(small_blind, 100), (big_blind, 100)]);
+        room.take_action(small_blind, Action::Call)?;
+        // the big blind takes the pot of 4 without a showdown
+        room.players[1].chips += 4;
+        let results = vec![Winnings {
+            player: big_blind,
+            amount: 4,
+            best_hand: vec![],
+        }];
+        let outcomes = room.hand_outcomes(&HashMap::new(), &results, &HashMap::new());
+        let small_blind_outcome = outcomes.iter().find(|o| o.player_id == small_blind).unwrap();
+        assert!(!small_blind_outcome.won);
+        assert_eq!(small_blind_outcome.net_chips, -2);
+        assert!(small_blind_outcome.vpip);
+        let big_blind_outcome = outcomes.iter().find(|o| o.player_id == big_blind).unwrap();
+        assert!(big_blind_outcome.won);
+        assert_eq!(big_blind_outcome.net_chips, 2);
+        assert!(!big_blind_outcome.vpip);
+        assert!(!big_blind_outcome.showdown);
+        Ok(())
+    }
+
+    #[test]
+    fn outcome_should_carry_the_net_chips_of_everyone_dealt_in() {
+        let mut room = heads_up_room();
+        let [small_blind, big_blind] = [0, 1].map(|i| room.players[i].id);
+        room.starting_chips = HashMap::from([(small_blind, 100), (big_blind, 100)]);
+        room.players[0].chips = 90;
+        room.players[1].chips = 109;
+        room.rake_taken = 1;
+        let winnings = vec![Winnings {
+            player: big_blind,
+            amount: 19,
+            best_hand: vec![],
+        }];
+        let outcome = room.outcome(&HashMap::new(), winnings);
+        assert_eq!(outcome.pot, 20);
+        assert!(outcome.is_winner(big_blind));
+        assert!(!outcome.is_winner(small_blind));
+        let net_chips: Vec<_> = outcome
+            .players
+            .iter()
+            .map(|result| (result.player, result.net_chips))
+            .collect();
+        assert_eq!(net_chips, vec![(small_blind, -10), (big_blind, 9)]);
+        assert!(outcome.players.iter().all(|result| result.eval.is_none()));
+    }
+
+    #[test]
+    fn held_seat_should_fold_its_turns_until_reclaimed() -> Result<()> {
+        let mut room = heads_up_room();
+        let [small_blind, big_blind] = [0, 1].map(|i| room.players[i].id);
+        let sid = room.players[0].sid;
+        let until = Utc::now() + TimeDelta::minutes(2);
+        // a connection the player no longer sits with can't hold the seat
+        room.players[0].sid = Sid::new();
+        assert!(!room.hold_seat(small_blind, sid, until));
+        room.players[0].sid = sid;
+        assert!(room.hold_seat(small_blind, sid, until));
+        assert!(room.is_turn_expired());
+
+        room.take_timeout_action(small_blind)?;
+        assert_eq!(room.players[0].fold_reason, Some(FoldReason::Disconnect));
+        assert_eq!(room.players[0].chips, 99);
+        assert!(room.expired_seat_holds(Utc::now()).is_empty());
+        assert_eq!(room.expired_seat_holds(until), vec![small_blind]);
+
+        assert!(room.reclaim_seat(small_blind, Sid::new()));
+        assert!(room.expired_seat_holds(until).is_empty());
+        assert!(room.seated_player(big_blind).is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn settlements_should_carry_each_stack_change_of_the_hand() {
+        let mut room = heads_up_room();
+        let [small_blind, big_blind] = [0, 1].map(|i| room.players[i].id);
+        room.starting_chips = HashMap::from([(small_blind, 100), (big_blind, 100)]);
+        room.players[0].chips = 90;
+        room.players[1].chips = 110;
+        let settlements = room.settlements();
+        assert_eq!(
+            settlements,
+            vec![
+                HandSettlement {
+                    player_id: small_blind,
+                    delta: -10,
+                    chips_after: 90,
+                },
+                HandSettlement {
+                    player_id: big_blind,
+                    delta: 10,
+                    chips_after: 110,
+                },
+            ]
+        );
+        // a player who sat down after the deal has nothing to settle yet
+        room.starting_chips.remove(&small_blind);
+        assert_eq!(room.settlements().len(), 1);
+    }
+
+    #[test]
+    fn all_in_ev_should_use_equity_when_the_money_went_in() -> Result<()> {
+        let mut room = room_with_table(&[0, 0], Some(0));
+        let [aces, queens] = [0, 1].map(|i| room.players[i].id);
+        room.players[0].hand = Some(Hand([card!("As")?, card!("Kd")?]));
+        room.players[1].hand = Some(Hand([card!("Qh")?, card!("Qs")?]));
+        room.community_cards = cards!("Ad 7c 2s 3h Qd").try_collect()?;
+        room.all_in_board = Some(4);
+        room.pots = vec![Pot {
+            amount: 200,
+            players: HashSet::from([aces, queens]),
+        }];
+        room.starting_chips = HashMap::from([(aces, 100), (queens, 100)]);
+        // the queen on the river wins it
+        room.players[1].chips = 200;
+        let expected = room.all_in_expected_winnings(&Evaluator::new())?;
+        let results = vec![Winnings {
+            player: queens,
+            amount: 200,
+            best_hand: vec![],
+        }];
+        let outcomes = room.hand_outcomes(&HashMap::new(), &results, &expected);
+        let outcome = |id| outcomes.iter().find(|o| o.player_id == id).unwrap();
+        assert_eq!(outcome(aces).net_chips, -100);
+        assert_eq!(outcome(aces).all_in_ev, Some(91));
+        assert_eq!(outcome(queens).all_in_ev, Some(-91));
+        Ok(())
+    }
+
+    #[test]
+    fn run_out_should_show_the_board_a_street_at_a_time() -> Result<()> {
+        let evaluator = Evaluator::new();
+        let mut room = room_with_table(&[0, 0], Some(0));
+        let aces = room.players[0].id;
+        room.players[0].hand = Some(Hand([card!("As")?, card!("Kd")?]));
+        room.players[1].hand = Some(Hand([card!("Qh")?, card!("Qs")?]));
+        room.community_cards = cards!("Ad 7c 2s 3h Qd").try_collect()?;
+        room.all_in_board = Some(3);
+
+        assert!(room.start_run_out(&evaluator)?);
+        assert_eq!(room.visible_board().len(), 3);
+        // the queens need one of the two queens left
+        let equity = &room.run_out.as_ref().unwrap().equity;
+        assert!(equity[&aces] > 0.9 && equity[&aces] < 0.93);
+
+        assert!(!room.next_run_out_street(&evaluator)?);
+        assert_eq!(room.visible_board().len(), 4);
+        assert!(room.is_running_out());
+
+        assert!(room.next_run_out_street(&evaluator)?);
+        assert_eq!(room.visible_board().len(), 5);
+        assert!(!room.is_running_out());
+        // the hand is settled next, not run out again
+        assert!(!room.start_run_out(&evaluator)?);
+        Ok(())
+    }
+
+    #[test]
+    fn run_out_should_keep_folded_hands_hidden() -> Result<()> {
+        let mut room = room_with_table(&[0, 0, 50], Some(0));
+        let folded = room.players[2].id;
+        room.players[0].hand = Some(Hand([card!("As")?, card!("Kd")?]));
+        room.players[1].hand = Some(Hand([card!("Qh")?, card!("Qs")?]));
+        room.players[2].hand = Some(Hand([card!("7h")?, card!("2d")?]));
+        room.players[2].has_folded = true;
+        room.community_cards = cards!("Ad 7c 2s 3h Qd").try_collect()?;
+        room.all_in_board = Some(3);
+        assert!(room.start_run_out(&Evaluator::new())?);
+
+        let state = SharedGameState::from_room(room, false);
+        for player in state.players {
+            if player.id == folded {
+                assert!(matches!(player.hand, HandState::Hidden));
+            } else {
+                assert!(matches!(player.hand, HandState::Revealed(_)));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn snapshot_should_refund_chips_bet_in_an_unfinished_hand() -> Result<()> {
+        let mut room = heads_up_room();
+        let [small_blind, big_blind] = [0, 1].map(|i| room.players[i].id);
+        room.starting_chips = HashMap::from([(small_blind, 100), (big_blind, 100)]);
+        room.take_action(small_blind, Action::Call)?;
+        let snapshot = room.snapshot();
+        assert_eq!(snapshot.room_id, room.id);
+        assert!(snapshot.seats.iter().all(|seat| seat.chips == 100));
+
+        // once the results are up the hand is settled and stacks are kept as they are
+        room.showdown = Some(ShowdownPhase {
+            deadline: Utc::now(),
+            ready: HashSet::new(),
+            shown: HashSet::new(),
+            undecided: HashSet::new(),
+            hands_eval: HashMap::new(),
+        });
+        let snapshot = room.snapshot();
+        let small_blind_seat = snapshot.seats.iter().find(|s| s.player_id == small_blind);
+        assert_eq!(small_blind_seat.map(|s| s.chips), Some(room.players[0].chips));
+        Ok(())
+    }
+
+    #[test]
+    fn details_should_list_seats_in_order_and_count_the_waitlist() {
+        let mut room = heads_up_room();
+        room.players.reverse();
+        let mut waiting = room.players[0].clone();
+        waiting.id = Uuid::new_v4();
+        room.player_joining_next_round.push(waiting);
+        let details = room.details(false);
+        assert_eq!(details.seats.len(), 2);
+        assert!(details.seats[0].seat < details.seats[1].seat);
+        assert_eq!(details.waitlist, 1);
+        assert_eq!(details.big_blind, BIG_BLIND);
+    }
+
+    #[test]
+    fn raise_above_stack_should_be_rejected() {
+        let mut room = heads_up_room();
+        let player_id = room.players[0].id;
+        let result = room.take_action(player_id, Action::Raise(100));
+        assert!(matches!(
+            result.map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::RaiseExceedsStack))
+        ));
+        assert_eq!(room.players[0].chips, 99);
+        assert_eq!(room.players[0].last_action, None);
+    }
+
+    #[test]
+    fn timed_out_player_should_fold_facing_a_bet_and_check_otherwise() -> Result<()> {
+        let mut room = heads_up_room();
+        let small_blind = room.players[0].id;
+        let big_blind = room.players[1].id;
+        assert_eq!(room.timeout_action(small_blind), Action::Fold);
+        room.take_action(small_blind, Action::Call)?;
+        assert_eq!(room.timeout_action(big_blind), Action::Check);
+        Ok(())
+    }
+
+    #[test]
+    fn folds_should_record_whether_the_player_timed_out_or_left() -> Result<()> {
+        let mut room = room_with_table(&[100, 100, 100], Some(0));
+        room.stage = Stage::PreFlop;
+        room.players[1].bet = 1;
+        room.players[2].bet = 2;
+        room.player_in_turn = Some(room.players[0].id);
+        let [in_turn, _, leaving] = [0, 1, 2].map(|i| room.players[i].id);
+
+        room.leave_player(leaving);
+        assert_eq!(room.players[2].last_action, Some(Action::Fold));
+        assert_eq!(room.players[2].fold_reason, Some(FoldReason::Disconnect));
+        room.take_timeout_action(in_turn)?;
+        assert_eq!(room.players[0].fold_reason, Some(FoldReason::Timeout));
+
+        let reasons: Vec<_> = room
+            .action_log
+            .iter()
+            .map(|logged| (logged.player_id, logged.fold_reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (leaving, Some(FoldReason::Disconnect)),
+                (in_turn, Some(FoldReason::Timeout)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn players_folded_by_the_timer_hands_in_a_row_should_be_kicked() -> Result<()> {
+        let mut room = room_with_table(&[100, 100, 100], Some(0));
+        room.config.kick_after_timeouts = 2;
+        let idle = room.players[0].id;
+        let deal = |room: &mut Room| {
+            room.stage = Stage::PreFlop;
+            for (player, bet) in room.players.iter_mut().zip([0, 1, 2]) {
+                player.has_folded = false;
+                player.bet = bet;
+            }
+            room.player_in_turn = Some(idle);
+        };
+
+        deal(&mut room);
+        room.take_timeout_action(idle)?;
+        assert!(!room.should_kick(idle));
+        // acting starts the count over
+        deal(&mut room);
+        room.take_action(idle, Action::Call)?;
+        deal(&mut room);
+        room.take_timeout_action(idle)?;
+        assert!(!room.should_kick(idle));
+        deal(&mut room);
+        room.take_timeout_action(idle)?;
+        assert!(room.should_kick(idle));
+
+        room.config.kick_after_timeouts = 0;
+        assert!(!room.should_kick(idle));
+        Ok(())
+    }
+
+    #[test]
+    fn top_ups_should_wait_for_the_next_hand_up_to_the_max_buy_in() -> Result<()> {
+        let mut room = heads_up_room();
+        let [player, other] = [0, 1].map(|i| room.players[i].id);
+        // the chip already in the pot counts towards the stack
+        assert!(room.check_top_up(player, MAX_BUY_IN - 99).is_err());
+        assert!(room.check_top_up(player, 0).is_err());
+        assert_eq!(room.top_up(player, 300)?, ServiceRequiredAction::NoAction);
+        assert_eq!(room.players[0].chips, 99);
+        assert_eq!(room.snapshot().seats[0].chips, 399);
+
+        room.start_game()?;
+        assert_eq!(room.starting_chips[&player], 399);
+        assert!(room.pending_top_ups.is_empty());
+
+        // paid back with the stack when leaving before the next hand
+        let chips = room.players[1].chips;
+        room.top_up(other, 50)?;
+        assert_eq!(room.leave_player(other), chips + 50);
+        Ok(())
+    }
+
+    #[test]
+    fn turn_deadline_should_only_reset_when_the_turn_changes() -> Result<()> {
+        let mut room = heads_up_room();
+        room.refresh_turn_deadline(TimeDelta::zero());
+        assert!(room.is_turn_expired());
+        let deadline = room.turn_deadline;
+        room.refresh_turn_deadline(TimeDelta::seconds(30));
+        assert_eq!(room.turn_deadline, deadline);
+
+        let small_blind = room.players[0].id;
+        room.take_action(small_blind, Action::Call)?;
+        room.refresh_turn_deadline(TimeDelta::seconds(30));
+        assert!(!room.is_turn_expired());
+        Ok(())
+    }
+
+    #[test]
+    fn personalized_state_should_include_to_call_and_effective_stacks() {
+        let mut room = room_with_table(&[99, 198, 48], Some(0));
+        room.stage = Stage::PreFlop;
+        room.players[1].bet = 1;
+        room.players[2].bet = 2;
+        room.player_in_turn = Some(room.players[0].id);
+        let [me, big_stack, short_stack] = [0, 1, 2].map(|i| room.players[i].id);
+
+        let state = SharedGameState::from_room(room, false).personalized(me);
+        // only the player in turn is told the price of a call
+        assert!(state.pot_odds(big_stack).is_none());
+        let personal = state.personal.expect("player is seated");
+        assert_eq!(personal.to_call, 2);
+        assert_eq!(personal.effective_stacks[&big_stack], 99);
+        assert_eq!(personal.effective_stacks[&short_stack], 50);
+        assert_eq!(personal.max_effective_stack(), 99);
+        // 3 chips in the middle for a call of 2
+        let pot_odds = personal.pot_odds.expect("there is a bet to call");
+        assert_eq!(pot_odds.ratio(), "1.5:1");
+        assert_eq!(pot_odds.equity_needed(), 0.4);
+    }
+
+    #[test]
+    fn exported_room_should_carry_on_its_hand_after_import() -> Result<()> {
+        let mut room = heads_up_room();
+        room.players[0].hand = Some(Hand([card!("As")?, card!("Ks")?]));
+        room.players[1].hand = Some(Hand([card!("Qh")?, card!("Qd")?]));
+        for card in [card!("As")?, card!("Ks")?, card!("Qh")?, card!("Qd")?] {
+            room.deck.remove(card);
+        }
+        let small_blind = room.players[0].id;
+        room.take_action(small_blind, Action::Call)?;
+        let (key, secret) = HandKey::generate();
+
+        let export = room.export(&key)?;
+        // what goes over the wire is read back by the other server
+        let export: RoomExport = serde_json::from_str(&serde_json::to_string(&export)?)?;
+        let (_, other_secret) = HandKey::generate();
+        assert!(Room::import(export.clone(), &other_secret, &Evaluator::new()).is_err());
+        let mut imported = Room::import(export, &secret, &Evaluator::new())?;
+
+        // socket ids belonged to the connections of the old server
+        let expected: Vec<Player> = room
+            .players
+            .iter()
+            .zip(&imported.players)
+            .map(|(player, imported)| Player {
+                sid: imported.sid,
+                ..player.clone()
+            })
+            .collect();
+        assert_eq!(imported.players, expected);
+        assert_eq!(imported.deck.bits(), room.deck.bits());
+        assert_eq!(imported.pots, room.pots);
+        assert_eq!(imported.action_seq, room.action_seq);
+        assert_eq!(imported.player_in_turn, room.player_in_turn);
+        assert_eq!(imported.awaiting_reconnect.len(), 2);
+        assert!(imported.reclaim_seat(small_blind, Default::default()));
+        assert!(!imported.reclaim_seat(small_blind, Default::default()));
+        let big_blind = imported.players[1].id;
+        imported.take_action(big_blind, Action::Check)?;
+        assert_eq!(imported.stage, Stage::Flop);
+        Ok(())
+    }
+
+    #[test]
+    fn redacted_state_should_not_name_players() -> Result<()> {
+        let mut room = heads_up_room();
+        let small_blind = room.players[0].id;
+        room.take_action(small_blind, Action::Call)?;
+        let action_log = room.action_log.clone();
+        let mut state = SharedGameState::from_room(room, false);
+        state.recent_actions = action_log;
+
+        let redacted = state.redacted();
+        let names: Vec<_> = redacted.players.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["Seat 0", "Seat 1"]);
+        assert_eq!(redacted.recent_actions.len(), 1);
+        assert_eq!(redacted.recent_actions[0].player_name, "Seat 0");
+        Ok(())
+    }
+
+    #[test]
+    fn hand_history_should_only_reveal_hole_cards_shown_down() -> Result<()> {
+        let mut room = room_with_table(&[100, 100, 100], Some(0));
+        room.players[0].hand = Some(Hand([card!("As")?, card!("Ks")?]));
+        room.players[2].hand = Some(Hand([card!("Qh")?, card!("Qd")?]));
+        room.community_cards = cards!("Ah Kd 3c 9s Tc").try_collect()?;
+        let shown_down = room.players[0].id;
+        let cards: Vec<_> = cards!("As Ks Ah Kd 3c 9s Tc").try_collect()?;
+        let eval = Evaluator::new().evaluate(cards)?;
+        let hands_eval = HashMap::from([(shown_down, eval)]);
+
+        let history = HandHistory::from_room(&room, &hands_eval, vec![]);
+        // the player who wasn't dealt in is left out
+        assert_eq!(history.players.len(), 2);
+        assert_eq!(history.players[0].hole_cards.as_ref().map(Vec::len), Some(2));
+        assert!(history.players[0].eval.is_some());
+        assert!(history.players[1].hole_cards.is_none());
+        assert_eq!(history.community_cards.len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn showdown_phase_should_end_once_every_connected_player_is_ready() {
+        let mut room = heads_up_room();
+        let [first, second] = [0, 1].map(|i| room.players[i].id);
+        assert!(!room.mark_ready(first));
+
+        room.start_showdown_phase(TimeDelta::seconds(30), HashMap::new(), HashSet::new());
+        assert!(!room.is_showdown_expired());
+        assert!(!room.mark_ready(first));
+        // strangers can't end the phase for the table
+        assert!(!room.mark_ready(Uuid::new_v4()));
+        assert!(room.mark_ready(second));
+
+        room.start_showdown_phase(TimeDelta::seconds(30), HashMap::new(), HashSet::new());
+        room.players[1].is_connected = false;
+        assert!(room.mark_ready(first));
+
+        room.start_showdown_phase(TimeDelta::zero(), HashMap::new(), HashSet::new());
+        assert!(room.is_showdown_expired());
+    }
+
+    #[test]
+    fn losers_should_only_show_their_cards_when_they_choose_to() -> Result<()> {
+        let mut room = heads_up_room();
+        room.players[0].hand = Some(Hand([card!("As")?, card!("Ks")?]));
+        room.players[1].hand = Some(Hand([card!("Qh")?, card!("Qd")?]));
+        let [winner, loser] = [0, 1].map(|i| room.players[i].id);
+        let evaluator = Evaluator::new();
+        let hands_eval = HashMap::from([
+            (winner, evaluator.evaluate(cards!("As Ks Ah Kd 3c").try_collect::<Vec<_>>()?)?),
+            (loser, evaluator.evaluate(cards!("Qh Qd Ah Kd 3c").try_collect::<Vec<_>>()?)?),
+        ]);
+        room.start_showdown_phase(TimeDelta::seconds(30), hands_eval, HashSet::from([winner]));
+
+        let state = SharedGameState::from_room(room.clone(), false);
+        assert!(state.players[0].eval.is_some());
+        assert!(matches!(state.players[1].hand, HandState::Hidden));
+        assert!(matches!(
+            room.show_or_muck(winner, true).map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::NoCardsToShow))
+        ));
+
+        assert!(room.show_or_muck(loser, true)?);
+        let state = SharedGameState::from_room(room.clone(), false);
+        assert!(matches!(state.players[1].hand, HandState::Revealed(_)));
+        // the choice is final
+        assert!(room.show_or_muck(loser, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn resume_summary_should_only_include_missed_actions() -> Result<()> {
+        let mut room = heads_up_room();
+        let small_blind = room.players[0].id;
+        let big_blind = room.players[1].id;
+        room.take_action(small_blind, Action::RaiseTo(4))?;
+
+        let summary = room.resume_summary(big_blind, None).expect("hand in progress");
+        assert_eq!(summary.actions.len(), 1);
+        assert_eq!(summary.actions[0].action, Action::RaiseTo(4));
+        assert_eq!(summary.to_call, Some(2));
+
+        let summary = room.resume_summary(big_blind, Some(room.action_seq));
+        assert!(summary.is_some_and(|s| s.actions.is_empty()));
+
+        // players waiting for the next hand have nothing to call
+        let summary = room.resume_summary(Uuid::new_v4(), None);
+        assert!(summary.is_some_and(|s| s.to_call.is_none()));
+        Ok(())
+    }
+
+    // a table of 2 to 5 players, where the dealer may already have left (None)
+    fn table() -> impl Strategy<Value = (Vec<u32>, Option<usize>)> {
+        (2..=5usize).prop_flat_map(|n| {
+            (
+                prop::collection::vec(0..10_000u32, n),
+                prop::option::of(0..n),
+            )
+        })
+    }
+
+    // pots as (amount, bitmask of winning seats), every pot having at least one winner
+    fn pots(players: usize) -> impl Strategy<Value = Vec<(u32, u32)>> {
+        prop::collection::vec((0..100_000u32, 1..(1u32 << players)), 1..=4)
+    }
+
+    #[test]
+    fn full_room_should_not_be_joinable() {
+        let mut room = room_with_table(&[100; DEFAULT_MAX_PLAYERS], None);
+        assert!(matches!(
+            room.check_joinable().map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::RoomIsFull))
+        ));
+        // a player who left stays at the table until the hand is over, but frees the seat
+        let leaving = room.players[0].id;
+        room.leave_player(leaving);
+        assert!(room.seated_player(leaving).is_some());
+        assert!(room.check_joinable().is_ok());
+    }
+
+    #[test]
+    fn table_size_should_bound_the_seats() -> Result<()> {
+        let mut room = room_with_table(&[100; DEFAULT_MAX_PLAYERS], None);
+        room.stage = Stage::PreFlop;
+        room.config.max_players = 9;
+        room.join_player_at(Player::new("Eighth".to_string(), 100), Some(8))?;
+        assert!(room.check_seat_free(9).is_err());
+        assert!(room.check_joinable().is_ok());
+
+        room.config.max_players = 2;
+        assert!(matches!(
+            room.check_joinable().map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::RoomIsFull))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn waiting_list_should_be_seated_in_order_once_a_seat_frees_up() -> Result<()> {
+        let mut room = room_with_table(&[100; DEFAULT_MAX_PLAYERS], None);
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(room.queue_player(first, 50, Sid::new())?, 1);
+        assert_eq!(room.queue_player(second, 80, Sid::new())?, 2);
+        // queueing again keeps the place
+        assert_eq!(room.queue_player(first, 60, Sid::new())?, 1);
+        assert!(room.next_waiting().is_none());
+
+        room.leave_player(room.players[0].id);
+        let seated = room.next_waiting().map(|w| (w.user_id, w.buy_in));
+        assert_eq!(seated, Some((first, 60)));
+        assert!(room.leave_waiting_list(second));
+        assert!(room.waiting_list.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn player_should_sit_at_the_seat_they_ask_for() -> Result<()> {
+        let mut room = room_with_table(&[100, 100], Some(0));
+        room.stage = Stage::PreFlop;
+        let chosen = Player::new("Chosen".to_string(), 100);
+        let chosen_id = chosen.id;
+        room.join_player_at(chosen, Some(4))?;
+        assert_eq!(room.seated_player(chosen_id).map(|p| p.seat), Some(4));
+
+        for seat in [4, DEFAULT_MAX_PLAYERS] {
+            let result = room.join_player_at(Player::new("Late".to_string(), 100), Some(seat));
+            assert!(matches!(
+                result.map_err(|e| e.downcast::<Error>()),
+                Err(Ok(Error::SeatUnavailable(s))) if s == seat
+            ));
+        }
+
+        // without a seat asked for, the first free one is taken
+        room.join_player(Player::new("Any".to_string(), 100))?;
+        room.start_game()?;
+        let seats: Vec<_> = room.players.iter().map(|p| p.seat).collect();
+        assert_eq!(seats, vec![0, 1, 2, 4]);
+        Ok(())
+    }
+
+    #[test]
+    fn hands_should_be_numbered_and_the_button_shown() -> Result<()> {
+        let mut room = room_with_table(&[100, 100, 100], Some(0));
+        room.stage = Stage::PreFlop;
+        room.start_game()?;
+        let state = SharedGameState::from_room(room.clone(), false);
+        assert_eq!((state.hand_number, state.dealer_seat), (1, Some(1)));
+
+        room.start_game()?;
+        let state = SharedGameState::from_room(room, false);
+        assert_eq!((state.hand_number, state.dealer_seat), (2, Some(2)));
+        Ok(())
+    }
+
+    fn room_with_table(chips: &[u32], dealer: Option<usize>) -> Room {
+        let mut room = Room::new();
+        room.players = chips
+            .iter()
+            .enumerate()
+            .map(|(seat, chips)| {
+                let mut player = Player::new(format!("Player {}", seat), *chips);
+                player.seat = seat;
+                player.position = if Some(seat) == dealer {
+                    Position::Dealer
+                } else {
+                    Position::Normal
+                };
+                player
+            })
+            .collect();
+        room
+    }
+
+    proptest! {
+        #[test]
+        fn split_pot_should_conserve_chips(
+            ((chips, dealer), pots) in table().prop_flat_map(|table| {
+                let players = table.0.len();
+                (Just(table), pots(players))
+            })
+        ) {
+            let mut room = room_with_table(&chips, dealer);
+            let winners = pots
+                .iter()
+                .map(|(amount, mask)| {
+                    let winner_ids = room
+                        .players
+                        .iter()
+                        .filter(|p| mask & (1 << p.seat) != 0)
+                        .map(|p| p.id)
+                        .collect::<HashSet<_>>();
+                    (*amount, winner_ids)
+                })
+                .collect::<Vec<_>>();
+
+            let pot_splits = room.split_pot(winners.clone()).unwrap();
+
+            let chips_before: u64 = chips.iter().map(|c| *c as u64).sum();
+            let chips_after: u64 = room.players.iter().map(|p| p.chips as u64).sum();
+            let pot_total: u64 = pots.iter().map(|(amount, _)| *amount as u64).sum();
+            prop_assert_eq!(chips_after, chips_before + pot_total);
+
+            prop_assert_eq!(pot_splits.len(), winners.len());
+            for (split, (amount, winner_ids)) in pot_splits.iter().zip(winners.iter()) {
+                let distributed: u32 = split.iter().map(|w| w.amount).sum();
+                prop_assert_eq!(distributed, *amount);
+                prop_assert_eq!(split.len(), winner_ids.len());
+                // only one winner receives the odd chips
+                let share = amount / winner_ids.len() as u32;
+                let odd_chip_winners = split.iter().filter(|w| w.amount != share).count();
+                prop_assert!(odd_chip_winners <= 1);
+            }
+        }
+
+        #[test]
+        fn split_pot_should_not_pay_out_when_a_pot_is_invalid(
+            (chips, dealer) in table(),
+            amount in 1..100_000u32,
+        ) {
+            let mut room = room_with_table(&chips, dealer);
+            let winner = room.players[0].id;
+            let winners = vec![
+                (amount, HashSet::from([winner])),
+                (amount, HashSet::new()),
+            ];
+
+            prop_assert!(room.split_pot(winners).is_err());
+            let chips_after = room.players.iter().map(|p| p.chips).collect::<Vec<_>>();
+            prop_assert_eq!(chips_after, chips);
+        }
+
+        // a failing seed replays the exact deal
+        #[test]
+        fn seeded_deal_should_never_repeat_a_card(
+            players in 2..=DEFAULT_MAX_PLAYERS,
+            seed in any::<u64>(),
+        ) {
+            let mut room = room_with_table(&vec![100; players], Some(0));
+            room.reseed(seed);
+            room.stage = Stage::PreFlop;
+            room.start_game().unwrap();
+            for street in [Stage::Flop, Stage::Turn, Stage::River] {
+                room.deal_community_card(street).unwrap();
+            }
+            let dealt: Vec<Card> = room
+                .players
+                .iter()
+                .filter_map(|p| p.hand.as_ref())
+                .flat_map(|Hand(cards)| cards.iter().copied())
+                .chain(room.community_cards.iter().copied())
+                .collect();
+            prop_assert_eq!(dealt.len(), 2 * players + 5);
+            prop_assert_eq!(dealt.iter().collect::<HashSet<_>>().len(), dealt.len());
+        }
+    }
 }
@@ -0,0 +1,224 @@
+use std::ops::RangeInclusive;
+
+use chrono::TimeDelta;
+use eyre::{bail, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::Action;
+use crate::error::Error;
+
+// seats at a table opened without asking for a size
+pub const DEFAULT_MAX_PLAYERS: usize = 5;
+// the table sizes a room can be opened with
+pub const TABLE_SIZES: RangeInclusive<usize> = 2..=9;
+pub const SMALL_BLIND: u32 = 1;
+pub const BIG_BLIND: u32 = 2;
+pub const BOT_BUY_IN: u32 = 200;
+// offered when sitting down, players can change it before joining
+pub const DEFAULT_BUY_IN: i64 = 100;
+// bankroll of a new account, and what a season reset puts balances back to
+pub const DEFAULT_BALANCE: i64 = 1000;
+// players can add chips between hands up to this stack
+pub const MAX_BUY_IN: u32 = 500;
+// free chips a user can claim, once every TOP_UP_INTERVAL
+pub const DAILY_TOP_UP: i64 = 500;
+pub const TOP_UP_INTERVAL: TimeDelta = TimeDelta::days(1);
+// the player in turn is acted for once this runs out
+pub const TURN_TIME: TimeDelta = TimeDelta::seconds(30);
+// how long the results of a hand stay up before the next one is dealt
+pub const SHOWDOWN_TIME: TimeDelta = TimeDelta::seconds(8);
+// players at an imported room have this long to reconnect before their seat is given up
+pub const RECLAIM_SEAT_TIME: TimeDelta = TimeDelta::seconds(60);
+// a dropped connection keeps its seat this long unless the server is told otherwise
+pub const DEFAULT_RECONNECT_GRACE: TimeDelta = TimeDelta::minutes(2);
+// hands in a row the turn timer may fold a player for before they are removed from the table
+pub const DEFAULT_KICK_AFTER_TIMEOUTS: u32 = 3;
+// bets a street may have in fixed limit, counting the big blind before the flop
+pub const FIXED_LIMIT_CAP: u32 = 4;
+
+/// How big bets and raises may be, chosen when a room is opened
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BettingStructure {
+    #[default]
+    NoLimit,
+    // raises go up to the size of the pot after calling
+    PotLimit,
+    // a big blind at a time before the turn and two after it
+    FixedLimit,
+}
+
+/// Where the betting on a street stands for the player about to bet or raise
+#[derive(Debug, Clone, Copy)]
+pub struct BettingRound {
+    pub max_bet: u32,
+    // what the player already put in on this street
+    pub bet: u32,
+    pub last_raise_size: u32,
+    // collected pots and every bet on the table
+    pub pot: u32,
+    // the turn and the river, where fixed limit bets double
+    pub late_street: bool,
+}
+
+/// Totals the player in turn may bet or raise to, within their stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct RaiseLimits {
+    pub min: u32,
+    pub max: u32,
+}
+
+/// An action the server accepts from the player in turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LegalAction {
+    Fold,
+    Check,
+    // chips added to match the highest bet
+    Call { amount: u32 },
+    // totals, for `Action::Bet`
+    Bet(RaiseLimits),
+    // totals, for `Action::RaiseTo`
+    Raise(RaiseLimits),
+    // the rest of the stack
+    AllIn { amount: u32 },
+}
+
+impl BettingStructure {
+    /// Totals a bet or raise may go to whatever the stack, None once fixed limit betting is capped
+    pub fn raise_to_range(self, round: &BettingRound) -> Option<RangeInclusive<u32>> {
+        let min = min_raise_to(round.max_bet, round.last_raise_size);
+        match self {
+            BettingStructure::NoLimit => Some(min..=u32::MAX),
+            BettingStructure::PotLimit => {
+                let to_call = round.max_bet.saturating_sub(round.bet);
+                let max = round.max_bet + round.pot + to_call;
+                Some(min..=max.max(min))
+            }
+            BettingStructure::FixedLimit => {
+                let size = if round.late_street {
+                    2 * BIG_BLIND
+                } else {
+                    BIG_BLIND
+                };
+                let total = round.max_bet + size;
+                (total <= FIXED_LIMIT_CAP * size).then_some(total..=total)
+            }
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BettingStructure::NoLimit => "No limit",
+            BettingStructure::PotLimit => "Pot limit",
+            BettingStructure::FixedLimit => "Fixed limit",
+        }
+    }
+}
+
+/// The `max_players` of rooms and game states saved before tables had a size
+pub(crate) fn default_max_players<T: From<u8>>() -> T {
+    T::from(DEFAULT_MAX_PLAYERS as u8)
+}
+
+/// Smallest total bet a raise may go to, a raise must be at least as big as the last full raise
+pub fn min_raise_to(max_bet: u32, last_raise_size: u32) -> u32 {
+    max_bet + last_raise_size
+}
+
+/// Turns a raise into the total it goes to, into a bet when there is nothing to call,
+/// and into an all-in when that is the whole stack
+pub fn normalize_raise(action: Action, bet: u32, chips: u32, max_bet: u32) -> Result<Action> {
+    let action = match action {
+        // no stack is that deep, so a raise past u32 is refused rather than wrapped
+        Action::Raise(amount) => {
+            Action::RaiseTo(bet.checked_add(amount).ok_or(Error::RaiseExceedsStack)?)
+        }
+        action => action,
+    };
+    let action = match action {
+        // older clients raise to open the betting too
+        Action::RaiseTo(total) if bet == max_bet => Action::Bet(total),
+        Action::Bet(_) if bet < max_bet => bail!(Error::BetFacingBet),
+        action => action,
+    };
+    let stack = bet + chips;
+    let action = match action {
+        Action::Bet(total) | Action::RaiseTo(total) if total == stack => Action::AllIn,
+        Action::Bet(total) | Action::RaiseTo(total) if total > stack => {
+            bail!(Error::RaiseExceedsStack)
+        }
+        action => action,
+    };
+    Ok(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raising_the_whole_stack_should_be_an_all_in() -> Result<()> {
+        assert_eq!(normalize_raise(Action::Raise(98), 2, 98, 4)?, Action::AllIn);
+        assert_eq!(
+            normalize_raise(Action::Raise(10), 2, 98, 4)?,
+            Action::RaiseTo(12)
+        );
+        assert!(normalize_raise(Action::RaiseTo(101), 2, 98, 4).is_err());
+        assert!(matches!(
+            normalize_raise(Action::Raise(u32::MAX), 2, 98, 4).map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::RaiseExceedsStack))
+        ));
+        assert_eq!(min_raise_to(8, 6), 14);
+        Ok(())
+    }
+
+    #[test]
+    fn bets_should_only_open_the_betting() -> Result<()> {
+        assert_eq!(normalize_raise(Action::Bet(10), 0, 98, 0)?, Action::Bet(10));
+        assert_eq!(normalize_raise(Action::Bet(98), 0, 98, 0)?, Action::AllIn);
+        assert_eq!(
+            normalize_raise(Action::RaiseTo(10), 0, 98, 0)?,
+            Action::Bet(10)
+        );
+        assert!(matches!(
+            normalize_raise(Action::Bet(20), 0, 98, 10).map_err(|e| e.downcast::<Error>()),
+            Err(Ok(Error::BetFacingBet))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn raises_should_follow_the_betting_structure() {
+        let round = BettingRound {
+            max_bet: 10,
+            bet: 2,
+            last_raise_size: 8,
+            pot: 30,
+            late_street: false,
+        };
+        assert_eq!(
+            BettingStructure::NoLimit.raise_to_range(&round),
+            Some(18..=u32::MAX)
+        );
+        // calling 8 makes the pot 38, which can be raised on top of the 10
+        assert_eq!(
+            BettingStructure::PotLimit.raise_to_range(&round),
+            Some(18..=48)
+        );
+        assert_eq!(
+            BettingStructure::FixedLimit.raise_to_range(&BettingRound {
+                max_bet: 2,
+                ..round
+            }),
+            Some(4..=4)
+        );
+        let capped = BettingRound {
+            max_bet: 16,
+            late_street: true,
+            ..round
+        };
+        assert_eq!(BettingStructure::FixedLimit.raise_to_range(&capped), None);
+    }
+}
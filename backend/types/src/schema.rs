@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+use uuid::Uuid;
+
+use crate::crypto::SealedHand;
+use crate::domain::{
+    ActionRequest, Announcement, ChatRequest, ConnectionRefused, CreateRoomRequest,
+    DarkActionRequest, FriendInvite, JoinGameRequest, Kicked, LobbyUpdate, LoginRequest,
+    LogoutRequest, PreActionRequest, RefreshRequest, RoomInfo, Seated, ShowCardsRequest,
+    ShowOrMuckPrompt, SignupRequest, SignupResponse, SocketAuth, TokenPair, TopUpRequest,
+    UpdateProfileRequest, User, Waitlisted,
+};
+use crate::protocol::{Handshake, UpgradeRequired};
+use crate::room::{ActionLogEntry, RoomClosing, RoomDetails, Winnings};
+use crate::state::{
+    ChatMessage, DealtHand, PlayerHand, ResumeSummary, SharedGameState, Timestamped,
+};
+
+/// Payload of each socket event, keyed `client.<event>` for what clients send
+/// and `server.<event>` for what the server sends
+pub fn socket_schemas() -> BTreeMap<&'static str, RootSchema> {
+    BTreeMap::from([
+        ("client.auth", schema_for!(SocketAuth)),
+        ("client.join", schema_for!(JoinGameRequest)),
+        ("client.action", schema_for!(ActionRequest)),
+        ("client.dark_action", schema_for!(DarkActionRequest)),
+        ("client.chat", schema_for!(ChatRequest)),
+        ("client.show_cards", schema_for!(ShowCardsRequest)),
+        ("client.top_up", schema_for!(TopUpRequest)),
+        ("client.pre_action", schema_for!(PreActionRequest)),
+        // these only carry the room id
+        ("client.ready", schema_for!(Uuid)),
+        ("client.sit_out", schema_for!(Uuid)),
+        ("client.sit_in", schema_for!(Uuid)),
+        ("server.handshake", schema_for!(Handshake)),
+        ("server.upgrade_required", schema_for!(UpgradeRequired)),
+        ("server.connection_refused", schema_for!(ConnectionRefused)),
+        ("server.room", schema_for!(Timestamped<SharedGameState>)),
+        // hole cards come in one of three shapes, depending on what the socket asked for
+        ("server.hand", schema_for!(Timestamped<PlayerHand>)),
+        ("server.hand.strength", schema_for!(Timestamped<DealtHand>)),
+        ("server.hand.sealed", schema_for!(Timestamped<SealedHand>)),
+        ("server.outcome", schema_for!(Timestamped<Vec<Winnings>>)),
+        ("server.room_closing", schema_for!(Timestamped<RoomClosing>)),
+        ("server.resume", schema_for!(Timestamped<ResumeSummary>)),
+        (
+            "server.show_or_muck",
+            schema_for!(Timestamped<ShowOrMuckPrompt>),
+        ),
+        ("server.seated", schema_for!(Timestamped<Seated>)),
+        ("server.waitlisted", schema_for!(Timestamped<Waitlisted>)),
+        ("server.kicked", schema_for!(Timestamped<Kicked>)),
+        ("server.chat", schema_for!(ChatMessage)),
+        ("server.action_log", schema_for!(ActionLogEntry)),
+        ("server.announcement", schema_for!(Announcement)),
+        ("server.service_error", schema_for!(String)),
+        ("server.lobby", schema_for!(LobbyUpdate)),
+        ("server.invite", schema_for!(FriendInvite)),
+    ])
+}
+
+/// Bodies of the REST endpoints clients need to log in and find a table
+pub fn rest_schemas() -> BTreeMap<&'static str, RootSchema> {
+    BTreeMap::from([
+        ("signup.request", schema_for!(SignupRequest)),
+        ("signup.response", schema_for!(SignupResponse)),
+        ("login.request", schema_for!(LoginRequest)),
+        ("login.response", schema_for!(TokenPair)),
+        ("refresh.request", schema_for!(RefreshRequest)),
+        ("logout.request", schema_for!(LogoutRequest)),
+        ("profile", schema_for!(User)),
+        ("profile.update", schema_for!(UpdateProfileRequest)),
+        ("rooms", schema_for!(Vec<RoomInfo>)),
+        ("rooms.create", schema_for!(CreateRoomRequest)),
+        ("rooms.details", schema_for!(RoomDetails)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonschema::JSONSchema;
+    use serde::Serialize;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::domain::Action;
+    use crate::room::Stage;
+
+    fn assert_valid<T: Serialize>(schemas: &BTreeMap<&str, RootSchema>, name: &str, payload: &T) {
+        let schema = serde_json::to_value(&schemas[name]).unwrap();
+        let compiled = JSONSchema::compile(&schema).unwrap();
+        let payload = serde_json::to_value(payload).unwrap();
+        assert!(
+            compiled.is_valid(&payload),
+            "{} does not match its schema",
+            name
+        );
+    }
+
+    #[test]
+    fn payloads_should_match_their_schemas() {
+        let schemas = socket_schemas();
+        let request = ActionRequest {
+            room_id: Uuid::new_v4(),
+            action: Action::RaiseTo(6),
+        };
+        assert_valid(&schemas, "client.action", &request);
+        assert_valid(
+            &schemas,
+            "server.room",
+            &Timestamped::new(SharedGameState::default()),
+        );
+        let update = LobbyUpdate::Pace {
+            room_id: Uuid::new_v4(),
+            hands_per_hour: Some(60),
+        };
+        assert_valid(&schemas, "server.lobby", &update);
+        assert_valid(
+            &schemas,
+            "server.action_log",
+            &ActionLogEntry::Street(Stage::Flop),
+        );
+    }
+
+    #[test]
+    fn schemas_should_reject_malformed_payloads() {
+        let schemas = socket_schemas();
+        let schema: Value = serde_json::to_value(&schemas["client.action"]).unwrap();
+        let compiled = JSONSchema::compile(&schema).unwrap();
+        let payload = json!({"room_id": Uuid::new_v4(), "action": {"Raise": "lots"}});
+        assert!(!compiled.is_valid(&payload));
+        let schema: Value = serde_json::to_value(&schemas["server.outcome"]).unwrap();
+        let compiled = JSONSchema::compile(&schema).unwrap();
+        let card = json!({"timestamp": "2026-10-17T12:00:00Z", "data": [
+            {"player": Uuid::new_v4(), "amount": 10, "best_hand": ["1x"]}
+        ]});
+        assert!(!compiled.is_valid(&card));
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use strum_macros::{AsRefStr, EnumString};
+use uuid::Uuid;
+
+/// Cosmetic badge for finishing a season near the top, it carries no chips
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize, AsRefStr, EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SeasonReward {
+    GoldCrown,
+    SilverCrown,
+    BronzeCrown,
+    TopTen,
+}
+
+impl SeasonReward {
+    pub fn for_rank(rank: i64) -> Option<Self> {
+        match rank {
+            1 => Some(SeasonReward::GoldCrown),
+            2 => Some(SeasonReward::SilverCrown),
+            3 => Some(SeasonReward::BronzeCrown),
+            4..=10 => Some(SeasonReward::TopTen),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonStanding {
+    pub user_id: Uuid,
+    pub name: String,
+    // 1 for the biggest balance
+    pub rank: i64,
+    pub balance: i64,
+    pub reward: Option<SeasonReward>,
+}
+
+/// Final standings of a season, archived when it rolls over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonSummary {
+    pub season_id: i64,
+    pub ended_at: DateTime<Utc>,
+    pub reset_balances: bool,
+    pub standings: Vec<SeasonStanding>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct RolloverRequest {
+    // put every balance back to the default bankroll
+    #[serde(default)]
+    pub reset_balances: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_top_ten_should_be_rewarded() {
+        assert_eq!(SeasonReward::for_rank(1), Some(SeasonReward::GoldCrown));
+        assert_eq!(SeasonReward::for_rank(3), Some(SeasonReward::BronzeCrown));
+        assert_eq!(SeasonReward::for_rank(10), Some(SeasonReward::TopTen));
+        assert_eq!(SeasonReward::for_rank(11), None);
+    }
+}
@@ -1,18 +1,25 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
+use itertools::Itertools;
 use poker::{Card, Eval, Rank, Suit};
 use ratatui::prelude::{Color, Span, Style};
 use ratatui::style::Stylize;
 use ratatui::text::Line;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Schema, SchemaObject, StringValidation};
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use uuid::Uuid;
 
-use crate::domain::Action;
-use crate::room::{Hand, Player, Position, Room, Stage};
+use crate::domain::{Action, Avatar, DarkAction, FoldReason, PreAction};
+use crate::fairness::Deal;
+use crate::room::{Hand, LoggedAction, Player, Position, Room, Stage};
+use crate::rules::{default_max_players, min_raise_to, BettingStructure, LegalAction, RaiseLimits};
+use crate::strength::HandStrength;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 pub struct SharedGameState {
     pub id: Uuid,
     pub players: Vec<PlayerState>,
@@ -20,6 +27,113 @@ pub struct SharedGameState {
     pub pots: Vec<u32>,
     pub stage: Stage,
     pub current_player: Option<Uuid>,
+    pub last_action_seq: u64,
+    pub turn_deadline: Option<DateTime<Utc>>,
+    // results are on display until then, or until every player is ready
+    #[serde(default)]
+    pub showdown_deadline: Option<DateTime<Utc>>,
+    // a raise must add at least this much on top of the highest bet
+    #[serde(default)]
+    pub min_raise: u32,
+    // players left with only calling or folding by a short all-in
+    #[serde(default)]
+    pub raise_closed_for: HashSet<Uuid>,
+    // actions taken since the previous snapshot sent to the room
+    #[serde(default)]
+    pub recent_actions: Vec<LoggedAction>,
+    // only set on the copy sent to a seated player
+    #[serde(default)]
+    pub personal: Option<PersonalState>,
+    // cards come from a scripted scenario, not a shuffled deck
+    #[serde(default)]
+    pub is_scripted: bool,
+    #[serde(default)]
+    pub hand_started_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub street_started_at: Option<DateTime<Utc>>,
+    // average over the latest hands at the table
+    #[serde(default)]
+    pub hands_per_hour: Option<u32>,
+    // chance of winning for each hand still in while an all-in board runs out
+    #[serde(default)]
+    pub equity: HashMap<Uuid, f64>,
+    // 0 until the first hand is dealt
+    #[serde(default)]
+    pub hand_number: u64,
+    // seat holding the button
+    #[serde(default)]
+    pub dealer_seat: Option<usize>,
+    // each player only gets their own, in `personal`
+    #[serde(skip)]
+    pub pre_actions: HashMap<Uuid, PreAction>,
+    #[serde(default)]
+    pub betting: BettingStructure,
+    // seats at the table, taken and free
+    #[serde(default = "default_max_players::<usize>")]
+    pub max_players: usize,
+    // what the player in turn may bet or raise to, None when they may not raise
+    #[serde(default)]
+    pub raise_limits: Option<RaiseLimits>,
+    // only the player in turn gets them, in `personal`
+    #[serde(skip)]
+    pub legal_actions: Vec<LegalAction>,
+    // what the hand in progress is dealt from, committed to before the first card
+    #[serde(default)]
+    pub deck_commitment: Option<String>,
+    // the seed of the hand just played, once it is over
+    #[serde(default)]
+    pub revealed_deal: Option<Deal>,
+}
+
+/// Betting figures for the player a state is sent to
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, JsonSchema)]
+pub struct PersonalState {
+    pub player_id: Uuid,
+    // chips needed to match the highest bet on the table
+    pub to_call: u32,
+    // the smaller of the player's stack and each opponent's stack still in the hand, bets included
+    pub effective_stacks: HashMap<Uuid, u32>,
+    // played for the player once their turn comes
+    #[serde(default)]
+    pub pre_action: Option<PreAction>,
+    // empty unless it is the player's turn
+    #[serde(default)]
+    pub legal_actions: Vec<LegalAction>,
+    // only while it is the player's turn and there is a bet to call
+    #[serde(default)]
+    pub pot_odds: Option<PotOdds>,
+}
+
+/// The price of a call against what it can win
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct PotOdds {
+    // chips the call puts in, no more than the player's stack
+    pub call: u32,
+    // every chip in the middle before the call, bets of the street included
+    pub pot: u32,
+}
+
+impl PotOdds {
+    /// Share of the pot after the call the player puts in, the equity needed to break even
+    pub fn equity_needed(&self) -> f64 {
+        self.call as f64 / (self.pot + self.call) as f64
+    }
+
+    /// Pot to call, like "3.5:1"
+    pub fn ratio(&self) -> String {
+        format!("{:.1}:1", self.pot as f64 / self.call as f64)
+    }
+}
+
+impl PersonalState {
+    /// Largest stack the player can win or lose this hand
+    pub fn max_effective_stack(&self) -> u32 {
+        self.effective_stacks
+            .values()
+            .max()
+            .copied()
+            .unwrap_or_default()
+    }
 }
 
 impl SharedGameState {
@@ -48,6 +162,89 @@ impl SharedGameState {
         }
     }
 
+    /// Seconds left for the player in turn to act
+    pub fn turn_time_left(&self) -> Option<i64> {
+        self.turn_deadline.map(seconds_until)
+    }
+
+    /// How long the current hand and street have been going, like "hand 2:13 · street 0:12"
+    pub fn hand_time(&self) -> Option<String> {
+        let hand = format_elapsed(self.hand_started_at?);
+        match self.street_started_at {
+            Some(street) => Some(format!("hand {} · street {}", hand, format_elapsed(street))),
+            None => Some(format!("hand {}", hand)),
+        }
+    }
+
+    /// Seconds until the next hand is dealt while results are on display
+    pub fn showdown_time_left(&self) -> Option<i64> {
+        self.showdown_deadline.map(seconds_until)
+    }
+
+    /// This state with the betting figures for `player_id` filled in
+    pub fn personalized(&self, player_id: Uuid) -> Self {
+        let mut state = self.clone();
+        state.personal = self
+            .players
+            .iter()
+            .find(|p| p.id == player_id)
+            .map(|me| PersonalState {
+                player_id,
+                to_call: self.max_bet().saturating_sub(me.bet),
+                effective_stacks: self
+                    .players
+                    .iter()
+                    .filter(|p| p.id != player_id && !p.has_folded)
+                    .map(|p| (p.id, (me.chips + me.bet).min(p.chips + p.bet)))
+                    .collect(),
+                pre_action: self.pre_actions.get(&player_id).copied(),
+                legal_actions: if self.current_player == Some(player_id) {
+                    self.legal_actions.clone()
+                } else {
+                    Vec::new()
+                },
+                pot_odds: self.pot_odds(player_id),
+            });
+        state
+    }
+
+    /// What calling costs the player in turn, None when there is nothing to call
+    pub fn pot_odds(&self, player_id: Uuid) -> Option<PotOdds> {
+        if self.current_player != Some(player_id) {
+            return None;
+        }
+        let me = self.players.iter().find(|p| p.id == player_id)?;
+        let call = self.max_bet().saturating_sub(me.bet).min(me.chips);
+        let pot = self.pots.iter().sum::<u32>() + self.players.iter().map(|p| p.bet).sum::<u32>();
+        (call > 0).then_some(PotOdds { call, pot })
+    }
+
+    /// Chips the player has to put in for the smallest legal raise, None if they may not raise
+    pub fn min_raise_amount(&self, player_id: Uuid) -> Option<u32> {
+        let me = self.players.iter().find(|p| p.id == player_id)?;
+        Some(self.min_raise_to(player_id)?.saturating_sub(me.bet))
+    }
+
+    /// Smallest total bet a raise by the player may go to
+    pub fn min_raise_to(&self, player_id: Uuid) -> Option<u32> {
+        if self.raise_closed_for.contains(&player_id) {
+            return None;
+        }
+        // the server works out the limits for the player in turn
+        if self.current_player == Some(player_id) {
+            return self.raise_limits.map(|limits| limits.min);
+        }
+        Some(min_raise_to(self.max_bet(), self.min_raise))
+    }
+
+    /// Largest total bet the player in turn may raise to, their stack in no limit
+    pub fn max_raise_to(&self, player_id: Uuid) -> Option<u32> {
+        if self.current_player != Some(player_id) {
+            return None;
+        }
+        self.raise_limits.map(|limits| limits.max)
+    }
+
     pub fn max_bet(&self) -> u32 {
         self.players
             .iter()
@@ -57,6 +254,25 @@ impl SharedGameState {
             .unwrap_or_default()
     }
 
+    /// This state without player names, to go into bug reports
+    pub fn redacted(mut self) -> Self {
+        let names: HashMap<Uuid, String> = self
+            .players
+            .iter_mut()
+            .map(|p| {
+                p.name = format!("Seat {}", p.seat);
+                (p.id, p.name.clone())
+            })
+            .collect();
+        self.recent_actions.iter_mut().for_each(|logged| {
+            logged.player_name = names
+                .get(&logged.player_id)
+                .cloned()
+                .unwrap_or_else(|| "Former player".to_string());
+        });
+        self
+    }
+
     pub fn filled_state_for_test() -> Self {
         let player_id = Uuid::from_str("a3853c6f-58d6-4872-a8ac-17257e330603").unwrap();
         Self {
@@ -65,6 +281,7 @@ impl SharedGameState {
                 PlayerState {
                     id: player_id,
                     name: "Yew Jung".to_string(),
+                    avatar: Avatar::default(),
                     chips: 500,
                     bet: 10,
                     has_folded: false,
@@ -73,10 +290,16 @@ impl SharedGameState {
                     eval: None,
                     is_connected: true,
                     last_action: Some(Action::Check),
+                    seat: 0,
+                    is_sitting_out: false,
+                    dark_action: None,
+                    fold_reason: None,
+                    pending_top_up: 0,
                 },
                 PlayerState {
                     id: Uuid::new_v4(),
                     name: "John Doe".to_string(),
+                    avatar: Avatar::default(),
                     chips: 1000,
                     bet: 20,
                     has_folded: false,
@@ -85,10 +308,16 @@ impl SharedGameState {
                     eval: None,
                     is_connected: true,
                     last_action: None,
+                    seat: 1,
+                    is_sitting_out: false,
+                    dark_action: None,
+                    fold_reason: None,
+                    pending_top_up: 0,
                 },
                 PlayerState {
                     id: Uuid::new_v4(),
                     name: "Jane Doe".to_string(),
+                    avatar: Avatar::default(),
                     chips: 1000,
                     bet: 20,
                     has_folded: true,
@@ -97,6 +326,11 @@ impl SharedGameState {
                     eval: None,
                     is_connected: false,
                     last_action: None,
+                    seat: 2,
+                    is_sitting_out: false,
+                    dark_action: None,
+                    fold_reason: None,
+                    pending_top_up: 0,
                 },
             ],
             community_cards: vec![
@@ -108,11 +342,82 @@ impl SharedGameState {
             pots: vec![1000, 2000],
             stage: Stage::Flop,
             current_player: Some(player_id),
+            last_action_seq: 0,
+            turn_deadline: None,
+            showdown_deadline: None,
+            min_raise: 10,
+            raise_closed_for: HashSet::new(),
+            recent_actions: vec![],
+            personal: None,
+            is_scripted: false,
+            hand_started_at: None,
+            street_started_at: None,
+            hands_per_hour: None,
+            equity: HashMap::new(),
+            hand_number: 1,
+            dealer_seat: Some(0),
+            pre_actions: HashMap::new(),
+            betting: BettingStructure::NoLimit,
+            max_players: 5,
+            raise_limits: Some(RaiseLimits { min: 20, max: 510 }),
+            legal_actions: vec![],
+            deck_commitment: None,
+            revealed_deal: None,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+fn seconds_until(deadline: DateTime<Utc>) -> i64 {
+    (deadline - Utc::now()).num_seconds().max(0)
+}
+
+fn format_elapsed(since: DateTime<Utc>) -> String {
+    let seconds = (Utc::now() - since).num_seconds().max(0);
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Sent to a player rejoining a room while a hand is in progress
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+pub struct ResumeSummary {
+    pub actions: Vec<LoggedAction>,
+    // None when the player is not dealt into the current hand
+    pub to_call: Option<u32>,
+}
+
+impl ResumeSummary {
+    pub fn to_call_line(&self) -> Line<'static> {
+        match self.to_call {
+            Some(0) => "Nothing to call".into(),
+            Some(amount) => format!("To call: {}", amount).into(),
+            None => "You will be dealt in next hand".into(),
+        }
+    }
+}
+
+/// Table chat message, as relayed to everyone in the room
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ChatMessage {
+    pub sender_id: Uuid,
+    pub sender_name: String,
+    pub message: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+impl ChatMessage {
+    pub fn line(&self) -> Line<'static> {
+        Line::from(vec![
+            self.sent_at
+                .with_timezone(&Local)
+                .format("%H:%M ")
+                .to_string()
+                .dark_gray(),
+            format!("{}: ", self.sender_name).bold(),
+            self.message.clone().into(),
+        ])
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 pub struct Timestamped<T> {
     pub timestamp: DateTime<Utc>,
     pub data: T,
@@ -136,10 +441,12 @@ impl From<[Card; 2]> for PlayerHand {
         PlayerHand([Some(SerdeCard(a)), Some(SerdeCard(b))])
     }
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct PlayerState {
     pub id: Uuid,
     pub name: String,
+    #[serde(default)]
+    pub avatar: Avatar,
     pub chips: u32,
     pub bet: u32,
     pub has_folded: bool,
@@ -148,6 +455,17 @@ pub struct PlayerState {
     pub eval: Option<String>,
     pub is_connected: bool,
     pub last_action: Option<Action>,
+    pub seat: usize,
+    #[serde(default)]
+    pub is_sitting_out: bool,
+    // declared for a street not dealt yet, the table sees it before it is played
+    #[serde(default)]
+    pub dark_action: Option<DarkAction>,
+    #[serde(default)]
+    pub fold_reason: Option<FoldReason>,
+    // bought with the balance, added to the stack as the next hand is dealt
+    #[serde(default)]
+    pub pending_top_up: u32,
 }
 
 impl PlayerState {
@@ -160,8 +478,19 @@ impl PlayerState {
     pub fn title_top(&self) -> &str {
         if let Some(eval) = &self.eval {
             eval
+        } else if self.is_sitting_out {
+            "Sitting out"
         } else if self.has_folded {
-            "Folded"
+            match self.fold_reason {
+                Some(FoldReason::Timeout) => "Timed out",
+                Some(FoldReason::Disconnect) => "Left",
+                _ => "Folded",
+            }
+        } else if let Some(dark_action) = self.dark_action {
+            match dark_action {
+                DarkAction::Check => "check (dark)",
+                DarkAction::BetPot => "bet pot (dark)",
+            }
         } else {
             self.last_action.as_ref().map(AsRef::as_ref).unwrap_or("")
         }
@@ -178,7 +507,10 @@ impl PlayerState {
     }
 
     pub fn chips_display(&self) -> Line {
-        format!("Chips: {}", self.chips).into()
+        match self.pending_top_up {
+            0 => format!("Chips: {}", self.chips).into(),
+            top_up => format!("Chips: {} (+{})", self.chips, top_up).into(),
+        }
     }
 
     pub fn bet_display(&self) -> Line {
@@ -186,7 +518,7 @@ impl PlayerState {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub enum HandState {
     Empty,
     Hidden,
@@ -205,9 +537,24 @@ impl HandState {
             HandState::Revealed(hand) => hand.line(),
         }
     }
+
+    /// Like `line`, with the cards in `best_hand` highlighted
+    pub fn line_highlighting(&self, best_hand: &[SerdeCard]) -> Line<'_> {
+        match self {
+            HandState::Revealed(PlayerHand(cards)) => cards
+                .iter()
+                .map(|card| match card {
+                    Some(card) if card.is_in(best_hand) => card.highlighted_span(),
+                    card => PlayerHand::card_span(card),
+                })
+                .collect::<Vec<_>>()
+                .into(),
+            hand => hand.line(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
 pub struct PlayerHand(pub [Option<SerdeCard>; 2]);
 
 impl PlayerHand {
@@ -241,19 +588,101 @@ impl PlayerHand {
     }
 }
 
+/// Hole cards sent to a socket that asked for hand strength hints
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+pub struct DealtHand {
+    pub cards: PlayerHand,
+    #[serde(default)]
+    pub strength: Option<HandStrength>,
+}
+
 impl SharedGameState {
     pub fn from_room(room: Room, reveal_cards: bool) -> Self {
+        // an all-in board is shown a street at a time with the hands face up
+        let running_out = room.is_running_out();
+        let community_cards = room
+            .visible_board()
+            .iter()
+            .copied()
+            .map(SerdeCard)
+            .collect();
+        let equity = room
+            .run_out
+            .as_ref()
+            .filter(|_| running_out)
+            .map(|run_out| run_out.equity.clone())
+            .unwrap_or_default();
+        let dealer_seat = room
+            .players
+            .iter()
+            .find(|p| p.position.is_dealer())
+            .map(|p| p.seat);
+        let raise_limits = room
+            .player_in_turn
+            .and_then(|player_id| room.raise_limits(player_id));
+        let legal_actions = room
+            .player_in_turn
+            .map(|player_id| room.legal_actions(player_id))
+            .unwrap_or_default();
+        let dark_actions = room.dark_actions;
+        // at showdown only the hands the players chose to show are revealed
+        let showdown = room.showdown;
+        // a player whose seat is held is shown as away until they reconnect
+        let awaiting_reconnect = room.awaiting_reconnect;
+        let pending_top_ups = room.pending_top_ups;
         SharedGameState {
             id: room.id,
             players: room
                 .players
                 .into_iter()
-                .map(|p| PlayerState::from_player(p, reveal_cards))
+                .chain(room.players_sitting_out)
+                .map(|p| {
+                    let dark_action = dark_actions.get(&p.id).map(|(_, action)| *action);
+                    let shown = showdown
+                        .as_ref()
+                        .is_some_and(|showdown| showdown.shown.contains(&p.id));
+                    let eval = showdown
+                        .as_ref()
+                        .filter(|_| shown)
+                        .and_then(|showdown| showdown.hands_eval.get(&p.id));
+                    let is_connected = p.is_connected && !awaiting_reconnect.contains_key(&p.id);
+                    // folded hands stay face down while the board runs out
+                    let reveal = reveal_cards || (running_out && !p.has_folded);
+                    PlayerState {
+                        dark_action,
+                        eval: eval.map(|e| e.to_string()),
+                        is_connected,
+                        pending_top_up: pending_top_ups.get(&p.id).copied().unwrap_or_default(),
+                        ..PlayerState::from_player(p, reveal || shown)
+                    }
+                })
+                .sorted_by_key(|p| p.seat)
                 .collect(),
-            community_cards: room.community_cards.into_iter().map(SerdeCard).collect(),
+            community_cards,
             pots: room.pots.iter().map(|p| p.amount).collect(),
             stage: room.stage,
             current_player: room.player_in_turn,
+            last_action_seq: room.action_seq,
+            turn_deadline: room.turn_deadline,
+            showdown_deadline: showdown.as_ref().map(|showdown| showdown.deadline),
+            min_raise: room.last_raise_size,
+            raise_closed_for: room.raise_closed_for,
+            recent_actions: vec![],
+            personal: None,
+            is_scripted: room.scenario.is_some(),
+            hand_started_at: room.hand_started_at,
+            street_started_at: room.street_started_at,
+            hands_per_hour: room.pace.hands_per_hour(),
+            equity,
+            hand_number: room.hand_number,
+            dealer_seat,
+            pre_actions: room.pre_actions,
+            betting: room.config.betting,
+            max_players: room.config.max_players,
+            raise_limits,
+            legal_actions,
+            deck_commitment: room.deal.map(|deal| deal.commitment),
+            revealed_deal: room.revealed_deal,
         }
     }
 
@@ -270,6 +699,7 @@ impl PlayerState {
         PlayerState {
             id: player.id,
             name: player.name,
+            avatar: player.avatar,
             chips: player.chips,
             bet: player.bet,
             has_folded: player.has_folded,
@@ -282,6 +712,11 @@ impl PlayerState {
             eval: None,
             is_connected: player.is_connected,
             last_action: player.last_action,
+            seat: player.seat,
+            is_sitting_out: player.is_sitting_out,
+            dark_action: None,
+            fold_reason: player.fold_reason,
+            pending_top_up: 0,
         }
     }
 
@@ -304,6 +739,15 @@ impl SerdeCard {
         };
         Span::from(card.to_string()).style(Style::default().fg(fg_color).bg(Color::White))
     }
+
+    /// Marks a card that is part of a winning hand
+    pub fn highlighted_span(&self) -> Span<'_> {
+        self.span().bg(Color::Yellow).bold()
+    }
+
+    pub fn is_in(&self, cards: &[SerdeCard]) -> bool {
+        cards.iter().any(|card| card.0 == self.0)
+    }
 }
 
 impl<'de> Deserialize<'de> for SerdeCard {
@@ -317,6 +761,25 @@ impl<'de> Deserialize<'de> for SerdeCard {
     }
 }
 
+// cards go over the wire as rank and suit, such as "Td"
+impl JsonSchema for SerdeCard {
+    fn schema_name() -> String {
+        "Card".to_string()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            string: Some(Box::new(StringValidation {
+                pattern: Some("^[2-9TJQKA][cdhs]$".to_string()),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 impl Serialize for SerdeCard {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -0,0 +1,224 @@
+use chrono::{DateTime, Utc};
+use poker::EvalClass;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use strum_macros::{AsRefStr, EnumString};
+use uuid::Uuid;
+
+use crate::domain::FoldReason;
+
+/// Category of a five card hand, without the ranks that make it up
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    AsRefStr,
+    EnumString,
+    JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum HandClass {
+    HighCard,
+    Pair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+}
+
+impl HandClass {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HandClass::HighCard => "High card",
+            HandClass::Pair => "Pair",
+            HandClass::TwoPair => "Two pair",
+            HandClass::ThreeOfAKind => "Three of a kind",
+            HandClass::Straight => "Straight",
+            HandClass::Flush => "Flush",
+            HandClass::FullHouse => "Full house",
+            HandClass::FourOfAKind => "Four of a kind",
+            HandClass::StraightFlush => "Straight flush",
+        }
+    }
+}
+
+impl From<EvalClass> for HandClass {
+    fn from(class: EvalClass) -> Self {
+        match class {
+            EvalClass::HighCard { .. } => HandClass::HighCard,
+            EvalClass::Pair { .. } => HandClass::Pair,
+            EvalClass::TwoPair { .. } => HandClass::TwoPair,
+            EvalClass::ThreeOfAKind { .. } => HandClass::ThreeOfAKind,
+            EvalClass::Straight { .. } => HandClass::Straight,
+            EvalClass::Flush { .. } => HandClass::Flush,
+            EvalClass::FullHouse { .. } => HandClass::FullHouse,
+            EvalClass::FourOfAKind { .. } => HandClass::FourOfAKind,
+            EvalClass::StraightFlush { .. } => HandClass::StraightFlush,
+        }
+    }
+}
+
+impl TryFrom<String> for HandClass {
+    type Error = strum::ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// One player's hand at a showdown
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShowdownResult {
+    pub player_id: Uuid,
+    pub hand_class: HandClass,
+    // won at least part of a pot
+    pub won: bool,
+}
+
+#[derive(Debug, Clone, FromRow, PartialEq, Serialize, Deserialize)]
+pub struct HandClassStats {
+    #[sqlx(try_from = "String")]
+    pub hand_class: HandClass,
+    pub showdowns: i64,
+    pub wins: i64,
+}
+
+impl HandClassStats {
+    pub fn win_rate(&self) -> f64 {
+        if self.showdowns == 0 {
+            0.0
+        } else {
+            self.wins as f64 / self.showdowns as f64
+        }
+    }
+}
+
+/// How one hand went for a player, added to their lifetime stats
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandOutcome {
+    pub player_id: Uuid,
+    // won at least part of a pot
+    pub won: bool,
+    pub net_chips: i64,
+    // voluntarily put chips in pre-flop
+    pub vpip: bool,
+    pub showdown: bool,
+    // net chips the player's equity was worth, when all in before the board was complete
+    pub all_in_ev: Option<i64>,
+    // set when the player folded without choosing to
+    pub fold_reason: Option<FoldReason>,
+}
+
+#[derive(Debug, Clone, Default, FromRow, PartialEq, Serialize, Deserialize)]
+pub struct PlayerStats {
+    pub hands_played: i64,
+    pub hands_won: i64,
+    pub net_winnings: i64,
+    pub vpip_hands: i64,
+    pub showdowns: i64,
+    // hands all in before the board was complete, what they were worth and what they paid
+    #[serde(default)]
+    pub all_in_hands: i64,
+    #[serde(default)]
+    pub all_in_ev: i64,
+    #[serde(default)]
+    pub all_in_net: i64,
+    // folds played by the turn timer, and folds from leaving mid hand
+    #[serde(default)]
+    pub timeout_folds: i64,
+    #[serde(default)]
+    pub disconnect_folds: i64,
+}
+
+impl PlayerStats {
+    fn share(&self, hands: i64) -> f64 {
+        if self.hands_played == 0 {
+            0.0
+        } else {
+            hands as f64 / self.hands_played as f64
+        }
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        self.share(self.hands_won)
+    }
+
+    pub fn vpip(&self) -> f64 {
+        self.share(self.vpip_hands)
+    }
+
+    pub fn showdown_rate(&self) -> f64 {
+        self.share(self.showdowns)
+    }
+
+    /// Net winnings as if every all-in had paid out its expected value
+    pub fn luck_adjusted_winnings(&self) -> i64 {
+        self.net_winnings - self.all_in_net + self.all_in_ev
+    }
+
+    /// How much more the all-ins paid than they were worth, negative when running bad
+    pub fn luck(&self) -> i64 {
+        self.all_in_net - self.all_in_ev
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfileStats {
+    #[serde(default)]
+    pub lifetime: PlayerStats,
+    // strongest class first
+    pub hand_classes: Vec<HandClassStats>,
+}
+
+/// The stretch of time a leaderboard sums winnings over, days and weeks start at midnight UTC
+#[derive(
+    Debug, Copy, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize, AsRefStr, EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum LeaderboardPeriod {
+    Daily,
+    // starting on Monday
+    Weekly,
+    #[default]
+    AllTime,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct LeaderboardQuery {
+    #[serde(default)]
+    pub period: LeaderboardPeriod,
+}
+
+#[derive(Debug, Clone, FromRow, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    // players with the same winnings share a rank
+    pub rank: i64,
+    pub user_id: Uuid,
+    pub name: String,
+    pub net_winnings: i64,
+    pub hands_played: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub period: LeaderboardPeriod,
+    // None for all time
+    pub since: Option<DateTime<Utc>>,
+    // best first
+    pub entries: Vec<LeaderboardEntry>,
+    // when it was last summed, it is cached for a minute
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeSet;
+
+use eyre::{ContextCompat, Result};
+use itertools::Itertools;
+use poker::{Card, Evaluator, Rank};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::stats::HandClass;
+
+/// A card to come that would improve the hand to a straight or a flush
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Draw {
+    Flush,
+    // two or more ranks complete the straight
+    OpenEndedStraight,
+    // a single rank completes the straight
+    Gutshot,
+}
+
+impl Draw {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Draw::Flush => "flush draw",
+            Draw::OpenEndedStraight => "open-ended straight draw",
+            Draw::Gutshot => "gutshot",
+        }
+    }
+}
+
+/// What a player's hole cards make with the community cards dealt so far
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct HandStrength {
+    pub class: HandClass,
+    #[serde(default)]
+    pub draws: Vec<Draw>,
+}
+
+impl HandStrength {
+    /// Draws are only looked for while cards are still to come, and must use a hole card
+    pub fn of(evaluator: &Evaluator, hole: [Card; 2], board: &[Card]) -> Result<Self> {
+        // five cards are needed to evaluate, before the flop there is a pair or nothing
+        if board.is_empty() {
+            let class = if hole[0].rank() == hole[1].rank() {
+                HandClass::Pair
+            } else {
+                HandClass::HighCard
+            };
+            return Ok(Self {
+                class,
+                draws: vec![],
+            });
+        }
+        let cards: Vec<Card> = hole.iter().chain(board).copied().collect();
+        let class: HandClass = evaluator.evaluate(&cards)?.class().into();
+        let mut draws = vec![];
+        if board.len() < 5 {
+            if class < HandClass::Flush && has_flush_draw(hole, &cards) {
+                draws.push(Draw::Flush);
+            }
+            if class < HandClass::Straight {
+                match straight_outs(hole, &cards) {
+                    0 => {}
+                    1 => draws.push(Draw::Gutshot),
+                    _ => draws.push(Draw::OpenEndedStraight),
+                }
+            }
+        }
+        Ok(Self { class, draws })
+    }
+
+    pub fn line(&self) -> String {
+        let draws = self.draws.iter().map(Draw::label);
+        std::iter::once(self.class.label())
+            .chain(draws)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// The five of `cards` that make the hand they evaluate to
+pub fn best_five(evaluator: &Evaluator, cards: &[Card]) -> Result<Vec<Card>> {
+    let best = evaluator.evaluate(cards)?;
+    if cards.len() <= 5 {
+        return Ok(cards.to_vec());
+    }
+    cards
+        .iter()
+        .copied()
+        .combinations(5)
+        .find(|five| {
+            evaluator
+                .evaluate(five)
+                .is_ok_and(|eval| eval.is_equal_to(best))
+        })
+        .wrap_err("No five cards make the hand")
+}
+
+fn has_flush_draw(hole: [Card; 2], cards: &[Card]) -> bool {
+    hole.iter()
+        .any(|h| cards.iter().filter(|c| c.suit() == h.suit()).count() == 4)
+}
+
+// ace plays both high and low
+fn rank_values(card: &Card) -> Vec<u8> {
+    match card.rank() {
+        Rank::Ace => vec![1, 14],
+        rank => vec![rank as u8 + 2],
+    }
+}
+
+// ranks not held yet that would make a straight using at least one hole card
+fn straight_outs(hole: [Card; 2], cards: &[Card]) -> usize {
+    let held: BTreeSet<u8> = cards.iter().flat_map(rank_values).collect();
+    let hole: BTreeSet<u8> = hole.iter().flat_map(rank_values).collect();
+    (2..=14u8)
+        .filter(|out| !held.contains(out))
+        .filter(|&out| {
+            let out_values = if out == 14 { vec![1, 14] } else { vec![out] };
+            (1..=10u8).any(|low| {
+                let window = low..low + 5;
+                window.clone().any(|v| out_values.contains(&v))
+                    && window.clone().any(|v| hole.contains(&v))
+                    && window
+                        .clone()
+                        .all(|v| held.contains(&v) || out_values.contains(&v))
+            })
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use poker::{card, cards};
+
+    use super::*;
+
+    fn strength(hole: &str, board: &str) -> Result<HandStrength> {
+        let hole: Vec<Card> = Card::parse_to_iter(hole.split_whitespace()).try_collect()?;
+        let board: Vec<Card> = Card::parse_to_iter(board.split_whitespace()).try_collect()?;
+        HandStrength::of(&Evaluator::new(), [hole[0], hole[1]], &board)
+    }
+
+    #[test]
+    fn preflop_strength_should_only_tell_pairs_apart() -> Result<()> {
+        let hole = [card!("Qs")?, card!("Qd")?];
+        let pair = HandStrength::of(&Evaluator::new(), hole, &[])?;
+        assert_eq!(pair.class, HandClass::Pair);
+        assert!(pair.draws.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn strength_should_name_the_made_hand_and_its_draws() -> Result<()> {
+        let flush_draw = strength("Ah Kh", "2h 7h Ks")?;
+        assert_eq!(flush_draw.class, HandClass::Pair);
+        assert_eq!(flush_draw.draws, vec![Draw::Flush]);
+        assert_eq!(flush_draw.line(), "Pair, flush draw");
+
+        let open_ended = strength("9c 8d", "7s 6h 2c")?;
+        assert_eq!(open_ended.draws, vec![Draw::OpenEndedStraight]);
+
+        let gutshot = strength("9c 8d", "6s 5h Kc")?;
+        assert_eq!(gutshot.draws, vec![Draw::Gutshot]);
+
+        let wheel_gutshot = strength("Ac 2d", "3s 4h Kc")?;
+        assert_eq!(wheel_gutshot.draws, vec![Draw::Gutshot]);
+        Ok(())
+    }
+
+    #[test]
+    fn best_five_should_pick_the_cards_that_make_the_hand() -> Result<()> {
+        let cards: Vec<Card> = cards!("Ah Kh 2h 7h 9h Ks 3c").try_collect()?;
+        let best: HashSet<Card> = best_five(&Evaluator::new(), &cards)?.into_iter().collect();
+        let flush: HashSet<Card> = cards!("Ah Kh 2h 7h 9h").try_collect()?;
+        assert_eq!(best, flush);
+        Ok(())
+    }
+
+    #[test]
+    fn strength_should_not_draw_on_the_river_or_on_the_board_alone() -> Result<()> {
+        let river = strength("Ah Kh", "2h 7h Ks 3c 9d")?;
+        assert!(river.draws.is_empty());
+
+        let board_draw = strength("2c 2d", "9h 8s 7c 6d")?;
+        assert_eq!(board_draw.class, HandClass::Pair);
+        assert!(board_draw.draws.is_empty());
+        Ok(())
+    }
+}
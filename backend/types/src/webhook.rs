@@ -0,0 +1,53 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::room::Winnings;
+
+/// Something that happened in a room, as posted to its webhooks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RoomEvent {
+    HandCompleted {
+        hand_id: Uuid,
+        results: Vec<Winnings>,
+    },
+    PlayerJoined {
+        player_id: Uuid,
+        name: String,
+    },
+    PlayerLeft {
+        player_id: Uuid,
+    },
+    // sent every hundred hands completed in the room
+    Milestone {
+        hands_played: u64,
+    },
+}
+
+/// Body of a webhook POST, signed with the webhook's secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    // the same for every retry, so receivers can drop duplicates
+    pub id: Uuid,
+    pub room_id: Uuid,
+    pub event: RoomEvent,
+    pub sent_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterWebhookRequest {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, FromRow, PartialEq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: Uuid,
+    // None for server wide webhooks, which receive events from every room
+    pub room_id: Option<Uuid>,
+    pub url: String,
+    // key for the signature header, only shown to whoever registered the webhook
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,41 @@
+//! Headless bot that sits at a table and plays with one of the bundled strategies.
+//!
+//! ```text
+//! POKER_BOT_EMAIL=bot@example.com POKER_BOT_PASSWORD=secret123 \
+//!     bot --room <room id> [--strategy tight-passive|loose-aggressive] [--buy-in 100]
+//! ```
+use client::bot::Bot;
+use client::client::Client;
+use eyre::{ContextCompat, Result};
+use types::bot::strategy_by_name;
+use types::cli::arg_value;
+use types::domain::LoginRequest;
+use uuid::Uuid;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let room_id: Uuid = arg_value(&args, "--room")
+        .wrap_err("Missing --room <room id>")?
+        .parse()?;
+    let strategy_name =
+        arg_value(&args, "--strategy").unwrap_or_else(|| "tight-passive".to_string());
+    let strategy = strategy_by_name(&strategy_name)
+        .wrap_err_with(|| format!("Unknown strategy {}", strategy_name))?;
+    let buy_in = arg_value(&args, "--buy-in")
+        .map(|buy_in| buy_in.parse())
+        .transpose()?
+        .unwrap_or(100);
+
+    let mut client = Client::new();
+    client
+        .login(LoginRequest {
+            email: std::env::var("POKER_BOT_EMAIL")?,
+            password: std::env::var("POKER_BOT_PASSWORD")?,
+        })
+        .await?;
+    let user = client.get_profile().await?;
+    client.user.replace(user);
+
+    Bot::new(client, strategy).play(room_id, buy_in).await
+}
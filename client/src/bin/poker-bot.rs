@@ -0,0 +1,72 @@
+//! Load test that logs in many players at once and has them play random legal actions,
+//! then reports how long actions took to be applied and the errors seen.
+//!
+//! ```text
+//! POKER_LOAD_EMAIL='loadbot{}@example.com' POKER_LOAD_PASSWORD=secret123 \
+//!     poker-bot --server http://localhost:8080 --users 50 \
+//!     [--rooms <room id>,<room id>] [--buy-in 100] [--think-ms 500] [--duration 60]
+//! ```
+//!
+//! `{}` in the email is replaced by the player's number, from 1. The accounts have to exist
+//! and be verified already. Without `--rooms` the players are spread over every public room.
+use std::time::Duration;
+
+use client::client::Client;
+use client::load::{run, LoadConfig};
+use eyre::{ContextCompat, Result};
+use types::cli::arg_value;
+use types::domain::LoginRequest;
+use uuid::Uuid;
+
+fn parsed_arg<T: std::str::FromStr>(args: &[String], name: &str, default: T) -> Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    Ok(arg_value(args, name)
+        .map(|value| value.parse())
+        .transpose()?
+        .unwrap_or(default))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    // never the public server by default
+    let base_url = arg_value(&args, "--server").wrap_err("Missing --server <url>")?;
+    let mut config = LoadConfig {
+        base_url,
+        email_pattern: std::env::var("POKER_LOAD_EMAIL")?,
+        password: std::env::var("POKER_LOAD_PASSWORD")?,
+        users: parsed_arg(&args, "--users", 10)?,
+        rooms: arg_value(&args, "--rooms")
+            .map(|rooms| rooms.split(',').map(|id| id.trim().parse()).collect())
+            .transpose()?
+            .unwrap_or_default(),
+        buy_in: parsed_arg(&args, "--buy-in", 100)?,
+        think_time: Duration::from_millis(parsed_arg(&args, "--think-ms", 500)?),
+        duration: Duration::from_secs(parsed_arg(&args, "--duration", 60)?),
+    };
+    if config.rooms.is_empty() {
+        config.rooms = public_rooms(&config).await?;
+    }
+
+    let stats = run(config).await?;
+    print!("{}", stats);
+    Ok(())
+}
+
+async fn public_rooms(config: &LoadConfig) -> Result<Vec<Uuid>> {
+    let mut client = Client::with_base_url(config.base_url.as_str());
+    client
+        .authenticate(LoginRequest {
+            email: config.email(0),
+            password: config.password.clone(),
+        })
+        .await?;
+    let rooms = client.get_rooms().await?;
+    Ok(rooms
+        .into_iter()
+        .filter(|room| !room.is_private)
+        .map(|room| room.room_id)
+        .collect())
+}
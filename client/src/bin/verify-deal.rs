@@ -0,0 +1,42 @@
+//! Checks a finished hand against the deck commitment the server published before dealing it.
+//!
+//! ```text
+//! POKER_EMAIL=me@example.com POKER_PASSWORD=secret123 \
+//!     verify-deal --room <room id> --hand <hand id> [--server http://localhost:8080]
+//! ```
+use client::client::Client;
+use eyre::{ContextCompat, Result};
+use types::cli::arg_value;
+use types::domain::LoginRequest;
+use uuid::Uuid;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let room_id: Uuid = arg_value(&args, "--room")
+        .wrap_err("Missing --room <room id>")?
+        .parse()?;
+    let hand_id: Uuid = arg_value(&args, "--hand")
+        .wrap_err("Missing --hand <hand id>")?
+        .parse()?;
+
+    let mut client = match arg_value(&args, "--server") {
+        Some(base_url) => Client::with_base_url(base_url),
+        None => Client::new(),
+    };
+    client
+        .login(LoginRequest {
+            email: std::env::var("POKER_EMAIL")?,
+            password: std::env::var("POKER_PASSWORD")?,
+        })
+        .await?;
+
+    let hand = client.get_hand(room_id, hand_id).await?;
+    hand.verify_deal()?;
+    let deal = hand.deal.wrap_err("This hand was not dealt from a seed")?;
+    println!(
+        "Hand {} checks out: seed {} matches commitment {}",
+        deal.hand_number, deal.seed, deal.commitment
+    );
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use eyre::{ContextCompat, Result};
+use log::debug;
+use tokio::time::sleep;
+use types::bot::{BotView, HistoryEntry, LegalActions, Strategy};
+use types::domain::{ActionRequest, JoinGameRequest};
+use types::room::Stage;
+use types::state::SharedGameState;
+use uuid::Uuid;
+
+use crate::client::{Client, CONNECTION_IS_CLOSE, GAME_STATE, HAND_STATE};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Drives a [`Client`] with a [`Strategy`], acting whenever it is the bot's turn.
+pub struct Bot {
+    client: Client,
+    strategy: Box<dyn Strategy>,
+    history: Vec<HistoryEntry>,
+}
+
+impl Bot {
+    pub fn new(client: Client, strategy: Box<dyn Strategy>) -> Self {
+        Self {
+            client,
+            strategy,
+            history: Vec::new(),
+        }
+    }
+
+    pub async fn play(&mut self, room_id: Uuid, buy_in: i64) -> Result<()> {
+        let player_id = self
+            .client
+            .user
+            .as_ref()
+            .map(|u| u.id)
+            .wrap_err("No user")?;
+        self.client
+            .join_game(JoinGameRequest {
+                room_id,
+                buy_in,
+                last_seen_seq: None,
+                password: None,
+                seat: None,
+            })
+            .await?;
+
+        let mut prev_state: Option<SharedGameState> = None;
+        let mut last_acted = None;
+        while !CONNECTION_IS_CLOSE.load(Ordering::Relaxed) {
+            sleep(POLL_INTERVAL).await;
+            let Some(game) = GAME_STATE.read().await.clone() else {
+                continue;
+            };
+            if let Some(prev) = &prev_state {
+                self.record_history(prev, &game.data);
+            }
+            prev_state = Some(game.data.clone());
+
+            // bots never need time to look at the results
+            if game.data.showdown_deadline.is_some() && last_acted != Some(game.timestamp) {
+                self.client.ready(game.data.id).await?;
+                last_acted = Some(game.timestamp);
+                continue;
+            }
+
+            if !game.data.is_player_turn(player_id) || last_acted == Some(game.timestamp) {
+                continue;
+            }
+            let hand = HAND_STATE
+                .read()
+                .await
+                .as_ref()
+                .map(|h| h.data.clone())
+                .unwrap_or_default();
+            let view = BotView {
+                player_id,
+                game: game.data,
+                hand,
+            };
+            let legal = LegalActions::from_view(&view);
+            let decision = self.strategy.decide(&view, &legal, &self.history);
+            let action = if legal.allows(&decision.action) {
+                decision.action
+            } else {
+                debug!(
+                    "{} chose an illegal action {:?}",
+                    self.strategy.name(),
+                    decision.action
+                );
+                legal.check_or_fold()
+            };
+            sleep(decision.think_time).await;
+            self.client
+                .action(ActionRequest {
+                    room_id: view.game.id,
+                    action,
+                })
+                .await?;
+            last_acted = Some(game.timestamp);
+        }
+        Ok(())
+    }
+
+    fn record_history(&mut self, prev: &SharedGameState, curr: &SharedGameState) {
+        if curr.stage == Stage::PreFlop && prev.stage != Stage::PreFlop {
+            self.history.clear();
+        }
+        if prev.current_player == curr.current_player {
+            return;
+        }
+        let acted = prev
+            .current_player
+            .and_then(|player| Some((player, *curr.last_action_by_player(player)?)));
+        if let Some((player, action)) = acted {
+            self.history.push(HistoryEntry {
+                player,
+                stage: prev.stage.clone(),
+                action,
+            });
+        }
+    }
+}
@@ -1,7 +1,9 @@
+use chrono::Local;
 use eyre::{bail, ContextCompat, Result};
 use futures_util::FutureExt;
 use lazy_static::lazy_static;
 use log::debug;
+use rand::Rng;
 use reqwest::Client as ReqwestClient;
 use reqwest::StatusCode;
 use rnglib::{Language, RNG};
@@ -10,20 +12,80 @@ use rust_socketio::asynchronous::ClientBuilder;
 use rust_socketio::{Payload};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::Duration;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::sync::{Mutex, RwLock};
 use tokio::time::sleep;
+use types::crypto::{HandKey, SealedHand, SecretKey};
 use types::domain::*;
-use types::room::Winnings;
-use types::state::{PlayerHand, SharedGameState, Timestamped};
+use types::history::{HandHistory, HandHistoryPage, HistoryQuery};
+use types::protocol::{Feature, Handshake, UpgradeRequired, PROTOCOL_VERSION};
+use types::room::{ActionLogEntry, Outcome, RoomClosing, RoomDetails};
+use types::state::{
+    ChatMessage, DealtHand, PlayerHand, ResumeSummary, SharedGameState, Timestamped,
+};
+use types::stats::{Leaderboard, LeaderboardPeriod, LeaderboardQuery, ProfileStats};
+use types::strength::HandStrength;
+use uuid::Uuid;
+
+use crate::practice::{PracticeTable, PRACTICE_BUY_IN};
+use crate::tutorial::{TutorialTable, TUTORIAL_BUY_IN};
 
 lazy_static! {
     pub static ref GAME_STATE: RwLock<Option<Timestamped<SharedGameState>>> = RwLock::new(None);
     pub static ref HAND_STATE: RwLock<Option<Timestamped<PlayerHand>>> = RwLock::new(None);
-    pub static ref OUTCOME_STATE: RwLock<Option<Timestamped<Vec<Winnings>>>> = RwLock::new(None);
+    // only sent by the server when the game socket asked for it
+    pub static ref HAND_STRENGTH_STATE: RwLock<Option<Timestamped<HandStrength>>> =
+        RwLock::new(None);
+    pub static ref OUTCOME_STATE: RwLock<Option<Timestamped<Outcome>>> = RwLock::new(None);
+    pub static ref ROOM_CLOSING_STATE: RwLock<Option<Timestamped<RoomClosing>>> = RwLock::new(None);
+    pub static ref RESUME_STATE: RwLock<Option<Timestamped<ResumeSummary>>> = RwLock::new(None);
+    // set while the server waits for this player to show or muck a losing hand
+    pub static ref SHOW_OR_MUCK_STATE: RwLock<Option<Timestamped<ShowOrMuckPrompt>>> =
+        RwLock::new(None);
+    // where this player sat down, and whether they wait for the next hand
+    pub static ref SEATED_STATE: RwLock<Option<Timestamped<Seated>>> = RwLock::new(None);
+    // place in the queue for a seat at a full table, until seated or leaving it
+    pub static ref WAITLISTED_STATE: RwLock<Option<Timestamped<Waitlisted>>> = RwLock::new(None);
+    // set when the server removed this player from their table for not acting
+    pub static ref KICKED_STATE: RwLock<Option<Timestamped<Kicked>>> = RwLock::new(None);
+    // what the server agreed to when the game socket was opened
+    pub static ref HANDSHAKE: RwLock<Option<Handshake>> = RwLock::new(None);
+    // table chat of the current room, oldest first
+    pub static ref CHAT_STATE: RwLock<Vec<ChatMessage>> = RwLock::new(Vec::new());
+    // what happened at the table since joining, oldest first
+    pub static ref ACTION_LOG: RwLock<Vec<ActionLogEntry>> = RwLock::new(Vec::new());
+    // last error the server reported for something this client sent over the socket
+    pub static ref SERVICE_ERROR_STATE: RwLock<Option<String>> = RwLock::new(None);
+    // latest server wide announcement, and whether the player dismissed it
+    pub static ref ANNOUNCEMENT_STATE: RwLock<Option<(Announcement, bool)>> = RwLock::new(None);
+    // what the tutorial asks the player to do next
+    pub static ref TUTORIAL_STATE: RwLock<Option<String>> = RwLock::new(None);
+    // set when the game socket closes, cleared once it is opened again
     pub static ref CONNECTION_IS_CLOSE: AtomicBool = AtomicBool::new(false);
+    // room list changes pushed by the server, oldest first
+    pub static ref LOBBY_UPDATES: RwLock<Vec<LobbyUpdate>> = RwLock::new(Vec::new());
+    // friends asking this player to their table, oldest first
+    pub static ref FRIEND_INVITES: RwLock<Vec<FriendInvite>> = RwLock::new(Vec::new());
+    // the room list is polled while the lobby socket is not connected
+    pub static ref LOBBY_IS_LIVE: AtomicBool = AtomicBool::new(false);
+    // socket events sent and received, oldest first, attached to bug reports
+    pub static ref EVENT_LOG: RwLock<VecDeque<String>> = RwLock::new(VecDeque::new());
+}
+
+// the server takes up to this many events with a bug report
+const MAX_LOGGED_EVENTS: usize = 200;
+pub const CLIENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+async fn log_event(event: String) {
+    let mut log = EVENT_LOG.write().await;
+    if log.len() == MAX_LOGGED_EVENTS {
+        log.pop_front();
+    }
+    log.push_back(format!("{} {}", Local::now().format("%H:%M:%S%.3f"), event));
 }
 
 async fn reset_state<T>(state_lock: &RwLock<Option<T>>) {
@@ -43,19 +105,83 @@ pub async fn reset_game_state() {
 
 pub async fn reset_hand_state() {
     reset_state(&HAND_STATE).await;
+    reset_state(&HAND_STRENGTH_STATE).await;
+}
+
+pub async fn reset_seated_state() {
+    reset_state(&SEATED_STATE).await;
+}
+
+pub async fn reset_room_closing_state() {
+    reset_state(&ROOM_CLOSING_STATE).await;
+}
+
+pub async fn reset_waitlisted_state() {
+    reset_state(&WAITLISTED_STATE).await;
+}
+
+pub async fn reset_kicked_state() {
+    reset_state(&KICKED_STATE).await;
+}
+
+pub async fn reset_show_or_muck_state() {
+    reset_state(&SHOW_OR_MUCK_STATE).await;
+}
+
+pub async fn reset_chat_state() {
+    CHAT_STATE.write().await.clear();
+}
+
+pub async fn reset_action_log() {
+    ACTION_LOG.write().await.clear();
+}
+
+/// The prompt of the current tutorial step, if playing the tutorial
+pub async fn tutorial_prompt() -> Option<String> {
+    TUTORIAL_STATE.read().await.clone()
+}
+
+/// Takes the last error the server reported, if any
+pub async fn take_service_error() -> Option<String> {
+    SERVICE_ERROR_STATE.write().await.take()
+}
+
+/// The latest announcement, unless the player dismissed it
+pub async fn announcement() -> Option<Announcement> {
+    match ANNOUNCEMENT_STATE.read().await.as_ref() {
+        Some((announcement, false)) => Some(announcement.clone()),
+        _ => None,
+    }
+}
+
+pub async fn dismiss_announcement() {
+    if let Some((_, dismissed)) = ANNOUNCEMENT_STATE.write().await.as_mut() {
+        *dismissed = true;
+    }
+}
+
+/// Features that can be used with the server, all of them until it says otherwise
+pub async fn negotiated_features() -> Vec<Feature> {
+    match HANDSHAKE.read().await.as_ref() {
+        Some(handshake) => handshake.features.clone(),
+        None => Feature::ALL.to_vec(),
+    }
+}
+
+/// Publishes state produced locally rather than received from the server
+pub(crate) async fn replace_state<T>(state: &RwLock<Option<Timestamped<T>>>, data: T) {
+    state.write().await.replace(Timestamped::new(data));
 }
 
 async fn update_connection_status() {
     // update CONNECTION_IS_CLOSE to true
     CONNECTION_IS_CLOSE.store(true, Ordering::Relaxed);
+    log_event("socket closed".to_string()).await;
 }
 
-async fn update_state<T: for<'a> Deserialize<'a> + Debug>(
-    payload: Payload,
-    state: &RwLock<Option<Timestamped<T>>>,
-) {
-    if let Payload::Text(values) = payload {
-        let states: Vec<Timestamped<T>> = values
+pub(crate) fn first_state<T: for<'a> Deserialize<'a>>(payload: Payload) -> Option<Timestamped<T>> {
+    match payload {
+        Payload::Text(values) => values
             .into_iter()
             .filter_map(|value| match serde_json::from_value(value) {
                 Ok(game_state) => Some(game_state),
@@ -64,19 +190,231 @@ async fn update_state<T: for<'a> Deserialize<'a> + Debug>(
                     None
                 }
             })
-            .collect();
-        if let Some(new_state) = states.into_iter().next() {
-            debug!("New state: {:#?}", new_state);
-            let mut state_lock = state.write().await;
-            if let Some(ref current_state) = *state_lock {
-                new_state.is_newer(current_state).then(|| {
-                    state_lock.replace(new_state);
-                });
-            } else {
-                state_lock.replace(new_state);
+            .next(),
+        _ => None,
+    }
+}
+
+async fn store_state<T: Debug>(
+    event: &str,
+    new_state: Timestamped<T>,
+    state: &RwLock<Option<Timestamped<T>>>,
+) {
+    debug!("New state: {:#?}", new_state);
+    log_event(format!("<- {} sent at {}", event, new_state.timestamp)).await;
+    let mut state_lock = state.write().await;
+    if let Some(ref current_state) = *state_lock {
+        new_state.is_newer(current_state).then(|| {
+            state_lock.replace(new_state);
+        });
+    } else {
+        state_lock.replace(new_state);
+    }
+}
+
+async fn update_state<T: for<'a> Deserialize<'a> + Debug>(
+    event: &str,
+    payload: Payload,
+    state: &RwLock<Option<Timestamped<T>>>,
+) {
+    if let Some(new_state) = first_state(payload) {
+        store_state(event, new_state, state).await;
+    }
+}
+
+/// Hands arrive sealed when this connection registered a key,
+/// and with a strength hint when it asked for one
+async fn update_hand_state(payload: Payload, secret: Option<SecretKey>, hand_strength: bool) {
+    let dealt = match secret {
+        Some(secret) => first_state::<SealedHand>(payload).and_then(|sealed| {
+            open_hand(&sealed.data, &secret, hand_strength)
+                .inspect_err(|e| debug!("Error opening hand: {:?}", e))
+                .ok()
+                .map(|data| Timestamped {
+                    timestamp: sealed.timestamp,
+                    data,
+                })
+        }),
+        None if hand_strength => first_state::<DealtHand>(payload),
+        None => first_state::<PlayerHand>(payload).map(|hand| Timestamped {
+            timestamp: hand.timestamp,
+            data: DealtHand {
+                cards: hand.data,
+                strength: None,
+            },
+        }),
+    };
+    let Some(Timestamped { timestamp, data }) = dealt else {
+        return;
+    };
+    if let Some(strength) = data.strength {
+        let new_state = Timestamped {
+            timestamp,
+            data: strength,
+        };
+        store_state("hand_strength", new_state, &HAND_STRENGTH_STATE).await;
+    }
+    let new_state = Timestamped {
+        timestamp,
+        data: data.cards,
+    };
+    store_state("hand", new_state, &HAND_STATE).await;
+}
+
+fn open_hand(sealed: &SealedHand, secret: &SecretKey, hand_strength: bool) -> Result<DealtHand> {
+    if hand_strength {
+        sealed.open(secret)
+    } else {
+        let cards = sealed.open(secret)?;
+        Ok(DealtHand {
+            cards,
+            strength: None,
+        })
+    }
+}
+
+// only the most recent messages are kept for the chat panel
+const MAX_CHAT_MESSAGES: usize = 200;
+
+async fn update_chat(payload: Payload) {
+    if let Payload::Text(values) = payload {
+        let messages = values
+            .into_iter()
+            .filter_map(|value| serde_json::from_value::<ChatMessage>(value).ok());
+        let mut chat = CHAT_STATE.write().await;
+        chat.extend(messages);
+        let overflow = chat.len().saturating_sub(MAX_CHAT_MESSAGES);
+        chat.drain(..overflow);
+    }
+}
+
+// the action log panel only shows the latest entries
+const MAX_ACTION_LOG_ENTRIES: usize = 200;
+
+async fn update_action_log(payload: Payload) {
+    if let Payload::Text(values) = payload {
+        let entries = values
+            .into_iter()
+            .filter_map(|value| serde_json::from_value::<ActionLogEntry>(value).ok());
+        let mut log = ACTION_LOG.write().await;
+        log.extend(entries);
+        let overflow = log.len().saturating_sub(MAX_ACTION_LOG_ENTRIES);
+        log.drain(..overflow);
+    }
+}
+
+// updates past this are dropped, the lobby fetches the whole list again when it is opened
+const MAX_LOBBY_UPDATES: usize = 256;
+
+async fn update_lobby(payload: Payload) {
+    if let Payload::Text(values) = payload {
+        let updates = values
+            .into_iter()
+            .filter_map(|value| serde_json::from_value::<LobbyUpdate>(value).ok());
+        let mut queue = LOBBY_UPDATES.write().await;
+        queue.extend(updates);
+        let overflow = queue.len().saturating_sub(MAX_LOBBY_UPDATES);
+        queue.drain(..overflow);
+    }
+}
+
+async fn update_friend_invites(payload: Payload) {
+    if let Payload::Text(values) = payload {
+        let invites = values
+            .into_iter()
+            .filter_map(|value| serde_json::from_value::<FriendInvite>(value).ok());
+        FRIEND_INVITES.write().await.extend(invites);
+    }
+}
+
+/// Invites from friends received since the last call, oldest first
+pub async fn take_friend_invites() -> Vec<FriendInvite> {
+    std::mem::take(&mut *FRIEND_INVITES.write().await)
+}
+
+async fn update_lobby_status() {
+    LOBBY_IS_LIVE.store(false, Ordering::Relaxed);
+}
+
+/// Room list changes received since the last call, oldest first
+pub async fn take_lobby_updates() -> Vec<LobbyUpdate> {
+    std::mem::take(&mut *LOBBY_UPDATES.write().await)
+}
+
+pub fn lobby_is_live() -> bool {
+    LOBBY_IS_LIVE.load(Ordering::Relaxed)
+}
+
+async fn update_service_error(payload: Payload) {
+    if let Payload::Text(values) = payload {
+        let message = values.into_iter().find_map(|value| match value {
+            serde_json::Value::String(message) => Some(message),
+            _ => None,
+        });
+        if let Some(message) = message {
+            debug!("Service error: {}", message);
+            log_event(format!("<- service_error: {}", message)).await;
+            SERVICE_ERROR_STATE.write().await.replace(message);
+        }
+    }
+}
+
+async fn update_connection_refused(payload: Payload) {
+    if let Payload::Text(values) = payload {
+        let refused = values
+            .into_iter()
+            .find_map(|value| serde_json::from_value::<ConnectionRefused>(value).ok());
+        if let Some(refused) = refused {
+            let message = format!(
+                "{} (retry in {}s)",
+                refused.reason, refused.retry_after_secs
+            );
+            SERVICE_ERROR_STATE.write().await.replace(message);
+        }
+    }
+}
+
+async fn update_handshake(payload: Payload) {
+    if let Payload::Text(values) = payload {
+        let handshake = values
+            .into_iter()
+            .find_map(|value| serde_json::from_value::<Handshake>(value).ok());
+        if let Some(handshake) = handshake {
+            log_event(format!("<- handshake: {:?}", handshake)).await;
+            HANDSHAKE.write().await.replace(handshake);
+        }
+    }
+}
+
+async fn update_upgrade_required(payload: Payload) {
+    if let Payload::Text(values) = payload {
+        let upgrade = values
+            .into_iter()
+            .find_map(|value| serde_json::from_value::<UpgradeRequired>(value).ok());
+        if let Some(upgrade) = upgrade {
+            let message = format!(
+                "Please upgrade, the server needs protocol {} or newer, this client speaks {}",
+                upgrade.min_version, PROTOCOL_VERSION
+            );
+            SERVICE_ERROR_STATE.write().await.replace(message);
+        }
+    }
+}
+
+// the same announcement is sent on both sockets, so one already held is not shown again
+async fn update_announcement(payload: Payload) {
+    if let Payload::Text(values) = payload {
+        let announcement = values
+            .into_iter()
+            .find_map(|value| serde_json::from_value::<Announcement>(value).ok());
+        if let Some(announcement) = announcement {
+            let mut state = ANNOUNCEMENT_STATE.write().await;
+            if state.as_ref().is_some_and(|(held, _)| held.id == announcement.id) {
+                return;
             }
+            state.replace((announcement, false));
         }
-    };
+    }
 }
 
 #[allow(deprecated)]
@@ -89,17 +427,113 @@ async fn default_callback(payload: Payload) {
     }
 }
 
+// tokens of a logged in user, the access token is renewed shortly before it expires
+struct Session {
+    access_token: String,
+    refresh_token: Uuid,
+    expires_at: Instant,
+}
+
+impl From<TokenPair> for Session {
+    fn from(tokens: TokenPair) -> Self {
+        let lifetime = Duration::from_secs(tokens.expires_in.max(0) as u64);
+        Self {
+            access_token: tokens.access_token,
+            refresh_token: tokens.refresh_token,
+            expires_at: Instant::now() + lifetime.saturating_sub(REFRESH_MARGIN),
+        }
+    }
+}
+
+// how long before the access token expires it is renewed
+const REFRESH_MARGIN: Duration = Duration::from_secs(30);
+
+// a dropped network should fail requests rather than hang the screen
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+// first retry after the server could not be reached, doubled after every failure
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Exponential backoff between retries while the server is unreachable
+#[derive(Debug, Default)]
+pub struct Backoff {
+    failures: u32,
+}
+
+impl Backoff {
+    /// Records a failure and returns how long to wait before the next try
+    pub fn failed(&mut self) -> Duration {
+        let delay = MIN_RETRY_DELAY
+            .saturating_mul(1 << self.failures.min(5))
+            .min(MAX_RETRY_DELAY);
+        self.failures += 1;
+        // up to a quarter more, so clients cut off together don't all come back at once
+        let jitter = rand::thread_rng().gen_range(0..=delay.as_millis() as u64 / 4);
+        delay + Duration::from_millis(jitter)
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    pub fn reset(&mut self) {
+        self.failures = 0;
+    }
+
+    pub fn is_failing(&self) -> bool {
+        self.failures > 0
+    }
+}
+
+/// Whether a request failed because the server could not be reached,
+/// as opposed to the server answering with an error
+pub fn is_offline(error: &eyre::Report) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|e| e.is_connect() || e.is_timeout() || e.is_request())
+}
+
+/// State of the game socket, shown to the player while it is down
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConnectionStatus {
+    Connected,
+    Reconnecting { attempt: u32, retry_in: Duration },
+}
+
 pub struct Client {
     pub client: ReqwestClient,
     pub ws_client: Option<SocketClient>,
-    pub token: Option<String>,
+    lobby_ws_client: Option<SocketClient>,
+    session: Mutex<Option<Session>>,
     pub user: Option<User>,
     generator: RNG,
+    // room and sequence number of the last action seen before leaving it
+    last_seen_action: Option<(Uuid, u64)>,
+    // actions are sent to an in-process table instead of the server in practice mode
+    practice: Option<UnboundedSender<Action>>,
+    // asks the server to seal hole cards to a key generated for each connection
+    pub encrypt_hands: bool,
+    // asks the server to send what the hole cards make with the board along with them
+    pub hand_strength: bool,
+    // server every request and socket goes to
+    base_url: String,
+    // room to sit back down at once a closed socket is opened again
+    rejoin: Option<JoinGameRequest>,
+    reconnect_backoff: Backoff,
+    next_reconnect: Option<Instant>,
+}
+
+const ENCRYPT_HANDS_ENV: &str = "POKER_ENCRYPT_HANDS";
+const HAND_STRENGTH_ENV: &str = "POKER_HAND_STRENGTH";
+
+// opt-in switches, off unless set to something other than 0
+fn flag_from_env(name: &str) -> bool {
+    std::env::var(name).is_ok_and(|value| value != "0" && !value.is_empty())
 }
 
 // const BASE_URL: &str = "http://yj-api-poker.ragib.cloudns.org:8080";
 // const BASE_URL: &str = "https://yj-api-poker.apps.bancuh.net";
-const BASE_URL: &str = "https://poker.yewjung.com";
+pub const DEFAULT_SERVER_URL: &str = "https://poker.yewjung.com";
 
 impl Default for Client {
     fn default() -> Self {
@@ -109,54 +543,193 @@ impl Default for Client {
 
 impl Client {
     pub fn new() -> Self {
+        Self::with_base_url(DEFAULT_SERVER_URL)
+    }
+
+    /// Client of another server than the public one, e.g. one started by a test
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client: ReqwestClient::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
             ws_client: None,
-            token: None,
+            lobby_ws_client: None,
+            session: Mutex::new(None),
             user: None,
             generator: RNG::from(&Language::Roman),
+            last_seen_action: None,
+            practice: None,
+            encrypt_hands: flag_from_env(ENCRYPT_HANDS_ENV),
+            hand_strength: flag_from_env(HAND_STRENGTH_ENV),
+            base_url: base_url.into(),
+            rejoin: None,
+            reconnect_backoff: Backoff::default(),
+            next_reconnect: None,
         }
     }
 
-    pub async fn new_with_token(token: String) -> Result<Self> {
-        let mut s = Self {
-            client: reqwest::Client::new(),
-            ws_client: None,
-            token: Some(token),
-            user: None,
-            generator: RNG::from(&Language::Roman),
-        };
+    /// Resumes a session on `base_url` from a refresh token saved by an earlier run
+    pub async fn new_with_refresh_token(
+        base_url: impl Into<String>,
+        refresh_token: Uuid,
+    ) -> Result<Self> {
+        let mut s = Self::with_base_url(base_url);
+        let tokens = s.request_refresh(refresh_token).await?;
+        s.session = Mutex::new(Some(tokens.into()));
         let user = s.get_profile().await?;
         s.user.replace(user);
         Ok(s)
     }
 
-    pub async fn signup(&self, request: SignupRequest) -> Result<()> {
-        let url = format!("{}/signup", BASE_URL);
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Points later requests and sockets at another server, before logging in there
+    pub fn set_base_url(&mut self, base_url: impl Into<String>) {
+        self.base_url = base_url.into();
+    }
+
+    pub async fn signup(&self, request: SignupRequest) -> Result<SignupResponse> {
+        let url = format!("{}/signup", self.base_url);
         let response = self.client.post(url).json(&request).send().await?;
         let status = response.status();
         match status {
-            StatusCode::CREATED => Ok(()),
+            StatusCode::CREATED => Ok(response.json().await?),
             _ => bail!(response.text().await?),
         }
     }
 
-    pub async fn login(&mut self, request: LoginRequest) -> Result<String> {
-        let url = format!("{}/login", BASE_URL);
+    pub async fn forgot_password(&self, request: ForgotPasswordRequest) -> Result<()> {
+        let url = format!("{}/password/forgot", self.base_url);
         let response = self.client.post(url).json(&request).send().await?;
         let status = response.status();
-        let token = match status {
-            StatusCode::OK => response.text().await?,
+        match status {
+            StatusCode::ACCEPTED => Ok(()),
             _ => bail!(response.text().await?),
-        };
-        self.token = Some(token.clone());
+        }
+    }
+
+    pub async fn reset_password(&self, request: ResetPasswordRequest) -> Result<()> {
+        let url = format!("{}/password/reset", self.base_url);
+        let response = self.client.post(url).json(&request).send().await?;
+        let status = response.status();
+        match status {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    pub async fn login(&mut self, request: LoginRequest) -> Result<TokenPair> {
+        let tokens = self.authenticate(request).await?;
         self.create_ws_connection().await?;
-        Ok(token)
+        Ok(tokens)
+    }
+
+    /// Logs in over HTTP only, leaving the sockets to the caller
+    pub async fn authenticate(&mut self, request: LoginRequest) -> Result<TokenPair> {
+        let url = format!("{}/login", self.base_url);
+        let response = self.client.post(url).json(&request).send().await?;
+        let status = response.status();
+        let tokens: TokenPair = match status {
+            StatusCode::OK => response.json().await?,
+            _ => bail!(response.text().await?),
+        };
+        self.session.lock().await.replace(tokens.clone().into());
+        Ok(tokens)
+    }
+
+    async fn request_refresh(&self, refresh_token: Uuid) -> Result<TokenPair> {
+        let url = format!("{}/refresh", self.base_url);
+        let response = self
+            .client
+            .post(url)
+            .json(&RefreshRequest { refresh_token })
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    /// Current access token, renewed first when it is about to expire
+    pub(crate) async fn access_token(&self) -> Result<String> {
+        let mut session = self.session.lock().await;
+        let current = session.as_ref().wrap_err("Not logged in")?;
+        if Instant::now() >= current.expires_at {
+            let tokens = self.request_refresh(current.refresh_token).await?;
+            session.replace(tokens.into());
+        }
+        Ok(session
+            .as_ref()
+            .map(|s| s.access_token.clone())
+            .unwrap_or_default())
+    }
+
+    /// The refresh token to save for the next run, it changes every time it is used
+    pub async fn refresh_token(&self) -> Option<Uuid> {
+        self.session.lock().await.as_ref().map(|s| s.refresh_token)
+    }
+
+    /// Ends this device's session on the server and closes the sockets
+    pub async fn logout(&mut self) -> Result<()> {
+        let url = format!("{}/logout", self.base_url);
+        let token = self.access_token().await?;
+        let refresh_token = self.refresh_token().await.wrap_err("Not logged in")?;
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&LogoutRequest { refresh_token })
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::NO_CONTENT => self.forget_session().await,
+            _ => bail!(response.text().await?),
+        }
+        Ok(())
+    }
+
+    /// Ends the sessions of every device this account is logged in on
+    pub async fn logout_everywhere(&mut self) -> Result<()> {
+        let url = format!("{}/sessions", self.base_url);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .delete(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::NO_CONTENT => self.forget_session().await,
+            _ => bail!(response.text().await?),
+        }
+        Ok(())
+    }
+
+    // the server closes the game socket too, dropping it here keeps it from being opened again
+    async fn forget_session(&mut self) {
+        self.session.lock().await.take();
+        self.user = None;
+        self.rejoin = None;
+        for ws_client in [self.ws_client.take(), self.lobby_ws_client.take()]
+            .into_iter()
+            .flatten()
+        {
+            let _ = ws_client.disconnect().await;
+        }
+        reset_game_state().await;
+        reset_hand_state().await;
     }
 
     pub async fn update_profile(&mut self, request: UpdateProfileRequest) -> Result<User> {
-        let url = format!("{}/profile", BASE_URL);
-        let token = self.token.clone().expect("No token");
+        let url = format!("{}/profile", self.base_url);
+        let token = self.access_token().await?;
         let response = self
             .client
             .patch(url)
@@ -178,13 +751,48 @@ impl Client {
         let last_name = self.generator.generate_name();
 
         let username = format!("{} {}", first_name, last_name);
-        let request = UpdateProfileRequest { username };
+        let request = UpdateProfileRequest {
+            username,
+            avatar: None,
+        };
         self.update_profile(request).await
     }
 
     pub async fn get_profile(&self) -> Result<User> {
-        let url = format!("{}/profile", BASE_URL);
-        let token = self.token.clone().expect("No token");
+        let url = format!("{}/profile", self.base_url);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    pub async fn get_profile_stats(&self) -> Result<ProfileStats> {
+        let url = format!("{}/profile/stats", self.base_url);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    pub async fn get_room_details(&self, room_id: Uuid) -> Result<RoomDetails> {
+        let url = format!("{}/rooms/{}", self.base_url, room_id);
+        let token = self.access_token().await?;
         let response = self
             .client
             .get(url)
@@ -199,8 +807,197 @@ impl Client {
     }
 
     pub async fn get_rooms(&self) -> Result<Vec<RoomInfo>> {
-        let url = format!("{}/rooms", BASE_URL);
-        let token = self.token.clone().expect("No token");
+        self.find_rooms(&RoomQuery::default()).await
+    }
+
+    pub async fn find_rooms(&self, query: &RoomQuery) -> Result<Vec<RoomInfo>> {
+        let url = format!("{}/rooms", self.base_url);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .query(query)
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    pub async fn create_room(&self, request: CreateRoomRequest) -> Result<RoomInfo> {
+        let url = format!("{}/rooms", self.base_url);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&request)
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::CREATED => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    /// Claims the daily free chips
+    pub async fn top_up(&self) -> Result<LedgerEntry> {
+        let url = format!("{}/balance/topup", self.base_url);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    pub async fn get_transactions(&self, query: &TransactionQuery) -> Result<TransactionPage> {
+        let url = format!("{}/balance/transactions", self.base_url);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .query(query)
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    /// Hands recently played in a room, newest first
+    pub async fn get_hand_history(
+        &self,
+        room_id: Uuid,
+        query: &HistoryQuery,
+    ) -> Result<HandHistoryPage> {
+        let url = format!("{}/rooms/{}/history", self.base_url, room_id);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .query(query)
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    pub async fn get_hand(&self, room_id: Uuid, hand_id: Uuid) -> Result<HandHistory> {
+        let url = format!("{}/rooms/{}/history/{}", self.base_url, room_id, hand_id);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    pub async fn get_leaderboard(&self, period: LeaderboardPeriod) -> Result<Leaderboard> {
+        let url = format!("{}/leaderboard", self.base_url);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .query(&LeaderboardQuery { period })
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    /// Files a bug report with the table as this client sees it and its recent socket events
+    pub async fn send_feedback(&self, note: Option<String>) -> Result<Uuid> {
+        let client_state = GAME_STATE
+            .read()
+            .await
+            .as_ref()
+            .map(|state| serde_json::to_value(state.data.clone().redacted()))
+            .transpose()?;
+        let request = FeedbackRequest {
+            note,
+            client_version: CLIENT_VERSION.to_string(),
+            client_state,
+            events: EVENT_LOG.read().await.iter().cloned().collect(),
+        };
+        let url = format!("{}/feedback", self.base_url);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&request)
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::CREATED => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    pub async fn create_invite(&self, room_id: Uuid) -> Result<RoomInvite> {
+        let url = format!("{}/rooms/{}/invite", self.base_url, room_id);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::CREATED => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    pub async fn get_invited_room(&self, code: &str) -> Result<RoomInfo> {
+        let url = format!("{}/invites/{}", self.base_url, code);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json().await?),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    pub async fn get_friends(&self) -> Result<Vec<Friend>> {
+        let url = format!("{}/friends", self.base_url);
+        let token = self.access_token().await?;
         let response = self
             .client
             .get(url)
@@ -214,45 +1011,391 @@ impl Client {
         }
     }
 
+    pub async fn request_friend(&self, username: String) -> Result<()> {
+        let url = format!("{}/friends/requests", self.base_url);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&FriendRequest { username })
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::CREATED => Ok(()),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    /// Accepts or declines the friend request of `requester_id`
+    pub async fn answer_friend(&self, requester_id: Uuid, accept: bool) -> Result<()> {
+        let answer = if accept { "accept" } else { "decline" };
+        let url = format!(
+            "{}/friends/requests/{}/{}",
+            self.base_url, requester_id, answer
+        );
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => bail!(response.text().await?),
+        }
+    }
+
+    /// Asks a friend to the room this player sits in
+    pub async fn invite_friend(&self, friend_id: Uuid) -> Result<()> {
+        let url = format!("{}/friends/{}/invite", self.base_url, friend_id);
+        let token = self.access_token().await?;
+        let response = self
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::NO_CONTENT => Ok(()),
+            _ => bail!(response.text().await?),
+        }
+    }
+
     pub async fn create_ws_connection(&mut self) -> Result<()> {
-        let hand_callback = |payload, _| update_state(payload, &HAND_STATE).boxed();
-        let room_callback = |payload, _| update_state(payload, &GAME_STATE).boxed();
-        let outcome_callback = |payload, _| update_state(payload, &OUTCOME_STATE).boxed();
-        let error_callback = |payload, _| default_callback(payload).boxed();
+        // a fresh key pair per connection, the secret never leaves this process
+        let (hand_key, hand_secret) = if self.encrypt_hands {
+            let (key, secret) = HandKey::generate();
+            (Some(key), Some(secret))
+        } else {
+            (None, None)
+        };
+        let hand_strength = self.hand_strength;
+        let hand_callback = move |payload, _| {
+            update_hand_state(payload, hand_secret.clone(), hand_strength).boxed()
+        };
+        let room_callback = |payload, _| update_state("room", payload, &GAME_STATE).boxed();
+        let outcome_callback = |payload, _| update_state("outcome", payload, &OUTCOME_STATE).boxed();
+        let room_closing_callback =
+            |payload, _| update_state("room_closing", payload, &ROOM_CLOSING_STATE).boxed();
+        let resume_callback = |payload, _| update_state("resume", payload, &RESUME_STATE).boxed();
+        let show_or_muck_callback = |payload, _| update_state("show_or_muck", payload, &SHOW_OR_MUCK_STATE).boxed();
+        let seated_callback = |payload, _| update_state("seated", payload, &SEATED_STATE).boxed();
+        let kicked_callback = |payload, _| update_state("kicked", payload, &KICKED_STATE).boxed();
+        let waitlisted_callback = |payload, _| update_state("waitlisted", payload, &WAITLISTED_STATE).boxed();
+        let error_callback = |payload, _| update_service_error(payload).boxed();
+        let refused_callback = |payload, _| update_connection_refused(payload).boxed();
+        let handshake_callback = |payload, _| update_handshake(payload).boxed();
+        let upgrade_callback = |payload, _| update_upgrade_required(payload).boxed();
+        let announcement_callback = |payload, _| update_announcement(payload).boxed();
+        let chat_callback = |payload, _| update_chat(payload).boxed();
+        let action_log_callback = |payload, _| update_action_log(payload).boxed();
         let default_callback = |payload, _| default_callback(payload).boxed();
         let close_callback = |_, _| update_connection_status().boxed();
 
         // Creates a GET request, upgrades and sends it.
-        let token = self.token.clone().expect("No token");
+        let token = self.access_token().await?;
+        let auth = SocketAuth {
+            token,
+            hand_key,
+            hand_strength,
+            protocol_version: PROTOCOL_VERSION,
+            features: Feature::ALL.to_vec(),
+        };
         self.ws_client = Some(
-            ClientBuilder::new(BASE_URL)
+            ClientBuilder::new(self.base_url.as_str())
                 .namespace("/game")
-                .auth(token)
+                .auth(serde_json::to_value(auth)?)
                 .on("hand", hand_callback)
                 .on("room", room_callback)
                 .on("outcome", outcome_callback)
+                .on("room_closing", room_closing_callback)
+                .on("resume", resume_callback)
+                .on("show_or_muck", show_or_muck_callback)
+                .on("seated", seated_callback)
+                .on("kicked", kicked_callback)
+                .on("waitlisted", waitlisted_callback)
+                .on("chat", chat_callback)
+                .on("action_log", action_log_callback)
                 .on("service_error", error_callback)
+                .on("connection_refused", refused_callback)
+                .on("handshake", handshake_callback)
+                .on("upgrade_required", upgrade_callback)
+                .on("announcement", announcement_callback)
                 .on("error", default_callback)
                 .on("close", close_callback)
                 .connect()
                 .await?,
         );
+        CONNECTION_IS_CLOSE.store(false, Ordering::Relaxed);
+        // the lobby falls back to polling when this socket cannot be opened
+        if let Some(lobby) = self.lobby_ws_client.take() {
+            let _ = lobby.disconnect().await;
+        }
+        self.lobby_ws_client = self
+            .connect_lobby()
+            .await
+            .inspect_err(|e| debug!("Failed to connect to the lobby: {:?}", e))
+            .ok();
         Ok(())
     }
 
+    async fn connect_lobby(&self) -> Result<SocketClient> {
+        let lobby_callback = |payload, _| update_lobby(payload).boxed();
+        let invite_callback = |payload, _| update_friend_invites(payload).boxed();
+        let close_callback = |_, _| update_lobby_status().boxed();
+        let announcement_callback = |payload, _| update_announcement(payload).boxed();
+        let token = self.access_token().await?;
+        let auth = SocketAuth {
+            token,
+            hand_key: None,
+            hand_strength: false,
+            protocol_version: PROTOCOL_VERSION,
+            features: vec![],
+        };
+        let socket = ClientBuilder::new(self.base_url.as_str())
+            .namespace("/lobby")
+            .auth(serde_json::to_value(auth)?)
+            .on("lobby", lobby_callback)
+            .on("invite", invite_callback)
+            .on("announcement", announcement_callback)
+            .on("close", close_callback)
+            .connect()
+            .await?;
+        LOBBY_IS_LIVE.store(true, Ordering::Relaxed);
+        Ok(socket)
+    }
+
     pub async fn join_game(&mut self, payload: JoinGameRequest) -> Result<()> {
-        self.emit(ClientEvent::Join, payload).await
+        // errors from an earlier request shouldn't be taken as this join failing
+        take_service_error().await;
+        self.emit(ClientEvent::Join, &payload).await?;
+        self.rejoin = Some(payload);
+        Ok(())
+    }
+
+    pub fn connection_status(&self) -> ConnectionStatus {
+        if !self.is_reconnecting() {
+            return ConnectionStatus::Connected;
+        }
+        ConnectionStatus::Reconnecting {
+            attempt: self.reconnect_backoff.failures() + 1,
+            retry_in: self
+                .next_reconnect
+                .map(|at| at.saturating_duration_since(Instant::now()))
+                .unwrap_or_default(),
+        }
+    }
+
+    // only a socket that was open and closed is opened again, not one never connected
+    fn is_reconnecting(&self) -> bool {
+        self.practice.is_none()
+            && self.ws_client.is_some()
+            && CONNECTION_IS_CLOSE.load(Ordering::Relaxed)
+    }
+
+    /// Opens the game socket again after it closed, with exponential backoff between tries.
+    /// The player sits back down at the room they were in, which sends its state again.
+    pub async fn reconnect_if_closed(&mut self) {
+        if !self.is_reconnecting() || self.next_reconnect.is_some_and(|at| Instant::now() < at) {
+            return;
+        }
+        match self.reconnect().await {
+            Ok(()) => {
+                self.reconnect_backoff.reset();
+                self.next_reconnect = None;
+            }
+            Err(e) => {
+                debug!("Failed to reconnect: {:?}", e);
+                log_event(format!("reconnect failed: {}", e)).await;
+                self.next_reconnect = Some(Instant::now() + self.reconnect_backoff.failed());
+            }
+        }
+    }
+
+    async fn reconnect(&mut self) -> Result<()> {
+        // the server folded the player and cashed out their chips when the socket closed
+        let user_id = self.user.as_ref().map(|u| u.id);
+        let last_seen = GAME_STATE.read().await.as_ref().map(|state| {
+            let chips = state
+                .data
+                .players
+                .iter()
+                .find(|p| Some(p.id) == user_id)
+                .map(|p| p.chips as i64);
+            (state.data.id, state.data.last_action_seq, chips)
+        });
+        self.create_ws_connection().await?;
+        reset_hand_state().await;
+        let Some(rejoin) = self.rejoin.take() else {
+            return Ok(());
+        };
+        let (last_seen_seq, chips) = match last_seen {
+            Some((room_id, seq, chips)) if room_id == rejoin.room_id => (Some(seq), chips),
+            _ => (None, None),
+        };
+        self.join_game(JoinGameRequest {
+            room_id: rejoin.room_id,
+            buy_in: chips.filter(|chips| *chips > 0).unwrap_or(rejoin.buy_in),
+            last_seen_seq,
+            password: rejoin.password,
+            seat: rejoin.seat,
+        })
+        .await
+    }
+
+    /// Starts an offline game against bots, with no account or server connection needed
+    pub async fn start_practice(&mut self, num_of_bots: usize) -> Result<()> {
+        reset_game_state().await;
+        reset_hand_state().await;
+        let user = User {
+            id: Uuid::new_v4(),
+            name: "You".to_string(),
+            balance: PRACTICE_BUY_IN as i64,
+            current_room: None,
+            premium_balance: 0,
+            avatar: Avatar::default(),
+        };
+        let (sender, receiver) = unbounded_channel();
+        let table = PracticeTable::new(&user, num_of_bots, receiver)?;
+        tokio::spawn(table.run());
+        self.user = Some(user);
+        self.practice = Some(sender);
+        Ok(())
+    }
+
+    /// Starts a scripted hand that walks a new player through the basics, played offline
+    pub async fn start_tutorial(&mut self) -> Result<()> {
+        reset_game_state().await;
+        reset_hand_state().await;
+        TUTORIAL_STATE.write().await.take();
+        let user = User {
+            id: Uuid::new_v4(),
+            name: "You".to_string(),
+            balance: TUTORIAL_BUY_IN as i64,
+            current_room: None,
+            premium_balance: 0,
+            avatar: Avatar::default(),
+        };
+        let (sender, receiver) = unbounded_channel();
+        tokio::spawn(TutorialTable::new(&user, receiver).run());
+        self.user = Some(user);
+        // the tutorial is a practice table with a script, so the same restrictions apply
+        self.practice = Some(sender);
+        Ok(())
+    }
+
+    pub fn is_practice(&self) -> bool {
+        self.practice.is_some()
     }
 
     pub async fn action(&mut self, payload: ActionRequest) -> Result<()> {
+        if let Some(practice) = &self.practice {
+            practice.send(payload.action)?;
+            return Ok(());
+        }
         self.emit(ClientEvent::Action, payload).await
     }
 
+    /// Lets the table deal the next hand without waiting out the results display
+    pub async fn ready(&mut self, room_id: Uuid) -> Result<()> {
+        // practice tables move on by themselves
+        if self.practice.is_some() {
+            return Ok(());
+        }
+        self.emit(ClientEvent::Ready, room_id).await
+    }
+
+    pub async fn send_chat(&mut self, room_id: Uuid, message: String) -> Result<()> {
+        if self.practice.is_some() {
+            bail!("Chat isn't available in practice");
+        }
+        self.emit(ClientEvent::Chat, ChatRequest { room_id, message })
+            .await
+    }
+
+    /// Reveals or mucks a hand that lost at showdown
+    pub async fn show_cards(&mut self, room_id: Uuid, show: bool) -> Result<()> {
+        reset_show_or_muck_state().await;
+        self.emit(ClientEvent::ShowCards, ShowCardsRequest { room_id, show })
+            .await
+    }
+
+    /// Declares an action to play blind on the next street
+    pub async fn dark_action(&mut self, room_id: Uuid, action: DarkAction) -> Result<()> {
+        if self.practice.is_some() {
+            bail!("Dark actions aren't available in practice");
+        }
+        self.emit(ClientEvent::DarkAction, DarkActionRequest { room_id, action })
+            .await
+    }
+
+    /// Sets what to play once the turn comes on this street, None takes it back
+    pub async fn pre_action(&mut self, room_id: Uuid, pre_action: Option<PreAction>) -> Result<()> {
+        if self.practice.is_some() {
+            bail!("Pre-actions aren't available in practice");
+        }
+        let request = PreActionRequest {
+            room_id,
+            pre_action,
+        };
+        self.emit(ClientEvent::PreAction, request).await
+    }
+
+    /// Skips hands from the next deal on, keeping the seat and chips
+    pub async fn sit_out(&mut self, room_id: Uuid) -> Result<()> {
+        if self.practice.is_some() {
+            bail!("Sitting out isn't available in practice");
+        }
+        self.emit(ClientEvent::SitOut, room_id).await
+    }
+
+    /// Buys chips from the balance, they are added to the stack as the next hand is dealt
+    pub async fn top_up_at_table(&mut self, room_id: Uuid, amount: u32) -> Result<()> {
+        if self.practice.is_some() {
+            bail!("Adding chips isn't available in practice");
+        }
+        self.emit(ClientEvent::TopUp, TopUpRequest { room_id, amount })
+            .await
+    }
+
+    pub async fn sit_in(&mut self, room_id: Uuid) -> Result<()> {
+        if self.practice.is_some() {
+            bail!("Sitting out isn't available in practice");
+        }
+        self.emit(ClientEvent::SitIn, room_id).await
+    }
+
+    /// Last action seen in the room before leaving it, so rejoining can catch up from there
+    pub fn last_seen_seq(&self, room_id: Uuid) -> Option<u64> {
+        self.last_seen_action
+            .filter(|(room, _)| *room == room_id)
+            .map(|(_, seq)| seq)
+    }
+
     pub async fn leave(&mut self) -> Result<()> {
+        self.last_seen_action = GAME_STATE
+            .read()
+            .await
+            .as_ref()
+            .map(|state| (state.data.id, state.data.last_action_seq));
+        self.rejoin = None;
+        // dropping the sender ends the practice table
+        if self.practice.take().is_some() {
+            TUTORIAL_STATE.write().await.take();
+            self.user = None;
+            return Ok(());
+        }
         self.emit(ClientEvent::Leave, String::default()).await
     }
 
     async fn emit<T: Serialize>(&mut self, event: ClientEvent, payload: T) -> Result<()> {
+        // payloads are left out, they can carry chat messages and room passwords
+        log_event(format!("-> {}", event.as_ref())).await;
         let ws_socket = self.ws_client.as_ref().wrap_err("No socket connection")?;
         ws_socket.emit(event.as_ref(), json!(payload)).await?;
 
@@ -262,7 +1405,7 @@ impl Client {
 
 impl Drop for Client {
     fn drop(&mut self) {
-        if let Some(ws_client) = &self.ws_client {
+        for ws_client in self.ws_client.iter().chain(self.lobby_ws_client.iter()) {
             let client = ws_client.clone();
             tokio::spawn(async move {
                 client
@@ -1 +1,5 @@
+pub mod bot;
 pub mod client;
+pub mod load;
+pub mod practice;
+pub mod tutorial;
@@ -0,0 +1,336 @@
+//! Many players on one process, each with its own game socket, playing random legal actions
+//! to load test the server.
+//!
+//! The other modules keep the table in process-wide state, so players here keep theirs apart.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use eyre::{ensure, Result};
+use futures_util::FutureExt;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rust_socketio::asynchronous::{Client as SocketClient, ClientBuilder};
+use rust_socketio::Payload;
+use serde_json::json;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::{sleep, timeout_at, Instant};
+use types::bot::{BotView, LegalActions};
+use types::domain::{
+    Action, ActionRequest, ClientEvent, JoinGameRequest, LoginRequest, SocketAuth,
+};
+use types::protocol::{Feature, PROTOCOL_VERSION};
+use types::state::{PlayerHand, SharedGameState};
+use uuid::Uuid;
+
+use crate::client::{first_state, Client};
+
+// an action the room has not moved past by then is counted as lost
+const ACTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct LoadConfig {
+    pub base_url: String,
+    /// Email of each player, with `{}` replaced by its number counting from 1
+    pub email_pattern: String,
+    pub password: String,
+    pub users: usize,
+    /// Rooms the players are spread over, in turn
+    pub rooms: Vec<Uuid>,
+    pub buy_in: i64,
+    /// How long a player waits once its turn comes, give or take half of it
+    pub think_time: Duration,
+    pub duration: Duration,
+}
+
+impl LoadConfig {
+    pub fn email(&self, index: usize) -> String {
+        self.email_pattern.replace("{}", &(index + 1).to_string())
+    }
+}
+
+/// What the players saw, added up over all of them
+#[derive(Debug, Default)]
+pub struct LoadStats {
+    pub players: usize,
+    pub connected: usize,
+    pub actions: u64,
+    // from sending an action until the room state moves past it
+    latencies: Vec<Duration>,
+    pub errors: BTreeMap<String, u64>,
+}
+
+impl LoadStats {
+    fn error(&mut self, kind: &str, error: impl fmt::Display) {
+        *self
+            .errors
+            .entry(format!("{}: {:#}", kind, error))
+            .or_default() += 1;
+    }
+
+    fn merge(&mut self, other: LoadStats) {
+        self.connected += other.connected;
+        self.actions += other.actions;
+        self.latencies.extend(other.latencies);
+        for (error, count) in other.errors {
+            *self.errors.entry(error).or_default() += count;
+        }
+    }
+
+    /// Latency under which `percent` of the answered actions came back
+    pub fn latency_percentile(&self, percent: usize) -> Option<Duration> {
+        let mut latencies = self.latencies.clone();
+        latencies.sort_unstable();
+        let last = latencies.len().checked_sub(1)?;
+        latencies
+            .get((latencies.len() * percent / 100).min(last))
+            .copied()
+    }
+}
+
+impl fmt::Display for LoadStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Players connected: {}/{}", self.connected, self.players)?;
+        writeln!(
+            f,
+            "Actions answered: {}/{}",
+            self.latencies.len(),
+            self.actions
+        )?;
+        let percentiles = [50, 95, 99, 100].map(|p| self.latency_percentile(p));
+        if let [Some(p50), Some(p95), Some(p99), Some(max)] = percentiles {
+            writeln!(
+                f,
+                "Action latency: p50 {:?}, p95 {:?}, p99 {:?}, max {:?}",
+                p50, p95, p99, max
+            )?;
+        }
+        writeln!(f, "Errors: {}", self.errors.values().sum::<u64>())?;
+        for (error, count) in &self.errors {
+            writeln!(f, "{:>8}  {}", count, error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Logs every player in, sits them down and plays until `duration` is up
+pub async fn run(config: LoadConfig) -> Result<LoadStats> {
+    ensure!(!config.rooms.is_empty(), "No rooms to play in");
+    let deadline = Instant::now() + config.duration;
+    let config = Arc::new(config);
+    let tasks: Vec<_> = (0..config.users)
+        .map(|index| {
+            let config = config.clone();
+            tokio::spawn(async move { play(&config, index, deadline).await })
+        })
+        .collect();
+    let mut stats = LoadStats {
+        players: config.users,
+        ..LoadStats::default()
+    };
+    for task in tasks {
+        match task.await {
+            Ok(player_stats) => stats.merge(player_stats),
+            Err(e) => stats.error("task", e),
+        }
+    }
+    Ok(stats)
+}
+
+enum PlayerEvent {
+    // boxed, a game state is far bigger than the other events
+    Room(Box<SharedGameState>),
+    Hand(PlayerHand),
+    ServiceError(String),
+    Closed,
+}
+
+async fn play(config: &LoadConfig, index: usize, deadline: Instant) -> LoadStats {
+    let mut stats = LoadStats::default();
+    let (events, receiver) = unbounded_channel();
+    let (socket, player_id) = match connect(config, index, events).await {
+        Ok(connected) => connected,
+        Err(e) => {
+            stats.error("login", e);
+            return stats;
+        }
+    };
+    stats.connected = 1;
+    let join = JoinGameRequest {
+        room_id: config.rooms[index % config.rooms.len()],
+        buy_in: config.buy_in,
+        last_seen_seq: None,
+        password: None,
+        seat: None,
+    };
+    if let Err(e) = socket.emit(ClientEvent::Join.as_ref(), json!(join)).await {
+        stats.error("join", e);
+    } else {
+        let mut player = LoadPlayer {
+            config,
+            socket: socket.clone(),
+            player_id,
+            hand: PlayerHand::default(),
+            pending: None,
+            readied: None,
+            stats,
+        };
+        player.play(receiver, deadline).await;
+        stats = player.stats;
+    }
+    let _ = socket.disconnect().await;
+    stats
+}
+
+async fn connect(
+    config: &LoadConfig,
+    index: usize,
+    events: UnboundedSender<PlayerEvent>,
+) -> Result<(SocketClient, Uuid)> {
+    let mut client = Client::with_base_url(config.base_url.as_str());
+    client
+        .authenticate(LoginRequest {
+            email: config.email(index),
+            password: config.password.clone(),
+        })
+        .await?;
+    let player_id = client.get_profile().await?.id;
+    let auth = SocketAuth {
+        token: client.access_token().await?,
+        hand_key: None,
+        hand_strength: false,
+        protocol_version: PROTOCOL_VERSION,
+        features: Feature::ALL.to_vec(),
+    };
+    let (room, hand, error) = (events.clone(), events.clone(), events.clone());
+    let socket = ClientBuilder::new(config.base_url.as_str())
+        .namespace("/game")
+        .auth(serde_json::to_value(auth)?)
+        .on("room", move |payload, _| {
+            if let Some(state) = first_state(payload) {
+                let _ = room.send(PlayerEvent::Room(Box::new(state.data)));
+            }
+            async {}.boxed()
+        })
+        .on("hand", move |payload, _| {
+            if let Some(state) = first_state(payload) {
+                let _ = hand.send(PlayerEvent::Hand(state.data));
+            }
+            async {}.boxed()
+        })
+        .on("service_error", move |payload, _| {
+            if let Payload::Text(values) = payload {
+                let message = values.into_iter().find_map(|value| match value {
+                    serde_json::Value::String(message) => Some(message),
+                    _ => None,
+                });
+                let _ = error.send(PlayerEvent::ServiceError(message.unwrap_or_default()));
+            }
+            async {}.boxed()
+        })
+        .on("close", move |_, _| {
+            let _ = events.send(PlayerEvent::Closed);
+            async {}.boxed()
+        })
+        .connect()
+        .await?;
+    Ok((socket, player_id))
+}
+
+struct LoadPlayer<'a> {
+    config: &'a LoadConfig,
+    socket: SocketClient,
+    player_id: Uuid,
+    hand: PlayerHand,
+    // sequence number of the room when the last action was sent, and when it was sent
+    pending: Option<(u64, Instant)>,
+    // results already skipped, so the table isn't asked twice
+    readied: Option<DateTime<Utc>>,
+    stats: LoadStats,
+}
+
+impl LoadPlayer<'_> {
+    async fn play(&mut self, mut events: UnboundedReceiver<PlayerEvent>, deadline: Instant) {
+        // the channel closes with the socket, and the time being up ends the run
+        while let Ok(Some(event)) = timeout_at(deadline, events.recv()).await {
+            match event {
+                PlayerEvent::Room(game) => self.on_room(*game).await,
+                PlayerEvent::Hand(hand) => self.hand = hand,
+                PlayerEvent::ServiceError(message) => self.stats.error("service", message),
+                PlayerEvent::Closed => {
+                    self.stats.error("socket", "closed by the server");
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn on_room(&mut self, game: SharedGameState) {
+        if let Some((seq, sent_at)) = self.pending {
+            if game.last_action_seq > seq {
+                self.stats.latencies.push(sent_at.elapsed());
+                self.pending = None;
+            } else if sent_at.elapsed() > ACTION_TIMEOUT {
+                self.stats.error("action", "not applied in time");
+                self.pending = None;
+            }
+        }
+
+        if game.showdown_deadline.is_some() && self.readied != game.showdown_deadline {
+            self.readied = game.showdown_deadline;
+            self.emit(ClientEvent::Ready, json!(game.id)).await;
+            return;
+        }
+
+        if !game.is_player_turn(self.player_id) || self.pending.is_some() {
+            return;
+        }
+        let think_time = {
+            let mut rng = rand::thread_rng();
+            self.config.think_time.mul_f64(rng.gen_range(0.5..1.5))
+        };
+        sleep(think_time).await;
+        let view = BotView {
+            player_id: self.player_id,
+            hand: self.hand.clone(),
+            game,
+        };
+        let action = random_action(&LegalActions::from_view(&view));
+        self.pending = Some((view.game.last_action_seq, Instant::now()));
+        self.stats.actions += 1;
+        let request = ActionRequest {
+            room_id: view.game.id,
+            action,
+        };
+        self.emit(ClientEvent::Action, json!(request)).await;
+    }
+
+    async fn emit(&mut self, event: ClientEvent, payload: serde_json::Value) {
+        if let Err(e) = self.socket.emit(event.as_ref(), payload).await {
+            self.stats.error(event.as_ref(), e);
+        }
+    }
+}
+
+// anything the server should accept, with raises of any legal size
+fn random_action(legal: &LegalActions) -> Action {
+    let mut rng = rand::thread_rng();
+    let mut choices = Vec::new();
+    if legal.can_check {
+        choices.push(Action::Check);
+    } else {
+        choices.push(Action::Fold);
+    }
+    if legal.call.is_some() {
+        choices.push(Action::Call);
+    }
+    if let Some(range) = &legal.raise {
+        choices.push(legal.raise_or_call(rng.gen_range(range.clone())));
+    }
+    if legal.can_all_in {
+        choices.push(Action::AllIn);
+    }
+    choices.choose(&mut rng).copied().unwrap_or(Action::Fold)
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use eyre::{ContextCompat, Result};
+use log::error;
+use poker::Evaluator;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::sleep;
+use types::bot::{BotView, HistoryEntry, LegalActions, LooseAggressive, Strategy, TightPassive};
+use types::domain::{Action, Avatar, ServiceRequiredAction, User};
+use types::room::{GameResult, Hand, Outcome, Player, Room};
+use types::state::{PlayerHand, SharedGameState};
+use uuid::Uuid;
+
+use crate::client::{replace_state, GAME_STATE, HAND_STATE, OUTCOME_STATE, SERVICE_ERROR_STATE};
+
+pub const PRACTICE_BUY_IN: u32 = 500;
+
+/// A table played entirely in-process against bots, publishing into the same
+/// state the socket callbacks fill so the game screen works unchanged.
+pub struct PracticeTable {
+    room: Room,
+    player_id: Uuid,
+    bots: HashMap<Uuid, Box<dyn Strategy>>,
+    evaluator: Evaluator,
+    actions: UnboundedReceiver<Action>,
+    // what the first hand needs, as the table is dealt in as soon as it is set up
+    first_hand: ServiceRequiredAction,
+}
+
+impl PracticeTable {
+    pub fn new(
+        user: &User,
+        num_of_bots: usize,
+        actions: UnboundedReceiver<Action>,
+    ) -> Result<Self> {
+        let mut room = Room::new();
+        let mut player = Player::new(user.name.clone(), PRACTICE_BUY_IN);
+        player.id = user.id;
+        player.avatar = user.avatar;
+        room.players.push(player);
+
+        let mut bots: HashMap<Uuid, Box<dyn Strategy>> = HashMap::new();
+        for i in 0..num_of_bots {
+            // alternate styles so the table has a mix of opponents
+            let strategy: Box<dyn Strategy> = if i % 2 == 0 {
+                Box::new(LooseAggressive::default())
+            } else {
+                Box::new(TightPassive::default())
+            };
+            let mut bot = Player::new(
+                format!("Bot {} ({})", i + 1, strategy.name()),
+                PRACTICE_BUY_IN,
+            );
+            bot.seat = i + 1;
+            bot.avatar = Avatar::Robot;
+            bots.insert(bot.id, strategy);
+            room.players.push(bot);
+        }
+        // everyone is seated up front, so the whole table is dealt into the first hand
+        let first_hand = room.proceed()?;
+
+        Ok(Self {
+            room,
+            player_id: user.id,
+            bots,
+            evaluator: Evaluator::new(),
+            actions,
+            first_hand,
+        })
+    }
+
+    /// Plays hands until the player leaves the table
+    pub async fn run(mut self) {
+        let first_hand = self.first_hand.clone();
+        if let Err(e) = self.service(first_hand).await {
+            error!("Practice table failed to start: {:?}", e);
+            return;
+        }
+        while !self.actions.is_closed() {
+            if let Err(e) = self.next_turn().await {
+                error!("Practice table stopped: {:?}", e);
+                return;
+            }
+        }
+    }
+
+    async fn next_turn(&mut self) -> Result<()> {
+        let Some(player_in_turn) = self.room.player_in_turn else {
+            // the table broke up, wait for the player to leave
+            self.actions.recv().await;
+            return Ok(());
+        };
+        let required = if player_in_turn == self.player_id {
+            let Some(action) = self.actions.recv().await else {
+                return Ok(());
+            };
+            match self.room.take_action(player_in_turn, action) {
+                Ok(required) => required,
+                // invalid input from the player, shown like a server error so they can try again
+                Err(e) => {
+                    SERVICE_ERROR_STATE.write().await.replace(e.to_string());
+                    return Ok(());
+                }
+            }
+        } else {
+            let action = self.bot_action(player_in_turn).await?;
+            self.room.take_action(player_in_turn, action)?
+        };
+        self.service(required).await
+    }
+
+    async fn bot_action(&mut self, bot_id: Uuid) -> Result<Action> {
+        let view = BotView::from_room(&self.room, bot_id);
+        let history = HistoryEntry::from_room(&self.room);
+        let legal = LegalActions::from_view(&view);
+        let strategy = self.bots.get_mut(&bot_id).wrap_err("Bot not found")?;
+        let decision = strategy.decide(&view, &legal, &history);
+        sleep(decision.think_time).await;
+        if legal.allows(&decision.action) {
+            Ok(decision.action)
+        } else {
+            Ok(legal.check_or_fold())
+        }
+    }
+
+    fn hand_of(&self, player_id: Uuid) -> Option<PlayerHand> {
+        self.room
+            .players
+            .iter()
+            .find(|p| p.id == player_id)
+            .and_then(|p| p.hand.as_ref())
+            .map(|Hand(cards)| (*cards).into())
+    }
+
+    // mirrors what the server does for each ServiceRequiredAction
+    async fn service(&mut self, mut required: ServiceRequiredAction) -> Result<()> {
+        loop {
+            if self.actions.is_closed() {
+                return Ok(());
+            }
+            match required {
+                ServiceRequiredAction::NoAction => {
+                    self.publish(SharedGameState::from_room(self.room.clone(), false))
+                        .await;
+                    return Ok(());
+                }
+                ServiceRequiredAction::PlayerReceiveCards => {
+                    self.publish(SharedGameState::from_room(self.room.clone(), false))
+                        .await;
+                    if let Some(hand) = self.hand_of(self.player_id) {
+                        replace_state(&HAND_STATE, hand).await;
+                    }
+                    return Ok(());
+                }
+                ServiceRequiredAction::FindWinners => {
+                    let GameResult {
+                        hands_eval,
+                        winners,
+                    } = self.room.find_winners(&self.evaluator)?;
+                    let is_showdown = !hands_eval.is_empty();
+                    self.publish(
+                        SharedGameState::from_room(self.room.clone(), true)
+                            .with_eval(hands_eval.clone()),
+                    )
+                    .await;
+                    sleep(Duration::from_secs(5)).await;
+
+                    let mut pot_splits = self.room.split_pot(winners)?;
+                    pot_splits.reverse();
+                    for mut winnings in pot_splits {
+                        if is_showdown {
+                            self.room.show_best_hands(&self.evaluator, &mut winnings)?;
+                        }
+                        let outcome = self.room.outcome(&hands_eval, winnings);
+                        replace_state(&OUTCOME_STATE, outcome).await;
+                        sleep(Duration::from_secs(3)).await;
+                    }
+                    replace_state(&OUTCOME_STATE, Outcome::default()).await;
+                    required = self.room.proceed()?;
+                }
+            }
+        }
+    }
+
+    async fn publish(&self, game_state: SharedGameState) {
+        if !self.actions.is_closed() {
+            replace_state(&GAME_STATE, game_state.personalized(self.player_id)).await;
+        }
+    }
+}
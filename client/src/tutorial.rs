@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use eyre::{ContextCompat, Result};
+use log::error;
+use poker::{Card, Evaluator, Rank, Suit};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::sleep;
+use types::domain::{Action, Avatar, ServiceRequiredAction, User};
+use types::room::{GameResult, Hand, Player, Room, Stage, Winnings};
+use types::state::SharedGameState;
+use uuid::Uuid;
+
+use crate::client::{
+    replace_state, GAME_STATE, HAND_STATE, OUTCOME_STATE, SERVICE_ERROR_STATE, TUTORIAL_STATE,
+};
+
+pub const TUTORIAL_BUY_IN: u32 = 100;
+const COACH_THINK_TIME: Duration = Duration::from_secs(1);
+
+const YOUR_CARDS: [Card; 2] = [
+    Card::new(Rank::Ace, Suit::Hearts),
+    Card::new(Rank::King, Suit::Hearts),
+];
+const COACH_CARDS: [Card; 2] = [
+    Card::new(Rank::Nine, Suit::Clubs),
+    Card::new(Rank::Nine, Suit::Diamonds),
+];
+const BOARD: [Card; 5] = [
+    Card::new(Rank::King, Suit::Diamonds),
+    Card::new(Rank::Seven, Suit::Spades),
+    Card::new(Rank::Two, Suit::Clubs),
+    Card::new(Rank::King, Suit::Spades),
+    Card::new(Rank::Four, Suit::Hearts),
+];
+
+/// What the player is asked to do on a street of the scripted hand
+struct TutorialStep {
+    prompt: &'static str,
+    expected: Option<Action>,
+}
+
+impl TutorialStep {
+    fn for_stage(stage: &Stage) -> Option<Self> {
+        let step = match stage {
+            Stage::NotEnoughPlayers => return None,
+            Stage::PreFlop => TutorialStep {
+                prompt: "Blinds: before any cards are seen, the two players after the dealer \
+                    post forced bets. You posted the small blind of 1 and the coach the big \
+                    blind of 2. Match the big blind: select Call and press Enter.",
+                expected: Some(Action::Call),
+            },
+            Stage::Flop => TutorialStep {
+                prompt: "The flop: three shared cards everyone can use. Nobody has bet yet, \
+                    so you can check and pass the action on for free. Select Check.",
+                expected: Some(Action::Check),
+            },
+            Stage::Turn => TutorialStep {
+                prompt: "The turn brings a second king, giving you three of a kind. Bet to \
+                    build the pot: select Bet, type a total of at least 2 and press Enter.",
+                expected: Some(Action::Bet(0)),
+            },
+            Stage::River => TutorialStep {
+                prompt: "The river is the last card. The coach checked to you, check back \
+                    to go to the showdown.",
+                expected: Some(Action::Check),
+            },
+            Stage::Showdown(_) => TutorialStep {
+                prompt: "Showdown: the best five card hand wins the pot, and your three kings \
+                    beat the coach's nines. That's the basics, press Esc to leave the tutorial.",
+                expected: None,
+            },
+        };
+        Some(step)
+    }
+
+    fn allows(&self, action: &Action) -> bool {
+        match (&self.expected, action) {
+            // any bet amount will do, the engine checks it is a legal one
+            (Some(Action::Bet(_)), Action::Bet(_)) => true,
+            (Some(expected), action) => expected == action,
+            (None, _) => false,
+        }
+    }
+}
+
+/// A scripted heads-up hand against a coach that always checks or calls, dealt with
+/// fixed cards so every prompt matches the table. Publishes into the same state as
+/// practice tables, so the game screen works unchanged.
+pub struct TutorialTable {
+    room: Room,
+    player_id: Uuid,
+    coach_id: Uuid,
+    evaluator: Evaluator,
+    actions: UnboundedReceiver<Action>,
+}
+
+impl TutorialTable {
+    pub fn new(user: &User, actions: UnboundedReceiver<Action>) -> Self {
+        let mut room = Room::new();
+        let mut player = Player::new(user.name.clone(), TUTORIAL_BUY_IN);
+        player.id = user.id;
+        player.avatar = user.avatar;
+        let mut coach = Player::new("Coach".to_string(), TUTORIAL_BUY_IN);
+        coach.seat = 1;
+        coach.avatar = Avatar::Owl;
+        let coach_id = coach.id;
+        room.players = vec![player, coach];
+        Self {
+            room,
+            player_id: user.id,
+            coach_id,
+            evaluator: Evaluator::new(),
+            actions,
+        }
+    }
+
+    /// Plays the scripted hand until the player leaves
+    pub async fn run(mut self) {
+        if let Err(e) = self.play().await {
+            error!("Tutorial stopped: {:?}", e);
+        }
+    }
+
+    async fn play(&mut self) -> Result<()> {
+        // the player is the dealer and small blind, so they act first pre-flop
+        let mut required = self.room.proceed()?;
+        loop {
+            self.rig_cards();
+            self.publish(&required).await?;
+            if required == ServiceRequiredAction::FindWinners {
+                // the hand is over, wait for the player to leave
+                while self.actions.recv().await.is_some() {}
+                return Ok(());
+            }
+            let player_in_turn = self.room.player_in_turn.wrap_err("Nobody in turn")?;
+            required = if player_in_turn == self.coach_id {
+                sleep(COACH_THINK_TIME).await;
+                let action = if self.room.timeout_action(self.coach_id) == Action::Check {
+                    Action::Check
+                } else {
+                    Action::Call
+                };
+                self.room.take_action(self.coach_id, action)?
+            } else {
+                let Some(action) = self.actions.recv().await else {
+                    return Ok(());
+                };
+                let step = TutorialStep::for_stage(&self.room.stage).wrap_err("No step")?;
+                if !step.allows(&action) {
+                    let hint = "Not quite, follow the tutorial prompt above the table";
+                    SERVICE_ERROR_STATE.write().await.replace(hint.to_string());
+                    continue;
+                }
+                match self.room.take_action(self.player_id, action) {
+                    Ok(required) => required,
+                    Err(e) => {
+                        SERVICE_ERROR_STATE.write().await.replace(e.to_string());
+                        continue;
+                    }
+                }
+            };
+        }
+    }
+
+    // swaps whatever the deck dealt for the scripted cards
+    fn rig_cards(&mut self) {
+        for player in self.room.players.iter_mut().filter(|p| p.hand.is_some()) {
+            let cards = if player.id == self.player_id {
+                YOUR_CARDS
+            } else {
+                COACH_CARDS
+            };
+            player.hand = Some(Hand(cards));
+        }
+        let dealt = self.room.community_cards.len();
+        self.room.community_cards = BOARD[..dealt].to_vec();
+    }
+
+    async fn publish(&mut self, required: &ServiceRequiredAction) -> Result<()> {
+        if let Some(step) = TutorialStep::for_stage(&self.room.stage) {
+            TUTORIAL_STATE.write().await.replace(step.prompt.to_string());
+        }
+        if *required != ServiceRequiredAction::FindWinners {
+            let state = SharedGameState::from_room(self.room.clone(), false);
+            replace_state(&GAME_STATE, state.personalized(self.player_id)).await;
+            replace_state(&HAND_STATE, YOUR_CARDS.into()).await;
+            return Ok(());
+        }
+        let GameResult {
+            hands_eval,
+            winners,
+        } = self.room.find_winners(&self.evaluator)?;
+        let is_showdown = !hands_eval.is_empty();
+        let state =
+            SharedGameState::from_room(self.room.clone(), true).with_eval(hands_eval.clone());
+        replace_state(&GAME_STATE, state.personalized(self.player_id)).await;
+        let winnings: Vec<Winnings> = self.room.split_pot(winners)?.into_iter().flatten().collect();
+        let mut winnings = Winnings::merge(winnings);
+        if is_showdown {
+            self.room.show_best_hands(&self.evaluator, &mut winnings)?;
+        }
+        let outcome = self.room.outcome(&hands_eval, winnings);
+        replace_state(&OUTCOME_STATE, outcome).await;
+        Ok(())
+    }
+}
@@ -1,14 +1,27 @@
-use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro::{TokenStream, TokenTree};
+use quote::{format_ident, quote};
 use std::path::PathBuf;
 use std::{env, fs};
 
+/// `generate_image_lookup!()` embeds `text_assets/*.txt` behind `lookup_image`.
+/// `generate_image_lookup!(avatars)` embeds `text_assets/avatars/*.txt` behind `lookup_avatars`.
 #[proc_macro]
-pub fn generate_image_lookup(_input: TokenStream) -> TokenStream {
-    let path = env::var("CARGO_MANIFEST_DIR")
+pub fn generate_image_lookup(input: TokenStream) -> TokenStream {
+    let sub_dir = input.into_iter().find_map(|token| match token {
+        TokenTree::Ident(ident) => Some(ident.to_string()),
+        _ => None,
+    });
+    let mut path = env::var("CARGO_MANIFEST_DIR")
         .map(PathBuf::from)
         .map(|path| path.join("text_assets"))
         .expect("Missing `CARGO_MANIFEST_DIR`");
+    if let Some(sub_dir) = &sub_dir {
+        path = path.join(sub_dir);
+    }
+    let fn_name = match &sub_dir {
+        Some(sub_dir) => format_ident!("lookup_{}", sub_dir),
+        None => format_ident!("lookup_image"),
+    };
     let mut entries = Vec::new();
 
     if let Ok(dir) = fs::read_dir(&path) {
@@ -26,7 +39,7 @@ pub fn generate_image_lookup(_input: TokenStream) -> TokenStream {
     }
 
     let expanded = quote! {
-        pub fn lookup_image(key: &str) -> Option<&'static str> {
+        pub fn #fn_name(key: &str) -> Option<&'static str> {
             match key {
                 #(#entries)*
                 _ => None,
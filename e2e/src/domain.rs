@@ -8,6 +8,8 @@ use crate::util::register_user;
 
 pub struct TestUser {
     pub client: Client,
+    // signed up with util::PASSWORD
+    pub email: String,
 }
 
 impl TestUser {
@@ -1,6 +1,8 @@
 #[cfg(test)]
 mod domain;
 #[cfg(test)]
+mod harness;
+#[cfg(test)]
 mod tests;
 #[cfg(test)]
 mod util;
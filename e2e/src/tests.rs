@@ -10,10 +10,13 @@ use uuid::Uuid;
 
 use client::client::Client;
 use types::domain::{
-    JoinGameRequest, LoginRequest, RoomInfo, SignupRequest, UpdateProfileRequest, User,
+    Avatar, CreateRoomRequest, JoinGameRequest, LoginRequest, RoomInfo, SignupRequest,
+    UpdateProfileRequest, User,
 };
+use types::room::Stage;
 
 use crate::domain::TestUser;
+use crate::harness::TestServer;
 use crate::util;
 
 lazy_static! {
@@ -61,6 +64,7 @@ async fn test_signup_and_login() -> Result<()> {
     // update profile
     let update_profile_request = UpdateProfileRequest {
         username: "new_username".to_string(),
+        avatar: Some(Avatar::Cat),
     };
     let user = client
         .update_profile(update_profile_request)
@@ -74,6 +78,8 @@ async fn test_signup_and_login() -> Result<()> {
             name: "new_username".to_string(),
             balance: 1000,
             current_room: None,
+            premium_balance: 0,
+            avatar: Avatar::Cat,
         }
     );
 
@@ -86,6 +92,8 @@ async fn test_signup_and_login() -> Result<()> {
             name: "new_username".to_string(),
             balance: 1000,
             current_room: None,
+            premium_balance: 0,
+            avatar: Avatar::Cat,
         }
     );
     Ok(())
@@ -103,6 +111,9 @@ async fn test_join_game() -> Result<()> {
         .join_game(JoinGameRequest {
             room_id,
             buy_in: 100,
+            last_seen_seq: None,
+            password: None,
+            seat: None,
         })
         .await?;
     sleep(Duration::from_secs(1)).await;
@@ -126,6 +137,9 @@ async fn test_join_game() -> Result<()> {
         .join_game(JoinGameRequest {
             room_id,
             buy_in: 100,
+            last_seen_seq: None,
+            password: None,
+            seat: None,
         })
         .await?;
     sleep(Duration::from_secs(1)).await;
@@ -144,6 +158,51 @@ async fn test_join_game() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_join_private_room() -> Result<()> {
+    let mut user = TestUser::new().await?;
+    let room = user
+        .client
+        .create_room(CreateRoomRequest {
+            password: Some("secret".to_string()),
+            ..Default::default()
+        })
+        .await?;
+    assert!(room.is_private);
+    let player_count = |rooms: Vec<RoomInfo>| {
+        rooms
+            .iter()
+            .find(|r| r.room_id == room.room_id)
+            .map(|r| r.player_count)
+    };
+
+    // wrong password is rejected
+    user.client
+        .join_game(JoinGameRequest {
+            room_id: room.room_id,
+            buy_in: 100,
+            last_seen_seq: None,
+            password: Some("wrong".to_string()),
+            seat: None,
+        })
+        .await?;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(player_count(user.client.get_rooms().await?), Some(0));
+
+    user.client
+        .join_game(JoinGameRequest {
+            room_id: room.room_id,
+            buy_in: 100,
+            last_seen_seq: None,
+            password: Some("secret".to_string()),
+            seat: None,
+        })
+        .await?;
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(player_count(user.client.get_rooms().await?), Some(1));
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_2_players_join_game() -> Result<()> {
     let mut user1 = TestUser::new().await?;
@@ -160,6 +219,9 @@ async fn test_2_players_join_game() -> Result<()> {
         .join_game(JoinGameRequest {
             room_id,
             buy_in: 100,
+            last_seen_seq: None,
+            password: None,
+            seat: None,
         })
         .await?;
     sleep(Duration::from_secs(1)).await;
@@ -169,6 +231,9 @@ async fn test_2_players_join_game() -> Result<()> {
         .join_game(JoinGameRequest {
             room_id,
             buy_in: 100,
+            last_seen_seq: None,
+            password: None,
+            seat: None,
         })
         .await?;
     sleep(Duration::from_secs(1)).await;
@@ -182,13 +247,125 @@ async fn test_2_players_join_game() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_hand_is_played_to_showdown_in_process() -> Result<()> {
+    let server = TestServer::start().await?;
+    let mut users = vec![server.user().await?, server.user().await?];
+    let room = users[0]
+        .client
+        .create_room(CreateRoomRequest::default())
+        .await?;
+    for user in users.iter_mut() {
+        user.client.join_game(join_request(room.room_id)).await?;
+    }
+
+    let seen = server.play_hand(room.room_id, &mut users).await?;
+    assert!(matches!(
+        seen.last().map(|state| &state.stage),
+        Some(Stage::Showdown(_))
+    ));
+
+    // chips only moved between the two players, and went back to their balances
+    for user in users.iter_mut() {
+        user.client.leave().await?;
+    }
+    let mut total = 0;
+    for user in users.iter() {
+        total += wait_for_profile(user, |user| user.current_room.is_none())
+            .await?
+            .balance;
+    }
+    assert_eq!(total, 2000);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_actions_stay_in_order_under_latency_in_process() -> Result<()> {
+    let server = TestServer::start().await?;
+    let mut users = vec![server.user().await?, server.user().await?];
+    let room = users[0]
+        .client
+        .create_room(CreateRoomRequest::default())
+        .await?;
+    server.proxy.set_latency(Duration::from_millis(150));
+    for user in users.iter_mut() {
+        user.client.join_game(join_request(room.room_id)).await?;
+    }
+
+    let seen = server.play_hand(room.room_id, &mut users).await?;
+    assert!(seen
+        .windows(2)
+        .all(|pair| pair[0].last_action_seq <= pair[1].last_action_seq));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_player_rejoins_after_connection_is_cut_in_process() -> Result<()> {
+    let server = TestServer::start().await?;
+    let mut user = server.user().await?;
+    let room = user
+        .client
+        .create_room(CreateRoomRequest::default())
+        .await?;
+    user.client.join_game(join_request(room.room_id)).await?;
+    server
+        .wait_for(room.room_id, |state| state.players.len() == 1)
+        .await?;
+
+    // the server takes a disconnected player out of the room and refunds the buy-in
+    server.proxy.disconnect();
+    let profile = wait_for_profile(&user, |user| user.current_room.is_none()).await?;
+    assert_eq!(profile.balance, 1000);
+    server
+        .wait_for(room.room_id, |state| state.players.is_empty())
+        .await?;
+
+    let email = user.email.clone();
+    drop(user);
+    let mut client = Client::with_base_url(&server.url);
+    client
+        .login(LoginRequest {
+            email,
+            password: util::PASSWORD.to_string(),
+        })
+        .await?;
+    client.join_game(join_request(room.room_id)).await?;
+    server
+        .wait_for(room.room_id, |state| state.players.len() == 1)
+        .await?;
+    Ok(())
+}
+
+fn join_request(room_id: Uuid) -> JoinGameRequest {
+    JoinGameRequest {
+        room_id,
+        buy_in: 100,
+        last_seen_seq: None,
+        password: None,
+        seat: None,
+    }
+}
+
+async fn wait_for_profile(user: &TestUser, check: impl Fn(&User) -> bool) -> Result<User> {
+    for _ in 0..100 {
+        let profile = user.client.get_profile().await?;
+        if check(&profile) {
+            return Ok(profile);
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    eyre::bail!("Profile did not reach the expected state")
+}
+
 async fn get_empty_room_id(rooms: Vec<RoomInfo>) -> Uuid {
     let mut already_used = true;
     let mut empty_room_id = Uuid::default();
     while already_used {
         let room_id = rooms
             .iter()
-            .find(|r| !room_map.contains(&r.room_id) && r.player_count == 0)
+            .find(|r| {
+                !room_map.contains(&r.room_id) && r.player_count == 0 && !r.is_private
+            })
             .unwrap()
             .room_id;
         empty_room_id = room_id;
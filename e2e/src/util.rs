@@ -8,13 +8,18 @@ use types::domain::{LoginRequest, SignupRequest, UpdateProfileRequest};
 
 use crate::domain::TestUser;
 
+pub const PASSWORD: &str = "password";
+
 pub async fn register_user() -> Result<TestUser> {
-    let mut client = Client::new();
+    register_user_with(Client::new()).await
+}
 
+/// Signs up and logs in a new user with a client of any server
+pub async fn register_user_with(mut client: Client) -> Result<TestUser> {
     let email = random_email();
     let request = SignupRequest {
         email: email.clone(),
-        password: "password".to_string(),
+        password: PASSWORD.to_string(),
     };
     client.signup(request).await?;
 
@@ -22,7 +27,7 @@ pub async fn register_user() -> Result<TestUser> {
     client
         .login(LoginRequest {
             email: email.clone(),
-            password: "password".to_string(),
+            password: PASSWORD.to_string(),
         })
         .await
         .tap_err(|e| println!("Error: {:?}", e))?;
@@ -30,10 +35,11 @@ pub async fn register_user() -> Result<TestUser> {
     let user = client
         .update_profile(UpdateProfileRequest {
             username: "username".to_string(),
+            avatar: None,
         })
         .await?;
 
-    Ok(TestUser { client })
+    Ok(TestUser { client, email })
 }
 
 pub fn random_email() -> String {
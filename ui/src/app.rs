@@ -1,21 +1,28 @@
 use std::default::Default;
-use std::sync::atomic::Ordering;
 use std::time::Duration;
 
 use crate::data::{OnKeyEvent, OnTick, Screen, ScreenChange};
 use crate::game::InGameWidget;
-use crate::lobby::{lobby_screen_data, LobbyWidget};
-use crate::login::LoginScreenWidget;
-use crate::TOKEN_MANAGER;
+use crate::i18n::{t, Text};
+use crate::lobby::{invited_lobby_screen_data, launch_lobby_screen_data, LobbyWidget};
+use crate::login::{LoginScreenData, LoginScreenWidget};
+use crate::notify::TurnNotifier;
+use crate::replay::ReplayWidget;
+use crate::settings::SettingsWidget;
+use crate::theme::Theme;
+use crate::{config, TOKEN_MANAGER};
 use chrono::{DateTime, Utc};
-use client::client::{Client, CONNECTION_IS_CLOSE};
+use client::client::{announcement, dismiss_announcement, Client, ConnectionStatus};
 use color_eyre::Result;
-use crossterm::event::{self, Event, KeyEvent};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::buffer::Buffer;
 use ratatui::layout::{Constraint, Layout, Rect};
-use ratatui::style::{Color, Style};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
 use ratatui::widgets::{Block, Clear, Paragraph, Widget, Wrap};
 use ratatui::{DefaultTerminal, Frame};
+use types::domain::Announcement;
+use uuid::Uuid;
 
 pub struct App {
     /// Is the application running?
@@ -23,6 +30,11 @@ pub struct App {
     client: Client,
     error_message: Option<ErrorMessage>,
     screen: Screen,
+    notifier: TurnNotifier,
+    // colors every screen is drawn with
+    theme: Theme,
+    // shown over every screen until dismissed
+    announcement: Option<Announcement>,
 }
 
 struct ErrorMessage {
@@ -36,20 +48,36 @@ impl ErrorMessage {
     }
 }
 
-fn get_token() -> Result<String> {
-    TOKEN_MANAGER.get_password().map_err(Into::into)
+fn get_refresh_token() -> Result<Uuid> {
+    Ok(TOKEN_MANAGER.get_password()?.parse()?)
+}
+
+// refresh tokens are rotated on every use, so the latest one is kept for the next run
+async fn save_refresh_token(client: &Client) {
+    if let Some(refresh_token) = client.refresh_token().await {
+        let _ = TOKEN_MANAGER.set_password(&refresh_token.to_string());
+    }
 }
 
 impl App {
-    /// Construct a new instance of [`App`].
-    pub async fn new() -> Result<Self> {
-        let token = get_token().ok();
-        // let token = Some("16cdde20-6268-4a91-b2e3-1198513e2460".to_string());
-        let app = match token {
-            Some(token) => {
-                let mut client = Client::new_with_token(token).await?;
+    /// Construct a new instance of [`App`] playing on `server_url`, optionally opening the room
+    /// behind an invite link.
+    pub async fn new(join_code: Option<String>, server_url: String) -> Result<Self> {
+        // an expired or revoked refresh token sends the user back to the login screen
+        let client = match get_refresh_token() {
+            Ok(refresh_token) => Client::new_with_refresh_token(&server_url, refresh_token)
+                .await
+                .ok(),
+            Err(_) => None,
+        };
+        let app = match client {
+            Some(mut client) => {
+                save_refresh_token(&client).await;
                 client.create_ws_connection().await?;
-                let lobby = lobby_screen_data(&mut client).await?;
+                let lobby = match &join_code {
+                    Some(code) => invited_lobby_screen_data(&mut client, code).await?,
+                    None => launch_lobby_screen_data(&mut client).await?,
+                };
                 // let user_id = client
                 //     .user
                 //     .as_ref()
@@ -68,14 +96,20 @@ impl App {
                     client,
                     error_message: None,
                     screen: Screen::Lobby(lobby),
+                    notifier: TurnNotifier::from_env(),
+                    theme: config::theme(),
+                    announcement: None,
                     // screen: Screen::InGame(in_game_data),
                 }
             }
             None => Self {
                 running: true,
-                client: Client::new(),
+                client: Client::with_base_url(&server_url),
                 error_message: None,
-                screen: Screen::Login(Default::default()),
+                screen: Screen::Login(LoginScreenData::new(join_code, &server_url)),
+                notifier: TurnNotifier::from_env(),
+                theme: config::theme(),
+                announcement: None,
             },
         };
         Ok(app)
@@ -85,14 +119,12 @@ impl App {
     pub async fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
         self.running = true;
         while self.running {
-            if CONNECTION_IS_CLOSE.load(Ordering::Relaxed) {
-                self.error_message
-                    .replace("Connection to server lost".to_string().into());
-                self.running = false;
-            }
+            self.client.reconnect_if_closed().await;
             terminal.draw(|frame| self.draw(frame))?;
             self.handle_crossterm_events().await?;
         }
+        self.notifier.update(false);
+        save_refresh_token(&self.client).await;
         Ok(())
     }
 
@@ -102,27 +134,90 @@ impl App {
     /// - <https://docs.rs/ratatui/latest/ratatui/widgets/index.html>
     /// - <https://github.com/ratatui/ratatui/tree/master/examples>
     fn draw(&mut self, frame: &mut Frame) {
+        let theme = self.theme;
         match self.screen {
             Screen::Login(ref mut data) => {
-                frame.render_stateful_widget(LoginScreenWidget, frame.area(), data);
+                frame.render_stateful_widget(LoginScreenWidget { theme }, frame.area(), data);
                 if let Some(pos) = data.cursor_position {
                     frame.set_cursor_position(pos);
                 }
             }
             Screen::Lobby(ref mut data) => {
-                frame.render_stateful_widget(LobbyWidget, frame.area(), data);
+                frame.render_stateful_widget(LobbyWidget { theme }, frame.area(), data);
                 if let Some(pos) = data.cursor_position {
                     frame.set_cursor_position(pos);
                 }
             }
             Screen::InGame(ref mut data) => {
-                frame.render_stateful_widget(InGameWidget, frame.area(), data);
+                frame.render_stateful_widget(InGameWidget { theme }, frame.area(), data);
+                if let Some(pos) = data.cursor_position {
+                    frame.set_cursor_position(pos);
+                }
+            }
+            Screen::Replay(ref mut data) => {
+                frame.render_stateful_widget(ReplayWidget { theme }, frame.area(), data);
+            }
+            Screen::Settings(ref mut data) => {
+                frame.render_stateful_widget(SettingsWidget, frame.area(), data);
             }
         }
 
+        self.render_announcement(frame);
+        self.render_connection_status(frame);
         self.render_error_message(frame);
     }
 
+    fn render_announcement(&self, frame: &mut Frame) {
+        let Some(announcement) = &self.announcement else {
+            return;
+        };
+        // below the connection status line
+        let [_, banner_area, _] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(5),
+            Constraint::Fill(1),
+        ])
+        .areas(frame.area());
+        let [_, banner_area, _] =
+            Layout::horizontal(Constraint::from_ratios([(1, 6), (2, 3), (1, 6)]))
+                .areas(banner_area);
+        frame.render_widget(
+            AnnouncementBanner {
+                message: announcement.message.clone(),
+            },
+            banner_area,
+        );
+    }
+
+    fn render_connection_status(&self, frame: &mut Frame) {
+        let ConnectionStatus::Reconnecting { attempt, retry_in } = self.client.connection_status()
+        else {
+            return;
+        };
+        let message = if retry_in.is_zero() {
+            format!(" Connection lost, reconnecting (attempt {}) ", attempt)
+        } else {
+            format!(
+                " Connection lost, reconnecting in {}s (attempt {}) ",
+                retry_in.as_secs() + 1,
+                attempt
+            )
+        };
+        let [status_area, _] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(frame.area());
+        let [_, status_area, _] = Layout::horizontal([
+            Constraint::Fill(1),
+            Constraint::Length(message.len() as u16),
+            Constraint::Fill(1),
+        ])
+        .areas(status_area);
+        frame.render_widget(Clear, status_area);
+        frame.render_widget(
+            Paragraph::new(message).style(Style::default().fg(Color::Black).bg(Color::Yellow)),
+            status_area,
+        );
+    }
+
     fn render_error_message(&mut self, frame: &mut Frame) {
         if let Some(error_message) = &self.error_message {
             if error_message.is_expired() {
@@ -149,34 +244,69 @@ impl App {
     /// [`event::poll`] function to check if there are any events available with a timeout.
     async fn handle_crossterm_events(&mut self) -> Result<()> {
         if event::poll(Duration::from_millis(16))? {
-            let event = event::read()?;
-            if let Event::Key(key_event) = event {
-                if let Err(e) = self.on_key_event(key_event).await {
-                    self.error_message.replace(e.to_string().into());
+            match event::read()? {
+                Event::Key(key_event) => {
+                    if let Err(e) = self.on_key_event(key_event).await {
+                        self.error_message.replace(e.to_string().into());
+                    }
                 }
+                Event::FocusGained => self.notifier.set_focused(true),
+                Event::FocusLost => self.notifier.set_focused(false),
+                _ => {}
             }
         } else {
             let result = match self.screen {
                 Screen::Login(ref mut data) => data.on_tick(&mut self.client).await,
                 Screen::Lobby(ref mut data) => data.on_tick(&mut self.client).await,
                 Screen::InGame(ref mut data) => data.on_tick(&mut self.client).await,
+                Screen::Replay(ref mut data) => data.on_tick(&mut self.client).await,
+                Screen::Settings(ref mut data) => data.on_tick(&mut self.client).await,
             };
             if let Err(e) = result {
                 // server connection error
                 self.error_message
-                    .replace(format!("Error occurred: {}", e).into());
+                    .replace(format!("{}: {}", t(Text::ErrorOccurred), e).into());
             }
+            // a player queued for a full table moves to it once seated
+            if let Screen::Lobby(ref mut data) = self.screen {
+                match data.take_queued_seat(&mut self.client).await {
+                    Ok(Some(ScreenChange::Switch(screen))) => self.screen = screen,
+                    Ok(_) => {}
+                    Err(e) => {
+                        self.error_message.replace(e.to_string().into());
+                    }
+                }
+            }
+            self.announcement = announcement().await;
+            let in_turn = matches!(&self.screen, Screen::InGame(data) if data.is_in_turn());
+            self.notifier.update(in_turn);
         }
         Ok(())
     }
 
     /// Handles the key events and updates the state of [`App`].
     async fn on_key_event(&mut self, key: KeyEvent) -> Result<()> {
+        // the banner takes its key before any screen does
+        if self.announcement.is_some()
+            && key.kind == KeyEventKind::Press
+            && key.modifiers == KeyModifiers::CONTROL
+            && key.code == KeyCode::Char('d')
+        {
+            dismiss_announcement().await;
+            self.announcement = None;
+            return Ok(());
+        }
         let change = match self.screen {
             Screen::Login(ref mut data) => data.on_key_event(key, &mut self.client).await?,
             Screen::Lobby(ref mut data) => data.on_key_event(key, &mut self.client).await?,
             Screen::InGame(ref mut data) => data.on_key_event(key, &mut self.client).await?,
+            Screen::Replay(ref mut data) => data.on_key_event(key, &mut self.client).await?,
+            Screen::Settings(ref mut data) => data.on_key_event(key, &mut self.client).await?,
         };
+        // choices on the settings screen apply everywhere at once
+        if let Screen::Settings(data) = &self.screen {
+            self.theme = data.theme;
+        }
 
         match change {
             ScreenChange::Switch(screen) => self.screen = screen,
@@ -205,7 +335,7 @@ impl Widget for ErrorPopup {
         Paragraph::new(self.message)
             .block(
                 Block::bordered()
-                    .title("Error occurred")
+                    .title(t(Text::ErrorOccurred))
                     .style(Style::default().fg(Color::Red)),
             )
             .wrap(Wrap { trim: true })
@@ -213,6 +343,31 @@ impl Widget for ErrorPopup {
     }
 }
 
+pub struct AnnouncementBanner {
+    message: String,
+}
+
+impl Widget for AnnouncementBanner {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        Clear.render(area, buf);
+        Paragraph::new(self.message)
+            .block(
+                Block::bordered()
+                    .title(t(Text::Announcement))
+                    .title_bottom(Line::from(vec![
+                        format!("{} ", t(Text::Dismiss)).into(),
+                        "<CTRL + D>".light_blue().bold(),
+                    ]))
+                    .style(Style::default().fg(Color::Cyan)),
+            )
+            .wrap(Wrap { trim: true })
+            .render(area, buf);
+    }
+}
+
 const ERROR_DURATION: Duration = Duration::from_secs(3);
 
 impl From<String> for ErrorMessage {
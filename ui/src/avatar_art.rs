@@ -0,0 +1,11 @@
+use ratatui::text::Text;
+use types::domain::Avatar;
+
+use crate::lookup_avatars;
+
+// every avatar is drawn in this many columns
+pub const AVATAR_WIDTH: u16 = 7;
+
+pub fn avatar_art(avatar: Avatar) -> Option<Text<'static>> {
+    lookup_avatars(avatar.as_ref()).map(|art| Text::raw(art.trim_end_matches('\n')))
+}
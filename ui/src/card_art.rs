@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use ansi_to_tui::IntoText;
+use lazy_static::lazy_static;
+use poker::{deck, Card};
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::style::Style;
+use ratatui::text::Text;
+use ratatui::widgets::Widget;
+
+use crate::lookup_image;
+use crate::theme::{Theme, ThemeName};
+
+// resizing the terminal leaves sizes behind that are not drawn anymore
+const MAX_DRAWN_ART: usize = 512;
+
+lazy_static! {
+    // converting the ansi art is costly, so it is done once per card rather than once per frame
+    static ref CARD_ART: RwLock<HashMap<Card, Text<'static>>> = RwLock::new(HashMap::new());
+    // the art drawn for a theme and card size, so a frame only copies its cells
+    static ref DRAWN_ART: RwLock<HashMap<(Card, ThemeName, u16, u16), Buffer>> =
+        RwLock::new(HashMap::new());
+}
+
+fn convert(card: Card) -> Option<()> {
+    if CARD_ART.read().ok()?.contains_key(&card) {
+        return Some(());
+    }
+    let text = lookup_image(&card.rank_suit_string())?.into_text().ok()?;
+    CARD_ART.write().ok()?.insert(card, text);
+    Some(())
+}
+
+fn draw(card: Card, theme: &Theme, width: u16, height: u16) -> Option<Buffer> {
+    convert(card)?;
+    let art = CARD_ART.read().ok()?;
+    let area = Rect::new(0, 0, width, height);
+    let mut cells = Buffer::empty(area);
+    // the parts of the art without a color of their own take the theme's
+    cells.set_style(area, Style::default().fg(theme.text));
+    art.get(&card)?.render(area, &mut cells);
+    Some(cells)
+}
+
+fn copy(cells: &Buffer, area: Rect, buf: &mut Buffer) {
+    for y in 0..area.height {
+        for x in 0..area.width {
+            if let Some(cell) = buf.cell_mut((area.x + x, area.y + y)) {
+                *cell = cells[(x, y)].clone();
+            }
+        }
+    }
+}
+
+/// Draws the art of the card into the area, false when the card has no art
+pub fn render_card_art(card: Card, theme: &Theme, area: Rect, buf: &mut Buffer) -> bool {
+    let key = (card, theme.name, area.width, area.height);
+    if let Ok(drawn) = DRAWN_ART.read() {
+        if let Some(cells) = drawn.get(&key) {
+            copy(cells, area, buf);
+            return true;
+        }
+    }
+    let Some(cells) = draw(card, theme, area.width, area.height) else {
+        return false;
+    };
+    copy(&cells, area, buf);
+    if let Ok(mut drawn) = DRAWN_ART.write() {
+        if drawn.len() >= MAX_DRAWN_ART {
+            drawn.clear();
+        }
+        drawn.insert(key, cells);
+    }
+    true
+}
+
+/// Converts the art of every card in the deck, so the first frames do not pay for it
+pub fn warm_up() {
+    deck::generate().for_each(|card| {
+        convert(card);
+    });
+}
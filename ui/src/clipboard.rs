@@ -0,0 +1,75 @@
+use std::sync::Mutex;
+
+use arboard::Clipboard;
+use lazy_static::lazy_static;
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::prelude::{Line, Widget};
+use ratatui::style::Stylize;
+use ratatui::widgets::{Block, BorderType, Clear, Paragraph, Wrap};
+
+lazy_static! {
+    // on X11 the copied text is only served while the clipboard handle is alive
+    static ref CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
+}
+
+/// Text the player asked to copy, shown in a popup until dismissed
+#[derive(Debug, Clone)]
+pub struct Copied {
+    pub label: &'static str,
+    pub text: String,
+    // false when there is no clipboard to copy to, e.g. over SSH
+    pub to_clipboard: bool,
+}
+
+/// Places the text on the system clipboard, falling back to only displaying it
+pub fn copy(label: &'static str, text: String) -> Copied {
+    let to_clipboard = set_clipboard_text(&text)
+        .inspect_err(|e| log::warn!("Failed to copy to clipboard: {}", e))
+        .is_ok();
+    Copied {
+        label,
+        text,
+        to_clipboard,
+    }
+}
+
+fn set_clipboard_text(text: &str) -> Result<(), arboard::Error> {
+    let mut clipboard = CLIPBOARD
+        .lock()
+        .map_err(|_| arboard::Error::ClipboardOccupied)?;
+    let clipboard = match clipboard.as_mut() {
+        Some(clipboard) => clipboard,
+        None => clipboard.insert(Clipboard::new()?),
+    };
+    clipboard.set_text(text)
+}
+
+pub fn copied_popup(area: Rect, copied: &Copied, buf: &mut Buffer) {
+    let message = if copied.to_clipboard {
+        format!("{} copied to clipboard", copied.label)
+    } else {
+        format!("Clipboard unavailable, copy the {} from here", copied.label)
+    };
+    let [popup_area] = Layout::vertical([Constraint::Length(6)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Max(60)])
+        .flex(Flex::Center)
+        .areas(popup_area);
+    Clear.render(popup_area, buf);
+    Paragraph::new(vec![
+        Line::from(message),
+        Line::default(),
+        Line::from(copied.text.as_str()).bold().light_yellow(),
+    ])
+    .centered()
+    .wrap(Wrap { trim: true })
+    .block(
+        Block::bordered()
+            .border_type(BorderType::Rounded)
+            .title(Line::from("Copy").centered())
+            .title_bottom(Line::from(vec!["Close ".into(), "<Esc>".red().bold()]).right_aligned()),
+    )
+    .render(popup_area, buf);
+}
@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Parser;
+use client::client::DEFAULT_SERVER_URL;
+use color_eyre::eyre::{ensure, ContextCompat};
+use color_eyre::Result;
+use serde::Deserialize;
+
+use crate::i18n::Language;
+use crate::sound::SoundSettings;
+use crate::theme::{CardBack, Theme, ThemeName};
+
+#[derive(Debug, Parser)]
+#[command(about = "Texas Hold 'Em in the terminal")]
+pub struct Args {
+    /// Invite link or code of a room to open straight away
+    #[arg(long)]
+    pub join: Option<String>,
+    /// Server to play on, instead of the one in the config file or the public one
+    #[arg(long, env = "POKER_SERVER_URL")]
+    pub server: Option<String>,
+}
+
+// ~/.config/poker/config.toml, every setting is optional
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    server_url: Option<String>,
+    // longest streak in the lobby's hand quiz
+    quiz_best: Option<u32>,
+    theme: Option<ThemeName>,
+    card_back: Option<CardBack>,
+    sound: Option<SoundSettings>,
+    language: Option<Language>,
+    pot_odds: Option<bool>,
+}
+
+fn config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("poker").join("config.toml"))
+}
+
+fn read_config() -> ConfigFile {
+    let Some(path) = config_path() else {
+        return ConfigFile::default();
+    };
+    let Ok(content) = fs::read_to_string(&path) else {
+        return ConfigFile::default();
+    };
+    toml::from_str(&content)
+        .inspect_err(|e| log::warn!("Ignoring invalid config {}: {}", path.display(), e))
+        .unwrap_or_default()
+}
+
+/// Server from the command line or `POKER_SERVER_URL`, then the config file, then the public one
+pub fn server_url(args: &Args) -> Result<String> {
+    let server_url = args
+        .server
+        .clone()
+        .or_else(|| read_config().server_url)
+        .unwrap_or_else(|| DEFAULT_SERVER_URL.to_string());
+    normalize_server_url(&server_url)
+}
+
+pub fn normalize_server_url(server_url: &str) -> Result<String> {
+    let server_url = server_url.trim().trim_end_matches('/');
+    ensure!(
+        server_url.starts_with("http://") || server_url.starts_with("https://"),
+        "Server address must start with http:// or https://"
+    );
+    Ok(server_url.to_string())
+}
+
+/// Keeps the server picked on the login screen for the next launch, other settings stay as they are
+pub fn save_server_url(server_url: &str) -> Result<()> {
+    save_setting("server_url", server_url.into())
+}
+
+pub fn quiz_best() -> u32 {
+    read_config().quiz_best.unwrap_or_default()
+}
+
+pub fn save_quiz_best(best: u32) -> Result<()> {
+    save_setting("quiz_best", i64::from(best).into())
+}
+
+/// Theme and card back picked on the settings screen
+pub fn theme() -> Theme {
+    let config = read_config();
+    Theme::new(
+        config.theme.unwrap_or_default(),
+        config.card_back.unwrap_or_default(),
+    )
+}
+
+pub fn save_theme(theme: &Theme) -> Result<()> {
+    save_setting("theme", toml::Value::try_from(theme.name)?)?;
+    save_setting("card_back", toml::Value::try_from(theme.card_back)?)
+}
+
+pub fn sound_settings() -> SoundSettings {
+    read_config().sound.unwrap_or_default()
+}
+
+pub fn save_sound_settings(settings: &SoundSettings) -> Result<()> {
+    save_setting("sound", toml::Value::try_from(settings)?)
+}
+
+pub fn language() -> Language {
+    read_config().language.unwrap_or_default()
+}
+
+pub fn save_language(language: Language) -> Result<()> {
+    save_setting("language", toml::Value::try_from(language)?)
+}
+
+/// Whether the price of a call is shown next to the Call button, on unless turned off
+pub fn show_pot_odds() -> bool {
+    read_config().pot_odds.unwrap_or(true)
+}
+
+pub fn save_show_pot_odds(show: bool) -> Result<()> {
+    save_setting("pot_odds", show.into())
+}
+
+fn save_setting(key: &str, value: toml::Value) -> Result<()> {
+    let path = config_path().wrap_err("No home directory to keep the config in")?;
+    let mut config: toml::Table = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| content.parse().ok())
+        .unwrap_or_default();
+    config.insert(key.to_string(), value);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, toml::to_string(&config)?)?;
+    Ok(())
+}
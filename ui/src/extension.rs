@@ -1,7 +1,11 @@
+use std::rc::Rc;
+
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 
 pub trait Splittable {
     fn split_equal<const N: usize>(area: Rect, direction: Direction) -> [Rect; N];
+    // for when the number of areas is only known at runtime
+    fn split_count(area: Rect, direction: Direction, count: usize) -> Rc<[Rect]>;
 }
 
 impl Splittable for Layout {
@@ -14,4 +18,9 @@ impl Splittable for Layout {
             Direction::Vertical => Self::vertical(Constraint::from_ratios([(1, n); N])).areas(area),
         }
     }
+
+    fn split_count(area: Rect, direction: Direction, count: usize) -> Rc<[Rect]> {
+        let n = count as u32;
+        Self::new(direction, Constraint::from_ratios(vec![(1, n); count])).split(area)
+    }
 }
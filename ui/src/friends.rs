@@ -0,0 +1,219 @@
+use client::client::Client;
+use color_eyre::Result;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::prelude::{Line, Modifier, StatefulWidget, Style, Widget};
+use ratatui::style::Stylize;
+use ratatui::widgets::{Block, BorderType, Cell, Clear, Paragraph, Row, Table, TableState, Wrap};
+use tui_input::backend::crossterm::EventHandler;
+use tui_input::Input;
+use types::domain::{Friend, FriendInvite, FriendStatus};
+
+use crate::theme::Theme;
+
+/// The player's friends and the requests waiting on either side
+#[derive(Debug)]
+pub struct Friends {
+    friends: Vec<Friend>,
+    table_state: TableState,
+    // the name of a player being typed in, to ask them to be friends
+    adding: Option<Input>,
+    // friends can only be invited while the player sits at a table
+    can_invite: bool,
+    // what the last request did
+    message: Option<String>,
+}
+
+impl Friends {
+    pub async fn open(client: &Client, can_invite: bool) -> Result<Self> {
+        Ok(Self {
+            friends: client.get_friends().await?,
+            table_state: TableState::default().with_selected(0),
+            adding: None,
+            can_invite,
+            message: None,
+        })
+    }
+
+    async fn reload(&mut self, client: &Client) -> Result<()> {
+        self.friends = client.get_friends().await?;
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<&Friend> {
+        self.table_state
+            .selected()
+            .and_then(|selected| self.friends.get(selected))
+    }
+
+    /// Returns whether the popup should be closed
+    pub async fn on_key_event(&mut self, key: KeyEvent, client: &Client) -> Result<bool> {
+        if key.kind != KeyEventKind::Press {
+            return Ok(false);
+        }
+        if self.adding.is_some() {
+            self.on_adding_key_event(key, client).await?;
+            return Ok(false);
+        }
+        let selected = self.selected().cloned();
+        match (key.code, selected) {
+            (KeyCode::Esc, _) => return Ok(true),
+            (KeyCode::Down, _) => self.table_state.select_next(),
+            (KeyCode::Up, _) => self.table_state.select_previous(),
+            (KeyCode::Char('a'), _) => self.adding = Some(Input::default()),
+            (KeyCode::Char('y'), Some(friend)) if friend.status == FriendStatus::Incoming => {
+                client.answer_friend(friend.user_id, true).await?;
+                self.message = Some(format!("You and {} are friends now", friend.name));
+                self.reload(client).await?;
+            }
+            (KeyCode::Char('n'), Some(friend)) if friend.status == FriendStatus::Incoming => {
+                client.answer_friend(friend.user_id, false).await?;
+                self.message = Some(format!("Declined {}", friend.name));
+                self.reload(client).await?;
+            }
+            (KeyCode::Char('i'), Some(friend))
+                if self.can_invite && friend.status == FriendStatus::Friend =>
+            {
+                client.invite_friend(friend.user_id).await?;
+                self.message = Some(format!("Invited {} to your table", friend.name));
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    async fn on_adding_key_event(&mut self, key: KeyEvent, client: &Client) -> Result<()> {
+        let Some(input) = &mut self.adding else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc => self.adding = None,
+            KeyCode::Enter => {
+                let username = input.value().trim().to_string();
+                self.adding = None;
+                if username.is_empty() {
+                    return Ok(());
+                }
+                client.request_friend(username.clone()).await?;
+                self.message = Some(format!("Asked {} to be friends", username));
+                self.reload(client).await?;
+            }
+            _ => {
+                input.handle_event(&Event::Key(key));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn status_cell(friend: &Friend, theme: &Theme) -> Cell<'static> {
+    match (friend.status, friend.current_room) {
+        (FriendStatus::Friend, Some(_)) => Cell::from("At a table".green()),
+        (FriendStatus::Friend, None) => Cell::from("Friend"),
+        (FriendStatus::Incoming, _) => Cell::from("Wants to be friends".light_yellow()),
+        (FriendStatus::Outgoing, _) => Cell::from("Request sent".fg(theme.muted)),
+    }
+}
+
+pub fn friends_popup(area: Rect, friends: &mut Friends, theme: &Theme, buf: &mut Buffer) {
+    let [popup_area] = Layout::vertical([Constraint::Percentage(70)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Max(70)])
+        .flex(Flex::Center)
+        .areas(popup_area);
+    Clear.render(popup_area, buf);
+    let mut instructions = vec!["Add ".into(), "<A>".light_blue().bold()];
+    if friends
+        .selected()
+        .is_some_and(|friend| friend.status == FriendStatus::Incoming)
+    {
+        instructions.extend([
+            " Accept ".into(),
+            "<Y>".light_blue().bold(),
+            " Decline ".into(),
+            "<N>".light_blue().bold(),
+        ]);
+    }
+    if friends.can_invite {
+        instructions.extend([" Invite ".into(), "<I>".light_blue().bold()]);
+    }
+    instructions.extend([" Close ".into(), "<Esc>".red().bold()]);
+    let block = Block::bordered()
+        .title(Line::from("Friends").centered())
+        .title_bottom(Line::from(instructions).right_aligned());
+    let inner_area = block.inner(popup_area);
+    block.render(popup_area, buf);
+    let [table_area, status_area] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(inner_area);
+
+    let status = match (&friends.adding, &friends.message) {
+        (Some(input), _) => Line::from(vec![
+            "Their username: ".into(),
+            input.value().to_string().bold(),
+            "_".slow_blink(),
+        ]),
+        (None, Some(message)) => Line::from(message.as_str()).fg(theme.muted),
+        (None, None) => Line::default(),
+    };
+    Paragraph::new(status).render(status_area, buf);
+
+    if friends.friends.is_empty() {
+        Paragraph::new("No friends yet, press A to add one")
+            .centered()
+            .render(table_area, buf);
+        return;
+    }
+    let header = ["Name", "Status"]
+        .into_iter()
+        .map(Cell::from)
+        .collect::<Row>()
+        .bold();
+    let rows = friends
+        .friends
+        .iter()
+        .map(|friend| Row::new([Cell::from(friend.name.clone()), status_cell(friend, theme)]));
+    let table = Table::new(rows, [Constraint::Fill(1), Constraint::Length(20)])
+        .header(header)
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    StatefulWidget::render(table, table_area, buf, &mut friends.table_state);
+}
+
+pub fn friend_invite_popup(area: Rect, invite: &FriendInvite, theme: &Theme, buf: &mut Buffer) {
+    let [popup_area] = Layout::vertical([Constraint::Length(5)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Max(50)])
+        .flex(Flex::Center)
+        .areas(popup_area);
+    Clear.render(popup_area, buf);
+    Paragraph::new(vec![
+        Line::from(vec![
+            invite.from_name.clone().bold(),
+            " invites you to their table".into(),
+        ]),
+        Line::from(format!(
+            "{}/{} seated",
+            invite.room.player_count, invite.room.max_players
+        ))
+        .fg(theme.muted),
+    ])
+    .centered()
+    .wrap(Wrap { trim: true })
+    .block(
+        Block::bordered()
+            .border_type(BorderType::Rounded)
+            .title(Line::from("Invite").centered())
+            .title_bottom(
+                Line::from(vec![
+                    "Join ".into(),
+                    "<Enter>".light_blue().bold(),
+                    " Ignore ".into(),
+                    "<Esc>".red().bold(),
+                ])
+                .right_aligned(),
+            ),
+    )
+    .render(popup_area, buf);
+}
@@ -2,27 +2,52 @@ use std::cmp::PartialEq;
 use std::fmt::Display;
 use std::iter::zip;
 
-use ansi_to_tui::IntoText;
-use client::client::{reset_game_state, reset_hand_state, Client, GAME_STATE, HAND_STATE, OUTCOME_STATE};
+use chrono::{DateTime, TimeDelta, Utc};
+use client::client::{
+    negotiated_features, reset_action_log, reset_chat_state, reset_game_state, reset_hand_state,
+    reset_kicked_state, reset_room_closing_state, reset_seated_state, take_service_error,
+    tutorial_prompt, Client, ACTION_LOG, CHAT_STATE, GAME_STATE, HAND_STATE, HAND_STRENGTH_STATE,
+    KICKED_STATE, OUTCOME_STATE, RESUME_STATE, ROOM_CLOSING_STATE, SEATED_STATE,
+    SHOW_OR_MUCK_STATE,
+};
 use color_eyre::eyre;
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::buffer::Buffer;
-use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
+use ratatui::layout::{Alignment, Constraint, Direction, Flex, Layout, Position, Rect};
 use ratatui::prelude::{Line, Modifier, StatefulWidget, Style, Widget};
 use ratatui::style::{Color, Stylize};
-use ratatui::widgets::{Block, BorderType, Borders, Paragraph};
+use ratatui::widgets::{Block, BorderType, Borders, Clear, Paragraph, Wrap};
 use tap::TapOptional;
 use tui_big_text::{BigText, PixelSize};
 use tui_input::backend::crossterm::EventHandler;
 use tui_input::Input;
-use types::domain::{Action, ActionRequest};
-use types::room::{Stage, Winnings, MAX_NUM_OF_PLAYERS};
-use types::state::{HandState, PlayerHand, PlayerState, SerdeCard, SharedGameState, Timestamped};
+use types::domain::{
+    Action, ActionRequest, DarkAction, FoldReason, Kicked, PreAction, Seated, ShowOrMuckPrompt,
+};
+use types::protocol::Feature;
+use types::room::{ActionLogEntry, Outcome, RoomClosing, Stage};
+use types::rules::{BettingStructure, LegalAction};
+use types::state::{
+    ChatMessage, HandState, PlayerHand, PlayerState, PotOdds, ResumeSummary, SerdeCard,
+    SharedGameState, Timestamped,
+};
+use types::strength::HandStrength;
 use uuid::Uuid;
 
-use crate::data::{highlight, OnKeyEvent, OnTick, ScreenChange, Sound};
+use crate::avatar_art::{avatar_art, AVATAR_WIDTH};
+use crate::clipboard::{copied_popup, copy, Copied};
+use crate::config;
+use crate::data::{OnKeyEvent, OnTick, ScreenChange};
 use crate::extension::Splittable;
-use crate::{lobby, lookup_image};
+use crate::card_art::render_card_art;
+use crate::friends::{friends_popup, Friends};
+use crate::i18n::{t, Text};
+use crate::lobby;
+use crate::login::LoginScreenData;
+use crate::report::{bug_report_popup, BugReport};
+use crate::sound::Sound;
+use crate::theme::Theme;
+use crate::top_up::{top_up_popup, TopUp};
 
 const ACTION_BUTTONS: [InGameFocus; 5] = [
     InGameFocus::Check,
@@ -31,27 +56,52 @@ const ACTION_BUTTONS: [InGameFocus; 5] = [
     InGameFocus::Fold,
     InGameFocus::AllIn,
 ];
+// in the order of `InGameData::pre_action_choices`
+const PRE_ACTION_KEYS: [&str; 3] = ["<CTRL + X>", "<CTRL + L>", "<CTRL + N>"];
+// how long the results of a hand stay over the table, short of the showdown
+const SUMMARY_TIME: TimeDelta = TimeDelta::seconds(5);
 
-pub struct InGameWidget;
+pub struct InGameWidget {
+    pub theme: Theme,
+}
 
 impl StatefulWidget for InGameWidget {
     type State = InGameData;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let theme = self.theme;
         let [community, hands, actions] =
             Layout::vertical(Constraint::from_percentages([70, 15, 15])).areas(area);
-        let outer_community_block = Block::new()
+        let [community, side_panel] =
+            Layout::horizontal(Constraint::from_percentages([75, 25])).areas(community);
+        let [action_log_area, chat_area] =
+            Layout::vertical(Constraint::from_percentages([40, 60])).areas(side_panel);
+        let mut outer_community_block = Block::new()
             .borders(Borders::BOTTOM)
             .border_type(BorderType::Rounded)
             .title_bottom(state.game.stage.line().centered())
             .title_bottom(state.game.pots_line().left_aligned());
+        if state.game.is_scripted {
+            outer_community_block = outer_community_block.title_bottom(
+                Line::from("Scripted deck, not random")
+                    .style(Style::default().fg(Color::Yellow))
+                    .right_aligned(),
+            );
+        }
+        if state.game.hand_number > 0 {
+            outer_community_block = outer_community_block
+                .title_top(Line::from(format!("Hand #{}", state.game.hand_number)).left_aligned());
+        }
+        if let Some(waiting) = waiting_line(state) {
+            outer_community_block = outer_community_block.title_top(waiting);
+        }
         let inner_community_block = outer_community_block.inner(community);
 
         // render outer block for community cards
         outer_community_block.render(community, buf);
 
-        let hand_areas: [_; MAX_NUM_OF_PLAYERS] = Layout::split_equal(hands, Direction::Horizontal);
-        let [_, actions, room_id_area] = Layout::horizontal([
+        let hand_areas = Layout::split_count(hands, Direction::Horizontal, state.game.max_players);
+        let [sit_out_area, actions, room_id_area] = Layout::horizontal([
             Constraint::Fill(1),
             Constraint::Percentage(50),
             Constraint::Fill(1),
@@ -71,8 +121,20 @@ impl StatefulWidget for InGameWidget {
         } else {
             let community_card_areas: [_; 5] =
                 Layout::split_equal(inner_community_block, Direction::Horizontal);
+            // the cards that won stand out while the results are up
+            let best_cards: Vec<SerdeCard> = if state.game.stage.is_showdown() {
+                let best_hands = state
+                    .outcome
+                    .data
+                    .winnings
+                    .iter()
+                    .map(|w| w.best_hand.iter());
+                best_hands.flatten().cloned().collect()
+            } else {
+                vec![]
+            };
             for (card_area, card) in zip(community_card_areas, &state.game.community_cards) {
-                card_paragraph(card_area, card, buf);
+                card_paragraph(card_area, card, card.is_in(&best_cards), &theme, buf);
             }
         }
 
@@ -85,33 +147,406 @@ impl StatefulWidget for InGameWidget {
             }
         }
 
-        for (hand_area, player_state) in zip(hand_areas, &state.game.players) {
-            hand_paragraph(hand_area, player_state, &state.game, &state.winners, buf);
+        // players are placed by seat, so they keep their place as others join and leave
+        for player_state in &state.game.players {
+            if let Some(hand_area) = hand_areas.get(player_state.seat) {
+                let strength = state
+                    .hand_strength
+                    .as_ref()
+                    .filter(|_| player_state.id == state.user_id && !player_state.has_folded);
+                hand_paragraph(
+                    *hand_area,
+                    player_state,
+                    strength,
+                    &state.game,
+                    &state.outcome.data,
+                    &theme,
+                    buf,
+                );
+            }
         }
 
-        action_paragraph(actions, state, buf);
-        room_id(room_id_area, state, buf);
+        action_paragraph(actions, state, &theme, buf);
+        action_log_panel(action_log_area, state, buf);
+        chat_panel(chat_area, state, &theme, buf);
+        let [_, top_up_area, friends_area, bug_report_area, dark_action_area, sit_out_area] =
+            Layout::vertical([
+                Constraint::Fill(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+            ])
+            .areas(sit_out_area);
+        if state.supports(Feature::TopUp) {
+            top_up_hint(top_up_area, buf);
+        }
+        friends_hint(friends_area, buf);
+        bug_report_hint(bug_report_area, buf);
+        if state.supports(Feature::DarkActions) {
+            dark_action_hint(dark_action_area, buf);
+        }
+        sit_out_hint(sit_out_area, state, buf);
+        room_id(room_id_area, state, &theme, buf);
+        if let Some(prompt) = &state.tutorial {
+            tutorial_banner(area, prompt, buf);
+        }
+        if let Some(resume) = &state.resume {
+            resume_popup(area, resume, buf);
+        }
+        if state.summary_until.is_some_and(|until| Utc::now() < until) {
+            hand_summary_popup(area, &state.outcome.data, &theme, buf);
+        }
+        if let Some(prompt) = &state.show_or_muck {
+            show_or_muck_popup(area, prompt, &theme, buf);
+        }
+        if let Some(copied) = &state.copied {
+            copied_popup(area, copied, buf);
+        }
+        if let Some(report) = &state.bug_report {
+            state.cursor_position = bug_report_popup(area, report, buf);
+        }
+        if let Some(friends) = &mut state.friends {
+            friends_popup(area, friends, &theme, buf);
+        }
+        if let Some(top_up) = &state.top_up {
+            state.cursor_position = Some(top_up_popup(area, top_up, buf));
+        }
     }
 }
 
-fn room_id(area: Rect, state: &InGameData, buf: &mut Buffer) {
-    let [_, area] = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+// shown until the player is dealt in after joining mid-hand
+fn waiting_line(state: &InGameData) -> Option<Line<'static>> {
+    let seated = state
+        .seated
+        .as_ref()
+        .filter(|seated| seated.data.room_id == state.game.id && seated.data.dealt_in_next_hand)?;
+    if state.game.players.iter().any(|p| p.id == state.user_id) {
+        return None;
+    }
+    let seat = seated.data.seat + 1;
+    let text = match seated.data.estimated_wait {
+        Some(wait) => {
+            let left = wait as i64 - (Utc::now() - seated.timestamp).num_seconds();
+            format!(
+                "Seat {} is yours, dealt in from the next hand (about {}s)",
+                seat,
+                left.max(0)
+            )
+        }
+        None => format!("Seat {} is yours, dealt in from the next hand", seat),
+    };
+    Some(Line::from(text).yellow().centered())
+}
+
+// summary of the hand so far for a player who just rejoined the table
+fn resume_popup(area: Rect, resume: &ResumeSummary, buf: &mut Buffer) {
+    let mut lines: Vec<Line> = resume.actions.iter().map(|a| a.line()).collect();
+    if lines.is_empty() {
+        lines.push("No actions yet this hand".into());
+    }
+    lines.push(Line::default());
+    lines.push(resume.to_call_line().bold());
+    let [popup_area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Max(60)])
+        .flex(Flex::Center)
+        .areas(popup_area);
+    Clear.render(popup_area, buf);
+    Paragraph::new(lines)
+        .block(
+            Block::bordered()
+                .border_type(BorderType::Rounded)
+                .title(Line::from("While you were away").centered())
+                .title_bottom(
+                    Line::from(vec!["Dismiss ".into(), "<Enter>".light_blue().bold()])
+                        .right_aligned(),
+                ),
+        )
+        .render(popup_area, buf);
+}
+
+// pot, hands shown and chips won or lost by everyone dealt into the last hand
+fn hand_summary_popup(area: Rect, outcome: &Outcome, theme: &Theme, buf: &mut Buffer) {
+    let mut lines = vec![Line::from(format!("{} {}", t(Text::Pot), outcome.pot)).bold()];
+    for result in &outcome.players {
+        let net_chips = format!("{:+}", result.net_chips);
+        let net_chips = match result.net_chips {
+            0 => net_chips.fg(theme.muted),
+            n if n > 0 => net_chips.light_green(),
+            _ => net_chips.light_red(),
+        };
+        let line = Line::from(vec![
+            format!("{:<16}", result.name).into(),
+            format!("{:<20}", result.eval.as_deref().unwrap_or("-")).into(),
+            net_chips,
+        ]);
+        lines.push(if outcome.is_winner(result.player) {
+            line.bold()
+        } else {
+            line
+        });
+    }
+    let [popup_area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Max(50)])
+        .flex(Flex::Center)
+        .areas(popup_area);
+    Clear.render(popup_area, buf);
+    Paragraph::new(lines)
+        .block(
+            Block::bordered()
+                .border_type(BorderType::Rounded)
+                .title(Line::from(t(Text::HandResults)).centered()),
+        )
+        .style(theme.text)
+        .render(popup_area, buf);
+}
+
+// asks a player who lost at showdown whether the table gets to see their cards
+fn show_or_muck_popup(area: Rect, prompt: &ShowOrMuckPrompt, theme: &Theme, buf: &mut Buffer) {
+    let seconds_left = (prompt.deadline - Utc::now()).num_seconds().max(0);
+    let lines = vec![
+        Line::from("You lost this hand. Show your cards?"),
+        Line::from(format!("Mucked in {}s", seconds_left)).fg(theme.muted),
+    ];
+    let [popup_area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Max(50)])
+        .flex(Flex::Center)
+        .areas(popup_area);
+    Clear.render(popup_area, buf);
+    Paragraph::new(lines)
+        .centered()
+        .block(
+            Block::bordered()
+                .border_type(BorderType::Rounded)
+                .title(Line::from("Showdown").centered())
+                .title_bottom(
+                    Line::from(vec![
+                        "Show ".into(),
+                        "<S>".light_blue().bold(),
+                        " Muck ".into(),
+                        "<M>".red().bold(),
+                    ])
+                    .right_aligned(),
+                ),
+        )
+        .render(popup_area, buf);
+}
+
+// what the tutorial asks for next, kept at the top so the table stays visible
+fn tutorial_banner(area: Rect, prompt: &str, buf: &mut Buffer) {
+    const WIDTH: u16 = 70;
+    // borders take two columns, and wrapping can leave a line short
+    let lines = prompt.len() as u16 / (WIDTH - 4) + 1;
+    let [banner_area] = Layout::vertical([Constraint::Length(lines + 2)])
+        .flex(Flex::Start)
+        .areas(area);
+    let [banner_area] = Layout::horizontal([Constraint::Max(WIDTH)])
+        .flex(Flex::Center)
+        .areas(banner_area);
+    Clear.render(banner_area, buf);
+    Paragraph::new(prompt)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::bordered()
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::LightBlue))
+                .title(Line::from("Tutorial").centered()),
+        )
+        .render(banner_area, buf);
+}
+
+fn room_id(area: Rect, state: &InGameData, theme: &Theme, buf: &mut Buffer) {
+    let [_, pace_area, closing_area, area] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(area);
+    let pace = state
+        .game
+        .hand_time()
+        .into_iter()
+        .chain(state.game.hands_per_hour.map(|hands| format!("~{} hands/hour", hands)))
+        .collect::<Vec<_>>()
+        .join(" · ");
+    Paragraph::new(Line::from(pace).fg(theme.muted))
+        .right_aligned()
+        .render(pace_area, buf);
+    if let Some(kicked) = &state.kicked {
+        let line = format!(
+            "Removed after {} hands without acting, {} chips returned. Press Esc to leave",
+            kicked.timed_out_hands, kicked.chips
+        );
+        Paragraph::new(Line::from(line).light_red())
+            .right_aligned()
+            .render(closing_area, buf);
+    } else if let Some(room_closing) = &state.room_closing {
+        Paragraph::new(room_closing.line().yellow())
+            .right_aligned()
+            .render(closing_area, buf);
+    }
     let room_id = state.game.id.to_string();
-    let room_id_text = format!("Room ID: {}", room_id);
-    let room_id_paragraph = Paragraph::new(room_id_text).right_aligned();
+    let room_id_line = Line::from(vec![
+        format!("Room ID: {} ", room_id).into(),
+        "<CTRL + Y>".light_blue().bold(),
+    ]);
+    let room_id_paragraph = Paragraph::new(room_id_line).right_aligned();
     room_id_paragraph.render(area, buf);
 }
 
-fn action_paragraph(area: Rect, state: &mut InGameData, buf: &mut Buffer) {
+// the latest entries that fit, newest at the bottom
+fn action_log_panel(area: Rect, state: &InGameData, buf: &mut Buffer) {
+    let block = Block::bordered()
+        .title(Line::from("Action log").centered())
+        .border_type(BorderType::Rounded);
+    let inner_area = block.inner(area);
+    block.render(area, buf);
+    let start = state
+        .action_log
+        .len()
+        .saturating_sub(inner_area.height as usize);
+    let lines: Vec<Line> = state.action_log[start..]
+        .iter()
+        .map(ActionLogEntry::line)
+        .collect();
+    Paragraph::new(lines).render(inner_area, buf);
+}
+
+fn chat_panel(area: Rect, state: &mut InGameData, theme: &Theme, buf: &mut Buffer) {
+    let block = Block::bordered()
+        .title(Line::from("Chat").centered())
+        .border_type(BorderType::Rounded);
+    let inner_area = block.inner(area);
+    block.render(area, buf);
+    let [messages_area, input_area] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).areas(inner_area);
+
+    // newest at the bottom, scrolled back by `chat_scroll` messages
+    let visible = messages_area.height as usize;
+    state.chat_scroll = state
+        .chat_scroll
+        .min(state.chat.len().saturating_sub(visible));
+    let end = state.chat.len() - state.chat_scroll;
+    let start = end.saturating_sub(visible);
+    let lines: Vec<Line> = state.chat[start..end].iter().map(|m| m.line()).collect();
+    Paragraph::new(lines).render(messages_area, buf);
+
+    let instructions = if state.chat_in_focus {
+        Line::from(vec![
+            "Send ".into(),
+            "<Enter>".light_blue().bold(),
+            " Done ".into(),
+            "<Esc>".red().bold(),
+        ])
+    } else if state.supports(Feature::Chat) {
+        Line::from(vec![
+            "Chat ".into(),
+            "<CTRL + T>".light_blue().bold(),
+            " Scroll ".into(),
+            "<PgUp/PgDn>".light_blue().bold(),
+        ])
+    } else {
+        Line::from(vec!["Scroll ".into(), "<PgUp/PgDn>".light_blue().bold()])
+    };
+    let style = if state.chat_in_focus {
+        theme.text
+    } else {
+        theme.muted
+    };
+    Paragraph::new(state.chat_input.value())
+        .block(
+            Block::bordered()
+                .style(style)
+                .title_bottom(instructions.right_aligned()),
+        )
+        .render(input_area, buf);
+    state.cursor_position = state.chat_in_focus.then(|| {
+        (
+            input_area.x + state.chat_input.visual_cursor() as u16 + 1,
+            input_area.y + 1,
+        )
+            .into()
+    });
+}
+
+fn top_up_hint(area: Rect, buf: &mut Buffer) {
+    Paragraph::new(Line::from(vec![
+        "Add chips ".into(),
+        "<CTRL + A>".light_blue().bold(),
+    ]))
+    .render(area, buf);
+}
+
+fn friends_hint(area: Rect, buf: &mut Buffer) {
+    Paragraph::new(Line::from(vec![
+        "Invite friends ".into(),
+        "<CTRL + F>".light_blue().bold(),
+    ]))
+    .render(area, buf);
+}
+
+fn bug_report_hint(area: Rect, buf: &mut Buffer) {
+    Paragraph::new(Line::from(vec![
+        "Report a bug ".into(),
+        "<CTRL + B>".light_blue().bold(),
+    ]))
+    .render(area, buf);
+}
+
+fn dark_action_hint(area: Rect, buf: &mut Buffer) {
+    Paragraph::new(Line::from(vec![
+        "Dark: check ".into(),
+        "<CTRL + K>".light_blue().bold(),
+        " bet pot ".into(),
+        "<CTRL + P>".light_blue().bold(),
+    ]))
+    .render(area, buf);
+}
+
+fn sit_out_hint(area: Rect, state: &InGameData, buf: &mut Buffer) {
+    let label = if state.is_sitting_out() {
+        "Sit in "
+    } else {
+        "Sit out "
+    };
+    Paragraph::new(Line::from(vec![
+        label.into(),
+        "<CTRL + O>".light_blue().bold(),
+    ]))
+    .render(area, buf);
+}
+
+fn action_paragraph(area: Rect, state: &mut InGameData, theme: &Theme, buf: &mut Buffer) {
     let mut outer_block = Block::bordered()
-        .title(Line::from("Actions").centered())
+        .title(Line::from(t(Text::Actions)).centered())
         .border_type(BorderType::Rounded)
-        .style(Color::DarkGray);
+        .style(theme.muted);
 
-    if state.is_in_turn() {
+    if let Some(secs) = state.game.showdown_time_left() {
+        outer_block = outer_block.title_bottom(
+            Line::from(vec![
+                format!("Next hand in {}s, ", secs).into(),
+                "<Enter>".light_blue().bold(),
+                " when ready".into(),
+            ])
+            .centered(),
+        );
+    } else if state.is_in_turn() {
+        let title = match state.game.turn_time_left() {
+            Some(secs) => format!("{} ({}s)", t(Text::YourTurn), secs),
+            None => t(Text::YourTurn).to_string(),
+        };
         outer_block = outer_block
-            .title_bottom(Line::from("It's Your Turn").centered())
-            .style(Color::White);
+            .title_bottom(Line::from(title).centered())
+            .style(theme.text);
     }
 
     let inner_area = outer_block.inner(area);
@@ -122,70 +557,156 @@ fn action_paragraph(area: Rect, state: &mut InGameData, buf: &mut Buffer) {
         Constraint::Fill(1),
     ])
     .areas(inner_area);
+    // while waiting for the turn, the buttons set what to play once it comes
+    if let Some(choices) = state.pre_action_choices() {
+        let toggles: [_; 3] = Layout::split_equal(button_area, Direction::Horizontal);
+        let selected = state.pre_action();
+        for ((toggle, pre_action), key) in toggles.into_iter().zip(choices).zip(PRE_ACTION_KEYS) {
+            pre_action_toggle(pre_action, key, state, selected == Some(pre_action), theme)
+                .render(toggle, buf);
+        }
+        return;
+    }
     // top = check, call, raise, fold, all-in
     let buttons: [_; 5] = Layout::split_equal(button_area, Direction::Horizontal);
     buttons
         .into_iter()
         .zip(ACTION_BUTTONS)
         .for_each(|(button, action)| {
-            action.paragraph(state).render(button, buf);
+            action.paragraph(state, theme).render(button, buf);
         });
 }
 
-fn hand_paragraph(area: Rect, state: &PlayerState, game_state: &SharedGameState, winners: &Timestamped<Vec<Winnings>>, buf: &mut Buffer) {
+fn pre_action_toggle<'a>(
+    pre_action: PreAction,
+    key: &'a str,
+    state: &InGameData,
+    selected: bool,
+    theme: &Theme,
+) -> Paragraph<'a> {
+    let label = match pre_action {
+        PreAction::CheckFold => "Check/Fold".to_string(),
+        PreAction::Call(_) if state.to_call() == 0 => "Check".to_string(),
+        PreAction::Call(_) => format!("Call ({})", state.to_call()),
+        PreAction::CallAny => "Call any".to_string(),
+    };
+    let mark = if selected { "[x] " } else { "[ ] " };
+    let line = theme.highlight(format!("{}{}", mark, label), selected);
+    Paragraph::new(line.into_centered_line()).block(
+        Block::bordered()
+            .title_bottom(Line::from(key.light_blue()).centered())
+            .style(theme.text),
+    )
+}
+
+fn hand_paragraph(
+    area: Rect,
+    state: &PlayerState,
+    strength: Option<&HandStrength>,
+    game_state: &SharedGameState,
+    outcome: &Outcome,
+    theme: &Theme,
+    buf: &mut Buffer,
+) {
+    let title = Line::from(state.title_top()).centered();
+    // folds the player did not choose stand out from ordinary ones
+    let title = match state.fold_reason {
+        Some(FoldReason::Timeout) if state.has_folded => title.light_red(),
+        Some(FoldReason::Disconnect) if state.has_folded => title.fg(theme.muted),
+        _ => title,
+    };
     let mut outer_block = Block::bordered()
-        .title(Line::from(state.title_top()).centered())
+        .title(title)
         .title_bottom(Line::from(state.name_title()).left_aligned())
         .border_type(BorderType::Rounded);
 
-    if game_state.stage.is_showdown() {
-        if winners.data.iter().any(|w| w.player == state.id) {
+    if state.is_sitting_out {
+        outer_block = outer_block.style(theme.muted);
+    } else if game_state.stage.is_showdown() && game_state.equity.is_empty() {
+        if outcome.is_winner(state.id) {
             outer_block = outer_block.border_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::SLOW_BLINK))
         }
     } else if game_state.is_player_turn(state.id) {
         outer_block = outer_block.border_style(Style::default().add_modifier(Modifier::SLOW_BLINK));
     }
 
-    if state.is_dealer() {
+    if game_state.dealer_seat == Some(state.seat) {
         outer_block = outer_block.title_bottom(Line::from("Dealer").right_aligned());
     }
 
     let inner_block_area = outer_block.inner(area);
+    outer_block.render(area, buf);
+    // only panels wide enough to keep the cards clear of it show the avatar
+    let avatar = avatar_art(state.avatar).filter(|_| inner_block_area.width >= AVATAR_WIDTH * 3);
+    let avatar_width = if avatar.is_some() { AVATAR_WIDTH } else { 0 };
+    let [avatar_area, content_area] =
+        Layout::horizontal([Constraint::Length(avatar_width), Constraint::Fill(1)])
+            .areas(inner_block_area);
+    if let Some(avatar) = avatar {
+        Paragraph::new(avatar)
+            .fg(theme.muted)
+            .render(avatar_area, buf);
+    }
     let [_, bet_area, chips_area] = Layout::vertical([
         Constraint::Min(0),
         Constraint::Length(1),
         Constraint::Length(1),
     ])
     .areas(inner_block_area);
-    Paragraph::new(state.hand.line())
-        .block(outer_block)
-        .centered()
-        .render(area, buf);
+    let best_hand = outcome
+        .winnings
+        .iter()
+        .find(|w| w.player == state.id)
+        .filter(|_| game_state.stage.is_showdown() && game_state.equity.is_empty())
+        .map_or(&[][..], |w| w.best_hand.as_slice());
+    // the strength hint sits under the player's own cards
+    let cards = match state.hand {
+        HandState::Hidden => theme.hidden_hand_line(),
+        _ => state.hand.line_highlighting(best_hand),
+    };
+    let mut lines = vec![cards];
+    if let Some(strength) = strength {
+        lines.push(Line::from(strength.line()).fg(theme.muted));
+    }
+    // the winners are not known until the board is run out
+    if let Some(equity) = game_state.equity.get(&state.id) {
+        lines.push(Line::from(format!("Equity {:.0}%", equity * 100.0)).light_cyan());
+    }
+    Paragraph::new(lines).centered().render(content_area, buf);
     Paragraph::new(state.bet_display().right_aligned()).render(bet_area, buf);
     Paragraph::new(state.chips_display().right_aligned()).render(chips_area, buf);
 }
 
-fn card_paragraph(area: Rect, card: &SerdeCard, buf: &mut Buffer) {
-    let image_key = card.rank_suit_string();
-    let image_text = lookup_image(&image_key);
-    let paragraph = image_text
-        .and_then(|c| c.into_text().ok())
-        .map_or(Paragraph::new(image_key), Paragraph::new);
-    paragraph
-        .block(
-            Block::bordered()
-                .title(card.span())
-                .title_bottom(card.span())
-                .title_alignment(Alignment::Center)
-                .border_type(BorderType::Rounded),
-        )
-        .render(area, buf);
+pub fn card_paragraph(
+    area: Rect,
+    card: &SerdeCard,
+    highlighted: bool,
+    theme: &Theme,
+    buf: &mut Buffer,
+) {
+    let mut block = Block::bordered()
+        .title(card.span())
+        .title_bottom(card.span())
+        .title_alignment(Alignment::Center)
+        .border_type(BorderType::Rounded);
+    if highlighted {
+        block = block
+            .border_type(BorderType::Thick)
+            .border_style(Style::default().fg(Color::Yellow));
+    }
+    let inner_area = block.inner(area);
+    block.render(area, buf);
+    if !render_card_art(**card, theme, inner_area, buf) {
+        Paragraph::new(card.rank_suit_string()).render(inner_area, buf);
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct InGameData {
     pub user_id: Uuid,
     pub hand: PlayerHand,
+    // what the hand makes with the board, when the server was asked for it
+    pub hand_strength: Option<HandStrength>,
     pub game: SharedGameState,
     pub raise_input: Input,
     pub focus: Option<InGameFocus>,
@@ -193,10 +714,39 @@ pub struct InGameData {
     pub prev_frame_player: Option<Uuid>,
     // The previous frame's stage of the game
     pub prev_frame_stage: Stage,
-    pub winners: Timestamped<Vec<Winnings>>
+    pub outcome: Timestamped<Outcome>,
+    // the results of the last hand are shown over the table until then
+    pub summary_until: Option<DateTime<Utc>>,
+    pub room_closing: Option<RoomClosing>,
+    pub kicked: Option<Kicked>,
+    pub seated: Option<Timestamped<Seated>>,
+    pub resume: Option<ResumeSummary>,
+    pub show_or_muck: Option<ShowOrMuckPrompt>,
+    pub copied: Option<Copied>,
+    // prompt of the current step when playing the tutorial
+    pub tutorial: Option<String>,
+    pub action_log: Vec<ActionLogEntry>,
+    pub chat: Vec<ChatMessage>,
+    pub chat_input: Input,
+    pub chat_in_focus: bool,
+    // messages scrolled back from the newest one
+    pub chat_scroll: usize,
+    // shown in a popup while Some
+    pub bug_report: Option<BugReport>,
+    pub friends: Option<Friends>,
+    pub top_up: Option<TopUp>,
+    // agreed on with the server, actions of other features are hidden
+    pub features: Vec<Feature>,
+    pub cursor_position: Option<Position>,
+    // turned off on the settings screen by those who would rather work it out themselves
+    pub show_pot_odds: bool,
 }
 
 impl InGameData {
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+
     pub fn is_in_turn(&self) -> bool {
         self.game
             .current_player
@@ -213,6 +763,20 @@ impl InGameData {
             .unwrap_or_default()
     }
 
+    pub fn to_call(&self) -> u32 {
+        match &self.game.personal {
+            Some(personal) => personal.to_call,
+            None => self.game.max_bet().saturating_sub(self.bet()),
+        }
+    }
+
+    pub fn pot_odds(&self) -> Option<PotOdds> {
+        if !self.show_pot_odds {
+            return None;
+        }
+        self.game.personal.as_ref()?.pot_odds
+    }
+
     pub fn chips(&self) -> u32 {
         self.game
             .players
@@ -222,13 +786,50 @@ impl InGameData {
             .unwrap_or_default()
     }
 
-    pub fn folded(&self) -> bool {
+    // what the server accepts from the player, empty until it is their turn
+    fn legal_actions(&self) -> &[LegalAction] {
+        self.game
+            .personal
+            .as_ref()
+            .map_or(&[], |personal| personal.legal_actions.as_slice())
+    }
+
+    // smallest and largest total the player may bet or raise to
+    fn raise_range(&self) -> Option<(u32, u32)> {
+        self.legal_actions().iter().find_map(|action| match action {
+            LegalAction::Bet(limits) | LegalAction::Raise(limits) => Some((limits.min, limits.max)),
+            _ => None,
+        })
+    }
+
+    pub fn is_sitting_out(&self) -> bool {
         self.game
             .players
             .iter()
             .find(|p| p.id == self.user_id)
-            .map(|p| p.has_folded)
-            .unwrap_or_default()
+            .is_some_and(|p| p.is_sitting_out)
+    }
+
+    /// Pre-actions on offer while waiting for the turn, None when there is no turn to wait for
+    pub fn pre_action_choices(&self) -> Option<[PreAction; 3]> {
+        let betting = matches!(
+            self.game.stage,
+            Stage::PreFlop | Stage::Flop | Stage::Turn | Stage::River
+        ) && self.game.equity.is_empty();
+        let me = self.game.players.iter().find(|p| p.id == self.user_id)?;
+        let in_hand = !me.has_folded && !me.is_sitting_out && me.chips > 0;
+        if !self.supports(Feature::PreActions) || !betting || !in_hand || self.is_in_turn() {
+            return None;
+        }
+        Some([
+            PreAction::CheckFold,
+            PreAction::Call(self.game.max_bet()),
+            PreAction::CallAny,
+        ])
+    }
+
+    pub fn pre_action(&self) -> Option<PreAction> {
+        self.game.personal.as_ref().and_then(|p| p.pre_action)
     }
 }
 
@@ -256,6 +857,18 @@ impl Display for InGameFocus {
     }
 }
 
+impl From<LegalAction> for InGameFocus {
+    fn from(action: LegalAction) -> Self {
+        match action {
+            LegalAction::Fold => InGameFocus::Fold,
+            LegalAction::Check => InGameFocus::Check,
+            LegalAction::Call { .. } => InGameFocus::Call,
+            LegalAction::Bet(_) | LegalAction::Raise(_) => InGameFocus::Raise,
+            LegalAction::AllIn { .. } => InGameFocus::AllIn,
+        }
+    }
+}
+
 impl InGameFocus {
     fn sound(&self) -> Sound {
         match self {
@@ -267,47 +880,67 @@ impl InGameFocus {
         }
     }
 
-    fn paragraph(&self, state: &mut InGameData) -> Paragraph {
+    fn paragraph(&self, state: &mut InGameData, theme: &Theme) -> Paragraph {
         let color = if self.enabled(state) {
-            Color::White
+            theme.text
         } else {
-            Color::DarkGray
+            theme.muted
         };
+        let focused = state.focus.as_ref().is_some_and(|f| f == self);
         let line = match self {
-            InGameFocus::Raise => Line::from(state.raise_input.value().to_string()).centered(),
-            InGameFocus::Call => highlight(
-                format!(
-                    "{} ({})",
-                    self,
-                    state.game.max_bet().saturating_sub(state.bet())
-                ),
-                state.focus.as_ref().is_some_and(|f| f == self),
-            )
-            .into_centered_line(),
-            InGameFocus::AllIn => highlight(
-                format!("{} ({})", self, state.chips()),
-                state.focus.as_ref().is_some_and(|f| f == self),
-            )
-            .into_centered_line(),
-            _ => highlight(
-                self.to_string(),
-                state.focus.as_ref().is_some_and(|f| f == self),
-            )
-            .into_centered_line(),
+            InGameFocus::Raise => raise_line(state, theme),
+            InGameFocus::Call => {
+                let label = format!("{} ({})", self, state.to_call());
+                theme.highlight(label, focused).into_centered_line()
+            }
+            InGameFocus::AllIn => {
+                let label = format!("{} ({})", self, state.chips());
+                theme.highlight(label, focused).into_centered_line()
+            }
+            _ => {
+                let label = self.to_string();
+                theme.highlight(label, focused).into_centered_line()
+            }
         };
         match self {
             InGameFocus::Raise => Paragraph::new(line).block(
                 Block::bordered()
-                    .title(highlight(
-                        self.to_string(),
-                        state.focus.as_ref().is_some_and(|f| f == self),
+                    .title(theme.highlight(
+                        match raise_bounds(state) {
+                            Some(bounds) if self.enabled(state) && state.to_call() == 0 => {
+                                format!("{} ({})", self.label(state), bounds)
+                            }
+                            Some(bounds) if self.enabled(state) => format!("{} to ({})", self, bounds),
+                            _ => self.label(state),
+                        },
+                        focused,
                     ))
                     .style(color),
             ),
+            InGameFocus::Call => {
+                let mut block = Block::bordered().style(color);
+                if let Some(pot_odds) = state.pot_odds() {
+                    let odds = format!(
+                        "{} · {:.0}%",
+                        pot_odds.ratio(),
+                        pot_odds.equity_needed() * 100.0
+                    );
+                    block = block.title_bottom(Line::from(odds).centered());
+                }
+                Paragraph::new(line).block(block)
+            }
             _ => Paragraph::new(line).block(Block::bordered().style(color)),
         }
     }
 
+    // the raise button opens the betting when there is nothing to call
+    fn label(&self, state: &InGameData) -> String {
+        match self {
+            InGameFocus::Raise if state.to_call() == 0 => "Bet".to_string(),
+            _ => self.to_string(),
+        }
+    }
+
     fn position_in_array(&self) -> usize {
         match self {
             InGameFocus::Check => 0,
@@ -334,25 +967,33 @@ impl InGameFocus {
     }
 
     fn enabled(&self, state: &InGameData) -> bool {
-        if state.game.current_player != Some(state.user_id) {
-            return false;
-        }
-        match self {
-            InGameFocus::Check => state.bet() >= state.game.max_bet(),
-            InGameFocus::Call => state.game.max_bet().saturating_sub(state.bet()) <= state.chips(),
-            InGameFocus::Raise => state.chips() > state.game.max_bet(),
-            InGameFocus::Fold => !state.folded(),
-            InGameFocus::AllIn => state.chips() > 0,
-        }
+        state
+            .legal_actions()
+            .iter()
+            .any(|action| Self::from(*action) == *self)
     }
 
     fn to_action_request(&self, state: &InGameData) -> eyre::Result<ActionRequest> {
+        if self == &InGameFocus::Raise {
+            let total: u32 = state.raise_input.value().parse()?;
+            if let Some((min, max)) = state.raise_range() {
+                eyre::ensure!(
+                    (min..=max).contains(&total),
+                    "Raise must be to between {} and {} chips",
+                    min,
+                    max
+                );
+            }
+        }
         Ok(ActionRequest {
             room_id: state.game.id,
             action: match self {
                 InGameFocus::Check => Action::Check,
                 InGameFocus::Call => Action::Call,
-                InGameFocus::Raise => Action::Raise(state.raise_input.value().parse()?),
+                InGameFocus::Raise if state.to_call() == 0 => {
+                    Action::Bet(state.raise_input.value().parse()?)
+                }
+                InGameFocus::Raise => Action::RaiseTo(state.raise_input.value().parse()?),
                 InGameFocus::Fold => Action::Fold,
                 InGameFocus::AllIn => Action::AllIn,
             },
@@ -360,11 +1001,44 @@ impl InGameFocus {
     }
 }
 
+// "min 10" in no limit, the whole range when the table caps raises
+fn raise_bounds(state: &InGameData) -> Option<String> {
+    let (min, max) = state.raise_range()?;
+    let bounds = match state.game.betting {
+        BettingStructure::NoLimit => format!("min {}", min),
+        _ if min == max => min.to_string(),
+        _ => format!("{}-{}", min, max),
+    };
+    Some(bounds)
+}
+
+// the total typed in, with how many chips that adds to the player's bet, red when out of range
+fn raise_line(state: &InGameData, theme: &Theme) -> Line<'static> {
+    let value = state.raise_input.value().to_string();
+    match value.parse::<u32>() {
+        Ok(total) => {
+            let in_range = state
+                .raise_range()
+                .is_none_or(|(min, max)| (min..=max).contains(&total));
+            let value = if in_range { value.into() } else { value.red() };
+            Line::from(vec![
+                value,
+                format!(" (+{})", total.saturating_sub(state.bet())).fg(theme.muted),
+            ])
+            .centered()
+        }
+        Err(_) => Line::from(value).centered(),
+    }
+}
+
 pub fn in_game_data(user_id: Uuid, hand: PlayerHand, game: SharedGameState) -> InGameData {
     let mut game = InGameData {
         user_id,
         hand,
         game,
+        // until the server says otherwise
+        features: Feature::ALL.to_vec(),
+        show_pot_odds: config::show_pot_odds(),
         ..Default::default()
     };
     game.focus = InGameFocus::first_enabled(&game);
@@ -383,12 +1057,72 @@ impl OnTick for InGameData {
             self.hand = hand_state.data.clone();
         }
 
-        if let Ok(Some(winnings)) = OUTCOME_STATE.try_read().as_deref() {
-            if self.winners.timestamp != winnings.timestamp {
-                self.winners = winnings.clone();
-                if self.winners.data.iter().any(|w| w.player == self.user_id) {
+        if let Ok(strength) = HAND_STRENGTH_STATE.try_read().as_deref() {
+            self.hand_strength = strength.as_ref().map(|s| s.data.clone());
+        }
+
+        if let Ok(resume) = RESUME_STATE.try_write().as_deref_mut() {
+            if let Some(resume) = resume.take() {
+                self.resume = Some(resume.data);
+            }
+        }
+
+        self.features = negotiated_features().await;
+
+        if let Ok(prompt) = SHOW_OR_MUCK_STATE.try_write().as_deref_mut() {
+            // the server would not hear the answer, the cards are mucked anyway
+            if let Some(prompt) = prompt.take().filter(|_| self.supports(Feature::ShowCards)) {
+                self.show_or_muck = Some(prompt.data);
+            }
+        }
+        // cards not shown in time are mucked by the server
+        if self
+            .show_or_muck
+            .as_ref()
+            .is_some_and(|prompt| Utc::now() >= prompt.deadline)
+        {
+            self.show_or_muck = None;
+        }
+
+        if let Ok(seated) = SEATED_STATE.try_read().as_deref() {
+            self.seated = seated.clone();
+        }
+
+        if let Ok(Some(room_closing)) = ROOM_CLOSING_STATE.try_read().as_deref() {
+            self.room_closing = Some(room_closing.data.clone());
+        }
+
+        if let Ok(Some(kicked)) = KICKED_STATE.try_read().as_deref() {
+            self.kicked = Some(kicked.data.clone());
+        }
+
+        if let Ok(log) = ACTION_LOG.try_read().as_deref() {
+            if log.last() != self.action_log.last() {
+                self.action_log = log.clone();
+            }
+        }
+
+        if let Ok(chat) = CHAT_STATE.try_read().as_deref() {
+            if chat.last() != self.chat.last() {
+                self.chat = chat.clone();
+            }
+        }
+
+        self.tutorial = tutorial_prompt().await;
+
+        // e.g. an action the server turned down
+        if let Some(message) = take_service_error().await {
+            eyre::bail!(message);
+        }
+
+        if let Ok(Some(outcome)) = OUTCOME_STATE.try_read().as_deref() {
+            if self.outcome.timestamp != outcome.timestamp {
+                self.outcome = outcome.clone();
+                if self.outcome.data.is_winner(self.user_id) {
                     Sound::Win.play();
                 }
+                self.summary_until =
+                    (!outcome.data.players.is_empty()).then(|| Utc::now() + SUMMARY_TIME);
             }
         }
 
@@ -459,14 +1193,135 @@ impl OnKeyEvent for InGameData {
         key: KeyEvent,
         client: &mut Client,
     ) -> eyre::Result<ScreenChange> {
+        if self.resume.is_some() {
+            if let (KeyEventKind::Press, KeyCode::Enter | KeyCode::Esc) = (key.kind, key.code) {
+                self.resume = None;
+            }
+            return Ok(ScreenChange::None);
+        }
+        if self.copied.is_some() {
+            if let (KeyEventKind::Press, KeyCode::Enter | KeyCode::Esc) = (key.kind, key.code) {
+                self.copied = None;
+            }
+            return Ok(ScreenChange::None);
+        }
+        if let Some(prompt) = &self.show_or_muck {
+            let show = match (key.kind, key.code) {
+                (KeyEventKind::Press, KeyCode::Char('s')) => true,
+                (KeyEventKind::Press, KeyCode::Char('m') | KeyCode::Esc) => false,
+                _ => return Ok(ScreenChange::None),
+            };
+            client.show_cards(prompt.room_id, show).await?;
+            self.show_or_muck = None;
+            return Ok(ScreenChange::None);
+        }
+        if let Some(report) = &mut self.bug_report {
+            if report.on_key_event(key, client).await? {
+                self.bug_report = None;
+            }
+            return Ok(ScreenChange::None);
+        }
+        if let Some(top_up) = &mut self.top_up {
+            if top_up.on_key_event(key, client).await? {
+                self.top_up = None;
+            }
+            return Ok(ScreenChange::None);
+        }
+        if let Some(friends) = &mut self.friends {
+            if friends.on_key_event(key, client).await? {
+                self.friends = None;
+            }
+            return Ok(ScreenChange::None);
+        }
+        if self.chat_in_focus {
+            return self.on_chat_key_event(key, client).await;
+        }
         let change = match (key.kind, key.modifiers, key.code) {
             (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Esc) => {
+                let is_practice = client.is_practice();
                 client.leave().await?;
                 reset_game_state().await;
                 reset_hand_state().await;
-                lobby::lobby_screen_data(client).await?.into()
+                reset_room_closing_state().await;
+                reset_kicked_state().await;
+                reset_chat_state().await;
+                reset_action_log().await;
+                reset_seated_state().await;
+                if is_practice {
+                    LoginScreenData::new(None, client.base_url()).into()
+                } else {
+                    lobby::lobby_screen_data(client).await?.into()
+                }
             }
             (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('c')) => ScreenChange::Quit,
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('t'))
+                if self.supports(Feature::Chat) =>
+            {
+                self.chat_in_focus = true;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::PageUp) => {
+                self.chat_scroll += 1;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::PageDown) => {
+                self.chat_scroll = self.chat_scroll.saturating_sub(1);
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('o')) => {
+                if self.is_sitting_out() {
+                    client.sit_in(self.game.id).await?;
+                } else {
+                    client.sit_out(self.game.id).await?;
+                }
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('k'))
+                if self.supports(Feature::DarkActions) =>
+            {
+                client.dark_action(self.game.id, DarkAction::Check).await?;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('p'))
+                if self.supports(Feature::DarkActions) =>
+            {
+                client.dark_action(self.game.id, DarkAction::BetPot).await?;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('x')) => {
+                self.toggle_pre_action(0, client).await?;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('l')) => {
+                self.toggle_pre_action(1, client).await?;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('n')) => {
+                self.toggle_pre_action(2, client).await?;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('y')) => {
+                self.copied = Some(copy("room ID", self.game.id.to_string()));
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('b'))
+                if !client.is_practice() =>
+            {
+                self.bug_report = Some(BugReport::default());
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('a'))
+                if !client.is_practice() && self.supports(Feature::TopUp) =>
+            {
+                self.top_up = Some(TopUp::new(self.game.id));
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('f'))
+                if !client.is_practice() =>
+            {
+                self.friends = Some(Friends::open(client, true).await?);
+                ScreenChange::None
+            }
             (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Tab) => {
                 self.focus = self
                     .focus
@@ -474,6 +1329,12 @@ impl OnKeyEvent for InGameData {
                     .map_or_else(|| InGameFocus::first_enabled(self), |f| f.switch(self));
                 ScreenChange::None
             }
+            (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Enter)
+                if self.game.showdown_deadline.is_some() =>
+            {
+                client.ready(self.game.id).await?;
+                ScreenChange::None
+            }
             (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Enter) => {
                 if let Some(focus) = &self.focus {
                     focus.sound().play();
@@ -504,12 +1365,51 @@ impl OnKeyEvent for InGameData {
     }
 }
 
+impl InGameData {
+    // pressing the key of the pre-action already set takes it back
+    async fn toggle_pre_action(&self, choice: usize, client: &mut Client) -> eyre::Result<()> {
+        let Some(choices) = self.pre_action_choices() else {
+            return Ok(());
+        };
+        let pre_action = (self.pre_action() != Some(choices[choice])).then_some(choices[choice]);
+        client.pre_action(self.game.id, pre_action).await
+    }
+
+    async fn on_chat_key_event(
+        &mut self,
+        key: KeyEvent,
+        client: &mut Client,
+    ) -> eyre::Result<ScreenChange> {
+        let change = match (key.kind, key.modifiers, key.code) {
+            (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Esc) => {
+                self.chat_in_focus = false;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Enter) => {
+                let message = self.chat_input.value().trim().to_string();
+                if !message.is_empty() {
+                    client.send_chat(self.game.id, message).await?;
+                    self.chat_input.reset();
+                    self.chat_scroll = 0;
+                }
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('c')) => ScreenChange::Quit,
+            _ => {
+                self.chat_input.handle_event(&Event::Key(key));
+                ScreenChange::None
+            }
+        };
+        Ok(change)
+    }
+}
+
 impl AsRef<InGameFocus> for Action {
     fn as_ref(&self) -> &InGameFocus {
         match self {
             Action::Check => &InGameFocus::Check,
             Action::Call => &InGameFocus::Call,
-            Action::Raise(_) => &InGameFocus::Raise,
+            Action::Bet(_) | Action::Raise(_) | Action::RaiseTo(_) => &InGameFocus::Raise,
             Action::Fold => &InGameFocus::Fold,
             Action::AllIn => &InGameFocus::AllIn,
         }
@@ -0,0 +1,195 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Spanish];
+
+    // in the language itself, so it can be found by those who read it
+    pub fn label(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::Spanish => "Español",
+        }
+    }
+}
+
+/// Text shown by the screens, looked up in the catalog of the language picked
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Text {
+    ErrorOccurred,
+    Announcement,
+    Dismiss,
+    // game
+    Actions,
+    YourTurn,
+    HandResults,
+    Pot,
+    // lobby
+    Username,
+    Avatar,
+    Balance,
+    Premium,
+    FreeChips,
+    Stats,
+    Quiz,
+    Transactions,
+    Friends,
+    Leaderboard,
+    Settings,
+    LogOut,
+    Rooms,
+    Room,
+    Players,
+    Blinds,
+    Stage,
+    AvgPot,
+    Activity,
+    CopyRoomId,
+    Currency,
+    Replays,
+    Quit,
+    // settings
+    Change,
+    Back,
+    Preview,
+    Theme,
+    CardBack,
+    Sound,
+    Language,
+    PotOdds,
+    On,
+    Off,
+    DealVolume,
+    ChipsVolume,
+    AlertsVolume,
+}
+
+lazy_static! {
+    static ref LANGUAGE: RwLock<Language> = RwLock::new(config::language());
+}
+
+pub fn language() -> Language {
+    LANGUAGE.read().map(|l| *l).unwrap_or_default()
+}
+
+pub fn set_language(language: Language) {
+    if let Ok(mut current) = LANGUAGE.write() {
+        *current = language;
+    }
+}
+
+/// `text` in the language picked on the settings screen
+pub fn t(text: Text) -> &'static str {
+    match language() {
+        Language::English => english(text),
+        Language::Spanish => spanish(text),
+    }
+}
+
+fn english(text: Text) -> &'static str {
+    match text {
+        Text::ErrorOccurred => "Error occurred",
+        Text::Announcement => "Announcement",
+        Text::Dismiss => "Dismiss",
+        Text::Actions => "Actions",
+        Text::YourTurn => "It's Your Turn",
+        Text::HandResults => "Hand results",
+        Text::Pot => "Pot",
+        Text::Username => "Username",
+        Text::Avatar => "Avatar",
+        Text::Balance => "Balance",
+        Text::Premium => "premium",
+        Text::FreeChips => "Free chips",
+        Text::Stats => "Stats",
+        Text::Quiz => "Quiz",
+        Text::Transactions => "Transactions",
+        Text::Friends => "Friends",
+        Text::Leaderboard => "Leaderboard",
+        Text::Settings => "Settings",
+        Text::LogOut => "Log out",
+        Text::Rooms => "Rooms",
+        Text::Room => "Room",
+        Text::Players => "Players",
+        Text::Blinds => "Blinds",
+        Text::Stage => "Stage",
+        Text::AvgPot => "Avg pot",
+        Text::Activity => "Activity",
+        Text::CopyRoomId => "Copy room ID",
+        Text::Currency => "Currency",
+        Text::Replays => "Replays",
+        Text::Quit => "Quit",
+        Text::Change => "Change",
+        Text::Back => "Back",
+        Text::Preview => "Preview",
+        Text::Theme => "Theme",
+        Text::CardBack => "Card back",
+        Text::Sound => "Sound",
+        Text::Language => "Language",
+        Text::PotOdds => "Pot odds",
+        Text::On => "On",
+        Text::Off => "Off",
+        Text::DealVolume => "Deal volume",
+        Text::ChipsVolume => "Chips volume",
+        Text::AlertsVolume => "Alerts volume",
+    }
+}
+
+fn spanish(text: Text) -> &'static str {
+    match text {
+        Text::ErrorOccurred => "Se produjo un error",
+        Text::Announcement => "Anuncio",
+        Text::Dismiss => "Cerrar",
+        Text::Actions => "Acciones",
+        Text::YourTurn => "Es tu turno",
+        Text::HandResults => "Resultado de la mano",
+        Text::Pot => "Bote",
+        Text::Username => "Usuario",
+        Text::Avatar => "Avatar",
+        Text::Balance => "Saldo",
+        Text::Premium => "premium",
+        Text::FreeChips => "Fichas gratis",
+        Text::Stats => "Estadísticas",
+        Text::Quiz => "Test",
+        Text::Transactions => "Movimientos",
+        Text::Friends => "Amigos",
+        Text::Leaderboard => "Clasificación",
+        Text::Settings => "Ajustes",
+        Text::LogOut => "Cerrar sesión",
+        Text::Rooms => "Mesas",
+        Text::Room => "Mesa",
+        Text::Players => "Jugadores",
+        Text::Blinds => "Ciegas",
+        Text::Stage => "Ronda",
+        Text::AvgPot => "Bote medio",
+        Text::Activity => "Actividad",
+        Text::CopyRoomId => "Copiar ID de mesa",
+        Text::Currency => "Moneda",
+        Text::Replays => "Repeticiones",
+        Text::Quit => "Salir",
+        Text::Change => "Cambiar",
+        Text::Back => "Volver",
+        Text::Preview => "Vista previa",
+        Text::Theme => "Tema",
+        Text::CardBack => "Reverso",
+        Text::Sound => "Sonido",
+        Text::Language => "Idioma",
+        Text::PotOdds => "Odds del bote",
+        Text::On => "Sí",
+        Text::Off => "No",
+        Text::DealVolume => "Volumen reparto",
+        Text::ChipsVolume => "Volumen fichas",
+        Text::AlertsVolume => "Volumen avisos",
+    }
+}
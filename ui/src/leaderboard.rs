@@ -0,0 +1,156 @@
+use chrono::Local;
+use client::client::Client;
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::prelude::{Line, Modifier, StatefulWidget, Style, Widget};
+use ratatui::style::Stylize;
+use ratatui::widgets::{Block, Cell, Clear, Paragraph, Row, Table, TableState, Tabs};
+use types::stats::{self, LeaderboardPeriod};
+use uuid::Uuid;
+
+use crate::theme::Theme;
+
+// in the order of the tabs
+const PERIODS: [(LeaderboardPeriod, &str); 3] = [
+    (LeaderboardPeriod::Daily, "Today"),
+    (LeaderboardPeriod::Weekly, "This week"),
+    (LeaderboardPeriod::AllTime, "All time"),
+];
+
+/// Top players by net winnings, a tab per period
+#[derive(Debug)]
+pub struct Leaderboard {
+    tab: usize,
+    board: stats::Leaderboard,
+    table_state: TableState,
+    // the player's own row is highlighted
+    user_id: Uuid,
+}
+
+impl Leaderboard {
+    pub async fn open(client: &Client, user_id: Uuid) -> Result<Self> {
+        let tab = 0;
+        Ok(Self {
+            tab,
+            board: client.get_leaderboard(PERIODS[tab].0).await?,
+            table_state: TableState::default().with_selected(0),
+            user_id,
+        })
+    }
+
+    async fn switch_to(&mut self, tab: usize, client: &Client) -> Result<()> {
+        self.board = client.get_leaderboard(PERIODS[tab].0).await?;
+        self.tab = tab;
+        self.table_state.select(Some(0));
+        Ok(())
+    }
+
+    /// Returns whether the popup should be closed
+    pub async fn on_key_event(&mut self, key: KeyEvent, client: &Client) -> Result<bool> {
+        if key.kind != KeyEventKind::Press {
+            return Ok(false);
+        }
+        match key.code {
+            KeyCode::Esc => return Ok(true),
+            KeyCode::Down => self.table_state.select_next(),
+            KeyCode::Up => self.table_state.select_previous(),
+            KeyCode::Right | KeyCode::Tab => {
+                self.switch_to((self.tab + 1) % PERIODS.len(), client)
+                    .await?;
+            }
+            KeyCode::Left | KeyCode::BackTab => {
+                self.switch_to((self.tab + PERIODS.len() - 1) % PERIODS.len(), client)
+                    .await?;
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+}
+
+pub fn leaderboard_popup(
+    area: Rect,
+    leaderboard: &mut Leaderboard,
+    theme: &Theme,
+    buf: &mut Buffer,
+) {
+    let [popup_area] = Layout::vertical([Constraint::Percentage(80)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Max(70)])
+        .flex(Flex::Center)
+        .areas(popup_area);
+    Clear.render(popup_area, buf);
+    let instructions = Line::from(vec![
+        "Period ".into(),
+        "<←/→>".light_blue().bold(),
+        " Close ".into(),
+        "<Esc>".red().bold(),
+    ]);
+    let block = Block::bordered()
+        .title(Line::from("Leaderboard").centered())
+        .title_bottom(instructions.right_aligned());
+    let inner_area = block.inner(popup_area);
+    block.render(popup_area, buf);
+    let [tabs_area, table_area, status_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(inner_area);
+    Tabs::new(PERIODS.iter().map(|(_, label)| *label))
+        .select(leaderboard.tab)
+        .highlight_style(Style::default().bold().light_blue())
+        .render(tabs_area, buf);
+    Paragraph::new(
+        Line::from(format!(
+            "Updated {}",
+            leaderboard
+                .board
+                .updated_at
+                .with_timezone(&Local)
+                .format("%H:%M")
+        ))
+        .fg(theme.muted),
+    )
+    .render(status_area, buf);
+
+    if leaderboard.board.entries.is_empty() {
+        Paragraph::new("No hands played yet")
+            .centered()
+            .render(table_area, buf);
+        return;
+    }
+    let header = ["#", "Player", "Net winnings", "Hands"]
+        .into_iter()
+        .map(Cell::from)
+        .collect::<Row>()
+        .bold();
+    let rows = leaderboard.board.entries.iter().map(|entry| {
+        let row = Row::new([
+            Cell::from(entry.rank.to_string()),
+            Cell::from(entry.name.clone()),
+            Cell::from(format!("{:+}", entry.net_winnings)),
+            Cell::from(entry.hands_played.to_string()),
+        ]);
+        if entry.user_id == leaderboard.user_id {
+            row.light_yellow()
+        } else {
+            row
+        }
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(4),
+            Constraint::Fill(1),
+            Constraint::Length(14),
+            Constraint::Length(8),
+        ],
+    )
+    .header(header)
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    StatefulWidget::render(table, table_area, buf, &mut leaderboard.table_state);
+}
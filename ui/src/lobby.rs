@@ -2,53 +2,234 @@ use std::default::Default;
 use std::ops::Not;
 use std::time::Duration;
 
-use chrono::{DateTime, Utc};
-use client::client::{Client, GAME_STATE, HAND_STATE};
-use color_eyre::eyre::ContextCompat;
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use client::client::{
+    is_offline, lobby_is_live, reset_waitlisted_state, take_friend_invites, take_lobby_updates,
+    take_service_error, Backoff, Client, GAME_STATE, HAND_STATE, SEATED_STATE, WAITLISTED_STATE,
+};
+use color_eyre::eyre::{bail, ContextCompat};
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::buffer::Buffer;
-use ratatui::layout::{Constraint, Direction, Layout, Position, Rect};
+use ratatui::layout::{Constraint, Direction, Flex, Layout, Position, Rect};
 use ratatui::prelude::{Line, Modifier, StatefulWidget, Style, Widget};
 use ratatui::style::Stylize;
-use ratatui::widgets::{Block, Cell, Paragraph, Row, Table, TableState};
+use ratatui::widgets::{Block, Cell, Clear, Paragraph, Row, Table, TableState};
 use tokio::time::sleep;
 use tokio::try_join;
 use tui_input::backend::crossterm::EventHandler;
 use tui_input::Input;
-use types::domain::{JoinGameRequest, RoomInfo, UpdateProfileRequest, User};
+use types::domain::{
+    Avatar, ChipCurrency, FriendInvite, JoinGameRequest, LobbyUpdate, RoomInfo, RoomInvite,
+    RoomQuery, UpdateProfileRequest, User, Waitlisted,
+};
 use types::error::Error;
-use types::room::MAX_NUM_OF_PLAYERS;
+use types::room::RoomDetails;
+use types::rules::DEFAULT_BUY_IN;
 use types::state::PlayerHand;
+use types::stats::ProfileStats;
+use uuid::Uuid;
 
+use crate::clipboard::{copied_popup, copy, Copied};
 use crate::data::{OnKeyEvent, OnTick, Screen, ScreenChange};
 use crate::extension::Splittable;
+use crate::friends::{friend_invite_popup, friends_popup, Friends};
 use crate::game::in_game_data;
+use crate::i18n::{t, Text};
+use crate::leaderboard::{leaderboard_popup, Leaderboard};
 use crate::login::LoginScreenData;
+use crate::quiz::{quiz_popup, Quiz};
+use crate::replay::replay_screen_data;
+use crate::settings::SettingsScreenData;
+use crate::theme::Theme;
+use crate::transactions::{transactions_popup, Transactions};
+use crate::{config, LAST_ROOM, TOKEN_MANAGER};
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+// while changes are pushed the whole list is only fetched now and then, to pick up the balance
+const LIVE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+// rooms without a hand for this long are listed as idle
+const IDLE_AFTER: TimeDelta = TimeDelta::minutes(10);
+
+fn refresh_interval() -> Duration {
+    if lobby_is_live() {
+        LIVE_REFRESH_INTERVAL
+    } else {
+        REFRESH_INTERVAL
+    }
+}
 
 #[derive(Debug)]
 pub struct LobbyScreenData {
     pub user: User,
     pub rooms: Vec<RoomInfo>,
+    // rooms of every currency are listed until one is picked
+    pub room_query: RoomQuery,
     pub table_state: TableState,
     pub next_refresh_time: DateTime<Utc>,
     pub username_input: Input,
     pub cursor_position: Option<Position>,
     pub username_in_focus: bool,
+    // room the player has been invited to, waiting for a buy-in amount
+    pub buy_in_room: Option<RoomInfo>,
+    pub buy_in_input: Input,
+    // private room waiting for its password, with the buy-in already chosen
+    pub password_room: Option<(RoomInfo, i64)>,
+    pub password_input: Input,
+    // shown in a popup while Some
+    pub stats: Option<ProfileStats>,
+    pub quiz: Option<Quiz>,
+    pub transactions: Option<Box<Transactions>>,
+    pub friends: Option<Friends>,
+    pub leaderboard: Option<Leaderboard>,
+    // the latest invite a friend sent to their table, until joined or ignored
+    pub friend_invite: Option<FriendInvite>,
+    pub copied: Option<Copied>,
+    // failing while the server is unreachable, the last user and rooms stay on screen
+    pub backoff: Backoff,
+    // details of the highlighted room, and which room they were last fetched for
+    pub preview: Option<RoomDetails>,
+    pub preview_of: Option<Uuid>,
+    // room the player sat at last time, offered again once on launch
+    pub rejoin: Option<(RoomInfo, RoomDetails)>,
+    // queued for a seat at a full table
+    pub waitlisted: Option<Waitlisted>,
 }
 
 impl LobbyScreenData {
     pub async fn refresh(&mut self, client: &mut Client) -> color_eyre::Result<()> {
+        for update in take_lobby_updates().await {
+            self.apply_update(update);
+        }
         if Utc::now() > self.next_refresh_time {
-            let data = lobby_screen_data(client).await?;
-            self.user = data.user;
-            self.rooms = data.rooms;
-            self.next_refresh_time = data.next_refresh_time;
+            match try_join!(client.get_profile(), client.find_rooms(&self.room_query)) {
+                Ok((user, rooms)) => {
+                    self.user = user;
+                    self.rooms = rooms;
+                    self.backoff.reset();
+                    self.next_refresh_time = Utc::now() + refresh_interval();
+                    self.preview_of = None;
+                }
+                Err(e) if is_offline(&e) => {
+                    self.next_refresh_time = Utc::now() + self.backoff.failed();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if !self.is_stale() {
+            self.refresh_preview(client).await;
         }
         Ok(())
     }
 
-    pub fn update_cursor_position(&mut self, username_area: &Rect) {
-        if self.username_in_focus {
+    fn apply_update(&mut self, update: LobbyUpdate) {
+        let room_id = match update {
+            LobbyUpdate::Room(room) => {
+                let room_id = room.room_id;
+                let is_listed = self
+                    .room_query
+                    .currency
+                    .is_none_or(|currency| currency == room.currency);
+                match self.rooms.iter_mut().find(|r| r.room_id == room_id) {
+                    Some(listed) => *listed = room,
+                    None if is_listed => self.rooms.push(room),
+                    None => {}
+                }
+                room_id
+            }
+            LobbyUpdate::Pace {
+                room_id,
+                hands_per_hour,
+            } => {
+                if let Some(listed) = self.rooms.iter_mut().find(|r| r.room_id == room_id) {
+                    listed.hands_per_hour = hands_per_hour;
+                }
+                room_id
+            }
+            LobbyUpdate::Activity { room_id, activity } => {
+                if let Some(listed) = self.rooms.iter_mut().find(|r| r.room_id == room_id) {
+                    listed.stage = activity.stage;
+                    listed.average_pot = activity.average_pot.map(|pot| pot as i32);
+                    listed.last_hand_at = activity.last_hand_at;
+                }
+                room_id
+            }
+            LobbyUpdate::Closed { room_id } => {
+                self.rooms.retain(|r| r.room_id != room_id);
+                room_id
+            }
+        };
+        // the preview of a room that changed is fetched again
+        if self.preview_of == Some(room_id) {
+            self.preview_of = None;
+        }
+    }
+
+    fn selected_room(&self) -> Option<&RoomInfo> {
+        self.table_state
+            .selected()
+            .and_then(|selected| self.rooms.get(selected))
+    }
+
+    async fn refresh_preview(&mut self, client: &Client) {
+        let room_id = self.selected_room().map(|room| room.room_id);
+        if room_id == self.preview_of {
+            return;
+        }
+        self.preview_of = room_id;
+        self.preview = match room_id {
+            Some(room_id) => client.get_room_details(room_id).await.ok(),
+            None => None,
+        };
+    }
+
+    /// Offers the room joined last time, if it is still open and has a free seat
+    pub async fn offer_rejoin(&mut self, client: &Client) {
+        let Some(room_id) = last_room() else {
+            return;
+        };
+        let Some(room) = self.rooms.iter().find(|r| r.room_id == room_id).cloned() else {
+            return;
+        };
+        if let Ok(details) = client.get_room_details(room_id).await {
+            if seated(&details) < details.max_players {
+                self.rejoin = Some((room, details));
+            }
+        }
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.backoff.is_failing()
+    }
+
+    pub fn prompt_buy_in(&mut self, room: RoomInfo) {
+        self.buy_in_input = Input::new(DEFAULT_BUY_IN.to_string());
+        self.buy_in_room = Some(room);
+    }
+
+    pub fn prompt_password(&mut self, room: RoomInfo, buy_in: i64) {
+        self.password_input = Input::default();
+        self.password_room = Some((room, buy_in));
+    }
+
+    pub fn update_cursor_position(&mut self, username_area: &Rect, buy_in_area: &Rect) {
+        if self.password_room.is_some() {
+            self.cursor_position = Some(
+                (
+                    buy_in_area.x + self.password_input.visual_cursor() as u16 + 1,
+                    buy_in_area.y + 1,
+                )
+                    .into(),
+            );
+        } else if self.buy_in_room.is_some() {
+            self.cursor_position = Some(
+                (
+                    buy_in_area.x + self.buy_in_input.visual_cursor() as u16 + 1,
+                    buy_in_area.y + 1,
+                )
+                    .into(),
+            );
+        } else if self.username_in_focus {
             self.cursor_position = Some(
                 (
                     username_area.x + self.username_input.visual_cursor() as u16 + 1,
@@ -71,7 +252,13 @@ impl LobbyScreenData {
             ]
             .into()
         } else {
-            vec!["Edit ".into(), "<CTRL + E>".light_blue().bold()].into()
+            vec![
+                "Edit ".into(),
+                "<CTRL + E>".light_blue().bold(),
+                format!(" {} ({}) ", t(Text::Avatar), self.user.avatar.as_ref()).into(),
+                "<CTRL + A>".light_blue().bold(),
+            ]
+            .into()
         }
     }
 }
@@ -79,56 +266,458 @@ impl LobbyScreenData {
 #[async_trait::async_trait]
 impl OnTick for LobbyScreenData {
     async fn on_tick(&mut self, client: &mut Client) -> color_eyre::Result<()> {
+        if let Some(invite) = take_friend_invites().await.pop() {
+            self.friend_invite = Some(invite);
+        }
+        if let Ok(waitlisted) = WAITLISTED_STATE.try_read().as_deref() {
+            self.waitlisted = waitlisted.as_ref().map(|w| w.data.clone());
+        }
         self.refresh(client).await
     }
 }
 
-pub struct LobbyWidget;
+impl LobbyScreenData {
+    /// The table the player was queued for, once a seat there is theirs
+    pub async fn take_queued_seat(
+        &mut self,
+        client: &mut Client,
+    ) -> color_eyre::Result<Option<ScreenChange>> {
+        if self.waitlisted.is_none() || SEATED_STATE.read().await.is_none() {
+            return Ok(None);
+        }
+        self.waitlisted = None;
+        reset_waitlisted_state().await;
+        wait_for_game(client).await.map(Some)
+    }
+}
+
+pub struct LobbyWidget {
+    pub theme: Theme,
+}
 
 impl StatefulWidget for LobbyWidget {
     type State = LobbyScreenData;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let theme = self.theme;
         let [user, rooms] =
             Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
         let [user_left, user_right] = Layout::split_equal(user, Direction::Horizontal);
         Paragraph::new(state.username_input.value())
             .block(
                 Block::bordered()
-                    .title("Username")
+                    .title(t(Text::Username))
                     .title_bottom(state.username_input_instructions().right_aligned()),
             )
             .render(user_left, buf);
-        Paragraph::new(state.user.balance.to_string())
-            .block(Block::bordered().title("Balance"))
+        let balance = format!(
+            "{} ({} {})",
+            state.user.balance,
+            state.user.premium_balance,
+            t(Text::Premium)
+        );
+        Paragraph::new(balance)
+            .block(
+                Block::bordered().title(t(Text::Balance)).title_bottom(
+                    Line::from(vec![
+                        format!("{} ", t(Text::FreeChips)).into(),
+                        "<CTRL + T>".light_blue().bold(),
+                        format!(" {} ", t(Text::Stats)).into(),
+                        "<CTRL + S>".light_blue().bold(),
+                        format!(" {} ", t(Text::Quiz)).into(),
+                        "<CTRL + G>".light_blue().bold(),
+                        format!(" {} ", t(Text::Transactions)).into(),
+                        "<CTRL + L>".light_blue().bold(),
+                        format!(" {} ", t(Text::Friends)).into(),
+                        "<CTRL + F>".light_blue().bold(),
+                        format!(" {} ", t(Text::Leaderboard)).into(),
+                        "<CTRL + R>".light_blue().bold(),
+                        format!(" {} ", t(Text::Settings)).into(),
+                        "<CTRL + O>".light_blue().bold(),
+                        format!(" {} ", t(Text::LogOut)).into(),
+                        "<CTRL + X>".red().bold(),
+                    ])
+                    .right_aligned(),
+                ),
+            )
             .render(user_right, buf);
-        let header = ["Room", "Player Count"]
-            .into_iter()
-            .map(Cell::from)
-            .collect::<Row>()
-            .height(1);
+        let header = [
+            Text::Room,
+            Text::Players,
+            Text::Blinds,
+            Text::Stage,
+            Text::AvgPot,
+            Text::Activity,
+        ]
+        .into_iter()
+        .map(t)
+        .map(Cell::from)
+        .collect::<Row>()
+        .height(1);
         let selected_row_style = Style::default().add_modifier(Modifier::REVERSED);
         let rows = state
             .rooms
             .iter()
             .map(|room| {
-                [
-                    room.room_id.to_string(),
-                    format!("{}/{}", room.player_count, MAX_NUM_OF_PLAYERS),
-                ]
+                Row::new([
+                    Cell::from(if room.is_private {
+                        format!("🔒 {}", room.room_id)
+                    } else {
+                        room.room_id.to_string()
+                    }),
+                    Cell::from(format!("{}/{}", room.player_count, room.max_players)),
+                    Cell::from(match room.currency {
+                        ChipCurrency::Play => format!("{}/{}", room.small_blind, room.big_blind),
+                        ChipCurrency::Premium => {
+                            format!("{}/{} ★", room.small_blind, room.big_blind)
+                        }
+                    }),
+                    Cell::from(room.stage.line()),
+                    Cell::from(
+                        room.average_pot
+                            .map(|pot| pot.to_string())
+                            .unwrap_or_default(),
+                    ),
+                    Cell::from(activity_line(room, &theme)),
+                ])
             })
-            .map(Row::new)
             .collect::<Vec<_>>();
-        let table = Table::new(rows, Constraint::from_percentages([70, 30]))
+        let table = Table::new(rows, Constraint::from_percentages([36, 10, 9, 15, 10, 20]))
             .block(
                 Block::bordered()
-                    .title(Line::from("Rooms").centered())
-                    .title_bottom(Line::from("Press Esc to quit").centered()),
+                    .title(rooms_title(state.is_stale(), state.room_query.currency).centered())
+                    .title(waitlisted_title(state.waitlisted.as_ref()).right_aligned())
+                    .title_bottom(
+                        Line::from(vec![
+                            format!("{} ", t(Text::CopyRoomId)).into(),
+                            "<CTRL + Y>".light_blue().bold(),
+                            format!(" {} ", t(Text::Currency)).into(),
+                            "<CTRL + U>".light_blue().bold(),
+                            format!(" {} ", t(Text::Replays)).into(),
+                            "<CTRL + P>".light_blue().bold(),
+                            format!(" {} ", t(Text::Quit)).into(),
+                            "<Esc>".red().bold(),
+                        ])
+                        .centered(),
+                    ),
             )
             .row_highlight_style(selected_row_style)
             .header(header);
-        StatefulWidget::render(table, rooms, buf, &mut state.table_state);
-        state.update_cursor_position(&user_left);
+        match &state.preview {
+            Some(preview) => {
+                let [rooms, preview_area] =
+                    Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                        .areas(rooms);
+                StatefulWidget::render(table, rooms, buf, &mut state.table_state);
+                preview_pane(preview, preview_area, &theme, buf);
+            }
+            None => StatefulWidget::render(table, rooms, buf, &mut state.table_state),
+        }
+
+        let [_, buy_in_area, _] = Layout::vertical([
+            Constraint::Fill(1),
+            Constraint::Length(3),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+        let [buy_in_area] = Layout::horizontal([Constraint::Max(50)])
+            .flex(Flex::Center)
+            .areas(buy_in_area);
+        if let Some(room) = &state.buy_in_room {
+            Clear.render(buy_in_area, buf);
+            Paragraph::new(state.buy_in_input.value())
+                .block(
+                    Block::bordered()
+                        .title(format!("Buy-in for room {}", room.room_id))
+                        .title_bottom(
+                            Line::from(vec![
+                                "Join ".into(),
+                                "<Enter>".light_blue().bold(),
+                                " Cancel ".into(),
+                                "<Esc>".red().bold(),
+                            ])
+                            .right_aligned(),
+                        ),
+                )
+                .render(buy_in_area, buf);
+        }
+        if let Some((room, _)) = &state.password_room {
+            Clear.render(buy_in_area, buf);
+            Paragraph::new("*".repeat(state.password_input.value().chars().count()))
+                .block(
+                    Block::bordered()
+                        .title(format!("Password for room {}", room.room_id))
+                        .title_bottom(
+                            Line::from(vec![
+                                "Join ".into(),
+                                "<Enter>".light_blue().bold(),
+                                " Cancel ".into(),
+                                "<Esc>".red().bold(),
+                            ])
+                            .right_aligned(),
+                        ),
+                )
+                .render(buy_in_area, buf);
+        }
+        if let Some((room, details)) = &state.rejoin {
+            Clear.render(buy_in_area, buf);
+            Paragraph::new(format!(
+                "Rejoin room {} ({}/{} seated)?",
+                short_id(room.room_id),
+                seated(details),
+                details.max_players
+            ))
+            .block(
+                Block::bordered().title("Last room").title_bottom(
+                    Line::from(vec![
+                        "Rejoin ".into(),
+                        "<Enter>".light_blue().bold(),
+                        " Dismiss ".into(),
+                        "<Esc>".red().bold(),
+                    ])
+                    .right_aligned(),
+                ),
+            )
+            .render(buy_in_area, buf);
+        }
+        if let Some(stats) = &state.stats {
+            stats_popup(area, stats, buf);
+        }
+        if let Some(quiz) = &state.quiz {
+            quiz_popup(area, quiz, &theme, buf);
+        }
+        if let Some(transactions) = &mut state.transactions {
+            transactions_popup(area, transactions, &theme, buf);
+        }
+        if let Some(friends) = &mut state.friends {
+            friends_popup(area, friends, &theme, buf);
+        }
+        if let Some(leaderboard) = &mut state.leaderboard {
+            leaderboard_popup(area, leaderboard, &theme, buf);
+        }
+        if let Some(invite) = &state.friend_invite {
+            friend_invite_popup(area, invite, &theme, buf);
+        }
+        if let Some(copied) = &state.copied {
+            copied_popup(area, copied, buf);
+        }
+        state.update_cursor_position(&user_left, &buy_in_area);
+    }
+}
+
+fn preview_pane(preview: &RoomDetails, area: Rect, theme: &Theme, buf: &mut Buffer) {
+    let mut lines = vec![
+        Line::from(format!(
+            "Blinds {}/{}",
+            preview.small_blind, preview.big_blind
+        )),
+        preview.stage.line(),
+        Line::from(format!(
+            "Seated {}/{}   Waiting {}",
+            preview.seats.len(),
+            preview.max_players,
+            preview.waitlist
+        )),
+    ];
+    if preview.queued > 0 {
+        lines.push(Line::from(format!("Queued for a seat {}", preview.queued)));
+    }
+    if preview.rake.percent > 0 {
+        let rake = format!("Rake {}% up to {}", preview.rake.percent, preview.rake.cap);
+        lines.push(Line::from(rake));
+    }
+    if let Some(closes_at) = preview.closes_at {
+        lines.push(Line::from(format!(
+            "Closes at {}",
+            closes_at.with_timezone(&Local).format("%H:%M")
+        )));
+    }
+    lines.push(Line::default());
+    lines.extend(preview.seats.iter().map(|seat| {
+        let mut line = Line::from(format!(
+            "Seat {}  {}  {}",
+            seat.seat + 1,
+            seat.name,
+            seat.chips
+        ));
+        if seat.is_bot {
+            line.push_span(" bot".fg(theme.muted));
+        }
+        if seat.is_sitting_out {
+            line.push_span(" sitting out".fg(theme.muted));
+        }
+        line
+    }));
+    Paragraph::new(lines)
+        .block(Block::bordered().title(Line::from("Table").centered()))
+        .render(area, buf);
+}
+
+fn stats_popup(area: Rect, stats: &ProfileStats, buf: &mut Buffer) {
+    let lifetime = &stats.lifetime;
+    let mut lifetime_lines = vec![
+        Line::from(format!(
+            "Hands played: {}   Won: {} ({:.0}%)",
+            lifetime.hands_played,
+            lifetime.hands_won,
+            lifetime.win_rate() * 100.0
+        )),
+        Line::from(format!("Net winnings: {:+}", lifetime.net_winnings)),
+        Line::from(format!(
+            "VPIP: {:.0}%   Showdowns: {} ({:.0}%)",
+            lifetime.vpip() * 100.0,
+            lifetime.showdowns,
+            lifetime.showdown_rate() * 100.0
+        )),
+    ];
+    if lifetime.all_in_hands > 0 {
+        lifetime_lines.push(Line::from(format!(
+            "Luck-adjusted winnings: {:+}",
+            lifetime.luck_adjusted_winnings()
+        )));
+        lifetime_lines.push(Line::from(format!(
+            "All-in luck: {:+} over {} hands",
+            lifetime.luck(),
+            lifetime.all_in_hands
+        )));
+    }
+    if lifetime.timeout_folds + lifetime.disconnect_folds > 0 {
+        lifetime_lines.push(Line::from(format!(
+            "Timed out: {}   Left mid hand: {}",
+            lifetime.timeout_folds, lifetime.disconnect_folds
+        )));
+    }
+    // lifetime lines, a heading, then the table and its header
+    let table_height = stats.hand_classes.len().max(1) as u16 + 1;
+    let [popup_area] = Layout::vertical([Constraint::Length(
+        lifetime_lines.len() as u16 + table_height + 3,
+    )])
+    .flex(Flex::Center)
+    .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Max(60)])
+        .flex(Flex::Center)
+        .areas(popup_area);
+    Clear.render(popup_area, buf);
+    let block = Block::bordered()
+        .title(Line::from("Your stats").centered())
+        .title_bottom(Line::from(vec!["Close ".into(), "<Esc>".red().bold()]).right_aligned());
+    let inner_area = block.inner(popup_area);
+    block.render(popup_area, buf);
+    let [lifetime_area, heading_area, table_area] = Layout::vertical([
+        Constraint::Length(lifetime_lines.len() as u16),
+        Constraint::Length(1),
+        Constraint::Min(0),
+    ])
+    .areas(inner_area);
+    Paragraph::new(lifetime_lines).render(lifetime_area, buf);
+    Paragraph::new("Showdowns by hand".bold())
+        .centered()
+        .render(heading_area, buf);
+    if stats.hand_classes.is_empty() {
+        Paragraph::new("No showdowns yet")
+            .centered()
+            .render(table_area, buf);
+        return;
+    }
+    let header = ["Hand", "Showdowns", "Won", "Win rate"]
+        .into_iter()
+        .map(Cell::from)
+        .collect::<Row>()
+        .bold();
+    let rows = stats.hand_classes.iter().map(|class| {
+        Row::new([
+            class.hand_class.label().to_string(),
+            class.showdowns.to_string(),
+            class.wins.to_string(),
+            format!("{:.0}%", class.win_rate() * 100.0),
+        ])
+    });
+    let table = Table::new(rows, Constraint::from_percentages([40, 20, 20, 20])).header(header);
+    Widget::render(table, table_area, buf);
+}
+
+impl LobbyScreenData {
+    async fn on_buy_in_key_event(
+        &mut self,
+        key: KeyEvent,
+        client: &mut Client,
+    ) -> color_eyre::Result<ScreenChange> {
+        let change = match (key.kind, key.modifiers, key.code) {
+            (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Esc) => {
+                self.buy_in_room = None;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Enter) => {
+                let room = self.buy_in_room.take().wrap_err(Error::NoRoomFound)?;
+                let buy_in = self.buy_in_input.value().parse()?;
+                if room.is_private {
+                    self.prompt_password(room, buy_in);
+                    ScreenChange::None
+                } else {
+                    join_room(client, room.room_id, buy_in, None).await?
+                }
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('c')) => ScreenChange::Quit,
+            (KeyEventKind::Press, _, KeyCode::Char(c)) if !c.is_numeric() => ScreenChange::None,
+            _ => {
+                self.buy_in_input.handle_event(&Event::Key(key));
+                ScreenChange::None
+            }
+        };
+        Ok(change)
+    }
+}
+
+impl LobbyScreenData {
+    async fn on_password_key_event(
+        &mut self,
+        key: KeyEvent,
+        client: &mut Client,
+    ) -> color_eyre::Result<ScreenChange> {
+        let change = match (key.kind, key.modifiers, key.code) {
+            (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Esc) => {
+                self.password_room = None;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Enter) => {
+                let (room, buy_in) = self.password_room.take().wrap_err(Error::NoRoomFound)?;
+                let password = self.password_input.value().to_string();
+                join_room(client, room.room_id, buy_in, Some(password)).await?
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('c')) => ScreenChange::Quit,
+            _ => {
+                self.password_input.handle_event(&Event::Key(key));
+                ScreenChange::None
+            }
+        };
+        Ok(change)
+    }
+}
+
+impl LobbyScreenData {
+    async fn on_rejoin_key_event(
+        &mut self,
+        key: KeyEvent,
+        client: &mut Client,
+    ) -> color_eyre::Result<ScreenChange> {
+        let change = match (key.kind, key.modifiers, key.code) {
+            (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Esc) => {
+                self.rejoin = None;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Enter) => {
+                let (room, _) = self.rejoin.take().wrap_err(Error::NoRoomFound)?;
+                if room.is_private {
+                    self.prompt_password(room, DEFAULT_BUY_IN);
+                    ScreenChange::None
+                } else {
+                    join_room(client, room.room_id, DEFAULT_BUY_IN, None).await?
+                }
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('c')) => ScreenChange::Quit,
+            _ => ScreenChange::None,
+        };
+        Ok(change)
     }
 }
 
@@ -139,11 +728,163 @@ impl OnKeyEvent for LobbyScreenData {
         key: KeyEvent,
         client: &mut Client,
     ) -> color_eyre::Result<ScreenChange> {
+        if self.stats.is_some() {
+            if let (KeyEventKind::Press, KeyCode::Esc | KeyCode::Enter) = (key.kind, key.code) {
+                self.stats = None;
+            }
+            return Ok(ScreenChange::None);
+        }
+        if let Some(quiz) = &mut self.quiz {
+            if quiz.on_key_event(key)? {
+                self.quiz = None;
+            }
+            return Ok(ScreenChange::None);
+        }
+        if let Some(transactions) = &mut self.transactions {
+            if transactions.on_key_event(key, client).await? {
+                self.transactions = None;
+            }
+            return Ok(ScreenChange::None);
+        }
+        if let Some(friends) = &mut self.friends {
+            if friends.on_key_event(key, client).await? {
+                self.friends = None;
+            }
+            return Ok(ScreenChange::None);
+        }
+        if let Some(leaderboard) = &mut self.leaderboard {
+            if leaderboard.on_key_event(key, client).await? {
+                self.leaderboard = None;
+            }
+            return Ok(ScreenChange::None);
+        }
+        if self.friend_invite.is_some() {
+            match (key.kind, key.code) {
+                (KeyEventKind::Press, KeyCode::Enter) => {
+                    let invite = self.friend_invite.take().wrap_err(Error::NoRoomFound)?;
+                    self.prompt_buy_in(invite.room);
+                }
+                (KeyEventKind::Press, KeyCode::Esc) => self.friend_invite = None,
+                _ => {}
+            }
+            return Ok(ScreenChange::None);
+        }
+        if self.copied.is_some() {
+            if let (KeyEventKind::Press, KeyCode::Esc | KeyCode::Enter) = (key.kind, key.code) {
+                self.copied = None;
+            }
+            return Ok(ScreenChange::None);
+        }
+        if self.rejoin.is_some() {
+            return self.on_rejoin_key_event(key, client).await;
+        }
+        if self.password_room.is_some() {
+            return self.on_password_key_event(key, client).await;
+        }
+        if self.buy_in_room.is_some() {
+            return self.on_buy_in_key_event(key, client).await;
+        }
         let change = match (key.kind, key.modifiers, key.code) {
             (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Esc) => {
-                LoginScreenData::default().into()
+                LoginScreenData::new(None, client.base_url()).into()
             }
             (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('c')) => ScreenChange::Quit,
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('s')) => {
+                self.stats = Some(client.get_profile_stats().await?);
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('t')) => {
+                self.user.balance = client.top_up().await?.balance_after;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('g')) => {
+                self.quiz = Some(Quiz::new()?);
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('l')) => {
+                self.transactions = Some(Box::new(Transactions::open(client).await?));
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('f')) => {
+                let can_invite = self.user.current_room.is_some();
+                self.friends = Some(Friends::open(client, can_invite).await?);
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('r')) => {
+                self.leaderboard = Some(Leaderboard::open(client, self.user.id).await?);
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('y'))
+                if !self.username_in_focus =>
+            {
+                let room = self
+                    .table_state
+                    .selected()
+                    .and_then(|selected| self.rooms.get(selected))
+                    .wrap_err(Error::NoRoomFound)?;
+                self.copied = Some(copy("room ID", room.room_id.to_string()));
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('u'))
+                if !self.username_in_focus =>
+            {
+                self.room_query.currency = match self.room_query.currency {
+                    None => Some(ChipCurrency::Play),
+                    Some(ChipCurrency::Play) => Some(ChipCurrency::Premium),
+                    Some(ChipCurrency::Premium) => None,
+                };
+                self.rooms = client.find_rooms(&self.room_query).await?;
+                self.table_state.select_first();
+                self.preview_of = None;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('p'))
+                if !self.username_in_focus =>
+            {
+                let room = self
+                    .table_state
+                    .selected()
+                    .and_then(|selected| self.rooms.get(selected))
+                    .wrap_err(Error::NoRoomFound)?;
+                replay_screen_data(client, room.room_id).await?.into()
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('w'))
+                if self.waitlisted.is_some() =>
+            {
+                client.leave().await?;
+                reset_waitlisted_state().await;
+                self.waitlisted = None;
+                ScreenChange::None
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('o'))
+                if !self.username_in_focus =>
+            {
+                SettingsScreenData::new(config::theme()).into()
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('x'))
+                if !self.username_in_focus =>
+            {
+                client.logout().await?;
+                // the next launch starts at the login screen too
+                let _ = TOKEN_MANAGER
+                    .delete_credential()
+                    .inspect_err(|e| log::warn!("Failed to forget refresh token: {}", e));
+                LoginScreenData::new(None, client.base_url()).into()
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('a'))
+                if !self.username_in_focus =>
+            {
+                let index = Avatar::ALL
+                    .iter()
+                    .position(|avatar| *avatar == self.user.avatar)
+                    .unwrap_or_default();
+                let request = UpdateProfileRequest {
+                    username: self.user.name.clone(),
+                    avatar: Some(Avatar::ALL[(index + 1) % Avatar::ALL.len()]),
+                };
+                self.user = client.update_profile(request).await?;
+                ScreenChange::None
+            }
             (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('e')) => {
                 self.username_input = Input::new(self.user.name.clone());
                 self.username_in_focus = self.username_in_focus.not();
@@ -173,7 +914,10 @@ impl OnKeyEvent for LobbyScreenData {
                 if self.username_in_focus {
                     let username = self.username_input.value().to_string();
                     let user = client
-                        .update_profile(UpdateProfileRequest { username })
+                        .update_profile(UpdateProfileRequest {
+                            username,
+                            avatar: None,
+                        })
                         .await?;
                     self.username_input = Input::new(user.name.clone());
                     self.user = user;
@@ -186,27 +930,11 @@ impl OnKeyEvent for LobbyScreenData {
                         .and_then(|selected| self.rooms.get(selected));
 
                     let room = room.wrap_err(Error::NoRoomFound)?;
-                    client
-                        .join_game(JoinGameRequest {
-                            room_id: room.room_id,
-                            buy_in: 100,
-                        })
-                        .await?;
-
-                    // poll GAME_STATE until it is Some
-                    loop {
-                        if let Ok(Some(game_state)) = GAME_STATE.try_read().as_deref() {
-                            let hand = HAND_STATE.read().await;
-                            let game = in_game_data(
-                                client.user.as_ref().map(|u| u.id).wrap_err("No user")?,
-                                hand.as_ref()
-                                    .map_or(PlayerHand::default(), |h| h.data.clone()),
-                                game_state.data.clone(),
-                            );
-                            return Ok(ScreenChange::Switch(Screen::InGame(game)));
-                        } else {
-                            sleep(Duration::from_secs(1)).await;
-                        }
+                    if room.is_private {
+                        self.prompt_password(room.clone(), DEFAULT_BUY_IN);
+                        ScreenChange::None
+                    } else {
+                        join_room(client, room.room_id, DEFAULT_BUY_IN, None).await?
                     }
                 }
             }
@@ -221,20 +949,169 @@ impl OnKeyEvent for LobbyScreenData {
     }
 }
 
+async fn join_room(
+    client: &mut Client,
+    room_id: Uuid,
+    buy_in: i64,
+    password: Option<String>,
+) -> color_eyre::Result<ScreenChange> {
+    let last_seen_seq = client.last_seen_seq(room_id);
+    reset_waitlisted_state().await;
+    client
+        .join_game(JoinGameRequest {
+            room_id,
+            buy_in,
+            last_seen_seq,
+            password,
+            seat: None,
+        })
+        .await?;
+    let change = wait_for_game(client).await?;
+    let _ = LAST_ROOM
+        .set_password(&room_id.to_string())
+        .inspect_err(|e| log::warn!("Failed to remember last room: {}", e));
+    Ok(change)
+}
+
+fn last_room() -> Option<Uuid> {
+    LAST_ROOM.get_password().ok()?.parse().ok()
+}
+
+// players waiting for the next hand hold a seat too
+fn seated(details: &RoomDetails) -> usize {
+    details.seats.len() + details.waitlist
+}
+
+// how fast the room plays, or since when nothing was played in it
+fn activity_line(room: &RoomInfo, theme: &Theme) -> Line<'static> {
+    let Some(last_hand_at) = room.last_hand_at else {
+        return Line::from("No hands yet").fg(theme.muted);
+    };
+    if Utc::now() - last_hand_at > IDLE_AFTER {
+        return Line::from(format!(
+            "Idle since {}",
+            last_hand_at.with_timezone(&Local).format("%H:%M")
+        ))
+        .fg(theme.muted);
+    }
+    match room.hands_per_hour {
+        Some(hands) => Line::from(format!("~{} hands/hour", hands)),
+        None => Line::from(format!(
+            "Last hand {}",
+            last_hand_at.with_timezone(&Local).format("%H:%M")
+        )),
+    }
+}
+
+fn waitlisted_title(waitlisted: Option<&Waitlisted>) -> Line<'static> {
+    let Some(waitlisted) = waitlisted else {
+        return Line::default();
+    };
+    Line::from(vec![
+        format!(
+            "Number {} in line for {} ",
+            waitlisted.position,
+            short_id(waitlisted.room_id)
+        )
+        .light_yellow(),
+        "Leave the line ".into(),
+        "<CTRL + W>".light_blue().bold(),
+    ])
+}
+
+fn short_id(room_id: Uuid) -> String {
+    room_id.to_string().chars().take(8).collect()
+}
+
+/// Waits for the first game state of the table just joined, then switches to it
+pub async fn wait_for_game(client: &mut Client) -> color_eyre::Result<ScreenChange> {
+    // poll GAME_STATE until it is Some
+    loop {
+        // a full table queued the player instead, the lobby waits for the seat
+        if WAITLISTED_STATE.read().await.is_some() && SEATED_STATE.read().await.is_none() {
+            return Ok(ScreenChange::None);
+        }
+        if let Ok(Some(game_state)) = GAME_STATE.try_read().as_deref() {
+            let hand = HAND_STATE.read().await;
+            let game = in_game_data(
+                client.user.as_ref().map(|u| u.id).wrap_err("No user")?,
+                hand.as_ref()
+                    .map_or(PlayerHand::default(), |h| h.data.clone()),
+                game_state.data.clone(),
+            );
+            return Ok(ScreenChange::Switch(Screen::InGame(game)));
+        } else if let Some(message) = take_service_error().await {
+            // the server turned the join down
+            bail!(message);
+        } else {
+            sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+/// Lobby with the buy-in prompt open for the room behind an invite link
+pub async fn invited_lobby_screen_data(
+    client: &mut Client,
+    invite_link: &str,
+) -> color_eyre::Result<LobbyScreenData> {
+    let mut lobby = lobby_screen_data(client).await?;
+    let code = RoomInvite::code_from_link(invite_link).wrap_err(Error::InvalidInviteCode)?;
+    let room = client.get_invited_room(code).await?;
+    lobby.prompt_buy_in(room);
+    Ok(lobby)
+}
+
+/// Lobby as first shown after launch, offering the room joined last time
+pub async fn launch_lobby_screen_data(client: &mut Client) -> color_eyre::Result<LobbyScreenData> {
+    let mut lobby = lobby_screen_data(client).await?;
+    lobby.offer_rejoin(client).await;
+    Ok(lobby)
+}
+
 pub async fn lobby_screen_data(client: &mut Client) -> color_eyre::Result<LobbyScreenData> {
+    // changes pushed while away from the lobby are already in the fetched list
+    take_lobby_updates().await;
     let (user, rooms) = try_join!(client.get_profile(), client.get_rooms())?;
     let username = user.name.clone();
     Ok(LobbyScreenData {
         user,
         rooms,
+        room_query: RoomQuery::default(),
         table_state: TableState::default().with_selected(0),
-        next_refresh_time: Utc::now() + Duration::from_secs(5),
+        next_refresh_time: Utc::now() + refresh_interval(),
         username_input: Input::new(username),
         cursor_position: None,
         username_in_focus: false,
+        buy_in_room: None,
+        buy_in_input: Input::default(),
+        password_room: None,
+        password_input: Input::default(),
+        stats: None,
+        quiz: None,
+        transactions: None,
+        friends: None,
+        leaderboard: None,
+        friend_invite: None,
+        copied: None,
+        backoff: Backoff::default(),
+        preview: None,
+        preview_of: None,
+        rejoin: None,
+        waitlisted: None,
     })
 }
 
+fn rooms_title(is_stale: bool, currency: Option<ChipCurrency>) -> Line<'static> {
+    let mut title = vec![t(Text::Rooms).into()];
+    if let Some(currency) = currency {
+        title.push(format!(" in {} chips", currency.as_ref()).into());
+    }
+    if is_stale {
+        title.push(" stale (offline)".red().bold());
+    }
+    Line::from(title)
+}
+
 impl From<LobbyScreenData> for ScreenChange {
     fn from(data: LobbyScreenData) -> Self {
         ScreenChange::Switch(Screen::Lobby(data))
@@ -1,22 +1,34 @@
 use crate::data::{OnKeyEvent, OnTick, Screen, ScreenChange};
 use crate::extension::Splittable;
-use crate::{data, lobby, TOKEN_MANAGER};
+use crate::theme::Theme;
+use crate::{config, lobby, TOKEN_MANAGER};
 use client::client::Client;
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::buffer::Buffer;
 use ratatui::layout::{Constraint, Direction, Flex, Layout, Position, Rect};
-use ratatui::prelude::{Color, Masked, Modifier, Span, StatefulWidget, Style, Widget};
+use ratatui::prelude::{Masked, Modifier, Span, StatefulWidget, Style, Widget};
 use ratatui::widgets::{Block, Paragraph};
 use tui_input::backend::crossterm::EventHandler;
 use tui_input::Input;
-use types::domain::{LoginRequest, SignupRequest};
+use types::domain::{ForgotPasswordRequest, LoginRequest, ResetPasswordRequest, SignupRequest};
+
+// bots seated with the player in offline practice games
+const PRACTICE_BOTS: usize = 3;
 
 #[derive(Debug, Default)]
 pub struct LoginScreenData {
+    // address of the server to log in to, the configured one to begin with
+    server_input: Input,
     email_input: Input,
     password_input: Input,
     focus: LoginScreenFocus,
     pub(crate) cursor_position: Option<Position>,
+    // invite link passed via --join, resolved once the player is logged in
+    pub(crate) join_code: Option<String>,
+    // shown under the buttons, e.g. to open the emailed verification link
+    notice: Option<String>,
+    // the emailed code being typed in, while resetting a forgotten password
+    reset_code: Option<Input>,
 }
 
 impl From<LoginScreenData> for ScreenChange {
@@ -26,63 +38,173 @@ impl From<LoginScreenData> for ScreenChange {
 }
 
 impl LoginScreenData {
+    pub fn new(join_code: Option<String>, server_url: &str) -> Self {
+        Self {
+            server_input: Input::new(server_url.to_string()),
+            join_code,
+            ..Default::default()
+        }
+    }
+
+    // points the client at the server typed in, kept for the next launch when it changed
+    fn apply_server(&self, client: &mut Client) -> color_eyre::Result<()> {
+        let server_url = config::normalize_server_url(self.server_input.value())?;
+        if server_url != client.base_url() {
+            let _ = config::save_server_url(&server_url)
+                .inspect_err(|e| log::warn!("Failed to save server address: {}", e));
+            client.set_base_url(server_url);
+        }
+        Ok(())
+    }
+
+    async fn lobby_screen_change(&self, client: &mut Client) -> color_eyre::Result<ScreenChange> {
+        let lobby = match &self.join_code {
+            Some(code) => lobby::invited_lobby_screen_data(client, code).await?,
+            None => lobby::launch_lobby_screen_data(client).await?,
+        };
+        Ok(lobby.into())
+    }
+
     fn switch_focus(&mut self) {
         match self.focus {
+            LoginScreenFocus::Server => {
+                self.focus = LoginScreenFocus::Email;
+            }
             LoginScreenFocus::Email => {
                 self.focus = LoginScreenFocus::Password;
             }
-            LoginScreenFocus::Password => {
+            LoginScreenFocus::Password if self.reset_code.is_some() => {
+                self.focus = LoginScreenFocus::ResetCode;
+            }
+            LoginScreenFocus::Password | LoginScreenFocus::ResetCode => {
                 self.focus = LoginScreenFocus::Login;
             }
             LoginScreenFocus::Login => {
                 self.focus = LoginScreenFocus::Signup;
             }
             LoginScreenFocus::Signup => {
-                self.focus = LoginScreenFocus::Email;
+                self.focus = LoginScreenFocus::Practice;
+            }
+            LoginScreenFocus::Practice => {
+                self.focus = LoginScreenFocus::Tutorial;
+            }
+            LoginScreenFocus::Tutorial => {
+                self.focus = LoginScreenFocus::Server;
             }
         }
     }
 
     pub(crate) fn handle_input_event(&mut self, key: KeyEvent) {
         match self.focus {
+            LoginScreenFocus::Server => {
+                self.server_input.handle_event(&Event::Key(key));
+            }
             LoginScreenFocus::Email => {
                 self.email_input.handle_event(&Event::Key(key));
             }
             LoginScreenFocus::Password => {
                 self.password_input.handle_event(&Event::Key(key));
             }
+            LoginScreenFocus::ResetCode => {
+                if let Some(code) = &mut self.reset_code {
+                    code.handle_event(&Event::Key(key));
+                }
+            }
             _ => {}
         }
     }
 
+    async fn log_in(&mut self, client: &mut Client) -> color_eyre::Result<ScreenChange> {
+        self.apply_server(client)?;
+        let tokens = client
+            .login(LoginRequest {
+                email: self.email_input.value().to_string(),
+                password: self.password_input.value().to_string(),
+            })
+            .await?;
+        TOKEN_MANAGER.set_password(&tokens.refresh_token.to_string())?;
+        // the first login of an account verified by email
+        if client.get_profile().await.is_err() {
+            client.update_profile_with_random_name().await?;
+        }
+        self.lobby_screen_change(client).await
+    }
+
+    // emails a reset code to the address typed in, then asks for it with the new password
+    async fn forgot_password(&mut self, client: &mut Client) -> color_eyre::Result<()> {
+        self.apply_server(client)?;
+        client
+            .forgot_password(ForgotPasswordRequest {
+                email: self.email_input.value().to_string(),
+            })
+            .await?;
+        self.notice = Some(format!(
+            "If {} has an account, a reset code is on its way. Enter it with a new password",
+            self.email_input.value()
+        ));
+        self.password_input.reset();
+        self.reset_code = Some(Input::default());
+        self.focus = LoginScreenFocus::ResetCode;
+        Ok(())
+    }
+
+    async fn reset_password(&mut self, client: &mut Client) -> color_eyre::Result<()> {
+        let code = self
+            .reset_code
+            .as_ref()
+            .map(|code| code.value().to_string());
+        client
+            .reset_password(ResetPasswordRequest {
+                email: self.email_input.value().to_string(),
+                code: code.unwrap_or_default(),
+                new_password: self.password_input.value().to_string(),
+            })
+            .await?;
+        self.reset_code = None;
+        self.notice = None;
+        Ok(())
+    }
+
     async fn handle_enter(&mut self, client: &mut Client) -> color_eyre::Result<ScreenChange> {
         let change = match self.focus {
-            LoginScreenFocus::Login => {
-                let token = client
-                    .login(LoginRequest {
-                        email: self.email_input.value().to_string(),
-                        password: self.password_input.value().to_string(),
-                    })
-                    .await?;
-                TOKEN_MANAGER.set_password(&token)?;
-                lobby::lobby_screen_data(client).await?.into()
+            LoginScreenFocus::Login | LoginScreenFocus::ResetCode if self.reset_code.is_some() => {
+                self.reset_password(client).await?;
+                self.log_in(client).await?
             }
+            LoginScreenFocus::Login => self.log_in(client).await?,
             LoginScreenFocus::Signup => {
-                client
+                self.apply_server(client)?;
+                let signup = client
                     .signup(SignupRequest {
                         email: self.email_input.value().to_string(),
                         password: self.password_input.value().to_string(),
                     })
                     .await?;
-                let token = client
+                if signup.verification_sent {
+                    self.notice = Some(format!(
+                        "We emailed a link to {}, open it and then log in",
+                        self.email_input.value()
+                    ));
+                    self.focus = LoginScreenFocus::Login;
+                    return Ok(ScreenChange::None);
+                }
+                let tokens = client
                     .login(LoginRequest {
                         email: self.email_input.value().to_string(),
                         password: self.password_input.value().to_string(),
                     })
                     .await?;
-                let _ = TOKEN_MANAGER.set_password(&token);
+                let _ = TOKEN_MANAGER.set_password(&tokens.refresh_token.to_string());
                 client.update_profile_with_random_name().await?;
-                lobby::lobby_screen_data(client).await?.into()
+                self.lobby_screen_change(client).await?
+            }
+            LoginScreenFocus::Practice => {
+                client.start_practice(PRACTICE_BOTS).await?;
+                lobby::wait_for_game(client).await?
+            }
+            LoginScreenFocus::Tutorial => {
+                client.start_tutorial().await?;
+                lobby::wait_for_game(client).await?
             }
             _ => {
                 self.switch_focus();
@@ -95,45 +217,42 @@ impl LoginScreenData {
 
 #[derive(Debug, PartialEq, Default)]
 pub enum LoginScreenFocus {
+    Server,
     #[default]
     Email,
     Password,
+    ResetCode,
     Login,
     Signup,
+    Practice,
+    Tutorial,
 }
 
 impl LoginScreenData {
-    fn update_cursor_position(&mut self, top: Rect, bottom: Rect) {
-        match self.focus {
-            LoginScreenFocus::Email => {
-                self.cursor_position = Some(
-                    (
-                        top.x + self.email_input.visual_cursor() as u16 + 1,
-                        top.y + 1,
-                    )
-                        .into(),
-                );
+    fn update_cursor_position(&mut self, server: Rect, email: Rect, password: Rect, code: Rect) {
+        let (area, input) = match (&self.focus, &self.reset_code) {
+            (LoginScreenFocus::Server, _) => (server, &self.server_input),
+            (LoginScreenFocus::Email, _) => (email, &self.email_input),
+            (LoginScreenFocus::Password, _) => (password, &self.password_input),
+            (LoginScreenFocus::ResetCode, Some(reset_code)) => (code, reset_code),
+            _ => {
+                self.cursor_position = None;
+                return;
             }
-            LoginScreenFocus::Password => {
-                self.cursor_position = Some(
-                    (
-                        bottom.x + self.password_input.visual_cursor() as u16 + 1,
-                        bottom.y + 1,
-                    )
-                        .into(),
-                );
-            }
-            _ => self.cursor_position = None,
-        }
+        };
+        self.cursor_position = Some((area.x + input.visual_cursor() as u16 + 1, area.y + 1).into());
     }
 }
 
-pub struct LoginScreenWidget;
+pub struct LoginScreenWidget {
+    pub theme: Theme,
+}
 
 impl StatefulWidget for LoginScreenWidget {
     type State = LoginScreenData;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let theme = self.theme;
         let [_, all, _] = Layout::vertical([
             Constraint::Fill(1),
             Constraint::Fill(1),
@@ -141,14 +260,24 @@ impl StatefulWidget for LoginScreenWidget {
         ])
         .flex(Flex::Center)
         .areas(area);
-        let [email, password, actions, instructions] = Layout::vertical([
+        let code_height = if state.reset_code.is_some() { 3 } else { 0 };
+        let [server, email, password, code, actions, instructions] = Layout::vertical([
+            Constraint::Length(3),
             Constraint::Length(3),
             Constraint::Length(3),
+            Constraint::Length(code_height),
             Constraint::Length(3),
             Constraint::Length(1),
         ])
         .areas(all);
 
+        let [server] = Layout::horizontal([Constraint::Max(50)])
+            .flex(Flex::Center)
+            .areas(server);
+        Paragraph::new(state.server_input.value())
+            .block(Block::bordered().title("Server"))
+            .render(server, buf);
+
         let [email] = Layout::horizontal([Constraint::Max(50)])
             .flex(Flex::Center)
             .areas(email);
@@ -160,31 +289,51 @@ impl StatefulWidget for LoginScreenWidget {
             .flex(Flex::Center)
             .areas(password);
         let password_text =
-            Span::styled(Masked::new(state.password_input.value(), '*'), Color::White);
+            Span::styled(Masked::new(state.password_input.value(), '*'), theme.text);
+        let password_title = if state.reset_code.is_some() {
+            "New password"
+        } else {
+            "Password"
+        };
         Paragraph::new(password_text)
-            .block(Block::bordered().title("Password"))
+            .block(Block::bordered().title(password_title))
             .render(password, buf);
-        let [_, login, signup, _] = Layout::split_equal(actions, Direction::Horizontal);
-
-        Paragraph::new(data::highlight(
-            "Login",
-            state.focus == LoginScreenFocus::Login,
-        ))
-        .centered()
-        .block(Block::bordered())
-        .render(login, buf);
-        Paragraph::new(data::highlight(
-            "Signup",
-            state.focus == LoginScreenFocus::Signup,
-        ))
-        .centered()
-        .block(Block::bordered())
-        .render(signup, buf);
-        Paragraph::new("Press Tab to switch focus")
+
+        let [code] = Layout::horizontal([Constraint::Max(50)])
+            .flex(Flex::Center)
+            .areas(code);
+        if let Some(reset_code) = &state.reset_code {
+            Paragraph::new(reset_code.value())
+                .block(Block::bordered().title("Reset code"))
+                .render(code, buf);
+        }
+        let [_, login, signup, practice, tutorial, _] = Layout::split_equal(actions, Direction::Horizontal);
+
+        Paragraph::new(theme.highlight("Login", state.focus == LoginScreenFocus::Login))
+            .centered()
+            .block(Block::bordered())
+            .render(login, buf);
+        Paragraph::new(theme.highlight("Signup", state.focus == LoginScreenFocus::Signup))
+            .centered()
+            .block(Block::bordered())
+            .render(signup, buf);
+        Paragraph::new(theme.highlight("Practice", state.focus == LoginScreenFocus::Practice))
+            .centered()
+            .block(Block::bordered())
+            .render(practice, buf);
+        Paragraph::new(theme.highlight("Tutorial", state.focus == LoginScreenFocus::Tutorial))
+            .centered()
+            .block(Block::bordered())
+            .render(tutorial, buf);
+        let instruction = state
+            .notice
+            .as_deref()
+            .unwrap_or("Press Tab to switch focus, Ctrl+F if you forgot your password");
+        Paragraph::new(instruction)
             .style(Style::default().add_modifier(Modifier::ITALIC))
             .centered()
             .render(instructions, buf);
-        state.update_cursor_position(email, password);
+        state.update_cursor_position(server, email, password, code);
     }
 }
 
@@ -203,6 +352,18 @@ impl OnKeyEvent for LoginScreenData {
         client: &mut Client,
     ) -> color_eyre::Result<ScreenChange> {
         match (key.kind, key.modifiers, key.code) {
+            (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Esc)
+                if self.reset_code.is_some() =>
+            {
+                self.reset_code = None;
+                self.notice = None;
+                self.focus = LoginScreenFocus::Password;
+                Ok(ScreenChange::None)
+            }
+            (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('f')) => {
+                self.forgot_password(client).await?;
+                Ok(ScreenChange::None)
+            }
             (KeyEventKind::Press, KeyModifiers::NONE, KeyCode::Esc)
             | (KeyEventKind::Press, KeyModifiers::CONTROL, KeyCode::Char('c')) => {
                 Ok(ScreenChange::Quit)
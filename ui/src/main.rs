@@ -1,30 +1,61 @@
 pub use app::App;
 
 pub mod app;
+mod avatar_art;
+mod card_art;
+mod clipboard;
+mod config;
 mod data;
 mod extension;
+mod friends;
 mod game;
+mod i18n;
+mod leaderboard;
 mod lobby;
 mod login;
+mod notify;
+mod quiz;
+mod replay;
+mod report;
+mod settings;
+mod sound;
+mod theme;
+mod top_up;
+mod transactions;
 
+use std::io::stdout;
+
+use clap::Parser;
 use cli_log::*;
+use crossterm::event::{DisableFocusChange, EnableFocusChange};
+use crossterm::execute;
 use common::generate_image_lookup;
 use keyring::Entry;
 use lazy_static::lazy_static;
 
 generate_image_lookup!();
+generate_image_lookup!(avatars);
 
 lazy_static! {
     static ref TOKEN_MANAGER: Entry =
         Entry::new("poker", "token").expect("Failed to create token manager");
+    // room joined last, offered again on the next launch
+    static ref LAST_ROOM: Entry =
+        Entry::new("poker", "last_room").expect("Failed to create last room store");
 }
 
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
     init_cli_log!("poker");
     color_eyre::install()?;
+    tokio::task::spawn_blocking(card_art::warm_up);
+    let args = config::Args::parse();
+    let server_url = config::server_url(&args)?;
     let terminal = ratatui::init();
-    let result = App::new().await?.run(terminal).await;
+    // focus reports tell the turn notifier whether the window is in the background
+    let _ = execute!(stdout(), EnableFocusChange);
+    let result = App::new(args.join, server_url).await?.run(terminal).await;
+    let _ = execute!(stdout(), DisableFocusChange);
     ratatui::restore();
     result
 }
@@ -0,0 +1,63 @@
+use std::io::{stdout, Write};
+
+use crossterm::execute;
+use crossterm::terminal::SetTitle;
+
+const TITLE: &str = "Poker";
+const TURN_TITLE: &str = "♠ Your turn — Poker";
+const BELL: &str = "\x07";
+// set to 0 to keep the terminal title as it is
+const TURN_TITLE_ENV: &str = "POKER_TURN_TITLE";
+// set to 0 to not ring the terminal bell
+const TURN_BELL_ENV: &str = "POKER_TURN_BELL";
+
+fn enabled_from_env(name: &str) -> bool {
+    std::env::var(name).map_or(true, |value| value != "0")
+}
+
+/// Tells the player their turn has come through the terminal title and bell,
+/// so they notice it while the window is in the background
+#[derive(Debug)]
+pub struct TurnNotifier {
+    title: bool,
+    bell: bool,
+    // None until the terminal reports focus, some terminals never do
+    focused: Option<bool>,
+    in_turn: bool,
+}
+
+impl TurnNotifier {
+    pub fn from_env() -> Self {
+        Self {
+            title: enabled_from_env(TURN_TITLE_ENV),
+            bell: enabled_from_env(TURN_BELL_ENV),
+            focused: None,
+            in_turn: false,
+        }
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = Some(focused);
+    }
+
+    /// Called every tick, only acts when the player's turn starts or ends
+    pub fn update(&mut self, in_turn: bool) {
+        if in_turn == self.in_turn {
+            return;
+        }
+        self.in_turn = in_turn;
+        if self.title {
+            let title = if in_turn { TURN_TITLE } else { TITLE };
+            let _ = execute!(stdout(), SetTitle(title))
+                .inspect_err(|e| log::warn!("Failed to set terminal title: {}", e));
+        }
+        // the bell is left out while the player is known to be looking
+        if in_turn && self.bell && self.focused != Some(true) {
+            let mut stdout = stdout();
+            let _ = stdout
+                .write_all(BELL.as_bytes())
+                .and_then(|_| stdout.flush())
+                .inspect_err(|e| log::warn!("Failed to ring terminal bell: {}", e));
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use lazy_static::lazy_static;
+use poker::{Card, Evaluator};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::prelude::{Line, Span, Widget};
+use ratatui::style::Stylize;
+use ratatui::widgets::{Block, BorderType, Clear, Paragraph};
+use types::deck::Deck;
+use types::state::SerdeCard;
+use types::stats::HandClass;
+
+use crate::config;
+use crate::theme::Theme;
+
+lazy_static! {
+    static ref EVALUATOR: Evaluator = Evaluator::new();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    First,
+    Second,
+    Tie,
+}
+
+// two hands on the same board, dealt locally
+#[derive(Debug)]
+struct Question {
+    board: Vec<SerdeCard>,
+    hands: [[SerdeCard; 2]; 2],
+    classes: [HandClass; 2],
+    outcome: Outcome,
+}
+
+impl Question {
+    fn deal() -> Result<Self> {
+        let mut deck = Deck::new();
+        let mut draw = || deck.draw();
+        let hands = [[draw()?, draw()?], [draw()?, draw()?]];
+        let board = (0..5).map(|_| draw()).collect::<Result<Vec<_>>>()?;
+        let [first, second] = hands.map(|hand| {
+            let cards: Vec<Card> = hand.iter().chain(&board).copied().collect();
+            EVALUATOR.evaluate(cards)
+        });
+        let (first, second) = (first?, second?);
+        let outcome = if first.is_better_than(second) {
+            Outcome::First
+        } else if second.is_better_than(first) {
+            Outcome::Second
+        } else {
+            Outcome::Tie
+        };
+        Ok(Self {
+            board: board.into_iter().map(SerdeCard).collect(),
+            hands: hands.map(|hand| hand.map(SerdeCard)),
+            classes: [first.class().into(), second.class().into()],
+            outcome,
+        })
+    }
+}
+
+/// Guessing which of two hands wins, to pass the time in the lobby.
+/// The best streak is kept in the config file.
+#[derive(Debug)]
+pub struct Quiz {
+    question: Question,
+    // the player's pick, until the next question is dealt
+    answer: Option<Outcome>,
+    streak: u32,
+    best: u32,
+}
+
+impl Quiz {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            question: Question::deal()?,
+            answer: None,
+            streak: 0,
+            best: config::quiz_best(),
+        })
+    }
+
+    /// Returns whether the popup should be closed
+    pub fn on_key_event(&mut self, key: KeyEvent) -> Result<bool> {
+        if key.kind != KeyEventKind::Press {
+            return Ok(false);
+        }
+        let answer = match key.code {
+            KeyCode::Esc => return Ok(true),
+            KeyCode::Enter if self.answer.is_some() => {
+                self.question = Question::deal()?;
+                self.answer = None;
+                return Ok(false);
+            }
+            KeyCode::Char('1') => Outcome::First,
+            KeyCode::Char('2') => Outcome::Second,
+            KeyCode::Char('t') => Outcome::Tie,
+            _ => return Ok(false),
+        };
+        if self.answer.is_some() {
+            return Ok(false);
+        }
+        self.answer = Some(answer);
+        if answer == self.question.outcome {
+            self.streak += 1;
+            if self.streak > self.best {
+                self.best = self.streak;
+                let _ = config::save_quiz_best(self.best)
+                    .inspect_err(|e| log::warn!("Failed to save quiz best: {}", e));
+            }
+        } else {
+            self.streak = 0;
+        }
+        Ok(false)
+    }
+
+    fn result_line(&self) -> Line<'_> {
+        let Some(answer) = self.answer else {
+            return Line::from("Which hand wins?");
+        };
+        let [first, second] = self.question.classes.map(|class| class.label());
+        let result = match self.question.outcome {
+            Outcome::First => format!("Hand 1 wins, {} against {}", first, second),
+            Outcome::Second => format!("Hand 2 wins, {} against {}", second, first),
+            Outcome::Tie => format!("A tie, {} each", first),
+        };
+        if answer == self.question.outcome {
+            Line::from(vec!["Right! ".green().bold(), result.into()])
+        } else {
+            Line::from(vec!["Wrong. ".red().bold(), result.into()])
+        }
+    }
+}
+
+fn cards_line<'a>(label: &'a str, cards: &'a [SerdeCard]) -> Line<'a> {
+    let mut spans = vec![Span::from(label)];
+    for card in cards {
+        spans.push(card.span());
+        spans.push(" ".into());
+    }
+    Line::from(spans)
+}
+
+pub fn quiz_popup(area: Rect, quiz: &Quiz, theme: &Theme, buf: &mut Buffer) {
+    let [popup_area] = Layout::vertical([Constraint::Length(9)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Max(60)])
+        .flex(Flex::Center)
+        .areas(popup_area);
+    Clear.render(popup_area, buf);
+    let instructions = if quiz.answer.is_some() {
+        Line::from(vec![
+            "Next ".into(),
+            "<Enter>".light_blue().bold(),
+            " Close ".into(),
+            "<Esc>".red().bold(),
+        ])
+    } else {
+        Line::from(vec![
+            "Hand 1 ".into(),
+            "<1>".light_blue().bold(),
+            " Hand 2 ".into(),
+            "<2>".light_blue().bold(),
+            " Tie ".into(),
+            "<T>".light_blue().bold(),
+            " Close ".into(),
+            "<Esc>".red().bold(),
+        ])
+    };
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .title(Line::from("Hand quiz").centered())
+        .title_bottom(instructions.right_aligned());
+    let [first, second] = &quiz.question.hands;
+    let lines = vec![
+        cards_line("Board   ", &quiz.question.board),
+        Line::default(),
+        cards_line("Hand 1  ", first),
+        cards_line("Hand 2  ", second),
+        Line::default(),
+        quiz.result_line(),
+        Line::from(format!("Streak {}   Best {}", quiz.streak, quiz.best)).fg(theme.muted),
+    ];
+    Paragraph::new(lines).block(block).render(popup_area, buf);
+}
@@ -0,0 +1,245 @@
+use std::iter::zip;
+
+use chrono::{DateTime, Local, Utc};
+use client::client::Client;
+use color_eyre::eyre::{bail, ContextCompat};
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::prelude::{Color, Line, Span, StatefulWidget, Style, Widget};
+use ratatui::style::Stylize;
+use ratatui::widgets::{Block, BorderType, Borders, Paragraph};
+use types::history::{HandHistory, HistoryQuery, ReplayFrame, ReplaySeat};
+use types::rules::DEFAULT_MAX_PLAYERS;
+use uuid::Uuid;
+
+use crate::data::{OnKeyEvent, OnTick, Screen, ScreenChange};
+use crate::extension::Splittable;
+use crate::game::card_paragraph;
+use crate::lobby;
+use crate::theme::Theme;
+
+// how far back the hands of a room can be stepped through
+const REPLAY_HAND_LIMIT: u32 = 50;
+
+/// A recorded hand stepped through action by action
+#[derive(Debug)]
+pub struct ReplayScreenData {
+    room_id: Uuid,
+    // the room's latest hands, newest first
+    hands: Vec<(Uuid, DateTime<Utc>)>,
+    hand: usize,
+    frames: Vec<ReplayFrame>,
+    frame: usize,
+}
+
+pub async fn replay_screen_data(client: &Client, room_id: Uuid) -> Result<ReplayScreenData> {
+    let query = HistoryQuery {
+        page: Some(0),
+        per_page: Some(REPLAY_HAND_LIMIT),
+    };
+    let hands: Vec<_> = client
+        .get_hand_history(room_id, &query)
+        .await?
+        .hands
+        .into_iter()
+        .map(|hand| (hand.id, hand.created_at))
+        .collect();
+    if hands.is_empty() {
+        bail!("No hands were played in this room yet");
+    }
+    let mut data = ReplayScreenData {
+        room_id,
+        hands,
+        hand: 0,
+        frames: vec![],
+        frame: 0,
+    };
+    data.load(client, 0).await?;
+    Ok(data)
+}
+
+impl ReplayScreenData {
+    async fn load(&mut self, client: &Client, hand: usize) -> Result<()> {
+        let (hand_id, _) = self.hands.get(hand).wrap_err("No such hand")?;
+        let history: HandHistory = client.get_hand(self.room_id, *hand_id).await?;
+        self.frames = history.frames();
+        self.hand = hand;
+        self.frame = 0;
+        Ok(())
+    }
+
+    fn current(&self) -> Option<&ReplayFrame> {
+        self.frames.get(self.frame)
+    }
+}
+
+impl From<ReplayScreenData> for ScreenChange {
+    fn from(data: ReplayScreenData) -> Self {
+        ScreenChange::Switch(Screen::Replay(data))
+    }
+}
+
+#[async_trait::async_trait]
+impl OnTick for ReplayScreenData {
+    async fn on_tick(&mut self, _client: &mut Client) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl OnKeyEvent for ReplayScreenData {
+    async fn on_key_event(&mut self, key: KeyEvent, client: &mut Client) -> Result<ScreenChange> {
+        if key.kind != KeyEventKind::Press {
+            return Ok(ScreenChange::None);
+        }
+        match (key.modifiers, key.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('c')) => return Ok(ScreenChange::Quit),
+            (_, KeyCode::Esc) => return Ok(lobby::lobby_screen_data(client).await?.into()),
+            (_, KeyCode::Right) => {
+                self.frame = (self.frame + 1).min(self.frames.len().saturating_sub(1));
+            }
+            (_, KeyCode::Left) => self.frame = self.frame.saturating_sub(1),
+            (_, KeyCode::Home) => self.frame = 0,
+            (_, KeyCode::End) => self.frame = self.frames.len().saturating_sub(1),
+            // hands are newest first
+            (_, KeyCode::Down) if self.hand + 1 < self.hands.len() => {
+                self.load(client, self.hand + 1).await?;
+            }
+            (_, KeyCode::Up) if self.hand > 0 => {
+                self.load(client, self.hand - 1).await?;
+            }
+            _ => {}
+        }
+        Ok(ScreenChange::None)
+    }
+}
+
+pub struct ReplayWidget {
+    pub theme: Theme,
+}
+
+impl StatefulWidget for ReplayWidget {
+    type State = ReplayScreenData;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let theme = self.theme;
+        let Some(frame) = state.current() else {
+            return;
+        };
+        let [board_area, seats_area, footer_area] = Layout::vertical([
+            Constraint::Percentage(60),
+            Constraint::Percentage(25),
+            Constraint::Fill(1),
+        ])
+        .areas(area);
+
+        let (_, played_at) = state.hands[state.hand];
+        let board_block = Block::new()
+            .borders(Borders::BOTTOM)
+            .border_type(BorderType::Rounded)
+            .title_top(
+                Line::from(format!(
+                    "Hand {} of {}, played {}",
+                    state.hand + 1,
+                    state.hands.len(),
+                    played_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                ))
+                .centered(),
+            )
+            .title_bottom(frame.stage.line().centered())
+            .title_bottom(Line::from(format!("Pot: {}", frame.total_pot())).left_aligned());
+        let inner_board_area = board_block.inner(board_area);
+        board_block.render(board_area, buf);
+        let card_areas: [_; 5] = Layout::split_equal(inner_board_area, Direction::Horizontal);
+        for (card_area, card) in zip(card_areas, &frame.board) {
+            card_paragraph(card_area, card, false, &theme, buf);
+        }
+
+        // histories do not keep the table size, so the table is as big as its last seat taken
+        let seat_count = frame
+            .seats
+            .iter()
+            .map(|seat| seat.seat + 1)
+            .fold(DEFAULT_MAX_PLAYERS, usize::max);
+        let seat_areas = Layout::split_count(seats_area, Direction::Horizontal, seat_count);
+        let acted = frame.action.as_ref().map(|action| action.player_id);
+        for seat in &frame.seats {
+            if let Some(seat_area) = seat_areas.get(seat.seat) {
+                seat_paragraph(*seat_area, seat, acted == Some(seat.id), &theme, buf);
+            }
+        }
+
+        let [step_area, hints_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Length(1)]).areas(footer_area);
+        Paragraph::new(step_line(state, frame, &theme))
+            .centered()
+            .render(step_area, buf);
+        Paragraph::new(Line::from(vec![
+            "Step ".into(),
+            "<←/→>".light_blue().bold(),
+            " First/last ".into(),
+            "<Home/End>".light_blue().bold(),
+            " Older/newer hand ".into(),
+            "<↓/↑>".light_blue().bold(),
+            " Back ".into(),
+            "<Esc>".red().bold(),
+        ]))
+        .centered()
+        .render(hints_area, buf);
+    }
+}
+
+fn step_line<'a>(state: &ReplayScreenData, frame: &'a ReplayFrame, theme: &Theme) -> Line<'a> {
+    let step = format!("{}/{} ", state.frame + 1, state.frames.len()).fg(theme.muted);
+    if let Some(action) = &frame.action {
+        let mut line = action.line();
+        line.spans.insert(0, step);
+        return line;
+    }
+    if frame.results.is_empty() {
+        return Line::from(vec![step, "Cards dealt, blinds posted".into()]);
+    }
+    let mut spans = vec![step];
+    for winnings in &frame.results {
+        let name = frame
+            .seats
+            .iter()
+            .find(|seat| seat.id == winnings.player)
+            .map_or("Someone", |seat| seat.name.as_str());
+        spans.push(format!("{} wins {} ", name, winnings.amount).light_green());
+    }
+    Line::from(spans)
+}
+
+fn seat_paragraph(area: Rect, seat: &ReplaySeat, acted: bool, theme: &Theme, buf: &mut Buffer) {
+    let mut block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .title(Line::from(seat.name.clone()).centered());
+    if acted {
+        block = block.border_style(Style::default().fg(Color::Yellow));
+    }
+    if seat.position.as_ref().is_some_and(|p| p.is_dealer()) {
+        block = block.title_bottom(Line::from("Dealer").right_aligned());
+    }
+    let cards = match &seat.hole_cards {
+        Some(cards) => {
+            let spans = cards.iter().flat_map(|card| [card.span(), Span::from(" ")]);
+            Line::from(spans.collect::<Vec<_>>())
+        }
+        None => theme.hidden_hand_line(),
+    };
+    let mut lines = vec![cards];
+    if seat.folded {
+        lines.push(Line::from("Folded").fg(theme.muted));
+    }
+    lines.push(Line::from(format!("Bet: {}", seat.bet)));
+    if let Some(chips) = seat.chips {
+        lines.push(Line::from(format!("Chips: {}", chips)));
+    }
+    Paragraph::new(lines)
+        .centered()
+        .block(block)
+        .render(area, buf);
+}
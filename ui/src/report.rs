@@ -0,0 +1,103 @@
+use client::client::Client;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Flex, Layout, Position, Rect};
+use ratatui::prelude::{Line, Widget};
+use ratatui::style::Stylize;
+use ratatui::widgets::{Block, BorderType, Clear, Paragraph, Wrap};
+use tui_input::backend::crossterm::EventHandler;
+use tui_input::Input;
+use uuid::Uuid;
+
+/// Bug report being written, sent with the table as the client sees it and its recent events
+#[derive(Debug, Default)]
+pub struct BugReport {
+    note: Input,
+    // set once the server has stored the report
+    sent: Option<Uuid>,
+}
+
+impl BugReport {
+    /// Returns whether the popup should be closed
+    pub async fn on_key_event(
+        &mut self,
+        key: KeyEvent,
+        client: &mut Client,
+    ) -> color_eyre::Result<bool> {
+        if key.kind != KeyEventKind::Press {
+            return Ok(false);
+        }
+        if self.sent.is_some() {
+            return Ok(matches!(key.code, KeyCode::Enter | KeyCode::Esc));
+        }
+        match (key.modifiers, key.code) {
+            (KeyModifiers::NONE, KeyCode::Esc) => Ok(true),
+            (KeyModifiers::NONE, KeyCode::Enter) => {
+                let note = Some(self.note.value().trim().to_string()).filter(|n| !n.is_empty());
+                self.sent = Some(client.send_feedback(note).await?);
+                Ok(false)
+            }
+            _ => {
+                self.note.handle_event(&Event::Key(key));
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Renders the report popup, returning where the cursor goes while the note is being written
+pub fn bug_report_popup(area: Rect, report: &BugReport, buf: &mut Buffer) -> Option<Position> {
+    let [popup_area] = Layout::vertical([Constraint::Length(7)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Max(60)])
+        .flex(Flex::Center)
+        .areas(popup_area);
+    Clear.render(popup_area, buf);
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .title(Line::from("Report a bug").centered());
+    if let Some(id) = report.sent {
+        Paragraph::new(vec![
+            Line::from("Thanks, the report was sent with reference"),
+            Line::default(),
+            Line::from(id.to_string()).bold().light_yellow(),
+        ])
+        .centered()
+        .wrap(Wrap { trim: true })
+        .block(
+            block.title_bottom(
+                Line::from(vec!["Close ".into(), "<Esc>".red().bold()]).right_aligned(),
+            ),
+        )
+        .render(popup_area, buf);
+        return None;
+    }
+    let inner_area = block.inner(popup_area);
+    block
+        .title_bottom(
+            Line::from(vec![
+                "Send ".into(),
+                "<Enter>".light_blue().bold(),
+                " Cancel ".into(),
+                "<Esc>".red().bold(),
+            ])
+            .right_aligned(),
+        )
+        .render(popup_area, buf);
+    let [hint_area, note_area] =
+        Layout::vertical([Constraint::Length(2), Constraint::Length(3)]).areas(inner_area);
+    Paragraph::new("What went wrong? The table state and recent events are attached.")
+        .wrap(Wrap { trim: true })
+        .render(hint_area, buf);
+    Paragraph::new(report.note.value())
+        .block(Block::bordered().title("Note (optional)"))
+        .render(note_area, buf);
+    Some(
+        (
+            note_area.x + report.note.visual_cursor() as u16 + 1,
+            note_area.y + 1,
+        )
+            .into(),
+    )
+}
@@ -0,0 +1,213 @@
+use client::client::Client;
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::prelude::{Line, StatefulWidget, Widget};
+use ratatui::style::Stylize;
+use ratatui::widgets::{Block, BorderType, Paragraph};
+
+use crate::config;
+use crate::data::{OnKeyEvent, OnTick, Screen, ScreenChange};
+use crate::i18n::{self, t, Language, Text};
+use crate::lobby;
+use crate::sound::{SoundCategory, SoundSettings, AUDIO};
+use crate::theme::{CardBack, Theme, ThemeName};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Row {
+    Theme,
+    CardBack,
+    Sound,
+    Volume(SoundCategory),
+    Language,
+    PotOdds,
+}
+
+// in the order they are listed
+const ROWS: [Row; 8] = [
+    Row::Theme,
+    Row::CardBack,
+    Row::Sound,
+    Row::Volume(SoundCategory::Deal),
+    Row::Volume(SoundCategory::Chips),
+    Row::Volume(SoundCategory::Alerts),
+    Row::Language,
+    Row::PotOdds,
+];
+
+impl Row {
+    fn label(self) -> &'static str {
+        match self {
+            Row::Theme => t(Text::Theme),
+            Row::CardBack => t(Text::CardBack),
+            Row::Sound => t(Text::Sound),
+            Row::Volume(category) => category.label(),
+            Row::Language => t(Text::Language),
+            Row::PotOdds => t(Text::PotOdds),
+        }
+    }
+}
+
+/// Colors, card back, sound, language and table aids, saved to the config file as soon as they
+/// are changed
+#[derive(Debug)]
+pub struct SettingsScreenData {
+    pub theme: Theme,
+    sound: SoundSettings,
+    show_pot_odds: bool,
+    row: usize,
+}
+
+impl SettingsScreenData {
+    pub fn new(theme: Theme) -> Self {
+        Self {
+            theme,
+            sound: AUDIO.settings(),
+            show_pot_odds: config::show_pot_odds(),
+            row: 0,
+        }
+    }
+
+    // steps through the values of the selected row, wrapping around except for volumes
+    fn cycle(&mut self, forward: bool) -> Result<()> {
+        fn next<T: Copy + PartialEq, const N: usize>(all: [T; N], current: T, forward: bool) -> T {
+            let index = all.iter().position(|v| *v == current).unwrap_or_default();
+            let step = if forward { 1 } else { N - 1 };
+            all[(index + step) % N]
+        }
+        match ROWS[self.row] {
+            Row::Theme => {
+                let name = next(ThemeName::ALL, self.theme.name, forward);
+                self.theme = self.theme.with_name(name);
+                config::save_theme(&self.theme)
+            }
+            Row::CardBack => {
+                let card_back = next(CardBack::ALL, self.theme.card_back, forward);
+                self.theme = self.theme.with_card_back(card_back);
+                config::save_theme(&self.theme)
+            }
+            Row::Sound => {
+                self.sound.muted = !self.sound.muted;
+                self.save_sound()
+            }
+            Row::Volume(category) => {
+                self.sound.step_volume(category, forward);
+                self.save_sound()?;
+                category.sample().play();
+                Ok(())
+            }
+            Row::Language => {
+                let language = next(Language::ALL, i18n::language(), forward);
+                i18n::set_language(language);
+                config::save_language(language)
+            }
+            Row::PotOdds => {
+                self.show_pot_odds = !self.show_pot_odds;
+                config::save_show_pot_odds(self.show_pot_odds)
+            }
+        }
+    }
+
+    fn save_sound(&self) -> Result<()> {
+        AUDIO.set_settings(self.sound);
+        config::save_sound_settings(&self.sound)
+    }
+
+    fn value(&self, row: Row) -> String {
+        match row {
+            Row::Theme => self.theme.name.label().to_string(),
+            Row::CardBack => self.theme.card_back.label().to_string(),
+            Row::Sound if self.sound.muted => t(Text::Off).to_string(),
+            Row::Sound => t(Text::On).to_string(),
+            Row::Volume(category) => format!("{}%", self.sound.volume(category)),
+            Row::Language => i18n::language().label().to_string(),
+            Row::PotOdds if self.show_pot_odds => t(Text::On).to_string(),
+            Row::PotOdds => t(Text::Off).to_string(),
+        }
+    }
+}
+
+impl From<SettingsScreenData> for ScreenChange {
+    fn from(data: SettingsScreenData) -> Self {
+        ScreenChange::Switch(Screen::Settings(data))
+    }
+}
+
+#[async_trait::async_trait]
+impl OnTick for SettingsScreenData {
+    async fn on_tick(&mut self, _client: &mut Client) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl OnKeyEvent for SettingsScreenData {
+    async fn on_key_event(&mut self, key: KeyEvent, client: &mut Client) -> Result<ScreenChange> {
+        if key.kind != KeyEventKind::Press {
+            return Ok(ScreenChange::None);
+        }
+        match (key.modifiers, key.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('c')) => return Ok(ScreenChange::Quit),
+            (_, KeyCode::Esc) => return Ok(lobby::lobby_screen_data(client).await?.into()),
+            (_, KeyCode::Down) => self.row = (self.row + 1) % ROWS.len(),
+            (_, KeyCode::Up) => self.row = (self.row + ROWS.len() - 1) % ROWS.len(),
+            (_, KeyCode::Right | KeyCode::Enter) => self.cycle(true)?,
+            (_, KeyCode::Left) => self.cycle(false)?,
+            _ => {}
+        }
+        Ok(ScreenChange::None)
+    }
+}
+
+pub struct SettingsWidget;
+
+impl StatefulWidget for SettingsWidget {
+    type State = SettingsScreenData;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let theme = state.theme;
+        let [area] = Layout::vertical([Constraint::Length(16)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::horizontal([Constraint::Max(50)])
+            .flex(Flex::Center)
+            .areas(area);
+        let instructions = Line::from(vec![
+            format!("{} ", t(Text::Change)).into(),
+            "<←/→>".light_blue().bold(),
+            format!(" {} ", t(Text::Back)).into(),
+            "<Esc>".red().bold(),
+        ]);
+        let block = Block::bordered()
+            .border_type(BorderType::Rounded)
+            .title(Line::from(t(Text::Settings)).centered())
+            .title_bottom(instructions.right_aligned())
+            .style(theme.text);
+        let inner_area = block.inner(area);
+        block.render(area, buf);
+
+        let mut lines: Vec<Line> = ROWS
+            .iter()
+            .enumerate()
+            .map(|(index, row)| {
+                Line::from(vec![
+                    format!("{:<16}", row.label()).into(),
+                    theme.highlight(format!("< {} >", state.value(*row)), index == state.row),
+                ])
+            })
+            .collect();
+        // a taste of the table with the choices applied
+        lines.push(Line::default());
+        lines.push(Line::from(t(Text::Preview)).fg(theme.muted));
+        lines.push(theme.hidden_hand_line());
+        lines.push(Line::from(vec![
+            theme.highlight(" Check ", true),
+            " ".into(),
+            " Call ".into(),
+            " ".into(),
+            " Fold ".fg(theme.muted),
+        ]));
+        Paragraph::new(lines).centered().render(inner_area, buf);
+    }
+}
@@ -0,0 +1,209 @@
+use std::io::Cursor;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::RwLock;
+use std::thread;
+
+use lazy_static::lazy_static;
+use rodio::{Decoder, OutputStream, Sink};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::i18n::{t, Text};
+
+static DING_SOUND: &[u8] = include_bytes!("../sound_assets/ding.wav");
+static CHIPS_SOUND: &[u8] = include_bytes!("../sound_assets/chips.wav");
+static CHECK_SOUND: &[u8] = include_bytes!("../sound_assets/check.mp3");
+static DEAL_SOUND: &[u8] = include_bytes!("../sound_assets/deal.wav");
+static WIN_SOUND: &[u8] = include_bytes!("../sound_assets/win.mp3");
+
+// how much a volume moves with one key press
+pub const VOLUME_STEP: u8 = 10;
+
+lazy_static! {
+    pub static ref AUDIO: AudioManager = AudioManager::start(config::sound_settings());
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Sound {
+    Ding,
+    Chips,
+    Check,
+    Deal,
+    Win,
+}
+
+/// Sounds that share a volume
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SoundCategory {
+    Deal,
+    Chips,
+    // the turn coming and pots being won
+    Alerts,
+}
+
+impl SoundCategory {
+    pub fn label(self) -> &'static str {
+        match self {
+            SoundCategory::Deal => t(Text::DealVolume),
+            SoundCategory::Chips => t(Text::ChipsVolume),
+            SoundCategory::Alerts => t(Text::AlertsVolume),
+        }
+    }
+
+    // played when the volume is changed, so it can be heard
+    pub fn sample(self) -> Sound {
+        match self {
+            SoundCategory::Deal => Sound::Deal,
+            SoundCategory::Chips => Sound::Chips,
+            SoundCategory::Alerts => Sound::Ding,
+        }
+    }
+}
+
+impl Sound {
+    fn bytes(&self) -> &'static [u8] {
+        match self {
+            Sound::Ding => DING_SOUND,
+            Sound::Chips => CHIPS_SOUND,
+            Sound::Check => CHECK_SOUND,
+            Sound::Deal => DEAL_SOUND,
+            Sound::Win => WIN_SOUND,
+        }
+    }
+
+    pub fn category(&self) -> SoundCategory {
+        match self {
+            Sound::Deal => SoundCategory::Deal,
+            Sound::Chips | Sound::Check => SoundCategory::Chips,
+            Sound::Ding | Sound::Win => SoundCategory::Alerts,
+        }
+    }
+
+    pub fn play(&self) {
+        AUDIO.play(*self, 1);
+    }
+
+    pub fn play_repeat(&self, times: usize) {
+        AUDIO.play(*self, times);
+    }
+}
+
+/// Mute and volumes in percent, kept in the config file under `[sound]`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundSettings {
+    pub muted: bool,
+    pub deal: u8,
+    pub chips: u8,
+    pub alerts: u8,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        Self {
+            muted: false,
+            deal: 100,
+            chips: 100,
+            alerts: 100,
+        }
+    }
+}
+
+impl SoundSettings {
+    pub fn volume(&self, category: SoundCategory) -> u8 {
+        match category {
+            SoundCategory::Deal => self.deal,
+            SoundCategory::Chips => self.chips,
+            SoundCategory::Alerts => self.alerts,
+        }
+    }
+
+    /// One step up or down, between silent and full
+    pub fn step_volume(&mut self, category: SoundCategory, up: bool) {
+        let volume = match category {
+            SoundCategory::Deal => &mut self.deal,
+            SoundCategory::Chips => &mut self.chips,
+            SoundCategory::Alerts => &mut self.alerts,
+        };
+        *volume = if up {
+            volume.saturating_add(VOLUME_STEP).min(100)
+        } else {
+            volume.saturating_sub(VOLUME_STEP)
+        };
+    }
+}
+
+struct PlayRequest {
+    sound: Sound,
+    times: usize,
+    volume: f32,
+}
+
+/// Plays every sound on one audio thread, fed through a channel
+pub struct AudioManager {
+    sender: Sender<PlayRequest>,
+    settings: RwLock<SoundSettings>,
+}
+
+impl AudioManager {
+    fn start(settings: SoundSettings) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("audio".to_string())
+            .spawn(move || audio_thread(receiver))
+            .inspect_err(|e| log::warn!("Failed to start the audio thread: {}", e))
+            .ok();
+        Self {
+            sender,
+            settings: RwLock::new(settings),
+        }
+    }
+
+    pub fn settings(&self) -> SoundSettings {
+        self.settings.read().map(|s| *s).unwrap_or_default()
+    }
+
+    pub fn set_settings(&self, settings: SoundSettings) {
+        if let Ok(mut current) = self.settings.write() {
+            *current = settings;
+        }
+    }
+
+    fn play(&self, sound: Sound, times: usize) {
+        let settings = self.settings();
+        let volume = settings.volume(sound.category());
+        if settings.muted || volume == 0 {
+            return;
+        }
+        // a closed channel means there is no audio thread, so nothing to play on
+        let _ = self.sender.send(PlayRequest {
+            sound,
+            times,
+            volume: f32::from(volume) / 100.0,
+        });
+    }
+}
+
+fn audio_thread(receiver: Receiver<PlayRequest>) {
+    // the stream must live as long as the sinks playing on it
+    let (_stream, stream_handle) = match OutputStream::try_default() {
+        Ok(output) => output,
+        Err(e) => {
+            log::warn!("No audio output, sounds are off: {}", e);
+            return;
+        }
+    };
+    for request in receiver {
+        let Ok(sink) = Sink::try_new(&stream_handle) else {
+            continue;
+        };
+        sink.set_volume(request.volume);
+        for _ in 0..request.times {
+            if let Ok(source) = Decoder::new(Cursor::new(request.sound.bytes())) {
+                sink.append(source);
+            }
+        }
+        // sounds overlap, each on its own sink
+        sink.detach();
+    }
+}
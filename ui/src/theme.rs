@@ -0,0 +1,137 @@
+use std::borrow::Cow;
+
+use ratatui::prelude::{Color, Line, Modifier, Span, Style};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    // for terminals with a light background
+    Light,
+    HighContrast,
+}
+
+impl ThemeName {
+    pub const ALL: [ThemeName; 3] = [ThemeName::Dark, ThemeName::Light, ThemeName::HighContrast];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ThemeName::Dark => "Dark",
+            ThemeName::Light => "Light",
+            ThemeName::HighContrast => "High contrast",
+        }
+    }
+}
+
+/// How the cards of other players are drawn until they are shown
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CardBack {
+    #[default]
+    Classic,
+    Checkered,
+    Plain,
+}
+
+impl CardBack {
+    pub const ALL: [CardBack; 3] = [CardBack::Classic, CardBack::Checkered, CardBack::Plain];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CardBack::Classic => "Classic",
+            CardBack::Checkered => "Checkered",
+            CardBack::Plain => "Plain",
+        }
+    }
+}
+
+/// Colors shared by every screen, picked on the settings screen
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Theme {
+    pub name: ThemeName,
+    pub card_back: CardBack,
+    // text and borders of what can be used right now
+    pub text: Color,
+    // what can't, like the action buttons while waiting for the turn
+    pub muted: Color,
+    // the focused button or field
+    pub highlight: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::new(ThemeName::default(), CardBack::default())
+    }
+}
+
+impl Theme {
+    pub fn new(name: ThemeName, card_back: CardBack) -> Self {
+        let (text, muted, highlight) = match name {
+            ThemeName::Dark => (
+                Color::White,
+                Color::DarkGray,
+                Style::default().bg(Color::White).fg(Color::Black),
+            ),
+            ThemeName::Light => (
+                Color::Black,
+                Color::Gray,
+                Style::default().bg(Color::Black).fg(Color::White),
+            ),
+            ThemeName::HighContrast => (
+                Color::White,
+                Color::Gray,
+                Style::default()
+                    .bg(Color::Yellow)
+                    .fg(Color::Black)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        };
+        Theme {
+            name,
+            card_back,
+            text,
+            muted,
+            highlight,
+        }
+    }
+
+    pub fn with_name(self, name: ThemeName) -> Self {
+        Theme::new(name, self.card_back)
+    }
+
+    pub fn with_card_back(self, card_back: CardBack) -> Self {
+        Theme::new(self.name, card_back)
+    }
+
+    pub fn highlight<'a>(&self, text: impl Into<Cow<'a, str>>, needed: bool) -> Span<'a> {
+        if needed {
+            Span::styled(text, self.highlight)
+        } else {
+            Span::styled(text, Style::default())
+        }
+    }
+
+    /// A card that is dealt but not shown
+    pub fn card_back_span(&self) -> Span<'static> {
+        match self.card_back {
+            CardBack::Classic => {
+                Span::styled("[ ?? ]", Style::default().fg(Color::Black).bg(Color::White))
+            }
+            CardBack::Checkered => {
+                Span::styled("[▚▚▚▚]", Style::default().fg(Color::Red).bg(Color::White))
+            }
+            CardBack::Plain => Span::styled("[    ]", Style::default().bg(Color::Blue)),
+        }
+    }
+
+    /// Two cards that are dealt but not shown
+    pub fn hidden_hand_line(&self) -> Line<'static> {
+        Line::from(vec![
+            self.card_back_span(),
+            " ".into(),
+            self.card_back_span(),
+        ])
+    }
+}
@@ -0,0 +1,98 @@
+use client::client::Client;
+use color_eyre::eyre::ContextCompat;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Flex, Layout, Position, Rect};
+use ratatui::prelude::{Line, Widget};
+use ratatui::style::Stylize;
+use ratatui::widgets::{Block, BorderType, Clear, Paragraph, Wrap};
+use tui_input::backend::crossterm::EventHandler;
+use tui_input::Input;
+use types::rules::{DEFAULT_BUY_IN, MAX_BUY_IN};
+use uuid::Uuid;
+
+/// Chips being bought from the balance for the stack at the table
+#[derive(Debug)]
+pub struct TopUp {
+    room_id: Uuid,
+    amount: Input,
+}
+
+impl TopUp {
+    pub fn new(room_id: Uuid) -> Self {
+        Self {
+            room_id,
+            amount: Input::new(DEFAULT_BUY_IN.to_string()),
+        }
+    }
+
+    /// Returns whether the popup should be closed
+    pub async fn on_key_event(
+        &mut self,
+        key: KeyEvent,
+        client: &mut Client,
+    ) -> color_eyre::Result<bool> {
+        if key.kind != KeyEventKind::Press {
+            return Ok(false);
+        }
+        match key.code {
+            KeyCode::Esc => Ok(true),
+            KeyCode::Enter => {
+                let amount: u32 = self
+                    .amount
+                    .value()
+                    .parse()
+                    .ok()
+                    .wrap_err("Enter the number of chips to add")?;
+                client.top_up_at_table(self.room_id, amount).await?;
+                Ok(true)
+            }
+            KeyCode::Char(c) if !c.is_ascii_digit() => Ok(false),
+            _ => {
+                self.amount.handle_event(&Event::Key(key));
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Renders the top-up popup, returning where the cursor goes
+pub fn top_up_popup(area: Rect, top_up: &TopUp, buf: &mut Buffer) -> Position {
+    let [popup_area] = Layout::vertical([Constraint::Length(7)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Max(50)])
+        .flex(Flex::Center)
+        .areas(popup_area);
+    Clear.render(popup_area, buf);
+    let block = Block::bordered()
+        .border_type(BorderType::Rounded)
+        .title(Line::from("Add chips").centered())
+        .title_bottom(
+            Line::from(vec![
+                "Buy ".into(),
+                "<Enter>".light_blue().bold(),
+                " Cancel ".into(),
+                "<Esc>".red().bold(),
+            ])
+            .right_aligned(),
+        );
+    let inner_area = block.inner(popup_area);
+    block.render(popup_area, buf);
+    let [hint_area, amount_area] =
+        Layout::vertical([Constraint::Length(2), Constraint::Length(3)]).areas(inner_area);
+    Paragraph::new(format!(
+        "Added as the next hand is dealt, up to a stack of {}",
+        MAX_BUY_IN
+    ))
+    .wrap(Wrap { trim: true })
+    .render(hint_area, buf);
+    Paragraph::new(top_up.amount.value())
+        .block(Block::bordered().title("Chips"))
+        .render(amount_area, buf);
+    (
+        amount_area.x + top_up.amount.visual_cursor() as u16 + 1,
+        amount_area.y + 1,
+    )
+        .into()
+}
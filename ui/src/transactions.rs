@@ -0,0 +1,322 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{Local, NaiveDate};
+use client::client::Client;
+use color_eyre::Result;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use ratatui::prelude::{Line, Modifier, StatefulWidget, Style, Widget};
+use ratatui::style::Stylize;
+use ratatui::widgets::{Block, Cell, Clear, Paragraph, Row, Table, TableState};
+use tui_input::backend::crossterm::EventHandler;
+use tui_input::Input;
+use types::domain::{LedgerEntry, TransactionKind, TransactionPage, TransactionQuery};
+
+use crate::theme::Theme;
+
+// the most the server hands out at once, used when exporting every page
+const EXPORT_PAGE_SIZE: u32 = 100;
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Clone, Copy)]
+enum DateField {
+    From,
+    To,
+}
+
+/// The user's balance ledger, a page at a time, with filters and an export to CSV
+#[derive(Debug)]
+pub struct Transactions {
+    query: TransactionQuery,
+    page: TransactionPage,
+    table_state: TableState,
+    // a date filter being typed in
+    editing: Option<(DateField, Input)>,
+    // where the last export went, or why it failed
+    message: Option<String>,
+}
+
+impl Transactions {
+    pub async fn open(client: &Client) -> Result<Self> {
+        let query = TransactionQuery::default();
+        let page = client.get_transactions(&query).await?;
+        Ok(Self {
+            query,
+            page,
+            table_state: TableState::default().with_selected(0),
+            editing: None,
+            message: None,
+        })
+    }
+
+    async fn reload(&mut self, client: &Client) -> Result<()> {
+        self.page = client.get_transactions(&self.query).await?;
+        self.table_state.select(Some(0));
+        Ok(())
+    }
+
+    async fn go_to_page(&mut self, page: u32, client: &Client) -> Result<()> {
+        self.query.page = Some(page);
+        self.reload(client).await
+    }
+
+    // no filter, then each kind in turn
+    fn next_kind(&self) -> Option<TransactionKind> {
+        match self.query.kind {
+            None => TransactionKind::ALL.first().copied(),
+            Some(kind) => TransactionKind::ALL
+                .iter()
+                .skip_while(|k| **k != kind)
+                .nth(1)
+                .copied(),
+        }
+    }
+
+    /// Returns whether the screen should be closed
+    pub async fn on_key_event(&mut self, key: KeyEvent, client: &Client) -> Result<bool> {
+        if key.kind != KeyEventKind::Press {
+            return Ok(false);
+        }
+        if self.editing.is_some() {
+            self.on_date_key_event(key, client).await?;
+            return Ok(false);
+        }
+        match key.code {
+            KeyCode::Esc => return Ok(true),
+            KeyCode::Down => self.table_state.select_next(),
+            KeyCode::Up => self.table_state.select_previous(),
+            KeyCode::Right if self.page.has_more => {
+                self.go_to_page(self.page.page + 1, client).await?;
+            }
+            KeyCode::Left if self.page.page > 0 => {
+                self.go_to_page(self.page.page - 1, client).await?;
+            }
+            KeyCode::Char('k') => {
+                self.query.kind = self.next_kind();
+                self.go_to_page(0, client).await?;
+            }
+            KeyCode::Char('f') => self.edit_date(DateField::From),
+            KeyCode::Char('t') => self.edit_date(DateField::To),
+            KeyCode::Char('c') => {
+                self.query = TransactionQuery::default();
+                self.reload(client).await?;
+            }
+            KeyCode::Char('e') => {
+                self.message = Some(match self.export(client).await {
+                    Ok(path) => format!("Saved to {}", path.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                });
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn edit_date(&mut self, field: DateField) {
+        let date = match field {
+            DateField::From => self.query.from,
+            DateField::To => self.query.to,
+        };
+        let value = date.map(|d| d.format(DATE_FORMAT).to_string());
+        self.editing = Some((field, Input::new(value.unwrap_or_default())));
+    }
+
+    async fn on_date_key_event(&mut self, key: KeyEvent, client: &Client) -> Result<()> {
+        let Some((field, input)) = &mut self.editing else {
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc => self.editing = None,
+            KeyCode::Enter => {
+                // left empty, the filter is cleared
+                let value = input.value().trim();
+                let date = if value.is_empty() {
+                    None
+                } else {
+                    Some(NaiveDate::parse_from_str(value, DATE_FORMAT)?)
+                };
+                match field {
+                    DateField::From => self.query.from = date,
+                    DateField::To => self.query.to = date,
+                }
+                self.editing = None;
+                self.go_to_page(0, client).await?;
+            }
+            _ => {
+                input.handle_event(&Event::Key(key));
+            }
+        }
+        Ok(())
+    }
+
+    // every page under the current filters, written next to where the app was started
+    async fn export(&self, client: &Client) -> Result<PathBuf> {
+        let mut query = TransactionQuery {
+            page: Some(0),
+            per_page: Some(EXPORT_PAGE_SIZE),
+            ..self.query.clone()
+        };
+        let mut csv = String::from("date,type,amount,balance_after,room_id,note\n");
+        loop {
+            let page = client.get_transactions(&query).await?;
+            for entry in &page.transactions {
+                csv.push_str(&csv_row(entry));
+            }
+            if !page.has_more {
+                break;
+            }
+            query.page = Some(page.page + 1);
+        }
+        let file_name = format!("transactions-{}.csv", Local::now().format("%Y%m%d-%H%M%S"));
+        let path = std::env::current_dir()?.join(file_name);
+        fs::write(&path, csv)?;
+        Ok(path)
+    }
+
+    fn filter_line(&self, theme: &Theme) -> Line<'_> {
+        let kind = self.query.kind.map_or("All types", |kind| kind.label());
+        let date = |date: Option<NaiveDate>| {
+            date.map_or("any".to_string(), |d| d.format(DATE_FORMAT).to_string())
+        };
+        Line::from(vec![
+            kind.bold(),
+            format!("   From {}", date(self.query.from)).into(),
+            format!("   To {}", date(self.query.to)).into(),
+            format!("   Page {}", self.page.page + 1).fg(theme.muted),
+        ])
+    }
+}
+
+fn reason_label(entry: &LedgerEntry) -> String {
+    entry.reason.as_ref().replace('_', " ")
+}
+
+fn csv_row(entry: &LedgerEntry) -> String {
+    let fields = [
+        entry.created_at.to_rfc3339(),
+        entry.reason.as_ref().to_string(),
+        entry.amount.to_string(),
+        entry.balance_after.to_string(),
+        entry.room_id.map(|id| id.to_string()).unwrap_or_default(),
+        entry.note.clone().unwrap_or_default(),
+    ];
+    let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+    format!("{}\n", fields.join(","))
+}
+
+// quoted only when it has to be, with quotes doubled
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn transactions_popup(
+    area: Rect,
+    transactions: &mut Transactions,
+    theme: &Theme,
+    buf: &mut Buffer,
+) {
+    let [popup_area] = Layout::vertical([Constraint::Percentage(90)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup_area] = Layout::horizontal([Constraint::Max(100)])
+        .flex(Flex::Center)
+        .areas(popup_area);
+    Clear.render(popup_area, buf);
+    let instructions = Line::from(vec![
+        "Type ".into(),
+        "<K>".light_blue().bold(),
+        " From ".into(),
+        "<F>".light_blue().bold(),
+        " To ".into(),
+        "<T>".light_blue().bold(),
+        " Clear ".into(),
+        "<C>".light_blue().bold(),
+        " Pages ".into(),
+        "<←/→>".light_blue().bold(),
+        " Export ".into(),
+        "<E>".light_blue().bold(),
+        " Close ".into(),
+        "<Esc>".red().bold(),
+    ]);
+    let block = Block::bordered()
+        .title(Line::from("Transactions").centered())
+        .title_bottom(instructions.right_aligned());
+    let inner_area = block.inner(popup_area);
+    block.render(popup_area, buf);
+    let [filter_area, table_area, status_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(inner_area);
+    Paragraph::new(transactions.filter_line(theme)).render(filter_area, buf);
+
+    let status = match (&transactions.editing, &transactions.message) {
+        (Some((field, input)), _) => {
+            let label = match field {
+                DateField::From => "From",
+                DateField::To => "To",
+            };
+            Line::from(vec![
+                format!("{} (YYYY-MM-DD, empty for any): ", label).into(),
+                input.value().to_string().bold(),
+                "_".slow_blink(),
+            ])
+        }
+        (None, Some(message)) => Line::from(message.as_str()).fg(theme.muted),
+        (None, None) => Line::default(),
+    };
+    Paragraph::new(status).render(status_area, buf);
+
+    if transactions.page.transactions.is_empty() {
+        Paragraph::new("No transactions")
+            .centered()
+            .render(table_area, buf);
+        return;
+    }
+    let header = ["Date", "Type", "Amount", "Balance", "Note"]
+        .into_iter()
+        .map(Cell::from)
+        .collect::<Row>()
+        .bold();
+    let rows = transactions.page.transactions.iter().map(|entry| {
+        let amount = format!("{:+}", entry.amount);
+        let amount = if entry.amount < 0 {
+            amount.red()
+        } else {
+            amount.green()
+        };
+        Row::new([
+            Cell::from(
+                entry
+                    .created_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+            ),
+            Cell::from(reason_label(entry)),
+            Cell::from(amount),
+            Cell::from(entry.balance_after.to_string()),
+            Cell::from(entry.note.clone().unwrap_or_default()),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(17),
+            Constraint::Length(15),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(header)
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    StatefulWidget::render(table, table_area, buf, &mut transactions.table_state);
+}